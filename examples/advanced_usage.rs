@@ -123,6 +123,21 @@ fn demo_export_functionality() -> anyhow::Result<()> {
             origin: ChangeOrigin::Unknown,
             confidence: None,
             batch_id: None,
+            over_budget: false,
+            content_hash: None,
+            long_lines: None,
+            coverage: None,
+            attribution_reason: None,
+            manifest_change: None,
+            container_path: None,
+            intent: None,
+            undeclared: false,
+            append_only: false,
+            encoding: None,
+            secrets: Vec::new(),
+            masked: false,
+            archive: None,
+            content: None,
         },
         FileEvent {
             path: Path::new("Cargo.toml").to_path_buf(),
@@ -133,6 +148,21 @@ fn demo_export_functionality() -> anyhow::Result<()> {
             origin: ChangeOrigin::Unknown,
             confidence: None,
             batch_id: None,
+            over_budget: false,
+            content_hash: None,
+            long_lines: None,
+            coverage: None,
+            attribution_reason: None,
+            manifest_change: None,
+            container_path: None,
+            intent: None,
+            undeclared: false,
+            append_only: false,
+            encoding: None,
+            secrets: Vec::new(),
+            masked: false,
+            archive: None,
+            content: None,
         },
     ];
     
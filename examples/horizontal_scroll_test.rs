@@ -1,20 +1,14 @@
-use std::path::PathBuf;
-use std::collections::HashSet;
-use watchdiff_tui::ui::TuiApp;
-use watchdiff_tui::core::FileWatcher;
 
 fn main() -> anyhow::Result<()> {
     println!("🧪 Horizontal Scroll Test");
     println!("========================\n");
     
     // Create test paths with varying lengths
-    let test_paths = vec![
-        "short.rs",
+    let test_paths = ["short.rs",
         "medium_length_file.rs", 
         "very_long_directory_name_that_should_require_horizontal_scrolling/deeply/nested/path/to/some/file.rs",
         "another/extremely/long/path/that/definitely/exceeds/terminal/width/and/needs/scrolling/functionality/test.rs",
-        "src/ui/components/dashboard/widgets/charts/advanced/analytics/performance/metrics/visualization.rs",
-    ];
+        "src/ui/components/dashboard/widgets/charts/advanced/analytics/performance/metrics/visualization.rs"];
     
     println!("📁 Test file paths:");
     for (i, path) in test_paths.iter().enumerate() {
@@ -31,7 +31,7 @@ fn demonstrate_performance_features() {
     println!();
     
     println!("3. ⏱️  Event Debouncing:");
-    println!("   • 100ms debounce window for file change events");
+    println!("   • The watcher folds bursts on a file within 100ms into one change");
     println!("   • Prevents excessive processing during rapid file changes");
     println!("   • Automatic cache invalidation on file changes");
     println!("   • Reduced CPU usage during bulk file operations");
@@ -49,17 +49,16 @@ fn demonstrate_cache_usage() {
     println!("📊 Cache Performance Example:");
     println!("-----------------------------");
     
-    let mut cache = PerformanceCache::new();
+    let cache = PerformanceCache::new();
     
     // Simulate file operations
-    let test_file = PathBuf::from("example.rs");
+    let _test_file = PathBuf::from("example.rs");
     
     println!("Initial cache state:");
     let stats = cache.stats();
     println!("  File content entries: {}/{}", stats.file_content_entries, stats.file_content_capacity);
     println!("  Syntax highlight entries: {}/{}", stats.syntax_highlight_entries, stats.syntax_highlight_capacity);
     println!("  Search cache active: {}", stats.search_cache_active);
-    println!("  Pending events: {}", stats.pending_events);
     println!();
     
     // Simulate file access pattern
@@ -125,8 +124,9 @@ fn demonstrate_benchmarks() {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use watchdiff_tui::performance::{FileContentCache, SyntaxHighlightCache, SearchResultCache, EventDebouncer};
-    use std::time::Duration;
+    use watchdiff_tui::performance::{FileContentCache, SyntaxHighlightCache, SearchResultCache};
+    use watchdiff_tui::core::{EventCoalescer, RawChange};
+    use std::time::{Duration, Instant};
     
     #[test]
     fn test_file_content_cache() {
@@ -162,30 +162,24 @@ mod tests {
     }
     
     #[test]
-    fn test_event_debouncer() {
-        let mut debouncer = EventDebouncer::new(Duration::from_millis(10));
+    fn test_event_coalescer() {
+        let mut coalescer = EventCoalescer::new(Duration::from_millis(10));
+        let start = Instant::now();
         
         // No events initially
-        assert_eq!(debouncer.get_ready_events().len(), 0);
-        assert_eq!(debouncer.pending_count(), 0);
+        assert!(coalescer.take_ready(start).is_empty());
+        assert!(coalescer.is_empty());
         
-        // Add event
-        let test_event = watchdiff_tui::core::FileEvent::new(
-            PathBuf::from("test.txt"),
-            watchdiff_tui::core::FileEventKind::Modified,
-        );
-        debouncer.add_event(test_event);
-        assert_eq!(debouncer.pending_count(), 1);
+        // Add a burst of events on one file
+        coalescer.add(PathBuf::from("test.txt"), RawChange::Modified, start);
+        coalescer.add(PathBuf::from("test.txt"), RawChange::Modified, start + Duration::from_millis(5));
         
         // Event should not be ready immediately
-        assert_eq!(debouncer.get_ready_events().len(), 0);
+        assert!(coalescer.take_ready(start + Duration::from_millis(5)).is_empty());
         
-        // Wait for debounce period
-        std::thread::sleep(Duration::from_millis(15));
-        
-        // Event should now be ready
-        let ready_events = debouncer.get_ready_events();
+        // After the debounce period, the burst is one change
+        let ready_events = coalescer.take_ready(start + Duration::from_millis(15));
         assert_eq!(ready_events.len(), 1);
-        assert_eq!(debouncer.pending_count(), 0);
+        assert!(coalescer.is_empty());
     }
 }
\ No newline at end of file
@@ -0,0 +1,264 @@
+//! Change budgets for runaway AI runs
+//!
+//! Tracks how many files and lines each batch touches, plus how many files
+//! changed in a rolling window overall, and raises a [`BudgetAlert`] the first
+//! time a configured budget is exceeded.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::time::{Instant, SystemTime};
+use crate::config::BudgetConfig;
+use crate::core::events::{BudgetAlert, BudgetKind, ChangeOrigin, FileEvent};
+
+#[derive(Default)]
+struct BatchUsage {
+    files: HashSet<PathBuf>,
+    lines: usize,
+    tool_name: Option<String>,
+    alerted: HashSet<BudgetKind>,
+}
+
+pub struct BudgetTracker {
+    config: BudgetConfig,
+    batches: HashMap<String, BatchUsage>,
+    window: VecDeque<(Instant, PathBuf)>,
+    window_alerted: bool,
+    paused: HashSet<String>,
+}
+
+impl BudgetTracker {
+    pub fn new(config: BudgetConfig) -> Self {
+        Self {
+            config,
+            batches: HashMap::new(),
+            window: VecDeque::new(),
+            window_alerted: false,
+            paused: HashSet::new(),
+        }
+    }
+
+    /// Whether any budget is configured at all
+    pub fn is_enabled(&self) -> bool {
+        self.config.max_files_per_batch.is_some()
+            || self.config.max_lines_per_batch.is_some()
+            || self.config.max_files_per_window.is_some()
+    }
+
+    /// Whether events from this batch should only be tagged as over budget
+    pub fn is_paused(&self, batch_id: Option<&str>) -> bool {
+        batch_id.is_some_and(|id| self.paused.contains(id))
+    }
+
+    /// Resume full processing of all paused batches
+    pub fn acknowledge(&mut self) {
+        self.paused.clear();
+    }
+
+    pub fn record(&mut self, event: &FileEvent) -> Vec<BudgetAlert> {
+        self.record_at(event, Instant::now())
+    }
+
+    /// Account for `event` observed at `now`, returning any budgets it pushed over the limit
+    pub fn record_at(&mut self, event: &FileEvent, now: Instant) -> Vec<BudgetAlert> {
+        let mut alerts = Vec::new();
+        if !self.is_enabled() {
+            return alerts;
+        }
+
+        let tool_name = match &event.origin {
+            ChangeOrigin::AIAgent { tool_name, .. } => Some(tool_name.clone()),
+            ChangeOrigin::Tool { name } => Some(name.clone()),
+            _ => None,
+        };
+
+        if let Some(ref batch_id) = event.batch_id {
+            // The detector only ever extends its current batch, so older
+            // batches can be dropped unless they are still paused
+            let paused = &self.paused;
+            self.batches.retain(|id, _| id == batch_id || paused.contains(id));
+
+            let usage = self.batches.entry(batch_id.clone()).or_default();
+            let stats = event.diff_stats();
+            usage.files.insert(event.path.clone());
+            usage.lines += stats.lines_added + stats.lines_removed;
+            if usage.tool_name.is_none() {
+                usage.tool_name = tool_name.clone();
+            }
+
+            let checks = [
+                (BudgetKind::BatchFiles, self.config.max_files_per_batch, usage.files.len()),
+                (BudgetKind::BatchLines, self.config.max_lines_per_batch, usage.lines),
+            ];
+            for (kind, limit, observed) in checks {
+                let Some(limit) = limit else { continue };
+                if observed > limit && usage.alerted.insert(kind) {
+                    alerts.push(BudgetAlert {
+                        kind,
+                        batch_id: Some(batch_id.clone()),
+                        tool_name: usage.tool_name.clone(),
+                        observed,
+                        limit,
+                        window_secs: None,
+                        timestamp: SystemTime::now(),
+                    });
+                }
+            }
+        }
+
+        if let Some(limit) = self.config.max_files_per_window {
+            let window = self.config.window_duration();
+            self.window.push_back((now, event.path.clone()));
+            while self
+                .window
+                .front()
+                .is_some_and(|(at, _)| now.duration_since(*at) > window)
+            {
+                self.window.pop_front();
+            }
+
+            let observed = self.window.iter().map(|(_, path)| path).collect::<HashSet<_>>().len();
+            if observed <= limit {
+                // Back under budget: the next overrun alerts again
+                self.window_alerted = false;
+            } else if !self.window_alerted {
+                self.window_alerted = true;
+                alerts.push(BudgetAlert {
+                    kind: BudgetKind::WindowFiles,
+                    batch_id: event.batch_id.clone(),
+                    tool_name,
+                    observed,
+                    limit,
+                    window_secs: Some(window.as_secs()),
+                    timestamp: SystemTime::now(),
+                });
+            }
+        }
+
+        if self.config.pause_over_budget {
+            for alert in &alerts {
+                if let Some(ref batch_id) = alert.batch_id {
+                    self.paused.insert(batch_id.clone());
+                }
+            }
+        }
+
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::core::FileEventKind;
+
+    fn ai_event(path: &str, batch: &str, added_lines: usize) -> FileEvent {
+        let body: String = (0..added_lines).map(|i| format!("+line {}\n", i)).collect();
+        FileEvent::new(PathBuf::from(path), FileEventKind::Modified)
+            .with_origin(ChangeOrigin::AIAgent { tool_name: "claude".to_string(), process_id: None })
+            .with_batch_id(batch.to_string())
+            .with_diff(format!("--- a\n+++ b\n@@ -0,0 +1,{} @@\n{}", added_lines, body))
+    }
+
+    fn config() -> BudgetConfig {
+        BudgetConfig {
+            max_files_per_batch: None,
+            max_lines_per_batch: None,
+            max_files_per_window: None,
+            window_minutes: 10,
+            pause_over_budget: false,
+        }
+    }
+
+    #[test]
+    fn test_batch_file_budget_fires_once() {
+        let mut tracker = BudgetTracker::new(BudgetConfig { max_files_per_batch: Some(3), ..config() });
+        let now = Instant::now();
+
+        for i in 0..3 {
+            assert!(tracker.record_at(&ai_event(&format!("f{}.rs", i), "batch_1", 1), now).is_empty());
+        }
+        // Touching an already-counted file does not grow the batch
+        assert!(tracker.record_at(&ai_event("f0.rs", "batch_1", 1), now).is_empty());
+
+        let alerts = tracker.record_at(&ai_event("f3.rs", "batch_1", 1), now);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind, BudgetKind::BatchFiles);
+        assert_eq!(alerts[0].batch_id.as_deref(), Some("batch_1"));
+        assert_eq!(alerts[0].tool_name.as_deref(), Some("claude"));
+        assert_eq!((alerts[0].observed, alerts[0].limit), (4, 3));
+
+        assert!(tracker.record_at(&ai_event("f4.rs", "batch_1", 1), now).is_empty());
+
+        // A new batch starts from zero
+        for i in 0..3 {
+            assert!(tracker.record_at(&ai_event(&format!("g{}.rs", i), "batch_2", 1), now).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_batch_line_budget() {
+        let mut tracker = BudgetTracker::new(BudgetConfig { max_lines_per_batch: Some(100), ..config() });
+        let now = Instant::now();
+
+        assert!(tracker.record_at(&ai_event("a.rs", "batch_1", 60), now).is_empty());
+        let alerts = tracker.record_at(&ai_event("b.rs", "batch_1", 60), now);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind, BudgetKind::BatchLines);
+        assert_eq!(alerts[0].observed, 120);
+        assert!(tracker.record_at(&ai_event("c.rs", "batch_1", 60), now).is_empty());
+    }
+
+    #[test]
+    fn test_rolling_window_budget_resets() {
+        let mut tracker = BudgetTracker::new(BudgetConfig {
+            max_files_per_window: Some(2),
+            window_minutes: 1,
+            ..config()
+        });
+        let start = Instant::now();
+
+        assert!(tracker.record_at(&ai_event("a.rs", "batch_1", 1), start).is_empty());
+        assert!(tracker.record_at(&ai_event("b.rs", "batch_2", 1), start).is_empty());
+        let alerts = tracker.record_at(&ai_event("c.rs", "batch_3", 1), start);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind, BudgetKind::WindowFiles);
+        assert_eq!(alerts[0].window_secs, Some(60));
+        assert!(tracker.record_at(&ai_event("d.rs", "batch_3", 1), start).is_empty());
+
+        // Once the window has rolled past the old changes the budget re-arms
+        let later = start + Duration::from_secs(90);
+        assert!(tracker.record_at(&ai_event("e.rs", "batch_4", 1), later).is_empty());
+        assert!(tracker.record_at(&ai_event("f.rs", "batch_4", 1), later).is_empty());
+        assert_eq!(tracker.record_at(&ai_event("g.rs", "batch_4", 1), later).len(), 1);
+    }
+
+    #[test]
+    fn test_pause_until_acknowledged() {
+        let mut tracker = BudgetTracker::new(BudgetConfig {
+            max_files_per_batch: Some(1),
+            pause_over_budget: true,
+            ..config()
+        });
+        let now = Instant::now();
+
+        tracker.record_at(&ai_event("a.rs", "batch_1", 1), now);
+        assert!(!tracker.is_paused(Some("batch_1")));
+        assert_eq!(tracker.record_at(&ai_event("b.rs", "batch_1", 1), now).len(), 1);
+        assert!(tracker.is_paused(Some("batch_1")));
+        assert!(!tracker.is_paused(Some("batch_2")));
+        assert!(!tracker.is_paused(None));
+
+        tracker.acknowledge();
+        assert!(!tracker.is_paused(Some("batch_1")));
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        let mut tracker = BudgetTracker::new(BudgetConfig::default());
+        assert!(!tracker.is_enabled());
+        for i in 0..100 {
+            assert!(tracker.record(&ai_event(&format!("f{}.rs", i), "batch_1", 100)).is_empty());
+        }
+    }
+}
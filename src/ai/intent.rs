@@ -0,0 +1,266 @@
+//! Intent files declared by AI agents before they edit
+//!
+//! An agent writes a JSON [`IntentFile`] (by default `.watchdiff/intent.json`
+//! under the watch root) listing the files it is about to change and why.
+//! Changes to declared files carry the declared reason; any other change
+//! while the intent is fresh is flagged as undeclared. An intent older than
+//! the configured age is ignored.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "agent": "claude",
+//!   "intent": "Add OAuth login",
+//!   "created_at": "2024-05-01T09:00:00Z",
+//!   "files": [
+//!     { "path": "src/auth/**", "reason": "new OAuth flow" },
+//!     { "path": "Cargo.toml" }
+//!   ]
+//! }
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::config::IntentConfig;
+
+/// Newest intent file format this version reads
+pub const INTENT_FORMAT_VERSION: u32 = 1;
+
+/// Reason given to declared files with neither their own nor an overall reason
+const NO_REASON: &str = "declared without a reason";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntentFile {
+    /// Format version, currently 1
+    pub version: u32,
+    /// Name of the agent that wrote the file
+    #[serde(default)]
+    pub agent: Option<String>,
+    /// Overall intent, the reason for files without their own
+    #[serde(default)]
+    pub intent: Option<String>,
+    /// When the intent was declared; the file's modification time if absent
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    /// Files the agent is about to change
+    pub files: Vec<DeclaredFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeclaredFile {
+    /// Path relative to the watch root, a directory, or a glob in gitignore
+    /// syntax such as `src/auth/**`
+    pub path: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// How a change relates to the current intent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntentMatch {
+    /// No fresh intent file
+    NoIntent,
+    /// The change was declared, with this reason
+    Declared(String),
+    Undeclared,
+}
+
+/// An [`IntentFile`] ready for matching
+#[derive(Debug, Clone)]
+struct ActiveIntent {
+    declared_at: SystemTime,
+    /// Matcher and reason of each declared entry
+    entries: Vec<(DeclaredPath, String)>,
+}
+
+#[derive(Debug, Clone)]
+enum DeclaredPath {
+    /// A file or directory, relative to the root
+    Exact(PathBuf),
+    Glob(Gitignore),
+}
+
+impl DeclaredPath {
+    fn parse(root: &Path, path: &str) -> Result<Self> {
+        if !path.contains(['*', '?', '[']) {
+            let path = Path::new(path.trim_end_matches('/'));
+            return Ok(Self::Exact(path.strip_prefix(root).unwrap_or(path).to_path_buf()));
+        }
+        let mut builder = GitignoreBuilder::new(root);
+        builder.add_line(None, path).with_context(|| format!("Invalid declared path: {}", path))?;
+        Ok(Self::Glob(builder.build().with_context(|| format!("Invalid declared path: {}", path))?))
+    }
+
+    fn matches(&self, relative: &Path) -> bool {
+        match self {
+            Self::Exact(declared) => relative.starts_with(declared),
+            Self::Glob(matcher) => matcher.matched_path_or_any_parents(relative, false).is_ignore(),
+        }
+    }
+}
+
+/// The intent file under a watch root, re-read when it changes
+#[derive(Debug)]
+pub struct IntentTracker {
+    root: PathBuf,
+    path: PathBuf,
+    max_age: Duration,
+    /// Modification time and size of the file when last read
+    loaded: Option<(SystemTime, u64)>,
+    active: Option<ActiveIntent>,
+}
+
+impl IntentTracker {
+    pub fn new(root: &Path, path: &Path, max_age: Duration) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            path: root.join(path),
+            max_age,
+            loaded: None,
+            active: None,
+        }
+    }
+
+    pub fn from_config(config: &IntentConfig, root: &Path) -> Self {
+        Self::new(root, &config.path, config.max_age())
+    }
+
+    /// Re-read the intent file if it changed, was created or was removed
+    pub fn refresh(&mut self) -> Result<()> {
+        let stamp = fs::metadata(&self.path).and_then(|meta| Ok((meta.modified()?, meta.len())));
+        let (modified, len) = match stamp {
+            Ok(stamp) => stamp,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                self.loaded = None;
+                self.active = None;
+                return Ok(());
+            }
+            Err(err) => return Err(err).with_context(|| format!("Failed to read intent file: {}", self.path.display())),
+        };
+        if self.loaded == Some((modified, len)) {
+            return Ok(());
+        }
+        // Remember the attempt so a broken file is not re-parsed on every event
+        self.loaded = Some((modified, len));
+        self.active = None;
+
+        let text = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read intent file: {}", self.path.display()))?;
+        let file: IntentFile = serde_json::from_str(&text)
+            .with_context(|| format!("Invalid intent file: {}", self.path.display()))?;
+        self.active = Some(self.activate(file, modified)?);
+        Ok(())
+    }
+
+    fn activate(&self, file: IntentFile, modified: SystemTime) -> Result<ActiveIntent> {
+        if file.version > INTENT_FORMAT_VERSION {
+            anyhow::bail!(
+                "Unsupported intent file version {} in {} (newest supported is {})",
+                file.version,
+                self.path.display(),
+                INTENT_FORMAT_VERSION
+            );
+        }
+        let entries = file
+            .files
+            .iter()
+            .map(|declared| {
+                let reason = declared.reason.clone().or_else(|| file.intent.clone()).unwrap_or_else(|| NO_REASON.to_string());
+                Ok((DeclaredPath::parse(&self.root, &declared.path)?, reason))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(ActiveIntent {
+            declared_at: file.created_at.map_or(modified, SystemTime::from),
+            entries,
+        })
+    }
+
+    /// Whether `path` was declared in an intent that is fresh at `now`. The
+    /// first matching entry gives the reason.
+    pub fn check(&self, path: &Path, now: SystemTime) -> IntentMatch {
+        let Some(ref active) = self.active else { return IntentMatch::NoIntent };
+        if crate::core::clock::elapsed_between(active.declared_at, now) > self.max_age {
+            return IntentMatch::NoIntent;
+        }
+        // The agent writing its own intent is not a change to flag
+        if path == self.path {
+            return IntentMatch::NoIntent;
+        }
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        match active.entries.iter().find(|(declared, _)| declared.matches(relative)) {
+            Some((_, reason)) => IntentMatch::Declared(reason.clone()),
+            None => IntentMatch::Undeclared,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_intent(root: &Path, json: &str) {
+        fs::create_dir_all(root.join(".watchdiff")).unwrap();
+        fs::write(root.join(".watchdiff/intent.json"), json).unwrap();
+    }
+
+    #[test]
+    fn test_declared_globs_and_paths_match_and_others_are_undeclared() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        write_intent(root, r#"{
+            "version": 1,
+            "intent": "Add OAuth login",
+            "files": [
+                { "path": "src/auth/**", "reason": "new OAuth flow" },
+                { "path": "*.toml" },
+                { "path": "docs/" }
+            ]
+        }"#);
+        let mut tracker = IntentTracker::new(root, Path::new(".watchdiff/intent.json"), Duration::from_secs(600));
+        tracker.refresh().unwrap();
+        let now = SystemTime::now();
+        let check = |tracker: &IntentTracker, path: &str| tracker.check(&root.join(path), now);
+
+        assert_eq!(check(&tracker, "src/auth/oauth/token.rs"), IntentMatch::Declared("new OAuth flow".to_string()));
+        assert_eq!(check(&tracker, "Cargo.toml"), IntentMatch::Declared("Add OAuth login".to_string()));
+        assert_eq!(check(&tracker, "docs/setup.md"), IntentMatch::Declared("Add OAuth login".to_string()));
+        assert_eq!(check(&tracker, "src/main.rs"), IntentMatch::Undeclared);
+        assert_eq!(check(&tracker, "docsite/index.md"), IntentMatch::Undeclared);
+        assert_eq!(check(&tracker, ".watchdiff/intent.json"), IntentMatch::NoIntent);
+
+        // Removing the file ends the intent
+        fs::remove_file(root.join(".watchdiff/intent.json")).unwrap();
+        tracker.refresh().unwrap();
+        assert_eq!(check(&tracker, "src/main.rs"), IntentMatch::NoIntent);
+    }
+
+    #[test]
+    fn test_stale_intent_expires() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        write_intent(root, r#"{
+            "version": 1,
+            "created_at": "2024-05-01T09:00:00Z",
+            "files": [{ "path": "src/lib.rs" }]
+        }"#);
+        let mut tracker = IntentTracker::new(root, Path::new(".watchdiff/intent.json"), Duration::from_secs(1800));
+        tracker.refresh().unwrap();
+
+        let declared_at = SystemTime::from(DateTime::parse_from_rfc3339("2024-05-01T09:00:00Z").unwrap());
+        let path = root.join("src/main.rs");
+        assert_eq!(tracker.check(&path, declared_at + Duration::from_secs(1799)), IntentMatch::Undeclared);
+        assert_eq!(tracker.check(&path, declared_at + Duration::from_secs(1801)), IntentMatch::NoIntent);
+
+        write_intent(root, r#"{ "version": 2, "files": [] }"#);
+        assert!(tracker.refresh().is_err());
+        assert_eq!(tracker.check(&path, declared_at), IntentMatch::NoIntent);
+    }
+}
@@ -0,0 +1,342 @@
+//! What changed inside a `.zip` or `.jar` archive
+//!
+//! Archives matching `archive.globs` are read in memory when they change.
+//! Their entry lists are compared (entries added, removed, or rewritten as
+//! told by size and CRC), and text entries small enough get a content diff
+//! of their own. Reading stops at strict limits on the archive size, the
+//! entry count and the total decompressed bytes, so a zip bomb is refused
+//! instead of unpacked. Reading archives needs the `archive` feature.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use anyhow::{bail, Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::{Deserialize, Serialize};
+use crate::config::ArchiveConfig;
+
+/// One file entry of an archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    pub name: String,
+    /// Uncompressed size in bytes
+    pub size: u64,
+    pub crc32: u32,
+}
+
+impl ArchiveEntry {
+    /// The entry as a line of the manifest diff, e.g. `lib/a.class 120 B crc 0a1b2c3d`
+    fn manifest_line(&self) -> String {
+        format!("{} {} B crc {:08x}\n", self.name, self.size, self.crc32)
+    }
+}
+
+/// An entry in both versions of an archive whose content differs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryChange {
+    pub name: String,
+    pub old_size: u64,
+    pub new_size: u64,
+    /// Unified diff of the entry, when both versions are text within
+    /// `archive.max_entry_diff_bytes`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+}
+
+/// File entries of one version of an archive, with the content of the text
+/// entries small enough to diff
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveListing {
+    /// Sorted by name
+    pub entries: Vec<ArchiveEntry>,
+    texts: BTreeMap<String, String>,
+}
+
+impl ArchiveListing {
+    /// Read the archive in `bytes`, failing once any limit in `limits` is
+    /// exceeded. Decompressed bytes are counted as they come out rather than
+    /// taken from the sizes the archive claims.
+    #[cfg(feature = "archive")]
+    pub fn read(bytes: &[u8], limits: &ArchiveConfig) -> Result<Self> {
+        use std::io::Read;
+
+        if bytes.len() as u64 > limits.max_archive_bytes {
+            bail!("archive is larger than {} bytes", limits.max_archive_bytes);
+        }
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).context("not a readable zip archive")?;
+        if archive.len() > limits.max_entries {
+            bail!("archive has {} entries, more than {}", archive.len(), limits.max_entries);
+        }
+
+        let mut listing = Self::default();
+        let mut total = 0u64;
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index).context("unreadable archive entry")?;
+            if file.is_dir() {
+                continue;
+            }
+            let entry = ArchiveEntry { name: file.name().to_string(), size: file.size(), crc32: file.crc32() };
+            let mut content = Vec::new();
+            let remaining = limits.max_total_bytes - total;
+            (&mut file)
+                .take(remaining.saturating_add(1))
+                .read_to_end(&mut content)
+                .with_context(|| format!("cannot decompress {}", entry.name))?;
+            total += content.len() as u64;
+            if total > limits.max_total_bytes {
+                bail!("archive decompresses to more than {} bytes", limits.max_total_bytes);
+            }
+            if content.len() <= limits.max_entry_diff_bytes && !crate::diff::is_binary(&content) {
+                if let Ok(text) = String::from_utf8(content) {
+                    listing.texts.insert(entry.name.clone(), text);
+                }
+            }
+            listing.entries.push(entry);
+        }
+        listing.entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(listing)
+    }
+
+    #[cfg(not(feature = "archive"))]
+    pub fn read(_bytes: &[u8], _limits: &ArchiveConfig) -> Result<Self> {
+        bail!("watchdiff was built without the archive feature")
+    }
+
+    /// One line per entry with its size and CRC, diffed to show what changed
+    fn manifest(&self) -> String {
+        self.entries.iter().map(ArchiveEntry::manifest_line).collect()
+    }
+}
+
+/// What changed between two versions of an archive
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveChange {
+    pub added: Vec<ArchiveEntry>,
+    pub removed: Vec<ArchiveEntry>,
+    pub changed: Vec<EntryChange>,
+}
+
+impl ArchiveChange {
+    /// Entries of `new` compared with those of `old`; with no `old`, every
+    /// entry is added. Entries with the same size and CRC are unchanged.
+    pub fn between(old: Option<&ArchiveListing>, new: &ArchiveListing) -> Self {
+        let empty = ArchiveListing::default();
+        let old = old.unwrap_or(&empty);
+        let old_entries: HashMap<&str, &ArchiveEntry> = old.entries.iter().map(|entry| (entry.name.as_str(), entry)).collect();
+        let new_names: HashSet<&str> = new.entries.iter().map(|entry| entry.name.as_str()).collect();
+
+        let mut change = Self {
+            removed: old.entries.iter().filter(|entry| !new_names.contains(entry.name.as_str())).cloned().collect(),
+            ..Self::default()
+        };
+        for entry in &new.entries {
+            match old_entries.get(entry.name.as_str()) {
+                None => change.added.push(entry.clone()),
+                Some(previous) if previous.size == entry.size && previous.crc32 == entry.crc32 => {}
+                Some(previous) => {
+                    let diff = match (old.texts.get(&entry.name), new.texts.get(&entry.name)) {
+                        (Some(before), Some(after)) => {
+                            Some(crate::diff::generate_unified_diff(before.as_str(), after.as_str(), &entry.name, &entry.name))
+                        }
+                        _ => None,
+                    };
+                    change.changed.push(EntryChange {
+                        name: entry.name.clone(),
+                        old_size: previous.size,
+                        new_size: entry.size,
+                        diff,
+                    });
+                }
+            }
+        }
+        change
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// One line per added (`+`), removed (`-`) or changed (`~`) entry, in
+    /// name order, with entries grouped under their directory
+    pub fn summary_lines(&self) -> Vec<String> {
+        let mut rows: Vec<(&str, char, String)> = Vec::new();
+        rows.extend(self.added.iter().map(|entry| (entry.name.as_str(), '+', format!("{} B", entry.size))));
+        rows.extend(self.removed.iter().map(|entry| (entry.name.as_str(), '-', format!("{} B", entry.size))));
+        rows.extend(self.changed.iter().map(|change| {
+            (change.name.as_str(), '~', format!("{} B -> {} B", change.old_size, change.new_size))
+        }));
+        rows.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut lines = Vec::new();
+        let mut current_dir = None;
+        for (name, marker, size) in rows {
+            let (dir, file) = match name.rsplit_once('/') {
+                Some((dir, file)) => (Some(dir), file),
+                None => (None, name),
+            };
+            if dir != current_dir {
+                if let Some(dir) = dir {
+                    lines.push(format!("{}/", dir));
+                }
+                current_dir = dir;
+            }
+            let indent = if dir.is_some() { "  " } else { "" };
+            lines.push(format!("{}{} {} ({})", indent, marker, file, size));
+        }
+        lines
+    }
+}
+
+/// Diff text of the entry manifests of two versions of an archive, shown as
+/// the archive event's diff
+pub fn manifest_diff(path: &Path, old: Option<&ArchiveListing>, new: &ArchiveListing) -> String {
+    let old = old.map(ArchiveListing::manifest).unwrap_or_default();
+    crate::diff::generate_unified_diff(old.as_str(), new.manifest().as_str(), path, path)
+}
+
+/// The last listing read of each archive under the watch root that matches
+/// `archive.globs`
+pub struct ArchiveTracker {
+    config: ArchiveConfig,
+    root: PathBuf,
+    globs: Gitignore,
+    listings: HashMap<PathBuf, Arc<ArchiveListing>>,
+}
+
+impl ArchiveTracker {
+    /// A tracker for `root`, or `None` when archive introspection is off
+    pub fn from_config(config: &ArchiveConfig, root: &Path) -> Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        if cfg!(not(feature = "archive")) {
+            tracing::warn!("archive.enabled is set, but watchdiff was built without the archive feature");
+            return Ok(None);
+        }
+        let mut builder = GitignoreBuilder::new(root);
+        for glob in &config.globs {
+            builder
+                .add_line(None, glob)
+                .with_context(|| format!("Invalid archive.globs pattern: {}", glob))?;
+        }
+        let globs = builder.build().context("Failed to build archive.globs patterns")?;
+        // Events carry absolute paths, so globs are matched against paths relative to the absolute root
+        let root = std::env::current_dir().map_or_else(|_| root.to_path_buf(), |cwd| cwd.join(root));
+        Ok(Some(Self { config: config.clone(), root, globs, listings: HashMap::new() }))
+    }
+
+    /// Whether `path` is an archive to look into
+    pub fn matches(&self, path: &Path) -> bool {
+        let path = std::env::current_dir().map_or_else(|_| path.to_path_buf(), |cwd| cwd.join(path));
+        path.strip_prefix(&self.root)
+            .is_ok_and(|relative| self.globs.matched_path_or_any_parents(relative, false).is_ignore())
+    }
+
+    /// Read `path` as it is now and remember it; returns the listing it had
+    /// before, if it was read before
+    pub fn record(&mut self, path: &Path) -> Result<(Option<Arc<ArchiveListing>>, Arc<ArchiveListing>)> {
+        let size = std::fs::metadata(path)?.len();
+        if size > self.config.max_archive_bytes {
+            bail!("archive is larger than {} bytes", self.config.max_archive_bytes);
+        }
+        let listing = Arc::new(ArchiveListing::read(&std::fs::read(path)?, &self.config)?);
+        let previous = self.listings.insert(path.to_path_buf(), Arc::clone(&listing));
+        Ok((previous, listing))
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.listings.remove(path);
+    }
+}
+
+#[cfg(all(test, feature = "archive"))]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::FileOptions;
+
+    fn zip_bytes(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for (name, content) in entries {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_entries_added_removed_and_changed() {
+        let config = ArchiveConfig::default();
+        let old = ArchiveListing::read(
+            &zip_bytes(&[
+                ("META-INF/MANIFEST.MF", b"Version: 1\n"),
+                ("lib/old.class", &[0xca, 0xfe, 0, 1]),
+                ("lib/same.class", &[0xca, 0xfe, 0, 2]),
+            ]),
+            &config,
+        )
+        .unwrap();
+        let new = ArchiveListing::read(
+            &zip_bytes(&[
+                ("META-INF/MANIFEST.MF", b"Version: 2\n"),
+                ("lib/same.class", &[0xca, 0xfe, 0, 2]),
+                ("lib/new.class", &[0xca, 0xfe, 0, 3]),
+            ]),
+            &config,
+        )
+        .unwrap();
+
+        let change = ArchiveChange::between(Some(&old), &new);
+        assert_eq!(change.added.iter().map(|e| e.name.as_str()).collect::<Vec<_>>(), vec!["lib/new.class"]);
+        assert_eq!(change.removed.iter().map(|e| e.name.as_str()).collect::<Vec<_>>(), vec!["lib/old.class"]);
+        assert_eq!(change.changed.len(), 1);
+        let diff = change.changed[0].diff.as_deref().unwrap();
+        assert!(diff.contains("-Version: 1"));
+        assert!(diff.contains("+Version: 2"));
+        assert_eq!(
+            change.summary_lines(),
+            vec!["META-INF/", "  ~ MANIFEST.MF (11 B -> 11 B)", "lib/", "  + new.class (4 B)", "  - old.class (4 B)"]
+        );
+
+        let manifest = manifest_diff(Path::new("app.jar"), Some(&old), &new);
+        assert!(manifest.contains("-lib/old.class 4 B crc"));
+        assert!(manifest.contains("+lib/new.class 4 B crc"));
+        assert!(!manifest.contains("-lib/same.class"));
+
+        // A first listing has every entry added
+        assert_eq!(ArchiveChange::between(None, &new).added.len(), 3);
+    }
+
+    #[test]
+    fn test_limits_refuse_zip_bombs() {
+        // 4 MiB of zeros deflates to a few KiB
+        let bomb = zip_bytes(&[("zeros.bin", &vec![0u8; 4 << 20])]);
+        assert!(bomb.len() < 64 * 1024);
+        let config = ArchiveConfig { max_total_bytes: 1 << 20, ..ArchiveConfig::default() };
+        let error = ArchiveListing::read(&bomb, &config).unwrap_err();
+        assert!(error.to_string().contains("decompresses to more than 1048576 bytes"));
+
+        let many = zip_bytes(&[("a", b"1"), ("b", b"2"), ("c", b"3")]);
+        let config = ArchiveConfig { max_entries: 2, ..ArchiveConfig::default() };
+        assert!(ArchiveListing::read(&many, &config).unwrap_err().to_string().contains("3 entries"));
+
+        let config = ArchiveConfig { max_archive_bytes: 16, ..ArchiveConfig::default() };
+        assert!(ArchiveListing::read(&many, &config).is_err());
+        assert!(ArchiveListing::read(b"not a zip", &ArchiveConfig::default()).is_err());
+
+        // No limit at all
+        let config = ArchiveConfig { max_total_bytes: u64::MAX, ..ArchiveConfig::default() };
+        assert_eq!(ArchiveListing::read(&many, &config).unwrap().entries.len(), 3);
+    }
+
+    #[test]
+    fn test_large_and_binary_entries_get_no_diff() {
+        let config = ArchiveConfig { max_entry_diff_bytes: 8, ..ArchiveConfig::default() };
+        let old = ArchiveListing::read(&zip_bytes(&[("big.txt", b"0123456789\n"), ("small.txt", b"a\n")]), &config).unwrap();
+        let new = ArchiveListing::read(&zip_bytes(&[("big.txt", b"0123456789!\n"), ("small.txt", b"b\n")]), &config).unwrap();
+        let change = ArchiveChange::between(Some(&old), &new);
+        let diffs: Vec<_> = change.changed.iter().map(|c| (c.name.as_str(), c.diff.is_some())).collect();
+        assert_eq!(diffs, vec![("big.txt", false), ("small.txt", true)]);
+    }
+}
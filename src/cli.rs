@@ -1,6 +1,12 @@
-use std::path::PathBuf;
-use clap::{Parser, ValueEnum};
-use crate::diff::DiffAlgorithmType;
+use std::path::{Path, PathBuf};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use crate::core::{FileEvent, PathDisplay};
+use crate::diff::{DiffAlgorithmType, DiffStats};
+use crate::core::ConfidenceLevel;
+use crate::export::{HunkSelection, LocationFormat};
+use crate::history::OriginKind;
+use crate::ui::{ColorDepth, IconMode};
 
 #[derive(Parser)]
 #[command(name = "watchdiff")]
@@ -33,6 +39,10 @@ pub struct Cli {
     #[arg(long, value_delimiter = ',', help = "File extensions to watch (e.g., rs,py,js)")]
     pub extensions: Option<Vec<String>>,
 
+    /// Files whose events are left out, as M does in the TUI
+    #[arg(long, value_name = "PATH", value_delimiter = ',', help = "Leave out events of these files, relative to the watch root (U lists them in the TUI)")]
+    pub mute: Vec<PathBuf>,
+
     /// Ignore additional patterns beyond .gitignore
     #[arg(long, value_delimiter = ',', help = "Additional patterns to ignore")]
     pub ignore: Option<Vec<String>>,
@@ -46,19 +56,428 @@ pub struct Cli {
     pub output: OutputFormat,
 
     /// Polling interval in milliseconds (for polling mode)
-    #[arg(long, default_value = "1000", help = "Polling interval in ms")]
-    pub poll_interval: u64,
+    #[arg(long, help = "Polling interval in ms [default: 1000]")]
+    pub poll_interval: Option<u64>,
     
     /// Diff algorithm to use
-    #[arg(long, default_value = "myers", help = "Diff algorithm (myers, patience, lcs)")]
+    #[arg(long, default_value = "myers", help = "Diff algorithm (myers, patience, lcs, histogram)")]
     pub algorithm: DiffAlgorithmType,
     
+    /// TUI redraw/input poll cadence
+    #[arg(long, default_value = "50", help = "TUI loop poll interval in ms")]
+    pub tui_refresh_ms: u64,
+    
+    /// Time queued writes get to finish on Ctrl+C or quit
+    #[arg(long, value_name = "MS", default_value = "3000", help = "Grace period for flushing writers on shutdown")]
+    pub shutdown_grace_ms: u64,
+    
+    /// Hide small modifications
+    #[arg(long, default_value = "0", help = "Hide modifications with fewer changed lines (0 = show all)")]
+    pub min_lines_changed: usize,
+    
+    /// How icons are drawn in the TUI
+    #[arg(long, value_enum, default_value = "emoji", help = "Icon style (emoji, ascii, none)")]
+    pub icons: IconMode,
+    
+    /// Shorthand for `--icons ascii`
+    #[arg(long, conflicts_with = "icons", help = "Use ASCII tags instead of emoji")]
+    pub no_emoji: bool,
+    
+    /// Colors the terminal can draw; detected from COLORTERM and TERM by default
+    #[arg(long, value_enum, help = "Color depth (16, 256, truecolor; default: detect)")]
+    pub color_depth: Option<ColorDepth>,
+    
+    /// Render previews and diffs as plain text, skipping syntect
+    #[arg(long, help = "Disable syntax highlighting (toggle in the TUI with F2)")]
+    pub no_highlight: bool,
+    
+    /// Leave out content previews of created and first-seen files
+    #[arg(long, help = "Do not keep or show content previews (toggle showing them in the TUI with F3)")]
+    pub no_preview: bool,
+    
+    /// Leave diffs and previews out of JSON output, keeping only their stats
+    #[arg(long, help = "Omit diff and content_preview from JSON output, keeping stats")]
+    pub stats_only: bool,
+    
+    /// Show the onboarding tour even if it was finished before
+    #[arg(long, help = "Start the TUI with the onboarding tour")]
+    pub tour: bool,
+    
+    /// Hash the tree at startup so `verify` can detect changes that were missed
+    #[arg(long, help = "Capture a content snapshot at startup for `verify`")]
+    pub integrity_snapshot: bool,
+    
+    /// Keep an editor location file of changed regions up to date
+    #[arg(long, value_name = "PATH", help = "Continuously rewrite a location list of changed hunks")]
+    pub locations_live: Option<PathBuf>,
+    
+    /// Format for --locations-live and the TUI location export
+    #[arg(long, value_enum, default_value = "quickfix", help = "Location list format (quickfix, json)")]
+    pub locations_format: LocationFormat,
+    
+    /// Warn when the watcher shows no sign of life for this long
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..), help = "Warn if no events or successful root checks occur for SECS seconds")]
+    pub expect_activity: Option<u64>,
+    
+    /// Tag events with a short hash of the changed file's content
+    #[arg(long, help = "Show a short SHA-256 of each changed file's content")]
+    pub content_hash: bool,
+    
+    /// Report unreadable files as errors instead of events without a diff
+    #[arg(long, help = "Report files that cannot be read or diffed (permissions, encoding, size limit) as errors")]
+    pub strict: bool,
+    
+    /// Show the git branch and commit, and report checkouts
+    #[arg(long, help = "Show the git branch and commit in the status bar and report branch switches")]
+    pub git_context: bool,
+    
+    /// Keep secrets found on added lines unredacted in JSON and exports
+    #[arg(long, help = "Do not redact secrets found on added lines in JSON output and exports (the TUI always redacts)")]
+    pub reveal_secrets: bool,
+    
+    /// Show the project name, root and session start above the diff log
+    #[arg(long, help = "Show a project header (name from Cargo.toml/package.json or the directory)")]
+    pub project_header: bool,
+    
+    /// Record every change in the history store, optionally at DIR
+    #[arg(long, value_name = "DIR", num_args = 0..=1, require_equals = true, help = "Record changes for `history query` [default: .watchdiff/history]")]
+    pub history_db: Option<Option<PathBuf>>,
+    
+    /// Keep diffs in the history, not just change metadata
+    #[arg(long, help = "Also record each change's diff in the history")]
+    pub history_diffs: bool,
+    
+    /// Answer read-only JSON queries about changes on a Unix socket instead of showing them
+    #[arg(long, value_name = "SOCKET", conflicts_with = "output", help = "Serve events and diffs to editors over a local Unix socket")]
+    pub serve: Option<PathBuf>,
+    
+    /// Threads computing diffs when many files change at once
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..256), help = "Compute diffs on N worker threads [default: 4]")]
+    pub diff_workers: Option<u64>,
+    
+    /// Only list files modified after this time in the initial scan
+    #[arg(long, value_name = "TIME", value_parser = parse_since_arg, help = "Only include files modified since TIME (RFC 3339, or relative like 2h, 30m, 1d)")]
+    pub since: Option<std::time::SystemTime>,
+    
+    /// Keep the history of matching files when the event log is pruned
+    #[arg(long = "pin", value_name = "GLOB", help = "Never prune events of files matching GLOB (gitignore syntax, repeatable)")]
+    pub pins: Vec<String>,
+    
+    /// Watch files git ignores too
+    #[arg(long, help = "Do not skip files ignored by .gitignore (.watchdiffignore still applies)")]
+    pub no_gitignore: bool,
+    
+    /// Deepest level watched
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..), help = "Only watch files up to N levels below the root (1 = the root's own files)")]
+    pub max_depth: Option<u64>,
+    
+    /// Only watch files matching these globs
+    #[arg(long = "include", value_name = "GLOB", help = "Only watch files matching GLOB, relative to the watch root (gitignore syntax, repeatable)")]
+    pub include: Vec<String>,
+    
+    /// Never watch files matching these globs
+    #[arg(long = "exclude", value_name = "GLOB", help = "Never watch files matching GLOB, even if included (gitignore syntax, repeatable)")]
+    pub exclude: Vec<String>,
+    
+    /// Sections of the TUI's file list
+    #[arg(long = "group", value_name = "LABEL=GLOB", help = "Group the TUI's file list into a LABEL section for files matching GLOB; first match wins (repeatable)")]
+    pub groups: Vec<String>,
+    
+    /// Coverage report used to flag changes to untested lines
+    #[arg(long, value_name = "PATH", help = "LCOV or Istanbul JSON coverage report; reloaded when it changes")]
+    pub coverage: Option<PathBuf>,
+    
+    /// Prefix removed from paths in the coverage report
+    #[arg(long, value_name = "PREFIX", help = "Strip PREFIX from coverage report paths")]
+    pub coverage_strip_prefix: Option<String>,
+    
+    /// Prefix added to coverage report paths after stripping
+    #[arg(long, value_name = "PREFIX", help = "Prepend PREFIX to coverage report paths")]
+    pub coverage_path_prefix: Option<String>,
+    
+    /// Intent file AI agents write before editing, relative to the watch root
+    #[arg(long, value_name = "PATH", help = "Read declared changes from PATH [default: .watchdiff/intent.json]")]
+    pub intent_file: Option<PathBuf>,
+    
+    /// Intents declared longer ago than this are ignored
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..), help = "Ignore intent files older than SECS seconds [default: 1800]")]
+    pub intent_max_age: Option<u64>,
+    
+    /// TOML file of confidence rules used instead of the configured ones
+    #[arg(long, value_name = "FILE", help = "Score with the [[rules]] in FILE instead of the configured [[ai.rules]]")]
+    pub confidence_rules: Option<PathBuf>,
+    
+    /// Daily windows holding notifications for a digest, e.g. 12:00-13:00,18:00-09:00
+    #[arg(long, value_name = "WINDOWS", help = "Hold notifications for a digest during HH:MM-HH:MM windows (comma-separated)")]
+    pub quiet_hours: Option<String>,
+    
+    /// Host directory mounted in a container, as HOST=CONTAINER
+    #[arg(long = "map", value_name = "HOST=CONTAINER", help = "Map host directory HOST to CONTAINER inside a container (repeatable)")]
+    pub maps: Vec<String>,
+    
+    /// docker-compose file whose services' bind mounts are mapped
+    #[arg(long, value_name = "FILE", help = "Map the bind mounts of the services in a docker-compose FILE")]
+    pub compose: Option<PathBuf>,
+    
+    /// Which path of a mapped change is shown
+    #[arg(long, value_enum, value_name = "WHICH", help = "Show host or container paths, or both, for mapped changes [default: host]")]
+    pub display_paths: Option<PathDisplay>,
+    
+    /// Command to run on each change; `{path}` and `{kind}` are substituted
+    #[arg(long, value_name = "CMD", help = "Run CMD through the shell on each change ({path}, {kind} placeholders)")]
+    pub exec: Option<String>,
+    
+    /// Kill a still-running --exec command and start it again on new changes
+    #[arg(long, requires = "exec", help = "Restart a running --exec command instead of queueing a rerun")]
+    pub restart: bool,
+    
+    /// Quiet period before the --exec command runs
+    #[arg(long, value_name = "MS", default_value = "300", requires = "exec", help = "Debounce for --exec in milliseconds")]
+    pub exec_debounce_ms: u64,
+    
     /// Export patches to directory (TUI mode only)
     #[arg(long, help = "Export patches to specified directory")]
     pub export_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
 }
 
-#[derive(Debug, Clone, ValueEnum)]
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Export the accepted hunks of a saved review session as a portable bundle
+    ExportBundle {
+        /// ID of the saved review session
+        session_id: String,
+        /// Bundle file to write
+        #[arg(short, long, default_value = "review.bundle.json")]
+        output: PathBuf,
+    },
+    /// Apply a review bundle to a checkout, verifying each file's base content
+    ApplyBundle {
+        /// Bundle file produced by export-bundle
+        bundle: PathBuf,
+        /// Root of the target checkout (defaults to the watch path)
+        #[arg(long)]
+        root: Option<PathBuf>,
+        /// Fall back to context matching when a file's base content differs
+        #[arg(long)]
+        fuzzy: bool,
+    },
+    /// Export all changes of one batch from a recorded log as a single patch
+    Export {
+        /// Batch ID to export
+        #[arg(long)]
+        batch: String,
+        /// Patch file to write
+        #[arg(long)]
+        out: PathBuf,
+        /// Events recorded with `--output json` to replay
+        #[arg(long)]
+        events: PathBuf,
+    },
+    /// Export every change from a recorded log as its own patch file, with a manifest
+    ExportPatches {
+        /// Directory to write the patches to
+        #[arg(long)]
+        out: PathBuf,
+        /// Events recorded with `--output json` to replay
+        #[arg(long)]
+        events: PathBuf,
+        /// Only export changes of this batch
+        #[arg(long)]
+        batch: Option<String>,
+        /// Finish an interrupted export, keeping the patches it already wrote
+        #[arg(long)]
+        resume: bool,
+    },
+    /// Write a jump list of changed hunks for an editor (Vim quickfix or JSON)
+    Locations {
+        /// Location list format
+        #[arg(long, value_enum, default_value = "quickfix")]
+        format: LocationFormat,
+        /// File to write (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Saved review session to list hunks from
+        #[arg(long, required_unless_present = "events", conflicts_with = "events")]
+        session: Option<String>,
+        /// Events recorded with `--output json` to replay
+        #[arg(long)]
+        events: Option<PathBuf>,
+        /// Which review decisions to include (sessions only)
+        #[arg(long, value_enum, default_value = "all")]
+        only: HunkSelection,
+    },
+    /// Report changes since a snapshot that no event was recorded for, as JSON
+    Verify {
+        /// Snapshot ID (defaults to the most recent one)
+        #[arg(long)]
+        snapshot: Option<String>,
+    },
+    /// Generate a tree and mutate it as a scenario describes, printing what was done as JSON lines
+    #[command(hide = true)]
+    Simulate {
+        /// Scenario file (TOML)
+        scenario: PathBuf,
+        /// Empty directory to build the tree in (defaults to a new temporary directory)
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Seed, overriding the scenario's
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Play the timeline this many times faster
+        #[arg(long, default_value = "1.0")]
+        speed: f64,
+        /// Seconds between building the tree and the first mutation
+        #[arg(long, default_value = "2")]
+        start_delay: u64,
+        /// Run under the scenario's AI tool name so origin detection sees it
+        #[arg(long)]
+        agent: bool,
+        /// Write the ground-truth log here instead of stdout
+        #[arg(long)]
+        log: Option<PathBuf>,
+    },
+    /// List, search and rename saved review sessions
+    Sessions {
+        #[command(subcommand)]
+        action: SessionsCommand,
+    },
+    /// Export a saved review session without opening the TUI
+    Review {
+        #[command(subcommand)]
+        action: ReviewCommand,
+    },
+    /// Query the changes recorded with --history-db
+    History {
+        #[command(subcommand)]
+        action: HistoryCommand,
+    },
+    /// Share the configuration as a profile, or adopt one
+    Profile {
+        #[command(subcommand)]
+        action: ProfileCommand,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ProfileCommand {
+    /// Write the effective configuration, without machine-specific paths and secrets
+    Export {
+        /// Profile file to write; JSON for a .json path, TOML otherwise
+        file: PathBuf,
+    },
+    /// Merge a profile into the user config, asking before changing a value already set
+    Import {
+        /// Profile file written by `profile export`
+        file: PathBuf,
+        /// Override values already set without asking
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ReviewCommand {
+    /// Write the markdown review report of a saved session
+    Export {
+        /// ID of the saved review session
+        #[arg(long)]
+        session: String,
+        /// Report file to write (defaults to next to the session)
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum HistoryCommand {
+    /// List recorded changes, newest first
+    Query {
+        /// Changes at or after this time: YYYY-MM-DD, YYYY-MM-DD HH:MM or RFC 3339
+        #[arg(long)]
+        since: Option<String>,
+        /// Changes before this time; a day includes all of it
+        #[arg(long)]
+        until: Option<String>,
+        /// Paths matching this glob (gitignore syntax, relative to the watch root)
+        #[arg(long)]
+        path: Option<String>,
+        #[arg(long, value_enum)]
+        origin: Option<OriginKind>,
+        #[arg(long, value_enum)]
+        confidence: Option<ConfidenceLevel>,
+        /// Batch ID, or part of one
+        #[arg(long)]
+        batch: Option<String>,
+        /// Read and show times in UTC instead of the local time zone
+        #[arg(long)]
+        utc: bool,
+        #[arg(long, value_enum, default_value = "table")]
+        format: HistoryFormat,
+        /// Changes per page
+        #[arg(long, default_value = "50")]
+        limit: usize,
+        #[arg(long, default_value = "1")]
+        page: usize,
+    },
+    /// Drop history beyond the configured retention now
+    Prune,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HistoryFormat {
+    Table,
+    Json,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum SessionsCommand {
+    /// Show saved sessions, newest first
+    List {
+        /// Fuzzy-search names, descriptions and ids
+        #[arg(long)]
+        search: Option<String>,
+    },
+    /// Give a saved session a new name; its id and file stay the same
+    Rename {
+        /// ID of the saved review session
+        session_id: String,
+        name: String,
+        /// Also replace the description
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// Set the description of a saved session
+    Describe {
+        /// ID of the saved review session
+        session_id: String,
+        #[arg(long)]
+        description: String,
+    },
+    /// Convert a saved session for another tool or a markdown report
+    Export {
+        /// ID of the saved review session
+        session_id: String,
+        #[arg(long, value_enum, default_value = "github-review")]
+        format: SessionExportFormat,
+        /// Write here instead of next to the session
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SessionExportFormat {
+    /// Draft pull request review for GitHub's review API, one comment per
+    /// rejected or commented hunk
+    GithubReview,
+    /// Markdown report of the session's decisions, rejected hunks first
+    Markdown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum WatchMode {
     /// Automatic detection (native events with polling fallback)
     Auto,
@@ -78,6 +497,40 @@ pub enum OutputFormat {
     Text,
     /// Compact single-line format
     Compact,
+    /// CSV with one row per event
+    Csv,
+    /// Git patches that `git apply` accepts, one per change
+    Patch,
+}
+
+/// One line of `--format json` output
+///
+/// Every `FileEvent` field is kept at the top level, with `diff` and
+/// `content_preview` left out under `--stats-only`.
+#[derive(Debug, Serialize)]
+pub struct JsonEventRecord {
+    #[serde(flatten)]
+    pub event: serde_json::Map<String, serde_json::Value>,
+    /// Path relative to the watch root, or the full path for files outside it
+    pub relative_path: PathBuf,
+    /// Line counts of the diff; all zero for events without one
+    pub stats: DiffStats,
+}
+
+impl JsonEventRecord {
+    pub fn new(event: &FileEvent, root: &Path, stats_only: bool) -> serde_json::Result<Self> {
+        let relative_path = event.path.strip_prefix(root).unwrap_or(&event.path).to_path_buf();
+        let stats = event.diff_stats();
+        let mut fields = match serde_json::to_value(event)? {
+            serde_json::Value::Object(fields) => fields,
+            _ => serde_json::Map::new(),
+        };
+        if stats_only {
+            fields.remove("diff");
+            fields.remove("content_preview");
+        }
+        Ok(Self { event: fields, relative_path, stats })
+    }
 }
 
 impl Cli {
@@ -87,6 +540,12 @@ impl Cli {
         })
     }
 
+    /// Files muted with --mute, as the watcher reports their paths
+    pub fn muted_paths(&self) -> std::collections::HashSet<PathBuf> {
+        let root = self.get_watch_path();
+        self.mute.iter().map(|path| root.join(path)).collect()
+    }
+
     pub fn should_watch_extension(&self, path: &std::path::Path) -> bool {
         if let Some(ref extensions) = self.extensions {
             if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
@@ -99,6 +558,20 @@ impl Cli {
         }
     }
 
+    /// Icon style, with `--no-emoji` selecting ASCII
+    pub fn icon_mode(&self) -> IconMode {
+        if self.no_emoji {
+            IconMode::Ascii
+        } else {
+            self.icons
+        }
+    }
+
+    /// Color depth from `--color-depth`, else detected from the environment
+    pub fn color_depth(&self) -> ColorDepth {
+        self.color_depth.unwrap_or_else(ColorDepth::detect)
+    }
+
     pub fn get_ignore_patterns(&self) -> Vec<String> {
         self.ignore.clone().unwrap_or_default()
     }
@@ -110,7 +583,9 @@ impl Cli {
             tracing::Level::INFO
         };
 
+        // Logs go to stderr so json, csv and patch output on stdout stay parseable
         tracing_subscriber::fmt()
+            .with_writer(std::io::stderr)
             .with_max_level(level)
             .with_target(false)
             .with_thread_ids(false)
@@ -134,10 +609,14 @@ impl Cli {
             return Err("Max events must be greater than 0".to_string());
         }
 
-        if self.poll_interval == 0 {
+        if self.poll_interval == Some(0) {
             return Err("Poll interval must be greater than 0".to_string());
         }
 
+        if self.tui_refresh_ms == 0 {
+            return Err("TUI refresh interval must be greater than 0".to_string());
+        }
+
         Ok(())
     }
 }
@@ -151,12 +630,134 @@ impl Default for Cli {
             verbose: false,
             no_color: false,
             extensions: None,
+            mute: Vec::new(),
             ignore: None,
             context: 3,
             output: OutputFormat::Tui,
-            poll_interval: 1000,
+            poll_interval: None,
             algorithm: DiffAlgorithmType::Myers,
+            tui_refresh_ms: 50,
+            shutdown_grace_ms: 3000,
+            min_lines_changed: 0,
+            icons: IconMode::Emoji,
+            no_emoji: false,
+            color_depth: None,
+            no_highlight: false,
+            no_preview: false,
+            stats_only: false,
+            tour: false,
+            integrity_snapshot: false,
+            locations_live: None,
+            locations_format: LocationFormat::Quickfix,
+            expect_activity: None,
+            content_hash: false,
+            strict: false,
+            git_context: false,
+            reveal_secrets: false,
+            project_header: false,
+            history_db: None,
+            history_diffs: false,
+            serve: None,
+            diff_workers: None,
+            since: None,
+            pins: Vec::new(),
+            no_gitignore: false,
+            max_depth: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            groups: Vec::new(),
+            coverage: None,
+            coverage_strip_prefix: None,
+            coverage_path_prefix: None,
+            intent_file: None,
+            intent_max_age: None,
+            confidence_rules: None,
+            quiet_hours: None,
+            maps: Vec::new(),
+            compose: None,
+            display_paths: None,
+            exec: None,
+            restart: false,
+            exec_debounce_ms: 300,
             export_dir: None,
+            command: None,
+        }
+    }
+}
+fn parse_since_arg(spec: &str) -> Result<std::time::SystemTime, String> {
+    parse_since(spec, std::time::SystemTime::now())
+}
+
+/// A point in time given as RFC 3339 (`2024-05-01T09:00:00Z`) or relative
+/// to `now`, such as `90s`, `30m`, `2h`, `1d`, `1w` or `1h30m`
+pub fn parse_since(spec: &str, now: std::time::SystemTime) -> Result<std::time::SystemTime, String> {
+    let spec = spec.trim();
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(spec) {
+        return Ok(time.with_timezone(&chrono::Utc).into());
+    }
+
+    let invalid = || format!("Invalid time '{}': expected RFC 3339 or a duration like 2h, 30m or 1d", spec);
+    let mut secs: u64 = 0;
+    let mut digits = String::new();
+    for c in spec.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
         }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3_600,
+            'd' => 86_400,
+            'w' => 604_800,
+            _ => return Err(invalid()),
+        };
+        let amount: u64 = digits.parse().map_err(|_| invalid())?;
+        secs = amount.checked_mul(unit).and_then(|s| s.checked_add(secs)).ok_or_else(invalid)?;
+        digits.clear();
+    }
+    if !digits.is_empty() || spec.is_empty() {
+        return Err(invalid());
+    }
+    now.checked_sub(std::time::Duration::from_secs(secs)).ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::FileEventKind;
+
+    #[test]
+    fn test_muted_paths_are_under_the_watch_root() {
+        let cli = Cli::parse_from(["watchdiff", "/repo", "--mute", "app.log,logs/debug.log", "--mute", "tmp/out.txt"]);
+        let muted = cli.muted_paths();
+        assert_eq!(muted.len(), 3);
+        assert!(muted.contains(Path::new("/repo/app.log")));
+        assert!(muted.contains(Path::new("/repo/logs/debug.log")));
+        assert!(muted.contains(Path::new("/repo/tmp/out.txt")));
+        assert!(Cli::default().muted_paths().is_empty());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_json_event_record_stats_and_relative_path() {
+        let event = FileEvent::new(PathBuf::from("/repo/src/main.rs"), FileEventKind::Modified)
+            .with_diff("--- a\n+++ b\n@@ -1,2 +1,3 @@\n-old\n+new\n+more\n keep".to_string())
+            .with_preview("new\nmore".to_string());
+
+        let full = serde_json::to_value(JsonEventRecord::new(&event, Path::new("/repo"), false).unwrap()).unwrap();
+        assert_eq!(full["relative_path"], "src/main.rs");
+        assert_eq!(full["path"], "/repo/src/main.rs");
+        assert_eq!(full["stats"]["lines_added"], 2);
+        assert_eq!(full["stats"]["lines_removed"], 1);
+        assert_eq!(full["stats"]["hunks"], 1);
+        assert!(full["diff"].is_string());
+
+        let small = serde_json::to_value(JsonEventRecord::new(&event, Path::new("/repo"), true).unwrap()).unwrap();
+        assert!(small.get("diff").is_none());
+        assert!(small.get("content_preview").is_none());
+        assert_eq!(small["stats"], full["stats"]);
+
+        let outside = serde_json::to_value(JsonEventRecord::new(&event, Path::new("/other"), true).unwrap()).unwrap();
+        assert_eq!(outside["relative_path"], "/repo/src/main.rs");
+    }
+}
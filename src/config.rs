@@ -3,11 +3,15 @@
 //! This module provides configuration structures and defaults for various
 //! components of the application including caching, file watching, and performance.
 
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::time::Duration;
 use serde::{Deserialize, Serialize};
 
 /// Global configuration for WatchDiff
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Default)]
+#[serde(default)]
 pub struct WatchDiffConfig {
     /// File watcher configuration
     pub watcher: WatcherConfig,
@@ -17,23 +21,225 @@ pub struct WatchDiffConfig {
     pub ui: UiConfig,
     /// AI detection configuration
     pub ai: AiConfig,
+    /// Notification delivery configuration
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+    /// Blast-radius budgets for AI runs
+    #[serde(default)]
+    pub budgets: BudgetConfig,
+    /// Command run on each change, like --exec
+    #[serde(default)]
+    pub exec: ExecConfig,
+    /// Test coverage report used to flag changes to untested lines
+    #[serde(default)]
+    pub coverage: CoverageConfig,
+    /// Host directories mounted into a container
+    #[serde(default)]
+    pub mounts: MountConfig,
+    /// Intent files declared by AI agents
+    #[serde(default)]
+    pub intent: IntentConfig,
+    /// Change history kept across sessions
+    #[serde(default)]
+    pub history: HistoryConfig,
+    /// Secret detection on added lines
+    #[serde(default)]
+    pub security: SecurityConfig,
+    /// Looking into changed .zip and .jar archives
+    #[serde(default)]
+    pub archive: ArchiveConfig,
+}
+
+/// Name of the project config file looked up in the watch root
+pub const PROJECT_CONFIG_FILE: &str = ".watchdiff.toml";
+
+/// Name of the user config file in [`user_config_dir`]. Project config keys
+/// override it.
+pub const USER_CONFIG_FILE: &str = "config.toml";
+
+/// `$XDG_CONFIG_HOME/watchdiff`, falling back to `~/.config/watchdiff`
+pub fn user_config_dir() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("watchdiff"))
+}
+
+/// Merge `over` into `base`: tables are merged key by key, any other value
+/// in `over` replaces the one in `base`
+pub fn merge_tables(base: &mut toml::Table, over: toml::Table) {
+    for (key, value) in over {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(over)) => merge_tables(base, over),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
 }
 
 /// Configuration for file watching
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WatcherConfig {
     /// Debounce duration for file events in milliseconds
     pub event_debounce_ms: u64,
+    /// How long a deletion waits for its file to reappear under another
+    /// name, to be reported as a move; 0 turns rename detection off
+    #[serde(default = "default_rename_window_ms")]
+    pub rename_window_ms: u64,
     /// Maximum number of events to keep in memory
     pub max_events: usize,
     /// Time to keep events before cleanup
     pub max_event_age_secs: u64,
     /// Cleanup interval in seconds
     pub cleanup_interval_secs: u64,
+    /// Maximum (decompressed) file size read for diffing, in bytes
+    #[serde(default = "default_max_diff_size_bytes")]
+    pub max_diff_size_bytes: usize,
+    /// Warn when the backend shows no sign of life for this many seconds
+    #[serde(default)]
+    pub expect_activity_secs: Option<u64>,
+    /// Keep content previews of created and first-seen files; off with --no-preview
+    #[serde(default = "default_preview")]
+    pub preview: bool,
+    /// Show a created file's content instead of an all-added diff
+    #[serde(default = "default_new_file_preview")]
+    pub new_file_preview: bool,
+    /// How much of a created file's content is kept for its preview, in bytes
+    #[serde(default = "default_new_file_preview_bytes")]
+    pub new_file_preview_bytes: usize,
+    /// Tag events with a short hash of the file's content after the change
+    #[serde(default = "default_content_hash")]
+    pub content_hash: bool,
+    /// Report files that cannot be read or diffed as errors instead of
+    /// delivering their events without a diff. Files that are not text by
+    /// name, such as images, are never read and are not errors.
+    #[serde(default = "default_strict")]
+    pub strict: bool,
+    /// Skip files ignored by git; off with --no-gitignore. The
+    /// `.watchdiffignore` file at the root applies either way.
+    #[serde(default = "default_gitignore")]
+    pub gitignore: bool,
+    /// Only watch paths matching one of these globs, when there are any
+    /// (gitignore syntax, relative to the watch root)
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Never watch paths matching one of these globs, even if included
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Deepest level watched, 1 being the root's own files; unlimited when unset
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    /// Directory names skipped wherever they are under the root
+    #[serde(default = "default_ignored_dirs")]
+    pub ignored_dirs: Vec<String>,
+    /// Diff lines longer than this many characters are truncated for display
+    /// and analysis; exported patches keep them whole
+    #[serde(default = "default_max_line_chars")]
+    pub max_line_chars: usize,
+    /// Merge diff hunks whose changes are fewer than this many unchanged
+    /// lines apart; 0 never merges
+    #[serde(default)]
+    pub merge_gap: usize,
+    /// Events kept per pinned path; pinned events do not count against
+    /// `max_events` and are never aged out
+    #[serde(default = "default_max_pinned_events_per_path")]
+    pub max_pinned_events_per_path: usize,
+    /// Most diff and preview text kept across the event log, in bytes,
+    /// counting the TUI's highlighted copies; the oldest unpinned events are
+    /// dropped beyond it
+    #[serde(default = "default_max_total_diff_bytes")]
+    pub max_total_diff_bytes: usize,
+    /// Threads computing diffs; changes wait for a free one once its queue is full
+    #[serde(default = "default_diff_workers")]
+    pub diff_workers: usize,
+    /// Show the git branch and commit, and report checkouts during the session
+    #[serde(default = "default_git_context")]
+    pub git_context: bool,
+    /// How file changes are detected
+    #[serde(default)]
+    pub backend: WatchBackend,
+    /// How often the polling backend scans the tree, in milliseconds
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+}
+
+/// File change detection requested in the config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchBackend {
+    /// Native events, falling back to polling when they can't be set up
+    #[default]
+    Auto,
+    Native,
+    Polling,
+}
+
+fn default_max_diff_size_bytes() -> usize {
+    crate::core::content::DEFAULT_MAX_CONTENT_SIZE
+}
+
+fn default_preview() -> bool {
+    true
+}
+
+fn default_new_file_preview() -> bool {
+    true
+}
+
+fn default_new_file_preview_bytes() -> usize {
+    crate::core::content::DEFAULT_NEW_FILE_PREVIEW_BYTES
+}
+
+fn default_content_hash() -> bool {
+    false
+}
+
+fn default_strict() -> bool {
+    false
+}
+
+fn default_gitignore() -> bool {
+    true
+}
+
+fn default_ignored_dirs() -> Vec<String> {
+    crate::core::filter::DEFAULT_IGNORED_DIRS.iter().map(|dir| dir.to_string()).collect()
+}
+
+fn default_git_context() -> bool {
+    false
+}
+
+fn default_poll_interval_ms() -> u64 {
+    1000
+}
+
+fn default_rename_window_ms() -> u64 {
+    crate::core::renames::DEFAULT_RENAME_WINDOW.as_millis() as u64
+}
+
+fn default_max_line_chars() -> usize {
+    crate::diff::DEFAULT_MAX_LINE_CHARS
+}
+
+fn default_max_pinned_events_per_path() -> usize {
+    crate::core::pins::DEFAULT_MAX_PINNED_EVENTS_PER_PATH
+}
+
+fn default_max_total_diff_bytes() -> usize {
+    crate::core::events::DEFAULT_MAX_TOTAL_DIFF_BYTES
+}
+
+fn default_diff_workers() -> usize {
+    crate::diff::DEFAULT_DIFF_WORKERS
 }
 
 /// Configuration for various caches
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CacheConfig {
     /// Maximum size of diff result cache
     pub diff_cache_size: usize,
@@ -43,10 +249,40 @@ pub struct CacheConfig {
     pub batch_changes_limit: usize,
     /// Cache cleanup threshold (when to trigger cleanup)
     pub cleanup_threshold: f32,
+    /// Maximum number of per-event content snapshots kept for comparisons
+    #[serde(default = "default_max_content_snapshots")]
+    pub max_content_snapshots: usize,
+    /// Maximum total size of retained content snapshots in bytes
+    #[serde(default = "default_max_snapshot_bytes")]
+    pub max_snapshot_bytes: usize,
+    /// Maximum total size of the last known content of watched files, which
+    /// the first change to a file is diffed against
+    #[serde(default = "default_max_baseline_bytes")]
+    pub max_baseline_bytes: usize,
+    /// Files larger than this are only hashed at startup, not kept
+    #[serde(default = "default_max_baseline_file_bytes")]
+    pub max_baseline_file_bytes: usize,
+}
+
+fn default_max_content_snapshots() -> usize {
+    crate::core::snapshots::DEFAULT_MAX_SNAPSHOTS
+}
+
+fn default_max_snapshot_bytes() -> usize {
+    crate::core::snapshots::DEFAULT_MAX_SNAPSHOT_BYTES
+}
+
+fn default_max_baseline_bytes() -> usize {
+    crate::core::snapshots::DEFAULT_MAX_BASELINE_BYTES
+}
+
+fn default_max_baseline_file_bytes() -> usize {
+    crate::core::snapshots::DEFAULT_MAX_BASELINE_FILE_BYTES
 }
 
 /// Configuration for user interface
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct UiConfig {
     /// Search debounce duration in milliseconds
     pub search_debounce_ms: u64,
@@ -54,10 +290,48 @@ pub struct UiConfig {
     pub max_search_results: usize,
     /// Default width for side-by-side diff display
     pub default_width: usize,
+    /// Ask for confirmation before leaving a review session with pending changes
+    #[serde(default = "default_confirm_review_exit")]
+    pub confirm_review_exit: bool,
+    /// Columns per tab stop in diff panes
+    #[serde(default = "default_tab_width")]
+    pub tab_width: usize,
+    /// Tab width per file extension, e.g. {"go": 8}
+    #[serde(default)]
+    pub tab_width_overrides: HashMap<String, usize>,
+    /// Mark tabs and trailing spaces on changed lines
+    #[serde(default)]
+    pub show_invisibles: bool,
+    /// Highlight the changed words of paired removed and added lines
+    #[serde(default = "default_word_diff")]
+    pub word_diff: bool,
+    /// Directory levels the summary's changes-per-directory rollup starts at
+    #[serde(default = "default_summary_rollup_depth")]
+    pub summary_rollup_depth: usize,
+    /// Sections of the watched file list as `Label=glob`, first match wins
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+fn default_summary_rollup_depth() -> usize {
+    1
+}
+
+fn default_tab_width() -> usize {
+    4
+}
+
+fn default_confirm_review_exit() -> bool {
+    true
+}
+
+fn default_word_diff() -> bool {
+    true
 }
 
 /// Configuration for AI detection and analysis
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AiConfig {
     /// How long to keep AI process information cached (seconds)
     pub process_cache_ttl_secs: u64,
@@ -65,15 +339,266 @@ pub struct AiConfig {
     pub batch_time_gap_secs: u64,
     /// Maximum age for changes in batch detection (seconds)
     pub batch_max_age_secs: u64,
+    /// Scale negative rule impacts in test code instead of scoring them like production code
+    #[serde(default = "default_dampen_test_code")]
+    pub dampen_test_code: bool,
+    /// Factor (0.0 - 1.0) applied to negative rule impacts in test code
+    #[serde(default = "default_test_dampening")]
+    pub test_dampening: f32,
+    /// Pattern rules scored in addition to the built-in ones
+    #[serde(default, alias = "custom_rules")]
+    pub rules: Vec<RuleConfig>,
+}
+
+/// A user-defined pattern rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleConfig {
+    /// Identifier reported in rule hits; `custom_rule_<index>` when empty
+    #[serde(default)]
+    pub id: String,
+    /// Regex matched against the diff
+    pub pattern: String,
+    /// Score change (-1.0 - 1.0) when the rule fires, e.g. -0.2
+    #[serde(default)]
+    pub impact: f32,
+    /// Reason shown with the change
+    pub reason: String,
+    #[serde(default)]
+    pub severity: crate::core::Severity,
+    /// Regex the changed file's path must match for the rule to apply. The
+    /// path is relative to the watch root, e.g. `src/db/migrations/002.sql`,
+    /// so `^src/` anchors at the root; a file outside the root is matched by
+    /// its full path.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+/// A standalone rules file, as given to `--confidence-rules`
+#[derive(Debug, Default, Deserialize)]
+struct RulesFile {
+    #[serde(default, alias = "custom_rules")]
+    rules: Vec<RuleConfig>,
+}
+
+/// Parse the `[[rules]]` tables of a standalone rules file
+pub fn rules_from_toml(text: &str) -> Result<Vec<RuleConfig>, String> {
+    toml::from_str::<RulesFile>(text).map(|file| file.rules).map_err(|e| e.to_string())
+}
+
+fn default_dampen_test_code() -> bool {
+    true
+}
+
+fn default_test_dampening() -> f32 {
+    0.25
+}
+
+/// Budgets on how much a single AI run may change; `None` disables a budget
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetConfig {
+    /// Maximum distinct files changed in one batch
+    pub max_files_per_batch: Option<usize>,
+    /// Maximum added + removed lines in one batch
+    pub max_lines_per_batch: Option<usize>,
+    /// Maximum distinct files changed within the rolling window, across all batches
+    pub max_files_per_window: Option<usize>,
+    /// Length of the rolling window in minutes
+    pub window_minutes: u64,
+    /// Stop scoring further events from a batch once it is over budget, until acknowledged
+    pub pause_over_budget: bool,
+}
+
+/// Configuration for notification delivery
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    /// Daily quiet-hours windows, e.g. "12:00-13:00,18:00-09:00"
+    pub quiet_hours: Option<String>,
+    /// Notification categories delivered even during focus mode or quiet hours
+    pub breakthrough_categories: Vec<String>,
+}
+
+/// Command to run on each change. Ignored in a project config file until the
+/// directory is trusted, see [`crate::trust`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecConfig {
+    /// Shell command with `{path}` and `{kind}` placeholders
+    pub command: Option<String>,
+    /// Kill a still-running command instead of queueing another run
+    pub restart: bool,
+    /// Quiet period before the command runs, in milliseconds
+    pub debounce_ms: u64,
+}
+
+/// Test coverage report used to flag changes to untested lines, see
+/// [`crate::coverage`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CoverageConfig {
+    /// lcov or istanbul JSON report, like --coverage
+    pub report: Option<std::path::PathBuf>,
+    /// Removed from the start of the report's paths
+    pub strip_prefix: Option<String>,
+    /// Added to the report's paths after stripping
+    pub path_prefix: Option<String>,
+    /// Confidence impact of a change whose lines are mostly uncovered
+    pub uncovered_impact: f32,
+    /// Fraction (0.0 - 1.0) of changed lines with coverage data that must be
+    /// uncovered for the impact to apply
+    pub uncovered_threshold: f32,
+}
+
+/// Host directories mounted into a container, see [`crate::core::mounts`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MountConfig {
+    /// `HOST=CONTAINER` mappings, like --map
+    pub maps: Vec<String>,
+    /// docker-compose file whose services' bind mounts are mapped, like --compose
+    pub compose: Option<std::path::PathBuf>,
+    /// Which path of a mapped event is shown
+    pub display: crate::core::PathDisplay,
+}
+
+/// Intent files declared by AI agents, see [`crate::ai::intent`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IntentConfig {
+    /// Intent file, relative to the watch root
+    pub path: std::path::PathBuf,
+    /// An intent declared longer ago than this is ignored
+    pub max_age_secs: u64,
+}
+
+impl Default for IntentConfig {
+    fn default() -> Self {
+        Self {
+            path: std::path::PathBuf::from(".watchdiff/intent.json"),
+            max_age_secs: 1800, // 30 minutes
+        }
+    }
+}
+
+impl IntentConfig {
+    pub fn max_age(&self) -> Duration {
+        Duration::from_secs(self.max_age_secs)
+    }
 }
 
-impl Default for WatchDiffConfig {
+/// Secret detection on added lines, see [`crate::security`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecurityConfig {
+    /// Scan added lines for secrets and redact them in all output
+    pub scan: bool,
+    /// Keep found secrets unredacted in JSON and export output, like --reveal-secrets
+    pub reveal: bool,
+    /// Minimum Shannon entropy, in bits per character, of a value assigned
+    /// to a token, secret, password or API key name
+    pub entropy_threshold: f64,
+    /// Values assigned to such names shorter than this are not secrets
+    pub min_length: usize,
+    /// Globs of paths never scanned, such as test fixtures
+    pub suppress_paths: Vec<String>,
+    /// Known fixture values never reported
+    pub allow_values: Vec<String>,
+}
+
+impl Default for SecurityConfig {
     fn default() -> Self {
         Self {
-            watcher: WatcherConfig::default(),
-            cache: CacheConfig::default(),
-            ui: UiConfig::default(),
-            ai: AiConfig::default(),
+            scan: true,
+            reveal: false,
+            entropy_threshold: 3.5,
+            min_length: 16,
+            suppress_paths: Vec::new(),
+            allow_values: Vec::new(),
+        }
+    }
+}
+
+/// Looking into changed archives, see [`crate::archive`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchiveConfig {
+    /// List and diff the entries of changed archives matching `globs`
+    pub enabled: bool,
+    /// Archives to look into (gitignore syntax, relative to the watch root)
+    pub globs: Vec<String>,
+    /// Larger archives are reported as binary files
+    pub max_archive_bytes: u64,
+    /// Archives with more entries are reported as binary files
+    pub max_entries: usize,
+    /// Reading stops when an archive decompresses to more than this many bytes
+    pub max_total_bytes: u64,
+    /// Text entries up to this size get a content diff
+    pub max_entry_diff_bytes: usize,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            globs: vec!["*.zip".to_string(), "*.jar".to_string()],
+            max_archive_bytes: 8 * 1024 * 1024,
+            max_entries: 2000,
+            max_total_bytes: 64 * 1024 * 1024,
+            max_entry_diff_bytes: 256 * 1024,
+        }
+    }
+}
+
+/// Change history kept across sessions, see [`crate::history`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// Record every change, like --history-db
+    pub enabled: bool,
+    /// Store directory, relative to the watch root
+    pub path: std::path::PathBuf,
+    /// Keep each change's diff, not just its metadata
+    pub include_diffs: bool,
+    /// History older than this many days is dropped at startup
+    pub max_age_days: Option<u64>,
+    /// Oldest history is dropped at startup while the store is larger than this
+    pub max_bytes: Option<u64>,
+    /// Size at which a new segment file is started
+    pub segment_bytes: u64,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: std::path::PathBuf::from(".watchdiff/history"),
+            include_diffs: false,
+            max_age_days: Some(90),
+            max_bytes: Some(256 * 1024 * 1024), // 256 MiB
+            segment_bytes: 4 * 1024 * 1024,     // 4 MiB
+        }
+    }
+}
+
+impl Default for CoverageConfig {
+    fn default() -> Self {
+        Self {
+            report: None,
+            strip_prefix: None,
+            path_prefix: None,
+            uncovered_impact: -0.2,
+            uncovered_threshold: 0.5,
+        }
+    }
+}
+
+impl Default for ExecConfig {
+    fn default() -> Self {
+        Self {
+            command: None,
+            restart: false,
+            debounce_ms: crate::exec::DEFAULT_EXEC_DEBOUNCE.as_millis() as u64,
         }
     }
 }
@@ -82,13 +607,53 @@ impl Default for WatcherConfig {
     fn default() -> Self {
         Self {
             event_debounce_ms: 100,
+            rename_window_ms: default_rename_window_ms(),
             max_events: 1000,
             max_event_age_secs: 3600, // 1 hour
             cleanup_interval_secs: 300, // 5 minutes
+            max_diff_size_bytes: default_max_diff_size_bytes(),
+            expect_activity_secs: None,
+            preview: default_preview(),
+            new_file_preview: default_new_file_preview(),
+            new_file_preview_bytes: default_new_file_preview_bytes(),
+            content_hash: default_content_hash(),
+            strict: default_strict(),
+            gitignore: default_gitignore(),
+            max_depth: None,
+            ignored_dirs: default_ignored_dirs(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            max_line_chars: default_max_line_chars(),
+            merge_gap: 0,
+            max_pinned_events_per_path: default_max_pinned_events_per_path(),
+            max_total_diff_bytes: default_max_total_diff_bytes(),
+            diff_workers: default_diff_workers(),
+            git_context: default_git_context(),
+            backend: WatchBackend::default(),
+            poll_interval_ms: default_poll_interval_ms(),
         }
     }
 }
 
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            max_files_per_batch: None,
+            max_lines_per_batch: None,
+            max_files_per_window: None,
+            window_minutes: 10,
+            pause_over_budget: false,
+        }
+    }
+}
+
+impl BudgetConfig {
+    /// Get the rolling window duration
+    pub fn window_duration(&self) -> Duration {
+        Duration::from_secs(self.window_minutes * 60)
+    }
+}
+
 impl Default for CacheConfig {
     fn default() -> Self {
         Self {
@@ -96,6 +661,10 @@ impl Default for CacheConfig {
             process_cache_size: 50,
             batch_changes_limit: 100,
             cleanup_threshold: 0.8, // Cleanup when 80% full
+            max_content_snapshots: default_max_content_snapshots(),
+            max_snapshot_bytes: default_max_snapshot_bytes(),
+            max_baseline_bytes: default_max_baseline_bytes(),
+            max_baseline_file_bytes: default_max_baseline_file_bytes(),
         }
     }
 }
@@ -106,6 +675,13 @@ impl Default for UiConfig {
             search_debounce_ms: 300,
             max_search_results: 1000,
             default_width: 120,
+            confirm_review_exit: default_confirm_review_exit(),
+            tab_width: default_tab_width(),
+            tab_width_overrides: HashMap::new(),
+            show_invisibles: false,
+            word_diff: default_word_diff(),
+            summary_rollup_depth: default_summary_rollup_depth(),
+            groups: Vec::new(),
         }
     }
 }
@@ -116,6 +692,9 @@ impl Default for AiConfig {
             process_cache_ttl_secs: 60, // 1 minute
             batch_time_gap_secs: 5,
             batch_max_age_secs: 30,
+            dampen_test_code: default_dampen_test_code(),
+            test_dampening: default_test_dampening(),
+            rules: Vec::new(),
         }
     }
 }
@@ -126,6 +705,22 @@ impl WatcherConfig {
         Duration::from_millis(self.event_debounce_ms)
     }
     
+    /// Get the time deletions are held back for rename detection
+    pub fn rename_window(&self) -> Duration {
+        Duration::from_millis(self.rename_window_ms)
+    }
+    
+    /// Get the polling backend's scan interval, at least a millisecond so
+    /// the poller never spins
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_millis(self.poll_interval_ms.max(1))
+    }
+    
+    /// Get the activity period the watchdog expects, if enabled
+    pub fn expect_activity_duration(&self) -> Option<Duration> {
+        self.expect_activity_secs.map(Duration::from_secs)
+    }
+    
     /// Get max event age duration
     pub fn max_event_age_duration(&self) -> Duration {
         Duration::from_secs(self.max_event_age_secs)
@@ -169,9 +764,24 @@ impl WatchDiffConfig {
         Self::default()
     }
     
+    /// Parse a config file. Every section and field is optional.
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+
+    /// Config from an already parsed file, or files merged with [`merge_tables`]
+    pub fn from_table(table: toml::Table) -> Result<Self, String> {
+        table.try_into().map_err(|e: toml::de::Error| e.to_string())
+    }
+
     /// Load configuration from environment variables
     pub fn from_env() -> Self {
-        let mut config = Self::default();
+        Self::default().with_env()
+    }
+
+    /// `self` with environment variable overrides applied
+    pub fn with_env(self) -> Self {
+        let mut config = self;
         
         // Override with environment variables if present
         if let Ok(val) = std::env::var("WATCHDIFF_DIFF_CACHE_SIZE") {
@@ -186,18 +796,217 @@ impl WatchDiffConfig {
             }
         }
         
+        if let Ok(val) = std::env::var("WATCHDIFF_MAX_PINNED_EVENTS") {
+            if let Ok(max) = val.parse::<usize>() {
+                config.watcher.max_pinned_events_per_path = max;
+            }
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_MAX_TOTAL_DIFF_BYTES") {
+            if let Ok(bytes) = val.parse::<usize>() {
+                config.watcher.max_total_diff_bytes = bytes;
+            }
+        }
+        
         if let Ok(val) = std::env::var("WATCHDIFF_EVENT_DEBOUNCE_MS") {
             if let Ok(ms) = val.parse::<u64>() {
                 config.watcher.event_debounce_ms = ms;
             }
         }
         
+        if let Ok(val) = std::env::var("WATCHDIFF_PREVIEW") {
+            config.watcher.preview = !matches!(val.as_str(), "0" | "false" | "no");
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_NEW_FILE_PREVIEW") {
+            config.watcher.new_file_preview = !matches!(val.as_str(), "0" | "false" | "no");
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_NEW_FILE_PREVIEW_BYTES") {
+            if let Ok(bytes) = val.parse::<usize>() {
+                config.watcher.new_file_preview_bytes = bytes;
+            }
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_COVERAGE") {
+            config.coverage.report = Some(std::path::PathBuf::from(val));
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_UNCOVERED_IMPACT") {
+            if let Ok(impact) = val.parse::<f32>() {
+                config.coverage.uncovered_impact = impact;
+            }
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_INTENT_FILE") {
+            config.intent.path = std::path::PathBuf::from(val);
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_INTENT_MAX_AGE_SECS") {
+            if let Ok(secs) = val.parse::<u64>() {
+                config.intent.max_age_secs = secs;
+            }
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_HISTORY_DB") {
+            config.history.enabled = true;
+            config.history.path = std::path::PathBuf::from(val);
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_COMPOSE_FILE") {
+            config.mounts.compose = Some(std::path::PathBuf::from(val));
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_DISPLAY_PATHS") {
+            if let Ok(display) = <crate::core::PathDisplay as clap::ValueEnum>::from_str(&val, true) {
+                config.mounts.display = display;
+            }
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_MAX_LINE_CHARS") {
+            if let Ok(chars) = val.parse::<usize>() {
+                config.watcher.max_line_chars = chars;
+            }
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_MERGE_GAP") {
+            if let Ok(gap) = val.parse::<usize>() {
+                config.watcher.merge_gap = gap;
+            }
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_DIFF_WORKERS") {
+            if let Ok(workers) = val.parse::<usize>() {
+                config.watcher.diff_workers = workers;
+            }
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_CONTENT_HASH") {
+            config.watcher.content_hash = !matches!(val.as_str(), "0" | "false" | "no");
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_STRICT") {
+            config.watcher.strict = !matches!(val.as_str(), "0" | "false" | "no");
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_GITIGNORE") {
+            config.watcher.gitignore = !matches!(val.as_str(), "0" | "false" | "no");
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_MAX_DEPTH") {
+            if let Ok(depth) = val.parse::<usize>() {
+                config.watcher.max_depth = (depth > 0).then_some(depth);
+            }
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_IGNORED_DIRS") {
+            config.watcher.ignored_dirs = val.split(',').map(str::trim).filter(|dir| !dir.is_empty()).map(String::from).collect();
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_REVEAL_SECRETS") {
+            config.security.reveal = !matches!(val.as_str(), "0" | "false" | "no");
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_ARCHIVES") {
+            config.archive.enabled = !matches!(val.as_str(), "0" | "false" | "no");
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_GIT_CONTEXT") {
+            config.watcher.git_context = !matches!(val.as_str(), "0" | "false" | "no");
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_RENAME_WINDOW_MS") {
+            if let Ok(ms) = val.parse::<u64>() {
+                config.watcher.rename_window_ms = ms;
+            }
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_POLL_INTERVAL_MS") {
+            if let Ok(ms) = val.parse::<u64>() {
+                config.watcher.poll_interval_ms = ms;
+            }
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_EXPECT_ACTIVITY_SECS") {
+            if let Ok(secs) = val.parse::<u64>() {
+                config.watcher.expect_activity_secs = Some(secs);
+            }
+        }
+        
         if let Ok(val) = std::env::var("WATCHDIFF_SEARCH_DEBOUNCE_MS") {
             if let Ok(ms) = val.parse::<u64>() {
                 config.ui.search_debounce_ms = ms;
             }
         }
         
+        if let Ok(val) = std::env::var("WATCHDIFF_CONFIRM_REVIEW_EXIT") {
+            config.ui.confirm_review_exit = !matches!(val.as_str(), "0" | "false" | "no");
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_TAB_WIDTH") {
+            if let Ok(width) = val.parse::<usize>() {
+                config.ui.tab_width = width;
+            }
+        }
+        
+        // Comma-separated extension=width pairs, e.g. "go=8,py=4"
+        if let Ok(val) = std::env::var("WATCHDIFF_TAB_WIDTHS") {
+            for pair in val.split(',') {
+                if let Some((ext, width)) = pair.split_once('=') {
+                    if let Ok(width) = width.trim().parse::<usize>() {
+                        let ext = ext.trim().trim_start_matches('.').to_string();
+                        config.ui.tab_width_overrides.insert(ext, width);
+                    }
+                }
+            }
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_SHOW_INVISIBLES") {
+            config.ui.show_invisibles = matches!(val.as_str(), "1" | "true" | "yes");
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_WORD_DIFF") {
+            config.ui.word_diff = !matches!(val.as_str(), "0" | "false" | "no");
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_MAX_BATCH_FILES") {
+            if let Ok(max) = val.parse::<usize>() {
+                config.budgets.max_files_per_batch = Some(max);
+            }
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_MAX_BATCH_LINES") {
+            if let Ok(max) = val.parse::<usize>() {
+                config.budgets.max_lines_per_batch = Some(max);
+            }
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_MAX_WINDOW_FILES") {
+            if let Ok(max) = val.parse::<usize>() {
+                config.budgets.max_files_per_window = Some(max);
+            }
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_BUDGET_WINDOW_MINUTES") {
+            if let Ok(minutes) = val.parse::<u64>() {
+                config.budgets.window_minutes = minutes;
+            }
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_PAUSE_OVER_BUDGET") {
+            config.budgets.pause_over_budget = matches!(val.as_str(), "1" | "true" | "yes");
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_DAMPEN_TESTS") {
+            config.ai.dampen_test_code = !matches!(val.as_str(), "0" | "false" | "no");
+        }
+        
+        if let Ok(val) = std::env::var("WATCHDIFF_TEST_DAMPENING") {
+            if let Ok(factor) = val.parse::<f32>() {
+                config.ai.test_dampening = factor;
+            }
+        }
+        
         config
     }
     
@@ -211,9 +1020,91 @@ impl WatchDiffConfig {
             return Err("max_events must be greater than 0".to_string());
         }
         
+        if !(0.0..=1.0).contains(&self.coverage.uncovered_threshold) {
+            return Err("coverage.uncovered_threshold must be between 0.0 and 1.0".to_string());
+        }
+        
+        if self.coverage.uncovered_impact > 0.0 {
+            return Err("coverage.uncovered_impact must not be positive".to_string());
+        }
+        
+        if self.ui.summary_rollup_depth == 0 {
+            return Err("ui.summary_rollup_depth must be greater than 0".to_string());
+        }
+        
+        if self.watcher.max_pinned_events_per_path == 0 {
+            return Err("max_pinned_events_per_path must be greater than 0".to_string());
+        }
+        
+        if self.watcher.max_total_diff_bytes == 0 {
+            return Err("max_total_diff_bytes must be greater than 0".to_string());
+        }
+        
+        if self.watcher.expect_activity_secs == Some(0) {
+            return Err("expect_activity_secs must be greater than 0".to_string());
+        }
+        
+        if self.watcher.max_line_chars == 0 {
+            return Err("max_line_chars must be greater than 0".to_string());
+        }
+        
+        if self.intent.max_age_secs == 0 {
+            return Err("intent.max_age_secs must be greater than 0".to_string());
+        }
+        
+        if self.history.max_age_days == Some(0) {
+            return Err("history.max_age_days must be greater than 0".to_string());
+        }
+        
+        if self.history.segment_bytes == 0 {
+            return Err("history.segment_bytes must be greater than 0".to_string());
+        }
+        
+        if !(0.0..=8.0).contains(&self.security.entropy_threshold) {
+            return Err("security.entropy_threshold must be between 0.0 and 8.0".to_string());
+        }
+        
+        if self.security.min_length == 0 {
+            return Err("security.min_length must be greater than 0".to_string());
+        }
+        
+        for map in &self.mounts.maps {
+            map.parse::<crate::core::PathMapping>().map_err(|err| err.to_string())?;
+        }
+        
+        if self.watcher.diff_workers == 0 {
+            return Err("diff_workers must be greater than 0".to_string());
+        }
+        
+        if self.watcher.new_file_preview_bytes == 0 {
+            return Err("new_file_preview_bytes must be greater than 0".to_string());
+        }
+        
+        if self.watcher.max_diff_size_bytes == 0 {
+            return Err("max_diff_size_bytes must be greater than 0".to_string());
+        }
+        
         if self.cache.cleanup_threshold <= 0.0 || self.cache.cleanup_threshold > 1.0 {
             return Err("cleanup_threshold must be between 0.0 and 1.0".to_string());
         }
+
+        if self.budgets.max_files_per_window.is_some() && self.budgets.window_minutes == 0 {
+            return Err("window_minutes must be greater than 0".to_string());
+        }
+
+        if !(0.0..=1.0).contains(&self.ai.test_dampening) {
+            return Err("test_dampening must be between 0.0 and 1.0".to_string());
+        }
+
+        crate::ai::ConfidenceScorer::with_config(&self.ai).map_err(|e| format!("ai.rules: {:#}", e))?;
+
+        if self.ui.tab_width == 0 || self.ui.tab_width_overrides.values().any(|w| *w == 0) {
+            return Err("tab_width must be greater than 0".to_string());
+        }
+
+        if let Some(ref spec) = self.notifications.quiet_hours {
+            crate::notifications::QuietHours::parse(spec).map_err(|e| e.to_string())?;
+        }
         
         Ok(())
     }
@@ -243,6 +1134,10 @@ mod tests {
         config.cache.diff_cache_size = 100;
         config.cache.cleanup_threshold = 1.5;
         assert!(config.validate().is_err());
+
+        config.cache.cleanup_threshold = 0.8;
+        config.notifications.quiet_hours = Some("9am-5pm".to_string());
+        assert!(config.validate().is_err());
     }
     
     #[test]
@@ -257,14 +1152,69 @@ mod tests {
     fn test_env_config_loading() {
         std::env::set_var("WATCHDIFF_DIFF_CACHE_SIZE", "200");
         std::env::set_var("WATCHDIFF_MAX_EVENTS", "2000");
+        // Only an explicit no turns a default-on switch off
+        std::env::set_var("WATCHDIFF_WORD_DIFF", "on");
         
         let config = WatchDiffConfig::from_env();
         
         assert_eq!(config.cache.diff_cache_size, 200);
         assert_eq!(config.watcher.max_events, 2000);
+        assert!(config.ui.word_diff);
+        
+        std::env::set_var("WATCHDIFF_WORD_DIFF", "no");
+        assert!(!WatchDiffConfig::from_env().ui.word_diff);
         
         // Cleanup
         std::env::remove_var("WATCHDIFF_DIFF_CACHE_SIZE");
         std::env::remove_var("WATCHDIFF_MAX_EVENTS");
+        std::env::remove_var("WATCHDIFF_WORD_DIFF");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_merged_tables_override_per_key() {
+        let mut base: toml::Table = toml::from_str("[ui]\ntab_width = 8\nshow_invisibles = true\n\n[watcher]\nexclude = [\"a/\", \"b/\"]\n").unwrap();
+        let over: toml::Table = toml::from_str("[ui]\ntab_width = 2\n\n[watcher]\nexclude = [\"c/\"]\n").unwrap();
+        merge_tables(&mut base, over);
+
+        let config = WatchDiffConfig::from_table(base).unwrap();
+        assert_eq!(config.ui.tab_width, 2);
+        assert!(config.ui.show_invisibles);
+        // Lists are replaced, not appended to
+        assert_eq!(config.watcher.exclude, ["c/"]);
+    }
+
+    #[test]
+    fn test_partial_toml_keeps_defaults() {
+        let config = WatchDiffConfig::from_toml("[ui]\ntab_width = 8\n\n[exec]\ncommand = \"make\"\n").unwrap();
+
+        assert_eq!(config.ui.tab_width, 8);
+        assert_eq!(config.exec.command.as_deref(), Some("make"));
+        assert_eq!(config.exec.debounce_ms, 300);
+        assert_eq!(config.watcher.max_events, 1000);
+        assert!(WatchDiffConfig::from_toml("[ui]\ntab_width = \"wide\"").is_err());
+    }
+    #[test]
+    fn test_custom_rules_validated_by_index() {
+        let text = r#"
+[[ai.custom_rules]]
+pattern = 'std::mem::transmute'
+impact = -0.4
+reason = "Transmute"
+
+[[ai.custom_rules]]
+pattern = '.*'
+path = '(unclosed'
+reason = "Broken scope"
+"#;
+        let config = WatchDiffConfig::from_toml(text).unwrap();
+        assert_eq!(config.ai.rules.len(), 2);
+        let error = config.validate().unwrap_err();
+        assert!(error.contains("rule 1 (custom_rule_1): invalid path pattern"), "{}", error);
+
+        // A standalone rules file holds the same tables at the top level
+        let rules = rules_from_toml("[[rules]]\nid = \"ffi\"\npattern = 'extern \"C\"'\nimpact = 1.5\nreason = \"FFI\"\n").unwrap();
+        let config = WatchDiffConfig { ai: AiConfig { rules, ..AiConfig::default() }, ..WatchDiffConfig::default() };
+        let error = config.validate().unwrap_err();
+        assert!(error.contains("rule 0 (ffi): impact 1.5 is not between -1.0 and 1.0"), "{}", error);
+    }
+}
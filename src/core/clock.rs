@@ -0,0 +1,140 @@
+//! Time source abstraction
+//!
+//! Time-dependent logic (event cleanup, batch detection, debouncing, summary
+//! time frames) reads the current time from a [`Clock`], so tests and event
+//! replay can drive it deterministically. Wall-clock time can step backwards
+//! (e.g. an NTP adjustment), so the helpers here never panic or wrap when it does.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use chrono::{DateTime, FixedOffset, Offset};
+
+/// Source of the current wall-clock time
+pub trait Clock: Send + Sync + fmt::Debug {
+    fn now(&self) -> SystemTime;
+
+    /// Offset of the local timezone at `time`, looked up each time so a
+    /// change of timezone takes effect at once
+    fn local_offset(&self, time: SystemTime) -> FixedOffset {
+        DateTime::<chrono::Local>::from(time).offset().fix()
+    }
+
+    /// The current time in the local timezone
+    fn local_now(&self) -> DateTime<FixedOffset> {
+        let now = self.now();
+        DateTime::<chrono::Utc>::from(now).with_timezone(&self.local_offset(now))
+    }
+}
+
+/// Shared handle to a clock
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Clock backed by the operating system
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Manually driven clock; clones share the same time and timezone
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<SystemTime>>,
+    /// Local timezone, the system's until set
+    offset: Arc<Mutex<Option<FixedOffset>>>,
+}
+
+impl MockClock {
+    pub fn new(start: SystemTime) -> Self {
+        Self { now: Arc::new(Mutex::new(start)), offset: Arc::default() }
+    }
+
+    /// Move the clock to another timezone, keeping the instant
+    pub fn set_offset(&self, offset: FixedOffset) {
+        *self.offset.lock().unwrap_or_else(|e| e.into_inner()) = Some(offset);
+    }
+
+    pub fn set(&self, time: SystemTime) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) = time;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(|e| e.into_inner());
+        *now += duration;
+    }
+
+    /// Step the clock backwards, as an NTP correction would
+    pub fn rewind(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(|e| e.into_inner());
+        *now = now.checked_sub(duration).unwrap_or(SystemTime::UNIX_EPOCH);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn local_offset(&self, time: SystemTime) -> FixedOffset {
+        match *self.offset.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some(offset) => offset,
+            None => DateTime::<chrono::Local>::from(time).offset().fix(),
+        }
+    }
+}
+
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// Time from `earlier` to `later`, or zero if the clock went backwards in between
+pub fn elapsed_between(earlier: SystemTime, later: SystemTime) -> Duration {
+    later.duration_since(earlier).unwrap_or(Duration::ZERO)
+}
+
+/// Time of day (`HH:MM:SS`) in the local timezone
+pub fn format_time_of_day(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Local>::from(time).format("%H:%M:%S").to_string()
+}
+
+/// Date and time (`YYYY-MM-DD HH:MM:SS`) in the local timezone
+pub fn format_local_datetime(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Local>::from(time).format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_is_shared_between_clones() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let clock = MockClock::new(start);
+        let handle = clock.clone();
+
+        handle.advance(Duration::from_secs(5));
+        assert_eq!(clock.now(), start + Duration::from_secs(5));
+
+        handle.rewind(Duration::from_secs(60));
+        assert_eq!(clock.now(), start - Duration::from_secs(55));
+    }
+
+    #[test]
+    fn test_elapsed_between_handles_backwards_steps() {
+        let earlier = SystemTime::UNIX_EPOCH + Duration::from_secs(100);
+        let later = earlier + Duration::from_secs(7);
+
+        assert_eq!(elapsed_between(earlier, later), Duration::from_secs(7));
+        assert_eq!(elapsed_between(later, earlier), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_format_pre_epoch_time_does_not_panic() {
+        let before_epoch = SystemTime::UNIX_EPOCH - Duration::from_secs(3600);
+        assert_eq!(format_time_of_day(before_epoch).len(), 8);
+    }
+}
@@ -0,0 +1,215 @@
+//! Coalescing bursts of raw file system events
+//!
+//! One save can reach the watcher as several events. Vim writes a temporary
+//! file and renames it over the original; other editors delete the file and
+//! create it again. [`EventCoalescer`] folds the events on each path into its
+//! net change and releases it once the path has been quiet for the debounce
+//! period. A rename over an existing file then comes out as one modification
+//! of that file, and a temporary file renamed away comes out as nothing.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind};
+
+/// A path's change as far as the coalescer is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawChange {
+    Created,
+    Modified,
+    Removed,
+}
+
+/// Changes to each path of `event`. A rename removes its source and modifies
+/// its target, since the target may have existed. Access events are left out.
+pub fn raw_changes(event: &Event) -> Vec<(PathBuf, RawChange)> {
+    let change = match event.kind {
+        EventKind::Create(_) => RawChange::Created,
+        EventKind::Remove(_) => RawChange::Removed,
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => RawChange::Removed,
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            let mut paths = event.paths.iter();
+            return paths.next().map(|from| (from.clone(), RawChange::Removed))
+                .into_iter()
+                .chain(paths.map(|to| (to.clone(), RawChange::Modified)))
+                .collect();
+        }
+        // Either side of a rename, which only the file system can tell
+        EventKind::Modify(ModifyKind::Name(RenameMode::Any | RenameMode::Other)) => {
+            return event.paths
+                .iter()
+                .map(|path| (path.clone(), if path.exists() { RawChange::Modified } else { RawChange::Removed }))
+                .collect();
+        }
+        EventKind::Modify(_) => RawChange::Modified,
+        _ => return Vec::new(),
+    };
+    event.paths.iter().map(|path| (path.clone(), change)).collect()
+}
+
+struct PendingPath {
+    first: RawChange,
+    last: RawChange,
+    first_seen: Instant,
+    last_seen: Instant,
+    /// Arrival order, so paths are released in the order they first changed
+    seq: u64,
+}
+
+impl PendingPath {
+    /// Net change from before the first event to after the last; None when
+    /// the path was created and removed again
+    fn net(&self) -> Option<RawChange> {
+        match (self.first, self.last) {
+            (RawChange::Created, RawChange::Removed) => None,
+            (_, RawChange::Removed) => Some(RawChange::Removed),
+            (RawChange::Created, _) => Some(RawChange::Created),
+            _ => Some(RawChange::Modified),
+        }
+    }
+}
+
+/// Raw changes waiting for their paths to go quiet
+pub struct EventCoalescer {
+    window: Duration,
+    /// A path written continuously is still released after this long
+    max_delay: Duration,
+    pending: HashMap<PathBuf, PendingPath>,
+    next_seq: u64,
+}
+
+impl EventCoalescer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            max_delay: window.saturating_mul(10),
+            pending: HashMap::new(),
+            next_seq: 0,
+        }
+    }
+
+    pub fn add(&mut self, path: PathBuf, change: RawChange, now: Instant) {
+        match self.pending.get_mut(&path) {
+            Some(pending) => {
+                pending.last = change;
+                pending.last_seen = now;
+            }
+            None => {
+                self.pending.insert(path, PendingPath { first: change, last: change, first_seen: now, last_seen: now, seq: self.next_seq });
+                self.next_seq += 1;
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// How long until the next path may be released, if any are waiting
+    pub fn next_due(&self, now: Instant) -> Option<Duration> {
+        self.pending
+            .values()
+            .map(|pending| self.due_at(pending))
+            .min()
+            .map(|due| due.saturating_duration_since(now))
+    }
+
+    /// Net changes of the paths quiet for the window, in the order they first changed
+    pub fn take_ready(&mut self, now: Instant) -> Vec<(PathBuf, RawChange)> {
+        let ready: Vec<PathBuf> = self.pending
+            .iter()
+            .filter(|(_, pending)| self.due_at(pending) <= now)
+            .map(|(path, _)| path.clone())
+            .collect();
+        self.release(ready)
+    }
+
+    /// Net changes of every waiting path
+    pub fn drain(&mut self) -> Vec<(PathBuf, RawChange)> {
+        let all: Vec<PathBuf> = self.pending.keys().cloned().collect();
+        self.release(all)
+    }
+
+    fn due_at(&self, pending: &PendingPath) -> Instant {
+        (pending.last_seen + self.window).min(pending.first_seen + self.max_delay)
+    }
+
+    fn release(&mut self, paths: Vec<PathBuf>) -> Vec<(PathBuf, RawChange)> {
+        let mut released: Vec<(PathBuf, PendingPath)> = paths
+            .into_iter()
+            .filter_map(|path| self.pending.remove_entry(&path))
+            .collect();
+        released.sort_by_key(|(_, pending)| pending.seq);
+        released
+            .into_iter()
+            .filter_map(|(path, pending)| pending.net().map(|change| (path, change)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, RemoveKind};
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_write_temp_then_rename_is_one_modification() {
+        let (temp, original) = (PathBuf::from("/r/.lib.rs.swp"), PathBuf::from("/r/lib.rs"));
+        let events = [
+            Event::new(EventKind::Create(CreateKind::File)).add_path(temp.clone()),
+            Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Any))).add_path(temp.clone()),
+            Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both))).add_path(temp.clone()).add_path(original.clone()),
+        ];
+        let start = Instant::now();
+        let mut coalescer = EventCoalescer::new(ms(100));
+        for (i, event) in events.iter().enumerate() {
+            for (path, change) in raw_changes(event) {
+                coalescer.add(path, change, start + ms(i as u64 * 10));
+            }
+        }
+
+        assert!(coalescer.take_ready(start + ms(50)).is_empty());
+        assert_eq!(coalescer.next_due(start + ms(50)), Some(ms(70)));
+        assert_eq!(coalescer.take_ready(start + ms(120)), vec![(original, RawChange::Modified)]);
+        assert!(coalescer.is_empty());
+        assert_eq!(coalescer.next_due(start), None);
+    }
+
+    #[test]
+    fn test_net_change_of_each_path() {
+        let start = Instant::now();
+        let mut coalescer = EventCoalescer::new(ms(100));
+        let bursts = [
+            ("/r/recreated", vec![RawChange::Removed, RawChange::Created]),
+            ("/r/new", vec![RawChange::Created, RawChange::Modified]),
+            ("/r/gone", vec![RawChange::Modified, RawChange::Removed]),
+            ("/r/edited", vec![RawChange::Modified, RawChange::Modified]),
+        ];
+        for (path, changes) in &bursts {
+            for change in changes {
+                coalescer.add(PathBuf::from(path), *change, start);
+            }
+        }
+        assert_eq!(coalescer.drain(), vec![
+            (PathBuf::from("/r/recreated"), RawChange::Modified),
+            (PathBuf::from("/r/new"), RawChange::Created),
+            (PathBuf::from("/r/gone"), RawChange::Removed),
+            (PathBuf::from("/r/edited"), RawChange::Modified),
+        ]);
+
+        // A path that never goes quiet is released after ten windows
+        for i in 0..12 {
+            coalescer.add(PathBuf::from("/r/log"), RawChange::Modified, start + ms(i * 90));
+        }
+        assert_eq!(coalescer.take_ready(start + ms(1000)), vec![(PathBuf::from("/r/log"), RawChange::Modified)]);
+
+        let removed = Event::new(EventKind::Remove(RemoveKind::File)).add_path(PathBuf::from("/r/a"));
+        assert_eq!(raw_changes(&removed), vec![(PathBuf::from("/r/a"), RawChange::Removed)]);
+        assert!(raw_changes(&Event::new(EventKind::Access(notify::event::AccessKind::Any)).add_path(PathBuf::from("/r/a"))).is_empty());
+    }
+}
@@ -0,0 +1,281 @@
+//! Reading file content for diffing and preview
+//!
+//! Compressed files are decoded on the fly based on their extension, so a
+//! change to `app.log.gz` is diffed as the text it contains. Text in UTF-16
+//! or Latin-1 is decoded to UTF-8, keeping note of the encoding it was in.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use flate2::read::MultiGzDecoder;
+use serde::{Deserialize, Serialize};
+
+/// Default limit on the (decompressed) size of content read for diffing
+pub const DEFAULT_MAX_CONTENT_SIZE: usize = 10 * 1024 * 1024;
+
+/// Default amount of a new file's content kept as its preview
+pub const DEFAULT_NEW_FILE_PREVIEW_BYTES: usize = 64 * 1024;
+
+/// `content` cut to at most `max_bytes` on a character boundary, with a
+/// trailing `...` when anything was cut
+pub fn preview_text(content: &str, max_bytes: usize) -> String {
+    if content.len() <= max_bytes {
+        return content.to_string();
+    }
+    let mut end = max_bytes;
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}...", &content[..end])
+}
+
+/// Short content hash: the first 8 hex digits of the SHA-256 of `content`
+/// with CRLF line endings normalized to LF, so a checkout with either line
+/// ending reports the same hash
+pub fn short_content_hash(content: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    let digest = if content.contains("\r\n") {
+        Sha256::digest(content.replace("\r\n", "\n").as_bytes())
+    } else {
+        Sha256::digest(content.as_bytes())
+    };
+    digest.iter().take(4).map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Decoder applied to a file's raw bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentDecoder {
+    Plain,
+    Gzip,
+}
+
+impl ContentDecoder {
+    pub fn for_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("gz") => ContentDecoder::Gzip,
+            _ => ContentDecoder::Plain,
+        }
+    }
+
+    /// Open `path` as a stream of decoded bytes
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Box<dyn Read>> {
+        let path = path.as_ref();
+        let file = BufReader::new(File::open(path)?);
+        Ok(match Self::for_path(path) {
+            ContentDecoder::Plain => Box::new(file),
+            ContentDecoder::Gzip => Box::new(MultiGzDecoder::new(file)),
+        })
+    }
+}
+
+/// Path of the decoded content, e.g. `app.log` for `app.log.gz`.
+/// Returns `None` for files that are not compressed.
+pub fn decoded_path(path: &Path) -> Option<&Path> {
+    match ContentDecoder::for_path(path) {
+        ContentDecoder::Gzip => path.file_stem().map(Path::new),
+        ContentDecoder::Plain => None,
+    }
+}
+
+/// Encoding a file's text was decoded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// ISO-8859-1, read as its Windows-1252 superset
+    Latin1,
+}
+
+impl TextEncoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => "UTF-8",
+            TextEncoding::Utf16Le => "UTF-16LE",
+            TextEncoding::Utf16Be => "UTF-16BE",
+            TextEncoding::Latin1 => "Latin-1",
+        }
+    }
+
+    /// The text in `bytes` and the encoding it was in. UTF-16 is recognized
+    /// by its byte order mark, or without one by the zero high bytes of
+    /// mostly-ASCII text; anything else that is not UTF-8 is read as Latin-1.
+    /// Content with zero bytes that is not UTF-16 is binary and gives `None`.
+    pub fn decode(bytes: &[u8]) -> Option<(String, TextEncoding)> {
+        let (encoding, text) = match Encoding::for_bom(bytes) {
+            Some((encoding, bom_len)) if encoding != UTF_8 => (encoding, &bytes[bom_len..]),
+            _ => match std::str::from_utf8(bytes) {
+                Ok(text) if !text.contains('\0') => return Some((text.to_string(), TextEncoding::Utf8)),
+                _ => (utf16_without_bom(bytes).or_else(|| (!bytes.contains(&0)).then_some(WINDOWS_1252))?, bytes),
+            },
+        };
+
+        let (decoded, _) = encoding.decode_without_bom_handling(text);
+        let detected = if encoding == UTF_16LE {
+            TextEncoding::Utf16Le
+        } else if encoding == UTF_16BE {
+            TextEncoding::Utf16Be
+        } else {
+            TextEncoding::Latin1
+        };
+        Some((decoded.into_owned(), detected))
+    }
+}
+
+/// UTF-16 without a byte order mark, guessed from where the zero bytes are:
+/// ASCII characters have a zero high byte, so about every other byte is zero
+fn utf16_without_bom(bytes: &[u8]) -> Option<&'static Encoding> {
+    if bytes.len() < 2 || bytes.len() % 2 != 0 {
+        return None;
+    }
+    let sample = &bytes[..bytes.len().min(4096)];
+    let pairs = sample.len() / 2;
+    let (even, odd) = sample.chunks_exact(2).fold((0, 0), |(even, odd), pair| {
+        (even + usize::from(pair[0] == 0), odd + usize::from(pair[1] == 0))
+    });
+    if even == 0 && odd * 2 >= pairs {
+        Some(UTF_16LE)
+    } else if odd == 0 && even * 2 >= pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// Reads file content as text, decompressing by extension, decoding UTF-16
+/// and Latin-1, and refusing content larger than `max_size` bytes once
+/// decompressed
+#[derive(Debug, Clone)]
+pub struct ContentReader {
+    max_size: usize,
+}
+
+impl Default for ContentReader {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONTENT_SIZE)
+    }
+}
+
+impl ContentReader {
+    pub fn new(max_size: usize) -> Self {
+        Self { max_size }
+    }
+
+    pub fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        self.read_text(path).map(|(content, _)| content)
+    }
+
+    /// The content as UTF-8 along with the encoding it was in
+    pub fn read_text<P: AsRef<Path>>(&self, path: P) -> io::Result<(String, TextEncoding)> {
+        let path = path.as_ref();
+        let reader = ContentDecoder::open(path)?;
+
+        // Read one byte past the limit so oversized content is detected
+        // without decompressing the whole stream
+        let mut bytes = Vec::new();
+        reader.take(self.max_size as u64 + 1).read_to_end(&mut bytes)?;
+        if bytes.len() > self.max_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("exceeds the maximum diff size of {} bytes", self.max_size),
+            ));
+        }
+
+        TextEncoding::decode(&bytes).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "not text: binary content or an unknown encoding")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use tempfile::TempDir;
+
+    fn write_gz(path: &Path, content: &str) {
+        let mut encoder = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    #[test]
+    fn test_modified_gz_diffs_decompressed_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("app.log.gz");
+        let reader = ContentReader::default();
+
+        write_gz(&path, "started\nrequest ok\n");
+        let old_content = reader.read_to_string(&path).unwrap();
+        write_gz(&path, "started\nrequest ok\nrequest failed\n");
+        let new_content = reader.read_to_string(&path).unwrap();
+
+        let diff = crate::diff::generate_unified_diff(&old_content, &new_content, &path, &path);
+        assert!(diff.contains("+request failed"));
+        assert!(!diff.contains("-request ok"));
+    }
+
+    #[test]
+    fn test_plain_files_read_unchanged() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("app.log");
+        std::fs::write(&path, "plain text\n").unwrap();
+
+        assert_eq!(ContentReader::default().read_to_string(&path).unwrap(), "plain text\n");
+    }
+
+    #[test]
+    fn test_decompressed_size_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("big.json.gz");
+        write_gz(&path, &"x".repeat(4096));
+
+        assert!(ContentReader::new(4096).read_to_string(&path).is_ok());
+        let err = ContentReader::new(1024).read_to_string(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    fn utf16le_with_bom(text: &str) -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        bytes
+    }
+
+    #[test]
+    fn test_utf16le_file_with_bom_diffs_its_text() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("settings.ini");
+        let reader = ContentReader::default();
+
+        std::fs::write(&path, utf16le_with_bom("[app]\r\nname=café\r\n")).unwrap();
+        let (old_content, encoding) = reader.read_text(&path).unwrap();
+        assert_eq!((old_content.as_str(), encoding), ("[app]\r\nname=café\r\n", TextEncoding::Utf16Le));
+        std::fs::write(&path, utf16le_with_bom("[app]\r\nname=café\r\ndebug=true\r\n")).unwrap();
+        let new_content = reader.read_to_string(&path).unwrap();
+
+        let diff = crate::diff::generate_unified_diff(&old_content, &new_content, &path, &path);
+        assert!(diff.contains("+debug=true"));
+        assert!(!diff.contains("-name=café"));
+    }
+
+    #[test]
+    fn test_encoding_detection() {
+        let be: Vec<u8> = "key=value\n".encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(TextEncoding::decode(&be), Some(("key=value\n".to_string(), TextEncoding::Utf16Be)));
+        let le: Vec<u8> = "key=value\n".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(TextEncoding::decode(&le).map(|(_, encoding)| encoding), Some(TextEncoding::Utf16Le));
+        assert_eq!(TextEncoding::decode(b"caf\xe9\n"), Some(("café\n".to_string(), TextEncoding::Latin1)));
+        assert_eq!(TextEncoding::decode("café\n".as_bytes()).map(|(_, encoding)| encoding), Some(TextEncoding::Utf8));
+        assert_eq!(TextEncoding::decode(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\x01"), None);
+    }
+
+    #[test]
+    fn test_decoded_path() {
+        assert_eq!(decoded_path(Path::new("logs/app.log.gz")), Some(Path::new("app.log")));
+        assert_eq!(decoded_path(Path::new("logs/app.log")), None);
+    }
+}
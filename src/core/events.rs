@@ -1,8 +1,12 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::SystemTime;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use serde::{Deserialize, Serialize};
 use crate::config::WatchDiffConfig;
+use super::clock::{system_clock, SharedClock};
+use super::pins::{PinnedPaths, DEFAULT_MAX_PINNED_EVENTS_PER_PATH};
+use super::snapshots::{ContentSnapshots, SnapshotKey};
 use super::summary::{ChangeSummary, SummaryFilters};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,7 +25,7 @@ pub enum ChangeOrigin {
     Unknown,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
 pub enum ConfidenceLevel {
     Safe,    // 🟢 Low risk, likely correct
     Review,  // 🟡 Medium risk, should review  
@@ -33,6 +37,91 @@ pub struct ChangeConfidence {
     pub level: ConfidenceLevel,
     pub score: f32,  // 0.0 (risky) to 1.0 (safe)
     pub reasons: Vec<String>,
+    /// Score impact of each entry in `reasons`, parallel to it
+    #[serde(default)]
+    pub reason_impacts: Vec<f32>,
+    /// Machine-readable record of every pattern rule that fired
+    #[serde(default)]
+    pub rule_hits: Vec<RuleHit>,
+    /// How the final score was assembled
+    #[serde(default)]
+    pub score_breakdown: Option<ScoreBreakdown>,
+}
+
+impl ChangeConfidence {
+    /// Whether a Critical rule fired, which makes the change Risky whatever its score
+    pub fn is_critical(&self) -> bool {
+        self.critical_hits().next().is_some()
+    }
+
+    pub fn critical_hits(&self) -> impl Iterator<Item = &RuleHit> {
+        self.rule_hits.iter().filter(|hit| hit.severity == Severity::Critical)
+    }
+
+    /// Each reason with its score impact, `None` where it was not recorded
+    pub fn explained_reasons(&self) -> impl Iterator<Item = (&str, Option<f32>)> {
+        self.reasons
+            .iter()
+            .enumerate()
+            .map(|(i, reason)| (reason.as_str(), self.reason_impacts.get(i).copied()))
+    }
+}
+
+/// How serious a rule's finding is, apart from its score impact
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Cosmetic, e.g. a TODO comment
+    Info,
+    #[default]
+    Warning,
+    /// Blocking, e.g. private key material: the change is Risky regardless of its score
+    Critical,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+/// A pattern rule that matched a change
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleHit {
+    pub rule_id: String,
+    pub impact: f32,
+    #[serde(default)]
+    pub severity: Severity,
+    /// Line in the new file where the rule first matched, if it matched a single line
+    pub line: Option<usize>,
+    pub excerpt: Option<String>,
+    /// Every match was in test code, so `impact` was dampened
+    #[serde(default)]
+    pub in_test_code: bool,
+}
+
+/// Per-rule contribution to a confidence score
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreDelta {
+    pub rule_id: String,
+    pub delta: f32,
+}
+
+/// Auditable decomposition of a confidence score:
+/// `base + sum(rule_deltas) + file_type_delta + size_delta + clamp_delta == final_score`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    pub base: f32,
+    pub rule_deltas: Vec<ScoreDelta>,
+    pub file_type_delta: f32,
+    pub size_delta: f32,
+    /// Adjustment applied when clamping the score into 0.0..=1.0
+    pub clamp_delta: f32,
+    pub final_score: f32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +134,53 @@ pub struct FileEvent {
     pub origin: ChangeOrigin,
     pub confidence: Option<ChangeConfidence>,
     pub batch_id: Option<String>,  // Groups related changes together
+    /// Set when the event's batch exceeded a change budget and was only tagged, not scored
+    #[serde(default)]
+    pub over_budget: bool,
+    /// Short hash of the content after the change, with --content-hash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// Full content of diff lines that were truncated for display and analysis
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub long_lines: Option<crate::diff::LongLines>,
+    /// Test coverage of the changed lines, with --coverage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<crate::coverage::ChangeCoverage>,
+    /// Why the origin was attributed, e.g. "matched process 'claude' pid 1234"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attribution_reason: Option<String>,
+    /// Dependency changes when the file is a Cargo or npm manifest or lockfile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest_change: Option<crate::manifest::ManifestChange>,
+    /// The path inside the container, when the file is under a mapped mount
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container_path: Option<PathBuf>,
+    /// Reason an agent's intent file gave for changing this file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intent: Option<String>,
+    /// Set when a fresh intent file exists but does not declare this file
+    #[serde(default)]
+    pub undeclared: bool,
+    /// Set when the file only grew at the end, like a log; the diff then holds
+    /// just the appended lines
+    #[serde(default)]
+    pub append_only: bool,
+    /// Encoding of a text file that is not UTF-8; its diff and preview show the decoded text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<super::TextEncoding>,
+    /// Secrets found on the added lines, with their values redacted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secrets: Vec<crate::security::SecretFinding>,
+    /// Set when secret values in the diff, preview or content were masked;
+    /// the diff then no longer matches the file's real lines
+    #[serde(default)]
+    pub masked: bool,
+    /// Entries added, removed or changed when the file is a watched archive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive: Option<crate::archive::ArchiveChange>,
+    /// Full file content after the change, moved into the state's snapshot store
+    #[serde(skip)]
+    pub content: Option<Arc<str>>,
 }
 
 #[derive(Debug, Clone)]
@@ -59,6 +195,18 @@ pub struct HighlightedFileEvent {
     pub origin: ChangeOrigin,
     pub confidence: Option<ChangeConfidence>,
     pub batch_id: Option<String>,
+    pub over_budget: bool,
+    pub content_hash: Option<String>,
+    pub coverage: Option<crate::coverage::ChangeCoverage>,
+    pub attribution_reason: Option<String>,
+    pub manifest_change: Option<crate::manifest::ManifestChange>,
+    pub container_path: Option<PathBuf>,
+    pub intent: Option<String>,
+    pub undeclared: bool,
+    pub append_only: bool,
+    pub encoding: Option<super::TextEncoding>,
+    pub secrets: Vec<crate::security::SecretFinding>,
+    pub archive: Option<crate::archive::ArchiveChange>,
 }
 
 impl FileEvent {
@@ -72,6 +220,21 @@ impl FileEvent {
             origin: ChangeOrigin::Unknown,
             confidence: None,
             batch_id: None,
+            over_budget: false,
+            content_hash: None,
+            long_lines: None,
+            coverage: None,
+            attribution_reason: None,
+            manifest_change: None,
+            container_path: None,
+            intent: None,
+            undeclared: false,
+            append_only: false,
+            encoding: None,
+            secrets: Vec::new(),
+            masked: false,
+            archive: None,
+            content: None,
         }
     }
 
@@ -80,6 +243,11 @@ impl FileEvent {
         self
     }
 
+    pub fn with_attribution_reason(mut self, reason: String) -> Self {
+        self.attribution_reason = Some(reason);
+        self
+    }
+
     pub fn with_confidence(mut self, confidence: ChangeConfidence) -> Self {
         self.confidence = Some(confidence);
         self
@@ -95,30 +263,143 @@ impl FileEvent {
         self
     }
 
+    /// Set the diff, truncating lines longer than `max_line_chars` and
+    /// keeping their full content for exports
+    pub fn with_bounded_diff(mut self, diff: &str, max_line_chars: usize) -> Self {
+        let (diff, long_lines) = crate::diff::truncate_long_lines(diff, max_line_chars);
+        self.diff = Some(diff);
+        self.long_lines = (!long_lines.is_empty()).then_some(long_lines);
+        self
+    }
+
+    /// The diff with truncated long lines restored, as exported patches need it
+    pub fn full_diff(&self) -> Option<String> {
+        let diff = self.diff.as_ref()?;
+        Some(match self.long_lines {
+            Some(ref long_lines) => long_lines.restore_diff(diff),
+            None => diff.clone(),
+        })
+    }
+
     pub fn with_preview(mut self, preview: String) -> Self {
         self.content_preview = Some(preview);
         self
     }
 
+    pub fn with_content_hash(mut self, hash: String) -> Self {
+        self.content_hash = Some(hash);
+        self
+    }
+
+    pub fn with_manifest_change(mut self, change: crate::manifest::ManifestChange) -> Self {
+        self.manifest_change = Some(change);
+        self
+    }
+
+    pub fn with_archive_change(mut self, change: crate::archive::ArchiveChange) -> Self {
+        self.archive = Some(change);
+        self
+    }
+
+    pub fn with_intent(mut self, reason: String) -> Self {
+        self.intent = Some(reason);
+        self
+    }
+
+    pub fn with_encoding(mut self, encoding: super::TextEncoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    pub fn with_container_path(mut self, path: PathBuf) -> Self {
+        self.container_path = Some(path);
+        self
+    }
+
+    /// The path as `display` asks for it; the host path when it is not mapped
+    pub fn display_path(&self, display: crate::core::PathDisplay) -> String {
+        display_path(&self.path, self.container_path.as_deref(), display)
+    }
+
+    pub fn with_coverage(mut self, coverage: crate::coverage::ChangeCoverage) -> Self {
+        self.coverage = Some(coverage);
+        self
+    }
+
+    pub fn with_content(mut self, content: Arc<str>) -> Self {
+        self.content = Some(content);
+        self
+    }
+
+    /// Ids of the confidence rules that fired, in the order they were hit
+    pub fn rule_ids(&self) -> Vec<String> {
+        self.confidence
+            .iter()
+            .flat_map(|confidence| confidence.rule_hits.iter().map(|hit| hit.rule_id.clone()))
+            .collect()
+    }
+
+    /// Line and hunk counts of the diff; all zero for events without one
+    pub fn diff_stats(&self) -> crate::diff::DiffStats {
+        self.diff.as_deref().map(crate::diff::DiffStats::from_unified_diff).unwrap_or_default()
+    }
+
+    /// Whether the event changes at least `min_lines` lines (added + removed).
+    /// Creations and deletions always pass, as do events without a diff.
+    pub fn meets_line_threshold(&self, min_lines: usize) -> bool {
+        meets_line_threshold(&self.kind, self.diff.as_deref(), min_lines)
+    }
+
+    /// This event with its diff and preview highlighted for terminals, as
+    /// far as the log shows them
     pub fn to_highlighted(&self) -> HighlightedFileEvent {
-        let highlighted_event = HighlightedFileEvent {
+        self.to_log_event(true)
+    }
+
+    /// This event for the log, highlighted only when `highlight` is set;
+    /// otherwise the highlighter is not touched
+    pub fn to_log_event(&self, highlight: bool) -> HighlightedFileEvent {
+        HighlightedFileEvent {
             path: self.path.clone(),
             kind: self.kind.clone(),
             timestamp: self.timestamp,
             diff: self.diff.clone(),
             content_preview: self.content_preview.clone(),
-            highlighted_diff: None,
-            highlighted_preview: None,
+            highlighted_diff: highlight.then(|| self.highlighted_diff()).flatten(),
+            highlighted_preview: highlight.then(|| self.highlighted_preview()).flatten(),
             origin: self.origin.clone(),
             confidence: self.confidence.clone(),
             batch_id: self.batch_id.clone(),
-        };
+            over_budget: self.over_budget,
+            content_hash: self.content_hash.clone(),
+            coverage: self.coverage,
+            attribution_reason: self.attribution_reason.clone(),
+            manifest_change: self.manifest_change.clone(),
+            container_path: self.container_path.clone(),
+            intent: self.intent.clone(),
+            undeclared: self.undeclared,
+            append_only: self.append_only,
+            encoding: self.encoding,
+            secrets: self.secrets.clone(),
+            archive: self.archive.clone(),
+        }
+    }
 
-        // Skip syntax highlighting to avoid ANSI escape codes in TUI
-        // The TUI will use its own built-in coloring for diff display
-        // Terminal highlighting is only useful for non-TUI output modes
+    /// Highlighted diff, for text files of a known language
+    fn highlighted_diff(&self) -> Option<String> {
+        let diff = self.diff.as_deref().filter(|diff| !crate::diff::is_binary_diff(diff))?;
+        let highlighter = crate::highlight::shared();
+        let language = highlighter.get_language_from_path(&self.path)?;
+        highlighter.get_terminal_highlighted_diff(diff, &language, HIGHLIGHTED_LINES)
+    }
 
-        highlighted_event
+    /// Highlighted preview, for files of a known language
+    fn highlighted_preview(&self) -> Option<String> {
+        let preview = self.content_preview.as_deref()?;
+        let highlighter = crate::highlight::shared();
+        let language = highlighter.get_language_from_path(&self.path)?;
+        let shown: String = preview.lines().take(HIGHLIGHTED_LINES).map(|line| format!("{}\n", line)).collect();
+        Some(highlighter.get_terminal_highlighted(&shown, &language))
     }
 }
 
@@ -126,11 +407,104 @@ impl HighlightedFileEvent {
     pub fn from_file_event(event: FileEvent) -> Self {
         event.to_highlighted()
     }
+
+    /// See [`FileEvent::display_path`]
+    pub fn display_path(&self, display: crate::core::PathDisplay) -> String {
+        display_path(&self.path, self.container_path.as_deref(), display)
+    }
+
+    /// See [`FileEvent::meets_line_threshold`]
+    pub fn meets_line_threshold(&self, min_lines: usize) -> bool {
+        meets_line_threshold(&self.kind, self.diff.as_deref(), min_lines)
+    }
+}
+
+fn display_path(host: &Path, container: Option<&Path>, display: crate::core::PathDisplay) -> String {
+    use crate::core::PathDisplay;
+    match (display, container) {
+        (PathDisplay::Container, Some(container)) => container.display().to_string(),
+        (PathDisplay::Both, Some(container)) => format!("{} → {}", host.display(), container.display()),
+        _ => host.display().to_string(),
+    }
+}
+
+fn meets_line_threshold(kind: &FileEventKind, diff: Option<&str>, min_lines: usize) -> bool {
+    if min_lines == 0 || matches!(kind, FileEventKind::Created | FileEventKind::Deleted) {
+        return true;
+    }
+    diff.map_or(true, |diff| crate::diff::DiffStats::from_unified_diff(diff).total_changes() >= min_lines)
+}
+
+/// Which change budget was exceeded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BudgetKind {
+    BatchFiles,
+    BatchLines,
+    WindowFiles,
+}
+
+/// Raised once when an AI run exceeds a configured budget
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BudgetAlert {
+    pub kind: BudgetKind,
+    pub batch_id: Option<String>,
+    pub tool_name: Option<String>,
+    pub observed: usize,
+    pub limit: usize,
+    /// Rolling window length, for `WindowFiles` alerts
+    pub window_secs: Option<u64>,
+    pub timestamp: SystemTime,
+}
+
+impl BudgetAlert {
+    pub fn message(&self) -> String {
+        let source = match (&self.batch_id, &self.tool_name) {
+            (Some(batch), Some(tool)) => format!("{} ({})", batch, tool),
+            (Some(batch), None) => batch.clone(),
+            (None, Some(tool)) => tool.clone(),
+            (None, None) => "unbatched changes".to_string(),
+        };
+
+        match self.kind {
+            BudgetKind::BatchFiles => format!(
+                "{} changed {} files (budget {})", source, self.observed, self.limit
+            ),
+            BudgetKind::BatchLines => format!(
+                "{} changed {} lines (budget {})", source, self.observed, self.limit
+            ),
+            BudgetKind::WindowFiles => format!(
+                "{} files changed in the last {} min (budget {}), latest from {}",
+                self.observed,
+                self.window_secs.unwrap_or_default() / 60,
+                self.limit,
+                source
+            ),
+        }
+    }
+}
+
+/// Why a changed file could not be read or diffed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeError {
+    pub path: PathBuf,
+    pub cause: String,
+}
+
+impl std::fmt::Display for ChangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.cause)
+    }
 }
 
 #[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum AppEvent {
     FileChanged(FileEvent),
+    /// A change that could not be read or diffed, with `--strict`
+    Error(ChangeError),
+    BudgetExceeded(BudgetAlert),
+    /// `HEAD` names another branch or commit than when last read
+    BranchChanged(super::git::BranchChange),
     Tick,
     Quit,
     ScrollUp,
@@ -149,11 +523,58 @@ pub struct AppState {
     /// Time-based cleanup: remove events older than this duration
     pub max_event_age: std::time::Duration,
     /// Last cleanup time to avoid frequent cleanup operations
-    last_cleanup: std::time::Instant,
+    last_cleanup: SystemTime,
+    /// Time source for cleanup and summary time frames
+    clock: SharedClock,
+    /// Most-recently-changed files, newest first, without duplicates
+    recent_files: VecDeque<PathBuf>,
+    /// Events ever added, including ones since evicted
+    total_seen: usize,
+    /// Events removed by the size limits or by age cleanup
+    dropped: usize,
+    /// Full content captured at recent events, for comparing two of them
+    snapshots: ContentSnapshots,
+    /// Paths whose events are exempt from `max_events` and `max_event_age`
+    pins: PinnedPaths,
+    /// Cap on the events kept for each pinned path
+    pub max_pinned_events_per_path: usize,
+    /// Events in the log per pinned path; only paths with events are present
+    pinned_counts: HashMap<PathBuf, usize>,
+    /// Cap on the diff and preview text kept across the log, in bytes
+    pub max_total_diff_bytes: usize,
+    /// Bytes of diff and preview text held by the log, see [`held_bytes`]
+    diff_bytes: usize,
+    /// Highlight the diffs and previews of added events; when off they are
+    /// kept plain and the highlighter is never run
+    pub highlight: bool,
+}
+
+/// Number of paths remembered for the recent files list
+const MAX_RECENT_FILES: usize = 50;
+
+/// Lines of a diff or preview highlighted by [`FileEvent::to_highlighted`],
+/// as many as the changes log shows
+const HIGHLIGHTED_LINES: usize = 20;
+
+/// Default cap on the diff text kept across the event log
+pub const DEFAULT_MAX_TOTAL_DIFF_BYTES: usize = 64 * 1024 * 1024;
+
+/// Bytes of diff and preview text an event holds in the log: in the event,
+/// and in its highlighted copy along with the highlighted text
+fn held_bytes(event: &FileEvent, highlighted: Option<&HighlightedFileEvent>) -> usize {
+    let len = |text: &Option<String>| text.as_ref().map_or(0, String::len);
+    let copy = highlighted.map_or(0, |highlighted| {
+        len(&highlighted.diff)
+            + len(&highlighted.content_preview)
+            + len(&highlighted.highlighted_diff)
+            + len(&highlighted.highlighted_preview)
+    });
+    len(&event.diff) + len(&event.content_preview) + copy
 }
 
 impl Default for AppState {
     fn default() -> Self {
+        let clock = system_clock();
         Self {
             events: VecDeque::new(),
             highlighted_events: VecDeque::new(),
@@ -162,7 +583,18 @@ impl Default for AppState {
             show_help: false,
             watched_files: std::collections::HashSet::new(),
             max_event_age: std::time::Duration::from_secs(3600), // 1 hour
-            last_cleanup: std::time::Instant::now(),
+            last_cleanup: clock.now(),
+            clock,
+            recent_files: VecDeque::new(),
+            total_seen: 0,
+            dropped: 0,
+            snapshots: ContentSnapshots::default(),
+            pins: PinnedPaths::default(),
+            max_pinned_events_per_path: DEFAULT_MAX_PINNED_EVENTS_PER_PATH,
+            pinned_counts: HashMap::new(),
+            max_total_diff_bytes: DEFAULT_MAX_TOTAL_DIFF_BYTES,
+            diff_bytes: 0,
+            highlight: true,
         }
     }
 }
@@ -170,6 +602,7 @@ impl Default for AppState {
 impl AppState {
     /// Create a new AppState with configuration
     pub fn with_config(config: &WatchDiffConfig) -> Self {
+        let clock = system_clock();
         Self {
             events: VecDeque::new(),
             highlighted_events: VecDeque::new(),
@@ -178,35 +611,79 @@ impl AppState {
             show_help: false,
             watched_files: std::collections::HashSet::new(),
             max_event_age: config.watcher.max_event_age_duration(),
-            last_cleanup: std::time::Instant::now(),
+            last_cleanup: clock.now(),
+            clock,
+            recent_files: VecDeque::new(),
+            total_seen: 0,
+            dropped: 0,
+            snapshots: ContentSnapshots::new(
+                config.cache.max_content_snapshots,
+                config.cache.max_snapshot_bytes,
+            ),
+            pins: PinnedPaths::default(),
+            max_pinned_events_per_path: config.watcher.max_pinned_events_per_path,
+            pinned_counts: HashMap::new(),
+            max_total_diff_bytes: config.watcher.max_total_diff_bytes,
+            diff_bytes: 0,
+            highlight: true,
         }
     }
     
+    /// Use a different time source, e.g. a mock clock in tests or replay
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.last_cleanup = clock.now();
+        self.clock = clock;
+        self
+    }
+    
+    /// Highlight added events, or keep them plain
+    pub fn with_highlighting(mut self, enabled: bool) -> Self {
+        self.highlight = enabled;
+        self
+    }
+    
     pub fn add_event(&mut self, event: FileEvent) {
         self.add_event_with_cleanup_interval(event, std::time::Duration::from_secs(300))
     }
     
-    pub fn add_event_with_cleanup_interval(&mut self, event: FileEvent, cleanup_interval: std::time::Duration) {
+    pub fn add_event_with_cleanup_interval(&mut self, mut event: FileEvent, cleanup_interval: std::time::Duration) {
+        // Keep the full content in the bounded snapshot store rather than on the event
+        if let Some(content) = event.content.take() {
+            self.snapshots.insert(SnapshotKey::new(event.path.clone(), event.timestamp), content);
+        }
+        
         // Convert to highlighted event
-        let highlighted = event.to_highlighted();
+        let highlighted = event.to_log_event(self.highlight);
+        
+        // Move the path to the front of the recent files list
+        self.recent_files.retain(|path| *path != event.path);
+        self.recent_files.push_front(event.path.clone());
+        self.recent_files.truncate(MAX_RECENT_FILES);
+        
+        if self.pins.is_pinned(&event.path) {
+            *self.pinned_counts.entry(event.path.clone()).or_default() += 1;
+        }
+        
+        self.diff_bytes += held_bytes(&event, Some(&highlighted));
         
         // Add to front of deque for newest-first ordering
         self.events.push_front(event);
         self.highlighted_events.push_front(highlighted);
         
-        // Maintain size limits using efficient pop_back
-        while self.events.len() > self.max_events {
-            self.events.pop_back();
-        }
-        while self.highlighted_events.len() > self.max_events {
-            self.highlighted_events.pop_back();
-        }
+        self.total_seen += 1;
+        
+        self.enforce_size_limits();
         
         // Periodic cleanup of old events
-        let now = std::time::Instant::now();
-        if now.duration_since(self.last_cleanup) > cleanup_interval {
-            self.cleanup_old_events();
-            self.last_cleanup = now;
+        let now = self.clock.now();
+        match now.duration_since(self.last_cleanup) {
+            Ok(elapsed) if elapsed > cleanup_interval => {
+                self.cleanup_old_events();
+                self.last_cleanup = now;
+            }
+            Ok(_) => {}
+            // The clock stepped backwards; restart the interval from here
+            Err(_) => self.last_cleanup = now,
         }
         
         self.scroll_offset = 0;
@@ -214,17 +691,166 @@ impl AppState {
     
     /// Remove events older than max_event_age to prevent indefinite memory growth
     fn cleanup_old_events(&mut self) {
-        let cutoff_time = std::time::SystemTime::now() - self.max_event_age;
-        
-        // Remove old events from back (oldest events)
-        while let Some(back_event) = self.events.back() {
-            if back_event.timestamp < cutoff_time {
-                self.events.pop_back();
-                self.highlighted_events.pop_back();
-            } else {
+        let Some(cutoff_time) = self.clock.now().checked_sub(self.max_event_age) else {
+            return; // Nothing can be older than a cutoff before the representable range
+        };
+        
+        // Remove old events from back (oldest events), stepping over pinned ones
+        let mut index = self.events.len();
+        while index > 0 {
+            index -= 1;
+            let event = &self.events[index];
+            if event.timestamp >= cutoff_time {
+                break;
+            }
+            if !self.pinned_counts.contains_key(&event.path) {
+                self.remove_event(index);
+                self.dropped += 1;
+            }
+        }
+    }
+
+    /// Evict the oldest events beyond `max_events` unpinned ones, beyond
+    /// `max_pinned_events_per_path` for each pinned path, and while the log
+    /// holds more than `max_total_diff_bytes` of diffs and previews
+    fn enforce_size_limits(&mut self) {
+        while self.events.len() - self.pinned_event_count() > self.max_events {
+            let Some(index) = self.events.iter().rposition(|e| !self.pinned_counts.contains_key(&e.path)) else {
+                break;
+            };
+            self.remove_event(index);
+            self.dropped += 1;
+        }
+
+        let over_cap: Vec<PathBuf> = self
+            .pinned_counts
+            .iter()
+            .filter(|(_, count)| **count > self.max_pinned_events_per_path)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in over_cap {
+            while self.pinned_counts.get(&path).is_some_and(|count| *count > self.max_pinned_events_per_path) {
+                let Some(index) = self.events.iter().rposition(|e| e.path == path) else { break };
+                self.remove_event(index);
+                self.dropped += 1;
+            }
+        }
+
+        // Pinned events are never evicted for size, and the newest event is
+        // kept however large its diff
+        while self.diff_bytes > self.max_total_diff_bytes {
+            let Some(index) = self.events.iter().rposition(|e| !self.pinned_counts.contains_key(&e.path)) else {
                 break;
+            };
+            if index == 0 {
+                break;
+            }
+            self.remove_event(index);
+            self.dropped += 1;
+        }
+    }
+
+    /// Remove the event at `index` from both event lists
+    fn remove_event(&mut self, index: usize) {
+        let Some(event) = self.events.remove(index) else { return };
+        let highlighted = self.highlighted_events.remove(index);
+        // Both lists are public, so the total is never trusted to cover what is removed
+        self.diff_bytes = self.diff_bytes.saturating_sub(held_bytes(&event, highlighted.as_ref()));
+        if let Some(count) = self.pinned_counts.get_mut(&event.path) {
+            *count -= 1;
+            if *count == 0 {
+                self.pinned_counts.remove(&event.path);
+            }
+        }
+    }
+
+    /// Replace the pinned paths, then prune any history that is no longer protected
+    pub fn set_pins(&mut self, pins: PinnedPaths) {
+        self.pins = pins;
+        self.recount_pinned();
+    }
+
+    /// Pin `path`, or unpin it if it was explicitly pinned. Returns whether it
+    /// is pinned afterwards.
+    pub fn toggle_pin(&mut self, path: &std::path::Path) -> bool {
+        self.pins.toggle(path);
+        self.recount_pinned();
+        self.pins.is_pinned(path)
+    }
+
+    fn recount_pinned(&mut self) {
+        self.pinned_counts.clear();
+        for event in &self.events {
+            if self.pins.is_pinned(&event.path) {
+                *self.pinned_counts.entry(event.path.clone()).or_default() += 1;
             }
         }
+        self.enforce_size_limits();
+    }
+
+    pub fn pins(&self) -> &PinnedPaths {
+        &self.pins
+    }
+
+    pub fn is_pinned(&self, path: &std::path::Path) -> bool {
+        self.pins.is_pinned(path)
+    }
+
+    /// Events in the log that belong to pinned paths
+    pub fn pinned_event_count(&self) -> usize {
+        self.pinned_counts.values().sum()
+    }
+
+    /// Pinned paths with at least one event in the log
+    pub fn pinned_path_count(&self) -> usize {
+        self.pinned_counts.len()
+    }
+
+    /// Number of events ever added, including evicted ones
+    pub fn total_seen(&self) -> usize {
+        self.total_seen
+    }
+
+    /// Number of events removed from the log, whether evicted by a size
+    /// limit or aged out past `max_event_age`
+    pub fn dropped_count(&self) -> usize {
+        self.dropped
+    }
+
+    /// Bytes of diff and preview text held by the events in the log,
+    /// counting the highlighted copies
+    pub fn total_diff_bytes(&self) -> usize {
+        self.diff_bytes
+    }
+
+    /// Content captured at the event identified by `key`, if still retained
+    pub fn content_snapshot(&self, key: &SnapshotKey) -> Option<&str> {
+        self.snapshots.get(key)
+    }
+
+    /// Unified diff between the content captured at two events, older first,
+    /// with long lines truncated for display.
+    /// Returns `None` if either snapshot is no longer retained.
+    pub fn diff_snapshots(&self, a: &SnapshotKey, b: &SnapshotKey) -> Option<String> {
+        let (older, newer) = if a.timestamp <= b.timestamp { (a, b) } else { (b, a) };
+        let old_content = self.snapshots.get(older)?;
+        let new_content = self.snapshots.get(newer)?;
+        let diff = crate::diff::generate_unified_diff(old_content, new_content, &older.path, &newer.path);
+        Some(crate::diff::truncate_long_lines(&diff, crate::diff::DEFAULT_MAX_LINE_CHARS).0)
+    }
+
+    /// Events of the batch `batch_id` still in the log, oldest first
+    pub fn events_in_batch(&self, batch_id: &str) -> Vec<&FileEvent> {
+        self.events
+            .iter()
+            .rev()
+            .filter(|event| event.batch_id.as_deref() == Some(batch_id))
+            .collect()
+    }
+
+    /// The `n` most recently changed files, newest first
+    pub fn recent_files(&self, n: usize) -> Vec<&PathBuf> {
+        self.recent_files.iter().take(n).collect()
     }
 
     pub fn scroll_up(&mut self) {
@@ -258,7 +884,7 @@ impl AppState {
     /// Generate a change summary from current events
     pub fn generate_summary(&self, filters: &SummaryFilters) -> ChangeSummary {
         let events: Vec<FileEvent> = self.events.iter().cloned().collect();
-        ChangeSummary::from_events(&events, filters)
+        ChangeSummary::from_events_at(&events, filters, self.clock.now())
     }
     
     /// Generate a summary with default filters
@@ -268,22 +894,28 @@ impl AppState {
     
     /// Generate a summary for the last hour
     pub fn generate_recent_summary(&self) -> ChangeSummary {
-        let mut filters = SummaryFilters::default();
-        filters.time_frame = super::summary::SummaryTimeFrame::LastHour;
+        let filters = SummaryFilters {
+            time_frame: super::summary::SummaryTimeFrame::LastHour,
+            ..SummaryFilters::default()
+        };
         self.generate_summary(&filters)
     }
     
     /// Generate a summary for a specific time frame
     pub fn generate_summary_for_timeframe(&self, timeframe: super::summary::SummaryTimeFrame) -> ChangeSummary {
-        let mut filters = SummaryFilters::default();
-        filters.time_frame = timeframe;
+        let filters = SummaryFilters {
+            time_frame: timeframe,
+            ..SummaryFilters::default()
+        };
         self.generate_summary(&filters)
     }
     
     /// Generate a summary filtered by origin (who made the changes)
     pub fn generate_summary_by_origin(&self, origins: Vec<ChangeOrigin>) -> ChangeSummary {
-        let mut filters = SummaryFilters::default();
-        filters.include_origins = origins;
+        let filters = SummaryFilters {
+            include_origins: origins,
+            ..SummaryFilters::default()
+        };
         self.generate_summary(&filters)
     }
     
@@ -356,6 +988,9 @@ mod tests {
             level: ConfidenceLevel::Review,
             score: 0.6,
             reasons: vec!["Debug output detected".to_string()],
+            reason_impacts: vec![],
+            rule_hits: Vec::new(),
+            score_breakdown: None,
         };
         
         let event = FileEvent::new(path, FileEventKind::Modified)
@@ -387,6 +1022,9 @@ mod tests {
             level: ConfidenceLevel::Safe,
             score: 0.9,
             reasons: vec![],
+            reason_impacts: vec![],
+            rule_hits: Vec::new(),
+            score_breakdown: None,
         };
         let batch_id = "batch_789".to_string();
         let diff = "- old line\n+ new line".to_string();
@@ -412,6 +1050,9 @@ mod tests {
             level: ConfidenceLevel::Safe,
             score: 0.95,
             reasons: vec!["Formatting tool".to_string()],
+            reason_impacts: vec![],
+            rule_hits: Vec::new(),
+            score_breakdown: None,
         };
         
         let event = FileEvent::new(path.clone(), FileEventKind::Modified)
@@ -429,6 +1070,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_to_highlighted_colors_diff_and_preview_of_known_languages() {
+        let diff = "--- a/lib.rs\n+++ b/lib.rs\n@@ -1,2 +1,2 @@\n fn a() {}\n-fn b() {}\n+fn c() {}";
+        let event = FileEvent::new(PathBuf::from("lib.rs"), FileEventKind::Modified)
+            .with_diff(diff.to_string())
+            .with_preview("fn c() {}".to_string());
+
+        let highlighted = event.to_highlighted();
+        let colored = highlighted.highlighted_diff.expect("highlighted diff");
+        assert!(colored.contains("\x1b[38;2;"), "{:?}", colored);
+        let text: Vec<String> = colored
+            .lines()
+            .map(|line| crate::ui::ansi::ansi_spans(line).iter().map(|span| span.content.as_ref()).collect())
+            .collect();
+        assert_eq!(text, diff.lines().collect::<Vec<_>>());
+        assert!(highlighted.highlighted_preview.expect("highlighted preview").contains("\x1b["));
+
+        // Nothing to highlight as for an unknown language
+        let plain = FileEvent::new(PathBuf::from("notes.unknownext"), FileEventKind::Modified).with_diff(diff.to_string());
+        assert!(plain.to_highlighted().highlighted_diff.is_none());
+    }
+
+    #[test]
+    fn test_events_added_with_highlighting_off_stay_plain() {
+        let event = FileEvent::new(PathBuf::from("lib.rs"), FileEventKind::Created).with_preview("fn c() {}".to_string());
+        let mut state = AppState::default().with_highlighting(false);
+        state.add_event(event.clone());
+        assert!(state.highlighted_events[0].highlighted_preview.is_none());
+
+        state.highlight = true;
+        state.add_event(event);
+        assert!(state.highlighted_events[0].highlighted_preview.is_some());
+    }
+
     #[test]
     fn test_app_state_add_event_with_ai_features() {
         let mut state = AppState::default();
@@ -442,6 +1117,9 @@ mod tests {
                 level: ConfidenceLevel::Review,
                 score: 0.7,
                 reasons: vec!["Large change detected".to_string()],
+                reason_impacts: vec![],
+                rule_hits: Vec::new(),
+                score_breakdown: None,
             })
             .with_batch_id("batch_001".to_string());
         
@@ -510,6 +1188,244 @@ mod tests {
         assert_eq!(deleted, 1);
     }
     
+    #[test]
+    fn test_app_state_cleanup_with_mock_clock() {
+        use std::time::Duration;
+        use crate::core::{Clock, MockClock};
+        
+        let clock = MockClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        let mut state = AppState::default().with_clock(std::sync::Arc::new(clock.clone()));
+        state.max_event_age = Duration::from_secs(600);
+        let interval = Duration::from_secs(60);
+        
+        let mut old = FileEvent::new(PathBuf::from("old.rs"), FileEventKind::Modified);
+        old.timestamp = clock.now();
+        state.add_event_with_cleanup_interval(old, interval);
+        
+        // Within the cleanup interval nothing is removed
+        clock.advance(Duration::from_secs(30));
+        let mut recent = FileEvent::new(PathBuf::from("recent.rs"), FileEventKind::Modified);
+        recent.timestamp = clock.now();
+        state.add_event_with_cleanup_interval(recent.clone(), interval);
+        assert_eq!(state.events.len(), 2);
+        
+        // A backwards clock step neither panics nor triggers cleanup
+        clock.rewind(Duration::from_secs(7200));
+        state.add_event_with_cleanup_interval(recent.clone(), interval);
+        assert_eq!(state.events.len(), 3);
+        
+        // Once the old event is past max age, the next cleanup drops it
+        clock.set(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_620));
+        state.add_event_with_cleanup_interval(recent, interval);
+        assert!(state.events.iter().all(|e| e.path != std::path::Path::new("old.rs")));
+        assert_eq!(state.events.len(), state.highlighted_events.len());
+        assert_eq!(state.dropped_count(), 1);
+    }
+    
+    #[test]
+    fn test_min_lines_changed_threshold() {
+        let one_line = FileEvent::new(PathBuf::from("a.rs"), FileEventKind::Modified)
+            .with_diff("--- a\n+++ b\n@@ -1 +1 @@\n+x".to_string());
+        let three_lines = FileEvent::new(PathBuf::from("b.rs"), FileEventKind::Modified)
+            .with_diff("--- a\n+++ b\n@@ -1,2 +1,2 @@\n-old\n+new\n+more".to_string());
+        let created = FileEvent::new(PathBuf::from("c.rs"), FileEventKind::Created);
+        
+        assert!(!one_line.meets_line_threshold(2));
+        assert!(three_lines.meets_line_threshold(2));
+        assert!(created.meets_line_threshold(2));
+        assert!(one_line.meets_line_threshold(0));
+        assert!(!one_line.to_highlighted().meets_line_threshold(2));
+    }
+    
+    #[test]
+    fn test_diff_stats_counts_lines_without_headers() {
+        let diff = "--- a/lib.rs\n+++ b/lib.rs\n@@ -1,3 +1,3 @@\n fn a() {}\n-fn b() {}\n+fn b() -> u8 { 1 }\n+fn c() {}\n@@ -10 +11 @@\n-// old\n+// new";
+        let event = FileEvent::new(PathBuf::from("lib.rs"), FileEventKind::Modified).with_diff(diff.to_string());
+
+        let stats = event.diff_stats();
+        assert_eq!(stats, crate::diff::DiffStats { lines_added: 3, lines_removed: 2, lines_modified: 2, hunks: 2 });
+        assert_eq!(FileEvent::new(PathBuf::from("gone.rs"), FileEventKind::Deleted).diff_stats(), crate::diff::DiffStats::default());
+    }
+
+    #[test]
+    fn test_app_state_recent_files_mru_order() {
+        let mut state = AppState::default();
+        
+        state.add_event(FileEvent::new(PathBuf::from("a.rs"), FileEventKind::Modified));
+        state.add_event(FileEvent::new(PathBuf::from("b.rs"), FileEventKind::Modified));
+        state.add_event(FileEvent::new(PathBuf::from("a.rs"), FileEventKind::Modified));
+        
+        let a = PathBuf::from("a.rs");
+        let b = PathBuf::from("b.rs");
+        assert_eq!(state.recent_files(10), vec![&a, &b]);
+        assert_eq!(state.recent_files(1), vec![&a]);
+    }
+    
+    #[test]
+    fn test_app_state_counts_evicted_events() {
+        let mut state = AppState {
+            max_events: 10,
+            ..AppState::default()
+        };
+        
+        for i in 0..34 {
+            state.add_event(FileEvent::new(PathBuf::from(format!("f{}.rs", i)), FileEventKind::Modified));
+        }
+        
+        assert_eq!(state.events.len(), 10);
+        assert_eq!(state.total_seen(), 34);
+        assert_eq!(state.dropped_count(), 24);
+        assert_eq!(state.events.back().unwrap().path, PathBuf::from("f24.rs"));
+    }
+    
+    #[test]
+    fn test_app_state_caps_total_diff_bytes() {
+        let mut state = AppState {
+            max_total_diff_bytes: 20_000,
+            ..AppState::default()
+        };
+        let large_diff = |i: usize| format!("--- a\n+++ b\n@@ -1 +1 @@\n+{}", "x".repeat(3000 + i));
+        let held = |state: &AppState| -> usize {
+            state.events.iter().zip(&state.highlighted_events).map(|(e, h)| held_bytes(e, Some(h))).sum()
+        };
+        
+        // No extension, so nothing is highlighted: each diff is held twice
+        for i in 0..10 {
+            let event = FileEvent::new(PathBuf::from(format!("f{}", i)), FileEventKind::Modified).with_diff(large_diff(i));
+            state.add_event(event);
+            assert!(state.total_diff_bytes() <= 20_000, "{} bytes", state.total_diff_bytes());
+        }
+        
+        // Only the newest three fit, well below the event count limit
+        assert_eq!(state.events.len(), 3);
+        assert_eq!(state.highlighted_events.len(), 3);
+        assert_eq!(state.dropped_count(), 7);
+        assert_eq!(state.events.back().unwrap().path, PathBuf::from("f7"));
+        let diffs: usize = state.events.iter().map(|e| e.diff.as_ref().unwrap().len()).sum();
+        assert_eq!(state.total_diff_bytes(), 2 * diffs);
+        
+        // Previews count too; highlighted text adds to them
+        state.add_event(FileEvent::new(PathBuf::from("notes"), FileEventKind::Modified).with_preview("y".repeat(1000)));
+        assert_eq!(state.total_diff_bytes(), held(&state));
+        state.add_event(FileEvent::new(PathBuf::from("lib.rs"), FileEventKind::Modified).with_diff(large_diff(0)));
+        assert!(state.highlighted_events[0].highlighted_diff.is_some());
+        assert_eq!(state.total_diff_bytes(), held(&state));
+        
+        // Events without a diff cost nothing; the count limit still applies
+        state.max_events = 5;
+        for i in 0..4 {
+            state.add_event(FileEvent::new(PathBuf::from(format!("g{}", i)), FileEventKind::Deleted));
+        }
+        assert_eq!(state.events.len(), 5);
+        assert_eq!(state.total_diff_bytes(), held(&state));
+        
+        // A diff larger than the whole budget is still kept as the newest event
+        state.add_event(FileEvent::new(PathBuf::from("huge"), FileEventKind::Modified).with_diff("+".repeat(20_000)));
+        assert_eq!(state.events.front().unwrap().path, PathBuf::from("huge"));
+        assert_eq!(state.events.len(), 1);
+        assert_eq!(state.total_diff_bytes(), 40_000);
+        
+        // Events removed behind the state's back never make the total wrap
+        state.events.clear();
+        state.diff_bytes = 10;
+        state.highlighted_events[0].diff = Some("+".repeat(30_000));
+        state.events.push_front(FileEvent::new(PathBuf::from("huge"), FileEventKind::Modified));
+        state.remove_event(0);
+        assert_eq!(state.total_diff_bytes(), 0);
+    }
+    
+    #[test]
+    fn test_pinned_history_survives_pruning() {
+        let mut state = AppState {
+            max_events: 10,
+            max_pinned_events_per_path: 20,
+            ..AppState::default()
+        };
+        state.set_pins(PinnedPaths::new(PathBuf::new()).with_paths([PathBuf::from("config.toml")]));
+        
+        for i in 0..45 {
+            let path = if i % 3 == 0 { "config.toml".to_string() } else { format!("f{}.rs", i) };
+            state.add_event(FileEvent::new(PathBuf::from(path), FileEventKind::Modified));
+        }
+        
+        // All 15 pinned events survive next to the 10 newest unpinned ones
+        assert_eq!(state.pinned_event_count(), 15);
+        assert_eq!(state.pinned_path_count(), 1);
+        assert_eq!(state.events.len(), 25);
+        assert_eq!(state.events.len(), state.highlighted_events.len());
+        assert_eq!(state.dropped_count(), 20);
+        
+        // Pinned history is bounded by its own cap
+        for _ in 0..10 {
+            state.add_event(FileEvent::new(PathBuf::from("config.toml"), FileEventKind::Modified));
+        }
+        assert_eq!(state.pinned_event_count(), 20);
+        assert_eq!(state.events.len(), 30);
+        
+        // Unpinning puts its events back under max_events
+        assert!(!state.toggle_pin(std::path::Path::new("config.toml")));
+        assert_eq!(state.pinned_event_count(), 0);
+        assert_eq!(state.events.len(), 10);
+        assert_eq!(state.events.len(), state.highlighted_events.len());
+    }
+    
+    #[test]
+    fn test_pinned_events_are_not_aged_out() {
+        use std::time::Duration;
+        use crate::core::{Clock, MockClock};
+        
+        let clock = MockClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        let mut state = AppState::default().with_clock(std::sync::Arc::new(clock.clone()));
+        state.max_event_age = Duration::from_secs(600);
+        state.set_pins(PinnedPaths::new(PathBuf::new()).with_globs(&["*.toml".to_string()]).unwrap());
+        let interval = Duration::from_secs(60);
+        
+        for path in ["old.rs", "Cargo.toml"] {
+            let mut event = FileEvent::new(PathBuf::from(path), FileEventKind::Modified);
+            event.timestamp = clock.now();
+            state.add_event_with_cleanup_interval(event, interval);
+        }
+        
+        clock.advance(Duration::from_secs(3600));
+        let mut recent = FileEvent::new(PathBuf::from("recent.rs"), FileEventKind::Modified);
+        recent.timestamp = clock.now();
+        state.add_event_with_cleanup_interval(recent, interval);
+        
+        let paths: Vec<&str> = state.events.iter().map(|e| e.path.to_str().unwrap()).collect();
+        assert_eq!(paths, vec!["recent.rs", "Cargo.toml"]);
+        assert_eq!(state.highlighted_events.len(), 2);
+    }
+    
+    #[test]
+    fn test_diff_between_retained_versions() {
+        let mut state = AppState::default();
+        let path = PathBuf::from("notes.txt");
+        let base = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        
+        let versions = ["one\ntwo\nthree\n", "one\n2\nthree\n", "one\n2\nthree\nfour\n"];
+        let mut keys = Vec::new();
+        for (i, content) in versions.iter().enumerate() {
+            let mut event = FileEvent::new(path.clone(), FileEventKind::Modified)
+                .with_content(Arc::from(*content));
+            event.timestamp = base + std::time::Duration::from_secs(i as u64);
+            keys.push(SnapshotKey::new(path.clone(), event.timestamp));
+            state.add_event(event);
+        }
+        
+        assert!(state.events.iter().all(|e| e.content.is_none()));
+        assert_eq!(state.content_snapshot(&keys[1]), Some(versions[1]));
+        
+        // Comparing the first and last versions spans both edits, in either order
+        let diff = state.diff_snapshots(&keys[2], &keys[0]).unwrap();
+        let changed: Vec<&str> = diff.lines()
+            .filter(|l| (l.starts_with('+') || l.starts_with('-')) && !l.starts_with("+++") && !l.starts_with("---"))
+            .collect();
+        assert_eq!(changed, vec!["-two", "+2", "+four"]);
+        
+        let missing = SnapshotKey::new(path, base + std::time::Duration::from_secs(99));
+        assert!(state.diff_snapshots(&keys[0], &missing).is_none());
+    }
+    
     #[test]
     fn test_app_state_summary_by_origin() {
         let mut state = AppState::default();
@@ -1,20 +1,185 @@
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use ignore::WalkBuilder;
-use anyhow::Result;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{Match, WalkBuilder};
+use anyhow::{Context, Result};
 
+/// Ignore file at the watch root, in gitignore syntax. It applies on top of
+/// the `.gitignore` files, and also when they are not respected.
+pub const WATCHDIFFIGNORE: &str = ".watchdiffignore";
+
+/// Build and tool directories skipped by name wherever they are, unless
+/// `ignored_dirs` is set otherwise
+pub const DEFAULT_IGNORED_DIRS: &[&str] = &["node_modules", "target", ".vscode", ".idea", ".nyc_output", "coverage"];
+
+#[derive(Debug, Clone)]
 pub struct FileFilter {
     root_path: PathBuf,
+    /// The root as an absolute, canonical path, when that differs from
+    /// `root_path`; event paths are absolute
+    resolved_root: Option<PathBuf>,
+    include: GlobPatterns,
+    exclude: GlobPatterns,
+    /// Whether files git ignores are skipped
+    respect_gitignore: bool,
+    /// Deepest level watched; files in the root are at depth 1
+    max_depth: Option<usize>,
+    /// Directory names skipped wherever they are
+    ignored_dirs: Arc<[String]>,
+    gitignores: GitignoreCache,
+    /// Directories above the root up to its git repository's top level, with
+    /// the root's path relative to each; their `.gitignore` files apply too
+    outer_dirs: Vec<(PathBuf, PathBuf)>,
+}
+
+/// Parsed `.gitignore` and `.watchdiffignore` files, shared by clones of a
+/// filter and parsed again when the file on disk changes
+#[derive(Debug, Clone, Default)]
+struct GitignoreCache {
+    files: Arc<Mutex<HashMap<PathBuf, CachedGitignore>>>,
+}
+
+#[derive(Debug)]
+struct CachedGitignore {
+    /// Modification time and length of the file when it was parsed; `None`
+    /// when there was no file
+    stamp: Option<(SystemTime, u64)>,
+    matcher: Option<Arc<Gitignore>>,
+}
+
+impl GitignoreCache {
+    /// The rules in `file`, with patterns relative to `dir`; `None` when the
+    /// file does not exist or cannot be parsed
+    fn get(&self, file: &Path, dir: &Path) -> Option<Arc<Gitignore>> {
+        let stamp = std::fs::metadata(file)
+            .ok()
+            .map(|metadata| (metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH), metadata.len()));
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(cached) = files.get(file).filter(|cached| cached.stamp == stamp) {
+            return cached.matcher.clone();
+        }
+        let matcher = stamp.and_then(|_| {
+            let mut builder = GitignoreBuilder::new(dir);
+            if let Some(err) = builder.add(file) {
+                tracing::debug!("Ignoring bad lines in {}: {}", file.display(), err);
+            }
+            builder.build().ok().map(Arc::new)
+        });
+        files.insert(file.to_path_buf(), CachedGitignore { stamp, matcher: matcher.clone() });
+        matcher
+    }
+}
+
+/// Globs in gitignore syntax, matched against paths relative to the root
+#[derive(Debug, Clone, Default)]
+struct GlobPatterns {
+    patterns: Vec<String>,
+    matcher: Option<Gitignore>,
+}
+
+impl GlobPatterns {
+    fn add(&mut self, root: &Path, pattern: &str, flag: &str) -> Result<()> {
+        let mut builder = GitignoreBuilder::new(root);
+        for existing in self.patterns.iter().map(String::as_str).chain([pattern]) {
+            builder
+                .add_line(None, existing)
+                .with_context(|| format!("Invalid {} pattern: {}", flag, existing))?;
+        }
+        self.matcher = Some(builder.build().with_context(|| format!("Invalid {} pattern: {}", flag, pattern))?);
+        self.patterns.push(pattern.to_string());
+        Ok(())
+    }
+
+    /// Whether `relative` or a directory it is in matches a pattern
+    fn matches(&self, relative: &Path) -> bool {
+        let Some(ref matcher) = self.matcher else { return false };
+        relative
+            .ancestors()
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .any(|ancestor| matcher.matched(ancestor, ancestor != relative).is_ignore())
+    }
 }
 
 impl FileFilter {
     pub fn new<P: AsRef<Path>>(root_path: P) -> Result<Self> {
         let root_path = root_path.as_ref().to_path_buf();
-        Ok(Self { root_path })
+        Ok(Self {
+            outer_dirs: outer_dirs(&root_path),
+            resolved_root: resolve_root(&root_path),
+            root_path,
+            include: GlobPatterns::default(),
+            exclude: GlobPatterns::default(),
+            respect_gitignore: true,
+            max_depth: None,
+            ignored_dirs: DEFAULT_IGNORED_DIRS.iter().map(|dir| dir.to_string()).collect(),
+            gitignores: GitignoreCache::default(),
+        })
+    }
+
+    /// Only watch files at most `max_depth` levels below the root, 1 being
+    /// the root's own files; `None` for no limit
+    pub fn set_max_depth(&mut self, max_depth: Option<usize>) {
+        self.max_depth = max_depth;
+    }
+
+    /// Skip directories with these names wherever they are, instead of
+    /// [`DEFAULT_IGNORED_DIRS`]
+    pub fn set_ignored_dirs(&mut self, dirs: &[String]) {
+        self.ignored_dirs = dirs.iter().cloned().collect();
+    }
+
+    /// Skip files git ignores, or watch them too (`--no-gitignore`)
+    pub fn set_respect_gitignore(&mut self, respect: bool) {
+        self.respect_gitignore = respect;
+    }
+
+    pub fn root_path(&self) -> &Path {
+        &self.root_path
+    }
+
+    /// Only watch paths matching `pattern` or another include glob. Globs use
+    /// gitignore syntax relative to the root, e.g. `src/**/*.rs`.
+    pub fn add_include_glob(&mut self, pattern: &str) -> Result<()> {
+        self.include.add(&self.root_path, pattern, "--include")
+    }
+
+    /// Never watch paths matching `pattern`, even when an include glob matches
+    pub fn add_exclude_glob(&mut self, pattern: &str) -> Result<()> {
+        self.exclude.add(&self.root_path, pattern, "--exclude")
+    }
+
+    /// Whether `path` passes the include and exclude globs: excludes always
+    /// win, and with include globs only paths matching one pass. Globs are
+    /// relative to the root, so with any glob set a path outside the root
+    /// does not pass.
+    pub fn matches_globs<P: AsRef<Path>>(&self, path: P) -> bool {
+        if self.include.matcher.is_none() && self.exclude.matcher.is_none() {
+            return true;
+        }
+        let Some(relative) = self.relative(path.as_ref()) else {
+            return false;
+        };
+        !self.exclude.matches(relative) && (self.include.matcher.is_none() || self.include.matches(relative))
+    }
+
+    /// `path` relative to the root, also when the root was given relative to
+    /// the working directory or through a symlink and `path` is absolute
+    fn relative<'a>(&self, path: &'a Path) -> Option<&'a Path> {
+        if let Ok(relative) = path.strip_prefix(&self.root_path) {
+            return Some(relative);
+        }
+        path.strip_prefix(self.resolved_root.as_deref()?).ok()
     }
 
     pub fn should_watch<P: AsRef<Path>>(&self, path: P) -> bool {
         let path = path.as_ref();
         
+        if !self.matches_globs(path) {
+            return false;
+        }
+        
         // Convert to string for easier pattern matching
         let path_str = path.to_string_lossy();
         
@@ -26,65 +191,125 @@ impl FileFilter {
             return false;
         }
         
-        // Ignore common build/temporary directories and files
-        if path_str.contains("/.DS_Store") ||
-           path_str.contains("/node_modules/") ||
-           path_str.contains("/.vscode/") ||
-           path_str.contains("/.idea/") ||
-           path_str.contains("/target/debug/") ||
-           path_str.contains("/target/release/") ||
-           path_str.contains("/.nyc_output/") ||
-           path_str.contains("/coverage/") {
+        // Never watch our own state directory (sessions, snapshots)
+        if path.components().any(|comp| comp.as_os_str() == ".watchdiff") {
+            return false;
+        }
+        
+        if path_str.contains("/.DS_Store") {
+            return false;
+        }
+
+        // Ignored directories and the depth limit count from the root, so a
+        // root inside a `target` directory is still watched
+        let relative = self.relative(path);
+        let within = relative.unwrap_or(path);
+        if within.components().any(|comp| self.ignored_dirs.iter().any(|dir| comp.as_os_str() == dir.as_str())) {
             return false;
         }
+        if let (Some(max_depth), Some(relative)) = (self.max_depth, relative) {
+            if relative.components().count() > max_depth {
+                return false;
+            }
+        }
         
         // Skip hidden files that start with . (except .gitignore, .env, etc.)
         if let Some(filename) = path.file_name().and_then(|f| f.to_str()) {
             if filename.starts_with('.') && 
-               !matches!(filename, ".gitignore" | ".env" | ".dockerignore" | ".editorconfig" | 
+               !matches!(filename, ".gitignore" | ".watchdiffignore" | ".env" | ".dockerignore" | ".editorconfig" | 
                                   ".eslintrc.json" | ".prettierrc" | ".babelrc") {
                 return false;
             }
         }
 
-        // Use ignore crate's gitignore matching
-        let mut builder = ignore::gitignore::GitignoreBuilder::new(&self.root_path);
-        
-        // Add .gitignore files - Fixed: Remove needless borrow
-        let _ = builder.add(self.root_path.join(".gitignore"));
+        match relative {
+            Some(relative) => !self.is_ignored(relative, path.is_dir()),
+            None => true,
+        }
+    }
+
+    /// Whether `relative`, or a directory it is in, is ignored by
+    /// `.watchdiffignore` or, unless they are not respected, the `.gitignore`
+    /// files. As in git, nothing inside an ignored directory can be
+    /// re-included.
+    fn is_ignored(&self, relative: &Path, is_dir: bool) -> bool {
+        let mut ancestors: Vec<&Path> = relative.ancestors().filter(|ancestor| !ancestor.as_os_str().is_empty()).collect();
+        ancestors.reverse();
+        ancestors
+            .into_iter()
+            .any(|ancestor| self.ignore_match(ancestor, ancestor != relative || is_dir).is_ignore())
+    }
+
+    /// The deciding rule for `relative` itself: `.watchdiffignore` first, so
+    /// its `!pattern` can watch what git ignores, then the `.gitignore` files
+    fn ignore_match(&self, relative: &Path, is_dir: bool) -> Match<()> {
+        let own = match self.gitignores.get(&self.root_path.join(WATCHDIFFIGNORE), &self.root_path) {
+            Some(watchdiffignore) => watchdiffignore.matched(relative, is_dir).map(|_| ()),
+            None => Match::None,
+        };
+        if !own.is_none() || !self.respect_gitignore {
+            return own;
+        }
+        self.gitignore_match(relative, is_dir)
+    }
+
+    /// The deciding `.gitignore` rule for `relative` itself. The `.gitignore`
+    /// files from the path's own directory up to the root are consulted
+    /// deepest first, then the user's global one; the first with a matching
+    /// rule decides. So a subdirectory's `.gitignore` overrides the root's,
+    /// and `!pattern` re-includes what a shallower file ignores.
+    fn gitignore_match(&self, relative: &Path, is_dir: bool) -> Match<()> {
+        for dir in relative.ancestors().skip(1) {
+            let dir_path = self.root_path.join(dir);
+            let Some(gitignore) = self.gitignores.get(&dir_path.join(".gitignore"), &dir_path) else { continue };
+            let within = relative.strip_prefix(dir).unwrap_or(relative);
+            match gitignore.matched(within, is_dir) {
+                Match::None => {}
+                decided => return decided.map(|_| ()),
+            }
+        }
+        for (dir, root_within) in &self.outer_dirs {
+            let Some(gitignore) = self.gitignores.get(&dir.join(".gitignore"), dir) else { continue };
+            match gitignore.matched(root_within.join(relative), is_dir) {
+                Match::None => {}
+                decided => return decided.map(|_| ()),
+            }
+        }
         if let Some(home) = std::env::var_os("HOME") {
-            let global_gitignore = std::path::PathBuf::from(home).join(".gitignore_global");
-            let _ = builder.add(global_gitignore);
-        }
-        
-        match builder.build() {
-            Ok(gitignore) => {
-                let relative_path = if let Ok(rel) = path.strip_prefix(&self.root_path) {
-                    rel
-                } else {
-                    path
-                };
-                
-                match gitignore.matched(relative_path, path.is_dir()) {
-                    ignore::Match::None | ignore::Match::Whitelist(_) => true,
-                    ignore::Match::Ignore(_) => false,
-                }
+            let global = PathBuf::from(home).join(".gitignore_global");
+            if let Some(gitignore) = self.gitignores.get(&global, &self.root_path) {
+                return gitignore.matched(relative, is_dir).map(|_| ());
             }
-            Err(_) => true, // If we can't build gitignore, watch everything
         }
+        Match::None
     }
 
-    pub fn get_watchable_files(&self) -> Result<Vec<PathBuf>> {
-        let mut files = Vec::new();
-        
-        for result in WalkBuilder::new(&self.root_path)
+    /// A walk of `root` that skips what this filter ignores without
+    /// descending into it: ignored files and directories, `.git`, and
+    /// anything below the depth limit
+    pub fn walker(&self, root: &Path) -> WalkBuilder {
+        let mut walker = WalkBuilder::new(root);
+        let ignored_dirs = Arc::clone(&self.ignored_dirs);
+        walker
             .hidden(false)
-            .git_ignore(true)
-            .git_global(true)
-            .git_exclude(true)
+            .git_ignore(self.respect_gitignore)
+            .git_global(self.respect_gitignore)
+            .git_exclude(self.respect_gitignore)
             .ignore(true)
+            .add_custom_ignore_filename(WATCHDIFFIGNORE)
             .parents(true)
-            .build() {
+            .max_depth(self.max_depth)
+            .filter_entry(move |entry| {
+                let name = entry.file_name();
+                entry.depth() == 0 || (name != ".git" && !ignored_dirs.iter().any(|dir| name == dir.as_str()))
+            });
+        walker
+    }
+
+    pub fn get_watchable_files(&self) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        
+        for result in self.walker(&self.root_path).build() {
             
             match result {
                 Ok(entry) => {
@@ -108,6 +333,11 @@ impl FileFilter {
     pub fn is_text_file<P: AsRef<Path>>(&self, path: P) -> bool {
         let path = path.as_ref();
         
+        // Compressed files are text if their decompressed content is
+        if let Some(inner) = super::content::decoded_path(path) {
+            return self.is_text_file(inner);
+        }
+        
         // Check file extension for common text files
         if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
             matches!(ext.to_lowercase().as_str(),
@@ -115,7 +345,7 @@ impl FileFilter {
                 "json" | "toml" | "yaml" | "yml" | "xml" | "md" | "txt" | "log" |
                 "c" | "cpp" | "h" | "hpp" | "java" | "kt" | "swift" | "go" |
                 "php" | "rb" | "sh" | "bash" | "zsh" | "fish" | "sql" | "dockerfile" |
-                "makefile" | "cmake" | "config" | "conf" | "ini" | "env"
+                "makefile" | "cmake" | "config" | "conf" | "ini" | "env" | "lock"
             )
         } else {
             // Check for files without extensions that are typically text
@@ -131,6 +361,32 @@ impl FileFilter {
     }
 }
 
+/// `root` as an absolute, canonical path, if that is not how it was given.
+/// Resolved once, as the working directory may change while watching.
+fn resolve_root(root: &Path) -> Option<PathBuf> {
+    let absolute = std::env::current_dir().ok()?.join(root);
+    let resolved = absolute.canonicalize().unwrap_or(absolute);
+    (resolved != root).then_some(resolved)
+}
+
+/// The directories above `root`, nearest first, up to the top level of the
+/// git repository `root` is in; none when it is not in one or is the top level
+fn outer_dirs(root: &Path) -> Vec<(PathBuf, PathBuf)> {
+    let Ok(root) = root.canonicalize() else { return Vec::new() };
+    if root.join(".git").exists() {
+        return Vec::new();
+    }
+    let mut dirs = Vec::new();
+    for dir in root.ancestors().skip(1) {
+        let root_within = root.strip_prefix(dir).unwrap_or(&root).to_path_buf();
+        dirs.push((dir.to_path_buf(), root_within));
+        if dir.join(".git").exists() {
+            return dirs;
+        }
+    }
+    Vec::new()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -145,6 +401,9 @@ mod tests {
         assert!(!filter.should_watch(temp_dir.path().join(".git/objects/abc123")));
         assert!(!filter.should_watch(temp_dir.path().join(".git/config")));
         assert!(!filter.should_watch(temp_dir.path().join("subdir/.git/HEAD")));
+        
+        // Nor watchdiff's own state
+        assert!(!filter.should_watch(temp_dir.path().join(".watchdiff/sessions/session_1.json")));
     }
 
     #[test]
@@ -172,6 +431,144 @@ mod tests {
         assert!(!filter.should_watch(temp_dir.path().join(".hidden_file")));
     }
 
+    #[test]
+    fn test_include_and_exclude_globs() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let mut filter = FileFilter::new(root).unwrap();
+        filter.add_exclude_glob("**/generated/**").unwrap();
+        // Without includes everything not excluded passes
+        assert!(filter.should_watch(root.join("README.md")));
+        assert!(!filter.should_watch(root.join("src/generated/schema.rs")));
+
+        filter.add_include_glob("src/**/*.rs").unwrap();
+        filter.add_include_glob("docs/").unwrap();
+        assert!(filter.should_watch(root.join("src/main.rs")));
+        assert!(filter.should_watch(root.join("src/core/filter.rs")));
+        assert!(filter.should_watch(root.join("docs/guide/intro.md")));
+        assert!(!filter.should_watch(root.join("README.md")));
+        assert!(!filter.should_watch(root.join("tests/src/main.rs")));
+        // Excludes win over includes
+        assert!(!filter.should_watch(root.join("src/generated/schema.rs")));
+
+        let error = filter.add_include_glob("src/[").unwrap_err();
+        assert!(format!("{:#}", error).contains("Invalid --include pattern: src/["), "{:#}", error);
+        // A bad pattern leaves the filter as it was
+        assert!(filter.should_watch(root.join("src/main.rs")));
+
+        // Paths outside the root match no glob
+        let outside = TempDir::new().unwrap();
+        assert!(!filter.matches_globs(outside.path().join("src/main.rs")));
+        let mut exclude_only = FileFilter::new(root).unwrap();
+        exclude_only.add_exclude_glob("*.log").unwrap();
+        assert!(!exclude_only.matches_globs(outside.path().join("notes.md")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_globs_match_through_a_symlinked_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let real = temp_dir.path().join("real");
+        std::fs::create_dir_all(real.join("src")).unwrap();
+        std::os::unix::fs::symlink(&real, temp_dir.path().join("link")).unwrap();
+
+        let mut filter = FileFilter::new(temp_dir.path().join("link")).unwrap();
+        filter.add_include_glob("src/**").unwrap();
+        let canonical = real.canonicalize().unwrap();
+        assert!(filter.matches_globs(canonical.join("src/main.rs")));
+        assert!(!filter.matches_globs(canonical.join("README.md")));
+    }
+
+    #[test]
+    fn test_nested_gitignores_and_negation() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("sub/deeper")).unwrap();
+        std::fs::create_dir_all(root.join("build")).unwrap();
+        std::fs::write(root.join(".gitignore"), "*.log\n!important.log\nbuild/\n!build/keep.rs\nsecret.txt\n").unwrap();
+        std::fs::write(root.join("sub/.gitignore"), "!keep.log\n*.tmp\n!secret.txt\n").unwrap();
+        std::fs::write(root.join("sub/deeper/.gitignore"), "!*.tmp\n").unwrap();
+        let filter = FileFilter::new(root).unwrap();
+
+        // Negation in the same file
+        assert!(!filter.should_watch(root.join("app.log")));
+        assert!(filter.should_watch(root.join("important.log")));
+        // A subdirectory's rules override the root's, but only under it
+        assert!(!filter.should_watch(root.join("sub/app.log")));
+        assert!(filter.should_watch(root.join("sub/keep.log")));
+        assert!(!filter.should_watch(root.join("keep.log")));
+        assert!(filter.should_watch(root.join("sub/secret.txt")));
+        assert!(!filter.should_watch(root.join("secret.txt")));
+        assert!(!filter.should_watch(root.join("sub/scratch.tmp")));
+        assert!(filter.should_watch(root.join("scratch.tmp")));
+        assert!(filter.should_watch(root.join("sub/deeper/scratch.tmp")));
+        // Nothing inside an ignored directory comes back
+        assert!(!filter.should_watch(root.join("build/out.rs")));
+        assert!(!filter.should_watch(root.join("build/keep.rs")));
+        assert!(!filter.should_watch(root.join("sub/build/out.rs")));
+
+        // Edits to a .gitignore apply to the next check
+        std::fs::write(root.join("sub/.gitignore"), "!*.log\n").unwrap();
+        assert!(filter.should_watch(root.join("sub/app.log")));
+        assert!(filter.should_watch(root.join("sub/scratch.tmp")));
+    }
+
+    #[test]
+    fn test_watchdiffignore_layers_over_gitignore() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("generated")).unwrap();
+        for file in ["generated/config.json", "generated/secret.json", "app.log", "notes.md", "main.rs"] {
+            std::fs::write(root.join(file), "").unwrap();
+        }
+        std::fs::write(root.join(".gitignore"), "generated/\n*.log\n").unwrap();
+        std::fs::write(root.join(WATCHDIFFIGNORE), "!generated/\nnotes.md\n").unwrap();
+        let mut filter = FileFilter::new(root).unwrap();
+        filter.add_exclude_glob("generated/secret.json").unwrap();
+
+        // .watchdiffignore re-includes what git ignores and ignores more;
+        // --exclude still wins
+        assert!(filter.should_watch(root.join("generated/config.json")));
+        assert!(!filter.should_watch(root.join("app.log")));
+        assert!(!filter.should_watch(root.join("notes.md")));
+        assert!(!filter.should_watch(root.join("generated/secret.json")));
+        assert!(filter.should_watch(root.join(WATCHDIFFIGNORE)));
+        let mut files: Vec<PathBuf> = filter.get_watchable_files().unwrap();
+        files.sort();
+        let expected: Vec<PathBuf> = [".gitignore", WATCHDIFFIGNORE, "generated/config.json", "main.rs"]
+            .iter()
+            .map(|file| root.join(file))
+            .collect();
+        assert_eq!(files, expected);
+
+        // Without gitignore, .watchdiffignore still applies
+        filter.set_respect_gitignore(false);
+        assert!(filter.should_watch(root.join("app.log")));
+        assert!(!filter.should_watch(root.join("notes.md")));
+
+        // Edits to .watchdiffignore apply to the next check
+        std::fs::write(root.join(WATCHDIFFIGNORE), "*.log\n").unwrap();
+        assert!(!filter.should_watch(root.join("app.log")));
+        assert!(filter.should_watch(root.join("notes.md")));
+    }
+
+    #[test]
+    fn test_gitignore_above_the_root_applies_inside_a_repository() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path();
+        std::fs::create_dir_all(repo.join(".git")).unwrap();
+        std::fs::create_dir_all(repo.join("crates/app/src")).unwrap();
+        std::fs::write(repo.join(".gitignore"), "*.bak\ncrates/app/dist/\n").unwrap();
+        std::fs::write(repo.join("crates/app/.gitignore"), "!keep.bak\n").unwrap();
+        let root = repo.join("crates/app");
+        let filter = FileFilter::new(&root).unwrap();
+
+        assert!(!filter.should_watch(root.join("src/main.rs.bak")));
+        assert!(filter.should_watch(root.join("keep.bak")));
+        assert!(!filter.should_watch(root.join("dist/bundle.js")));
+        assert!(filter.should_watch(root.join("src/main.rs")));
+    }
+
     #[test]
     fn test_is_text_file() {
         let temp_dir = TempDir::new().unwrap();
@@ -187,5 +584,8 @@ mod tests {
         assert!(!filter.is_text_file("image.png"));
         assert!(!filter.is_text_file("binary.exe"));
         assert!(!filter.is_text_file("unknown"));
+        assert!(filter.is_text_file("app.log.gz"));
+        assert!(filter.is_text_file("events.json.gz"));
+        assert!(!filter.is_text_file("archive.tar.gz"));
     }
 }
\ No newline at end of file
@@ -0,0 +1,195 @@
+//! Git branch and commit of the watched repository
+//!
+//! Read straight from `.git/HEAD` and the refs, without running `git`. The
+//! watcher re-reads them when `HEAD` or a branch ref changes and reports a
+//! checkout made during the session as [`BranchChange`].
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+/// What `HEAD` points at
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GitHead {
+    Branch(String),
+    /// Detached at a commit
+    Detached(String),
+}
+
+/// Branch and commit checked out in the watched repository
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitContext {
+    pub head: GitHead,
+    /// Full hash of the checked out commit; `None` on a branch without commits
+    pub commit: Option<String>,
+}
+
+impl GitContext {
+    /// The branch name, or `detached` for a detached HEAD
+    pub fn branch_label(&self) -> &str {
+        match self.head {
+            GitHead::Branch(ref branch) => branch,
+            GitHead::Detached(_) => "detached",
+        }
+    }
+
+    pub fn short_commit(&self) -> Option<&str> {
+        self.commit.as_deref().map(|commit| &commit[..commit.len().min(7)])
+    }
+
+    /// `main @ 1a2b3c4`
+    pub fn describe(&self) -> String {
+        match self.short_commit() {
+            Some(commit) => format!("{} @ {}", self.branch_label(), commit),
+            None => self.branch_label().to_string(),
+        }
+    }
+}
+
+/// A checkout during the session: `HEAD` now names another branch or commit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BranchChange {
+    pub from: GitContext,
+    pub to: GitContext,
+}
+
+impl BranchChange {
+    pub fn message(&self) -> String {
+        format!("Switched from {} to {}", self.from.describe(), self.to.describe())
+    }
+}
+
+/// `HEAD` file content as a branch or detached commit
+pub fn parse_head(content: &str) -> Option<GitHead> {
+    let content = content.trim();
+    if let Some(reference) = content.strip_prefix("ref:") {
+        let reference = reference.trim();
+        let branch = reference.strip_prefix("refs/heads/").unwrap_or(reference);
+        return (!branch.is_empty()).then(|| GitHead::Branch(branch.to_string()));
+    }
+    is_commit_hash(content).then(|| GitHead::Detached(content.to_string()))
+}
+
+fn is_commit_hash(text: &str) -> bool {
+    matches!(text.len(), 40 | 64) && text.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// The repository's git context, cached between changes to `HEAD`
+#[derive(Debug, Clone)]
+pub struct GitHeadTracker {
+    git_dir: PathBuf,
+    /// Where branch refs and `packed-refs` live; differs from `git_dir` in a worktree
+    common_dir: PathBuf,
+    current: Option<GitContext>,
+}
+
+impl GitHeadTracker {
+    /// Tracker for the repository whose `.git` is directly under `root`
+    pub fn discover(root: &Path) -> Option<Self> {
+        let dot_git = root.join(".git");
+        let git_dir = if dot_git.is_dir() {
+            dot_git
+        } else {
+            // Worktrees and submodules have a `gitdir: <path>` file instead
+            let text = fs::read_to_string(&dot_git).ok()?;
+            root.join(text.trim().strip_prefix("gitdir:")?.trim())
+        };
+        let common_dir = match fs::read_to_string(git_dir.join("commondir")) {
+            Ok(text) => git_dir.join(text.trim()),
+            Err(_) => git_dir.clone(),
+        };
+        let mut tracker = Self { git_dir, common_dir, current: None };
+        tracker.current = tracker.read();
+        Some(tracker)
+    }
+
+    pub fn current(&self) -> Option<&GitContext> {
+        self.current.as_ref()
+    }
+
+    /// Whether a change to `path` can move `HEAD` or the checked out commit
+    pub fn is_head_path(&self, path: &Path) -> bool {
+        path == self.git_dir.join("HEAD")
+            || path == self.common_dir.join("packed-refs")
+            || path.starts_with(self.common_dir.join("refs").join("heads"))
+    }
+
+    /// Re-read `HEAD`. Returns the change when it now names another branch
+    /// or detached commit; new commits on the same branch only update the cache.
+    pub fn refresh(&mut self) -> Option<BranchChange> {
+        let context = self.read()?;
+        let previous = self.current.replace(context.clone())?;
+        (previous.head != context.head).then_some(BranchChange { from: previous, to: context })
+    }
+
+    fn read(&self) -> Option<GitContext> {
+        let head = parse_head(&fs::read_to_string(self.git_dir.join("HEAD")).ok()?)?;
+        let commit = match head {
+            GitHead::Branch(ref branch) => self.resolve_branch(branch),
+            GitHead::Detached(ref commit) => Some(commit.clone()),
+        };
+        Some(GitContext { head, commit })
+    }
+
+    fn resolve_branch(&self, branch: &str) -> Option<String> {
+        let reference = format!("refs/heads/{}", branch);
+        for dir in [&self.git_dir, &self.common_dir] {
+            if let Ok(text) = fs::read_to_string(dir.join(&reference)) {
+                let commit = text.trim();
+                if is_commit_hash(commit) {
+                    return Some(commit.to_string());
+                }
+            }
+        }
+        // Branches not touched since `git gc` are only in packed-refs
+        let packed = fs::read_to_string(self.common_dir.join("packed-refs")).ok()?;
+        packed.lines().find_map(|line| {
+            let (commit, name) = line.split_once(' ')?;
+            (name == reference && is_commit_hash(commit)).then(|| commit.to_string())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const COMMIT_A: &str = "1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b";
+    const COMMIT_B: &str = "fedcba9876543210fedcba9876543210fedcba98";
+
+    #[test]
+    fn test_parse_head_and_detect_branch_change() {
+        assert_eq!(parse_head("ref: refs/heads/main\n"), Some(GitHead::Branch("main".to_string())));
+        assert_eq!(parse_head("ref: refs/heads/feature/login"), Some(GitHead::Branch("feature/login".to_string())));
+        assert_eq!(parse_head(&format!("{}\n", COMMIT_A)), Some(GitHead::Detached(COMMIT_A.to_string())));
+        assert_eq!(parse_head("garbage"), None);
+
+        let dir = TempDir::new().unwrap();
+        let git = dir.path().join(".git");
+        fs::create_dir_all(git.join("refs/heads")).unwrap();
+        fs::write(git.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        fs::write(git.join("refs/heads/main"), format!("{}\n", COMMIT_A)).unwrap();
+        fs::write(git.join("packed-refs"), format!("# pack-refs with: peeled\n{} refs/heads/topic\n", COMMIT_B)).unwrap();
+
+        let mut tracker = GitHeadTracker::discover(dir.path()).unwrap();
+        assert_eq!(tracker.current().unwrap().describe(), "main @ 1a2b3c4");
+        assert!(tracker.is_head_path(&git.join("HEAD")));
+        assert!(!tracker.is_head_path(&git.join("index")));
+
+        // A commit on the same branch is not a branch change
+        fs::write(git.join("refs/heads/main"), format!("{}\n", COMMIT_B)).unwrap();
+        assert_eq!(tracker.refresh(), None);
+        assert_eq!(tracker.current().unwrap().short_commit(), Some("fedcba9"));
+
+        // Checking out a packed branch is
+        fs::write(git.join("HEAD"), "ref: refs/heads/topic\n").unwrap();
+        let change = tracker.refresh().unwrap();
+        assert_eq!(change.from.head, GitHead::Branch("main".to_string()));
+        assert_eq!(change.to.describe(), "topic @ fedcba9");
+        assert_eq!(change.message(), "Switched from main @ fedcba9 to topic @ fedcba9");
+
+        fs::write(git.join("HEAD"), COMMIT_A).unwrap();
+        assert_eq!(tracker.refresh().unwrap().to.describe(), "detached @ 1a2b3c4");
+    }
+}
@@ -0,0 +1,171 @@
+//! Liveness watchdog for the file system backend
+//!
+//! Some notify backends silently stop delivering events, e.g. after a
+//! remount. When an activity period is configured, the watched root is
+//! re-stat'ed periodically: a stat that succeeds and shows no unreported
+//! change means the tree is genuinely idle. A failed stat, or a root that
+//! changed without any event arriving, does not count. The watcher is
+//! unhealthy once neither an event nor a successful stat happened for the
+//! whole period.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Signs of life of the watcher backend
+#[derive(Debug, Clone, Copy)]
+pub struct WatchHealth {
+    started: Instant,
+    last_event: Option<Instant>,
+    last_successful_stat: Option<Instant>,
+    expect_activity: Duration,
+}
+
+impl WatchHealth {
+    pub fn new(expect_activity: Duration, now: Instant) -> Self {
+        Self {
+            started: now,
+            last_event: None,
+            last_successful_stat: None,
+            expect_activity,
+        }
+    }
+
+    /// The backend delivered an event
+    pub fn record_event(&mut self, now: Instant) {
+        self.last_event = Some(now);
+    }
+
+    /// The root was stat'ed and showed no change the backend missed
+    pub fn record_successful_stat(&mut self, now: Instant) {
+        self.last_successful_stat = Some(now);
+    }
+
+    pub fn last_event(&self) -> Option<Instant> {
+        self.last_event
+    }
+
+    pub fn expect_activity(&self) -> Duration {
+        self.expect_activity
+    }
+
+    /// Time since the last event or successful stat (or since startup)
+    pub fn silent_for(&self, now: Instant) -> Duration {
+        let last_alive = [Some(self.started), self.last_event, self.last_successful_stat]
+            .into_iter()
+            .flatten()
+            .max()
+            .unwrap_or(self.started);
+        now.saturating_duration_since(last_alive)
+    }
+
+    pub fn is_healthy_at(&self, now: Instant) -> bool {
+        self.silent_for(now) < self.expect_activity
+    }
+}
+
+/// Re-stat `root` a few times per activity period until `health` is dropped
+pub(crate) fn spawn_watchdog(root: PathBuf, health: &Arc<Mutex<WatchHealth>>) {
+    let interval = (lock(health).expect_activity / 4).clamp(Duration::from_millis(250), Duration::from_secs(30));
+    let health = Arc::downgrade(health);
+
+    thread::spawn(move || {
+        let mut probe = RootProbe::new(&root, Instant::now());
+        let mut was_healthy = true;
+
+        loop {
+            thread::sleep(interval);
+            let Some(health) = health.upgrade() else { break };
+
+            let now = Instant::now();
+            let healthy = {
+                let mut health = lock(&health);
+                probe.probe(&root, &mut health, now);
+                health.is_healthy_at(now)
+            };
+
+            if healthy != was_healthy {
+                if healthy {
+                    tracing::info!("File watcher activity resumed for {}", root.display());
+                } else {
+                    tracing::warn!("No file watcher activity for {}; changes may be missed", root.display());
+                }
+                was_healthy = healthy;
+            }
+        }
+    });
+}
+
+fn lock(health: &Mutex<WatchHealth>) -> std::sync::MutexGuard<'_, WatchHealth> {
+    health.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// State carried between stats of the root
+struct RootProbe {
+    last_probe: Instant,
+    last_modified: Option<SystemTime>,
+    /// The root changed without an event; stats don't count until one arrives
+    unreported_change: bool,
+}
+
+impl RootProbe {
+    fn new(root: &Path, now: Instant) -> Self {
+        Self {
+            last_probe: now,
+            last_modified: fs::metadata(root).and_then(|m| m.modified()).ok(),
+            unreported_change: false,
+        }
+    }
+
+    fn probe(&mut self, root: &Path, health: &mut WatchHealth, now: Instant) {
+        let event_since_last_probe = health.last_event.is_some_and(|at| at >= self.last_probe);
+        if event_since_last_probe {
+            self.unreported_change = false;
+        }
+
+        match fs::metadata(root).and_then(|m| m.modified()) {
+            Ok(modified) => {
+                if self.last_modified != Some(modified) && !event_since_last_probe {
+                    self.unreported_change = true;
+                }
+                self.last_modified = Some(modified);
+                if !self.unreported_change {
+                    health.record_successful_stat(now);
+                }
+            }
+            Err(err) => tracing::debug!("Failed to stat {}: {}", root.display(), err),
+        }
+
+        self.last_probe = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_from_last_event_and_stat() {
+        let start = Instant::now();
+        let secs = |n| start + Duration::from_secs(n);
+        let mut health = WatchHealth::new(Duration::from_secs(60), start);
+
+        // Startup counts as activity
+        assert!(health.is_healthy_at(secs(59)));
+        assert!(!health.is_healthy_at(secs(60)));
+
+        // An event or a successful stat keeps the watcher healthy
+        health.record_event(secs(50));
+        assert!(health.is_healthy_at(secs(100)));
+        health.record_successful_stat(secs(140));
+        assert!(health.is_healthy_at(secs(190)));
+        assert_eq!(health.silent_for(secs(190)), Duration::from_secs(50));
+
+        // Neither for the whole period: dead
+        assert!(!health.is_healthy_at(secs(200)));
+        health.record_event(secs(205));
+        assert!(health.is_healthy_at(secs(210)));
+    }
+}
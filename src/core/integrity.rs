@@ -0,0 +1,447 @@
+//! Integrity checks for changes the watcher may have missed
+//!
+//! A [`TreeSnapshot`] records the content hash of every watched file. While
+//! watching, each event appends the file's resulting hash to an
+//! [`EventJournal`]. [`verify`] rescans the tree and reports files that
+//! changed without an event, and events whose recorded content no longer
+//! matches the file on disk.
+//!
+//! Files are hashed one at a time from a stream, so no tree content is held
+//! in memory, and every scan can be cancelled between files.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use super::content::ContentDecoder;
+use super::filter::FileFilter;
+use super::paths::SafePathResolver;
+use super::{FileEvent, FileEventKind};
+
+/// Directory under the watch root holding snapshots and their journals
+pub fn snapshots_dir(root: &Path) -> PathBuf {
+    root.join(".watchdiff").join("snapshots")
+}
+
+/// Hex-encoded SHA-256 of a file's decoded content, read as a stream
+pub fn hash_file<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    let mut reader = ContentDecoder::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn hash_content(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+fn check_cancelled(cancel: &AtomicBool) -> Result<()> {
+    if cancel.load(Ordering::Relaxed) {
+        anyhow::bail!("Integrity scan cancelled");
+    }
+    Ok(())
+}
+
+/// Content hashes of every watched file at a point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeSnapshot {
+    pub id: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Hash per path, relative to the watch root
+    pub files: BTreeMap<PathBuf, String>,
+}
+
+impl TreeSnapshot {
+    /// Hash every file the watcher would watch under `root`
+    pub fn capture(root: &Path, id: impl Into<String>, cancel: &AtomicBool) -> Result<Self> {
+        let filter = FileFilter::new(root)?;
+        let resolver = SafePathResolver::new(root)?;
+        let mut files = BTreeMap::new();
+
+        for path in filter.get_watchable_files()? {
+            check_cancelled(cancel)?;
+            let Some(relative) = resolver.relativize(&path) else {
+                continue;
+            };
+            match hash_file(&path) {
+                Ok(hash) => {
+                    files.insert(relative, hash);
+                }
+                Err(err) => tracing::warn!("Skipping {} in snapshot: {}", path.display(), err),
+            }
+        }
+
+        Ok(Self {
+            id: id.into(),
+            created_at: chrono::Utc::now(),
+            files,
+        })
+    }
+
+    pub fn save(&self, root: &Path) -> Result<PathBuf> {
+        let dir = snapshots_dir(root);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.json", self.id));
+        fs::write(&path, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write snapshot: {}", path.display()))?;
+        Ok(path)
+    }
+
+    pub fn load(root: &Path, id: &str) -> Result<Self> {
+        let path = snapshots_dir(root).join(format!("{}.json", id));
+        let json = fs::read_to_string(&path)
+            .with_context(|| format!("Snapshot not found: {}", id))?;
+        serde_json::from_str(&json).context("Invalid snapshot format")
+    }
+
+    /// ID of the most recently created snapshot under `root`
+    pub fn latest_id(root: &Path) -> Result<Option<String>> {
+        let dir = snapshots_dir(root);
+        if !dir.exists() {
+            return Ok(None);
+        }
+
+        let mut latest: Option<(chrono::DateTime<chrono::Utc>, String)> = None;
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if let Ok(snapshot) = Self::load(root, id) {
+                if latest.as_ref().map_or(true, |(at, _)| snapshot.created_at > *at) {
+                    latest = Some((snapshot.created_at, snapshot.id));
+                }
+            }
+        }
+
+        Ok(latest.map(|(_, id)| id))
+    }
+}
+
+/// Content of a file as of its last recorded event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", content = "hash", rename_all = "snake_case")]
+pub enum RecordedContent {
+    Hash(String),
+    Deleted,
+    /// The content could not be read when the event was recorded
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JournalEntry {
+    path: PathBuf,
+    content: RecordedContent,
+}
+
+/// Final content per path of the events recorded since a snapshot,
+/// optionally persisted next to the snapshot as JSON lines
+#[derive(Debug, Clone)]
+pub struct EventJournal {
+    snapshot_id: String,
+    resolver: SafePathResolver,
+    file: Option<PathBuf>,
+    entries: HashMap<PathBuf, RecordedContent>,
+}
+
+impl EventJournal {
+    /// Journal kept in memory only
+    pub fn new(root: &Path, snapshot_id: impl Into<String>) -> io::Result<Self> {
+        Ok(Self {
+            snapshot_id: snapshot_id.into(),
+            resolver: SafePathResolver::new(root)?,
+            file: None,
+            entries: HashMap::new(),
+        })
+    }
+
+    /// Journal appended to `<snapshot id>.events.jsonl`, loading any entries
+    /// recorded by earlier runs
+    pub fn open(root: &Path, snapshot_id: &str) -> Result<Self> {
+        let mut journal = Self::new(root, snapshot_id)?;
+        let dir = snapshots_dir(root);
+        fs::create_dir_all(&dir)?;
+        let file = dir.join(format!("{}.events.jsonl", snapshot_id));
+
+        if let Ok(existing) = fs::read_to_string(&file) {
+            for line in existing.lines().filter(|l| !l.trim().is_empty()) {
+                let entry: JournalEntry = serde_json::from_str(line)
+                    .with_context(|| format!("Invalid journal entry in {}", file.display()))?;
+                journal.entries.insert(entry.path, entry.content);
+            }
+        }
+
+        journal.file = Some(file);
+        Ok(journal)
+    }
+
+    pub fn snapshot_id(&self) -> &str {
+        &self.snapshot_id
+    }
+
+    /// Record the content a file was left in by `event`
+    pub fn record(&mut self, event: &FileEvent) -> io::Result<()> {
+        if let FileEventKind::Moved { from, .. } = &event.kind {
+            self.record_content(from, RecordedContent::Deleted)?;
+        }
+
+        let content = match event.kind {
+            FileEventKind::Deleted => RecordedContent::Deleted,
+            _ => match event.content {
+                Some(ref content) => RecordedContent::Hash(hash_content(content)),
+                None => hash_file(&event.path)
+                    .map(RecordedContent::Hash)
+                    .unwrap_or(RecordedContent::Unknown),
+            },
+        };
+        self.record_content(&event.path, content)
+    }
+
+    fn record_content(&mut self, path: &Path, content: RecordedContent) -> io::Result<()> {
+        let Some(relative) = self.resolver.relativize(path) else {
+            return Ok(()); // Outside of the watched tree
+        };
+
+        if let Some(ref file) = self.file {
+            let entry = JournalEntry { path: relative.clone(), content: content.clone() };
+            let mut out = OpenOptions::new().create(true).append(true).open(file)?;
+            writeln!(out, "{}", serde_json::to_string(&entry)?)?;
+        }
+
+        self.entries.insert(relative, content);
+        Ok(())
+    }
+
+    pub fn get(&self, relative: &Path) -> Option<&RecordedContent> {
+        self.entries.get(relative)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// A recorded event that does not explain the file's current state
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtraEvent {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// Outcome of comparing the tree against a snapshot and the event journal
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IntegrityReport {
+    pub snapshot_id: String,
+    /// Files hashed in the current tree
+    pub scanned: usize,
+    /// Files identical to the snapshot with no recorded events
+    pub unchanged: usize,
+    /// Files whose current content matches their last recorded event
+    pub matched: usize,
+    /// Files that changed without any recorded event
+    pub missed: Vec<PathBuf>,
+    /// Recorded events whose content no longer matches the file
+    pub extra: Vec<ExtraEvent>,
+}
+
+impl IntegrityReport {
+    pub fn is_clean(&self) -> bool {
+        self.missed.is_empty() && self.extra.is_empty()
+    }
+
+    fn classify(
+        &mut self,
+        path: &Path,
+        before: Option<&String>,
+        current: Option<&String>,
+        recorded: Option<&RecordedContent>,
+    ) {
+        let extra = |reason: &str| ExtraEvent { path: path.to_path_buf(), reason: reason.to_string() };
+        match recorded {
+            None if before == current => self.unchanged += 1,
+            None => self.missed.push(path.to_path_buf()),
+            Some(RecordedContent::Unknown) => self.matched += 1,
+            Some(RecordedContent::Deleted) if current.is_none() => self.matched += 1,
+            Some(RecordedContent::Deleted) => {
+                self.extra.push(extra("recorded as deleted but the file exists"));
+            }
+            Some(RecordedContent::Hash(hash)) if current == Some(hash) => self.matched += 1,
+            Some(RecordedContent::Hash(_)) if current.is_none() => {
+                self.extra.push(extra("deleted after the last recorded event"));
+            }
+            Some(RecordedContent::Hash(_)) => {
+                self.extra.push(extra("changed after the last recorded event"));
+            }
+        }
+    }
+}
+
+/// Rescan the tree at `root` and compare it against `snapshot` and `journal`
+pub fn verify(
+    root: &Path,
+    snapshot: &TreeSnapshot,
+    journal: &EventJournal,
+    cancel: &AtomicBool,
+) -> Result<IntegrityReport> {
+    let filter = FileFilter::new(root)?;
+    let resolver = SafePathResolver::new(root)?;
+    let mut report = IntegrityReport {
+        snapshot_id: snapshot.id.clone(),
+        ..IntegrityReport::default()
+    };
+    let mut seen = HashSet::new();
+
+    for path in filter.get_watchable_files()? {
+        check_cancelled(cancel)?;
+        let Some(relative) = resolver.relativize(&path) else {
+            continue;
+        };
+        let current = match hash_file(&path) {
+            Ok(hash) => hash,
+            Err(err) => {
+                tracing::warn!("Skipping {} in integrity scan: {}", path.display(), err);
+                continue;
+            }
+        };
+
+        report.scanned += 1;
+        report.classify(&relative, snapshot.files.get(&relative), Some(&current), journal.get(&relative));
+        seen.insert(relative);
+    }
+
+    // Files that have disappeared since the snapshot
+    for (relative, hash) in &snapshot.files {
+        if !seen.contains(relative) {
+            report.classify(relative, Some(hash), None, journal.get(relative));
+        }
+    }
+
+    // Files created and removed again after the snapshot
+    for (relative, recorded) in &journal.entries {
+        if !seen.contains(relative) && !snapshot.files.contains_key(relative) {
+            report.classify(relative, None, None, Some(recorded));
+        }
+    }
+
+    report.missed.sort();
+    report.extra.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, name: &str, content: &str) -> PathBuf {
+        let path = root.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    /// Record an event the way the watcher would, with the content it read
+    fn watched_write(journal: &mut EventJournal, root: &Path, name: &str, content: &str) {
+        let path = write(root, name, content);
+        let event = FileEvent::new(path, FileEventKind::Modified).with_content(Arc::from(content));
+        journal.record(&event).unwrap();
+    }
+
+    #[test]
+    fn test_detects_missed_and_stale_changes() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        for name in ["seen.txt", "missed.txt", "stale.txt", "untouched.txt", "gone.txt"] {
+            write(root, name, "original\n");
+        }
+        let not_cancelled = AtomicBool::new(false);
+        let snapshot = TreeSnapshot::capture(root, "startup_1", &not_cancelled).unwrap();
+        snapshot.save(root).unwrap();
+        assert_eq!(snapshot.files.len(), 5);
+
+        let mut journal = EventJournal::open(root, "startup_1").unwrap();
+
+        // Through the watcher
+        watched_write(&mut journal, root, "seen.txt", "edited\n");
+        watched_write(&mut journal, root, "stale.txt", "first edit\n");
+        // Around it
+        write(root, "missed.txt", "edited behind our back\n");
+        write(root, "stale.txt", "second edit, never seen\n");
+        write(root, "new_unseen.txt", "created behind our back\n");
+        fs::remove_file(root.join("gone.txt")).unwrap();
+        // Our own state directory is never reported
+        assert!(snapshots_dir(root).join("startup_1.events.jsonl").exists());
+
+        let snapshot = TreeSnapshot::load(root, "startup_1").unwrap();
+        let journal = EventJournal::open(root, "startup_1").unwrap();
+        assert_eq!(journal.len(), 2);
+        let report = verify(root, &snapshot, &journal, &not_cancelled).unwrap();
+
+        assert_eq!(
+            report.missed,
+            vec![PathBuf::from("gone.txt"), PathBuf::from("missed.txt"), PathBuf::from("new_unseen.txt")]
+        );
+        assert_eq!(report.extra.len(), 1);
+        assert_eq!(report.extra[0].path, PathBuf::from("stale.txt"));
+        assert_eq!(report.matched, 1);
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(report.scanned, 5);
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn test_clean_when_every_change_was_recorded() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        write(root, "a.txt", "a\n");
+        let not_cancelled = AtomicBool::new(false);
+        let snapshot = TreeSnapshot::capture(root, "s", &not_cancelled).unwrap();
+        let mut journal = EventJournal::new(root, "s").unwrap();
+
+        watched_write(&mut journal, root, "a.txt", "b\n");
+        let path = root.join("a.txt");
+        fs::remove_file(&path).unwrap();
+        journal.record(&FileEvent::new(path, FileEventKind::Deleted)).unwrap();
+
+        let report = verify(root, &snapshot, &journal, &not_cancelled).unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.matched, 1);
+    }
+
+    #[test]
+    fn test_scans_are_cancellable() {
+        let dir = TempDir::new().unwrap();
+        write(dir.path(), "a.txt", "a\n");
+        let cancelled = AtomicBool::new(true);
+
+        assert!(TreeSnapshot::capture(dir.path(), "s", &cancelled).is_err());
+        let snapshot = TreeSnapshot::capture(dir.path(), "s", &AtomicBool::new(false)).unwrap();
+        let journal = EventJournal::new(dir.path(), "s").unwrap();
+        assert!(verify(dir.path(), &snapshot, &journal, &cancelled).is_err());
+    }
+
+    #[test]
+    fn test_latest_snapshot_id() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(TreeSnapshot::latest_id(dir.path()).unwrap(), None);
+
+        let not_cancelled = AtomicBool::new(false);
+        let mut older = TreeSnapshot::capture(dir.path(), "older", &not_cancelled).unwrap();
+        older.created_at -= chrono::Duration::minutes(5);
+        older.save(dir.path()).unwrap();
+        TreeSnapshot::capture(dir.path(), "newer", &not_cancelled).unwrap().save(dir.path()).unwrap();
+
+        assert_eq!(TreeSnapshot::latest_id(dir.path()).unwrap(), Some("newer".to_string()));
+    }
+}
@@ -4,12 +4,40 @@
 
 pub mod events;
 pub mod watcher;
+pub mod poll;
+pub mod coalesce;
+pub mod renames;
 pub mod filter;
 pub mod summary;
+pub mod paths;
+pub mod content;
+pub mod clock;
+pub mod replay;
+pub mod snapshots;
+pub mod integrity;
+pub mod health;
+pub mod pins;
+pub mod mounts;
+pub mod git;
+pub mod project;
 
 // Re-export main types
-pub use events::{FileEvent, FileEventKind, HighlightedFileEvent, AppState, AppEvent};
-pub use events::{ChangeOrigin, ChangeConfidence, ConfidenceLevel};
-pub use watcher::FileWatcher;
+pub use events::{FileEvent, FileEventKind, HighlightedFileEvent, AppState, AppEvent, ChangeError};
+pub use events::{ChangeOrigin, ChangeConfidence, ConfidenceLevel, RuleHit, ScoreBreakdown, ScoreDelta, Severity};
+pub use events::{BudgetAlert, BudgetKind};
+pub use watcher::{BackendKind, FileWatcher, WatchOptions};
 pub use filter::FileFilter;
-pub use summary::{ChangeSummary, ChangeSummaryStats, FileSummaryEntry, SummaryFilters, SummaryTimeFrame, SummaryGrouping};
\ No newline at end of file
+pub use coalesce::{EventCoalescer, RawChange};
+pub use renames::{FileIdentity, HeldDeletion, RenameDetector};
+pub use paths::SafePathResolver;
+pub use content::{ContentReader, TextEncoding};
+pub use clock::{Clock, MockClock, SharedClock, SystemClock};
+pub use replay::EventReplayer;
+pub use snapshots::{Baseline, ContentSnapshots, SnapshotKey, SnapshotStore};
+pub use integrity::{EventJournal, IntegrityReport, TreeSnapshot};
+pub use health::WatchHealth;
+pub use pins::PinnedPaths;
+pub use mounts::{PathDisplay, PathMapper, PathMapping};
+pub use git::{BranchChange, GitContext, GitHead, GitHeadTracker};
+pub use project::{ProjectInfo, ProjectNameSource};
+pub use summary::{ChangeSummary, ChangeSummaryStats, DirRollup, FileSummaryEntry, SummaryFilters, SummarySort, SummaryTimeFrame, SummaryGrouping};
\ No newline at end of file
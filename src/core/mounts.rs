@@ -0,0 +1,290 @@
+//! Host paths as a container sees them
+//!
+//! When the watched tree is bind-mounted into a container, the build errors
+//! and logs from inside the container name `/app/src/main.rs` while the
+//! watcher sees `/home/me/project/src/main.rs`. Mappings come from `--map
+//! HOST=CONTAINER` and from the bind mounts in the `volumes` of a
+//! docker-compose file's services. For nested mounts the longest matching
+//! prefix wins.
+
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+use crate::config::MountConfig;
+
+/// Which side of a mapping is shown for an event's path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PathDisplay {
+    /// Paths as the watcher sees them (default)
+    #[default]
+    Host,
+    /// Paths inside the container, where a mapping applies
+    Container,
+    /// Both, as `host → container`
+    Both,
+}
+
+/// A host directory mounted at a container directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathMapping {
+    pub host: PathBuf,
+    pub container: PathBuf,
+}
+
+impl FromStr for PathMapping {
+    type Err = anyhow::Error;
+
+    /// `HOST=CONTAINER`, e.g. `./src=/app/src`
+    fn from_str(s: &str) -> Result<Self> {
+        let (host, container) = s
+            .split_once('=')
+            .filter(|(host, container)| !host.is_empty() && !container.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("Invalid path mapping '{}': expected HOST=CONTAINER", s))?;
+        Ok(Self { host: PathBuf::from(host), container: PathBuf::from(container) })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PathMapper {
+    mappings: Vec<PathMapping>,
+    /// Relative event paths are taken relative to this directory
+    base: PathBuf,
+}
+
+impl PathMapper {
+    /// Mappings with host directories relative to `base`
+    pub fn new(mappings: Vec<PathMapping>, base: &Path) -> Self {
+        let mappings = mappings
+            .into_iter()
+            .map(|mapping| PathMapping { host: resolve(base, &mapping.host), ..mapping })
+            .collect();
+        Self { mappings, base: base.to_path_buf() }
+    }
+
+    /// Mappings from `--map` and `--compose`, or `None` without any. `--map`
+    /// mappings come first, so they win over compose mounts of the same directory.
+    pub fn from_config(config: &MountConfig) -> Result<Option<Self>> {
+        let cwd = std::env::current_dir().context("Failed to read the current directory")?;
+        let mut mappings = config
+            .maps
+            .iter()
+            .map(|map| map.parse::<PathMapping>())
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(ref compose) = config.compose {
+            let text = std::fs::read_to_string(compose)
+                .with_context(|| format!("Failed to read compose file: {}", compose.display()))?;
+            let compose_dir = resolve(&cwd, compose.parent().unwrap_or(Path::new("")));
+            mappings.extend(
+                parse_compose_volumes(&text, &compose_dir)
+                    .with_context(|| format!("Invalid compose file: {}", compose.display()))?,
+            );
+        }
+        if mappings.is_empty() {
+            return Ok(None);
+        }
+
+        let mut mapper = Self::new(mappings, &cwd);
+        // Match the watcher's paths when the mounted directory is behind a symlink
+        for mapping in &mut mapper.mappings {
+            if let Ok(host) = mapping.host.canonicalize() {
+                mapping.host = host;
+            }
+        }
+        Ok(Some(mapper))
+    }
+
+    pub fn mappings(&self) -> &[PathMapping] {
+        &self.mappings
+    }
+
+    /// `host` inside the container, if it is under a mounted directory
+    pub fn to_container(&self, host: &Path) -> Option<PathBuf> {
+        let host = resolve(&self.base, host);
+        let (mapping, rest) = longest_prefix(&self.mappings, |mapping| &mapping.host, &host)?;
+        Some(mapping.container.join(rest))
+    }
+
+    /// The host path of `container`, if it is under a mount point
+    pub fn to_host(&self, container: &Path) -> Option<PathBuf> {
+        let container = normalize(container);
+        let (mapping, rest) = longest_prefix(&self.mappings, |mapping| &mapping.container, &container)?;
+        Some(mapping.host.join(rest))
+    }
+}
+
+/// The mapping whose side `side` is the longest prefix of `path`, and the
+/// rest of the path. The first of equally long prefixes wins.
+fn longest_prefix<'a>(
+    mappings: &'a [PathMapping],
+    side: impl Fn(&PathMapping) -> &Path,
+    path: &Path,
+) -> Option<(&'a PathMapping, PathBuf)> {
+    let mut best: Option<(&PathMapping, PathBuf)> = None;
+    for mapping in mappings {
+        let Ok(rest) = path.strip_prefix(side(mapping)) else { continue };
+        let longer = best
+            .as_ref()
+            .map_or(true, |(best, _)| side(mapping).components().count() > side(best).components().count());
+        if longer {
+            best = Some((mapping, rest.to_path_buf()));
+        }
+    }
+    best
+}
+
+/// The bind mounts in the `volumes` of every service, in the order the
+/// services are listed. Named and anonymous volumes are skipped; relative
+/// host paths are resolved against `compose_dir`, the compose file's directory.
+pub fn parse_compose_volumes(text: &str, compose_dir: &Path) -> Result<Vec<PathMapping>> {
+    let document: Value = serde_yaml::from_str(text).context("Failed to parse YAML")?;
+    let Some(services) = document.get("services").and_then(Value::as_mapping) else {
+        return Ok(Vec::new());
+    };
+
+    let mut mappings = Vec::new();
+    for (_, service) in services {
+        let Some(volumes) = service.get("volumes").and_then(Value::as_sequence) else { continue };
+        for volume in volumes {
+            let mount = match volume {
+                Value::String(short) => short_syntax_bind(short),
+                Value::Mapping(_) => long_syntax_bind(volume),
+                _ => None,
+            };
+            if let Some((source, target)) = mount {
+                mappings.push(PathMapping {
+                    host: resolve(compose_dir, &expand_home(source)),
+                    container: normalize(Path::new(target)),
+                });
+            }
+        }
+    }
+    Ok(mappings)
+}
+
+/// `SOURCE:TARGET[:MODE]` when SOURCE is a host path rather than a volume name
+fn short_syntax_bind(volume: &str) -> Option<(&str, &str)> {
+    let mut parts = volume.splitn(3, ':');
+    let source = parts.next()?;
+    let target = parts.next()?;
+    let is_host_path = source.starts_with('.') || source.starts_with('/') || source.starts_with('~');
+    is_host_path.then_some((source, target))
+}
+
+/// `type: bind` with `source` and `target`
+fn long_syntax_bind(volume: &Value) -> Option<(&str, &str)> {
+    if volume.get("type").and_then(Value::as_str) != Some("bind") {
+        return None;
+    }
+    let source = volume.get("source").and_then(Value::as_str)?;
+    let target = volume.get("target").and_then(Value::as_str)?;
+    Some((source, target))
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => match std::env::var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(rest.trim_start_matches('/')),
+            None => PathBuf::from(path),
+        },
+        _ => PathBuf::from(path),
+    }
+}
+
+/// `path` relative to `base` unless absolute, without `.` and `..` components
+fn resolve(base: &Path, path: &Path) -> PathBuf {
+    normalize(&base.join(path))
+}
+
+/// `path` with `.` and `..` removed without touching the file system
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPOSE: &str = r#"
+services:
+  web:
+    image: node:20
+    volumes:
+      - ./src:/app/src:ro
+      - node_modules:/app/node_modules
+      - /var/cache/app
+      - type: bind
+        source: ../shared
+        target: /app/shared
+      - type: volume
+        source: data
+        target: /data
+  worker:
+    volumes:
+      - .:/app
+volumes:
+  node_modules:
+  data:
+"#;
+
+    #[test]
+    fn test_compose_bind_mounts_in_short_and_long_syntax() {
+        let mappings = parse_compose_volumes(COMPOSE, Path::new("/home/me/project")).unwrap();
+        let pairs: Vec<(&str, &str)> = mappings
+            .iter()
+            .map(|m| (m.host.to_str().unwrap(), m.container.to_str().unwrap()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("/home/me/project/src", "/app/src"),
+                ("/home/me/shared", "/app/shared"),
+                ("/home/me/project", "/app"),
+            ]
+        );
+
+        assert!(parse_compose_volumes("services: [", Path::new("/")).is_err());
+        assert!(parse_compose_volumes("version: '3'", Path::new("/")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_longest_prefix_wins_for_nested_mounts() {
+        let mappings = vec![
+            "/home/me/project=/app".parse().unwrap(),
+            "/home/me/project/src=/srv/src".parse().unwrap(),
+            "vendor=/opt/vendor".parse().unwrap(),
+        ];
+        let mapper = PathMapper::new(mappings, Path::new("/home/me/project"));
+
+        let container = |p: &str| mapper.to_container(Path::new(p));
+        assert_eq!(container("/home/me/project/src/main.rs"), Some(PathBuf::from("/srv/src/main.rs")));
+        assert_eq!(container("/home/me/project/Cargo.toml"), Some(PathBuf::from("/app/Cargo.toml")));
+        assert_eq!(container("/home/me/project/vendor/lib.rs"), Some(PathBuf::from("/opt/vendor/lib.rs")));
+        // Relative to the base directory, and not fooled by a shared name prefix
+        assert_eq!(container("src/lib.rs"), Some(PathBuf::from("/srv/src/lib.rs")));
+        assert_eq!(container("/home/me/project-old/a.rs"), None);
+
+        assert_eq!(mapper.to_host(Path::new("/srv/src/main.rs")), Some(PathBuf::from("/home/me/project/src/main.rs")));
+        assert_eq!(mapper.to_host(Path::new("/app/./tests/a.rs")), Some(PathBuf::from("/home/me/project/tests/a.rs")));
+        assert_eq!(mapper.to_host(Path::new("/etc/hosts")), None);
+
+        assert!("no-equals".parse::<PathMapping>().is_err());
+        assert!("=/app".parse::<PathMapping>().is_err());
+    }
+}
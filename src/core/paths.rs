@@ -0,0 +1,104 @@
+//! Safe resolution of repo-relative paths onto a checkout root
+
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Maps repo-relative paths onto a root directory, refusing anything that
+/// would land outside of it (absolute paths, `..` segments, symlink escapes).
+#[derive(Debug, Clone)]
+pub struct SafePathResolver {
+    root: PathBuf,
+}
+
+impl SafePathResolver {
+    pub fn new<P: AsRef<Path>>(root: P) -> io::Result<Self> {
+        let root = root.as_ref().canonicalize()?;
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Resolve a repo-relative path to an absolute path under the root
+    pub fn resolve<P: AsRef<Path>>(&self, relative: P) -> io::Result<PathBuf> {
+        let relative = relative.as_ref();
+        let mut resolved = self.root.clone();
+
+        for component in relative.components() {
+            match component {
+                Component::Normal(part) => resolved.push(part),
+                Component::CurDir => {}
+                Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("Path escapes checkout root: {}", relative.display()),
+                    ));
+                }
+            }
+        }
+
+        // Follow symlinks for the part of the path that already exists
+        let existing = resolved
+            .ancestors()
+            .find(|p| p.exists())
+            .unwrap_or(&self.root);
+        if !existing.canonicalize()?.starts_with(&self.root) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Path escapes checkout root via symlink: {}", relative.display()),
+            ));
+        }
+
+        Ok(resolved)
+    }
+
+    /// Express `path` relative to the root, if it lies under it
+    pub fn relativize<P: AsRef<Path>>(&self, path: P) -> Option<PathBuf> {
+        let path = path.as_ref();
+        if path.is_relative() {
+            return Some(path.to_path_buf());
+        }
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        canonical
+            .strip_prefix(&self.root)
+            .ok()
+            .map(|p| p.to_path_buf())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_resolve_relative_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let resolver = SafePathResolver::new(temp_dir.path()).unwrap();
+
+        let resolved = resolver.resolve("src/main.rs").unwrap();
+        assert_eq!(resolved, resolver.root().join("src").join("main.rs"));
+    }
+
+    #[test]
+    fn test_resolve_rejects_escapes() {
+        let temp_dir = TempDir::new().unwrap();
+        let resolver = SafePathResolver::new(temp_dir.path()).unwrap();
+
+        assert!(resolver.resolve("../outside.txt").is_err());
+        assert!(resolver.resolve("src/../../outside.txt").is_err());
+        assert!(resolver.resolve("/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_relativize() {
+        let temp_dir = TempDir::new().unwrap();
+        let resolver = SafePathResolver::new(temp_dir.path()).unwrap();
+        let file = temp_dir.path().join("a.txt");
+        std::fs::write(&file, "a").unwrap();
+
+        assert_eq!(resolver.relativize(&file), Some(PathBuf::from("a.txt")));
+        assert_eq!(resolver.relativize("/definitely/elsewhere"), None);
+    }
+}
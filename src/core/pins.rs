@@ -0,0 +1,128 @@
+//! Files whose event history is kept when the log is pruned
+//!
+//! A path is pinned either explicitly, from the file list or search results,
+//! or by a `--pin` glob. Globs use gitignore syntax relative to the watch
+//! root. Explicit pins are remembered in the UI state file; globs come from
+//! the command line each run.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// Default cap on events kept per pinned path
+pub const DEFAULT_MAX_PINNED_EVENTS_PER_PATH: usize = 500;
+
+#[derive(Debug, Clone)]
+pub struct PinnedPaths {
+    root: PathBuf,
+    /// Explicitly pinned paths, relative to `root`
+    explicit: BTreeSet<PathBuf>,
+    globs: Vec<String>,
+    matcher: Option<Gitignore>,
+}
+
+impl Default for PinnedPaths {
+    fn default() -> Self {
+        Self::new(PathBuf::new())
+    }
+}
+
+impl PinnedPaths {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            explicit: BTreeSet::new(),
+            globs: Vec::new(),
+            matcher: None,
+        }
+    }
+
+    /// Also pin every path matching one of `globs`
+    pub fn with_globs(mut self, globs: &[String]) -> Result<Self> {
+        if globs.is_empty() {
+            return Ok(self);
+        }
+        let mut builder = GitignoreBuilder::new(&self.root);
+        for glob in globs {
+            builder
+                .add_line(None, glob)
+                .with_context(|| format!("Invalid --pin pattern: {}", glob))?;
+        }
+        self.matcher = Some(builder.build().context("Failed to build --pin patterns")?);
+        self.globs = globs.to_vec();
+        Ok(self)
+    }
+
+    /// Also pin each of `paths`, absolute or relative to the root
+    pub fn with_paths(mut self, paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        for path in paths {
+            let relative = self.relative(&path).to_path_buf();
+            self.explicit.insert(relative);
+        }
+        self
+    }
+
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.root).unwrap_or(path)
+    }
+
+    pub fn is_pinned(&self, path: &Path) -> bool {
+        let relative = self.relative(path);
+        self.explicit.contains(relative)
+            || self
+                .matcher
+                .as_ref()
+                .is_some_and(|matcher| matcher.matched(relative, false).is_ignore())
+    }
+
+    /// Pin `path` explicitly, or unpin it if it was. Returns whether it is
+    /// now explicitly pinned; a path matching a glob stays pinned regardless.
+    pub fn toggle(&mut self, path: &Path) -> bool {
+        let relative = self.relative(path).to_path_buf();
+        if self.explicit.remove(&relative) {
+            false
+        } else {
+            self.explicit.insert(relative);
+            true
+        }
+    }
+
+    /// Explicitly pinned paths, relative to the root
+    pub fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        self.explicit.iter()
+    }
+
+    pub fn globs(&self) -> &[String] {
+        &self.globs
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.explicit.is_empty() && self.globs.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explicit_and_glob_pins() {
+        let root = PathBuf::from("/work/repo");
+        let mut pins = PinnedPaths::new(root.clone())
+            .with_globs(&["*.toml".to_string(), "src/config/**".to_string()])
+            .unwrap()
+            .with_paths([PathBuf::from("README.md")]);
+
+        assert!(pins.is_pinned(&root.join("Cargo.toml")));
+        assert!(pins.is_pinned(&root.join("src/config/mod.rs")));
+        assert!(pins.is_pinned(&root.join("README.md")));
+        assert!(!pins.is_pinned(&root.join("src/main.rs")));
+
+        assert!(pins.toggle(&root.join("src/main.rs")));
+        assert!(pins.is_pinned(&root.join("src/main.rs")));
+        assert!(!pins.toggle(&root.join("README.md")));
+        assert!(!pins.is_pinned(&root.join("README.md")));
+        assert_eq!(pins.paths().collect::<Vec<_>>(), vec![&PathBuf::from("src/main.rs")]);
+    }
+}
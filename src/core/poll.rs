@@ -0,0 +1,191 @@
+//! Change detection by scanning the tree on an interval
+//!
+//! Native notifications never arrive for changes made on another machine,
+//! as on NFS and SMB mounts and some container bind mounts. [`StatPoller`]
+//! stats every file each interval instead and reports the files created,
+//! removed, or changed in modification time or size as notify events, so
+//! they go through the same pipeline as native ones. Comparing sizes as well
+//! catches rewrites within the modification time's granularity.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use anyhow::{Context, Result};
+use ignore::WalkBuilder;
+use notify::event::{CreateKind, DataChange, ModifyKind, RemoveKind};
+use notify::{Event, EventKind};
+use super::filter::FileFilter;
+
+/// What a scan knows about a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+/// Scans a tree every interval until dropped
+pub struct StatPoller {
+    stop: Arc<AtomicBool>,
+}
+
+impl StatPoller {
+    /// Record the files under `root` as they are now, then report changes to
+    /// them on `tx` every `interval`. What `filter` ignores is not scanned.
+    pub fn start(root: &Path, interval: Duration, filter: &FileFilter, tx: Sender<notify::Result<Event>>) -> Result<Self> {
+        // Events carry absolute paths, as native ones do
+        let root = std::env::current_dir().map_or_else(|_| root.to_path_buf(), |cwd| cwd.join(root));
+        if !root.is_dir() {
+            anyhow::bail!("Failed to start polling directory: {} is not a directory", root.display());
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        let filter = filter.clone();
+        let mut known = scan(&root, &filter);
+        thread::Builder::new()
+            .name("watchdiff-poll".to_string())
+            .spawn(move || {
+                while !stopped.load(Ordering::Relaxed) {
+                    thread::sleep(interval);
+                    if stopped.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    let current = scan(&root, &filter);
+                    for event in changes(&known, &current) {
+                        if tx.send(Ok(event)).is_err() {
+                            return; // Watcher dropped, stop polling
+                        }
+                    }
+                    known = current;
+                }
+            })
+            .context("Failed to start polling thread")?;
+        Ok(Self { stop })
+    }
+}
+
+impl Drop for StatPoller {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Every file under `root` the walk does not ignore, plus the files under
+/// `.git` that tell a checkout happened
+fn scan(root: &Path, filter: &FileFilter) -> HashMap<PathBuf, FileStamp> {
+    let mut files = HashMap::new();
+    for entry in filter.walker(root).build().flatten() {
+        if entry.file_type().is_some_and(|kind| kind.is_file()) {
+            if let Ok(metadata) = entry.metadata() {
+                files.insert(entry.into_path(), stamp(&metadata));
+            }
+        }
+    }
+
+    let git_dir = root.join(".git");
+    for path in [git_dir.join("HEAD"), git_dir.join("packed-refs")] {
+        if let Ok(metadata) = std::fs::metadata(&path) {
+            files.insert(path, stamp(&metadata));
+        }
+    }
+    let heads = git_dir.join("refs").join("heads");
+    if heads.is_dir() {
+        for entry in WalkBuilder::new(&heads).standard_filters(false).build().flatten() {
+            if entry.file_type().is_some_and(|kind| kind.is_file()) {
+                if let Ok(metadata) = entry.metadata() {
+                    files.insert(entry.into_path(), stamp(&metadata));
+                }
+            }
+        }
+    }
+    files
+}
+
+fn stamp(metadata: &std::fs::Metadata) -> FileStamp {
+    FileStamp { modified: metadata.modified().ok(), len: metadata.len() }
+}
+
+/// Events turning `before` into `after`, sorted by path
+fn changes(before: &HashMap<PathBuf, FileStamp>, after: &HashMap<PathBuf, FileStamp>) -> Vec<Event> {
+    let mut events: Vec<Event> = after
+        .iter()
+        .filter_map(|(path, stamp)| match before.get(path) {
+            None => Some(Event::new(EventKind::Create(CreateKind::File)).add_path(path.clone())),
+            Some(known) if known != stamp => {
+                Some(Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Any))).add_path(path.clone()))
+            }
+            Some(_) => None,
+        })
+        .chain(
+            before
+                .keys()
+                .filter(|path| !after.contains_key(*path))
+                .map(|path| Event::new(EventKind::Remove(RemoveKind::File)).add_path(path.clone())),
+        )
+        .collect();
+    events.sort_by(|a, b| a.paths.cmp(&b.paths));
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use tempfile::TempDir;
+
+    /// Wait for `kind` on `path`; a write can also be seen half done first
+    fn wait_for(rx: &mpsc::Receiver<notify::Result<Event>>, kind: EventKind, path: &Path) {
+        loop {
+            let event = rx.recv_timeout(Duration::from_secs(5)).expect("poll event").unwrap();
+            if event.kind == kind && event.paths[0] == path {
+                return;
+            }
+        }
+    }
+
+    #[test]
+    fn test_poller_reports_creates_rewrites_and_removals() {
+        let dir = TempDir::new().unwrap();
+        let existing = dir.path().join("existing.rs");
+        std::fs::write(&existing, "fn a() {}\n").unwrap();
+        let (tx, rx) = mpsc::channel();
+        let _poller = StatPoller::start(dir.path(), Duration::from_millis(20), &FileFilter::new(dir.path()).unwrap(), tx).unwrap();
+
+        let created = dir.path().join("new.rs");
+        std::fs::write(&created, "fn b() {}\n").unwrap();
+        wait_for(&rx, EventKind::Create(CreateKind::File), &created);
+
+        std::fs::write(&existing, "fn a() { 1 }\n").unwrap();
+        wait_for(&rx, EventKind::Modify(ModifyKind::Data(DataChange::Any)), &existing);
+
+        std::fs::remove_file(&created).unwrap();
+        wait_for(&rx, EventKind::Remove(RemoveKind::File), &created);
+        // Nothing else changed
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn test_changes_compare_modification_time_and_size() {
+        let at = |secs| Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+        let before = HashMap::from([
+            (PathBuf::from("/r/a"), FileStamp { modified: at(1), len: 3 }),
+            (PathBuf::from("/r/b"), FileStamp { modified: at(1), len: 3 }),
+            (PathBuf::from("/r/c"), FileStamp { modified: at(1), len: 3 }),
+        ]);
+        let after = HashMap::from([
+            (PathBuf::from("/r/a"), FileStamp { modified: at(1), len: 3 }),
+            // Rewritten within one mtime tick
+            (PathBuf::from("/r/b"), FileStamp { modified: at(1), len: 4 }),
+            (PathBuf::from("/r/d"), FileStamp { modified: at(2), len: 1 }),
+        ]);
+        let events: Vec<_> = changes(&before, &after).into_iter().map(|e| (e.kind, e.paths[0].clone())).collect();
+        assert_eq!(events, vec![
+            (EventKind::Modify(ModifyKind::Data(DataChange::Any)), PathBuf::from("/r/b")),
+            (EventKind::Remove(RemoveKind::File), PathBuf::from("/r/c")),
+            (EventKind::Create(CreateKind::File), PathBuf::from("/r/d")),
+        ]);
+    }
+}
@@ -0,0 +1,84 @@
+//! The watched tree as one project
+//!
+//! With `--project-header` the TUI shows the project's name, root and the
+//! time the session started above the diff log, which tells apart several
+//! WatchDiff instances running side by side.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local};
+
+/// Where a project's name came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectNameSource {
+    CargoToml,
+    PackageJson,
+    Directory,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProjectInfo {
+    pub name: String,
+    pub name_source: ProjectNameSource,
+    pub root: PathBuf,
+    pub started_at: DateTime<Local>,
+}
+
+impl ProjectInfo {
+    /// Name the project at `root` after its `Cargo.toml` package, its
+    /// `package.json` name, or else the directory
+    pub fn detect(root: &Path) -> Self {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let (name, name_source) = cargo_package_name(&root)
+            .map(|name| (name, ProjectNameSource::CargoToml))
+            .or_else(|| npm_package_name(&root).map(|name| (name, ProjectNameSource::PackageJson)))
+            .unwrap_or_else(|| {
+                let name = root
+                    .file_name()
+                    .map_or_else(|| root.display().to_string(), |name| name.to_string_lossy().into_owned());
+                (name, ProjectNameSource::Directory)
+            });
+        Self { name, name_source, root, started_at: Local::now() }
+    }
+}
+
+/// `[package] name`; a workspace root without a package has none
+fn cargo_package_name(root: &Path) -> Option<String> {
+    let manifest = fs::read_to_string(root.join("Cargo.toml")).ok()?.parse::<toml::Table>().ok()?;
+    let name = manifest.get("package")?.get("name")?.as_str()?;
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+fn npm_package_name(root: &Path) -> Option<String> {
+    let package: serde_json::Value = serde_json::from_str(&fs::read_to_string(root.join("package.json")).ok()?).ok()?;
+    let name = package.get("name")?.as_str()?;
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_detect_project_name() {
+        let dir = TempDir::new().unwrap();
+        let project = dir.path().join("my-dir");
+        fs::create_dir(&project).unwrap();
+
+        let info = ProjectInfo::detect(&project);
+        assert_eq!((info.name.as_str(), info.name_source), ("my-dir", ProjectNameSource::Directory));
+
+        fs::write(project.join("package.json"), r#"{ "name": "web-app" }"#).unwrap();
+        assert_eq!(ProjectInfo::detect(&project).name, "web-app");
+
+        // A workspace manifest without a package falls through
+        fs::write(project.join("Cargo.toml"), "[workspace]\nmembers = [\"a\"]\n").unwrap();
+        assert_eq!(ProjectInfo::detect(&project).name_source, ProjectNameSource::PackageJson);
+
+        fs::write(project.join("Cargo.toml"), "[package]\nname = \"my-crate\"\nversion = \"0.1.0\"\n").unwrap();
+        let info = ProjectInfo::detect(&project);
+        assert_eq!((info.name.as_str(), info.name_source), ("my-crate", ProjectNameSource::CargoToml));
+        assert_eq!(info.root, project.canonicalize().unwrap());
+    }
+}
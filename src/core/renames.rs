@@ -0,0 +1,333 @@
+//! Pairing deletions with creations into moves
+//!
+//! Moving a file reaches the watcher as the removal of one path and the
+//! creation of another, and so does an editor that saves by writing a new
+//! file and deleting the old one. [`RenameDetector`] holds each deletion back
+//! for a short window. A file that appears within it with the same content,
+//! or nearly the same, or that is the same binary file on disk by size and
+//! inode, claims the deletion and is reported as one move. Deletions nobody claims
+//! are released as deletions once the window passes.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use similar::TextDiff;
+use super::snapshots::{content_hash, Baseline};
+
+/// Default time a deletion waits for the file to reappear elsewhere
+pub const DEFAULT_RENAME_WINDOW: Duration = Duration::from_millis(500);
+
+/// Share of lines a new file must have in common with a deleted one to be
+/// taken as its move
+pub const RENAME_SIMILARITY: f32 = 0.8;
+
+/// Files whose identity is remembered at most; the longest known are
+/// forgotten first
+pub const MAX_IDENTITIES: usize = 100_000;
+
+/// Which file on disk a path is, as far as a rename keeps it: the size, and
+/// the device and inode on Unix or else the modification time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileIdentity {
+    size: u64,
+    #[cfg(unix)]
+    dev: u64,
+    #[cfg(unix)]
+    ino: u64,
+    #[cfg(not(unix))]
+    modified: Option<SystemTime>,
+}
+
+impl FileIdentity {
+    /// Identity of the file at `path`, if it can be looked up
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::symlink_metadata(path).ok()?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            Some(Self { size: metadata.len(), dev: metadata.dev(), ino: metadata.ino() })
+        }
+        #[cfg(not(unix))]
+        {
+            Some(Self { size: metadata.len(), modified: metadata.modified().ok() })
+        }
+    }
+
+    /// Whether `other` is this file renamed. The size has to match too, as a
+    /// deleted file's inode is soon handed out again.
+    fn is_same_file(&self, other: &FileIdentity) -> bool {
+        #[cfg(not(unix))]
+        if self.modified.is_none() {
+            return false;
+        }
+        self == other
+    }
+}
+
+/// A deletion held back in case the file reappears
+#[derive(Debug, Clone)]
+pub struct HeldDeletion {
+    pub path: PathBuf,
+    /// What the file held when last seen, if it is a text file
+    pub baseline: Option<Baseline>,
+    /// Which file on disk it was, if it was looked up
+    pub identity: Option<FileIdentity>,
+    /// When the file was deleted, for the event if it is released as a deletion
+    pub timestamp: SystemTime,
+    held_at: Instant,
+}
+
+/// Deletions waiting to be claimed by a creation
+#[derive(Debug)]
+pub struct RenameDetector {
+    window: Duration,
+    held: Vec<HeldDeletion>,
+    /// Identity of each file seen, to recognize it once renamed, with the
+    /// number it was first seen as
+    identities: HashMap<PathBuf, (FileIdentity, u64)>,
+    /// Files in the order they were first seen, to forget the oldest. Entries
+    /// whose number no longer matches were held or forgotten already.
+    seen: VecDeque<(u64, PathBuf)>,
+    next_seen: u64,
+    max_identities: usize,
+}
+
+impl RenameDetector {
+    /// Detector holding deletions for `window`; a zero window turns detection off
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            held: Vec::new(),
+            identities: HashMap::new(),
+            seen: VecDeque::new(),
+            next_seen: 0,
+            max_identities: MAX_IDENTITIES,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.window.is_zero()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.held.is_empty()
+    }
+
+    /// Look up and remember which file on disk `path` is now
+    pub fn observe(&mut self, path: &Path) -> Option<FileIdentity> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let identity = FileIdentity::of(path)?;
+        match self.identities.get_mut(path) {
+            Some((known, _)) => *known = identity,
+            None => {
+                self.identities.insert(path.to_path_buf(), (identity, self.next_seen));
+                self.seen.push_back((self.next_seen, path.to_path_buf()));
+                self.next_seen += 1;
+            }
+        }
+        while self.seen.len() > self.max_identities {
+            let Some((number, path)) = self.seen.pop_front() else { break };
+            if self.identities.get(&path).is_some_and(|(_, seen)| *seen == number) {
+                self.identities.remove(&path);
+            }
+        }
+        Some(identity)
+    }
+
+    /// Hold back the deletion of `path`, which last held `baseline`. Returns
+    /// false, holding nothing, when there is nothing to recognize the file by.
+    pub fn hold(&mut self, path: PathBuf, baseline: Option<Baseline>, now: Instant) -> bool {
+        let identity = self.identities.remove(&path).map(|(identity, _)| identity);
+        if !self.is_enabled() || (baseline.is_none() && identity.is_none()) {
+            return false;
+        }
+        self.held.retain(|held| held.path != path);
+        self.held.push(HeldDeletion { path, baseline, identity, timestamp: SystemTime::now(), held_at: now });
+        true
+    }
+
+    /// The held deletion that the file just appeared at `path`, with
+    /// `content` if it is text, was moved from: one with the same content,
+    /// the same binary file on disk, or else the most similar one above
+    /// [`RENAME_SIMILARITY`]. Being the same file on disk is not enough for
+    /// text, which must also be similar, as inodes are reused. A file deleted
+    /// and created again at the same path claims its own deletion.
+    pub fn claim(&mut self, path: &Path, content: Option<&str>, identity: Option<FileIdentity>) -> Option<HeldDeletion> {
+        let hash = content.map(content_hash);
+        let similar = |held: &HeldDeletion| -> Option<f32> {
+            let old = held.baseline.as_ref()?.content.as_deref()?;
+            similarity(old, content?).filter(|ratio| *ratio >= RENAME_SIMILARITY)
+        };
+        let index = self
+            .held
+            .iter()
+            .rposition(|held| {
+                held.path == path
+                    || (hash.is_some() && held.baseline.as_ref().map(|baseline| baseline.hash) == hash)
+                    || (held.identity.zip(identity).is_some_and(|(held, new)| held.is_same_file(&new))
+                        && (content.is_none() || similar(held).is_some()))
+            })
+            .or_else(|| {
+                self.held
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, held)| Some((i, similar(held)?)))
+                    .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .map(|(i, _)| i)
+            })?;
+        Some(self.held.remove(index))
+    }
+
+    /// How long until the oldest held deletion is due, if any are held
+    pub fn next_due(&self, now: Instant) -> Option<Duration> {
+        self.held
+            .iter()
+            .map(|held| (held.held_at + self.window).saturating_duration_since(now))
+            .min()
+    }
+
+    /// Deletions whose window has passed, oldest first
+    pub fn take_expired(&mut self, now: Instant) -> Vec<HeldDeletion> {
+        let window = self.window;
+        let (expired, held) = std::mem::take(&mut self.held)
+            .into_iter()
+            .partition(|held| held.held_at + window <= now);
+        self.held = held;
+        expired
+    }
+
+    /// Every held deletion, e.g. when watching stops
+    pub fn drain(&mut self) -> Vec<HeldDeletion> {
+        std::mem::take(&mut self.held)
+    }
+}
+
+/// Share of lines `old` and `new` have in common; None when their sizes are
+/// too far apart for them to be similar
+fn similarity(old: &str, new: &str) -> Option<f32> {
+    let (shorter, longer) = (old.len().min(new.len()), old.len().max(new.len()));
+    if longer == 0 || (shorter as f32) < longer as f32 * RENAME_SIMILARITY {
+        return None;
+    }
+    Some(TextDiff::from_lines(old, new).ratio())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::snapshots::SnapshotStore;
+
+    fn baseline(content: &str) -> Option<Baseline> {
+        let mut store = SnapshotStore::default();
+        store.seed(PathBuf::from("/r/x"), content);
+        store.remove(Path::new("/r/x"))
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_creation_claims_a_deletion_with_the_same_or_similar_content() {
+        let start = Instant::now();
+        let mut renames = RenameDetector::new(ms(500));
+        let lib: String = (0..20).map(|i| format!("fn f{}() {{}}\n", i)).collect();
+        renames.hold(PathBuf::from("/r/old.rs"), baseline(&lib), start);
+        renames.hold(PathBuf::from("/r/notes.md"), baseline("# Notes\n"), start);
+
+        // Unrelated content claims nothing
+        assert!(renames.claim(Path::new("/r/other.rs"), Some("struct Other;\n"), None).is_none());
+
+        // One line of twenty edited on the way
+        let edited = lib.replace("fn f3() {}", "fn f3() { todo!() }");
+        let moved = renames.claim(Path::new("/r/src/new.rs"), Some(&edited), None).unwrap();
+        assert_eq!(moved.path, PathBuf::from("/r/old.rs"));
+
+        assert_eq!(renames.claim(Path::new("/r/NOTES.md"), Some("# Notes\n"), None).unwrap().path, PathBuf::from("/r/notes.md"));
+        assert!(renames.is_empty());
+
+        // Without content or anything else known, a deletion is not held
+        assert!(!renames.hold(PathBuf::from("/r/image.png"), None, start));
+        assert!(renames.is_empty());
+    }
+
+    #[test]
+    fn test_unclaimed_deletions_expire_after_the_window() {
+        let start = Instant::now();
+        let mut renames = RenameDetector::new(ms(500));
+        renames.hold(PathBuf::from("/r/a"), baseline("a\n"), start);
+        renames.hold(PathBuf::from("/r/b"), baseline("b\n"), start + ms(200));
+
+        assert_eq!(renames.next_due(start + ms(100)), Some(ms(400)));
+        assert!(renames.take_expired(start + ms(499)).is_empty());
+        let expired = renames.take_expired(start + ms(600));
+        assert_eq!(expired.iter().map(|held| held.path.clone()).collect::<Vec<_>>(), [PathBuf::from("/r/a")]);
+        assert_eq!(renames.next_due(start + ms(600)), Some(ms(100)));
+        assert_eq!(renames.drain().len(), 1);
+        assert_eq!(renames.next_due(start), None);
+
+        assert!(!RenameDetector::new(Duration::ZERO).is_enabled());
+    }
+
+    #[test]
+    fn test_binary_file_is_recognized_by_its_identity() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (old, new, other) = (dir.path().join("a.png"), dir.path().join("b.png"), dir.path().join("c.png"));
+        std::fs::write(&old, [0x89, b'P', b'N', b'G', 0, 1, 2]).unwrap();
+        std::fs::write(&other, [0x89, b'P', b'N', b'G', 0, 1]).unwrap();
+        let mut renames = RenameDetector::new(ms(500));
+        renames.observe(&old);
+
+        std::fs::rename(&old, &new).unwrap();
+        assert!(renames.hold(old.clone(), None, Instant::now()));
+        // A different file claims nothing
+        assert!(renames.claim(&other, None, FileIdentity::of(&other)).is_none());
+        let identity = renames.observe(&new);
+        assert_eq!(renames.claim(&new, None, identity).unwrap().path, old);
+    }
+
+    #[test]
+    fn test_text_file_needs_similar_content_besides_its_identity() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (old, new) = (dir.path().join("a.rs"), dir.path().join("b.rs"));
+        std::fs::write(&old, "fn a() {}\n").unwrap();
+        let mut renames = RenameDetector::new(ms(500));
+        renames.observe(&old);
+
+        // Same inode and size, but the text has nothing in common
+        std::fs::rename(&old, &new).unwrap();
+        std::fs::write(&new, "struct B;\n").unwrap();
+        assert!(renames.hold(old.clone(), baseline("fn a() {}\n"), Instant::now()));
+        let identity = renames.observe(&new);
+        assert!(renames.claim(&new, Some("struct B;\n"), identity).is_none());
+    }
+
+    #[test]
+    fn test_identities_are_bounded() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let paths: Vec<PathBuf> = (0..3).map(|i| dir.path().join(format!("{}.bin", i))).collect();
+        for path in &paths {
+            std::fs::write(path, [0, 1, 2]).unwrap();
+        }
+        let mut renames = RenameDetector::new(ms(500));
+        renames.max_identities = 2;
+
+        for path in &paths {
+            renames.observe(path);
+        }
+        assert_eq!(renames.identities.len(), 2);
+        assert!(!renames.identities.contains_key(&paths[0]));
+
+        // Files deleted and seen again do not pile up
+        for _ in 0..10 {
+            renames.hold(paths[2].clone(), None, Instant::now());
+            renames.claim(&paths[2], None, None);
+            renames.observe(&paths[2]);
+        }
+        assert!(renames.seen.len() <= 2);
+        assert!(renames.identities.len() <= 2);
+        assert!(renames.identities.contains_key(&paths[2]));
+    }
+}
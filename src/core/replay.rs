@@ -0,0 +1,117 @@
+//! Deterministic replay of recorded events
+//!
+//! Events recorded with `--output json` are fed back through batch detection
+//! and [`AppState`], with a [`MockClock`] set to each event's timestamp so
+//! batching and cleanup behave exactly as they would have live.
+
+use std::io::BufRead;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use anyhow::{Context, Result};
+use crate::ai::BatchChangeDetector;
+use crate::config::WatchDiffConfig;
+use super::clock::MockClock;
+use super::events::{AppState, FileEvent};
+
+pub struct EventReplayer {
+    clock: MockClock,
+    state: AppState,
+    batch_detector: BatchChangeDetector,
+    cleanup_interval: Duration,
+}
+
+impl EventReplayer {
+    pub fn new(config: &WatchDiffConfig) -> Self {
+        let clock = MockClock::new(SystemTime::UNIX_EPOCH);
+        Self {
+            state: AppState::with_config(config).with_clock(Arc::new(clock.clone())),
+            batch_detector: BatchChangeDetector::with_clock(config.ai.clone(), Arc::new(clock.clone())),
+            cleanup_interval: config.watcher.cleanup_interval_duration(),
+            clock,
+        }
+    }
+
+    /// Replay one event at its recorded time, re-running batch detection
+    pub fn replay(&mut self, mut event: FileEvent) {
+        self.clock.set(event.timestamp);
+
+        event.batch_id = self
+            .batch_detector
+            .process_change_at(&event.path, &event.origin, event.timestamp);
+        self.state.add_event_with_cleanup_interval(event, self.cleanup_interval);
+    }
+
+    /// Replay JSON-lines output, one event per line. Returns the number of events replayed.
+    pub fn replay_jsonl<R: BufRead>(&mut self, reader: R) -> Result<usize> {
+        let mut count = 0;
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event: FileEvent = serde_json::from_str(&line)
+                .with_context(|| format!("Invalid event on line {}", index + 1))?;
+            self.replay(event);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    pub fn clock(&self) -> &MockClock {
+        &self.clock
+    }
+
+    pub fn state(&self) -> &AppState {
+        &self.state
+    }
+
+    pub fn into_state(self) -> AppState {
+        self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use crate::core::{ChangeOrigin, Clock, FileEventKind};
+
+    fn recorded(path: &str, secs: u64) -> FileEvent {
+        let mut event = FileEvent::new(PathBuf::from(path), FileEventKind::Modified)
+            .with_origin(ChangeOrigin::AIAgent { tool_name: "claude".to_string(), process_id: None })
+            .with_batch_id("stale".to_string());
+        event.timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000 + secs);
+        event
+    }
+
+    #[test]
+    fn test_replay_rebatches_by_recorded_time() {
+        let mut replayer = EventReplayer::new(&WatchDiffConfig::default());
+        let lines: String = [recorded("a.rs", 0), recorded("b.rs", 2), recorded("c.rs", 60)]
+            .iter()
+            .map(|e| serde_json::to_string(e).unwrap() + "\n")
+            .collect();
+
+        assert_eq!(replayer.replay_jsonl(lines.as_bytes()).unwrap(), 3);
+
+        // Events are newest first
+        let batches: Vec<_> = replayer.state().events.iter().map(|e| e.batch_id.clone().unwrap()).collect();
+        assert_eq!(batches, vec!["batch_1700000060000", "batch_1700000000000", "batch_1700000000000"]);
+    }
+
+    #[test]
+    fn test_replay_cleanup_uses_recorded_time() {
+        let mut config = WatchDiffConfig::default();
+        config.watcher.max_event_age_secs = 600;
+        config.watcher.cleanup_interval_secs = 60;
+        let mut replayer = EventReplayer::new(&config);
+
+        replayer.replay(recorded("old.rs", 0));
+        replayer.replay(recorded("new.rs", 3600));
+
+        // The hour-old event was cleaned up as it would have been live
+        assert_eq!(replayer.state().events.len(), 1);
+        assert_eq!(replayer.state().events[0].path, PathBuf::from("new.rs"));
+        assert_eq!(replayer.clock().now(), SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_003_600));
+    }
+}
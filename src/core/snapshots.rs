@@ -0,0 +1,343 @@
+//! Bounded retention of file content
+//!
+//! [`ContentSnapshots`] keeps the content of each event, so two events of the
+//! log can be compared against each other after the file on disk has moved
+//! on. The oldest snapshots are evicted first once either the count or the
+//! total size limit is reached.
+//!
+//! [`SnapshotStore`] keeps the last known content of each file, starting
+//! from the files that existed when watching began, so the first change to
+//! such a file diffs against what it held before. Large files are only
+//! hashed, and the least recently used files are forgotten once the size
+//! limit is reached.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Default number of snapshots kept
+pub const DEFAULT_MAX_SNAPSHOTS: usize = 200;
+/// Default total size of retained snapshots
+pub const DEFAULT_MAX_SNAPSHOT_BYTES: usize = 32 * 1024 * 1024;
+
+/// Default total size of the baselines kept by a [`SnapshotStore`]
+pub const DEFAULT_MAX_BASELINE_BYTES: usize = 64 * 1024 * 1024;
+/// Default size above which a file's baseline is only its hash
+pub const DEFAULT_MAX_BASELINE_FILE_BYTES: usize = 1024 * 1024;
+
+/// Bytes counted for each file on top of its content, so hash-only
+/// baselines are bounded too
+const BASELINE_ENTRY_BYTES: usize = 64;
+
+/// Identifies an event of the log by file and time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotKey {
+    pub path: PathBuf,
+    pub timestamp: SystemTime,
+}
+
+impl SnapshotKey {
+    pub fn new(path: PathBuf, timestamp: SystemTime) -> Self {
+        Self { path, timestamp }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    key: SnapshotKey,
+    content: Arc<str>,
+}
+
+/// File content captured at each event, identified by path and timestamp
+#[derive(Debug, Clone)]
+pub struct ContentSnapshots {
+    snapshots: VecDeque<Snapshot>,
+    max_snapshots: usize,
+    max_bytes: usize,
+    bytes: usize,
+}
+
+impl Default for ContentSnapshots {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_SNAPSHOTS, DEFAULT_MAX_SNAPSHOT_BYTES)
+    }
+}
+
+impl ContentSnapshots {
+    pub fn new(max_snapshots: usize, max_bytes: usize) -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+            max_snapshots,
+            max_bytes,
+            bytes: 0,
+        }
+    }
+
+    /// Retain `content` for the event identified by `key`
+    pub fn insert(&mut self, key: SnapshotKey, content: Arc<str>) {
+        if self.max_snapshots == 0 || content.len() > self.max_bytes {
+            return;
+        }
+
+        self.bytes += content.len();
+        self.snapshots.push_back(Snapshot { key, content });
+
+        while self.snapshots.len() > self.max_snapshots || self.bytes > self.max_bytes {
+            match self.snapshots.pop_front() {
+                Some(evicted) => self.bytes -= evicted.content.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Content captured for the event identified by `key`, if still retained
+    pub fn get(&self, key: &SnapshotKey) -> Option<&str> {
+        self.snapshots
+            .iter()
+            .rev()
+            .find(|s| s.key == *key)
+            .map(|s| s.content.as_ref())
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Total size of retained content in bytes
+    pub fn total_bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+/// Hash of file content, as compared by [`SnapshotStore`]
+pub fn content_hash(content: &str) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// What a file held when last seen
+#[derive(Debug, Clone, PartialEq)]
+pub struct Baseline {
+    pub hash: u64,
+    /// The content, unless the file was over the size threshold
+    pub content: Option<Arc<str>>,
+}
+
+impl Baseline {
+    fn new(content: &str, max_file_bytes: usize) -> Self {
+        Self {
+            hash: content_hash(content),
+            content: (content.len() <= max_file_bytes).then(|| Arc::from(content)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BaselineEntry {
+    latest: Baseline,
+    /// Content when watching began, for files that existed then
+    original: Option<Arc<str>>,
+    /// Position in the store's recency order
+    used: u64,
+}
+
+impl BaselineEntry {
+    fn bytes(&self, path: &Path) -> usize {
+        let latest = self.latest.content.as_ref();
+        // The original is usually the same content as the latest, shared
+        let original = self
+            .original
+            .as_ref()
+            .filter(|original| !latest.is_some_and(|latest| Arc::ptr_eq(original, latest)));
+        BASELINE_ENTRY_BYTES
+            + path.as_os_str().len()
+            + latest.map_or(0, |content| content.len())
+            + original.map_or(0, |content| content.len())
+    }
+}
+
+/// Last known content of each watched file, bounded in size
+#[derive(Debug, Clone)]
+pub struct SnapshotStore {
+    entries: HashMap<PathBuf, BaselineEntry>,
+    /// Paths by when they were last recorded or looked up, oldest first
+    recency: BTreeMap<u64, PathBuf>,
+    next_use: u64,
+    max_bytes: usize,
+    max_file_bytes: usize,
+    bytes: usize,
+}
+
+impl Default for SnapshotStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_BASELINE_BYTES, DEFAULT_MAX_BASELINE_FILE_BYTES)
+    }
+}
+
+impl SnapshotStore {
+    /// Store of at most `max_bytes`, keeping the content of files up to
+    /// `max_file_bytes` and only the hash of larger ones
+    pub fn new(max_bytes: usize, max_file_bytes: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            next_use: 0,
+            max_bytes,
+            max_file_bytes,
+            bytes: 0,
+        }
+    }
+
+    /// Record the content a file had when watching began
+    pub fn seed(&mut self, path: PathBuf, content: &str) {
+        let latest = Baseline::new(content, self.max_file_bytes);
+        let original = latest.content.clone();
+        self.insert(path, latest, original);
+    }
+
+    /// Record the content of `path` after a change, returning what it held before
+    pub fn record(&mut self, path: &Path, content: &str) -> Option<Baseline> {
+        let latest = Baseline::new(content, self.max_file_bytes);
+        match self.take(path) {
+            Some(entry) => {
+                self.insert(path.to_path_buf(), latest, entry.original);
+                Some(entry.latest)
+            }
+            None => {
+                self.insert(path.to_path_buf(), latest, None);
+                None
+            }
+        }
+    }
+
+    /// What `path` held when last seen
+    pub fn latest(&mut self, path: &Path) -> Option<&Baseline> {
+        let used = self.next_use;
+        let entry = self.entries.get_mut(path)?;
+        self.recency.remove(&entry.used);
+        self.recency.insert(used, path.to_path_buf());
+        entry.used = used;
+        self.next_use += 1;
+        Some(&entry.latest)
+    }
+
+    /// Content of `path` when watching began, if it existed then, was small
+    /// enough to keep and has not been evicted
+    pub fn get_baseline(&self, path: &Path) -> Option<Arc<str>> {
+        self.entries.get(path)?.original.clone()
+    }
+
+    /// Forget `path`, e.g. once it is deleted, returning what it last held
+    pub fn remove(&mut self, path: &Path) -> Option<Baseline> {
+        self.take(path).map(|entry| entry.latest)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Size counted against the limit, in bytes
+    pub fn total_bytes(&self) -> usize {
+        self.bytes
+    }
+
+    fn take(&mut self, path: &Path) -> Option<BaselineEntry> {
+        let entry = self.entries.remove(path)?;
+        self.recency.remove(&entry.used);
+        self.bytes -= entry.bytes(path);
+        Some(entry)
+    }
+
+    fn insert(&mut self, path: PathBuf, latest: Baseline, original: Option<Arc<str>>) {
+        self.take(&path);
+        let entry = BaselineEntry { latest, original, used: self.next_use };
+        self.next_use += 1;
+        self.bytes += entry.bytes(&path);
+        self.recency.insert(entry.used, path.clone());
+        self.entries.insert(path, entry);
+
+        while self.bytes > self.max_bytes {
+            let Some((_, oldest)) = self.recency.pop_first() else { break };
+            if let Some(evicted) = self.entries.remove(&oldest) {
+                self.bytes -= evicted.bytes(&oldest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(secs: u64) -> SnapshotKey {
+        SnapshotKey::new(PathBuf::from("a.rs"), SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    #[test]
+    fn test_evicts_oldest_by_count_and_size() {
+        let mut snapshots = ContentSnapshots::new(2, 10);
+
+        snapshots.insert(at(1), Arc::from("one"));
+        snapshots.insert(at(2), Arc::from("two"));
+        snapshots.insert(at(3), Arc::from("three"));
+        assert_eq!(snapshots.len(), 2);
+        assert!(snapshots.get(&at(1)).is_none());
+        assert_eq!(snapshots.get(&at(3)), Some("three"));
+
+        snapshots.insert(at(4), Arc::from("fourfour"));
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots.total_bytes(), 8);
+
+        // Content larger than the whole budget is never retained
+        snapshots.insert(at(5), Arc::from("far too large"));
+        assert!(snapshots.get(&at(5)).is_none());
+        assert_eq!(snapshots.get(&at(4)), Some("fourfour"));
+    }
+
+    #[test]
+    fn test_snapshot_store_keeps_baselines_within_budget() {
+        let path = |name: &str| PathBuf::from(name);
+        let entry = |name: &str, content: &str| BASELINE_ENTRY_BYTES + name.len() + content.len();
+        let mut store = SnapshotStore::new(2 * entry("a.rs", "one\n") + 2, 8);
+
+        store.seed(path("a.rs"), "one\n");
+        store.seed(path("b.rs"), "two\n");
+        assert_eq!(store.total_bytes(), 2 * entry("a.rs", "one\n"));
+
+        // A change returns the content before it; the baseline stays the original
+        let before = store.record(Path::new("a.rs"), "one!\n").unwrap();
+        assert_eq!(before.content.as_deref(), Some("one\n"));
+        assert_eq!(store.latest(Path::new("a.rs")).unwrap().content.as_deref(), Some("one!\n"));
+        assert_eq!(store.get_baseline(Path::new("a.rs")).as_deref(), Some("one\n"));
+
+        // Over budget: b.rs was used least recently
+        assert!(store.latest(Path::new("b.rs")).is_none());
+        assert_eq!(store.len(), 1);
+        store.seed(path("b.rs"), "two\n");
+        assert!(store.latest(Path::new("a.rs")).is_none());
+
+        // Files over the threshold keep only their hash; new files have no baseline
+        store.record(Path::new("big.rs"), "much too long\n");
+        let big = store.latest(Path::new("big.rs")).unwrap();
+        assert_eq!(big.hash, content_hash("much too long\n"));
+        assert!(big.content.is_none());
+        assert!(store.get_baseline(Path::new("big.rs")).is_none());
+
+        store.remove(Path::new("big.rs"));
+        store.remove(Path::new("b.rs"));
+        assert!(store.is_empty());
+        assert_eq!(store.total_bytes(), 0);
+    }
+}
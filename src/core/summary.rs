@@ -4,7 +4,7 @@
 //! of file changes, including statistics and aggregated views.
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 use std::time::{Duration, SystemTime};
 use serde::{Deserialize, Serialize};
 
@@ -19,6 +19,12 @@ pub struct ChangeSummaryStats {
     pub files_deleted: usize,
     pub files_moved: usize,
     pub total_changes: usize,
+    /// Changes to files a fresh agent intent file did not declare
+    #[serde(default)]
+    pub undeclared_changes: usize,
+    /// Secrets found on added lines across all changes
+    #[serde(default)]
+    pub secret_findings: usize,
     pub time_span: Duration,
     pub earliest_change: Option<SystemTime>,
     pub latest_change: Option<SystemTime>,
@@ -35,11 +41,46 @@ pub struct FileSummaryEntry {
     pub batch_id: Option<String>,
     pub change_count: usize, // Number of times this file was changed
     pub has_diff: bool,
+    /// Lines added and removed across all of the file's changes
+    #[serde(default)]
+    pub lines_added: usize,
+    #[serde(default)]
+    pub lines_removed: usize,
     pub preview: Option<String>,
     /// Reference to the most recent event for this file
     pub latest_event_idx: usize,
 }
 
+impl FileSummaryEntry {
+    /// Lines added plus lines removed
+    pub fn lines_changed(&self) -> usize {
+        self.lines_added + self.lines_removed
+    }
+}
+
+/// Changes aggregated over the files under one directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirRollup {
+    /// Directory relative to the rollup root; empty for the root itself
+    pub path: PathBuf,
+    pub files: usize,
+    pub changes: usize,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+impl DirRollup {
+    /// Lines added plus lines removed
+    pub fn lines_changed(&self) -> usize {
+        self.lines_added + self.lines_removed
+    }
+
+    /// Number of directory levels below the rollup root
+    pub fn depth(&self) -> usize {
+        self.path.components().count()
+    }
+}
+
 /// Time-based grouping options for summary
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SummaryTimeFrame {
@@ -60,6 +101,16 @@ pub enum SummaryGrouping {
     Chronological, // Show all changes in chronological order
 }
 
+/// Order of the summary's file list
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SummarySort {
+    /// Most recently changed first
+    #[default]
+    Recent,
+    /// Most lines added and removed first
+    Size,
+}
+
 /// Filter options for summary
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SummaryFilters {
@@ -69,6 +120,8 @@ pub struct SummaryFilters {
     pub exclude_origins: Vec<ChangeOrigin>,
     pub min_confidence: Option<ConfidenceLevel>,
     pub file_pattern: Option<String>, // Glob pattern for file paths
+    #[serde(default)]
+    pub sort: SummarySort,
 }
 
 /// Complete change summary
@@ -89,6 +142,7 @@ impl Default for SummaryFilters {
             exclude_origins: vec![],
             min_confidence: None,
             file_pattern: None,
+            sort: SummarySort::Recent,
         }
     }
 }
@@ -108,13 +162,8 @@ impl SummaryTimeFrame {
     /// Check if a timestamp falls within this time frame
     pub fn includes_time(&self, timestamp: SystemTime, now: SystemTime) -> bool {
         match self.duration() {
-            Some(duration) => {
-                if let Ok(elapsed) = now.duration_since(timestamp) {
-                    elapsed <= duration
-                } else {
-                    false
-                }
-            }
+            // Timestamps ahead of `now` (clock stepped back) count as just happened
+            Some(duration) => super::clock::elapsed_between(timestamp, now) <= duration,
             None => true, // All includes everything
         }
     }
@@ -131,6 +180,8 @@ impl ChangeSummary {
                 files_deleted: 0,
                 files_moved: 0,
                 total_changes: 0,
+                undeclared_changes: 0,
+                secret_findings: 0,
                 time_span: Duration::from_secs(0),
                 earliest_change: None,
                 latest_change: None,
@@ -143,8 +194,13 @@ impl ChangeSummary {
     
     /// Generate a summary from a collection of file events
     pub fn from_events(events: &[FileEvent], filters: &SummaryFilters) -> Self {
+        Self::from_events_at(events, filters, SystemTime::now())
+    }
+    
+    /// Generate a summary, evaluating time frames relative to `now`
+    pub fn from_events_at(events: &[FileEvent], filters: &SummaryFilters, now: SystemTime) -> Self {
         let mut summary = Self::new();
-        let now = SystemTime::now();
+        summary.generated_at = now;
         
         // Store filters as JSON
         if let Ok(filters_json) = serde_json::to_string(filters) {
@@ -205,6 +261,11 @@ impl ChangeSummary {
                 .max_by_key(|e| e.timestamp)
                 .unwrap(); // Safe because we know there's at least one event
                 
+            let (lines_added, lines_removed) = file_events
+                .iter()
+                .map(|event| event.diff_stats())
+                .fold((0, 0), |(added, removed), stats| (added + stats.lines_added, removed + stats.lines_removed));
+
             let entry = FileSummaryEntry {
                 path,
                 change_type: latest_event.kind.clone(),
@@ -214,6 +275,8 @@ impl ChangeSummary {
                 batch_id: latest_event.batch_id.clone(),
                 change_count: file_events.len(),
                 has_diff: latest_event.diff.is_some(),
+                lines_added,
+                lines_removed,
                 preview: latest_event.content_preview.clone()
                     .or_else(|| latest_event.diff.as_ref().and_then(|d| {
                         // Create a short preview from diff
@@ -230,12 +293,11 @@ impl ChangeSummary {
             summary.files.push(entry);
         }
         
-        // Sort files by most recent change
-        summary.files.sort_by(|a, b| b.changed_at.cmp(&a.changed_at));
-        
         // Calculate statistics
         summary.stats.total_files = summary.files.len();
         summary.stats.total_changes = filtered_events.len();
+        summary.stats.undeclared_changes = filtered_events.iter().filter(|event| event.undeclared).count();
+        summary.stats.secret_findings = filtered_events.iter().map(|event| event.secrets.len()).sum();
         
         for file in &summary.files {
             match file.change_type {
@@ -247,17 +309,28 @@ impl ChangeSummary {
         }
         
         // Calculate time span
-        if let (Some(first), Some(last)) = (summary.files.last(), summary.files.first()) {
-            summary.stats.earliest_change = Some(first.changed_at);
-            summary.stats.latest_change = Some(last.changed_at);
+        let earliest = summary.files.iter().map(|f| f.changed_at).min();
+        let latest = summary.files.iter().map(|f| f.changed_at).max();
+        if let (Some(earliest), Some(latest)) = (earliest, latest) {
+            summary.stats.earliest_change = Some(earliest);
+            summary.stats.latest_change = Some(latest);
             
-            if let Ok(duration) = last.changed_at.duration_since(first.changed_at) {
+            if let Ok(duration) = latest.duration_since(earliest) {
                 summary.stats.time_span = duration;
             }
         }
-        
+
+        summary.sort_files(filters.sort);
         summary
     }
+
+    /// Order the file list by `sort`, most recent change breaking ties
+    pub fn sort_files(&mut self, sort: SummarySort) {
+        match sort {
+            SummarySort::Recent => self.files.sort_by_key(|f| std::cmp::Reverse(f.changed_at)),
+            SummarySort::Size => self.files.sort_by_key(|f| (std::cmp::Reverse(f.lines_changed()), std::cmp::Reverse(f.changed_at))),
+        }
+    }
     
     /// Get files filtered by change type
     pub fn files_by_type(&self, change_type: &FileEventKind) -> Vec<&FileSummaryEntry> {
@@ -283,6 +356,36 @@ impl ChangeSummary {
             .collect()
     }
     
+    /// Aggregate files by their directory relative to `root`, the watch
+    /// root, cut off `depth` levels below it, most changed lines first.
+    /// Files outside `root` are left out, and files shallower than `depth`
+    /// count towards their own directory.
+    pub fn by_directory(&self, root: &Path, depth: usize) -> Vec<DirRollup> {
+        let mut rollups: HashMap<PathBuf, DirRollup> = HashMap::new();
+        for file in &self.files {
+            let Some(dir) = rollup_dir(root, &file.path, depth) else { continue };
+            let rollup = rollups.entry(dir.clone()).or_insert_with(|| DirRollup {
+                path: dir,
+                files: 0,
+                changes: 0,
+                lines_added: 0,
+                lines_removed: 0,
+            });
+            rollup.files += 1;
+            rollup.changes += file.change_count;
+            rollup.lines_added += file.lines_added;
+            rollup.lines_removed += file.lines_removed;
+        }
+
+        let mut rollups: Vec<DirRollup> = rollups.into_values().collect();
+        rollups.sort_by(|a, b| {
+            b.lines_changed().cmp(&a.lines_changed())
+                .then(b.changes.cmp(&a.changes))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        rollups
+    }
+
     /// Get summary of change types as percentages
     pub fn change_type_distribution(&self) -> HashMap<String, f32> {
         let mut distribution = HashMap::new();
@@ -303,6 +406,16 @@ impl ChangeSummary {
     }
 }
 
+/// Directory of `path` relative to `root`, keeping at most `depth` levels
+fn rollup_dir(root: &Path, path: &Path, depth: usize) -> Option<PathBuf> {
+    let relative = path.strip_prefix(root).ok()?;
+    let dir = relative.parent().unwrap_or(Path::new(""));
+    Some(dir.components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .take(depth)
+        .collect())
+}
+
 impl Default for ChangeSummary {
     fn default() -> Self {
         Self::new()
@@ -314,6 +427,7 @@ mod tests {
     use super::*;
     use crate::core::events::{ChangeOrigin, ChangeConfidence, ConfidenceLevel};
     use std::time::{SystemTime, Duration};
+    use std::path::Path;
 
     fn create_test_event(path: &str, kind: FileEventKind, origin: ChangeOrigin) -> FileEvent {
         FileEvent {
@@ -327,8 +441,26 @@ mod tests {
                 level: ConfidenceLevel::Safe,
                 score: 0.8,
                 reasons: vec!["Test".to_string()],
+                reason_impacts: vec![],
+                rule_hits: Vec::new(),
+                score_breakdown: None,
             }),
             batch_id: None,
+            over_budget: false,
+            content_hash: None,
+            long_lines: None,
+            coverage: None,
+            attribution_reason: None,
+            manifest_change: None,
+            container_path: None,
+            intent: None,
+            undeclared: false,
+            append_only: false,
+            encoding: None,
+            secrets: Vec::new(),
+            masked: false,
+            archive: None,
+            content: None,
         }
     }
 
@@ -356,7 +488,7 @@ mod tests {
         assert_eq!(summary.stats.files_modified, 2); // Both files show as modified (latest state)
         
         // Check that file1 shows change_count = 2
-        let file1_entry = summary.files.iter().find(|f| f.path == PathBuf::from("file1.rs"));
+        let file1_entry = summary.files.iter().find(|f| f.path == Path::new("file1.rs"));
         assert!(file1_entry.is_some());
         assert_eq!(file1_entry.unwrap().change_count, 2);
     }
@@ -370,8 +502,10 @@ mod tests {
         
         let events = vec![old_event, recent_event];
         
-        let mut filters = SummaryFilters::default();
-        filters.time_frame = SummaryTimeFrame::LastHour;
+        let filters = SummaryFilters {
+            time_frame: SummaryTimeFrame::LastHour,
+            ..SummaryFilters::default()
+        };
         
         let summary = ChangeSummary::from_events(&events, &filters);
         
@@ -387,8 +521,10 @@ mod tests {
                 ChangeOrigin::AIAgent { tool_name: "Claude".to_string(), process_id: Some(123) }),
         ];
         
-        let mut filters = SummaryFilters::default();
-        filters.include_origins = vec![ChangeOrigin::Human];
+        let filters = SummaryFilters {
+            include_origins: vec![ChangeOrigin::Human],
+            ..SummaryFilters::default()
+        };
         
         let summary = ChangeSummary::from_events(&events, &filters);
         
@@ -414,6 +550,79 @@ mod tests {
         assert_eq!(distribution.get("Deleted").unwrap(), &25.0); // 1/4 = 25%
     }
 
+    #[test]
+    fn test_sort_files_by_change_size() {
+        let mut events = vec![
+            create_test_event("small.rs", FileEventKind::Modified, ChangeOrigin::Human),
+            create_test_event("large.rs", FileEventKind::Modified, ChangeOrigin::Human),
+            create_test_event("medium.rs", FileEventKind::Modified, ChangeOrigin::Human),
+        ];
+        let diffs = ["+a", "+b\n+c\n-d\n-e", "+f\n-g"];
+        for (i, (event, diff)) in events.iter_mut().zip(diffs).enumerate() {
+            event.diff = Some(diff.to_string());
+            event.timestamp = SystemTime::now() - Duration::from_secs(10 - i as u64);
+        }
+        let names = |summary: &ChangeSummary| summary.files.iter().map(|f| f.path.to_string_lossy().into_owned()).collect::<Vec<_>>();
+
+        let filters = SummaryFilters { sort: SummarySort::Size, ..SummaryFilters::default() };
+        let mut summary = ChangeSummary::from_events(&events, &filters);
+        assert_eq!(names(&summary), ["large.rs", "medium.rs", "small.rs"]);
+        assert_eq!(summary.files[0].lines_changed(), 4);
+
+        summary.sort_files(SummarySort::Recent);
+        assert_eq!(names(&summary), ["medium.rs", "large.rs", "small.rs"]);
+
+        // The time span does not depend on the order the files are listed in
+        let summary = ChangeSummary::from_events(&events, &filters);
+        assert_eq!(summary.stats.earliest_change, Some(events[0].timestamp));
+        assert_eq!(summary.stats.latest_change, Some(events[2].timestamp));
+        assert_eq!(summary.stats.time_span, events[2].timestamp.duration_since(events[0].timestamp).unwrap());
+    }
+
+    #[test]
+    fn test_rollup_by_directory_depth() {
+        let mut events = vec![
+            create_test_event("/repo/src/a/one.rs", FileEventKind::Modified, ChangeOrigin::Human),
+            create_test_event("/repo/src/a/two.rs", FileEventKind::Modified, ChangeOrigin::Human),
+            create_test_event("/repo/src/b/three.rs", FileEventKind::Modified, ChangeOrigin::Human),
+            create_test_event("/repo/src/main.rs", FileEventKind::Modified, ChangeOrigin::Human),
+            create_test_event("/repo/README.md", FileEventKind::Modified, ChangeOrigin::Human),
+            create_test_event("/elsewhere/notes.md", FileEventKind::Modified, ChangeOrigin::Human),
+        ];
+        let root = Path::new("/repo");
+        let diffs = ["+a\n+b\n-c", "+d", "+e\n+f\n+g\n+h\n-i", "-j", "+k", "+l"];
+        for (event, diff) in events.iter_mut().zip(diffs) {
+            event.diff = Some(diff.to_string());
+        }
+        let summary = ChangeSummary::from_events(&events, &SummaryFilters::default());
+
+        let shallow = summary.by_directory(root, 1);
+        let rows: Vec<(&Path, usize, usize, usize)> = shallow
+            .iter()
+            .map(|dir| (dir.path.as_path(), dir.files, dir.lines_added, dir.lines_removed))
+            .collect();
+        assert_eq!(rows, [(Path::new("src"), 4, 7, 3), (Path::new(""), 1, 1, 0)]);
+
+        // One level down the two subdirectories split, most changed first,
+        // and a file directly in src stays with src
+        let deep = summary.by_directory(root, 2);
+        let rows: Vec<(&Path, usize, usize, usize)> = deep
+            .iter()
+            .map(|dir| (dir.path.as_path(), dir.files, dir.lines_added, dir.lines_removed))
+            .collect();
+        assert_eq!(rows, [
+            (Path::new("src/b"), 1, 4, 1),
+            (Path::new("src/a"), 2, 3, 1),
+            (Path::new(""), 1, 1, 0),
+            (Path::new("src"), 1, 0, 1),
+        ]);
+        assert_eq!(deep[0].depth(), 2);
+
+        // Paths under a subdirectory are taken relative to it
+        let under = summary.by_directory(&root.join("src"), 1);
+        assert_eq!(under.iter().map(|dir| dir.path.as_path()).collect::<Vec<_>>(), [Path::new("b"), Path::new("a"), Path::new("")]);
+    }
+
     #[test]
     fn test_files_by_type() {
         let events = vec![
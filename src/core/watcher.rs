@@ -1,188 +1,876 @@
+use std::collections::{HashSet, VecDeque};
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
-use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::time::{Duration, Instant, SystemTime};
+use notify::{Event, RecursiveMode, Watcher};
 use anyhow::{Result, Context};
-use super::{FileEvent, FileEventKind, filter::FileFilter};
-use super::events::AppEvent;
-use crate::ai::{AIDetector, ConfidenceScorer};
-use crate::config::WatchDiffConfig;
+use super::{ChangeOrigin, FileEvent, FileEventKind, filter::{FileFilter, DEFAULT_IGNORED_DIRS}, content::{preview_text, short_content_hash, ContentReader, TextEncoding}};
+use super::events::{AppEvent, ChangeError};
+use super::health::{spawn_watchdog, WatchHealth};
+use super::poll::StatPoller;
+use super::coalesce::{raw_changes, EventCoalescer, RawChange};
+use super::renames::{HeldDeletion, RenameDetector};
+use super::mounts::PathMapper;
+use super::git::{GitContext, GitHeadTracker};
+use super::snapshots::{content_hash, SnapshotStore};
+use crate::archive::{ArchiveChange, ArchiveListing, ArchiveTracker};
+use crate::ai::{AIDetector, BudgetTracker, ConfidenceScorer, IntentMatch, IntentTracker, ScoringContext};
+use crate::config::{WatchBackend, WatchDiffConfig, WatcherConfig};
+use crate::coverage::CoverageProvider;
+use crate::diff::pool::DiffPool;
+use crate::history::{HistoryRecord, Retention};
+use crate::security::SecretScanner;
+use crate::notifications::{EventSink, FocusSink, FocusSwitch, LogSink, Notification};
+
+/// Diffs by (old, new) content hash
+type DiffCache = std::collections::HashMap<(u64, u64), String>;
+
+/// A change whose file content has been read, waiting to be diffed
+enum PendingChange {
+    Created(String),
+    Modified { old_content: Option<Arc<str>>, new_content: String },
+    /// A file that appeared where a held deletion's content went
+    Moved { from: PathBuf, old_content: Option<Arc<str>>, new_content: String },
+    /// Nothing to diff: deletions and binary files
+    Ready(FileEventKind),
+    /// A watched archive whose entries changed
+    Archive { kind: FileEventKind, previous: Option<Arc<ArchiveListing>>, listing: Arc<ArchiveListing> },
+}
+
+/// How the watcher is detecting changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    /// The platform's file system notifications
+    Native,
+    /// Scanning the tree every poll interval; changes show up late
+    Polling,
+}
+
+impl BackendKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BackendKind::Native => "native",
+            BackendKind::Polling => "polling",
+        }
+    }
+}
+
+/// Limits on what [`FileWatcher::with_options`] watches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchOptions {
+    /// Deepest level watched, 1 being the root's own files; `None` for no limit
+    pub max_depth: Option<usize>,
+    /// Directory names skipped wherever they are
+    pub ignored_dirs: Vec<String>,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            ignored_dirs: DEFAULT_IGNORED_DIRS.iter().map(|dir| dir.to_string()).collect(),
+        }
+    }
+}
+
+impl WatchOptions {
+    /// Apply these limits to `config`
+    pub fn configure(self, config: &mut WatcherConfig) {
+        config.max_depth = self.max_depth;
+        config.ignored_dirs = self.ignored_dirs;
+    }
+}
+
+/// A started change source and what it is; dropping it stops watching
+struct Backend {
+    watcher: Box<dyn Send>,
+    kind: BackendKind,
+    /// Why native events couldn't be used, when `auto` fell back to polling
+    fallback_reason: Option<String>,
+}
+
+impl Backend {
+    fn start(path: &Path, tx: Sender<notify::Result<Event>>, config: &WatcherConfig, filter: &FileFilter) -> Result<Self> {
+        let native = || -> notify::Result<Box<dyn Send>> {
+            let mut watcher = notify::recommended_watcher(tx.clone())?;
+            watcher.watch(path, RecursiveMode::Recursive)?;
+            Ok(Box::new(watcher))
+        };
+        let polling = || -> Result<Box<dyn Send>> {
+            Ok(Box::new(StatPoller::start(path, config.poll_interval(), filter, tx.clone())?))
+        };
+
+        match config.backend {
+            WatchBackend::Native => Ok(Self {
+                watcher: native().context("Failed to start watching directory")?,
+                kind: BackendKind::Native,
+                fallback_reason: None,
+            }),
+            WatchBackend::Polling => Ok(Self { watcher: polling()?, kind: BackendKind::Polling, fallback_reason: None }),
+            WatchBackend::Auto => match native() {
+                Ok(watcher) => Ok(Self { watcher, kind: BackendKind::Native, fallback_reason: None }),
+                Err(err) => {
+                    tracing::warn!("Native file events unavailable ({}), polling instead", err);
+                    Ok(Self { watcher: polling()?, kind: BackendKind::Polling, fallback_reason: Some(err.to_string()) })
+                }
+            },
+        }
+    }
+}
 
 pub struct FileWatcher {
-    _watcher: RecommendedWatcher,
+    _watcher: Box<dyn Send>,
+    backend: BackendKind,
+    poll_interval: Duration,
+    fallback_reason: Option<String>,
     event_rx: Receiver<AppEvent>,
     filter: FileFilter,
+    budget: Arc<Mutex<BudgetTracker>>,
+    health: Option<Arc<Mutex<WatchHealth>>>,
+    /// Branch and commit of the watched repository, with --git-context
+    git: Option<Arc<Mutex<GitHeadTracker>>>,
+    /// Files last modified before this are left out of the initial scan
+    modified_since: Option<SystemTime>,
+    /// Last known content of each file, seeded with the files present at startup
+    snapshots: Arc<Mutex<SnapshotStore>>,
+    /// Scanner for secrets to keep out of saved baselines, unless revealed
+    baseline_secrets: Option<Arc<SecretScanner>>,
+    /// Focus mode of the notifications sent for changes
+    focus: FocusSwitch,
 }
 
+/// How often held notifications are checked for a digest when no changes arrive
+const FOCUS_TICK: Duration = Duration::from_secs(1);
+
 impl FileWatcher {
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
         Self::with_config(path, WatchDiffConfig::default())
     }
     
+    /// Watch `path` with the default config, detecting changes with
+    /// `backend`. Polling stats every file each `poll_interval`, at least a
+    /// millisecond apart, for network file systems and bind mounts that
+    /// deliver no native events.
+    pub fn with_backend<P: AsRef<Path>>(path: P, backend: WatchBackend, poll_interval: Duration) -> Result<Self> {
+        let mut config = WatchDiffConfig::default();
+        config.watcher.backend = backend;
+        config.watcher.poll_interval_ms = (poll_interval.as_millis() as u64).max(1);
+        Self::with_config(path, config)
+    }
+
+    /// Watch `path` with the default config, within the limits of `options`
+    pub fn with_options<P: AsRef<Path>>(path: P, options: WatchOptions) -> Result<Self> {
+        let mut config = WatchDiffConfig::default();
+        options.configure(&mut config.watcher);
+        Self::with_config(path, config)
+    }
+
+    /// Watch `path` with the default config, releasing a burst of changes to
+    /// a file as one event once it has been quiet for `debounce`
+    pub fn with_debounce<P: AsRef<Path>>(path: P, debounce: Duration) -> Result<Self> {
+        let mut config = WatchDiffConfig::default();
+        config.watcher.event_debounce_ms = debounce.as_millis() as u64;
+        Self::with_config(path, config)
+    }
+
     pub fn with_config<P: AsRef<Path>>(path: P, config: WatchDiffConfig) -> Result<Self> {
         let path = path.as_ref();
-        let filter = FileFilter::new(path)?;
+        let mut filter = FileFilter::new(path)?;
+        filter.set_respect_gitignore(config.watcher.gitignore);
+        filter.set_max_depth(config.watcher.max_depth);
+        filter.set_ignored_dirs(&config.watcher.ignored_dirs);
+        for pattern in &config.watcher.include {
+            filter.add_include_glob(pattern)?;
+        }
+        for pattern in &config.watcher.exclude {
+            filter.add_exclude_glob(pattern)?;
+        }
         
         let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
         let (event_tx, event_rx) = mpsc::channel::<AppEvent>();
 
-        // Create the notify watcher
-        let mut watcher = notify::recommended_watcher(tx)
-            .context("Failed to create file system watcher")?;
+        let backend = Backend::start(path, tx, &config.watcher, &filter)?;
+        let poll_interval = config.watcher.poll_interval();
 
-        watcher
-            .watch(path, RecursiveMode::Recursive)
-            .context("Failed to start watching directory")?;
+        let filter_clone = filter.clone();
+        let config_clone = Arc::new(config.clone());
+        let content_reader = ContentReader::new(config.watcher.max_diff_size_bytes);
+        let budget = Arc::new(Mutex::new(BudgetTracker::new(config.budgets.clone())));
+        let budget_clone = Arc::clone(&budget);
 
-        let filter_clone = FileFilter::new(path)?;
-        let config_clone = config.clone();
+        let health = config
+            .watcher
+            .expect_activity_duration()
+            .map(|expect| Arc::new(Mutex::new(WatchHealth::new(expect, Instant::now()))));
+        if let Some(ref health) = health {
+            spawn_watchdog(path.to_path_buf(), health);
+        }
+        let health_clone = health.clone();
+        let snapshots = Arc::new(Mutex::new(SnapshotStore::new(
+            config.cache.max_baseline_bytes,
+            config.cache.max_baseline_file_bytes,
+        )));
+        let snapshots_clone = Arc::clone(&snapshots);
+        let git = config
+            .watcher
+            .git_context
+            .then(|| GitHeadTracker::discover(path))
+            .flatten()
+            .map(|git| Arc::new(Mutex::new(git)));
+        let git_clone = git.clone();
+        let branch_tx = event_tx.clone();
+        let mut coverage = CoverageProvider::from_config(&config.coverage, path)?;
+        let mounts = PathMapper::from_config(&config.mounts)?;
+        let mut intents = IntentTracker::from_config(&config.intent, path);
+        let mut history = match config.history.enabled {
+            true => {
+                let mut store = crate::history::open(&config.history, path)?;
+                let dropped = store.prune(&Retention::from_config(&config.history), chrono::Utc::now())?;
+                if dropped > 0 {
+                    tracing::info!("Dropped {} change(s) from the history beyond its retention", dropped);
+                }
+                Some(store)
+            }
+            false => None,
+        };
+        // Events carry absolute paths, so records are made relative to the absolute root
+        let history_root = std::env::current_dir().map_or_else(|_| path.to_path_buf(), |cwd| cwd.join(path));
+        let history_diffs = config.history.include_diffs;
+        let secrets = match config.security.scan {
+            true => Some(Arc::new(SecretScanner::from_config(&config.security, path)?)),
+            false => None,
+        };
+        let reveal_secrets = config.security.reveal;
+        // Saved baselines are kept free of secrets the same way events are
+        let baseline_secrets = secrets.clone().filter(|_| !reveal_secrets);
+        let mut archives = ArchiveTracker::from_config(&config.archive, path)?;
+
+        // Diffs are computed on a bounded pool; events come back in the order they happened
+        let (mut diff_pool, mut diffed_events) = DiffPool::<Result<FileEvent, ChangeError>>::new(config.watcher.diff_workers);
+        let strict = config.watcher.strict;
+
+        // Notifications wait for the digest while focus mode or quiet hours are on
+        let focus = FocusSwitch::default();
+        let mut notification_sink = FocusSink::new(LogSink, &config.notifications)?.with_switch(focus.clone());
+
+        // Score, budget-check and deliver events as their diffs complete
+        let confidence_scorer = ConfidenceScorer::with_config(&config.ai)?
+            .with_coverage_config(&config.coverage)
+            .with_root(&history_root);
+        thread::spawn(move || {
+            loop {
+                let result = match diffed_events.recv_timeout(FOCUS_TICK) {
+                    Ok(result) => result,
+                    Err(RecvTimeoutError::Timeout) => {
+                        // Send the digest once focus mode is turned off or quiet hours end
+                        if let Err(err) = notification_sink.tick() {
+                            tracing::warn!("Failed to deliver notification digest: {}", err);
+                        }
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                let mut fe = match result {
+                    Ok(fe) => fe,
+                    Err(error) => {
+                        tracing::warn!("Cannot diff {}", error);
+                        if event_tx.send(AppEvent::Error(error)).is_err() {
+                            return; // Receiver dropped, exit thread
+                        }
+                        continue;
+                    }
+                };
+                if let Some(container_path) = mounts.as_ref().and_then(|mounts| mounts.to_container(&fe.path)) {
+                    fe = fe.with_container_path(container_path);
+                }
+                // Match the change against the files an agent declared it would touch
+                if let Err(err) = intents.refresh() {
+                    tracing::warn!("Failed to load intent file: {:#}", err);
+                }
+                match intents.check(&fe.path, fe.timestamp) {
+                    IntentMatch::Declared(reason) => fe = fe.with_intent(reason),
+                    IntentMatch::Undeclared => fe.undeclared = fe.origin != ChangeOrigin::Human,
+                    IntentMatch::NoIntent => {}
+                }
+                // Batches paused for exceeding a budget are only tagged until acknowledged
+                let mut budget = budget_clone.lock().unwrap_or_else(|e| e.into_inner());
+                if budget.is_paused(fe.batch_id.as_deref()) {
+                    fe.over_budget = true;
+                } else if let Some(diff) = fe.diff.clone() {
+                    // Look up test coverage of the changed lines, reloading a rewritten report
+                    if let Some(ref mut coverage) = coverage {
+                        if let Err(err) = coverage.refresh() {
+                            tracing::warn!("Failed to reload coverage report: {:#}", err);
+                        }
+                        let change_coverage = coverage.change_coverage(&fe.path, &diff);
+                        fe = fe.with_coverage(change_coverage);
+                    }
+                    // Score confidence if we have diff content
+                    let context = ScoringContext {
+                        coverage: fe.coverage.as_ref(),
+                        manifest: fe.manifest_change.as_ref(),
+                        append_only: fe.append_only,
+                    };
+                    let confidence = confidence_scorer.score_change_in_context(&diff, &fe.path, context);
+                    fe = fe.with_confidence(confidence);
+                }
+                let alerts = budget.record(&fe);
+                drop(budget);
+
+                // Secrets are masked before the event reaches history or any output
+                if let Some(ref secrets) = secrets {
+                    secrets.check(&mut fe, reveal_secrets);
+                }
+
+                if let Some(ref mut history) = history {
+                    if let Err(err) = history.append(&HistoryRecord::from_event(&fe, &history_root, history_diffs)) {
+                        tracing::warn!("Failed to record change history: {:#}", err);
+                    }
+                }
+
+                // Critical findings are always announced, whatever the budget or score
+                let critical = Notification::critical_finding(&fe);
+                if event_tx.send(AppEvent::FileChanged(fe)).is_err() {
+                    break; // Receiver dropped, exit thread
+                }
+                if let Some(notification) = critical {
+                    if let Err(err) = notification_sink.send(&notification) {
+                        tracing::warn!("Failed to deliver critical finding notification: {}", err);
+                    }
+                }
+
+                for alert in alerts {
+                    if let Err(err) = notification_sink.send(&Notification::from(&alert)) {
+                        tracing::warn!("Failed to deliver budget notification: {}", err);
+                    }
+                    if event_tx.send(AppEvent::BudgetExceeded(alert)).is_err() {
+                        return; // Receiver dropped, exit thread
+                    }
+                }
+            }
+        });
 
         // Spawn background thread to process notify events
         thread::spawn(move || {
-            let mut previous_contents = std::collections::HashMap::<PathBuf, String>::new();
-            let mut last_event_time = std::collections::HashMap::<PathBuf, std::time::Instant>::new();
+            // Deletions wait a moment for their file to reappear under another name
+            let mut renames = RenameDetector::new(config_clone.watcher.rename_window());
+            // Record what the files held before watching began; changes arriving meanwhile are handled after
+            let mut early = Self::seed_snapshots(&filter_clone, &content_reader, &snapshots_clone, &mut renames, &rx);
+            if let Some(ref mut archives) = archives {
+                Self::seed_archives(&filter_clone, archives);
+            }
             let mut ai_detector = AIDetector::new();
-            let confidence_scorer = ConfidenceScorer::new();
             
-            // Diff cache: (old_hash, new_hash) -> diff_result
-            let mut diff_cache = std::collections::HashMap::<(u64, u64), String>::new();
-            let cache_size_limit = config_clone.cache.diff_cache_size;
-            let debounce_duration = config_clone.watcher.event_debounce_duration();
-
-            while let Ok(result) = rx.recv() {
-                match result {
-                    Ok(event) => {
-                        // Debounce rapid events on the same path
-                        let now = std::time::Instant::now();
-                        
-                        for path in event.paths {
+            // Diff cache shared by the workers: (old_hash, new_hash) -> diff_result
+            let diff_cache = Arc::new(Mutex::new(DiffCache::new()));
+            // Bursts on a path are folded into one change once it goes quiet
+            let mut coalescer = EventCoalescer::new(config_clone.watcher.event_debounce_duration());
+
+            loop {
+                let now = Instant::now();
+                let received = match (early.pop_front(), coalescer.next_due(now).into_iter().chain(renames.next_due(now)).min()) {
+                    (Some(event), _) => Ok(event),
+                    (None, Some(wait)) => rx.recv_timeout(wait),
+                    (None, None) => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+                let disconnected = match received {
+                    Ok(Ok(event)) => {
+                        let now = Instant::now();
+                        if let Some(ref health) = health_clone {
+                            health.lock().unwrap_or_else(|e| e.into_inner()).record_event(now);
+                        }
+
+                        for (path, raw) in raw_changes(&event) {
+                            // Checkouts are reported, other changes under .git are ignored
+                            if let Some(ref git) = git_clone {
+                                let mut git = git.lock().unwrap_or_else(|e| e.into_inner());
+                                if git.is_head_path(&path) {
+                                    if let Some(change) = git.refresh() {
+                                        if branch_tx.send(AppEvent::BranchChanged(change)).is_err() {
+                                            return;
+                                        }
+                                    }
+                                    continue;
+                                }
+                            }
+
                             // Filter out ignored files
-                            if !filter_clone.should_watch(&path) {
-                                continue;
+                            if filter_clone.should_watch(&path) {
+                                coalescer.add(path, raw, now);
                             }
-                            
-                            // Debounce: ignore events that happen too quickly after the previous one
-                            if let Some(last_time) = last_event_time.get(&path) {
-                                if now.duration_since(*last_time) < debounce_duration {
-                                    continue;  // Skip this event as it's too soon
+                        }
+                        false
+                    }
+                    Ok(Err(err)) => {
+                        tracing::error!("File watcher error: {}", err);
+                        false
+                    }
+                    Err(RecvTimeoutError::Timeout) => false,
+                    Err(RecvTimeoutError::Disconnected) => true,
+                };
+
+                // Deletions nobody claimed in time are deletions after all
+                if !Self::submit_deletions(renames.take_expired(Instant::now()), &mut ai_detector, &mut diff_pool) {
+                    return;
+                }
+
+                // Changes still waiting are delivered when watching stops
+                let ready = match disconnected {
+                    true => coalescer.drain(),
+                    false => coalescer.take_ready(Instant::now()),
+                };
+                for (path, raw) in ready {
+                    // Watched archives are listed instead of read as text
+                    let archive_kind = match raw {
+                        RawChange::Created => Some(FileEventKind::Created),
+                        RawChange::Modified => Some(FileEventKind::Modified),
+                        RawChange::Removed => None,
+                    };
+                    let archive = match (archives.as_mut(), archive_kind) {
+                        (Some(archives), Some(kind)) if archives.matches(&path) => match archives.record(&path) {
+                            // Skip if no entry changed
+                            Ok((Some(previous), listing)) if previous.entries == listing.entries => continue,
+                            Ok((previous, listing)) => Some(PendingChange::Archive { kind, previous, listing }),
+                            Err(err) if strict => {
+                                let error = ChangeError { path: path.clone(), cause: format!("{:#}", err) };
+                                if !diff_pool.submit_ready(Err(error)) {
+                                    return;
                                 }
+                                continue;
                             }
-                            last_event_time.insert(path.clone(), now);
-
-                            let file_event = match event.kind {
-                                notify::EventKind::Create(_) => {
-                                    let mut fe = FileEvent::new(path.clone(), FileEventKind::Created);
-                                    
-                                    // For new files, read content for preview
-                                    if filter_clone.is_text_file(&path) {
-                                        if let Ok(content) = std::fs::read_to_string(&path) {
-                                            let preview = if content.len() > 200 {
-                                                format!("{}...", &content[..200])
-                                            } else {
-                                                content.clone()
-                                            };
-                                            fe = fe.with_preview(preview);
-                                            previous_contents.insert(path.clone(), content);
-                                        }
+                            Err(err) => {
+                                tracing::warn!("Cannot list archive {}: {:#}", path.display(), err);
+                                None
+                            }
+                        },
+                        _ => None,
+                    };
+
+                    // File contents are read here, in event order; diffing them is left to the pool
+                    let content = match raw {
+                        RawChange::Created | RawChange::Modified if archive.is_none() => {
+                            let read = Self::read_changed_text(&filter_clone, &content_reader, &path);
+                            match Self::changed_content(strict, &path, read) {
+                                Ok(content) => content,
+                                Err(error) => {
+                                    if !diff_pool.submit_ready(Err(error)) {
+                                        return;
                                     }
-                                    Some(fe)
+                                    continue;
                                 }
-                                notify::EventKind::Modify(_) => {
-                                    let mut fe = FileEvent::new(path.clone(), FileEventKind::Modified);
-                                    
-                                    // Generate diff for modified files
-                                    if filter_clone.is_text_file(&path) {
-                                        if let Ok(new_content) = std::fs::read_to_string(&path) {
-                                            if let Some(old_content) = previous_contents.get(&path) {
-                                                // Skip if content hasn't actually changed
-                                                if *old_content == new_content {
-                                                    continue;
-                                                }
-                                                
-                                                // Use hash-based diff caching
-                                                let old_hash = Self::hash_content(old_content);
-                                                let new_hash = Self::hash_content(&new_content);
-                                                let cache_key = (old_hash, new_hash);
-                                                
-                                                let diff = if let Some(cached_diff) = diff_cache.get(&cache_key) {
-                                                    // Use cached diff
-                                                    cached_diff.clone()
-                                                } else {
-                                                    // Generate new diff and cache it
-                                                    let new_diff = crate::diff::generate_unified_diff(old_content, &new_content, &path, &path);
-                                                    diff_cache.insert(cache_key, new_diff.clone());
-                                                    
-                                                    // Limit cache size to prevent memory growth
-                                                    if diff_cache.len() > cache_size_limit {
-                                                        // Clear cache when it exceeds limit
-                                                        let cleanup_threshold = (cache_size_limit as f32 * config_clone.cache.cleanup_threshold) as usize;
-                                                        if diff_cache.len() > cleanup_threshold {
-                                                            diff_cache.clear();
-                                                        }
-                                                    }
-                                                    
-                                                    new_diff
-                                                };
-                                                
-                                                fe = fe.with_diff(diff);
-                                            } else {
-                                                // First time seeing this file - show a preview instead of empty diff
-                                                let preview = if new_content.len() > 200 {
-                                                    format!("{}...", &new_content[..200])
-                                                } else {
-                                                    new_content.clone()
-                                                };
-                                                fe = fe.with_preview(preview);
-                                            }
-                                            previous_contents.insert(path.clone(), new_content);
-                                        }
+                            }
+                        }
+                        _ => None,
+                    };
+                    let mut encoding = None;
+                    let identity = match raw {
+                        RawChange::Created | RawChange::Modified if archive.is_none() => renames.observe(&path),
+                        _ => None,
+                    };
+                    let change = match raw {
+                        _ if archive.is_some() => archive,
+                        RawChange::Created => {
+                            // For new text files, show the content itself (or an all-added diff)
+                            match content {
+                                Some((content, detected)) => {
+                                    encoding = (detected != TextEncoding::Utf8).then_some(detected);
+                                    snapshots_clone.lock().unwrap_or_else(|e| e.into_inner()).record(&path, &content);
+                                    match renames.claim(&path, Some(&content), identity) {
+                                        Some(held) => Self::reappeared(&path, held, Some(content)),
+                                        None => Some(PendingChange::Created(content)),
                                     }
-                                    Some(fe)
-                                }
-                                notify::EventKind::Remove(_) => {
-                                    previous_contents.remove(&path);
-                                    Some(FileEvent::new(path.clone(), FileEventKind::Deleted))
                                 }
-                                _ => None,
-                            };
-
-                            if let Some(mut fe) = file_event {
-                                // Detect change origin using AI detector
-                                let origin = ai_detector.detect_change_origin();
-                                fe = fe.with_origin(origin.clone());
-
-                                // Detect batch changes
-                                if let Some(batch_id) = ai_detector.detect_batch_change(&path, &origin) {
-                                    fe = fe.with_batch_id(batch_id);
+                                None => match renames.claim(&path, None, identity) {
+                                    Some(held) => Self::reappeared(&path, held, None),
+                                    None => Some(PendingChange::Ready(FileEventKind::Created)),
+                                },
+                            }
+                        }
+                        RawChange::Modified => {
+                            match content {
+                                Some((new_content, detected)) => {
+                                    encoding = (detected != TextEncoding::Utf8).then_some(detected);
+                                    let previous = snapshots_clone.lock().unwrap_or_else(|e| e.into_inner()).record(&path, &new_content);
+                                    match previous {
+                                        // Skip if content hasn't actually changed
+                                        Some(previous) if previous.hash == content_hash(&new_content) => None,
+                                        // A file first seen here may be the target of a rename
+                                        None => match renames.claim(&path, Some(&new_content), identity) {
+                                            Some(held) => Self::reappeared(&path, held, Some(new_content)),
+                                            None => Some(PendingChange::Modified { old_content: None, new_content }),
+                                        },
+                                        previous => Some(PendingChange::Modified {
+                                            old_content: previous.and_then(|previous| previous.content),
+                                            new_content,
+                                        }),
+                                    }
                                 }
+                                // A binary file renamed into place is still the same file on disk
+                                None => match renames.claim(&path, None, identity) {
+                                    Some(held) => Self::reappeared(&path, held, None),
+                                    None => Some(PendingChange::Ready(FileEventKind::Modified)),
+                                },
+                            }
+                        }
+                        RawChange::Removed => {
+                            let baseline = snapshots_clone.lock().unwrap_or_else(|e| e.into_inner()).remove(&path);
+                            if let Some(ref mut archives) = archives {
+                                archives.remove(&path);
+                            }
+                            // Only files known by content or identity can be matched up with their move
+                            if renames.hold(path.clone(), baseline, Instant::now()) {
+                                continue;
+                            }
+                            Some(PendingChange::Ready(FileEventKind::Deleted))
+                        }
+                    };
+                    let Some(change) = change else { continue };
 
-                                // Score confidence if we have diff content
-                                if let Some(ref diff) = fe.diff {
-                                    let confidence = confidence_scorer.score_change(diff, &path);
-                                    fe = fe.with_confidence(confidence);
-                                }
+                    // Detect change origin using AI detector, while the change is fresh
+                    let attribute = Self::attribution(&mut ai_detector, &path, encoding);
 
-                                if event_tx.send(AppEvent::FileChanged(fe)).is_err() {
-                                    break; // Receiver dropped, exit thread
-                                }
-                            }
+                    let submitted = match change {
+                        PendingChange::Ready(kind) => diff_pool.submit_ready(Ok(attribute(FileEvent::new(path, kind)))),
+                        PendingChange::Created(content) => {
+                            let config = Arc::clone(&config_clone);
+                            diff_pool.submit(move || Self::run_diff_job(strict, &path, |path| {
+                                attribute(Self::created_file_event(path, &content, &config.watcher))
+                            }))
                         }
-                    }
-                    Err(err) => {
-                        tracing::error!("File watcher error: {}", err);
+                        PendingChange::Archive { kind, previous, listing } => {
+                            diff_pool.submit(move || Self::run_diff_job(strict, &path, |path| {
+                                attribute(Self::archive_event(path, kind, previous.as_deref(), &listing))
+                            }))
+                        }
+                        PendingChange::Modified { old_content, new_content } => {
+                            let diff_cache = Arc::clone(&diff_cache);
+                            let config = Arc::clone(&config_clone);
+                            diff_pool.submit(move || Self::run_diff_job(strict, &path, |path| {
+                                attribute(Self::modified_file_event(path, old_content.as_deref(), &new_content, &config, &diff_cache))
+                            }))
+                        }
+                        PendingChange::Moved { from, old_content, new_content } => {
+                            let config = Arc::clone(&config_clone);
+                            diff_pool.submit(move || Self::run_diff_job(strict, &path, |path| {
+                                attribute(Self::moved_file_event(&from, path, old_content.as_deref(), &new_content, &config))
+                            }))
+                        }
+                    };
+                    if !submitted {
+                        return; // Events are no longer delivered, exit thread
                     }
                 }
+                if disconnected {
+                    Self::submit_deletions(renames.drain(), &mut ai_detector, &mut diff_pool);
+                    break;
+                }
             }
         });
 
         Ok(Self {
-            _watcher: watcher,
+            _watcher: backend.watcher,
+            backend: backend.kind,
+            poll_interval,
+            fallback_reason: backend.fallback_reason,
             event_rx,
             filter,
+            budget,
+            health,
+            git,
+            modified_since: None,
+            snapshots,
+            baseline_secrets,
+            focus,
         })
     }
 
+    /// Tags for an event of `path`: its origin and batch, detected now while
+    /// the change is fresh, and the encoding of its content
+    fn attribution(ai_detector: &mut AIDetector, path: &Path, encoding: Option<TextEncoding>) -> impl FnOnce(FileEvent) -> FileEvent {
+        let (origin, reason) = ai_detector.detect_change_origin_with_reason();
+        let batch_id = ai_detector.detect_batch_change(path, &origin);
+        move |mut fe: FileEvent| {
+            fe = fe.with_origin(origin);
+            if let Some(encoding) = encoding {
+                fe = fe.with_encoding(encoding);
+            }
+            if let Some(reason) = reason {
+                fe = fe.with_attribution_reason(reason);
+            }
+            // Detect batch changes
+            if let Some(batch_id) = batch_id {
+                fe = fe.with_batch_id(batch_id);
+            }
+            fe
+        }
+    }
+
+    /// Deliver held deletions as deletion events, stamped with when they
+    /// happened. Returns false once events are no longer delivered.
+    fn submit_deletions(
+        deletions: Vec<HeldDeletion>,
+        ai_detector: &mut AIDetector,
+        diff_pool: &mut DiffPool<Result<FileEvent, ChangeError>>,
+    ) -> bool {
+        deletions.into_iter().all(|held| {
+            let attribute = Self::attribution(ai_detector, &held.path, None);
+            let mut fe = attribute(FileEvent::new(held.path, FileEventKind::Deleted));
+            fe.timestamp = held.timestamp;
+            diff_pool.submit_ready(Ok(fe))
+        })
+    }
+
+    /// Change for a file appearing at `path`, with `content` if it is text,
+    /// after `held` was deleted: a move, or a modification if it is the same
+    /// path again
+    fn reappeared(path: &Path, held: HeldDeletion, content: Option<String>) -> Option<PendingChange> {
+        let same_path = held.path == path;
+        let Some(content) = content else {
+            return Some(PendingChange::Ready(match same_path {
+                true => FileEventKind::Modified,
+                false => FileEventKind::Moved { from: held.path, to: path.to_path_buf() },
+            }));
+        };
+        let unchanged = held.baseline.as_ref().is_some_and(|baseline| baseline.hash == content_hash(&content));
+        let old_content = held.baseline.and_then(|baseline| baseline.content);
+        match same_path {
+            true if unchanged => None,
+            true => Some(PendingChange::Modified { old_content, new_content: content }),
+            false => Some(PendingChange::Moved { from: held.path, old_content, new_content: content }),
+        }
+    }
+
+    /// Record the content of every watchable text file in `snapshots`, and
+    /// which file on disk each file is in `renames`. Binary files and files
+    /// too large to diff only get the latter.
+    ///
+    /// Watching has already begun, so a file may change while this runs.
+    /// Events from `rx` are taken in between files and returned for handling
+    /// afterwards; a file with an event by the time it has been read gets no
+    /// baseline, since what was read may already be the changed content.
+    fn seed_snapshots(
+        filter: &FileFilter,
+        reader: &ContentReader,
+        snapshots: &Mutex<SnapshotStore>,
+        renames: &mut RenameDetector,
+        rx: &Receiver<notify::Result<Event>>,
+    ) -> VecDeque<notify::Result<Event>> {
+        let mut early = VecDeque::new();
+        let mut changed = HashSet::new();
+        let take_events = |early: &mut VecDeque<notify::Result<Event>>, changed: &mut HashSet<PathBuf>| {
+            while let Ok(event) = rx.try_recv() {
+                if let Ok(ref event) = event {
+                    changed.extend(raw_changes(event).into_iter().map(|(path, _)| path));
+                }
+                early.push_back(event);
+            }
+        };
+        let files = match filter.get_watchable_files() {
+            Ok(files) => files,
+            Err(err) => {
+                tracing::warn!("Failed to list files for diff baselines: {:#}", err);
+                return early;
+            }
+        };
+        // Events carry absolute paths, so the files are recorded by theirs
+        let cwd = std::env::current_dir().unwrap_or_default();
+        for path in files {
+            let absolute = cwd.join(&path);
+            renames.observe(&absolute);
+            let read = Self::read_changed_text(filter, reader, &path);
+            take_events(&mut early, &mut changed);
+            match read {
+                Ok(Some(_)) if changed.contains(&absolute) => {
+                    tracing::debug!("No diff baseline for {}: changed while starting", path.display())
+                }
+                Ok(Some((content, _))) => snapshots.lock().unwrap_or_else(|e| e.into_inner()).seed(absolute, &content),
+                Ok(None) => {}
+                Err(err) => tracing::debug!("No diff baseline for {}: {}", path.display(), err),
+            }
+        }
+        early
+    }
+
+    /// List every watched archive, so the first change to one is compared
+    /// with what it held at startup
+    fn seed_archives(filter: &FileFilter, archives: &mut ArchiveTracker) {
+        let Ok(files) = filter.get_watchable_files() else { return };
+        // Events carry absolute paths, so the archives are recorded by theirs
+        let cwd = std::env::current_dir().unwrap_or_default();
+        for path in files.into_iter().map(|path| cwd.join(path)) {
+            if archives.matches(&path) {
+                if let Err(err) = archives.record(&path) {
+                    tracing::debug!("No archive baseline for {}: {:#}", path.display(), err);
+                }
+            }
+        }
+    }
+
+    /// Content of a changed text file. Files that are not text by name, such
+    /// as images, are never read and are `Ok(None)`, so `--strict` does not
+    /// report them; nor files already removed again. Files that cannot be
+    /// read or decoded are errors.
+    fn read_changed_text(
+        filter: &FileFilter,
+        reader: &ContentReader,
+        path: &Path,
+    ) -> std::io::Result<Option<(String, TextEncoding)>> {
+        if !filter.is_text_file(path) {
+            return Ok(None);
+        }
+        match reader.read_text(path) {
+            Ok(content) => Ok(Some(content)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Content to diff for a changed file. A file that could not be read is an
+    /// error with `strict`, otherwise its event goes without a diff.
+    fn changed_content(
+        strict: bool,
+        path: &Path,
+        read: std::io::Result<Option<(String, TextEncoding)>>,
+    ) -> Result<Option<(String, TextEncoding)>, ChangeError> {
+        match read {
+            Ok(content) => Ok(content),
+            Err(err) if strict => Err(ChangeError { path: path.to_path_buf(), cause: err.to_string() }),
+            Err(err) => {
+                tracing::debug!("Skipping diff of {}: {}", path.display(), err);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Run a diff job for `path`. With `strict`, a job that panics becomes an
+    /// error; otherwise the pool drops its event.
+    fn run_diff_job(strict: bool, path: &Path, job: impl FnOnce(&Path) -> FileEvent) -> Result<FileEvent, ChangeError> {
+        if !strict {
+            return Ok(job(path));
+        }
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| job(path))).map_err(|payload| {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown cause".to_string());
+            ChangeError { path: path.to_path_buf(), cause: format!("diff failed: {}", message) }
+        })
+    }
+
+    /// Event for a modified text file: its diff against `old_content`, or a
+    /// preview the first time the file is seen
+    fn modified_file_event(
+        path: &Path,
+        old_content: Option<&str>,
+        new_content: &str,
+        config: &WatchDiffConfig,
+        diff_cache: &Mutex<DiffCache>,
+    ) -> FileEvent {
+        let mut fe = FileEvent::new(path.to_path_buf(), FileEventKind::Modified);
+        if let Some(old_content) = old_content {
+            let diff = match crate::diff::generate_append_diff(old_content, new_content, path, path) {
+                // Appends to logs and the like need no diff algorithm
+                Some(append_diff) => {
+                    fe.append_only = true;
+                    append_diff
+                }
+                None => Self::cached_diff(path, old_content, new_content, config, diff_cache),
+            };
+
+            fe = fe.with_bounded_diff(&diff, config.watcher.max_line_chars);
+            if let Some(manifest) = crate::manifest::ManifestChange::analyze(path, old_content, new_content) {
+                fe = fe.with_manifest_change(manifest);
+            }
+        } else if config.watcher.preview {
+            // First time seeing this file - show a preview instead of empty diff
+            fe = fe.with_preview(preview_text(new_content, 200));
+        }
+        if config.watcher.content_hash {
+            fe = fe.with_content_hash(short_content_hash(new_content));
+        }
+        fe.with_content(Arc::from(new_content))
+    }
+
+    /// Diff of `old_content` against `new_content`, from `diff_cache` when the
+    /// same pair of contents was diffed before
+    fn cached_diff(
+        path: &Path,
+        old_content: &str,
+        new_content: &str,
+        config: &WatchDiffConfig,
+        diff_cache: &Mutex<DiffCache>,
+    ) -> String {
+        // Use hash-based diff caching
+        let cache_key = (Self::hash_content(old_content), Self::hash_content(new_content));
+        let cached = diff_cache.lock().unwrap_or_else(|e| e.into_inner()).get(&cache_key).cloned();
+        match cached {
+            Some(cached_diff) => cached_diff,
+            None => {
+                // Generate new diff and cache it
+                let generator = crate::diff::DiffConfig::new().merge_gap(config.watcher.merge_gap).build();
+                let result = generator.generate(old_content, new_content);
+                let new_diff = crate::diff::DiffFormatter::format_unified(&result, path, path);
+                let mut diff_cache = diff_cache.lock().unwrap_or_else(|e| e.into_inner());
+                diff_cache.insert(cache_key, new_diff.clone());
+
+                // Limit cache size to prevent memory growth
+                let cache_size_limit = config.cache.diff_cache_size;
+                if diff_cache.len() > cache_size_limit {
+                    // Clear cache when it exceeds limit
+                    let cleanup_threshold = (cache_size_limit as f32 * config.cache.cleanup_threshold) as usize;
+                    if diff_cache.len() > cleanup_threshold {
+                        diff_cache.clear();
+                    }
+                }
+                new_diff
+            }
+        }
+    }
+
+    /// Event for a file moved from `from` to `path`, with the diff of the
+    /// changes made on the way, if any
+    fn moved_file_event(from: &Path, path: &Path, old_content: Option<&str>, new_content: &str, config: &WatchDiffConfig) -> FileEvent {
+        let mut fe = FileEvent::new(path.to_path_buf(), FileEventKind::Moved { from: from.to_path_buf(), to: path.to_path_buf() });
+        if let Some(old_content) = old_content.filter(|old_content| *old_content != new_content) {
+            let generator = crate::diff::DiffConfig::new().merge_gap(config.watcher.merge_gap).build();
+            let diff = crate::diff::DiffFormatter::format_unified(&generator.generate(old_content, new_content), from, path);
+            fe = fe.with_bounded_diff(&diff, config.watcher.max_line_chars);
+        }
+        if config.watcher.content_hash {
+            fe = fe.with_content_hash(short_content_hash(new_content));
+        }
+        fe.with_content(Arc::from(new_content))
+    }
+
+    /// Event for a watched archive: the diff of its entry manifest, with the
+    /// entries added, removed and changed
+    fn archive_event(path: &Path, kind: FileEventKind, previous: Option<&ArchiveListing>, listing: &ArchiveListing) -> FileEvent {
+        FileEvent::new(path.to_path_buf(), kind)
+            .with_diff(crate::archive::manifest_diff(path, previous, listing))
+            .with_archive_change(ArchiveChange::between(previous, listing))
+    }
+
+    /// Event for a newly created text file: its content (up to the preview
+    /// cap) as the preview, or an all-added diff when new file previews are
+    /// off. With all previews off it has neither.
+    fn created_file_event(path: &Path, content: &str, config: &WatcherConfig) -> FileEvent {
+        let fe = FileEvent::new(path.to_path_buf(), FileEventKind::Created);
+        let fe = match (config.new_file_preview, config.preview) {
+            (true, true) => fe.with_preview(preview_text(content, config.new_file_preview_bytes)),
+            (true, false) => fe,
+            (false, _) => fe.with_bounded_diff(&crate::diff::generate_unified_diff("", content, path, path), config.max_line_chars),
+        };
+        let fe = if config.content_hash {
+            fe.with_content_hash(short_content_hash(content))
+        } else {
+            fe
+        };
+        fe.with_content(Arc::from(content))
+    }
+
     pub fn try_recv(&self) -> Result<AppEvent, std::sync::mpsc::TryRecvError> {
         self.event_rx.try_recv()
     }
@@ -195,8 +883,92 @@ impl FileWatcher {
         self.event_rx.recv_timeout(timeout)
     }
 
+    /// Acknowledge budget alerts, resuming full processing of paused batches
+    pub fn acknowledge_budget_alerts(&self) {
+        self.budget.lock().unwrap_or_else(|e| e.into_inner()).acknowledge();
+    }
+
+    /// Whether the backend showed signs of life within `--expect-activity`.
+    /// Always true when no activity period is configured.
+    pub fn is_healthy(&self) -> bool {
+        self.health().map_or(true, |health| health.is_healthy_at(Instant::now()))
+    }
+
+    /// Whether changes arrive as native events or by polling
+    pub fn backend_kind(&self) -> BackendKind {
+        self.backend
+    }
+
+    /// Scan interval of the polling backend; `None` with native events
+    pub fn poll_interval(&self) -> Option<Duration> {
+        (self.backend == BackendKind::Polling).then_some(self.poll_interval)
+    }
+
+    /// Why native events couldn't be set up, if the watcher fell back to polling
+    pub fn fallback_reason(&self) -> Option<&str> {
+        self.fallback_reason.as_deref()
+    }
+
+    /// One line describing the backend, e.g. `polling every 1000ms`
+    pub fn backend_description(&self) -> String {
+        match self.poll_interval() {
+            Some(interval) => format!("{} every {}ms", self.backend.name(), interval.as_millis()),
+            None => self.backend.name().to_string(),
+        }
+    }
+
+    /// Liveness state, if an activity period is configured
+    pub fn health(&self) -> Option<WatchHealth> {
+        self.health
+            .as_ref()
+            .map(|health| *health.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Branch and commit checked out when `HEAD` was last read, with --git-context
+    pub fn git_context(&self) -> Option<GitContext> {
+        let git = self.git.as_ref()?.lock().unwrap_or_else(|e| e.into_inner());
+        git.current().cloned()
+    }
+
+    /// Focus mode switch of the notifications sent for changes
+    pub fn focus(&self) -> &FocusSwitch {
+        &self.focus
+    }
+
+    /// Only list files modified at or after `since` in the initial scan (`--since`)
+    pub fn with_modified_since(mut self, since: Option<SystemTime>) -> Self {
+        self.modified_since = since;
+        self
+    }
+    
+    /// Last known content of each watched file, which changes are diffed against
+    pub fn snapshots(&self) -> &Arc<Mutex<SnapshotStore>> {
+        &self.snapshots
+    }
+
+    /// Scanner for secrets that must not be written out with baselines;
+    /// `None` when scanning is off or secrets are revealed
+    pub fn baseline_secrets(&self) -> Option<&SecretScanner> {
+        self.baseline_secrets.as_deref()
+    }
+
+    /// Content of `path` when watching began, if it was recorded and is still kept
+    pub fn baseline(&self, path: &Path) -> Option<Arc<str>> {
+        self.snapshots.lock().unwrap_or_else(|e| e.into_inner()).get_baseline(path)
+    }
+    
+    /// Directory being watched, as given
+    pub fn root(&self) -> &Path {
+        self.filter.root_path()
+    }
+
     pub fn get_initial_files(&self) -> Result<Vec<PathBuf>> {
-        self.filter.get_watchable_files()
+        let files = self.filter.get_watchable_files()?;
+        let Some(since) = self.modified_since else { return Ok(files) };
+        Ok(files
+            .into_iter()
+            .filter(|path| std::fs::metadata(path).and_then(|m| m.modified()).is_ok_and(|mtime| mtime >= since))
+            .collect())
     }
     
     /// Hash content for diff caching
@@ -219,4 +991,489 @@ pub fn start_ticker(sender: Sender<AppEvent>) {
             }
         }
     });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_polling_backend_reports_its_interval() {
+        let dir = TempDir::new().unwrap();
+        let mut config = WatchDiffConfig::default();
+        config.watcher.backend = WatchBackend::Polling;
+        config.watcher.poll_interval_ms = 250;
+
+        let watcher = FileWatcher::with_config(dir.path(), config).unwrap();
+        assert_eq!(watcher.backend_kind(), BackendKind::Polling);
+        assert_eq!(watcher.poll_interval(), Some(Duration::from_millis(250)));
+        assert_eq!(watcher.backend_description(), "polling every 250ms");
+        assert!(watcher.fallback_reason().is_none());
+
+        let watcher = FileWatcher::new(dir.path()).unwrap();
+        assert_eq!(watcher.backend_kind(), BackendKind::Native);
+        assert_eq!(watcher.poll_interval(), None);
+    }
+
+    #[test]
+    fn test_sub_millisecond_poll_interval_is_clamped() {
+        let dir = TempDir::new().unwrap();
+        let watcher = FileWatcher::with_backend(dir.path(), WatchBackend::Polling, Duration::from_micros(500)).unwrap();
+        assert_eq!(watcher.poll_interval(), Some(Duration::from_millis(1)));
+
+        let mut config = WatchDiffConfig::default();
+        config.watcher.poll_interval_ms = 0;
+        assert_eq!(config.watcher.poll_interval(), Duration::from_millis(1));
+    }
+
+    #[test]
+    fn test_poll_backend_delivers_file_changes() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("lib.rs");
+        std::fs::write(&path, "fn a() {}\n").unwrap();
+        let watcher = FileWatcher::with_backend(dir.path(), WatchBackend::Polling, Duration::from_millis(20)).unwrap();
+        assert_eq!(watcher.backend_description(), "polling every 20ms");
+        assert_eq!(watcher.get_initial_files().unwrap().len(), 1);
+        // Wait until the startup content is recorded, so the change diffs against it
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while watcher.baseline(&path).is_none() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        std::fs::write(&path, "fn b() {}\n").unwrap();
+        let diff = loop {
+            match watcher.recv_timeout(Duration::from_secs(5)).expect("change event") {
+                AppEvent::FileChanged(event) if event.path == path && event.diff.is_some() => break event.diff.unwrap(),
+                _ => {}
+            }
+        };
+        assert!(diff.contains("-fn a() {}\n+fn b() {}"), "{}", diff);
+    }
+
+    #[test]
+    fn test_write_temp_then_rename_emits_one_modification() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("lib.rs");
+        std::fs::write(&path, "fn a() {}\n").unwrap();
+        let watcher = FileWatcher::with_debounce(dir.path(), Duration::from_millis(200)).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while watcher.baseline(&path).is_none() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // What vim does on :w with the default backupcopy
+        let temp = dir.path().join("lib.rs.tmp");
+        std::fs::write(&temp, "fn b() {}\n").unwrap();
+        std::fs::rename(&temp, &path).unwrap();
+
+        let mut events = Vec::new();
+        while let Ok(event) = watcher.recv_timeout(Duration::from_secs(1)) {
+            if let AppEvent::FileChanged(event) = event {
+                events.push(event);
+            }
+        }
+        assert_eq!(events.len(), 1, "{:?}", events.iter().map(|e| (&e.path, &e.kind)).collect::<Vec<_>>());
+        assert_eq!(events[0].path, path);
+        assert!(matches!(events[0].kind, FileEventKind::Modified));
+        assert!(events[0].diff.as_deref().unwrap().contains("-fn a() {}\n+fn b() {}"));
+    }
+
+    #[test]
+    fn test_delete_then_create_elsewhere_is_one_move() {
+        let dir = TempDir::new().unwrap();
+        let old = dir.path().join("old.rs");
+        let lib: String = (0..20).map(|i| format!("fn f{}() {{}}\n", i)).collect();
+        std::fs::write(&old, &lib).unwrap();
+        std::fs::write(dir.path().join("gone.rs"), "struct Gone;\n").unwrap();
+        let watcher = FileWatcher::with_debounce(dir.path(), Duration::from_millis(50)).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while watcher.baseline(&old).is_none() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // Saved under a new name with one line edited, then the original deleted
+        std::fs::remove_file(&old).unwrap();
+        thread::sleep(Duration::from_millis(150));
+        let new = dir.path().join("new.rs");
+        std::fs::write(&new, lib.replace("fn f3() {}", "fn f3() { todo!() }")).unwrap();
+        std::fs::remove_file(dir.path().join("gone.rs")).unwrap();
+
+        let mut events = Vec::new();
+        while let Ok(event) = watcher.recv_timeout(Duration::from_secs(1)) {
+            if let AppEvent::FileChanged(event) = event {
+                events.push(event);
+            }
+        }
+        let kinds: Vec<_> = events.iter().map(|e| (e.path.file_name().unwrap().to_owned(), e.kind.clone())).collect();
+        assert_eq!(events.len(), 2, "{:?}", kinds);
+        assert!(matches!(events[0].kind, FileEventKind::Moved { ref from, ref to } if *from == old && *to == new));
+        assert_eq!(events[0].path, new);
+        assert!(events[0].diff.as_deref().unwrap().contains("-fn f3() {}\n+fn f3() { todo!() }"));
+        // An unclaimed deletion still comes through once the window has passed
+        assert!(matches!(events[1].kind, FileEventKind::Deleted));
+        assert!(events[1].path.ends_with("gone.rs"));
+    }
+
+    #[test]
+    fn test_renamed_text_and_binary_files_are_single_moves() {
+        let dir = TempDir::new().unwrap();
+        let (text, image) = (dir.path().join("notes.md"), dir.path().join("logo.png"));
+        std::fs::write(&text, "# Notes\n").unwrap();
+        std::fs::write(&image, [0x89, b'P', b'N', b'G', 0, 0, 0, 13]).unwrap();
+        let watcher = FileWatcher::with_debounce(dir.path(), Duration::from_millis(50)).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while watcher.baseline(&text).is_none() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let (moved_text, moved_image) = (dir.path().join("README.md"), dir.path().join("icon.png"));
+        std::fs::rename(&text, &moved_text).unwrap();
+        std::fs::rename(&image, &moved_image).unwrap();
+
+        let mut events = Vec::new();
+        while let Ok(event) = watcher.recv_timeout(Duration::from_secs(1)) {
+            if let AppEvent::FileChanged(event) = event {
+                events.push(event);
+            }
+        }
+        events.sort_by(|a, b| a.path.cmp(&b.path));
+        let kinds: Vec<_> = events.iter().map(|e| (e.path.file_name().unwrap().to_owned(), e.kind.clone())).collect();
+        assert_eq!(events.len(), 2, "{:?}", kinds);
+        assert!(matches!(events[0].kind, FileEventKind::Moved { ref from, .. } if *from == text), "{:?}", kinds);
+        assert_eq!(events[0].path, moved_text);
+        assert!(matches!(events[1].kind, FileEventKind::Moved { ref from, .. } if *from == image), "{:?}", kinds);
+        assert_eq!(events[1].path, moved_image);
+    }
+
+    #[test]
+    fn test_max_depth_and_ignored_dirs_limit_the_initial_files() {
+        let dir = TempDir::new().unwrap();
+        for file in ["a.rs", "src/b.rs", "src/deep/c.rs", "node_modules/pkg/index.js", "target/debug/out.rs"] {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        let initial = |options: WatchOptions| {
+            let watcher = FileWatcher::with_options(dir.path(), options).unwrap();
+            let mut files: Vec<String> = watcher.get_initial_files().unwrap()
+                .iter()
+                .map(|path| path.strip_prefix(dir.path()).unwrap().to_string_lossy().replace('\\', "/"))
+                .collect();
+            files.sort();
+            files
+        };
+
+        assert_eq!(initial(WatchOptions::default()), ["a.rs", "src/b.rs", "src/deep/c.rs"]);
+        assert_eq!(initial(WatchOptions { max_depth: Some(1), ..WatchOptions::default() }), ["a.rs"]);
+        assert_eq!(initial(WatchOptions { max_depth: Some(2), ..WatchOptions::default() }), ["a.rs", "src/b.rs"]);
+        assert_eq!(
+            initial(WatchOptions { max_depth: None, ignored_dirs: vec!["deep".to_string()] }),
+            ["a.rs", "node_modules/pkg/index.js", "src/b.rs", "target/debug/out.rs"]
+        );
+
+        // Events beyond the limits are dropped the same way
+        let mut filter = FileFilter::new(dir.path()).unwrap();
+        filter.set_max_depth(Some(1));
+        assert!(filter.should_watch(dir.path().join("a.rs")));
+        assert!(!filter.should_watch(dir.path().join("src/b.rs")));
+        assert!(!FileFilter::new(dir.path()).unwrap().should_watch(dir.path().join("node_modules/pkg/index.js")));
+        // Directories are matched below the root only
+        let nested = dir.path().join("target/debug");
+        assert!(FileFilter::new(&nested).unwrap().should_watch(nested.join("out.rs")));
+    }
+
+    #[test]
+    fn test_created_file_previews_full_content_up_to_cap() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("new.rs");
+        let content = "fn main() {\n    println!(\"héllo\");\n}\n";
+        std::fs::write(&path, content).unwrap();
+        let content = ContentReader::default().read_to_string(&path).unwrap();
+        let mut config = WatcherConfig::default();
+
+        let event = FileWatcher::created_file_event(&path, &content, &config);
+        assert_eq!(event.content_preview.as_deref(), Some(content.as_str()));
+        assert!(event.diff.is_none());
+
+        // Cut on a character boundary once over the cap
+        config.new_file_preview_bytes = 28;
+        let event = FileWatcher::created_file_event(&path, &content, &config);
+        assert_eq!(event.content_preview.as_deref(), Some("fn main() {\n    println!(\"h..."));
+        assert_eq!(event.content.as_deref(), Some(content.as_str()));
+
+        config.new_file_preview = false;
+        let event = FileWatcher::created_file_event(&path, &content, &config);
+        assert!(event.content_preview.is_none());
+        assert!(event.diff.unwrap().contains("+    println!(\"héllo\");"));
+    }
+
+    #[test]
+    fn test_no_preview_leaves_content_preview_empty() {
+        let mut config = WatchDiffConfig::default();
+        config.watcher.preview = false;
+        let cache = Mutex::new(DiffCache::new());
+        let path = Path::new("a.rs");
+
+        let created = FileWatcher::created_file_event(path, "fn a() {}\n", &config.watcher);
+        assert!(created.content_preview.is_none());
+        assert!(created.diff.is_none());
+        assert_eq!(created.content.as_deref(), Some("fn a() {}\n"));
+
+        // A file seen for the first time has no baseline to diff against either
+        let first_seen = FileWatcher::modified_file_event(path, None, "fn a() {}\n", &config, &cache);
+        assert!(first_seen.content_preview.is_none());
+        assert!(first_seen.to_highlighted().highlighted_preview.is_none());
+
+        // Diffs are unaffected
+        let modified = FileWatcher::modified_file_event(path, Some("fn a() {}\n"), "fn b() {}\n", &config, &cache);
+        assert!(modified.diff.is_some());
+    }
+
+    #[test]
+    fn test_first_seen_preview_cuts_on_a_character_boundary() {
+        let config = WatchDiffConfig::default();
+        let cache = Mutex::new(DiffCache::new());
+        // "é" is two bytes, so byte 200 falls inside one
+        let content = format!("a{}", "é".repeat(150));
+
+        let event = FileWatcher::modified_file_event(Path::new("notes.txt"), None, &content, &config, &cache);
+        let preview = event.content_preview.unwrap();
+        assert_eq!(preview, format!("a{}...", "é".repeat(99)));
+    }
+
+    #[test]
+    fn test_appends_are_detected_without_diffing() {
+        let config = WatchDiffConfig::default();
+        let cache = Mutex::new(DiffCache::new());
+        let path = Path::new("server.log");
+        let old = "INFO boot\nINFO listening\n";
+        let modified = |old: &str, new: &str| FileWatcher::modified_file_event(path, Some(old), new, &config, &cache);
+
+        // Pure append: just the new lines, and nothing cached
+        let event = modified(old, "INFO boot\nINFO listening\nERROR disk full\n");
+        assert!(event.append_only);
+        assert_eq!(event.diff.as_deref(), Some("--- server.log\n+++ server.log\n@@ -3,0 +3,1 @@\n+ERROR disk full"));
+        assert!(cache.lock().unwrap().is_empty());
+
+        // Truncated and rewritten with more lines than before
+        let event = modified(old, "INFO boot\nINFO rotated\nINFO listening\nINFO ready\n");
+        assert!(!event.append_only);
+        assert!(event.diff.unwrap().contains("+INFO rotated"));
+
+        // Changed in the middle while also growing
+        let event = modified(old, "INFO boot\nWARN listening\nINFO ready\n");
+        assert!(!event.append_only);
+        assert!(event.diff.unwrap().contains("-INFO listening"));
+        assert_eq!(cache.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_glob_rejected_at_startup() {
+        let dir = TempDir::new().unwrap();
+        let mut config = WatchDiffConfig::default();
+        config.watcher.exclude = vec!["**/generated/**".to_string(), "build/[".to_string()];
+
+        let error = FileWatcher::with_config(dir.path(), config).err().unwrap();
+        assert!(format!("{:#}", error).contains("Invalid --exclude pattern: build/["), "{:#}", error);
+    }
+
+    #[test]
+    fn test_strict_reports_unreadable_files() {
+        let path = Path::new("/project/secret.rs");
+        let denied = || Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "Permission denied"));
+
+        let error = FileWatcher::changed_content(true, path, denied()).unwrap_err();
+        assert_eq!(error.path, path);
+        assert_eq!(error.to_string(), "/project/secret.rs: Permission denied");
+        // Without --strict the change is still reported, just without a diff
+        assert_eq!(FileWatcher::changed_content(false, path, denied()).unwrap(), None);
+
+        // A file removed again before it was read is not an error
+        let dir = TempDir::new().unwrap();
+        let gone = dir.path().join("gone.rs");
+        let read = FileWatcher::read_changed_text(&FileFilter::new(dir.path()).unwrap(), &ContentReader::default(), &gone);
+        assert_eq!(FileWatcher::changed_content(true, &gone, read).unwrap(), None);
+
+        let panicked = FileWatcher::run_diff_job(true, path, |_| panic!("boom"));
+        assert_eq!(panicked.unwrap_err().cause, "diff failed: boom");
+    }
+
+    #[test]
+    fn test_strict_reports_a_failing_read_as_an_error_event() {
+        // Zero bytes without a UTF-16 pattern: a .rs file that cannot be decoded
+        const BINARY: &[u8] = &[0x00, 0x00, 0x01, 0x02, 0x00, 0x00, 0xff, 0x7f];
+        let dir = TempDir::new().unwrap();
+        let filter = FileFilter::new(dir.path()).unwrap();
+        let blob = dir.path().join("blob.rs");
+        std::fs::write(&blob, BINARY).unwrap();
+        let read = FileWatcher::read_changed_text(&filter, &ContentReader::default(), &blob);
+        assert!(read.is_err());
+        assert_eq!(FileWatcher::changed_content(false, &blob, read).unwrap(), None);
+
+        // Files that are not text by name are never read, so --strict lets them pass
+        let image = dir.path().join("logo.png");
+        std::fs::write(&image, BINARY).unwrap();
+        let read = FileWatcher::read_changed_text(&filter, &ContentReader::default(), &image);
+        assert_eq!(FileWatcher::changed_content(true, &image, read).unwrap(), None);
+
+        let mut config = WatchDiffConfig::default();
+        config.watcher.strict = true;
+        let watcher = FileWatcher::with_config(dir.path(), config).unwrap();
+        let path = dir.path().join("data.rs");
+        std::fs::write(&path, BINARY).unwrap();
+        let error = loop {
+            match watcher.recv_timeout(Duration::from_secs(5)).expect("error event") {
+                AppEvent::Error(error) if error.path == path => break error,
+                AppEvent::FileChanged(event) => assert_ne!(event.path, path, "unreadable file reported as a change"),
+                _ => {}
+            }
+        };
+        assert!(error.cause.contains("not text"), "{}", error.cause);
+    }
+
+    #[test]
+    fn test_content_hash_matches_for_identical_content() {
+        let mut config = WatcherConfig::default();
+        let hash = |path: &str, content: &str, config: &WatcherConfig| {
+            FileWatcher::created_file_event(Path::new(path), content, config).content_hash
+        };
+        assert_eq!(hash("a.rs", "fn a() {}\n", &config), None);
+
+        config.content_hash = true;
+        let a = hash("a.rs", "fn a() {}\n", &config).unwrap();
+        assert_eq!(a.len(), 8);
+        assert!(a.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(hash("copy/a.rs", "fn a() {}\n", &config).unwrap(), a);
+        // Line endings are normalized
+        assert_eq!(hash("a.rs", "fn a() {}\r\n", &config).unwrap(), a);
+        assert_ne!(hash("a.rs", "fn b() {}\n", &config).unwrap(), a);
+
+        let json = serde_json::to_value(FileWatcher::created_file_event(Path::new("a.rs"), "fn a() {}\n", &config)).unwrap();
+        assert_eq!(json["content_hash"], a.as_str());
+    }
+
+    #[test]
+    fn test_first_change_to_existing_file_diffs_against_startup_content() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("lib.rs");
+        std::fs::write(&path, "fn a() {}\nfn b() {}\n").unwrap();
+        std::fs::write(dir.path().join("logo.png"), b"\x89PNG\r\n\x1a\n\0\0").unwrap();
+        let watcher = FileWatcher::new(dir.path()).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while watcher.baseline(&path).is_none() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(watcher.baseline(&path).as_deref(), Some("fn a() {}\nfn b() {}\n"));
+        // Binary files get no baseline
+        assert_eq!(watcher.snapshots().lock().unwrap().len(), 1);
+
+        std::fs::write(&path, "fn a() {}\nfn c() {}\n").unwrap();
+        let event = loop {
+            match watcher.recv_timeout(Duration::from_secs(5)).expect("change event") {
+                AppEvent::FileChanged(event) if event.path == path && event.diff.is_some() => break event,
+                AppEvent::FileChanged(event) if event.path == path => panic!("no diff: {:?}", event.content_preview),
+                _ => {}
+            }
+        };
+        let diff = event.diff.unwrap();
+        assert!(diff.contains("-fn b() {}\n+fn c() {}"), "{}", diff);
+        // The baseline stays the content from before watching began
+        assert_eq!(watcher.baseline(&path).as_deref(), Some("fn a() {}\nfn b() {}\n"));
+    }
+
+    #[test]
+    fn test_files_changed_while_seeding_get_no_baseline() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("steady.rs"), "fn a() {}\n").unwrap();
+        std::fs::write(root.join("busy.rs"), "fn b() {}\n").unwrap();
+        let filter = FileFilter::new(&root).unwrap();
+        let snapshots = Mutex::new(SnapshotStore::default());
+        let mut renames = RenameDetector::new(Duration::from_millis(100));
+        let (tx, rx) = mpsc::channel();
+        let change = Event::new(notify::EventKind::Modify(notify::event::ModifyKind::Any)).add_path(root.join("busy.rs"));
+        tx.send(Ok(change)).unwrap();
+
+        let early = FileWatcher::seed_snapshots(&filter, &ContentReader::default(), &snapshots, &mut renames, &rx);
+        // The change is still handled, against no baseline rather than its own content
+        assert_eq!(early.len(), 1);
+        let snapshots = snapshots.lock().unwrap();
+        assert_eq!(snapshots.get_baseline(&root.join("steady.rs")).as_deref(), Some("fn a() {}\n"));
+        assert!(snapshots.get_baseline(&root.join("busy.rs")).is_none());
+    }
+
+    #[test]
+    fn test_secret_stays_masked_when_a_neighbouring_line_changes() {
+        const KEY: &str = "AKIAZ7QK2M4T9VXW3BNC";
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("settings.py");
+        std::fs::write(&path, "DEBUG = True\n").unwrap();
+        let watcher = FileWatcher::new(dir.path()).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while watcher.baseline(&path).is_none() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let next_diff = |expected: &str| loop {
+            match watcher.recv_timeout(Duration::from_secs(5)).expect("change event") {
+                AppEvent::FileChanged(event) if event.path == path && event.diff.as_deref().is_some_and(|d| d.contains(expected)) => {
+                    break event
+                }
+                _ => {}
+            }
+        };
+
+        std::fs::write(&path, format!("DEBUG = True\nAWS_KEY = \"{}\"\n", KEY)).unwrap();
+        let added = next_diff("+AWS_KEY");
+        assert_eq!(added.secrets.len(), 1);
+        std::fs::write(&path, format!("DEBUG = False\nAWS_KEY = \"{}\"\n", KEY)).unwrap();
+        let edited = next_diff("+DEBUG = False");
+
+        for event in [added, edited] {
+            let json = serde_json::to_string(&event).unwrap();
+            assert!(!json.contains(KEY), "{}", json);
+        }
+    }
+
+    #[test]
+    fn test_since_excludes_files_modified_earlier() {
+        let dir = TempDir::new().unwrap();
+        let now = SystemTime::now();
+        for (name, age_secs) in [("fresh.rs", 60), ("hour_old.rs", 3_600), ("stale.rs", 3 * 86_400)] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, "fn main() {}\n").unwrap();
+            let mtime = filetime::FileTime::from_system_time(now - Duration::from_secs(age_secs));
+            filetime::set_file_mtime(&path, mtime).unwrap();
+        }
+        let names = |watcher: &FileWatcher| {
+            let mut names: Vec<String> = watcher
+                .get_initial_files()
+                .unwrap()
+                .iter()
+                .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            names
+        };
+
+        let watcher = FileWatcher::new(dir.path()).unwrap();
+        assert_eq!(names(&watcher), ["fresh.rs", "hour_old.rs", "stale.rs"]);
+
+        let since = crate::cli::parse_since("2h", now).unwrap();
+        let watcher = watcher.with_modified_since(Some(since));
+        assert_eq!(names(&watcher), ["fresh.rs", "hour_old.rs"]);
+
+        let since = crate::cli::parse_since("1h30m", now).unwrap();
+        assert_eq!(now.duration_since(since).unwrap(), Duration::from_secs(5_400));
+        let watcher = watcher.with_modified_since(Some(crate::cli::parse_since("30m", now).unwrap()));
+        assert_eq!(names(&watcher), ["fresh.rs"]);
+
+        let absolute = chrono::DateTime::<chrono::Utc>::from(now - Duration::from_secs(2 * 86_400)).to_rfc3339();
+        let watcher = watcher.with_modified_since(Some(crate::cli::parse_since(&absolute, now).unwrap()));
+        assert_eq!(names(&watcher), ["fresh.rs", "hour_old.rs"]);
+
+        for bad in ["", "2", "h", "2x", "yesterday"] {
+            assert!(crate::cli::parse_since(bad, now).is_err(), "{:?}", bad);
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,326 @@
+//! Test coverage of changed lines, from a CI coverage report
+//!
+//! `--coverage` points at an lcov `lcov.info` or an istanbul
+//! `coverage-final.json`. The report describes the file before the change,
+//! so changed lines are looked up in the old file: a removed or rewritten
+//! line at its own line number, a purely inserted line at the old line it
+//! was inserted before. Lines the report has no data for, such as comments,
+//! count as unknown. Report paths are usually relative to the CI checkout;
+//! a prefix can be stripped from them and another added so they line up
+//! with the watch root.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use crate::config::CoverageConfig;
+
+/// How the changed lines of one event are covered by tests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeCoverage {
+    pub covered: usize,
+    pub uncovered: usize,
+    /// Changed lines the report has no data for
+    pub unknown: usize,
+}
+
+impl ChangeCoverage {
+    /// Percentage of the changed lines with data that tests run
+    pub fn percent(&self) -> Option<u32> {
+        let known = self.covered + self.uncovered;
+        (known > 0).then(|| (self.covered * 100 / known) as u32)
+    }
+
+    /// Whether more than `threshold` (0.0 - 1.0) of the lines with data are uncovered
+    pub fn is_mostly_uncovered(&self, threshold: f32) -> bool {
+        let known = self.covered + self.uncovered;
+        known > 0 && self.uncovered as f32 / known as f32 > threshold
+    }
+}
+
+/// Maps report paths onto paths relative to the watch root
+#[derive(Debug, Clone, Default)]
+pub struct PathRemap {
+    /// Removed from the start of report paths, e.g. `/home/ci/build/`
+    pub strip_prefix: Option<String>,
+    /// Added after stripping, e.g. `packages/web` for paths relative to a package
+    pub path_prefix: Option<String>,
+}
+
+impl PathRemap {
+    /// `report_path` relative to `root`
+    pub fn apply(&self, report_path: &str, root: &Path) -> PathBuf {
+        let stripped = self
+            .strip_prefix
+            .as_deref()
+            .and_then(|prefix| report_path.strip_prefix(prefix))
+            .unwrap_or(report_path);
+        let stripped = stripped.strip_prefix("./").unwrap_or(stripped);
+        let path = match self.path_prefix {
+            Some(ref prefix) => Path::new(prefix).join(stripped.trim_start_matches('/')),
+            None => PathBuf::from(stripped),
+        };
+        path.strip_prefix(root).map(Path::to_path_buf).unwrap_or(path)
+    }
+}
+
+/// Hit counts by line number for each file of a coverage report
+#[derive(Debug, Clone, Default)]
+pub struct CoverageReport {
+    files: HashMap<PathBuf, BTreeMap<usize, u64>>,
+}
+
+impl CoverageReport {
+    /// Parse an istanbul JSON report if `text` is a JSON object, lcov otherwise
+    pub fn parse(text: &str) -> Result<Self> {
+        if text.trim_start().starts_with('{') {
+            Self::parse_istanbul(text)
+        } else {
+            Self::parse_lcov(text)
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read coverage report: {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid coverage report: {}", path.display()))
+    }
+
+    /// `SF:` starts a file, `DA:<line>,<hits>` records a line
+    pub fn parse_lcov(text: &str) -> Result<Self> {
+        let mut report = Self::default();
+        let mut current: Option<PathBuf> = None;
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if let Some(path) = line.strip_prefix("SF:") {
+                current = Some(PathBuf::from(path));
+            } else if let Some(data) = line.strip_prefix("DA:") {
+                let Some(ref path) = current else {
+                    anyhow::bail!("line {}: DA record outside a file", number + 1);
+                };
+                let mut fields = data.split(',');
+                let parse = |field: Option<&str>| field.and_then(|f| f.trim().parse::<u64>().ok());
+                let (Some(line_no), Some(hits)) = (parse(fields.next()), parse(fields.next())) else {
+                    anyhow::bail!("line {}: malformed DA record", number + 1);
+                };
+                *report.files.entry(path.clone()).or_default().entry(line_no as usize).or_default() += hits;
+            } else if line == "end_of_record" {
+                current = None;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Line hits from the statements of an istanbul `coverage-final.json`;
+    /// a line takes the highest count of the statements starting on it
+    pub fn parse_istanbul(text: &str) -> Result<Self> {
+        let root: serde_json::Value = serde_json::from_str(text)?;
+        let files = root.as_object().context("expected an object of files")?;
+        let mut report = Self::default();
+
+        for (key, file) in files {
+            let path = file.get("path").and_then(|p| p.as_str()).unwrap_or(key);
+            let statements = file.get("statementMap").and_then(|s| s.as_object());
+            let counts = file.get("s").and_then(|s| s.as_object());
+            let (Some(statements), Some(counts)) = (statements, counts) else {
+                anyhow::bail!("{}: missing statementMap or s", key);
+            };
+
+            let lines = report.files.entry(PathBuf::from(path)).or_default();
+            for (id, statement) in statements {
+                let line = statement.pointer("/start/line").and_then(|l| l.as_u64());
+                let hits = counts.get(id).and_then(|c| c.as_u64());
+                if let (Some(line), Some(hits)) = (line, hits) {
+                    let entry = lines.entry(line as usize).or_default();
+                    *entry = (*entry).max(hits);
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// The report with every path mapped through `remap`
+    pub fn remapped(self, remap: &PathRemap, root: &Path) -> Self {
+        let mut files: HashMap<PathBuf, BTreeMap<usize, u64>> = HashMap::new();
+        for (path, lines) in self.files {
+            let path = remap.apply(&path.to_string_lossy(), root);
+            files.entry(path).or_default().extend(lines);
+        }
+        Self { files }
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Hit counts for `path`, as the report names it after remapping
+    pub fn line_hits(&self, path: &Path) -> Option<&BTreeMap<usize, u64>> {
+        self.files.get(path)
+    }
+
+    /// Coverage of the lines `diff` changes in `path`
+    pub fn change_coverage(&self, path: &Path, diff: &str) -> ChangeCoverage {
+        let hits = self.line_hits(path);
+        let mut coverage = ChangeCoverage::default();
+        let mut count = |line: usize| match hits.and_then(|hits| hits.get(&line)) {
+            Some(0) => coverage.uncovered += 1,
+            Some(_) => coverage.covered += 1,
+            None => coverage.unknown += 1,
+        };
+        // Old file line of the next diff line; `None` in the file headers
+        let mut old_line: Option<usize> = None;
+        // Whether the current run of changed lines removes any
+        let mut replaces = false;
+
+        for line in diff.lines() {
+            if let Some(ranges) = line.strip_prefix("@@ -") {
+                old_line = Some(ranges.split([',', ' ']).next().and_then(|start| start.parse().ok()).unwrap_or(1));
+                replaces = false;
+                continue;
+            }
+            let Some(ref mut old_line) = old_line else { continue };
+            match line.chars().next() {
+                Some('-') => {
+                    count(*old_line);
+                    replaces = true;
+                    *old_line += 1;
+                }
+                // Lines replacing removed ones are represented by those
+                Some('+') if replaces => {}
+                Some('+') => count(*old_line),
+                _ => {
+                    replaces = false;
+                    *old_line += 1;
+                }
+            }
+        }
+        coverage
+    }
+}
+
+/// Keeps a coverage report loaded, re-reading it whenever the file changes
+#[derive(Debug)]
+pub struct CoverageProvider {
+    report_path: PathBuf,
+    root: PathBuf,
+    remap: PathRemap,
+    report: CoverageReport,
+    /// Modification time of the report when it was last read
+    loaded_at: Option<SystemTime>,
+}
+
+impl CoverageProvider {
+    /// Load the report named by `config` for the watch root `root`; `None`
+    /// when no report is configured
+    pub fn from_config(config: &CoverageConfig, root: &Path) -> Result<Option<Self>> {
+        let Some(ref report_path) = config.report else { return Ok(None) };
+        let remap = PathRemap {
+            strip_prefix: config.strip_prefix.clone(),
+            path_prefix: config.path_prefix.clone(),
+        };
+        let mut provider = Self {
+            report_path: report_path.clone(),
+            root: root.to_path_buf(),
+            remap,
+            report: CoverageReport::default(),
+            loaded_at: None,
+        };
+        provider.refresh()?;
+        Ok(Some(provider))
+    }
+
+    /// Re-read the report if it changed since it was last read. Returns
+    /// whether it was read.
+    pub fn refresh(&mut self) -> Result<bool> {
+        let modified = fs::metadata(&self.report_path)
+            .and_then(|meta| meta.modified())
+            .with_context(|| format!("Failed to read coverage report: {}", self.report_path.display()))?;
+        if self.loaded_at == Some(modified) {
+            return Ok(false);
+        }
+        self.report = CoverageReport::load(&self.report_path)?.remapped(&self.remap, &self.root);
+        self.loaded_at = Some(modified);
+        Ok(true)
+    }
+
+    pub fn report(&self) -> &CoverageReport {
+        &self.report
+    }
+
+    /// Coverage of the lines `diff` changes in the watched file `path`
+    pub fn change_coverage(&self, path: &Path, diff: &str) -> ChangeCoverage {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        self.report.change_coverage(relative, diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LCOV: &str = "TN:\n\
+        SF:/home/ci/build/src/parser.rs\n\
+        DA:1,4\nDA:2,4\nDA:3,0\nDA:4,0\nDA:6,2\n\
+        LF:5\nLH:3\nend_of_record\n\
+        SF:/home/ci/build/src/lib.rs\n\
+        DA:1,1\nend_of_record\n";
+
+    const ISTANBUL: &str = r#"{
+        "/home/ci/build/web/src/app.ts": {
+            "path": "/home/ci/build/web/src/app.ts",
+            "statementMap": {
+                "0": {"start": {"line": 1, "column": 0}, "end": {"line": 1, "column": 20}},
+                "1": {"start": {"line": 2, "column": 2}, "end": {"line": 2, "column": 9}},
+                "2": {"start": {"line": 2, "column": 10}, "end": {"line": 2, "column": 30}},
+                "3": {"start": {"line": 5, "column": 2}, "end": {"line": 5, "column": 12}}
+            },
+            "s": {"0": 3, "1": 0, "2": 1, "3": 0},
+            "fnMap": {}, "f": {}, "branchMap": {}, "b": {}
+        }
+    }"#;
+
+    #[test]
+    fn test_lcov_changed_line_coverage() {
+        let remap = PathRemap { strip_prefix: Some("/home/ci/build/".to_string()), path_prefix: None };
+        let report = CoverageReport::parse(LCOV).unwrap().remapped(&remap, Path::new("/work/repo"));
+        assert_eq!(report.file_count(), 2);
+        assert_eq!(report.line_hits(Path::new("src/parser.rs")).unwrap().get(&3), Some(&0));
+
+        // Lines 2 (covered) and 3 (uncovered) are rewritten, a line is inserted before 5 (no data)
+        let diff = "--- a\n+++ b\n@@ -1,5 +1,6 @@\n one\n-two\n-three\n+TWO\n+THREE\n four\n+inserted\n five\n";
+        let coverage = report.change_coverage(Path::new("src/parser.rs"), diff);
+        assert_eq!(coverage, ChangeCoverage { covered: 1, uncovered: 1, unknown: 1 });
+        assert_eq!(coverage.percent(), Some(50));
+        assert!(!coverage.is_mostly_uncovered(0.5));
+
+        let unknown = report.change_coverage(Path::new("src/other.rs"), diff);
+        assert_eq!(unknown.percent(), None);
+        assert!(CoverageReport::parse_lcov("DA:1,1\n").is_err());
+    }
+
+    #[test]
+    fn test_istanbul_report_with_remapped_root() {
+        // Report paths are relative to the web package of a CI checkout
+        let remap = PathRemap {
+            strip_prefix: Some("/home/ci/build/web/".to_string()),
+            path_prefix: Some("packages/web".to_string()),
+        };
+        let report = CoverageReport::parse(ISTANBUL).unwrap().remapped(&remap, Path::new("/work/repo"));
+        let hits = report.line_hits(Path::new("packages/web/src/app.ts")).unwrap();
+        // The highest count of the statements on a line wins
+        assert_eq!(hits.get(&2), Some(&1));
+        assert_eq!(hits.get(&5), Some(&0));
+
+        let diff = "@@ -5,1 +5,1 @@\n-  return a;\n+  return b;\n";
+        let coverage = report.change_coverage(Path::new("packages/web/src/app.ts"), diff);
+        assert_eq!(coverage, ChangeCoverage { covered: 0, uncovered: 1, unknown: 0 });
+        assert!(coverage.is_mostly_uncovered(0.5));
+
+        // Absolute paths under the watch root are made relative
+        let local = PathRemap::default().apply("/work/repo/src/main.rs", Path::new("/work/repo"));
+        assert_eq!(local, PathBuf::from("src/main.rs"));
+    }
+}
@@ -1,10 +1,37 @@
-use similar::{TextDiff, ChangeTag, Algorithm};
+use std::collections::HashMap;
+use std::ops::Range;
+use similar::algorithms::{Capture, DiffHook, Replace};
+use similar::{group_diff_ops, Algorithm, ChangeTag, DiffOp, DiffableStr, TextDiff};
 use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use super::words::{WordChange, WordSplitter};
+
+/// Lines of unchanged context around each hunk unless configured otherwise
+pub const DEFAULT_CONTEXT_LINES: usize = 3;
+
+/// Leading bytes of each side checked for binary content, as many as git checks
+pub const BINARY_CHECK_BYTES: usize = 8000;
+
+/// Whether `bytes` look binary: a zero byte or invalid UTF-8 within the first
+/// [`BINARY_CHECK_BYTES`]. A character cut off at the end of that window is
+/// not counted as invalid.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(BINARY_CHECK_BYTES)];
+    sample.contains(&0) || std::str::from_utf8(sample).is_err_and(|err| err.error_len().is_some())
+}
 
 /// Trait defining a diff algorithm interface
 pub trait DiffAlgorithm: Send + Sync {
     /// Generate a diff between old and new content
     fn diff(&self, old: &str, new: &str) -> DiffResult;
+
+    /// Generate a diff with `context_lines` of context around each hunk.
+    /// Changes closer together than twice that share a hunk. Algorithms
+    /// that don't support it use their default context.
+    fn diff_with_context(&self, old: &str, new: &str, context_lines: usize) -> DiffResult {
+        let _ = context_lines;
+        self.diff(old, new)
+    }
     
     /// Get the algorithm name
     fn name(&self) -> &'static str;
@@ -13,33 +40,254 @@ pub trait DiffAlgorithm: Send + Sync {
     fn description(&self) -> &'static str;
 }
 
+/// Whether diff text is the `Binary files ... differ` of a binary result,
+/// rather than hunks
+pub fn is_binary_diff(diff: &str) -> bool {
+    diff.lines()
+        .take_while(|line| !line.starts_with("@@"))
+        .any(|line| line.starts_with("Binary files ") && line.ends_with(" differ"))
+}
+
 /// Result of a diff operation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DiffResult {
     pub hunks: Vec<DiffHunk>,
     pub stats: DiffStats,
+    /// Either side was binary; there are no hunks and formatters only say
+    /// that the files differ
+    #[serde(default)]
+    pub is_binary: bool,
 }
 
-/// A single hunk (contiguous block of changes)
-#[derive(Debug, Clone)]
+/// A single hunk (contiguous block of changes). Starts are 0-based.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DiffHunk {
     pub old_start: usize,
     pub old_len: usize,
     pub new_start: usize,
     pub new_len: usize,
     pub operations: Vec<DiffOperation>,
+    /// Byte ranges of each operation's line that differ from its paired
+    /// line, parallel to `operations`; empty unless word diffing is on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub word_ranges: Vec<Vec<Range<usize>>>,
+}
+
+impl DiffHunk {
+    /// Context lines before the first change
+    fn leading_equal(&self) -> usize {
+        self.operations.iter().take_while(|op| matches!(op, DiffOperation::Equal(_))).count()
+    }
+
+    /// Context lines after the last change
+    fn trailing_equal(&self) -> usize {
+        self.operations.iter().rev().take_while(|op| matches!(op, DiffOperation::Equal(_))).count()
+    }
+
+    /// Unified diff hunk header, e.g. `@@ -3,2 +3,4 @@`, with 1-based starts
+    pub fn header(&self) -> String {
+        format!("@@ -{},{} +{},{} @@", self.old_start + 1, self.old_len, self.new_start + 1, self.new_len)
+    }
+
+    /// Ranges of a `@@ -old_start,old_count +new_start,new_count @@` header,
+    /// as written (1-based). Only the part between the `@@` markers is read,
+    /// so a section heading after them (`@@ -1 +1 @@ x = -5`) cannot be taken
+    /// for a range.
+    pub fn parse_header(header: &str) -> Option<(usize, usize, usize, usize)> {
+        let (ranges, _) = header.strip_prefix("@@ ")?.split_once(" @@")?;
+        let (old, new) = ranges.split_once(' ')?;
+        let range = |part: &str| -> Option<(usize, usize)> {
+            match part.split_once(',') {
+                Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+                None => Some((part.parse().ok()?, 1)),
+            }
+        };
+        let (old_start, old_count) = range(old.strip_prefix('-')?)?;
+        let (new_start, new_count) = range(new.strip_prefix('+')?)?;
+        Some((old_start, old_count, new_start, new_count))
+    }
+}
+
+impl DiffResult {
+    /// Result for content that is binary on either side
+    pub fn binary() -> Self {
+        Self { hunks: Vec::new(), stats: DiffStats::default(), is_binary: true }
+    }
+
+    /// Rebuild a result from unified diff text, e.g. the diff stored on an
+    /// event. File headers and `\ No newline` markers are skipped.
+    pub fn from_unified_diff(diff: &str) -> Self {
+        let mut hunks: Vec<DiffHunk> = Vec::new();
+        for line in diff.lines() {
+            if let Some((old_start, old_len, new_start, new_len)) = DiffHunk::parse_header(line) {
+                hunks.push(DiffHunk {
+                    old_start: old_start.saturating_sub(1),
+                    old_len,
+                    new_start: new_start.saturating_sub(1),
+                    new_len,
+                    operations: Vec::new(),
+                    word_ranges: Vec::new(),
+                });
+                continue;
+            }
+            // Lines before the first hunk are file headers
+            let Some(hunk) = hunks.last_mut() else { continue };
+            let operation = match line.chars().next() {
+                Some('+') => DiffOperation::Insert(line[1..].to_string()),
+                Some('-') => DiffOperation::Delete(line[1..].to_string()),
+                Some(' ') => DiffOperation::Equal(line[1..].to_string()),
+                None => DiffOperation::Equal(String::new()),
+                _ => continue,
+            };
+            hunk.operations.push(operation);
+        }
+
+        Self {
+            hunks,
+            stats: DiffStats::from_unified_diff(diff),
+            is_binary: is_binary_diff(diff),
+        }
+    }
+
+    /// Fill in each hunk's word ranges, see [`word_ranges`]
+    pub fn with_word_ranges(mut self) -> Self {
+        for hunk in &mut self.hunks {
+            hunk.word_ranges = word_ranges(&hunk.operations);
+        }
+        self
+    }
+
+    /// Merge hunks whose changes are fewer than `gap` unchanged lines apart,
+    /// the lines between them becoming context. `old` is the content the
+    /// result was diffed from. A gap of 0 never merges.
+    pub fn merge_hunks(mut self, old: &str, gap: usize) -> Self {
+        if gap == 0 || self.hunks.len() < 2 {
+            return self;
+        }
+        let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+        let mut merged: Vec<DiffHunk> = Vec::with_capacity(self.hunks.len());
+        for hunk in self.hunks {
+            let Some(prev) = merged.last_mut() else {
+                merged.push(hunk);
+                continue;
+            };
+            let between = prev.old_start + prev.old_len..hunk.old_start;
+            let unchanged = prev.trailing_equal() + between.len() + hunk.leading_equal();
+            let Some(lines) = old_lines.get(between).filter(|_| unchanged < gap) else {
+                merged.push(hunk);
+                continue;
+            };
+            if !prev.word_ranges.is_empty() {
+                prev.word_ranges.resize(prev.word_ranges.len() + lines.len(), Vec::new());
+                prev.word_ranges.extend(hunk.word_ranges);
+            }
+            prev.operations.extend(lines.iter().map(|line| DiffOperation::Equal(line.to_string())));
+            prev.operations.extend(hunk.operations);
+            prev.old_len = hunk.old_start + hunk.old_len - prev.old_start;
+            prev.new_len = hunk.new_start + hunk.new_len - prev.new_start;
+        }
+        self.stats.hunks = merged.len();
+        self.hunks = merged;
+        self
+    }
 }
 
 /// Individual diff operation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", content = "line")]
 pub enum DiffOperation {
     Equal(String),
     Insert(String),
     Delete(String),
 }
 
+impl DiffOperation {
+    /// The line as written in a unified diff, after its ` `, `+` or `-` marker
+    pub fn unified_line(&self) -> String {
+        match self {
+            DiffOperation::Equal(line) => format!(" {}", line.trim_end()),
+            DiffOperation::Insert(line) => format!("+{}", line.trim_end()),
+            DiffOperation::Delete(line) => format!("-{}", line.trim_end()),
+        }
+    }
+
+    /// The line exactly as it must appear in a patch: trailing whitespace
+    /// kept, and a `\ No newline at end of file` marker after a last line
+    /// without one
+    pub fn patch_line(&self) -> String {
+        let (marker, line) = match self {
+            DiffOperation::Equal(line) => (' ', line),
+            DiffOperation::Insert(line) => ('+', line),
+            DiffOperation::Delete(line) => ('-', line),
+        };
+        match line.strip_suffix('\n') {
+            Some(line) => format!("{}{}", marker, line),
+            None => format!("{}{}\n\\ No newline at end of file", marker, line),
+        }
+    }
+}
+
+/// Lines longer than this many characters are not diffed word by word
+pub const MAX_WORD_DIFF_CHARS: usize = 4000;
+
+/// Byte ranges of the words that changed in each line of `operations`,
+/// parallel to it. A run of removed lines directly followed by a run of added
+/// lines is paired up line by line, and a pair is only diffed word by word
+/// when the two lines share at least half their text. Other lines get no
+/// ranges.
+pub fn word_ranges(operations: &[DiffOperation]) -> Vec<Vec<Range<usize>>> {
+    static SPLITTER: std::sync::OnceLock<WordSplitter> = std::sync::OnceLock::new();
+    let splitter = SPLITTER.get_or_init(WordSplitter::default);
+
+    let mut ranges = vec![Vec::new(); operations.len()];
+    let mut i = 0;
+    while i < operations.len() {
+        let deletes = operations[i..].iter().take_while(|op| matches!(op, DiffOperation::Delete(_))).count();
+        let inserts = operations[i + deletes..].iter().take_while(|op| matches!(op, DiffOperation::Insert(_))).count();
+        for k in 0..deletes.min(inserts) {
+            let (old_index, new_index) = (i + k, i + deletes + k);
+            let (DiffOperation::Delete(old), DiffOperation::Insert(new)) = (&operations[old_index], &operations[new_index]) else {
+                continue;
+            };
+            let (old, new) = (old.trim_end_matches(['\r', '\n']), new.trim_end_matches(['\r', '\n']));
+            if old.chars().count() > MAX_WORD_DIFF_CHARS || new.chars().count() > MAX_WORD_DIFF_CHARS {
+                continue;
+            }
+            if let Some(changes) = splitter.diff(old, new) {
+                (ranges[old_index], ranges[new_index]) = changed_ranges(&changes);
+            }
+        }
+        i += (deletes + inserts).max(1);
+    }
+    ranges
+}
+
+/// Byte ranges of the deleted words in the old line and the inserted words
+/// in the new one
+fn changed_ranges(changes: &[WordChange]) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let (mut old, mut new) = (Vec::new(), Vec::new());
+    let (mut old_pos, mut new_pos) = (0, 0);
+    for change in changes {
+        match change {
+            WordChange::Equal(text) => {
+                old_pos += text.len();
+                new_pos += text.len();
+            }
+            WordChange::Delete(text) => {
+                old.push(old_pos..old_pos + text.len());
+                old_pos += text.len();
+            }
+            WordChange::Insert(text) => {
+                new.push(new_pos..new_pos + text.len());
+                new_pos += text.len();
+            }
+        }
+    }
+    (old, new)
+}
+
 /// Statistics about the diff
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DiffStats {
     pub lines_added: usize,
     pub lines_removed: usize,
@@ -55,6 +303,22 @@ impl DiffStats {
     pub fn net_change(&self) -> isize {
         self.lines_added as isize - self.lines_removed as isize
     }
+    
+    /// Compute statistics from unified diff text, ignoring file headers
+    pub fn from_unified_diff(diff: &str) -> Self {
+        let mut stats = DiffStats::default();
+        for line in diff.lines() {
+            if line.starts_with("@@") {
+                stats.hunks += 1;
+            } else if line.starts_with('+') && !line.starts_with("+++") {
+                stats.lines_added += 1;
+            } else if line.starts_with('-') && !line.starts_with("---") {
+                stats.lines_removed += 1;
+            }
+        }
+        stats.lines_modified = stats.lines_added.min(stats.lines_removed);
+        stats
+    }
 }
 
 /// Myers diff algorithm implementation
@@ -62,11 +326,15 @@ pub struct MyersAlgorithm;
 
 impl DiffAlgorithm for MyersAlgorithm {
     fn diff(&self, old: &str, new: &str) -> DiffResult {
+        self.diff_with_context(old, new, DEFAULT_CONTEXT_LINES)
+    }
+
+    fn diff_with_context(&self, old: &str, new: &str, context_lines: usize) -> DiffResult {
         let diff = TextDiff::configure()
             .algorithm(Algorithm::Myers)
             .diff_lines(old, new);
-        
-        self.convert_to_result(&diff)
+
+        self.convert_to_result(&diff, context_lines)
     }
     
     fn name(&self) -> &'static str {
@@ -83,11 +351,15 @@ pub struct PatienceAlgorithm;
 
 impl DiffAlgorithm for PatienceAlgorithm {
     fn diff(&self, old: &str, new: &str) -> DiffResult {
+        self.diff_with_context(old, new, DEFAULT_CONTEXT_LINES)
+    }
+
+    fn diff_with_context(&self, old: &str, new: &str, context_lines: usize) -> DiffResult {
         let diff = TextDiff::configure()
             .algorithm(Algorithm::Patience)
             .diff_lines(old, new);
-            
-        self.convert_to_result(&diff)
+
+        self.convert_to_result(&diff, context_lines)
     }
     
     fn name(&self) -> &'static str {
@@ -104,11 +376,15 @@ pub struct LcsAlgorithm;
 
 impl DiffAlgorithm for LcsAlgorithm {
     fn diff(&self, old: &str, new: &str) -> DiffResult {
+        self.diff_with_context(old, new, DEFAULT_CONTEXT_LINES)
+    }
+
+    fn diff_with_context(&self, old: &str, new: &str, context_lines: usize) -> DiffResult {
         let diff = TextDiff::configure()
             .algorithm(Algorithm::Lcs)
             .diff_lines(old, new);
-            
-        self.convert_to_result(&diff)
+
+        self.convert_to_result(&diff, context_lines)
     }
     
     fn name(&self) -> &'static str {
@@ -120,53 +396,201 @@ impl DiffAlgorithm for LcsAlgorithm {
     }
 }
 
-// Shared implementation for converting similar::TextDiff to our DiffResult
-trait DiffConverter {
-    fn convert_to_result(&self, diff: &TextDiff<str>) -> DiffResult {
-        let mut hunks = Vec::new();
-        let mut stats = DiffStats::default();
-        
-        for (_idx, group) in diff.grouped_ops(3).iter().enumerate() {
-            let mut operations = Vec::new();
-            
-            let old_start = group[0].old_range().start;
-            let new_start = group[0].new_range().start;
-            let old_len = group.iter().map(|op| op.old_range().len()).sum();
-            let new_len = group.iter().map(|op| op.new_range().len()).sum();
-            
-            for op in group {
-                for change in diff.iter_changes(op) {
-                    let content = change.value().to_string();
-                    
-                    match change.tag() {
-                        ChangeTag::Equal => {
-                            operations.push(DiffOperation::Equal(content));
-                        }
-                        ChangeTag::Insert => {
-                            operations.push(DiffOperation::Insert(content));
-                            stats.lines_added += 1;
-                        }
-                        ChangeTag::Delete => {
-                            operations.push(DiffOperation::Delete(content));
-                            stats.lines_removed += 1;
-                        }
+/// Histogram diff, after git's: patience diff, except that the anchor
+/// between two ranges is the common region whose lines occur least often,
+/// not only lines occurring exactly once. Boilerplate repeated across a file
+/// then no longer pulls hunk boundaries onto the wrong copy.
+pub struct HistogramAlgorithm;
+
+/// Lines occurring more often than this in a range are never anchors
+const HISTOGRAM_MAX_CHAIN: usize = 64;
+
+impl DiffAlgorithm for HistogramAlgorithm {
+    fn diff(&self, old: &str, new: &str) -> DiffResult {
+        self.diff_with_context(old, new, DEFAULT_CONTEXT_LINES)
+    }
+
+    fn diff_with_context(&self, old: &str, new: &str, context_lines: usize) -> DiffResult {
+        let old_lines = old.tokenize_lines();
+        let new_lines = new.tokenize_lines();
+        result_from_ops(histogram_ops(&old_lines, &new_lines), &old_lines, &new_lines, context_lines)
+    }
+
+    fn name(&self) -> &'static str {
+        "Histogram"
+    }
+
+    fn description(&self) -> &'static str {
+        "Histogram diff - patience with rare lines as anchors, stable on repeated lines"
+    }
+}
+
+fn histogram_ops(old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+    let mut hook = Replace::new(Capture::new());
+    histogram_diff(&mut hook, old, 0..old.len(), new, 0..new.len());
+    // Capture and Replace never fail
+    let _ = hook.finish();
+    hook.into_inner().into_ops()
+}
+
+fn histogram_diff<D: DiffHook>(
+    hook: &mut D,
+    old: &[&str],
+    mut old_range: Range<usize>,
+    new: &[&str],
+    mut new_range: Range<usize>,
+) {
+    // Common prefix and suffix are equal as they are
+    let prefix = old[old_range.clone()]
+        .iter()
+        .zip(&new[new_range.clone()])
+        .take_while(|(a, b)| a == b)
+        .count();
+    if prefix > 0 {
+        let _ = hook.equal(old_range.start, new_range.start, prefix);
+        old_range.start += prefix;
+        new_range.start += prefix;
+    }
+    let suffix = old[old_range.clone()]
+        .iter()
+        .rev()
+        .zip(new[new_range.clone()].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    old_range.end -= suffix;
+    new_range.end -= suffix;
+
+    if old_range.is_empty() && !new_range.is_empty() {
+        let _ = hook.insert(old_range.start, new_range.start, new_range.len());
+    } else if new_range.is_empty() && !old_range.is_empty() {
+        let _ = hook.delete(old_range.start, old_range.len(), new_range.start);
+    } else if !old_range.is_empty() {
+        match histogram_anchor(old, old_range.clone(), new, new_range.clone()) {
+            Some((old_at, new_at, len)) => {
+                histogram_diff(hook, old, old_range.start..old_at, new, new_range.start..new_at);
+                let _ = hook.equal(old_at, new_at, len);
+                histogram_diff(hook, old, old_at + len..old_range.end, new, new_at + len..new_range.end);
+            }
+            // Only lines too common to anchor on are shared: as git does, let Myers decide
+            None => {
+                let _ = similar::algorithms::myers::diff(
+                    &mut similar::algorithms::NoFinishHook::new(&mut *hook),
+                    old,
+                    old_range.clone(),
+                    new,
+                    new_range.clone(),
+                );
+            }
+        }
+    }
+
+    if suffix > 0 {
+        let _ = hook.equal(old_range.end, new_range.end, suffix);
+    }
+}
+
+/// The common region of the two ranges, as `(old_start, new_start, len)`,
+/// whose rarest line occurs least often in `old_range`; the longest such
+/// region on a tie
+fn histogram_anchor(
+    old: &[&str],
+    old_range: Range<usize>,
+    new: &[&str],
+    new_range: Range<usize>,
+) -> Option<(usize, usize, usize)> {
+    let mut occurrences: HashMap<&str, Vec<usize>> = HashMap::new();
+    for i in old_range.clone() {
+        occurrences.entry(old[i]).or_default().push(i);
+    }
+
+    let mut best: Option<(usize, usize, usize)> = None;
+    let mut best_count = HISTOGRAM_MAX_CHAIN + 1;
+    let mut j = new_range.start;
+    while j < new_range.end {
+        let mut next_j = j + 1;
+        let positions = occurrences.get(new[j]).map_or(&[][..], Vec::as_slice);
+        if positions.len() <= HISTOGRAM_MAX_CHAIN {
+            for &i in positions {
+                // Grow the match in both directions, tracking its rarest line
+                let (mut start_old, mut start_new, mut end_old, mut end_new) = (i, j, i + 1, j + 1);
+                while start_old > old_range.start && start_new > new_range.start && old[start_old - 1] == new[start_new - 1] {
+                    start_old -= 1;
+                    start_new -= 1;
+                }
+                while end_old < old_range.end && end_new < new_range.end && old[end_old] == new[end_new] {
+                    end_old += 1;
+                    end_new += 1;
+                }
+                let count = (start_old..end_old)
+                    .map(|k| occurrences.get(old[k]).map_or(usize::MAX, Vec::len))
+                    .min()
+                    .unwrap_or(usize::MAX);
+                let len = end_old - start_old;
+                if count < best_count || (count == best_count && best.map_or(true, |(_, _, best_len)| len > best_len)) {
+                    best = Some((start_old, start_new, len));
+                    best_count = count;
+                }
+                next_j = next_j.max(end_new);
+            }
+        }
+        j = next_j;
+    }
+    best
+}
+
+/// Group `ops` into hunks with `context_lines` lines of context
+fn result_from_ops(ops: Vec<DiffOp>, old: &[&str], new: &[&str], context_lines: usize) -> DiffResult {
+    let mut hunks = Vec::new();
+    let mut stats = DiffStats::default();
+
+    for group in group_diff_ops(ops, context_lines) {
+        let mut operations = Vec::new();
+
+        let old_start = group[0].old_range().start;
+        let new_start = group[0].new_range().start;
+        let old_len = group.iter().map(|op| op.old_range().len()).sum();
+        let new_len = group.iter().map(|op| op.new_range().len()).sum();
+
+        for op in &group {
+            for change in op.iter_changes(old, new) {
+                let content = change.value().to_string();
+
+                match change.tag() {
+                    ChangeTag::Equal => {
+                        operations.push(DiffOperation::Equal(content));
+                    }
+                    ChangeTag::Insert => {
+                        operations.push(DiffOperation::Insert(content));
+                        stats.lines_added += 1;
+                    }
+                    ChangeTag::Delete => {
+                        operations.push(DiffOperation::Delete(content));
+                        stats.lines_removed += 1;
                     }
                 }
             }
-            
-            hunks.push(DiffHunk {
-                old_start,
-                old_len, 
-                new_start,
-                new_len,
-                operations,
-            });
         }
-        
-        stats.hunks = hunks.len();
-        stats.lines_modified = stats.lines_added.min(stats.lines_removed);
-        
-        DiffResult { hunks, stats }
+
+        hunks.push(DiffHunk {
+            old_start,
+            old_len,
+            new_start,
+            new_len,
+            operations,
+            word_ranges: Vec::new(),
+        });
+    }
+
+    stats.hunks = hunks.len();
+    stats.lines_modified = stats.lines_added.min(stats.lines_removed);
+
+    DiffResult { hunks, stats, is_binary: false }
+}
+
+// Shared implementation for converting similar::TextDiff to our DiffResult
+trait DiffConverter {
+    fn convert_to_result(&self, diff: &TextDiff<str>, context_lines: usize) -> DiffResult {
+        result_from_ops(diff.ops().to_vec(), diff.old_slices(), diff.new_slices(), context_lines)
     }
 }
 
@@ -176,15 +600,18 @@ impl DiffConverter for LcsAlgorithm {}
 
 /// Available diff algorithms
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[derive(Default)]
 pub enum DiffAlgorithmType {
+    #[default]
     Myers,
     Patience, 
     Lcs,
+    Histogram,
 }
 
 impl DiffAlgorithmType {
     pub fn all() -> &'static [DiffAlgorithmType] {
-        &[Self::Myers, Self::Patience, Self::Lcs]
+        &[Self::Myers, Self::Patience, Self::Lcs, Self::Histogram]
     }
     
     pub fn create(&self) -> Box<dyn DiffAlgorithm> {
@@ -192,14 +619,27 @@ impl DiffAlgorithmType {
             Self::Myers => Box::new(MyersAlgorithm),
             Self::Patience => Box::new(PatienceAlgorithm),
             Self::Lcs => Box::new(LcsAlgorithm),
+            Self::Histogram => Box::new(HistogramAlgorithm),
         }
     }
     
+    /// Diff operations between two sequences of lines
+    pub(crate) fn line_ops(&self, old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+        let algorithm = match self {
+            Self::Myers => Algorithm::Myers,
+            Self::Patience => Algorithm::Patience,
+            Self::Lcs => Algorithm::Lcs,
+            Self::Histogram => return histogram_ops(old, new),
+        };
+        similar::capture_diff_slices(algorithm, old, new)
+    }
+
     pub fn name(&self) -> &'static str {
         match self {
             Self::Myers => "Myers",
             Self::Patience => "Patience",
             Self::Lcs => "LCS",
+            Self::Histogram => "Histogram",
         }
     }
 }
@@ -210,11 +650,6 @@ impl std::fmt::Display for DiffAlgorithmType {
     }
 }
 
-impl Default for DiffAlgorithmType {
-    fn default() -> Self {
-        Self::Myers
-    }
-}
 
 #[cfg(test)]
 mod tests {
@@ -243,6 +678,36 @@ mod tests {
         assert!(!result.hunks.is_empty());
     }
     
+    #[test]
+    fn test_histogram_diff_anchors_on_rare_lines() {
+        let histogram = HistogramAlgorithm;
+        let old = "fn a() {\n    x\n}\n\nfn b() {\n    y\n}\n";
+        let new = "fn a() {\n    x\n}\n\nfn c() {\n    z\n}\n\nfn b() {\n    y\n}\n";
+
+        let result = histogram.diff(old, new);
+        assert_eq!((result.stats.lines_added, result.stats.lines_removed), (4, 0));
+        // The new function is inserted whole, not spliced into the braces of its neighbours
+        let inserted: Vec<String> = result.hunks[0]
+            .operations
+            .iter()
+            .filter(|op| matches!(op, DiffOperation::Insert(_)))
+            .map(DiffOperation::unified_line)
+            .collect();
+        assert_eq!(inserted, ["+fn c() {", "+    z", "+}", "+"]);
+
+        let generator = crate::diff::DiffGenerator::new(DiffAlgorithmType::Histogram);
+        for (old, new, added, removed) in [
+            ("", "a\nb\n", 2, 0),
+            ("a\nb\n", "", 0, 2),
+            ("x\nx\nx\n", "x\ny\nx\n", 1, 1),
+            ("a\nb\nc", "c\nb\na", 2, 2),
+            ("same\n", "same\n", 0, 0),
+        ] {
+            let stats = generator.generate(old, new).stats;
+            assert_eq!((stats.lines_added, stats.lines_removed), (added, removed), "{:?} -> {:?}", old, new);
+        }
+    }
+
     #[test]
     fn test_diff_stats() {
         let stats = DiffStats {
@@ -255,4 +720,30 @@ mod tests {
         assert_eq!(stats.total_changes(), 8);
         assert_eq!(stats.net_change(), 2);
     }
+
+    #[test]
+    fn test_word_ranges_of_paired_lines() {
+        let operations = vec![
+            DiffOperation::Equal("fn total() {\n".to_string()),
+            DiffOperation::Delete("    price * count\n".to_string()),
+            DiffOperation::Delete("    // old note\n".to_string()),
+            DiffOperation::Insert("    price * amount\n".to_string()),
+            DiffOperation::Insert("let unrelated = vec![];\n".to_string()),
+        ];
+        let ranges = word_ranges(&operations);
+
+        assert_eq!(ranges.len(), operations.len());
+        assert_eq!(ranges[1], vec![12..17]);
+        assert_eq!(ranges[3], vec![12..18]);
+        // Lines without a similar partner keep their whole-line colour
+        assert!(ranges[0].is_empty() && ranges[2].is_empty() && ranges[4].is_empty());
+
+        // Very long lines are not diffed word by word
+        let long = "word ".repeat(MAX_WORD_DIFF_CHARS);
+        let operations = vec![
+            DiffOperation::Delete(long.clone()),
+            DiffOperation::Insert(format!("{}more", long)),
+        ];
+        assert!(word_ranges(&operations).iter().all(Vec::is_empty));
+    }
 }
\ No newline at end of file
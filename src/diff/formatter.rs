@@ -1,5 +1,11 @@
+use std::borrow::Cow;
 use std::path::Path;
+use serde::Serialize;
 use super::algorithms::{DiffResult, DiffOperation};
+use super::words::{render_inline, WordSplitter};
+
+/// The path git patches use for the missing side of a created or deleted file
+pub const DEV_NULL: &str = "/dev/null";
 
 /// Different output formats for diffs
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -8,6 +14,22 @@ pub enum DiffFormat {
     SideBySide,
     Context,
     GitPatch,
+    /// Unified, with modified lines shown once as `~` lines marking the
+    /// changed words `[-old-]{+new+}`
+    WordDiff,
+    /// The result itself as JSON, with the two paths added
+    Json,
+}
+
+/// A result as [`DiffFormat::Json`] writes it. Parses back into a
+/// `DiffResult`, which ignores the paths. Paths that are not UTF-8 are
+/// written lossily, as JSON strings must be.
+#[derive(Serialize)]
+struct JsonDiff<'a> {
+    old_path: Cow<'a, str>,
+    new_path: Cow<'a, str>,
+    #[serde(flatten)]
+    result: &'a DiffResult,
 }
 
 /// Formats diff results into various text representations
@@ -18,40 +40,87 @@ impl DiffFormatter {
     pub fn format_unified<P: AsRef<Path>>(result: &DiffResult, old_path: P, new_path: P) -> String {
         let old_path = old_path.as_ref();
         let new_path = new_path.as_ref();
+        if result.is_binary {
+            return Self::binary_line(old_path, new_path);
+        }
         
         let mut output = Vec::new();
         output.push(format!("--- {}", old_path.display()));
         output.push(format!("+++ {}", new_path.display()));
         
         for hunk in &result.hunks {
-            // Add hunk header
-            output.push(format!(
-                "@@ -{},{} +{},{} @@",
-                hunk.old_start + 1,
-                hunk.old_len,
-                hunk.new_start + 1,
-                hunk.new_len
-            ));
-            
-            // Add operations
-            for op in &hunk.operations {
-                match op {
-                    DiffOperation::Equal(line) => {
-                        output.push(format!(" {}", line.trim_end()));
-                    }
-                    DiffOperation::Insert(line) => {
-                        output.push(format!("+{}", line.trim_end()));
-                    }
-                    DiffOperation::Delete(line) => {
-                        output.push(format!("-{}", line.trim_end()));
+            output.push(hunk.header());
+            output.extend(hunk.operations.iter().map(DiffOperation::unified_line));
+        }
+        
+        output.join("\n")
+    }
+    
+    /// Format a diff result as a unified diff with word-level changes on
+    /// modified lines, splitting words at whitespace and punctuation
+    pub fn format_word_diff<P: AsRef<Path>>(result: &DiffResult, old_path: P, new_path: P) -> String {
+        Self::format_word_diff_with(result, old_path, new_path, &WordSplitter::default())
+    }
+
+    /// Format a word diff splitting words with `splitter`. Deleted lines are
+    /// paired in order with the inserted lines that follow them; pairs with
+    /// too little in common, and lines left unpaired, render as in a unified
+    /// diff.
+    pub fn format_word_diff_with<P: AsRef<Path>>(
+        result: &DiffResult,
+        old_path: P,
+        new_path: P,
+        splitter: &WordSplitter,
+    ) -> String {
+        if result.is_binary {
+            return Self::binary_line(old_path.as_ref(), new_path.as_ref());
+        }
+        let mut output = Vec::new();
+        output.push(format!("--- {}", old_path.as_ref().display()));
+        output.push(format!("+++ {}", new_path.as_ref().display()));
+
+        for hunk in &result.hunks {
+            output.push(hunk.header());
+            let ops = &hunk.operations;
+            let mut i = 0;
+            while i < ops.len() {
+                let deletes = ops[i..].iter().take_while(|op| matches!(op, DiffOperation::Delete(_))).count();
+                let inserts = ops[i + deletes..].iter().take_while(|op| matches!(op, DiffOperation::Insert(_))).count();
+                if deletes == 0 || inserts == 0 {
+                    output.push(ops[i].unified_line());
+                    i += 1;
+                    continue;
+                }
+
+                let (removed, added) = (&ops[i..i + deletes], &ops[i + deletes..i + deletes + inserts]);
+                let mut removed_lines = Vec::new();
+                let mut added_lines = Vec::new();
+                let mut modified = Vec::new();
+                for (index, (old, new)) in removed.iter().zip(added).enumerate() {
+                    if let (DiffOperation::Delete(old), DiffOperation::Insert(new)) = (old, new) {
+                        match splitter.diff(old.trim_end(), new.trim_end()) {
+                            Some(changes) => modified.push(format!("~{}", render_inline(&changes))),
+                            None => {
+                                removed_lines.push(removed[index].unified_line());
+                                added_lines.push(added[index].unified_line());
+                            }
+                        }
                     }
                 }
+                let paired = deletes.min(inserts);
+                removed_lines.extend(removed[paired..].iter().map(DiffOperation::unified_line));
+                added_lines.extend(added[paired..].iter().map(DiffOperation::unified_line));
+
+                output.extend(modified);
+                output.extend(removed_lines);
+                output.extend(added_lines);
+                i += deletes + inserts;
             }
         }
-        
+
         output.join("\n")
     }
-    
+
     /// Format a diff result as side-by-side comparison
     pub fn format_side_by_side<P: AsRef<Path>>(
         result: &DiffResult, 
@@ -61,14 +130,17 @@ impl DiffFormatter {
     ) -> String {
         let old_path = old_path.as_ref();
         let new_path = new_path.as_ref();
+        if result.is_binary {
+            return Self::binary_line(old_path, new_path);
+        }
         
         let mut output = Vec::new();
         let half_width = (width - 3) / 2; // Account for separator " | "
         
         output.push(format!(
-            "{:<width$} | {}", 
+            "{:<width$} | +++ {}", 
             format!("--- {}", old_path.display()), 
-            format!("+++ {}", new_path.display()),
+            new_path.display(),
             width = half_width
         ));
         output.push("-".repeat(width));
@@ -98,27 +170,135 @@ impl DiffFormatter {
         output.join("\n")
     }
     
-    /// Format as Git patch format
+    /// Format as a Git patch that `git apply` accepts. An old path of
+    /// `/dev/null` makes it a file creation, a new path of `/dev/null` a
+    /// deletion.
     pub fn format_git_patch<P: AsRef<Path>>(result: &DiffResult, old_path: P, new_path: P) -> String {
+        if result.is_binary {
+            return Self::format_git_binary(old_path, new_path);
+        }
         let old_path = old_path.as_ref();
         let new_path = new_path.as_ref();
-        
-        let mut output = Vec::new();
-        
-        // Git patch header
-        output.push(format!("diff --git a/{} b/{}", old_path.display(), new_path.display()));
-        output.push(format!("index 0000000..1111111 100644")); // Placeholder hashes
-        
-        // Standard unified diff content
-        output.push(Self::format_unified(result, old_path, new_path));
-        
+        let created = old_path == Path::new(DEV_NULL);
+        let deleted = new_path == Path::new(DEV_NULL);
+        let a = if created { new_path } else { old_path };
+        let b = if deleted { old_path } else { new_path };
+
+        let mut output = vec![format!("diff --git a/{} b/{}", a.display(), b.display())];
+        if created {
+            output.push("new file mode 100644".to_string());
+            output.push("index 0000000..1111111".to_string()); // Placeholder hashes
+        } else if deleted {
+            output.push("deleted file mode 100644".to_string());
+            output.push("index 1111111..0000000".to_string());
+        } else {
+            output.push("index 0000000..1111111 100644".to_string());
+        }
+        Self::push_git_hunks(&mut output, result, old_path, new_path);
+
+        output.join("\n")
+    }
+
+    /// Format a renamed file as a Git patch, with the changes made along
+    /// with the rename if there were any
+    pub fn format_git_rename<P: AsRef<Path>>(result: Option<&DiffResult>, from: P, to: P) -> String {
+        let from = from.as_ref();
+        let to = to.as_ref();
+        let mut output = vec![
+            format!("diff --git a/{} b/{}", from.display(), to.display()),
+            format!("rename from {}", from.display()),
+            format!("rename to {}", to.display()),
+        ];
+        if let Some(result) = result {
+            Self::push_git_hunks(&mut output, result, from, to);
+        }
+
+        output.join("\n")
+    }
+
+    /// Format a change to a binary file the way git does without `--binary`
+    pub fn format_git_binary<P: AsRef<Path>>(old_path: P, new_path: P) -> String {
+        let old_path = old_path.as_ref();
+        let new_path = new_path.as_ref();
+        let created = old_path == Path::new(DEV_NULL);
+        let deleted = new_path == Path::new(DEV_NULL);
+        let a = if created { new_path } else { old_path };
+        let b = if deleted { old_path } else { new_path };
+
+        let mut output = vec![format!("diff --git a/{} b/{}", a.display(), b.display())];
+        if created {
+            output.push("new file mode 100644".to_string());
+        } else if deleted {
+            output.push("deleted file mode 100644".to_string());
+        }
+        let side = |prefix: &str, path: &Path, absent: bool| match absent {
+            true => DEV_NULL.to_string(),
+            false => format!("{}/{}", prefix, path.display()),
+        };
+        output.push(format!(
+            "Binary files {} and {} differ",
+            side("a", a, created),
+            side("b", b, deleted)
+        ));
+
         output.join("\n")
     }
+
+    /// `Binary files <old> and <new> differ`, in place of the hunks of a
+    /// binary result
+    fn binary_line(old_path: &Path, new_path: &Path) -> String {
+        format!("Binary files {} and {} differ", old_path.display(), new_path.display())
+    }
+
+    /// `---`/`+++` headers and hunks with exact lines. Left out entirely when
+    /// there are no hunks, as git does for empty files and pure renames.
+    fn push_git_hunks(output: &mut Vec<String>, result: &DiffResult, old_path: &Path, new_path: &Path) {
+        let side = |prefix: &str, path: &Path| match path == Path::new(DEV_NULL) {
+            true => DEV_NULL.to_string(),
+            false => format!("{}/{}", prefix, path.display()),
+        };
+        if result.is_binary {
+            output.push(format!("Binary files {} and {} differ", side("a", old_path), side("b", new_path)));
+            return;
+        }
+        if result.hunks.is_empty() {
+            return;
+        }
+        output.push(format!("--- {}", side("a", old_path)));
+        output.push(format!("+++ {}", side("b", new_path)));
+        for hunk in &result.hunks {
+            // An empty range starts at the line before it, so `-0,0` for a new file
+            let start = |start: usize, len: usize| if len == 0 { start } else { start + 1 };
+            output.push(format!(
+                "@@ -{},{} +{},{} @@",
+                start(hunk.old_start, hunk.old_len),
+                hunk.old_len,
+                start(hunk.new_start, hunk.new_len),
+                hunk.new_len
+            ));
+            output.extend(hunk.operations.iter().map(DiffOperation::patch_line));
+        }
+    }
     
+    /// Format as one line of JSON: paths, `is_binary`, stats and hunks of
+    /// `{"op": "Insert" | "Delete" | "Equal", "line": ...}` operations
+    pub fn format_json<P: AsRef<Path>>(result: &DiffResult, old_path: P, new_path: P) -> String {
+        let json = JsonDiff {
+            old_path: old_path.as_ref().to_string_lossy(),
+            new_path: new_path.as_ref().to_string_lossy(),
+            result,
+        };
+        // Only strings, numbers and booleans, none of which can fail
+        serde_json::to_string(&json).expect("diff results serialize to JSON")
+    }
+
     /// Format diff statistics as a summary
     pub fn format_stats(result: &DiffResult) -> String {
         let stats = &result.stats;
         
+        if result.is_binary {
+            return "Binary files differ".to_string();
+        }
         if stats.total_changes() == 0 {
             return "No changes".to_string();
         }
@@ -165,15 +345,18 @@ impl DiffFormatter {
             }
             DiffFormat::GitPatch => Self::format_git_patch(result, old_path, new_path),
             DiffFormat::Context => Self::format_unified(result, old_path, new_path), // Same as unified for now
+            DiffFormat::WordDiff => Self::format_word_diff(result, old_path, new_path),
+            DiffFormat::Json => Self::format_json(result, old_path, new_path),
         }
     }
     
     fn truncate_line(line: &str, max_width: usize) -> String {
-        if line.len() > max_width {
+        // Counted in characters so multi-byte text is never cut mid-character
+        if line.chars().count() > max_width {
             if max_width > 3 {
-                format!("{}...", &line[..max_width - 3])
+                format!("{}...", line.chars().take(max_width - 3).collect::<String>())
             } else {
-                line[..max_width].to_string()
+                line.chars().take(max_width).collect()
             }
         } else {
             line.to_string()
@@ -219,4 +402,76 @@ mod tests {
         assert!(formatted.contains("diff --git"));
         assert!(formatted.contains("index 0000000..1111111"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_format_binary() {
+        let result = DiffResult::binary();
+        assert_eq!(DiffFormatter::format_unified(&result, "a/logo.png", "b/logo.png"), "Binary files a/logo.png and b/logo.png differ");
+        assert_eq!(DiffFormatter::format_stats(&result), "Binary files differ");
+        assert_eq!(
+            DiffFormatter::format_git_patch(&result, "logo.png", "logo.png"),
+            "diff --git a/logo.png b/logo.png\nBinary files a/logo.png and b/logo.png differ"
+        );
+        assert_eq!(
+            DiffFormatter::format_git_rename(Some(&result), "logo.png", "icon.png"),
+            "diff --git a/logo.png b/icon.png\nrename from logo.png\nrename to icon.png\nBinary files a/logo.png and b/icon.png differ"
+        );
+
+        // Binary diff text reads back as a binary result
+        let text = DiffFormatter::format_unified(&result, "logo.png", "logo.png");
+        assert!(DiffResult::from_unified_diff(&text).is_binary);
+        assert!(!DiffResult::from_unified_diff(&DiffFormatter::format_unified(&create_test_diff(), "a", "b")).is_binary);
+    }
+
+    #[test]
+    fn test_format_word_diff() {
+        let myers = MyersAlgorithm;
+        let result = myers.diff(
+            "fn area(w: u32) -> u32 {\n    w * 2\n}\nold tail\n",
+            "fn area(w: u32) -> u32 {\n    w * 4\n}\nadded line\nanother one\n",
+        );
+        let formatted = DiffFormatter::format(&result, DiffFormat::WordDiff, "a.rs", "a.rs", None);
+
+        assert!(formatted.contains("~    w * [-2-]{+4+}"));
+        assert!(!formatted.contains("-    w * 2"));
+        // Lines with nothing in common and unpaired additions stay whole
+        assert!(formatted.contains("-old tail"));
+        assert!(formatted.contains("+added line"));
+        assert!(formatted.contains("+another one"));
+
+        let added = myers.diff("a\n", "a\nb\n");
+        let formatted = DiffFormatter::format(&added, DiffFormat::WordDiff, "a.txt", "a.txt", None);
+        assert_eq!(formatted, DiffFormatter::format_unified(&added, "a.txt", "a.txt"));
+    }
+
+    #[test]
+    fn test_format_json_round_trips() {
+        let myers = MyersAlgorithm;
+        let result = myers.diff("keep\nold\ntail\n", "keep\nnew\nadded\ntail\n").with_word_ranges();
+        let formatted = DiffFormatter::format(&result, DiffFormat::Json, "a.rs", "b.rs", None);
+
+        let value: serde_json::Value = serde_json::from_str(&formatted).unwrap();
+        assert_eq!((value["old_path"].as_str(), value["new_path"].as_str()), (Some("a.rs"), Some("b.rs")));
+        assert_eq!(value["stats"]["lines_added"], 2);
+        let hunk = &value["hunks"][0];
+        assert_eq!((hunk["old_start"].as_u64(), hunk["old_len"].as_u64()), (Some(0), Some(3)));
+        assert_eq!(hunk["operations"][1], serde_json::json!({"op": "Delete", "line": "old\n"}));
+
+        let parsed: DiffResult = serde_json::from_str(&formatted).unwrap();
+        assert_eq!(parsed, result);
+
+        let binary: DiffResult = serde_json::from_str(&DiffFormatter::format_json(&DiffResult::binary(), "a", "b")).unwrap();
+        assert!(binary.is_binary && binary.hunks.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_format_json_writes_non_utf8_paths_lossily() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(std::ffi::OsStr::from_bytes(b"caf\xe9.txt"));
+        let formatted = DiffFormatter::format_json(&MyersAlgorithm.diff("a\n", "b\n"), path, path);
+        let value: serde_json::Value = serde_json::from_str(&formatted).unwrap();
+        assert_eq!(value["old_path"], "caf\u{fffd}.txt");
+    }
+}
@@ -1,8 +1,17 @@
-use super::algorithms::{DiffAlgorithm, DiffAlgorithmType, DiffResult};
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+use similar::DiffTag;
+use super::algorithms::{is_binary, DiffAlgorithm, DiffAlgorithmType, DiffHunk, DiffOperation, DiffResult, DiffStats, DEFAULT_CONTEXT_LINES};
+
+/// Lines of each side held in memory at once by a streaming diff
+pub const DEFAULT_WINDOW_LINES: usize = 4096;
 
 /// High-level diff generator that can use different algorithms
 pub struct DiffGenerator {
     algorithm: Box<dyn DiffAlgorithm>,
+    context_lines: usize,
+    merge_gap: usize,
+    word_diff: bool,
 }
 
 impl DiffGenerator {
@@ -10,19 +19,62 @@ impl DiffGenerator {
     pub fn new(algorithm_type: DiffAlgorithmType) -> Self {
         Self {
             algorithm: algorithm_type.create(),
+            context_lines: DEFAULT_CONTEXT_LINES,
+            merge_gap: 0,
+            word_diff: false,
         }
     }
     
     /// Create a diff generator with a custom algorithm
     pub fn with_algorithm(algorithm: Box<dyn DiffAlgorithm>) -> Self {
-        Self { algorithm }
+        Self { algorithm, context_lines: DEFAULT_CONTEXT_LINES, merge_gap: 0, word_diff: false }
+    }
+
+    /// Lines of context around each hunk; 0 gives minimal hunks
+    pub fn with_context(mut self, lines: usize) -> Self {
+        self.context_lines = lines;
+        self
+    }
+
+    /// Merge hunks whose changes are fewer than `gap` unchanged lines apart;
+    /// 0 never merges. Changes within twice the context of each other always
+    /// share a hunk, so this only matters for larger gaps.
+    pub fn with_merge_gap(mut self, gap: usize) -> Self {
+        self.merge_gap = gap;
+        self
+    }
+
+    /// Also compute the changed words of paired removed and added lines
+    pub fn with_word_diff(mut self, word_diff: bool) -> Self {
+        self.word_diff = word_diff;
+        self
     }
     
-    /// Generate a diff between old and new content
+    /// Generate a diff between old and new content. Content with zero bytes
+    /// is binary and gives a result without hunks.
     pub fn generate(&self, old: &str, new: &str) -> DiffResult {
-        self.algorithm.diff(old, new)
+        if is_binary(old.as_bytes()) || is_binary(new.as_bytes()) {
+            return DiffResult::binary();
+        }
+        let result = self.algorithm
+            .diff_with_context(old, new, self.context_lines)
+            .merge_hunks(old, self.merge_gap);
+        if self.word_diff {
+            result.with_word_ranges()
+        } else {
+            result
+        }
     }
     
+    /// Generate a diff between raw file contents, which are binary when
+    /// either starts with a zero byte or invalid UTF-8 (see [`is_binary`])
+    pub fn generate_bytes(&self, old: &[u8], new: &[u8]) -> DiffResult {
+        if is_binary(old) || is_binary(new) {
+            return DiffResult::binary();
+        }
+        self.generate(&String::from_utf8_lossy(old), &String::from_utf8_lossy(new))
+    }
+
     /// Get the current algorithm name
     pub fn algorithm_name(&self) -> &str {
         self.algorithm.name()
@@ -44,13 +96,19 @@ impl Default for DiffGenerator {
 pub struct DiffConfig {
     algorithm: DiffAlgorithmType,
     context_lines: usize,
+    merge_gap: usize,
+    window_lines: usize,
+    word_diff: bool,
 }
 
 impl DiffConfig {
     pub fn new() -> Self {
         Self {
             algorithm: DiffAlgorithmType::default(),
-            context_lines: 3,
+            context_lines: DEFAULT_CONTEXT_LINES,
+            merge_gap: 0,
+            window_lines: DEFAULT_WINDOW_LINES,
+            word_diff: false,
         }
     }
     
@@ -64,8 +122,38 @@ impl DiffConfig {
         self
     }
     
+    /// Merge hunks fewer than `gap` unchanged lines apart; not applied by
+    /// streaming diffs
+    pub fn merge_gap(mut self, gap: usize) -> Self {
+        self.merge_gap = gap;
+        self
+    }
+    
+    /// Lines of each side a streaming diff holds at once
+    pub fn window_lines(mut self, lines: usize) -> Self {
+        self.window_lines = lines.max(1);
+        self
+    }
+    
+    /// Compute word-level ranges for paired removed and added lines
+    pub fn word_diff(mut self, enabled: bool) -> Self {
+        self.word_diff = enabled;
+        self
+    }
+    
     pub fn build(self) -> DiffGenerator {
         DiffGenerator::new(self.algorithm)
+            .with_context(self.context_lines)
+            .with_merge_gap(self.merge_gap)
+            .with_word_diff(self.word_diff)
+    }
+
+    pub fn build_streaming(self) -> StreamingDiffGenerator {
+        StreamingDiffGenerator {
+            algorithm: self.algorithm,
+            context_lines: self.context_lines,
+            window_lines: self.window_lines,
+        }
     }
 }
 
@@ -75,9 +163,230 @@ impl Default for DiffConfig {
     }
 }
 
+/// Diffs readers window by window, so files larger than memory can be
+/// compared. Each window is diffed with the configured algorithm; lines after
+/// its last common line are carried into the next window, so changes spanning
+/// a window boundary are still paired up. Where a whole window has nothing in
+/// common the result is correct but may not be minimal.
+#[derive(Debug, Clone, Copy)]
+pub struct StreamingDiffGenerator {
+    algorithm: DiffAlgorithmType,
+    context_lines: usize,
+    window_lines: usize,
+}
+
+impl Default for StreamingDiffGenerator {
+    fn default() -> Self {
+        DiffConfig::new().build_streaming()
+    }
+}
+
+impl StreamingDiffGenerator {
+    /// Hunks between `old` and `new`, produced as the readers are consumed
+    pub fn generate<O: BufRead, N: BufRead>(&self, old: O, new: N) -> StreamingDiff<O, N> {
+        StreamingDiff {
+            config: *self,
+            old: LineSource::new(old),
+            new: LineSource::new(new),
+            old_line: 0,
+            new_line: 0,
+            run: VecDeque::new(),
+            hunk: None,
+            ready: VecDeque::new(),
+            stats: DiffStats::default(),
+            failed: false,
+        }
+    }
+}
+
+/// Lines read from one side and not yet diffed
+struct LineSource<R> {
+    reader: R,
+    lines: VecDeque<String>,
+    exhausted: bool,
+}
+
+impl<R: BufRead> LineSource<R> {
+    fn new(reader: R) -> Self {
+        Self { reader, lines: VecDeque::new(), exhausted: false }
+    }
+
+    /// Read until `window` lines are buffered or the reader ends. Lines keep
+    /// their newline, as in `DiffGenerator::generate`; invalid UTF-8 is replaced.
+    fn fill(&mut self, window: usize) -> io::Result<()> {
+        let mut buf = Vec::new();
+        while !self.exhausted && self.lines.len() < window {
+            buf.clear();
+            if self.reader.read_until(b'\n', &mut buf)? == 0 {
+                self.exhausted = true;
+            } else {
+                self.lines.push_back(String::from_utf8_lossy(&buf).into_owned());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Iterator over the hunks of a streaming diff. Stops after the first read
+/// error.
+pub struct StreamingDiff<O, N> {
+    config: StreamingDiffGenerator,
+    old: LineSource<O>,
+    new: LineSource<N>,
+    /// Lines consumed from each side so far
+    old_line: usize,
+    new_line: usize,
+    /// Equal lines since the last change, at most enough to close or open a hunk
+    run: VecDeque<String>,
+    hunk: Option<DiffHunk>,
+    ready: VecDeque<DiffHunk>,
+    stats: DiffStats,
+    failed: bool,
+}
+
+impl<O: BufRead, N: BufRead> StreamingDiff<O, N> {
+    /// Totals for the hunks produced so far
+    pub fn stats(&self) -> &DiffStats {
+        &self.stats
+    }
+
+    /// Diff the next window, or flush the last hunk once both sides are done
+    fn advance(&mut self) -> io::Result<bool> {
+        let window = self.config.window_lines;
+        self.old.fill(window)?;
+        self.new.fill(window)?;
+        if self.old.lines.is_empty() && self.new.lines.is_empty() {
+            self.finish();
+            return Ok(false);
+        }
+
+        let ops = {
+            let old: Vec<&str> = self.old.lines.iter().map(String::as_str).collect();
+            let new: Vec<&str> = self.new.lines.iter().map(String::as_str).collect();
+            self.config.algorithm.line_ops(&old, &new)
+        };
+        // Changes after the last common line may pair up with lines not read yet
+        let done = self.old.exhausted && self.new.exhausted;
+        let take = match ops.iter().rposition(|op| op.tag() == DiffTag::Equal) {
+            Some(last_equal) if !done => last_equal + 1,
+            _ => ops.len(),
+        };
+
+        for op in &ops[..take] {
+            let (tag, old_range, new_range) = op.as_tag_tuple();
+            if tag == DiffTag::Equal {
+                for _ in old_range {
+                    self.new.lines.pop_front();
+                    let line = self.old.lines.pop_front().unwrap_or_default();
+                    self.equal(line);
+                }
+                continue;
+            }
+            for _ in old_range {
+                let line = self.old.lines.pop_front().unwrap_or_default();
+                self.change(DiffOperation::Delete(line));
+            }
+            for _ in new_range {
+                let line = self.new.lines.pop_front().unwrap_or_default();
+                self.change(DiffOperation::Insert(line));
+            }
+        }
+        Ok(true)
+    }
+
+    fn equal(&mut self, line: String) {
+        self.old_line += 1;
+        self.new_line += 1;
+        self.run.push_back(line);
+
+        let context = self.config.context_lines;
+        // A gap wider than both contexts ends the hunk
+        if self.hunk.is_some() && self.run.len() > context * 2 {
+            self.close_hunk();
+        }
+        if self.hunk.is_none() {
+            while self.run.len() > context {
+                self.run.pop_front();
+            }
+        }
+    }
+
+    fn change(&mut self, operation: DiffOperation) {
+        let leading = self.run.len();
+        let hunk = self.hunk.get_or_insert_with(|| DiffHunk {
+            old_start: self.old_line - leading,
+            old_len: 0,
+            new_start: self.new_line - leading,
+            new_len: 0,
+            operations: Vec::new(),
+            word_ranges: Vec::new(),
+        });
+        for line in self.run.drain(..) {
+            hunk.old_len += 1;
+            hunk.new_len += 1;
+            hunk.operations.push(DiffOperation::Equal(line));
+        }
+        match operation {
+            DiffOperation::Delete(_) => {
+                self.old_line += 1;
+                hunk.old_len += 1;
+                self.stats.lines_removed += 1;
+            }
+            DiffOperation::Insert(_) => {
+                self.new_line += 1;
+                hunk.new_len += 1;
+                self.stats.lines_added += 1;
+            }
+            DiffOperation::Equal(_) => {}
+        }
+        hunk.operations.push(operation);
+    }
+
+    /// Emit the open hunk with up to the context's worth of trailing lines
+    fn close_hunk(&mut self) {
+        let Some(mut hunk) = self.hunk.take() else { return };
+        let trailing = self.run.len().min(self.config.context_lines);
+        for line in self.run.iter().take(trailing) {
+            hunk.old_len += 1;
+            hunk.new_len += 1;
+            hunk.operations.push(DiffOperation::Equal(line.clone()));
+        }
+        self.stats.hunks += 1;
+        self.stats.lines_modified = self.stats.lines_added.min(self.stats.lines_removed);
+        self.ready.push_back(hunk);
+    }
+
+    fn finish(&mut self) {
+        self.close_hunk();
+        self.run.clear();
+    }
+}
+
+impl<O: BufRead, N: BufRead> Iterator for StreamingDiff<O, N> {
+    type Item = io::Result<DiffHunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.failed {
+            if let Some(hunk) = self.ready.pop_front() {
+                return Some(Ok(hunk));
+            }
+            match self.advance() {
+                Ok(true) => {}
+                Ok(false) => return self.ready.pop_front().map(Ok),
+                Err(err) => {
+                    self.failed = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::diff::BINARY_CHECK_BYTES;
 
     #[test]
     fn test_diff_generator() {
@@ -98,4 +407,146 @@ mod tests {
             
         assert_eq!(generator.algorithm_name(), "Patience");
     }
+
+    #[test]
+    fn test_merge_gap_joins_nearby_hunks() {
+        let old: String = (1..=12).map(|i| format!("line {}\n", i)).collect();
+        // Changes at lines 3 and 6 with two unchanged lines between, and at 12
+        let new = old.replace("line 3\n", "line three\n").replace("line 6\n", "line six\n").replace("line 12\n", "");
+        let ranges = |gap: usize| -> Vec<_> {
+            DiffConfig::new().context_lines(0).merge_gap(gap).build()
+                .generate(&old, &new)
+                .hunks.iter().map(|h| (h.old_start, h.old_len, h.new_start, h.new_len)).collect()
+        };
+
+        assert_eq!(ranges(0), vec![(2, 1, 2, 1), (5, 1, 5, 1), (11, 1, 11, 0)]);
+        let merged = DiffConfig::new().context_lines(0).merge_gap(3).build().generate(&old, &new);
+        assert_eq!(merged.stats.hunks, 2);
+        assert_eq!(ranges(3), vec![(2, 4, 2, 4), (11, 1, 11, 0)]);
+        let lines: Vec<_> = merged.hunks[0].operations.iter().map(DiffOperation::unified_line).collect();
+        assert_eq!(lines, vec!["-line 3", "+line three", " line 4", " line 5", "-line 6", "+line six"]);
+        // Two unchanged lines are not fewer than a gap of 2
+        assert_eq!(ranges(2).len(), 3);
+    }
+
+    #[test]
+    fn test_binary_content_is_not_diffed() {
+        let generator = DiffGenerator::default();
+        assert!(!generator.generate("a\nb\n", "a\nc\n").is_binary);
+
+        let result = generator.generate_bytes(b"PNG\0\x01\x02", b"PNG\0\x01\x03");
+        assert!(result.is_binary);
+        assert!(result.hunks.is_empty());
+        // Invalid UTF-8 on one side is enough
+        assert!(generator.generate_bytes(b"text\n", b"\xff\xfe\xfd text\n").is_binary);
+        assert!(generator.generate("text\n", "te\0xt\n").is_binary);
+
+        // A character cut off by the checked window is still text
+        let mut long = "a".repeat(BINARY_CHECK_BYTES - 1).into_bytes();
+        long.extend("é\n".as_bytes());
+        assert!(!is_binary(&long));
+        assert!(!generator.generate_bytes(b"", &long).is_binary);
+    }
+
+    #[test]
+    fn test_word_diff_flag() {
+        let old = "a\nlet x = 1;\nc\n";
+        let new = "a\nlet x = 2;\nc\n";
+
+        let result = DiffConfig::new().build().generate(old, new);
+        assert!(result.hunks[0].word_ranges.is_empty());
+
+        let result = DiffConfig::new().word_diff(true).build().generate(old, new);
+        let hunk = &result.hunks[0];
+        assert_eq!(hunk.word_ranges.len(), hunk.operations.len());
+        assert_eq!(hunk.word_ranges[1], vec![8..9]);
+        assert_eq!(hunk.word_ranges[2], vec![8..9]);
+    }
+
+    /// Lines generated on demand, never held in memory as a whole
+    struct GeneratedLines {
+        next: usize,
+        count: usize,
+        changed_every: usize,
+        pending: Vec<u8>,
+    }
+
+    impl io::Read for GeneratedLines {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = io::BufRead::fill_buf(self)?.len().min(buf.len());
+            buf[..n].copy_from_slice(&self.pending[..n]);
+            self.consume(n);
+            Ok(n)
+        }
+    }
+
+    impl BufRead for GeneratedLines {
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            if self.pending.is_empty() && self.next < self.count {
+                let line = match self.changed_every {
+                    every if every > 0 && self.next % every == every - 1 => format!("changed {}\n", self.next),
+                    _ => format!("log line {}\n", self.next),
+                };
+                self.pending = line.into_bytes();
+                self.next += 1;
+            }
+            Ok(&self.pending)
+        }
+
+        fn consume(&mut self, amt: usize) {
+            self.pending.drain(..amt);
+        }
+    }
+
+    fn render(hunks: &[DiffHunk]) -> Vec<String> {
+        hunks
+            .iter()
+            .flat_map(|hunk| std::iter::once(hunk.header()).chain(hunk.operations.iter().map(DiffOperation::unified_line)))
+            .collect()
+    }
+
+    #[test]
+    fn test_streaming_matches_whole_file_diff_across_windows() {
+        let old: String = (0..200).map(|i| format!("line {}\n", i)).collect();
+        let new: String = (0..200)
+            .filter(|i| i % 37 != 5)
+            .map(|i| if i % 23 == 0 { format!("edited {}\n", i) } else { format!("line {}\n", i) })
+            .collect();
+        let expected = DiffGenerator::default().generate(&old, &new);
+
+        for window in [7, 16, 64, 1000] {
+            let mut diff = DiffConfig::new().window_lines(window).build_streaming().generate(old.as_bytes(), new.as_bytes());
+            let hunks: Vec<DiffHunk> = diff.by_ref().collect::<io::Result<_>>().unwrap();
+            assert_eq!(render(&hunks), render(&expected.hunks), "window of {} lines", window);
+            assert_eq!(diff.stats().lines_added, expected.stats.lines_added);
+            assert_eq!(diff.stats().lines_removed, expected.stats.lines_removed);
+        }
+    }
+
+    #[test]
+    fn test_streaming_honors_context_lines() {
+        let old = "a\nb\nc\nd\ne\nf\ng\n";
+        let new = "a\nb\nc\nX\ne\nf\ng\n";
+        let hunks: Vec<DiffHunk> = DiffConfig::new()
+            .context_lines(1)
+            .build_streaming()
+            .generate(old.as_bytes(), new.as_bytes())
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(render(&hunks), vec!["@@ -3,3 +3,3 @@", " c", "-d", "+X", " e"]);
+    }
+
+    #[test]
+    fn test_streaming_diffs_inputs_larger_than_its_window() {
+        let lines = 200_000;
+        let old = GeneratedLines { next: 0, count: lines, changed_every: 0, pending: Vec::new() };
+        let new = GeneratedLines { next: 0, count: lines, changed_every: 50_000, pending: Vec::new() };
+        let mut diff = DiffConfig::new().window_lines(256).build_streaming().generate(old, new);
+        let hunks: Vec<DiffHunk> = diff.by_ref().collect::<io::Result<_>>().unwrap();
+
+        assert_eq!(hunks.len(), 4);
+        assert_eq!(hunks[0].header(), "@@ -49997,7 +49997,7 @@");
+        assert_eq!(diff.stats().lines_added, 4);
+        assert_eq!(diff.stats().lines_removed, 4);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,142 @@
+//! Safeguards for extremely long diff lines
+//!
+//! A minified file can put megabytes on a single line. Diff lines longer
+//! than the limit are stored truncated, ending in a marker such as
+//! `… (18,344 more chars)`, so hunks, rendering and confidence scanning only
+//! ever see a bounded prefix. The full lines are kept once, on the side, and
+//! put back wherever a valid patch is needed.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Default limit on the length of a diff line kept inline, in characters
+pub const DEFAULT_MAX_LINE_CHARS: usize = 4_000;
+
+/// Full content of the lines of a diff that were truncated
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LongLines {
+    /// Full line by its truncated form, `+`/`-`/` ` marker included.
+    /// Shared, so cloning an event does not copy megabytes of text.
+    lines: Arc<HashMap<String, String>>,
+}
+
+impl LongLines {
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// The full line for `line`, if it is a truncated one
+    pub fn full_line(&self, line: &str) -> Option<&str> {
+        self.lines.get(line).map(String::as_str)
+    }
+
+    /// The full line for `line` if it was truncated, otherwise `line` itself
+    pub fn restore_line<'a>(&'a self, line: &'a str) -> &'a str {
+        self.full_line(line).unwrap_or(line)
+    }
+
+    /// `diff` with every truncated line restored
+    pub fn restore_diff(&self, diff: &str) -> String {
+        if self.is_empty() {
+            return diff.to_string();
+        }
+        diff.split('\n').map(|line| self.restore_line(line)).collect::<Vec<_>>().join("\n")
+    }
+
+    /// The same lines with `f` applied to both the truncated and full forms
+    pub fn map_lines(&self, f: impl Fn(&str) -> String) -> Self {
+        let lines = self.lines.iter().map(|(truncated, full)| (f(truncated), f(full))).collect();
+        Self { lines: Arc::new(lines) }
+    }
+}
+
+impl Serialize for LongLines {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.lines.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for LongLines {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        HashMap::deserialize(deserializer).map(|lines| Self { lines: Arc::new(lines) })
+    }
+}
+
+/// `line` cut to `max_chars` characters followed by `… (N more chars)`, or
+/// `None` if it is short enough
+pub fn truncate_line(line: &str, max_chars: usize) -> Option<String> {
+    // Byte length bounds the character count, so short lines are not counted
+    if line.len() <= max_chars {
+        return None;
+    }
+    let (cut, _) = line.char_indices().nth(max_chars)?;
+    let more = line[cut..].chars().count();
+    Some(format!("{}… ({} more chars)", &line[..cut], group_digits(more)))
+}
+
+/// Truncate the lines of `diff` longer than `max_chars`, returning the
+/// bounded diff and the full content of the lines that were cut
+pub fn truncate_long_lines(diff: &str, max_chars: usize) -> (String, LongLines) {
+    if diff.split('\n').all(|line| line.len() <= max_chars) {
+        return (diff.to_string(), LongLines::default());
+    }
+
+    let mut lines = HashMap::new();
+    let mut out = Vec::new();
+    for line in diff.split('\n') {
+        match truncate_line(line, max_chars) {
+            // Two different lines with the same truncated form stay inline
+            // rather than restoring to the wrong one
+            Some(truncated) if lines.get(&truncated).map_or(true, |full: &String| full == line) => {
+                lines.insert(truncated.clone(), line.to_string());
+                out.push(truncated);
+            }
+            _ => out.push(line.to_string()),
+        }
+    }
+    (out.join("\n"), LongLines { lines: Arc::new(lines) })
+}
+
+/// `18344` as `18,344`
+pub fn group_digits(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncates_long_lines_and_restores_them() {
+        let minified = format!("var a={};", "x".repeat(2_000_000));
+        let old = format!("// header\n{}\n", minified);
+        let new = format!("// header\n{}\n", minified.replace("var a", "var b"));
+        let diff = crate::diff::generate_unified_diff(&old, &new, "app.min.js", "app.min.js");
+
+        let (bounded, long_lines) = truncate_long_lines(&diff, DEFAULT_MAX_LINE_CHARS);
+        assert_eq!(long_lines.len(), 2);
+        assert!(bounded.len() < 3 * DEFAULT_MAX_LINE_CHARS);
+        assert!(bounded.contains("x… (1,996,008 more chars)"));
+        assert!(bounded.contains("\n // header\n"));
+        assert_eq!(long_lines.restore_diff(&bounded), diff);
+
+        // Multi-byte characters are cut on a character boundary
+        assert_eq!(truncate_line("+ééé", 3).unwrap(), "+éé… (1 more chars)");
+        assert_eq!(truncate_line("+ééé", 4), None);
+        assert_eq!(group_digits(1_234_567), "1,234,567");
+        assert_eq!(group_digits(999), "999");
+    }
+}
@@ -7,15 +7,22 @@
 pub mod algorithms;
 pub mod generator;
 pub mod formatter;
+pub mod long_lines;
+pub mod pool;
+pub mod words;
 
 // Re-export the main types for easier use
 pub use algorithms::{
     DiffAlgorithm, DiffAlgorithmType, DiffResult, DiffHunk, DiffOperation, DiffStats,
-    MyersAlgorithm, PatienceAlgorithm, LcsAlgorithm,
+    MyersAlgorithm, PatienceAlgorithm, LcsAlgorithm, HistogramAlgorithm,
+    is_binary, is_binary_diff, word_ranges, BINARY_CHECK_BYTES, DEFAULT_CONTEXT_LINES, MAX_WORD_DIFF_CHARS,
 };
 
-pub use generator::{DiffGenerator, DiffConfig};
-pub use formatter::{DiffFormatter, DiffFormat};
+pub use generator::{DiffGenerator, DiffConfig, StreamingDiff, StreamingDiffGenerator, DEFAULT_WINDOW_LINES};
+pub use formatter::{DiffFormatter, DiffFormat, DEV_NULL};
+pub use long_lines::{truncate_long_lines, LongLines, DEFAULT_MAX_LINE_CHARS};
+pub use pool::{DiffPool, OrderedResults, DEFAULT_DIFF_WORKERS};
+pub use words::{WordChange, WordSplitter, DEFAULT_WORD_BOUNDARY};
 
 /// Convenience function to generate a unified diff with default settings
 pub fn generate_unified_diff<P: AsRef<std::path::Path>>(
@@ -24,11 +31,58 @@ pub fn generate_unified_diff<P: AsRef<std::path::Path>>(
     old_path: P,
     new_path: P,
 ) -> String {
-    let generator = DiffGenerator::default();
+    generate_unified_diff_with_context(old, new, old_path, new_path, DEFAULT_CONTEXT_LINES)
+}
+
+/// Convenience function to generate a unified diff with `context_lines` of
+/// context around each hunk
+pub fn generate_unified_diff_with_context<P: AsRef<std::path::Path>>(
+    old: &str,
+    new: &str,
+    old_path: P,
+    new_path: P,
+    context_lines: usize,
+) -> String {
+    let generator = DiffConfig::new().context_lines(context_lines).build();
     let result = generator.generate(old, new);
     DiffFormatter::format_unified(&result, old_path, new_path)
 }
 
+/// The text appended to `old` to make `new`, when `new` is exactly `old` with
+/// whole lines added at the end
+pub fn appended_text<'a>(old: &str, new: &'a str) -> Option<&'a str> {
+    let appended = new.strip_prefix(old)?;
+    (!appended.is_empty() && (old.is_empty() || old.ends_with('\n'))).then_some(appended)
+}
+
+/// Unified diff of `new` against `old` when `new` only appends lines, built
+/// from the appended lines without running a diff algorithm. It has no
+/// context lines and `None` is returned for any other change.
+pub fn generate_append_diff<P: AsRef<std::path::Path>>(
+    old: &str,
+    new: &str,
+    old_path: P,
+    new_path: P,
+) -> Option<String> {
+    let appended = appended_text(old, new)?;
+    let old_lines = old.lines().count();
+    let operations: Vec<DiffOperation> = appended
+        .split_inclusive('\n')
+        .map(|line| DiffOperation::Insert(line.to_string()))
+        .collect();
+    let stats = DiffStats { lines_added: operations.len(), hunks: 1, ..DiffStats::default() };
+    let hunk = DiffHunk {
+        old_start: old_lines,
+        old_len: 0,
+        new_start: old_lines,
+        new_len: operations.len(),
+        operations,
+        word_ranges: Vec::new(),
+    };
+    let result = DiffResult { hunks: vec![hunk], stats, is_binary: false };
+    Some(DiffFormatter::format_unified(&result, old_path, new_path))
+}
+
 /// Convenience function to generate a side-by-side diff with default settings
 pub fn generate_side_by_side_diff<P: AsRef<std::path::Path>>(
     old: &str,
@@ -66,4 +120,44 @@ mod tests {
         assert_eq!(stats.lines_added, 1);
         assert_eq!(stats.lines_removed, 1);
     }
+
+    #[test]
+    fn test_append_diff() {
+        let old = "INFO start\nINFO ready\n";
+        let new = "INFO start\nINFO ready\nWARN slow\nERROR failed\n";
+        let diff = generate_append_diff(old, new, "app.log", "app.log").unwrap();
+        assert_eq!(diff, "--- app.log\n+++ app.log\n@@ -3,0 +3,2 @@\n+WARN slow\n+ERROR failed");
+        // The same hunk a zero-context diff would find
+        assert_eq!(diff, generate_unified_diff_with_context(old, new, "app.log", "app.log", 0));
+
+        // Truncated and rewritten, even with a longer result
+        assert!(generate_append_diff(old, "INFO restart\nINFO start\nINFO ready\n", "a", "b").is_none());
+        assert!(generate_append_diff(old, "INFO start\n", "a", "b").is_none());
+        // Changed in the middle
+        assert!(generate_append_diff(old, "INFO start\nINFO steady\nWARN slow\n", "a", "b").is_none());
+        // A last line without a newline that is continued was modified, not appended to
+        assert!(generate_append_diff("INFO sta", "INFO start\n", "a", "b").is_none());
+        assert!(generate_append_diff(old, old, "a", "b").is_none());
+    }
+
+    #[test]
+    fn test_context_lines() {
+        let old: String = (1..=30).map(|i| format!("line {}\n", i)).collect();
+        let new = old.replace("line 5\n", "five\n").replace("line 20\n", "twenty\n");
+        let hunk_headers = |diff: &str| diff.lines().filter(|line| line.starts_with("@@")).map(str::to_string).collect::<Vec<_>>();
+
+        // Zero context: only the changed lines
+        let minimal = generate_unified_diff_with_context(&old, &new, "a", "b", 0);
+        assert_eq!(hunk_headers(&minimal), ["@@ -5,1 +5,1 @@", "@@ -20,1 +20,1 @@"]);
+        assert!(!minimal.lines().any(|line| line.starts_with(' ')));
+
+        // The default keeps the changes apart
+        let default = generate_unified_diff(old.as_str(), new.as_str(), "a", "b");
+        assert_eq!(hunk_headers(&default), ["@@ -2,7 +2,7 @@", "@@ -17,7 +17,7 @@"]);
+
+        // Ten lines of context overlap, so the changes share one hunk
+        let wide = generate_unified_diff_with_context(&old, &new, "a", "b", 10);
+        assert_eq!(hunk_headers(&wide), ["@@ -1,30 +1,30 @@"]);
+        assert_eq!(wide.lines().filter(|line| line.starts_with(' ')).count(), 28);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,184 @@
+//! Bounded pool of diff workers
+//!
+//! Diffs are computed on a fixed number of worker threads instead of the
+//! thread that reads file system events. `submit` blocks while the job
+//! queue is full, so a burst of changes slows event intake down rather than
+//! queueing without bound. Results come out of [`OrderedResults`] in the
+//! order their jobs were submitted, whichever worker finishes first.
+
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Worker threads used when `--diff-workers` is not given
+pub const DEFAULT_DIFF_WORKERS: usize = 4;
+
+/// Jobs that may wait for a worker, per worker
+const QUEUE_PER_WORKER: usize = 8;
+
+type Job<T> = Box<dyn FnOnce() -> T + Send>;
+
+pub struct DiffPool<T> {
+    jobs: SyncSender<(u64, Job<T>)>,
+    /// Results that need no worker skip the queue but keep their place in line
+    results: Sender<(u64, Option<T>)>,
+    next_seq: u64,
+}
+
+impl<T: Send + 'static> DiffPool<T> {
+    /// A pool of `workers` threads (at least one) and the receiving end of its results
+    pub fn new(workers: usize) -> (Self, OrderedResults<T>) {
+        let workers = workers.max(1);
+        Self::with_capacity(workers, workers * QUEUE_PER_WORKER)
+    }
+
+    /// Like [`DiffPool::new`], with at most `capacity` jobs waiting for a worker
+    pub fn with_capacity(workers: usize, capacity: usize) -> (Self, OrderedResults<T>) {
+        let (job_tx, job_rx) = mpsc::sync_channel::<(u64, Job<T>)>(capacity);
+        let (result_tx, result_rx) = mpsc::channel();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+
+        for _ in 0..workers.max(1) {
+            let job_rx = Arc::clone(&job_rx);
+            let result_tx = result_tx.clone();
+            thread::spawn(move || loop {
+                let job = job_rx.lock().unwrap_or_else(|e| e.into_inner()).recv();
+                let Ok((seq, job)) = job else { break };
+                // A panicking job still takes its turn, so later results are not held back
+                let result = panic::catch_unwind(AssertUnwindSafe(job)).ok();
+                if result_tx.send((seq, result)).is_err() {
+                    break;
+                }
+            });
+        }
+
+        let pool = Self { jobs: job_tx, results: result_tx, next_seq: 0 };
+        let results = OrderedResults { rx: result_rx, pending: BTreeMap::new(), next_seq: 0 };
+        (pool, results)
+    }
+
+    /// Queue `job`, waiting while the queue is full. False once the results
+    /// are no longer received.
+    pub fn submit(&mut self, job: impl FnOnce() -> T + Send + 'static) -> bool {
+        let seq = self.take_seq();
+        self.jobs.send((seq, Box::new(job))).is_ok()
+    }
+
+    /// Deliver `value` in turn without a worker, for events with nothing to diff
+    pub fn submit_ready(&mut self, value: T) -> bool {
+        let seq = self.take_seq();
+        self.results.send((seq, Some(value))).is_ok()
+    }
+
+    fn take_seq(&mut self) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        seq
+    }
+}
+
+/// Results of a [`DiffPool`] in submission order
+pub struct OrderedResults<T> {
+    rx: Receiver<(u64, Option<T>)>,
+    /// Results that finished before an earlier one
+    pending: BTreeMap<u64, Option<T>>,
+    next_seq: u64,
+}
+
+impl<T> OrderedResults<T> {
+    /// The next result, waiting for it if needed. `None` once the pool is
+    /// dropped and every result has been delivered.
+    pub fn recv(&mut self) -> Option<T> {
+        loop {
+            while let Some(result) = self.pending.remove(&self.next_seq) {
+                self.next_seq += 1;
+                if result.is_some() {
+                    return result;
+                }
+            }
+            let (seq, result) = self.rx.recv().ok()?;
+            self.pending.insert(seq, result);
+        }
+    }
+
+    /// The next result, waiting at most `timeout` for it
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            while let Some(result) = self.pending.remove(&self.next_seq) {
+                self.next_seq += 1;
+                if let Some(result) = result {
+                    return Ok(result);
+                }
+            }
+            let (seq, result) = self.rx.recv_timeout(deadline.saturating_duration_since(Instant::now()))?;
+            self.pending.insert(seq, result);
+        }
+    }
+}
+
+impl<T> Iterator for OrderedResults<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.recv()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_many_changes_diffed_in_order_by_bounded_pool() {
+        const CHANGES: usize = 200;
+        const WORKERS: usize = 3;
+        let (mut pool, results) = DiffPool::with_capacity(WORKERS, 4);
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let consumer = thread::spawn(move || results.collect::<Vec<(usize, String)>>());
+        for i in 0..CHANGES {
+            if i % 10 == 0 {
+                // Unchanged files have nothing to diff
+                assert!(pool.submit_ready((i, String::new())));
+                continue;
+            }
+            let (running, peak) = (Arc::clone(&running), Arc::clone(&peak));
+            assert!(pool.submit(move || {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                // Later jobs finish first, so results arrive out of order
+                thread::sleep(Duration::from_micros(((CHANGES - i) % 7 * 100) as u64));
+                let diff = crate::diff::generate_unified_diff(&format!("v{}\n", i), &format!("v{}\n", i + 1), "f", "f");
+                running.fetch_sub(1, Ordering::SeqCst);
+                (i, diff)
+            }));
+        }
+        drop(pool);
+
+        let results = consumer.join().unwrap();
+        assert_eq!(results.len(), CHANGES);
+        for (expected, (i, diff)) in results.iter().enumerate() {
+            assert_eq!(*i, expected);
+            if i % 10 != 0 {
+                assert!(diff.contains(&format!("+v{}", i + 1)), "{}", diff);
+            }
+        }
+        assert!(peak.load(Ordering::SeqCst) <= WORKERS);
+    }
+
+    #[test]
+    fn test_panicking_job_does_not_hold_back_later_results() {
+        let (mut pool, results) = DiffPool::new(2);
+        pool.submit(|| 1);
+        pool.submit(|| panic!("diff failed"));
+        pool.submit(|| 3);
+        drop(pool);
+        assert_eq!(results.collect::<Vec<_>>(), vec![1, 3]);
+    }
+}
@@ -0,0 +1,147 @@
+//! Word-level (intra-line) diffs of modified line pairs
+
+use regex::Regex;
+
+/// Boundaries between words when none are configured: runs of whitespace and
+/// single punctuation characters
+pub const DEFAULT_WORD_BOUNDARY: &str = r"\s+|[\p{P}\p{S}]";
+
+/// Lines with more words than this are not diffed word by word
+const MAX_WORDS: usize = 500;
+
+/// A span of a word-diffed line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WordChange {
+    Equal(String),
+    Delete(String),
+    Insert(String),
+}
+
+/// Splits lines into words on a regex boundary. Each boundary match is a
+/// token of its own, so punctuation changes show up as precisely as words.
+#[derive(Debug, Clone)]
+pub struct WordSplitter {
+    boundary: Regex,
+}
+
+impl Default for WordSplitter {
+    fn default() -> Self {
+        Self { boundary: Regex::new(DEFAULT_WORD_BOUNDARY).unwrap() }
+    }
+}
+
+impl WordSplitter {
+    pub fn new(boundary: &str) -> Result<Self, regex::Error> {
+        Ok(Self { boundary: Regex::new(boundary)? })
+    }
+
+    pub fn split<'a>(&self, line: &'a str) -> Vec<&'a str> {
+        let mut tokens = Vec::new();
+        let mut last = 0;
+        for found in self.boundary.find_iter(line) {
+            if found.start() > last {
+                tokens.push(&line[last..found.start()]);
+            }
+            if !found.as_str().is_empty() {
+                tokens.push(found.as_str());
+            }
+            last = found.end();
+        }
+        if last < line.len() {
+            tokens.push(&line[last..]);
+        }
+        tokens
+    }
+
+    /// Diff two versions of a line word by word, or `None` when they have
+    /// too little in common to read as one modified line
+    pub fn diff(&self, old: &str, new: &str) -> Option<Vec<WordChange>> {
+        let old_words = self.split(old);
+        let new_words = self.split(new);
+        if old_words.len() > MAX_WORDS || new_words.len() > MAX_WORDS {
+            return None;
+        }
+
+        // Longest common subsequence of words, filled from the end
+        let (n, m) = (old_words.len(), new_words.len());
+        let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i][j] = if old_words[i] == new_words[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let mut changes: Vec<WordChange> = Vec::new();
+        let mut common = 0;
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            let change = if i < n && j < m && old_words[i] == new_words[j] {
+                common += old_words[i].trim().len();
+                i += 1;
+                j += 1;
+                WordChange::Equal(old_words[i - 1].to_string())
+            } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+                i += 1;
+                WordChange::Delete(old_words[i - 1].to_string())
+            } else {
+                j += 1;
+                WordChange::Insert(new_words[j - 1].to_string())
+            };
+            push_merged(&mut changes, change);
+        }
+
+        // Related when at least half of their non-space text is shared
+        let total = old.split_whitespace().map(str::len).sum::<usize>()
+            + new.split_whitespace().map(str::len).sum::<usize>();
+        (total > 0 && common * 4 >= total).then_some(changes)
+    }
+}
+
+fn push_merged(changes: &mut Vec<WordChange>, change: WordChange) {
+    match (changes.last_mut(), change) {
+        (Some(WordChange::Equal(last)), WordChange::Equal(text))
+        | (Some(WordChange::Delete(last)), WordChange::Delete(text))
+        | (Some(WordChange::Insert(last)), WordChange::Insert(text)) => last.push_str(&text),
+        (_, change) => changes.push(change),
+    }
+}
+
+/// Render word changes inline, the way `git diff --word-diff` does
+pub fn render_inline(changes: &[WordChange]) -> String {
+    changes
+        .iter()
+        .map(|change| match change {
+            WordChange::Equal(text) => text.clone(),
+            WordChange::Delete(text) => format!("[-{}-]", text),
+            WordChange::Insert(text) => format!("{{+{}+}}", text),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_diff_marks_only_changed_words() {
+        let splitter = WordSplitter::default();
+        let changes = splitter.diff("let total = price * count;", "let total = price * amount;").unwrap();
+        assert_eq!(render_inline(&changes), "let total = price * [-count-]{+amount+};");
+
+        // Unrelated lines are left to render as a removal and an addition
+        assert!(splitter.diff("fn main() {", "// a comment").is_none());
+    }
+
+    #[test]
+    fn test_custom_boundary() {
+        let splitter = WordSplitter::new(",").unwrap();
+        assert_eq!(splitter.split("a b,c"), vec!["a b", ",", "c"]);
+
+        let changes = splitter.diff("one two,three", "one two,four").unwrap();
+        assert_eq!(render_inline(&changes), "one two,[-three-]{+four+}");
+    }
+}
@@ -0,0 +1,351 @@
+//! Run a user command on each change, like `entr`
+//!
+//! `--exec "cargo test {path}"` runs the command through the shell once
+//! changes stop arriving for the debounce period. While it is still running,
+//! further changes either queue one more run for when it finishes or, with
+//! `--restart`, kill it and start over. On Unix the command runs in its own
+//! process group, so killing it also kills whatever it started.
+
+use std::fmt;
+use std::io;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use crate::core::FileEvent;
+use crate::export::csv_writer::kind_name;
+use crate::shutdown::ShutdownWriter;
+
+/// Default quiet period before the command runs
+pub const DEFAULT_EXEC_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// How often a running command is checked on while shutting down
+const DRAIN_POLL: Duration = Duration::from_millis(20);
+
+/// `template` with `{path}` (shell-quoted) and `{kind}` filled in from `event`
+pub fn substitute(template: &str, event: &FileEvent) -> String {
+    template
+        .replace("{path}", &shell_quote(&event.path.display().to_string()))
+        .replace("{kind}", kind_name(&event.kind))
+}
+
+#[cfg(not(windows))]
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(windows)]
+fn shell_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+/// What to do about pending changes right now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecAction {
+    /// Nothing changed since the last run
+    Idle,
+    /// Changes are still arriving; wait for the debounce period
+    Wait,
+    /// Run the command
+    Start,
+    /// Kill the running command and run it again
+    Restart,
+    /// Run again once the running command finishes
+    Queue,
+}
+
+/// Decide what to do given the time of the last unhandled change
+pub fn next_action(
+    last_change: Option<Instant>,
+    now: Instant,
+    debounce: Duration,
+    running: bool,
+    restart: bool,
+) -> ExecAction {
+    match last_change {
+        None => ExecAction::Idle,
+        Some(at) if now.saturating_duration_since(at) < debounce => ExecAction::Wait,
+        Some(_) if !running => ExecAction::Start,
+        Some(_) if restart => ExecAction::Restart,
+        Some(_) => ExecAction::Queue,
+    }
+}
+
+/// Outcome of one run of the command
+#[derive(Debug, Clone)]
+pub struct ExecStatus {
+    pub command: String,
+    /// Exit code; `None` when killed by a signal
+    pub code: Option<i32>,
+    pub success: bool,
+    pub duration: Duration,
+}
+
+impl ExecStatus {
+    fn new(command: String, status: ExitStatus, duration: Duration) -> Self {
+        Self {
+            command,
+            code: status.code(),
+            success: status.success(),
+            duration,
+        }
+    }
+}
+
+impl fmt::Display for ExecStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "`{}` exited with {} after {:.1}s", self.command, code, self.duration.as_secs_f32()),
+            None => write!(f, "`{}` was terminated after {:.1}s", self.command, self.duration.as_secs_f32()),
+        }
+    }
+}
+
+struct Running {
+    child: Child,
+    command: String,
+    started: Instant,
+}
+
+/// Runs the `--exec` command for incoming changes
+pub struct ExecRunner {
+    template: String,
+    restart: bool,
+    debounce: Duration,
+    inherit_output: bool,
+    running: Option<Running>,
+    /// Latest change not yet handed to a run, and when it arrived
+    pending: Option<(FileEvent, Instant)>,
+}
+
+impl ExecRunner {
+    pub fn new(template: String) -> Self {
+        Self {
+            template,
+            restart: false,
+            debounce: DEFAULT_EXEC_DEBOUNCE,
+            inherit_output: true,
+            running: None,
+            pending: None,
+        }
+    }
+
+    /// Kill and rerun a command that is still running when changes arrive
+    pub fn with_restart(mut self, restart: bool) -> Self {
+        self.restart = restart;
+        self
+    }
+
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Pass the command's stdout/stderr through; off where it would corrupt
+    /// the TUI or machine-readable output
+    pub fn with_inherited_output(mut self, inherit: bool) -> Self {
+        self.inherit_output = inherit;
+        self
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Note a change; the command runs once changes stop for the debounce period
+    pub fn record(&mut self, event: &FileEvent, now: Instant) {
+        self.pending = Some((event.clone(), now));
+    }
+
+    /// Reap a finished run and start the next one if due. Returns the status
+    /// of each run that ended, including one killed for a restart.
+    pub fn poll(&mut self, now: Instant) -> io::Result<Vec<ExecStatus>> {
+        let mut finished = Vec::new();
+        if let Some(running) = self.running.as_mut() {
+            if let Some(status) = running.child.try_wait()? {
+                let running = self.running.take().expect("checked above");
+                finished.push(ExecStatus::new(running.command, status, now.saturating_duration_since(running.started)));
+            }
+        }
+
+        let last_change = self.pending.as_ref().map(|(_, at)| *at);
+        match next_action(last_change, now, self.debounce, self.is_running(), self.restart) {
+            ExecAction::Idle | ExecAction::Wait | ExecAction::Queue => {}
+            ExecAction::Restart => {
+                finished.extend(self.kill(now)?);
+                self.start(now)?;
+            }
+            ExecAction::Start => self.start(now)?,
+        }
+        Ok(finished)
+    }
+
+    fn start(&mut self, now: Instant) -> io::Result<()> {
+        let Some((event, _)) = self.pending.take() else { return Ok(()) };
+        let command = substitute(&self.template, &event);
+
+        let mut shell = if cfg!(windows) {
+            let mut shell = Command::new("cmd");
+            shell.arg("/C");
+            shell
+        } else {
+            let mut shell = Command::new("sh");
+            shell.arg("-c");
+            shell
+        };
+        shell.arg(&command).stdin(Stdio::null());
+        own_process_group(&mut shell);
+        if !self.inherit_output {
+            shell.stdout(Stdio::null()).stderr(Stdio::null());
+        }
+
+        let child = shell.spawn()?;
+        self.running = Some(Running { child, command, started: now });
+        Ok(())
+    }
+
+    /// Stop the running command, if any
+    pub fn kill(&mut self, now: Instant) -> io::Result<Option<ExecStatus>> {
+        let Some(mut running) = self.running.take() else { return Ok(None) };
+        // The child may have exited on its own in the meantime
+        let _ = kill_process_group(&mut running.child);
+        let status = running.child.wait()?;
+        Ok(Some(ExecStatus::new(running.command, status, now.saturating_duration_since(running.started))))
+    }
+}
+
+/// Start the shell as the leader of a new process group
+#[cfg(unix)]
+fn own_process_group(shell: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    shell.process_group(0);
+}
+
+#[cfg(not(unix))]
+fn own_process_group(_shell: &mut Command) {}
+
+/// Kill the shell and everything in its process group. The shell is not
+/// reaped yet, so its pid still names the group.
+#[cfg(unix)]
+fn kill_process_group(child: &mut Child) -> io::Result<()> {
+    let group = -(child.id() as libc::pid_t);
+    // SAFETY: kill only sends a signal; it has no memory safety requirements
+    if unsafe { libc::kill(group, libc::SIGKILL) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(child: &mut Child) -> io::Result<()> {
+    child.kill()
+}
+
+/// On shutdown a running command gets the grace period to finish; a run
+/// still waiting for its debounce is not started
+impl ShutdownWriter for ExecRunner {
+    fn pending(&self) -> usize {
+        usize::from(self.pending.is_some()) + usize::from(self.is_running())
+    }
+
+    fn drain(&mut self, deadline: Instant) -> Result<()> {
+        while let Some(running) = self.running.as_mut() {
+            if running.child.try_wait()?.is_some() {
+                self.running = None;
+            } else if Instant::now() >= deadline {
+                break;
+            } else {
+                thread::sleep(DRAIN_POLL);
+            }
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.pending = None;
+        self.kill(Instant::now())?;
+        Ok(())
+    }
+}
+
+impl Drop for ExecRunner {
+    fn drop(&mut self) {
+        let _ = self.kill(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use crate::core::FileEventKind;
+
+    #[test]
+    fn test_substitutes_quoted_path_and_kind() {
+        let event = FileEvent::new(PathBuf::from("src/it's here.rs"), FileEventKind::Modified);
+        let command = substitute("cargo test --quiet {path} # {kind} {other}", &event);
+
+        #[cfg(not(windows))]
+        assert_eq!(command, r"cargo test --quiet 'src/it'\''s here.rs' # modified {other}");
+        #[cfg(windows)]
+        assert_eq!(command, "cargo test --quiet \"src/it's here.rs\" # modified {other}");
+    }
+
+    #[test]
+    fn test_restart_or_queue_for_rapid_changes() {
+        let debounce = Duration::from_millis(300);
+        let start = Instant::now();
+        let ms = |n| start + Duration::from_millis(n);
+
+        assert_eq!(next_action(None, ms(0), debounce, false, false), ExecAction::Idle);
+
+        // A burst of saves at 0, 100 and 200ms runs once, 300ms after the last
+        for last in [0, 100, 200] {
+            assert_eq!(next_action(Some(ms(last)), ms(250), debounce, false, false), ExecAction::Wait);
+        }
+        assert_eq!(next_action(Some(ms(200)), ms(500), debounce, false, false), ExecAction::Start);
+
+        // Changes while the command runs: queue by default, restart on request
+        assert_eq!(next_action(Some(ms(200)), ms(400), debounce, true, true), ExecAction::Wait);
+        assert_eq!(next_action(Some(ms(200)), ms(500), debounce, true, false), ExecAction::Queue);
+        assert_eq!(next_action(Some(ms(200)), ms(500), debounce, true, true), ExecAction::Restart);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_kill_stops_processes_the_command_started() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let pid_file = dir.path().join("pid");
+        let template = format!("sleep 30 & echo $! > '{}'; wait", pid_file.display());
+        let mut runner = ExecRunner::new(template).with_inherited_output(false);
+
+        let start = Instant::now();
+        runner.record(&FileEvent::new(PathBuf::from("a.rs"), FileEventKind::Modified), start);
+        runner.poll(start + DEFAULT_EXEC_DEBOUNCE).unwrap();
+        assert!(runner.is_running());
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let pid = loop {
+            if let Some(pid) = std::fs::read_to_string(&pid_file).ok().and_then(|s| s.trim().parse::<u32>().ok()) {
+                break pid;
+            }
+            assert!(Instant::now() < deadline, "command never started sleep");
+            thread::sleep(DRAIN_POLL);
+        };
+
+        let status = runner.kill(Instant::now()).unwrap().unwrap();
+        assert!(!status.success);
+
+        // The orphaned sleep goes away too, or is left as a zombie for init to reap
+        let alive = || {
+            std::fs::read_to_string(format!("/proc/{}/stat", pid))
+                .is_ok_and(|stat| stat.rsplit(')').next().is_some_and(|rest| !rest.trim_start().starts_with('Z')))
+        };
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while alive() {
+            assert!(Instant::now() < deadline, "sleep {} outlived the killed command", pid);
+            thread::sleep(DRAIN_POLL);
+        }
+    }
+}
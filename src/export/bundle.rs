@@ -0,0 +1,330 @@
+//! Portable review bundles
+//!
+//! A bundle carries the accepted hunks of a review session together with the
+//! repo-relative path and the hash of each file's pre-change content, so the
+//! decisions can be applied to another checkout of the same repository.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::core::FileEventKind;
+use crate::core::paths::SafePathResolver;
+use crate::review::apply::{apply_hunks, reverse_hunk, ApplyReport};
+use crate::review::{DiffHunk, ReviewAction, ReviewSession};
+
+const BUNDLE_VERSION: u32 = 1;
+
+/// Accepted changes for a single file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleEntry {
+    /// Path relative to the repository root
+    pub path: PathBuf,
+    pub kind: FileEventKind,
+    /// SHA-256 of the file content before the change, if it could be recovered
+    pub base_hash: Option<String>,
+    pub hunks: Vec<DiffHunk>,
+    /// Accepted hunks that rely on hunks left out, see `ReviewableChange::dependency_warnings`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// A set of review decisions that can be applied to a different checkout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewBundle {
+    pub version: u32,
+    pub session_id: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub entries: Vec<BundleEntry>,
+}
+
+/// Options for applying a bundle
+#[derive(Debug, Clone, Default)]
+pub struct BundleApplyOptions {
+    /// Fall back to context matching when a file's base hash does not match
+    pub fuzzy: bool,
+}
+
+impl ReviewBundle {
+    /// Build a bundle from the accepted hunks of `session`, with paths made
+    /// relative to `root`.
+    ///
+    /// The pre-change content is recovered by reverting the full change from
+    /// the file currently on disk.
+    pub fn from_session(session: &ReviewSession, root: &Path) -> Result<Self> {
+        let resolver = SafePathResolver::new(root)
+            .with_context(|| format!("Invalid bundle root: {}", root.display()))?;
+        let mut entries = Vec::new();
+
+        for change in &session.changes {
+            // Truncated long lines are restored so the hunks still apply
+            let hunks: Vec<DiffHunk> = change
+                .hunks
+                .iter()
+                .filter(|h| matches!(change.review_actions.get(&h.id), Some(ReviewAction::Accept)))
+                .map(|h| change.full_hunk(h))
+                .collect();
+            if hunks.is_empty() {
+                continue;
+            }
+
+            let path = resolver.relativize(&change.event.path).with_context(|| {
+                format!("{} is outside of {}", change.event.path.display(), root.display())
+            })?;
+
+            let base_hash = fs::read_to_string(resolver.resolve(&path)?)
+                .ok()
+                .and_then(|current| {
+                    let reversed: Vec<DiffHunk> = change.hunks.iter().map(|h| reverse_hunk(&change.full_hunk(h))).collect();
+                    let refs: Vec<&DiffHunk> = reversed.iter().collect();
+                    let outcome = apply_hunks(&current, &refs, false);
+                    outcome.conflicts.is_empty().then(|| content_hash(&outcome.content))
+                });
+
+            entries.push(BundleEntry {
+                path,
+                kind: change.event.kind.clone(),
+                base_hash,
+                hunks,
+                warnings: change.dependency_warnings(),
+            });
+        }
+
+        Ok(Self {
+            version: BUNDLE_VERSION,
+            session_id: session.id.clone(),
+            created_at: chrono::Utc::now(),
+            entries,
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path.as_ref(), json)
+            .with_context(|| format!("Failed to write bundle: {}", path.as_ref().display()))?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let json = fs::read_to_string(path.as_ref())
+            .with_context(|| format!("Failed to read bundle: {}", path.as_ref().display()))?;
+        let bundle: Self = serde_json::from_str(&json).context("Invalid bundle format")?;
+        if bundle.version > BUNDLE_VERSION {
+            anyhow::bail!("Unsupported bundle version {}", bundle.version);
+        }
+        Ok(bundle)
+    }
+
+    /// Apply the bundle to the checkout at `root`.
+    ///
+    /// Each file is verified against its recorded base hash first. On a
+    /// mismatch the file is left untouched and reported as a conflict, unless
+    /// `fuzzy` is set, in which case hunks are placed by context instead.
+    pub fn apply(&self, root: &Path, options: &BundleApplyOptions) -> Result<ApplyReport> {
+        let resolver = SafePathResolver::new(root)
+            .with_context(|| format!("Invalid checkout root: {}", root.display()))?;
+        let mut report = ApplyReport::default();
+
+        for entry in &self.entries {
+            let target = match resolver.resolve(&entry.path) {
+                Ok(target) => target,
+                Err(err) => {
+                    report.add_conflict(entry.path.clone(), None, err.to_string());
+                    continue;
+                }
+            };
+
+            let current = match fs::read_to_string(&target) {
+                Ok(content) => content,
+                Err(err) => {
+                    report.add_conflict(entry.path.clone(), None, format!("Cannot read target file: {}", err));
+                    continue;
+                }
+            };
+
+            let base_matches = entry
+                .base_hash
+                .as_ref()
+                .is_some_and(|hash| *hash == content_hash(&current));
+            if !base_matches && !options.fuzzy {
+                let reason = match entry.base_hash {
+                    Some(_) => "Base content hash mismatch",
+                    None => "No base hash recorded",
+                };
+                report.add_conflict(entry.path.clone(), None, reason);
+                continue;
+            }
+
+            let hunks: Vec<&DiffHunk> = entry.hunks.iter().collect();
+            let outcome = apply_hunks(&current, &hunks, !base_matches);
+
+            for (hunk_id, reason) in outcome.conflicts {
+                report.add_conflict(entry.path.clone(), Some(hunk_id), reason);
+            }
+
+            if !outcome.applied.is_empty() {
+                fs::write(&target, &outcome.content)
+                    .with_context(|| format!("Failed to write {}", target.display()))?;
+                report.hunks_applied += outcome.applied.len();
+                report.files_modified.push(entry.path.clone());
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// Hex-encoded SHA-256 of file content
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use crate::core::FileEvent;
+
+    const BASE: &str = "fn main() {\n    let a = 1;\n    let b = 2;\n    println!(\"{}\", a + b);\n}\n";
+    const CHANGED: &str = "fn main() {\n    let a = 1;\n    let b = 3;\n    println!(\"{}\", a + b);\n}\n";
+
+    /// Review a change to src/main.rs in `laptop` and export the accepted bundle
+    fn reviewed_bundle(laptop: &Path) -> ReviewBundle {
+        let file = laptop.join("src").join("main.rs");
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, CHANGED).unwrap();
+
+        let diff = crate::diff::generate_unified_diff(BASE, CHANGED, &file, &file);
+        let event = FileEvent::new(file, FileEventKind::Modified).with_diff(diff);
+
+        let mut session = ReviewSession::new();
+        session.add_change(event);
+        session.changes[0].accept_all();
+
+        ReviewBundle::from_session(&session, laptop).unwrap()
+    }
+
+    fn checkout(base: &str) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src").join("main.rs"), base).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_bundle_records_relative_paths_and_base_hash() {
+        let laptop = TempDir::new().unwrap();
+        let bundle = reviewed_bundle(laptop.path());
+
+        assert_eq!(bundle.entries.len(), 1);
+        assert_eq!(bundle.entries[0].path, PathBuf::from("src/main.rs"));
+        assert_eq!(bundle.entries[0].base_hash, Some(content_hash(BASE)));
+    }
+
+    #[test]
+    fn test_apply_to_matching_checkout() {
+        let laptop = TempDir::new().unwrap();
+        let server = checkout(BASE);
+        let bundle_path = laptop.path().join("review.bundle.json");
+        reviewed_bundle(laptop.path()).save(&bundle_path).unwrap();
+
+        let bundle = ReviewBundle::load(&bundle_path).unwrap();
+        let report = bundle.apply(server.path(), &BundleApplyOptions::default()).unwrap();
+
+        assert!(!report.has_conflicts());
+        assert_eq!(report.hunks_applied, 1);
+        assert_eq!(report.files_modified, vec![PathBuf::from("src/main.rs")]);
+        assert_eq!(fs::read_to_string(server.path().join("src/main.rs")).unwrap(), CHANGED);
+    }
+
+    #[test]
+    fn test_apply_refuses_diverged_base() {
+        let laptop = TempDir::new().unwrap();
+        let diverged = format!("// server-only header\n{}", BASE);
+        let server = checkout(&diverged);
+        let bundle = reviewed_bundle(laptop.path());
+
+        let report = bundle.apply(server.path(), &BundleApplyOptions::default()).unwrap();
+
+        assert_eq!(report.conflicts.len(), 1);
+        assert!(report.conflicts[0].hunk_id.is_none());
+        assert_eq!(report.hunks_applied, 0);
+        assert_eq!(fs::read_to_string(server.path().join("src/main.rs")).unwrap(), diverged);
+    }
+
+    #[test]
+    fn test_fuzzy_apply_on_diverged_base() {
+        let laptop = TempDir::new().unwrap();
+        let server = checkout(&format!("// server-only header\n{}", BASE));
+        let bundle = reviewed_bundle(laptop.path());
+
+        let report = bundle
+            .apply(server.path(), &BundleApplyOptions { fuzzy: true })
+            .unwrap();
+
+        assert!(!report.has_conflicts());
+        assert_eq!(report.hunks_applied, 1);
+        assert_eq!(
+            fs::read_to_string(server.path().join("src/main.rs")).unwrap(),
+            format!("// server-only header\n{}", CHANGED)
+        );
+    }
+
+    #[test]
+    fn test_apply_rejects_paths_outside_root() {
+        let server = checkout(BASE);
+        let bundle = ReviewBundle {
+            version: BUNDLE_VERSION,
+            session_id: "test".to_string(),
+            created_at: chrono::Utc::now(),
+            entries: vec![BundleEntry {
+                path: PathBuf::from("../escape.txt"),
+                kind: FileEventKind::Modified,
+                base_hash: None,
+                hunks: Vec::new(),
+                warnings: Vec::new(),
+            }],
+        };
+
+        let report = bundle.apply(server.path(), &BundleApplyOptions { fuzzy: true }).unwrap();
+        assert_eq!(report.conflicts.len(), 1);
+        assert!(report.files_modified.is_empty());
+    }
+
+    #[test]
+    fn test_bundle_and_patch_keep_truncated_long_lines_whole() {
+        let minified = |name: &str| format!("var {}={};\n", name, "x".repeat(500_000));
+        let base = format!("// app\n{}", minified("a"));
+        let changed = format!("// app\n{}", minified("b"));
+        let laptop = TempDir::new().unwrap();
+        let file = laptop.path().join("app.min.js");
+        fs::write(&file, &changed).unwrap();
+
+        let diff = crate::diff::generate_unified_diff(base.as_str(), changed.as_str(), &file, &file);
+        let event = FileEvent::new(file.clone(), FileEventKind::Modified)
+            .with_bounded_diff(&diff, crate::diff::DEFAULT_MAX_LINE_CHARS);
+
+        // Hunks and display only hold the truncated prefix
+        let mut session = ReviewSession::new();
+        session.add_change(event.clone());
+        let hunk_bytes: usize = session.changes[0].hunks.iter().flat_map(|h| &h.lines).map(String::len).sum();
+        assert!(hunk_bytes < 3 * crate::diff::DEFAULT_MAX_LINE_CHARS, "{}", hunk_bytes);
+        assert!(event.diff.as_ref().unwrap().contains("… (496,008 more chars)"));
+
+        // Exported patches carry the full lines
+        let patch = laptop.path().join("app.patch");
+        crate::export::DiffExporter::unified().export_multifile_patch(&[event], &patch).unwrap();
+        assert!(fs::read_to_string(&patch).unwrap().contains(&diff));
+
+        session.changes[0].accept_all();
+        let bundle = ReviewBundle::from_session(&session, laptop.path()).unwrap();
+        assert_eq!(bundle.entries[0].base_hash, Some(content_hash(&base)));
+
+        let server = TempDir::new().unwrap();
+        fs::write(server.path().join("app.min.js"), &base).unwrap();
+        let report = bundle.apply(server.path(), &BundleApplyOptions::default()).unwrap();
+        assert!(!report.has_conflicts());
+        assert_eq!(fs::read_to_string(server.path().join("app.min.js")).unwrap(), changed);
+    }
+}
@@ -0,0 +1,147 @@
+//! CSV output of file events for spreadsheet analysis
+
+use std::io::Write;
+use anyhow::Result;
+use crate::core::{ChangeOrigin, ConfidenceLevel, FileEvent, FileEventKind};
+
+const HEADER: [&str; 10] = [
+    "timestamp",
+    "path",
+    "kind",
+    "origin",
+    "confidence_score",
+    "confidence_level",
+    "lines_added",
+    "lines_removed",
+    "batch_id",
+    "rule_ids",
+];
+
+/// Writes one CSV row per event, preceded by a header row
+pub struct CsvEventWriter<W: Write> {
+    writer: csv::Writer<W>,
+}
+
+impl<W: Write> CsvEventWriter<W> {
+    /// Create a writer and emit the header row
+    pub fn new(output: W) -> Result<Self> {
+        let mut writer = csv::Writer::from_writer(output);
+        writer.write_record(HEADER)?;
+        writer.flush()?;
+        Ok(Self { writer })
+    }
+
+    pub fn write_event(&mut self, event: &FileEvent) -> Result<()> {
+        let timestamp = chrono::DateTime::<chrono::Utc>::from(event.timestamp).to_rfc3339();
+        let stats = event.diff_stats();
+        let (score, level) = match event.confidence {
+            Some(ref confidence) => (
+                format!("{:.2}", confidence.score),
+                confidence_level_name(&confidence.level).to_string(),
+            ),
+            None => (String::new(), String::new()),
+        };
+
+        self.writer.write_record([
+            timestamp,
+            event.path.display().to_string(),
+            kind_name(&event.kind).to_string(),
+            origin_name(&event.origin),
+            score,
+            level,
+            stats.lines_added.to_string(),
+            stats.lines_removed.to_string(),
+            event.batch_id.clone().unwrap_or_default(),
+            event.rule_ids().join(";"),
+        ])?;
+        // Flush per row so output can be tailed while watching
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> Result<W> {
+        self.writer
+            .into_inner()
+            .map_err(|e| anyhow::anyhow!("Failed to flush CSV output: {}", e.error()))
+    }
+}
+
+pub(crate) fn kind_name(kind: &FileEventKind) -> &'static str {
+    match kind {
+        FileEventKind::Created => "created",
+        FileEventKind::Modified => "modified",
+        FileEventKind::Deleted => "deleted",
+        FileEventKind::Moved { .. } => "moved",
+    }
+}
+
+pub(crate) fn origin_name(origin: &ChangeOrigin) -> String {
+    match origin {
+        ChangeOrigin::Human => "human".to_string(),
+        ChangeOrigin::AIAgent { tool_name, .. } => format!("ai:{}", tool_name),
+        ChangeOrigin::Tool { name } => format!("tool:{}", name),
+        ChangeOrigin::Unknown => "unknown".to_string(),
+    }
+}
+
+pub(crate) fn confidence_level_name(level: &ConfidenceLevel) -> &'static str {
+    match level {
+        ConfidenceLevel::Safe => "safe",
+        ConfidenceLevel::Review => "review",
+        ConfidenceLevel::Risky => "risky",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+    use crate::core::{ChangeConfidence, RuleHit};
+
+    fn rule_hit(rule_id: &str) -> RuleHit {
+        RuleHit { rule_id: rule_id.to_string(), impact: -0.1, severity: Default::default(), line: None, excerpt: None, in_test_code: false }
+    }
+
+    #[test]
+    fn test_csv_header_and_escaped_rows() {
+        let timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        let mut modified = FileEvent::new(PathBuf::from("src/a, b.rs"), FileEventKind::Modified)
+            .with_diff("--- a\n+++ b\n@@ -1,2 +1,2 @@\n-old\n+new\n+more\n context".to_string())
+            .with_origin(ChangeOrigin::AIAgent { tool_name: "claude".to_string(), process_id: None })
+            .with_confidence(ChangeConfidence {
+                level: ConfidenceLevel::Review,
+                score: 0.55,
+                reasons: vec![],
+                reason_impacts: vec![],
+                rule_hits: vec![rule_hit("unwrap_usage"), rule_hit("todo_comment")],
+                score_breakdown: None,
+            })
+            .with_batch_id("batch_1".to_string());
+        modified.timestamp = timestamp;
+
+        let mut created = FileEvent::new(PathBuf::from("say \"hi\".txt"), FileEventKind::Created);
+        created.timestamp = timestamp;
+
+        let mut writer = CsvEventWriter::new(Vec::new()).unwrap();
+        writer.write_event(&modified).unwrap();
+        writer.write_event(&created).unwrap();
+        let output = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "timestamp,path,kind,origin,confidence_score,confidence_level,lines_added,lines_removed,batch_id,rule_ids"
+        );
+        assert_eq!(
+            lines[1],
+            "2023-11-14T22:13:20+00:00,\"src/a, b.rs\",modified,ai:claude,0.55,review,2,1,batch_1,unwrap_usage;todo_comment"
+        );
+        assert_eq!(
+            lines[2],
+            "2023-11-14T22:13:20+00:00,\"say \"\"hi\"\".txt\",created,unknown,,,0,0,,"
+        );
+    }
+}
@@ -0,0 +1,255 @@
+//! Review sessions as draft GitHub pull request reviews
+//!
+//! The JSON written here is the request body of GitHub's "create a review
+//! for a pull request" endpoint, so it can be submitted as is with
+//! `gh api repos/OWNER/REPO/pulls/N/reviews --input FILE`. It has no `event`,
+//! which leaves the review pending as a draft for the reviewer to finish on
+//! GitHub. Rejected and commented hunks become line comments, anchored on the
+//! lines the hunk adds, or on the lines it removes if it only removes.
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use crate::review::{DiffHunk, ReviewAction, ReviewSession, ReviewableChange};
+
+/// Side of a pull request's diff a comment is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DiffSide {
+    /// The base version, for lines the change removes
+    Left,
+    /// The head version, for lines the change adds or keeps
+    Right,
+}
+
+/// A line comment; multi-line when `start_line` is set
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GithubReviewComment {
+    pub path: String,
+    pub body: String,
+    /// Last line of the commented range, in the file on `side`
+    pub line: usize,
+    pub side: DiffSide,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_side: Option<DiffSide>,
+}
+
+/// A pending pull request review
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GithubReview {
+    pub body: String,
+    pub comments: Vec<GithubReviewComment>,
+}
+
+impl GithubReview {
+    /// A comment for each rejected or commented hunk of `session`, with paths
+    /// relative to `root`, and the session's stats as the review body
+    pub fn from_session(session: &ReviewSession, root: &Path) -> Self {
+        let absolute_root = std::env::current_dir().map_or_else(|_| root.to_path_buf(), |cwd| cwd.join(root));
+        let mut comments = Vec::new();
+        for change in &session.changes {
+            let path = change.event.path
+                .strip_prefix(root)
+                .or_else(|_| change.event.path.strip_prefix(&absolute_root))
+                .unwrap_or(&change.event.path);
+            for hunk in &change.hunks {
+                if let Some(body) = comment_body(change, hunk) {
+                    comments.push(comment(repo_path(path), body, hunk));
+                }
+            }
+        }
+
+        let stats = session.get_review_stats();
+        let mut body = match session.description {
+            Some(ref description) => format!("Review of {}: {}\n\n", session.display_name(), description),
+            None => format!("Review of {}\n\n", session.display_name()),
+        };
+        body.push_str(&format!(
+            "{} change(s): {} accepted, {} rejected, {} skipped, {} need discussion, {} pending.",
+            stats.total, stats.accepted, stats.rejected, stats.skipped, stats.deferred, stats.pending
+        ));
+        if stats.inconsistent > 0 {
+            body.push_str(&format!(" {} change(s) split hunks that depend on each other.", stats.inconsistent));
+        }
+        Self { body, comments }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_json()?)
+            .with_context(|| format!("Failed to write GitHub review: {}", path.display()))
+    }
+
+    /// Where the review of session `session_id` is exported by default
+    pub fn default_path(base_dir: &Path, session_id: &str) -> PathBuf {
+        base_dir.join(".watchdiff").join("sessions").join(format!("{}.github-review.json", session_id))
+    }
+}
+
+/// The reviewer's comment on `hunk`, after a note that it was rejected;
+/// None for hunks neither rejected nor commented on
+fn comment_body(change: &ReviewableChange, hunk: &DiffHunk) -> Option<String> {
+    let rejected = matches!(change.review_actions.get(&hunk.id), Some(ReviewAction::Reject));
+    match (rejected, change.comments.get(&hunk.id)) {
+        (true, Some(comment)) => Some(format!("Rejected in review.\n\n{}", comment)),
+        (true, None) => Some("Rejected in review.".to_string()),
+        (false, Some(comment)) => Some(comment.clone()),
+        (false, None) => None,
+    }
+}
+
+/// Forward slashes whatever the platform, as GitHub expects
+fn repo_path(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn comment(path: String, body: String, hunk: &DiffHunk) -> GithubReviewComment {
+    let (removed, added) = changed_lines(hunk);
+    let (side, lines) = if !added.is_empty() {
+        (DiffSide::Right, added)
+    } else if !removed.is_empty() {
+        (DiffSide::Left, removed)
+    } else {
+        // Nothing changed in the hunk; comment on where it starts
+        (DiffSide::Right, vec![hunk.new_start.max(1)])
+    };
+    let (first, last) = (lines[0], lines[lines.len() - 1]);
+    let range = first < last;
+    GithubReviewComment {
+        path,
+        body,
+        line: last,
+        side,
+        start_line: range.then_some(first),
+        start_side: range.then_some(side),
+    }
+}
+
+/// Lines of the old file `hunk` removes and lines of the new file it adds
+fn changed_lines(hunk: &DiffHunk) -> (Vec<usize>, Vec<usize>) {
+    let (mut old_line, mut new_line) = (hunk.old_start, hunk.new_start);
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    for line in &hunk.lines {
+        match line.as_bytes().first() {
+            Some(b'+') => {
+                added.push(new_line);
+                new_line += 1;
+            }
+            Some(b'-') => {
+                removed.push(old_line);
+                old_line += 1;
+            }
+            // "\ No newline at end of file" is not a line of either file
+            Some(b'\\') => {}
+            // Context, including blank lines whose leading space was trimmed
+            _ => {
+                old_line += 1;
+                new_line += 1;
+            }
+        }
+    }
+    (removed, added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{FileEvent, FileEventKind};
+
+    const MULTI_HUNK_DIFF: &str = "\
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -2,5 +2,6 @@
+ use std::fs;
+-use std::io;
++use std::io::{self, Read};
++use std::path::Path;
+
+ fn main() {
+     run();
+@@ -20,6 +21,4 @@ fn run() {
+     let a = 1;
+-    let b = 2;
+-    let c = 3;
+     let d = 4;
+
+     a + d
+@@ -40,2 +39,5 @@ fn done() {
+ }
++
++fn extra() {
++}
+ // end
+\\ No newline at end of file";
+
+    #[test]
+    fn test_comments_anchor_on_changed_lines_of_each_hunk() {
+        let event = FileEvent::new(PathBuf::from("/repo/src/lib.rs"), FileEventKind::Modified)
+            .with_diff(MULTI_HUNK_DIFF.to_string());
+        let mut session = ReviewSession::new();
+        session.add_change(event);
+        let change = &mut session.changes[0];
+        for id in ["hunk_0", "hunk_1", "hunk_2"] {
+            change.reject_hunk(id);
+        }
+        change.set_comment("hunk_1", "Keep b, parse_args still reads it");
+
+        let review = GithubReview::from_session(&session, Path::new("/repo"));
+        let anchors: Vec<_> = review.comments
+            .iter()
+            .map(|c| (c.path.as_str(), c.side, c.start_line, c.line))
+            .collect();
+        assert_eq!(anchors, vec![
+            // Replaces line 3 with new lines 3 and 4
+            ("src/lib.rs", DiffSide::Right, Some(3), 4),
+            // Only removes old lines 21 and 22
+            ("src/lib.rs", DiffSide::Left, Some(21), 22),
+            // Adds new lines 40 to 42, one up from the old file after the hunks above
+            ("src/lib.rs", DiffSide::Right, Some(40), 42),
+        ]);
+        assert_eq!(review.comments[0].body, "Rejected in review.");
+        assert_eq!(review.comments[1].body, "Rejected in review.\n\nKeep b, parse_args still reads it");
+        assert!(review.body.contains("1 change(s): 0 accepted, 1 rejected"));
+    }
+
+    #[test]
+    fn test_only_rejected_or_commented_hunks_are_exported() {
+        let diff = "@@ -5,2 +5,2 @@\n-a\n+b\n ctx\n@@ -9 +9 @@\n-c\n+d";
+        let mut session = ReviewSession::new();
+        session.add_change(FileEvent::new(PathBuf::from("/repo/x.rs"), FileEventKind::Modified).with_diff(diff.to_string()));
+        session.changes[0].accept_hunk("hunk_0");
+        session.changes[0].accept_hunk("hunk_1");
+        assert!(GithubReview::from_session(&session, Path::new("/repo")).comments.is_empty());
+
+        // A comment on an accepted hunk is still posted; one line has no range
+        session.changes[0].set_comment("hunk_1", "Nice");
+        let review = GithubReview::from_session(&session, Path::new("/repo"));
+        assert_eq!(review.comments, vec![GithubReviewComment {
+            path: "x.rs".to_string(),
+            body: "Nice".to_string(),
+            line: 9,
+            side: DiffSide::Right,
+            start_line: None,
+            start_side: None,
+        }]);
+        let json: serde_json::Value = serde_json::from_str(&review.to_json().unwrap()).unwrap();
+        assert_eq!(json["comments"][0], serde_json::json!({"path": "x.rs", "body": "Nice", "line": 9, "side": "RIGHT"}));
+        assert!(json.get("event").is_none());
+
+        session.changes[0].set_comment("hunk_1", "  ");
+        assert!(session.changes[0].comments.is_empty());
+    }
+}
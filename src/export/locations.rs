@@ -0,0 +1,343 @@
+//! Editor jump lists of changed regions
+//!
+//! One entry per diff hunk, written either as a Vim quickfix file
+//! (`path:line:col: message`, loaded with `:cfile`) or as a JSON array that
+//! VS Code tasks and extensions can consume.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+use crate::core::{AppState, FileEvent, FileEventKind};
+use crate::review::{DiffHunk, ReviewAction, ReviewSession, ReviewableChange};
+use crate::shutdown::ShutdownWriter;
+use super::csv_writer::{confidence_level_name, kind_name, origin_name};
+
+/// Quiet period before a live location file is rewritten
+pub const LIVE_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// On-disk format of a location list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum LocationFormat {
+    /// Vim quickfix lines, `path:line:col: message`
+    #[default]
+    Quickfix,
+    /// JSON array of `{file, line, column, message}` objects
+    Json,
+}
+
+/// Which hunks of a review session to list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum HunkSelection {
+    /// Every hunk regardless of decision
+    #[default]
+    All,
+    /// Hunks that have not been decided yet
+    Pending,
+    /// Accepted hunks only
+    Accepted,
+}
+
+impl HunkSelection {
+    fn includes(&self, action: &ReviewAction) -> bool {
+        match self {
+            HunkSelection::All => true,
+            HunkSelection::Pending => *action == ReviewAction::Pending,
+            HunkSelection::Accepted => *action == ReviewAction::Accept,
+        }
+    }
+}
+
+/// A single changed region
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Location {
+    pub file: PathBuf,
+    /// 1-based line of the hunk in the new file
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// Changed regions in the order they happened
+#[derive(Debug, Clone, Default)]
+pub struct LocationList {
+    pub entries: Vec<Location>,
+}
+
+impl LocationList {
+    /// One entry per hunk of each event; deleted files have nothing to jump to
+    pub fn from_events<'a>(events: impl IntoIterator<Item = &'a FileEvent>) -> Self {
+        let mut entries = Vec::new();
+        for event in events {
+            if matches!(event.kind, FileEventKind::Deleted) {
+                continue;
+            }
+            for hunk in ReviewableChange::parse_diff_into_hunks(&event.diff) {
+                entries.push(location(event, &hunk, None));
+            }
+        }
+        Self { entries }
+    }
+
+    /// Entries for the events currently in the log, oldest first
+    pub fn from_state(state: &AppState) -> Self {
+        Self::from_events(state.events.iter().rev())
+    }
+
+    /// Entries for the hunks of a review session matching `selection`
+    pub fn from_session(session: &ReviewSession, selection: HunkSelection) -> Self {
+        let mut entries = Vec::new();
+        for change in &session.changes {
+            if matches!(change.event.kind, FileEventKind::Deleted) {
+                continue;
+            }
+            for hunk in &change.hunks {
+                let action = change.review_actions.get(&hunk.id).unwrap_or(&ReviewAction::Pending);
+                if selection.includes(action) {
+                    entries.push(location(&change.event, hunk, Some(action)));
+                }
+            }
+        }
+        Self { entries }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Quickfix text, one `path:line:col: message` line per entry.
+    ///
+    /// Paths are written verbatim: Vim's default `%f:%l:%c: %m` accepts
+    /// spaces and colons in file names as long as `:line:col:` follows.
+    pub fn to_quickfix(&self) -> String {
+        let mut out = String::new();
+        for entry in &self.entries {
+            out.push_str(&format!(
+                "{}:{}:{}: {}\n",
+                entry.file.display(),
+                entry.line,
+                entry.column,
+                entry.message
+            ));
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.entries)?)
+    }
+
+    pub fn render(&self, format: LocationFormat) -> Result<String> {
+        match format {
+            LocationFormat::Quickfix => Ok(self.to_quickfix()),
+            LocationFormat::Json => self.to_json(),
+        }
+    }
+
+    /// Write the list to `path`, replacing it atomically so an editor never
+    /// reads a half-written file
+    pub fn write<P: AsRef<Path>>(&self, path: P, format: LocationFormat) -> Result<()> {
+        let path = path.as_ref();
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| anyhow::anyhow!("Invalid location file path: {}", path.display()))?;
+        let tmp = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+
+        fs::write(&tmp, self.render(format)?)
+            .with_context(|| format!("Failed to write locations: {}", tmp.display()))?;
+        fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to replace locations: {}", path.display()))?;
+        Ok(())
+    }
+}
+
+fn location(event: &FileEvent, hunk: &DiffHunk, action: Option<&ReviewAction>) -> Location {
+    let confidence = match event.confidence {
+        Some(ref confidence) => format!(
+            "{} {:.0}%",
+            confidence_level_name(&confidence.level),
+            confidence.score * 100.0
+        ),
+        None => "unscored".to_string(),
+    };
+    let mut message = format!(
+        "{} by {}, {}: {}",
+        kind_name(&event.kind),
+        origin_name(&event.origin),
+        confidence,
+        hunk.header.trim()
+    );
+    if let Some(action) = action {
+        message.push_str(&format!(" [{:?}]", action).to_lowercase());
+    }
+
+    Location {
+        file: event.path.clone(),
+        // Hunks that only delete lines start at 0 in the new file
+        line: hunk.new_start.max(1),
+        column: 1,
+        // Quickfix entries are one line each
+        message: message.replace(['\n', '\r'], " "),
+    }
+}
+
+/// Keeps a location file in sync with incoming events (`--locations-live`)
+pub struct LiveLocations {
+    path: PathBuf,
+    format: LocationFormat,
+    events: VecDeque<FileEvent>,
+    max_events: usize,
+    dirty_since: Option<Instant>,
+}
+
+impl LiveLocations {
+    pub fn new(path: PathBuf, format: LocationFormat, max_events: usize) -> Self {
+        Self {
+            path,
+            format,
+            events: VecDeque::new(),
+            max_events,
+            dirty_since: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Add an event; the file is rewritten once events stop arriving
+    pub fn record(&mut self, event: &FileEvent, now: Instant) {
+        self.events.push_back(event.clone());
+        while self.events.len() > self.max_events {
+            self.events.pop_front();
+        }
+        self.dirty_since = Some(now);
+    }
+
+    /// Rewrite the file if it is stale and no event arrived for `LIVE_DEBOUNCE`.
+    /// Returns whether the file was written.
+    pub fn flush_if_due(&mut self, now: Instant) -> Result<bool> {
+        match self.dirty_since {
+            Some(since) if now.duration_since(since) >= LIVE_DEBOUNCE => self.flush().map(|_| true),
+            _ => Ok(false),
+        }
+    }
+
+    /// Write any events not yet flushed, e.g. on shutdown
+    pub fn finish(&mut self) -> Result<()> {
+        if self.dirty_since.is_some() {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Rewrite the file now
+    pub fn flush(&mut self) -> Result<()> {
+        self.dirty_since = None;
+        LocationList::from_events(&self.events).write(&self.path, self.format)
+    }
+}
+
+impl ShutdownWriter for LiveLocations {
+    fn pending(&self) -> usize {
+        usize::from(self.dirty_since.is_some())
+    }
+
+    fn drain(&mut self, _deadline: Instant) -> Result<()> {
+        self.finish()
+    }
+
+    /// Each rewrite replaces the file in one step, so there is nothing left to close
+    fn close(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+    use tempfile::TempDir;
+    use crate::core::{ChangeConfidence, ChangeOrigin, ConfidenceLevel};
+
+    const DIFF: &str = "--- a\n+++ b\n@@ -1,2 +3,2 @@\n-old\n+new\n@@ -20,1 +0,0 @@\n-gone";
+
+    fn event(path: &str) -> FileEvent {
+        FileEvent::new(PathBuf::from(path), FileEventKind::Modified)
+            .with_diff(DIFF.to_string())
+            .with_origin(ChangeOrigin::AIAgent { tool_name: "claude".to_string(), process_id: None })
+            .with_confidence(ChangeConfidence {
+                level: ConfidenceLevel::Review,
+                score: 0.55,
+                reasons: vec![],
+                reason_impacts: vec![],
+                rule_hits: Vec::new(),
+                score_breakdown: None,
+            })
+    }
+
+    #[test]
+    fn test_quickfix_lines_with_colons_and_spaces_in_paths() {
+        let list = LocationList::from_events(&[event("my dir/a:b.rs"), event("C:/x y/z.rs")]);
+        let quickfix = list.to_quickfix();
+        let lines: Vec<&str> = quickfix.lines().collect();
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "my dir/a:b.rs:3:1: modified by ai:claude, review 55%: @@ -1,2 +3,2 @@");
+        // A deletion-only hunk still points at a real line
+        assert_eq!(lines[1], "my dir/a:b.rs:1:1: modified by ai:claude, review 55%: @@ -20,1 +0,0 @@");
+
+        // Parse the way Vim's `%f:%l:%c: %m` does: shortest file name followed by :line:col:
+        let efm = Regex::new(r"^(.+?):(\d+):(\d+): (.*)$").unwrap();
+        for (line, expected) in lines.iter().zip(["my dir/a:b.rs", "my dir/a:b.rs", "C:/x y/z.rs", "C:/x y/z.rs"]) {
+            let caps = efm.captures(line).unwrap();
+            assert_eq!(&caps[1], expected);
+            assert_eq!(&caps[3], "1");
+        }
+    }
+
+    #[test]
+    fn test_session_selection_and_json() {
+        let mut session = ReviewSession::new();
+        session.add_change(event("a.rs"));
+        session.add_change(FileEvent::new(PathBuf::from("gone.rs"), FileEventKind::Deleted).with_diff(DIFF.to_string()));
+        let first_hunk = session.changes[0].hunks[0].id.clone();
+        session.changes[0].accept_hunk(&first_hunk);
+
+        let accepted = LocationList::from_session(&session, HunkSelection::Accepted);
+        assert_eq!(accepted.len(), 1);
+        assert!(accepted.entries[0].message.ends_with("[accept]"));
+        assert_eq!(LocationList::from_session(&session, HunkSelection::Pending).len(), 1);
+        assert_eq!(LocationList::from_session(&session, HunkSelection::All).len(), 2);
+
+        let json: serde_json::Value = serde_json::from_str(&accepted.to_json().unwrap()).unwrap();
+        assert_eq!(json[0]["file"], "a.rs");
+        assert_eq!(json[0]["line"], 3);
+    }
+
+    #[test]
+    fn test_live_locations_debounce_and_atomic_rewrite() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("changes.qf");
+        let mut live = LiveLocations::new(path.clone(), LocationFormat::Quickfix, 10);
+        let start = Instant::now();
+
+        live.record(&event("a.rs"), start);
+        assert!(!live.flush_if_due(start + Duration::from_millis(10)).unwrap());
+        assert!(!path.exists());
+
+        assert!(live.flush_if_due(start + LIVE_DEBOUNCE).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+        assert!(!live.flush_if_due(start + LIVE_DEBOUNCE * 2).unwrap());
+
+        // Only the location file is left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
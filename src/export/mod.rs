@@ -3,12 +3,27 @@
 //! This module provides functionality to export diffs in various formats
 //! to files or other outputs.
 
+use std::collections::HashSet;
 use std::fs;
 use std::io::Write;
 use std::path::Path;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use crate::diff::{DiffResult, DiffFormatter, DiffFormat};
-use crate::core::FileEvent;
+use crate::core::{AppState, FileEvent, PathDisplay};
+
+pub mod bundle;
+pub mod csv_writer;
+pub mod github_review;
+pub mod locations;
+pub mod patch_writer;
+pub mod review_report;
+
+pub use bundle::{BundleApplyOptions, BundleEntry, ReviewBundle};
+pub use csv_writer::CsvEventWriter;
+pub use github_review::{DiffSide, GithubReview, GithubReviewComment};
+pub use locations::{HunkSelection, LiveLocations, LocationFormat, LocationList};
+pub use patch_writer::{PatchEventWriter, PatchOutcome};
+pub use review_report::ReviewReportExporter;
 
 /// Export configuration
 #[derive(Debug, Clone)]
@@ -17,6 +32,8 @@ pub struct ExportConfig {
     pub include_stats: bool,
     pub include_metadata: bool,
     pub width: Option<usize>, // For side-by-side format
+    /// Which path of a change under a container mount is written
+    pub path_display: PathDisplay,
 }
 
 impl Default for ExportConfig {
@@ -26,10 +43,30 @@ impl Default for ExportConfig {
             include_stats: true,
             include_metadata: true,
             width: Some(120),
+            path_display: PathDisplay::Host,
         }
     }
 }
 
+/// Records which patches of a bundle were written, one file name per line
+/// after a `total <n>` header. Removed once the bundle is complete.
+const BUNDLE_PROGRESS_FILE: &str = ".export-progress";
+
+/// Options for writing a patch bundle
+#[derive(Debug, Clone, Default)]
+pub struct PatchBundleOptions {
+    /// Keep patches an earlier, interrupted run already wrote
+    pub resume: bool,
+}
+
+/// Outcome of writing a patch bundle
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatchBundleReport {
+    pub written: usize,
+    /// Patches kept from an earlier run
+    pub skipped: usize,
+}
+
 /// Handles exporting diffs to various formats and destinations
 pub struct DiffExporter {
     config: ExportConfig,
@@ -49,6 +86,12 @@ impl DiffExporter {
         }
     }
     
+    /// Write container paths, host paths or both for changes under a container mount
+    pub fn with_path_display(mut self, display: PathDisplay) -> Self {
+        self.config.path_display = display;
+        self
+    }
+    
     /// Export a single diff result to a file
     pub fn export_diff<P: AsRef<Path>>(
         &self,
@@ -116,6 +159,23 @@ impl DiffExporter {
         Ok(())
     }
     
+    /// Export every event of one batch in the log as a single patch.
+    /// Returns the number of events exported.
+    pub fn export_batch<P: AsRef<Path>>(
+        &self,
+        state: &AppState,
+        batch_id: &str,
+        output_path: P,
+    ) -> Result<usize> {
+        let events: Vec<FileEvent> = state.events_in_batch(batch_id).into_iter().cloned().collect();
+        if events.is_empty() {
+            anyhow::bail!("No events found for batch {}", batch_id);
+        }
+        
+        self.export_multifile_patch(&events, output_path)?;
+        Ok(events.len())
+    }
+    
     /// Export to a writer (for streaming or custom outputs)
     pub fn export_diff_to_writer<W: Write>(
         &self,
@@ -151,10 +211,41 @@ impl DiffExporter {
         events: &[FileEvent],
         bundle_path: P,
     ) -> Result<()> {
+        self.create_patch_bundle_with_options(events, bundle_path, &PatchBundleOptions::default())?;
+        Ok(())
+    }
+    
+    /// Create a patch bundle, recording progress so an interrupted export can
+    /// be finished with `resume` instead of starting over.
+    ///
+    /// Each patch is written to a `.partial` file and renamed once complete,
+    /// so a failure never leaves a truncated patch behind.
+    pub fn create_patch_bundle_with_options<P: AsRef<Path>>(
+        &self,
+        events: &[FileEvent],
+        bundle_path: P,
+        options: &PatchBundleOptions,
+    ) -> Result<PatchBundleReport> {
         // For now, just create a directory with individual patch files
         let bundle_dir = bundle_path.as_ref();
-        fs::create_dir_all(bundle_dir)?;
+        fs::create_dir_all(bundle_dir)
+            .with_context(|| format!("Failed to create {}", bundle_dir.display()))?;
+        
+        let progress_path = bundle_dir.join(BUNDLE_PROGRESS_FILE);
+        let completed = if options.resume {
+            Self::read_bundle_progress(&progress_path, events.len())?
+        } else {
+            fs::write(&progress_path, format!("total {}\n", events.len()))
+                .with_context(|| format!("Failed to write {}", progress_path.display()))?;
+            HashSet::new()
+        };
+        let mut progress = fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&progress_path)
+            .with_context(|| format!("Failed to open {}", progress_path.display()))?;
         
+        let mut report = PatchBundleReport::default();
         for (i, event) in events.iter().enumerate() {
             let filename = format!("{:03}_{}.patch", 
                 i + 1, 
@@ -163,16 +254,57 @@ impl DiffExporter {
                     .unwrap_or("unknown")
             );
             
-            let patch_path = bundle_dir.join(filename);
+            let patch_path = bundle_dir.join(&filename);
+            if completed.contains(&filename) && patch_path.exists() {
+                report.skipped += 1;
+                continue;
+            }
+            
+            let partial_path = bundle_dir.join(format!("{}.partial", filename));
             let patch_content = self.format_file_event(event);
-            fs::write(patch_path, patch_content)?;
+            if let Err(err) = fs::write(&partial_path, patch_content) {
+                let _ = fs::remove_file(&partial_path);
+                return Err(err).with_context(|| format!("Failed to write {}", patch_path.display()));
+            }
+            fs::rename(&partial_path, &patch_path)
+                .with_context(|| format!("Failed to write {}", patch_path.display()))?;
+            writeln!(progress, "{}", filename)
+                .with_context(|| format!("Failed to update {}", progress_path.display()))?;
+            report.written += 1;
         }
         
         // Write a manifest file
         let manifest_content = self.create_manifest(events);
-        fs::write(bundle_dir.join("manifest.txt"), manifest_content)?;
+        fs::write(bundle_dir.join("manifest.txt"), manifest_content)
+            .with_context(|| format!("Failed to write manifest in {}", bundle_dir.display()))?;
         
-        Ok(())
+        drop(progress);
+        fs::remove_file(&progress_path)
+            .with_context(|| format!("Failed to remove {}", progress_path.display()))?;
+        Ok(report)
+    }
+    
+    /// Patch file names an earlier run completed; none if it left no record
+    fn read_bundle_progress(path: &Path, total: usize) -> Result<HashSet<String>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                fs::write(path, format!("total {}\n", total))
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                return Ok(HashSet::new());
+            }
+            Err(err) => return Err(err).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        
+        let mut lines = text.lines();
+        let recorded = lines.next().and_then(|header| header.strip_prefix("total "));
+        if recorded != Some(total.to_string().as_str()) {
+            anyhow::bail!(
+                "Cannot resume: {} was recorded for a different set of changes",
+                path.display()
+            );
+        }
+        Ok(lines.map(str::to_string).collect())
     }
     
     fn format_metadata(&self, old_path: &Path, new_path: &Path) -> String {
@@ -188,17 +320,27 @@ impl DiffExporter {
         let mut content = String::new();
         
         // Add event metadata
-        content.push_str(&format!("File: {}\n", event.path.display()));
+        content.push_str(&format!("File: {}\n", event.display_path(self.config.path_display)));
         content.push_str(&format!("Event: {:?}\n", event.kind));
         content.push_str(&format!("Timestamp: {}\n", 
             chrono::DateTime::<chrono::Utc>::from(event.timestamp)
                 .format("%Y-%m-%d %H:%M:%S UTC")
         ));
+        if let Some(encoding) = event.encoding {
+            content.push_str(&format!("Encoding: {} (the diff shows the decoded text)\n", encoding.name()));
+        }
         
         // Add diff if available
-        if let Some(ref diff) = event.diff {
-            content.push_str("\n");
-            content.push_str(diff);
+        // Patches must stay valid, so truncated long lines are restored
+        if let Some(diff) = event.full_diff() {
+            content.push('\n');
+            match event.container_path {
+                // The patch applies inside the container
+                Some(ref container) if self.config.path_display == PathDisplay::Container => {
+                    content.push_str(&retarget_diff_headers(&diff, container));
+                }
+                _ => content.push_str(&diff),
+            }
         }
         
         content
@@ -207,7 +349,7 @@ impl DiffExporter {
     fn create_manifest(&self, events: &[FileEvent]) -> String {
         let mut content = String::new();
         
-        content.push_str(&format!("Patch Bundle Manifest\n"));
+        content.push_str("Patch Bundle Manifest\n");
         content.push_str(&format!("Generated at: {}\n", 
             chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
         ));
@@ -217,7 +359,7 @@ impl DiffExporter {
             content.push_str(&format!(
                 "{:03}. {} ({:?})\n",
                 i + 1,
-                event.path.display(),
+                event.display_path(self.config.path_display),
                 event.kind
             ));
         }
@@ -226,6 +368,27 @@ impl DiffExporter {
     }
 }
 
+/// `diff` with the paths of its `---` and `+++` headers replaced by `path`
+fn retarget_diff_headers(diff: &str, path: &Path) -> String {
+    let mut retargeted = String::with_capacity(diff.len());
+    let mut in_header = true;
+    for line in diff.split_inclusive('\n') {
+        let header = ["--- ", "+++ "].into_iter().find(|prefix| line.starts_with(prefix));
+        match header {
+            Some(prefix) if in_header => {
+                let newline = &line[line.trim_end_matches(['\r', '\n']).len()..];
+                retargeted.push_str(&format!("{}{}{}", prefix, path.display(), newline));
+            }
+            _ => {
+                // Removed lines that look like headers appear after the first hunk
+                in_header = in_header && !line.starts_with("@@");
+                retargeted.push_str(line);
+            }
+        }
+    }
+    retargeted
+}
+
 /// Predefined export presets
 impl DiffExporter {
     /// Create an exporter for Git-style patches
@@ -290,6 +453,21 @@ mod tests {
             origin: crate::core::ChangeOrigin::Unknown,
             confidence: None,
             batch_id: None,
+            over_budget: false,
+            content_hash: None,
+            long_lines: None,
+            coverage: None,
+            attribution_reason: None,
+            manifest_change: None,
+            container_path: None,
+            intent: None,
+            undeclared: false,
+            append_only: false,
+            encoding: None,
+            secrets: Vec::new(),
+            masked: false,
+            archive: None,
+            content: None,
         };
         
         let exporter = DiffExporter::unified();
@@ -299,4 +477,102 @@ mod tests {
         assert!(content.contains("Multi-file patch"));
         assert!(content.contains("test.txt"));
     }
+
+    #[test]
+    fn test_export_writes_container_paths_when_configured() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_path = temp_dir.path().join("container.patch");
+
+        let event = FileEvent::new(Path::new("/home/me/project/src/main.rs").to_path_buf(), FileEventKind::Modified)
+            .with_diff("--- /home/me/project/src/main.rs\n+++ /home/me/project/src/main.rs\n@@ -1,2 +1,1 @@\n---- old\n+new\n".to_string())
+            .with_container_path(Path::new("/app/src/main.rs").to_path_buf());
+
+        DiffExporter::unified()
+            .with_path_display(PathDisplay::Container)
+            .export_multifile_patch(std::slice::from_ref(&event), &output_path)
+            .unwrap();
+        let content = fs::read_to_string(&output_path).unwrap();
+        assert!(content.contains("File: /app/src/main.rs\n"));
+        assert!(content.contains("--- /app/src/main.rs\n+++ /app/src/main.rs\n@@ -1,2 +1,1 @@\n---- old\n+new\n"), "{}", content);
+        assert!(!content.contains("/home/me"));
+
+        DiffExporter::unified()
+            .with_path_display(PathDisplay::Both)
+            .export_multifile_patch(&[event], &output_path)
+            .unwrap();
+        let content = fs::read_to_string(&output_path).unwrap();
+        assert!(content.contains("File: /home/me/project/src/main.rs → /app/src/main.rs\n"));
+        assert!(content.contains("+++ /home/me/project/src/main.rs\n"));
+    }
+
+    #[test]
+    fn test_export_batch_only_includes_its_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_path = temp_dir.path().join("batch.patch");
+
+        let mut state = AppState::default();
+        for (name, batch) in [("a.rs", "batch_1"), ("b.rs", "batch_2"), ("c.rs", "batch_1"), ("d.rs", "batch_2")] {
+            state.add_event(
+                FileEvent::new(Path::new(name).to_path_buf(), FileEventKind::Modified)
+                    .with_diff(format!("--- a/{0}\n+++ b/{0}\n@@ -1 +1 @@\n-old\n+new", name))
+                    .with_batch_id(batch.to_string()),
+            );
+        }
+
+        let exporter = DiffExporter::unified();
+        assert_eq!(exporter.export_batch(&state, "batch_1", &output_path).unwrap(), 2);
+
+        let content = fs::read_to_string(&output_path).unwrap();
+        assert!(content.contains("containing 2 files"));
+        assert!(content.contains("File: a.rs") && content.contains("File: c.rs"));
+        assert!(!content.contains("b.rs") && !content.contains("d.rs"));
+        // Oldest change first
+        assert!(content.find("a.rs").unwrap() < content.find("c.rs").unwrap());
+
+        assert!(exporter.export_batch(&state, "batch_3", &output_path).is_err());
+    }
+
+    #[test]
+    fn test_resumed_patch_bundle_keeps_written_patches() {
+        let temp_dir = TempDir::new().unwrap();
+        let bundle_dir = temp_dir.path().join("bundle");
+        let events: Vec<FileEvent> = (1..=6)
+            .map(|i| {
+                let name = format!("file{}.rs", i);
+                FileEvent::new(Path::new(&name).to_path_buf(), FileEventKind::Modified)
+                    .with_diff(format!("--- a/{0}\n+++ b/{0}\n@@ -1 +1 @@\n-old\n+new", name))
+            })
+            .collect();
+        let exporter = DiffExporter::unified();
+
+        // The fifth patch cannot be written, as if the disk filled up
+        fs::create_dir_all(bundle_dir.join("005_file5.rs.patch.partial")).unwrap();
+        let err = exporter.create_patch_bundle(&events, &bundle_dir).unwrap_err();
+        assert!(err.to_string().contains("005_file5.rs.patch"));
+        for i in 1..=4 {
+            assert!(bundle_dir.join(format!("00{0}_file{0}.rs.patch", i)).exists());
+        }
+        assert!(!bundle_dir.join("005_file5.rs.patch").exists());
+        assert!(!bundle_dir.join("manifest.txt").exists());
+
+        // Mark the finished patches to tell whether they are rewritten
+        for i in 1..=4 {
+            fs::write(bundle_dir.join(format!("00{0}_file{0}.rs.patch", i)), "kept").unwrap();
+        }
+        fs::remove_dir(bundle_dir.join("005_file5.rs.patch.partial")).unwrap();
+
+        let options = PatchBundleOptions { resume: true };
+        let report = exporter.create_patch_bundle_with_options(&events, &bundle_dir, &options).unwrap();
+        assert_eq!(report, PatchBundleReport { written: 2, skipped: 4 });
+        for i in 1..=4 {
+            assert_eq!(fs::read_to_string(bundle_dir.join(format!("00{0}_file{0}.rs.patch", i))).unwrap(), "kept");
+        }
+        assert!(fs::read_to_string(bundle_dir.join("006_file6.rs.patch")).unwrap().contains("+new"));
+        assert!(fs::read_to_string(bundle_dir.join("manifest.txt")).unwrap().contains("Total files: 6"));
+        assert!(!bundle_dir.join(BUNDLE_PROGRESS_FILE).exists());
+
+        // Resuming with a different set of changes is refused
+        fs::write(bundle_dir.join(BUNDLE_PROGRESS_FILE), "total 3\n001_file1.rs.patch\n").unwrap();
+        assert!(exporter.create_patch_bundle_with_options(&events, &bundle_dir, &options).is_err());
+    }
 }
\ No newline at end of file
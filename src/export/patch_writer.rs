@@ -0,0 +1,255 @@
+//! Streaming output of file events as patches `git apply` accepts
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use anyhow::Result;
+use crate::core::{FileEvent, FileEventKind};
+use crate::diff::{DiffAlgorithmType, DiffFormat, DiffFormatter, DiffGenerator, DEV_NULL};
+
+/// What became of an event handed to [`PatchEventWriter::write_event`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchOutcome {
+    Written,
+    /// The content did not change
+    Unchanged,
+    /// No patch could be made, for the given reason
+    Skipped(&'static str),
+}
+
+/// Writes one patch per event, with paths relative to the watch root.
+///
+/// Event diffs are shortened for display, so patches are diffed again from
+/// the full content of each file as of its previous event. The first event
+/// of a file that existed when watching began is diffed against the content
+/// given to [`PatchEventWriter::seed`]; without it there is nothing to diff
+/// against and the patch is skipped.
+///
+/// Patches carry only the change itself: origin, confidence, rule hits and
+/// the other event fields have no place in a patch `git apply` accepts, and
+/// are left to the JSON, CSV and history outputs.
+pub struct PatchEventWriter<W: Write> {
+    output: W,
+    root: PathBuf,
+    generator: DiffGenerator,
+    /// Content after the last event of each file seen; `None` where it is
+    /// unknown, e.g. binary or deleted
+    contents: HashMap<PathBuf, Option<Arc<str>>>,
+}
+
+impl<W: Write> PatchEventWriter<W> {
+    /// `root` must be absolute, as event paths are
+    pub fn new(output: W, root: PathBuf) -> Self {
+        Self {
+            output,
+            root,
+            generator: DiffGenerator::default(),
+            contents: HashMap::new(),
+        }
+    }
+
+    pub fn with_algorithm(mut self, algorithm: DiffAlgorithmType) -> Self {
+        self.generator = DiffGenerator::new(algorithm);
+        self
+    }
+
+    pub fn write_event(&mut self, event: &FileEvent) -> Result<PatchOutcome> {
+        let patch = self.patch(event);
+        self.track(event);
+        let patch = match patch {
+            Ok(Some(patch)) => patch,
+            Ok(None) => return Ok(PatchOutcome::Unchanged),
+            Err(reason) => return Ok(PatchOutcome::Skipped(reason)),
+        };
+        writeln!(self.output, "{}", patch)?;
+        // Flush per patch so output can be tailed while watching
+        self.output.flush()?;
+        Ok(PatchOutcome::Written)
+    }
+
+    /// Before the first event of a file, supply its content from when
+    /// watching began. `baseline` is only asked for files no event has been
+    /// seen for, since later events carry their own content.
+    pub fn seed(&mut self, event: &FileEvent, baseline: impl FnOnce(&Path) -> Option<Arc<str>>) {
+        let previous = match event.kind {
+            FileEventKind::Moved { ref from, .. } => from,
+            _ => &event.path,
+        };
+        if !self.contents.contains_key(previous) {
+            if let Some(content) = baseline(previous) {
+                self.contents.insert(previous.clone(), Some(content));
+            }
+        }
+    }
+
+    /// Remember the content after `event` without writing a patch, so that
+    /// the next patch of the file applies on top of it
+    pub fn track(&mut self, event: &FileEvent) {
+        match event.kind {
+            // Content decoded from another encoding is no base for a patch
+            FileEventKind::Created | FileEventKind::Modified => {
+                let content = event.content.clone().filter(|_| event.encoding.is_none());
+                self.contents.insert(event.path.clone(), content);
+            }
+            FileEventKind::Deleted => {
+                self.contents.insert(event.path.clone(), None);
+            }
+            FileEventKind::Moved { ref from, ref to } => {
+                let content = self.contents.insert(from.clone(), None).flatten();
+                self.contents.insert(to.clone(), event.content.clone().or(content));
+            }
+        }
+    }
+
+    /// Content after the previous event of `path`, if known
+    fn previous(&self, path: &Path) -> Option<&Arc<str>> {
+        self.contents.get(path)?.as_ref()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    /// The patch for `event`, none if nothing changed, or why there is none
+    fn patch(&self, event: &FileEvent) -> Result<Option<String>, &'static str> {
+        let dev_null = Path::new(DEV_NULL);
+        if event.encoding.is_some() {
+            // A patch of the decoded text would not apply to the file's bytes
+            return Err("its text is not UTF-8");
+        }
+
+        match event.kind {
+            FileEventKind::Created => {
+                let path = self.relative(&event.path)?;
+                Ok(Some(match event.content {
+                    Some(ref content) => {
+                        let result = self.generator.generate("", content);
+                        DiffFormatter::format(&result, DiffFormat::GitPatch, dev_null, path.as_path(), None)
+                    }
+                    // Binary, or too large to read
+                    None => DiffFormatter::format_git_binary(dev_null, path.as_path()),
+                }))
+            }
+            FileEventKind::Modified => {
+                let path = self.relative(&event.path)?;
+                match (self.previous(&event.path), event.content.as_ref()) {
+                    (Some(old), Some(new)) => {
+                        let result = self.generator.generate(old, new);
+                        Ok((result.is_binary || !result.hunks.is_empty())
+                            .then(|| DiffFormatter::format(&result, DiffFormat::GitPatch, &path, &path, None)))
+                    }
+                    (_, None) => Ok(Some(DiffFormatter::format_git_binary(&path, &path))),
+                    (None, Some(_)) => Err("its earlier content is unknown"),
+                }
+            }
+            FileEventKind::Deleted => {
+                let path = self.relative(&event.path)?;
+                match self.previous(&event.path) {
+                    Some(old) => {
+                        let result = self.generator.generate(old, "");
+                        Ok(Some(DiffFormatter::format(&result, DiffFormat::GitPatch, path.as_path(), dev_null, None)))
+                    }
+                    None => Err("its content before the deletion is unknown"),
+                }
+            }
+            FileEventKind::Moved { ref from, ref to } => {
+                let (from_path, to_path) = (self.relative(from)?, self.relative(to)?);
+                let result = match (self.previous(from), event.content.as_ref()) {
+                    (Some(old), Some(new)) => Some(self.generator.generate(old, new)),
+                    _ => None,
+                };
+                Ok(Some(DiffFormatter::format_git_rename(result.as_ref(), &from_path, &to_path)))
+            }
+        }
+    }
+
+    fn relative(&self, path: &Path) -> Result<PathBuf, &'static str> {
+        path.strip_prefix(&self.root)
+            .map(Path::to_path_buf)
+            .map_err(|_| "it is outside the watch root")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(path: &str, kind: FileEventKind, content: Option<&str>) -> FileEvent {
+        let event = FileEvent::new(PathBuf::from("/repo").join(path), kind);
+        match content {
+            Some(content) => event.with_content(Arc::from(content)),
+            None => event,
+        }
+    }
+
+    fn output(writer: PatchEventWriter<Vec<u8>>) -> String {
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn test_patches_follow_each_file() {
+        let mut writer = PatchEventWriter::new(Vec::new(), PathBuf::from("/repo"));
+        let created = event("src/a.txt", FileEventKind::Created, Some("one\ntwo \n"));
+        assert_eq!(writer.write_event(&created).unwrap(), PatchOutcome::Written);
+        let patch = output(writer);
+        assert!(patch.starts_with("diff --git a/src/a.txt b/src/a.txt\nnew file mode 100644\n"));
+        assert!(patch.contains("--- /dev/null\n+++ b/src/a.txt\n@@ -0,0 +1,2 @@\n+one\n+two \n"));
+
+        let mut writer = PatchEventWriter::new(Vec::new(), PathBuf::from("/repo"));
+        writer.track(&created);
+        let modified = event("src/a.txt", FileEventKind::Modified, Some("one\nthree"));
+        assert_eq!(writer.write_event(&modified).unwrap(), PatchOutcome::Written);
+        assert_eq!(writer.write_event(&modified).unwrap(), PatchOutcome::Unchanged);
+        let deleted = event("src/a.txt", FileEventKind::Deleted, None);
+        assert_eq!(writer.write_event(&deleted).unwrap(), PatchOutcome::Written);
+        let patch = output(writer);
+        assert!(patch.contains("--- a/src/a.txt\n+++ b/src/a.txt\n@@ -1,2 +1,2 @@\n one\n-two \n+three\n\\ No newline at end of file\n"));
+        assert!(patch.contains("deleted file mode 100644\nindex 1111111..0000000\n--- a/src/a.txt\n+++ /dev/null\n@@ -1,2 +0,0 @@\n"));
+    }
+
+    #[test]
+    fn test_renames_binaries_and_unknown_files() {
+        let mut writer = PatchEventWriter::new(Vec::new(), PathBuf::from("/repo"));
+        let moved = FileEventKind::Moved { from: PathBuf::from("/repo/old.rs"), to: PathBuf::from("/repo/new.rs") };
+        assert_eq!(writer.write_event(&event("new.rs", moved, None)).unwrap(), PatchOutcome::Written);
+        assert_eq!(writer.write_event(&event("logo.png", FileEventKind::Created, None)).unwrap(), PatchOutcome::Written);
+        assert_eq!(writer.write_event(&event("logo.png", FileEventKind::Modified, None)).unwrap(), PatchOutcome::Written);
+
+        let unseen = event("seen_late.txt", FileEventKind::Modified, Some("x\n"));
+        assert!(matches!(writer.write_event(&unseen).unwrap(), PatchOutcome::Skipped(_)));
+        let outside = FileEvent::new(PathBuf::from("/elsewhere/a.txt"), FileEventKind::Created);
+        assert!(matches!(writer.write_event(&outside).unwrap(), PatchOutcome::Skipped(_)));
+        let utf16 = event("notes.txt", FileEventKind::Created, Some("x\n")).with_encoding(crate::core::TextEncoding::Utf16Le);
+        assert!(matches!(writer.write_event(&utf16).unwrap(), PatchOutcome::Skipped(_)));
+
+        assert_eq!(
+            output(writer),
+            "diff --git a/old.rs b/new.rs\nrename from old.rs\nrename to new.rs\n\
+             diff --git a/logo.png b/logo.png\nnew file mode 100644\nBinary files /dev/null and b/logo.png differ\n\
+             diff --git a/logo.png b/logo.png\nBinary files a/logo.png and b/logo.png differ\n"
+        );
+    }
+
+    #[test]
+    fn test_first_change_to_existing_file_diffs_against_baseline() {
+        let mut writer = PatchEventWriter::new(Vec::new(), PathBuf::from("/repo"));
+        let baseline = |path: &Path| (path == Path::new("/repo/a.txt")).then(|| Arc::from("one\ntwo\n"));
+
+        let modified = event("a.txt", FileEventKind::Modified, Some("one\n2\n"));
+        writer.seed(&modified, baseline);
+        assert_eq!(writer.write_event(&modified).unwrap(), PatchOutcome::Written);
+
+        // Once seen, the file's own events are the base, even when unknown
+        let binary = event("a.txt", FileEventKind::Modified, None);
+        writer.seed(&binary, baseline);
+        assert_eq!(writer.write_event(&binary).unwrap(), PatchOutcome::Written);
+        let again = event("a.txt", FileEventKind::Modified, Some("one\n"));
+        writer.seed(&again, baseline);
+        assert!(matches!(writer.write_event(&again).unwrap(), PatchOutcome::Skipped(_)));
+
+        let patch = output(writer);
+        assert!(patch.starts_with("diff --git a/a.txt b/a.txt\n"));
+        assert!(patch.contains("@@ -1,2 +1,2 @@\n one\n-two\n+2\n"));
+    }
+}
@@ -0,0 +1,188 @@
+//! Review sessions as markdown reports
+//!
+//! The report is meant to be pasted into a pull request comment: the
+//! session's decisions as a table, the rejected hunks under "Requires
+//! changes", then every other hunk by file. Hunks are shown as `diff` code
+//! blocks, with the reviewer's comment below them.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use crate::review::{DiffHunk, ReviewAction, ReviewSession, ReviewableChange};
+use crate::ui::icons::{IconMode, Icons};
+
+/// Writes review sessions as markdown
+pub struct ReviewReportExporter {
+    /// Paths in the report are relative to this
+    root: PathBuf,
+    /// `root` from the working directory, for events recorded with absolute paths
+    absolute_root: PathBuf,
+}
+
+impl ReviewReportExporter {
+    pub fn new(root: &Path) -> Self {
+        let absolute_root = std::env::current_dir().map_or_else(|_| root.to_path_buf(), |cwd| cwd.join(root));
+        Self { root: root.to_path_buf(), absolute_root }
+    }
+
+    /// Write the report of `session` to `path`
+    pub fn export_markdown(&self, session: &ReviewSession, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        fs::write(path, self.to_markdown(session))
+            .with_context(|| format!("Failed to write review report: {}", path.display()))
+    }
+
+    /// Where the report of session `session_id` is exported by default
+    pub fn default_path(base_dir: &Path, session_id: &str) -> PathBuf {
+        base_dir.join(".watchdiff").join("sessions").join(format!("{}.review.md", session_id))
+    }
+
+    pub fn to_markdown(&self, session: &ReviewSession) -> String {
+        let mut out = String::new();
+        let started = chrono::DateTime::<chrono::Utc>::from(session.started_at).format("%Y-%m-%d %H:%M:%S UTC");
+        let _ = writeln!(out, "# Review: {}\n", session.display_name());
+        let _ = writeln!(out, "- Session: `{}`", session.id);
+        let _ = writeln!(out, "- Started: {}", started);
+        if let Some(ref description) = session.description {
+            let _ = writeln!(out, "- Description: {}", description);
+        }
+
+        let stats = session.get_review_stats();
+        let _ = writeln!(out, "\n## Summary\n");
+        let _ = writeln!(out, "| Decision | Changes | Share |");
+        let _ = writeln!(out, "| --- | ---: | ---: |");
+        for (action, label, count) in [
+            (ReviewAction::Accept, "Accepted", stats.accepted),
+            (ReviewAction::Reject, "Rejected", stats.rejected),
+            (ReviewAction::Skip, "Skipped", stats.skipped),
+            (ReviewAction::Defer, "Needs discussion", stats.deferred),
+            (ReviewAction::Pending, "Pending", stats.pending),
+        ] {
+            let _ = writeln!(out, "| {} {} | {} | {} |", action_emoji(&action), label, count, percent(count, stats.total));
+        }
+        let _ = writeln!(out, "| Total | {} | |", stats.total);
+        if stats.inconsistent > 0 {
+            let _ = writeln!(out, "\n{} change(s) split hunks that depend on each other.", stats.inconsistent);
+        }
+
+        let rejected: Vec<(&ReviewableChange, Vec<&DiffHunk>)> = session.changes
+            .iter()
+            .map(|change| (change, change.hunks.iter().filter(|hunk| hunk_action(change, hunk) == ReviewAction::Reject).collect::<Vec<_>>()))
+            .filter(|(_, hunks)| !hunks.is_empty())
+            .collect();
+        if !rejected.is_empty() {
+            let _ = writeln!(out, "\n## Requires changes");
+            for (change, hunks) in rejected {
+                self.write_file_section(&mut out, change, &hunks);
+            }
+        }
+
+        let others: Vec<(&ReviewableChange, Vec<&DiffHunk>)> = session.changes
+            .iter()
+            .map(|change| (change, change.hunks.iter().filter(|hunk| hunk_action(change, hunk) != ReviewAction::Reject).collect::<Vec<_>>()))
+            .filter(|(change, hunks)| !hunks.is_empty() || change.hunks.is_empty())
+            .collect();
+        if !others.is_empty() {
+            let _ = writeln!(out, "\n## Changes");
+            for (change, hunks) in others {
+                self.write_file_section(&mut out, change, &hunks);
+            }
+        }
+        out
+    }
+
+    fn write_file_section(&self, out: &mut String, change: &ReviewableChange, hunks: &[&DiffHunk]) {
+        let path = change.event.path
+            .strip_prefix(&self.root)
+            .or_else(|_| change.event.path.strip_prefix(&self.absolute_root))
+            .unwrap_or(&change.event.path);
+        let _ = writeln!(out, "\n### {} `{}` ({:?})", action_emoji(&change.overall_action), path.display(), change.event.kind);
+        if change.hunks.is_empty() {
+            let _ = writeln!(out, "\nNo diff to review.");
+        }
+        for hunk in hunks {
+            let action = hunk_action(change, hunk);
+            let _ = writeln!(out, "\n{} `{}`\n", action_emoji(&action), hunk.header.trim());
+            // A fence longer than any backtick run in the diff can't be closed by it
+            let longest_run = hunk.lines
+                .iter()
+                .flat_map(|line| line.split(|c| c != '`'))
+                .map(str::len)
+                .max()
+                .unwrap_or(0);
+            let fence = "`".repeat(longest_run.max(2) + 1);
+            let _ = writeln!(out, "{}diff", fence);
+            for line in &hunk.lines {
+                let _ = writeln!(out, "{}", line);
+            }
+            let _ = writeln!(out, "{}", fence);
+            if let Some(comment) = change.comments.get(&hunk.id) {
+                for line in comment.lines() {
+                    let _ = writeln!(out, "\n> {}", line);
+                }
+            }
+        }
+    }
+}
+
+fn hunk_action(change: &ReviewableChange, hunk: &DiffHunk) -> ReviewAction {
+    change.review_actions.get(&hunk.id).cloned().unwrap_or(ReviewAction::Pending)
+}
+
+fn action_emoji(action: &ReviewAction) -> &'static str {
+    Icons::new(IconMode::Emoji).get(Icons::review_action(action))
+}
+
+/// `count` of `total` as a percentage with one decimal
+fn percent(count: usize, total: usize) -> String {
+    match total {
+        0 => "0.0%".to_string(),
+        _ => format!("{:.1}%", count as f64 * 100.0 / total as f64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{FileEvent, FileEventKind};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_report_groups_rejected_hunks_and_summarizes_decisions() {
+        let diff = "@@ -1,2 +1,2 @@ fn main()\n-let a = 1;\n+let a = 2;\n ctx\n@@ -9 +9 @@\n-``old``\n+new";
+        let mut session = ReviewSession::new();
+        session.name = Some("auth pass".to_string());
+        session.add_change(FileEvent::new(PathBuf::from("/repo/src/lib.rs"), FileEventKind::Modified).with_diff(diff.to_string()));
+        session.add_change(FileEvent::new(PathBuf::from("/repo/README.md"), FileEventKind::Modified).with_diff("@@ -3 +3 @@\n-x\n+y".to_string()));
+        session.add_change(FileEvent::new(PathBuf::from("/repo/notes.txt"), FileEventKind::Modified).with_diff("@@ -1 +1 @@\n-p\n+q".to_string()));
+        session.changes[0].accept_hunk("hunk_0");
+        session.changes[0].reject_hunk("hunk_1");
+        session.changes[0].set_comment("hunk_1", "Keep the old name\nit is public");
+        session.changes[1].accept_hunk("hunk_0");
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("out").join("report.md");
+        ReviewReportExporter::new(Path::new("/repo")).export_markdown(&session, &path).unwrap();
+        let report = fs::read_to_string(&path).unwrap();
+
+        assert!(report.starts_with(&format!("# Review: auth pass\n\n- Session: `{}`\n- Started: ", session.id)));
+        // lib.rs counts as accepted, since part of it lands
+        assert!(report.contains("| ✅ Accepted | 2 | 66.7% |"), "{}", report);
+        assert!(report.contains("| ⏳ Pending | 1 | 33.3% |"));
+        assert!(report.contains("| Total | 3 | |"));
+
+        // The rejected hunk is listed once, under "Requires changes", with its comment
+        let requires = report.find("## Requires changes").unwrap();
+        let changes = report.find("## Changes").unwrap();
+        let rejected = report.find("❌ `@@ -9 +9 @@`").unwrap();
+        assert!(requires < rejected && rejected < changes);
+        assert_eq!(report.matches("@@ -9 +9 @@").count(), 1);
+        assert!(report.contains("```diff\n-``old``\n+new\n```\n\n> Keep the old name\n\n> it is public\n"));
+        assert!(report[changes..].contains("### ✅ `src/lib.rs` (Modified)\n\n✅ `@@ -1,2 +1,2 @@ fn main()`\n\n```diff\n-let a = 1;\n"));
+        assert!(report[changes..].contains("`README.md`"));
+        assert!(report[changes..].contains("⏳ `@@ -1 +1 @@`"));
+    }
+}
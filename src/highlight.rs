@@ -1,4 +1,5 @@
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use syntect::easy::HighlightLines;
 use syntect::highlighting::{ThemeSet, Style};
 use syntect::parsing::SyntaxSet;
@@ -8,6 +9,8 @@ use ratatui::style::{Color, Modifier};
 pub struct SyntaxHighlighter {
     syntax_set: SyntaxSet,
     theme_set: ThemeSet,
+    /// Number of highlight requests served, for the performance overlay
+    calls: AtomicUsize,
 }
 
 impl Default for SyntaxHighlighter {
@@ -21,9 +24,15 @@ impl SyntaxHighlighter {
         Self {
             syntax_set: SyntaxSet::load_defaults_newlines(),
             theme_set: ThemeSet::load_defaults(),
+            calls: AtomicUsize::new(0),
         }
     }
 
+    /// How many times a line or block of code was highlighted
+    pub fn highlight_calls(&self) -> usize {
+        self.calls.load(Ordering::Relaxed)
+    }
+
     pub fn get_language_from_path<P: AsRef<Path>>(&self, path: P) -> Option<String> {
         let path = path.as_ref();
         
@@ -55,6 +64,7 @@ impl SyntaxHighlighter {
     }
 
     pub fn highlight_line(&self, line: &str, language: &str, _line_number: usize) -> Vec<(ratatui::style::Style, String)> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
         let syntax = match self.syntax_set.find_syntax_by_name(language) {
             Some(syntax) => syntax,
             None => return vec![(ratatui::style::Style::default(), line.to_string())],
@@ -81,9 +91,10 @@ impl SyntaxHighlighter {
     }
 
     pub fn highlight_code(&self, code: &str, language: &str) -> Vec<Vec<(ratatui::style::Style, String)>> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
         let syntax = match self.syntax_set.find_syntax_by_name(language) {
             Some(syntax) => syntax,
-            None => return code.lines().map(|line| vec![(ratatui::style::Style::default(), line.to_string())]).collect(),
+            None => return plain_lines(code),
         };
 
         let theme = match self.theme_set.themes.get("base16-ocean.dark") {
@@ -142,6 +153,49 @@ impl SyntaxHighlighter {
         result
     }
 
+    /// The first `max_lines` lines of a unified `diff`, with the code on
+    /// each line highlighted as `language` for terminals. Markers are green
+    /// or red and hunk headers cyan. `None` for an unknown language.
+    pub fn get_terminal_highlighted_diff(&self, diff: &str, language: &str, max_lines: usize) -> Option<String> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        let syntax = self.syntax_set.find_syntax_by_name(language)?;
+        let theme = match self.theme_set.themes.get("base16-ocean.dark") {
+            Some(theme) => theme,
+            None => &self.theme_set.themes["InspiredGitHub"],
+        };
+
+        // Both sides go through one highlighter, so a construct spanning lines
+        // is mostly still recognized
+        let mut highlighter = HighlightLines::new(syntax, theme);
+        let mut lines = Vec::new();
+        for line in diff.lines().take(max_lines) {
+            let marker = match line.chars().next() {
+                _ if line.starts_with("+++") || line.starts_with("---") => {
+                    lines.push(format!("\x1b[1m{}\x1b[0m", line));
+                    continue;
+                }
+                _ if line.starts_with("@@") => {
+                    lines.push(format!("\x1b[36m{}\x1b[0m", line));
+                    continue;
+                }
+                Some('+') => "\x1b[32m+",
+                Some('-') => "\x1b[31m-",
+                Some(' ') => " ",
+                _ => {
+                    lines.push(line.to_string());
+                    continue;
+                }
+            };
+            let code = format!("{}\n", &line[1..]);
+            let highlighted = match highlighter.highlight_line(&code, &self.syntax_set) {
+                Ok(ranges) => as_24_bit_terminal_escaped(&ranges[..], false),
+                Err(_) => code,
+            };
+            lines.push(format!("{}\x1b[0m{}\x1b[0m", marker, highlighted.trim_end_matches('\n')));
+        }
+        Some(lines.join("\n"))
+    }
+
     fn convert_syntect_style_to_ratatui(&self, style: Style) -> ratatui::style::Style {
         let mut ratatui_style = ratatui::style::Style::default();
 
@@ -212,6 +266,19 @@ impl SyntaxHighlighter {
     }
 }
 
+/// Highlighter shared by everything that has no highlighter of its own,
+/// loaded on first use
+pub fn shared() -> &'static SyntaxHighlighter {
+    static SHARED: std::sync::OnceLock<SyntaxHighlighter> = std::sync::OnceLock::new();
+    SHARED.get_or_init(SyntaxHighlighter::new)
+}
+
+/// `code` split into lines of a single unstyled span, the shape
+/// `highlight_code` returns, for when highlighting is off
+pub fn plain_lines(code: &str) -> Vec<Vec<(ratatui::style::Style, String)>> {
+    code.lines().map(|line| vec![(ratatui::style::Style::default(), line.to_string())]).collect()
+}
+
 // Helper function to detect if a file is likely to be binary
 pub fn is_likely_text_file<P: AsRef<Path>>(path: P) -> bool {
     let path = path.as_ref();
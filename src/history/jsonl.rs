@@ -0,0 +1,321 @@
+//! History kept in append-only JSON Lines segments
+//!
+//! Records are appended to numbered segment files (`00000001.jsonl`, ...)
+//! in the store directory, and a new segment is started once the current
+//! one reaches the segment size. `schema.json` holds the schema version the
+//! segments were written with. Retention drops whole segments, oldest first.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{HistoryPage, HistoryQuery, HistoryRecord, HistoryStore, Retention, HISTORY_SCHEMA_VERSION};
+
+const SCHEMA_FILE: &str = "schema.json";
+
+/// Upgrades between schema versions: entry `i` turns a version `i + 1`
+/// record into a version `i + 2` one. Add an entry with each new version.
+const MIGRATIONS: &[fn(&mut Value)] = &[];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SchemaFile {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub struct JsonlStore {
+    dir: PathBuf,
+    segment_bytes: u64,
+    /// Segment being appended to and its size, found on the first append
+    current: Option<(PathBuf, u64)>,
+}
+
+impl JsonlStore {
+    /// Open the store in `dir`, creating it or upgrading segments written
+    /// by an older version
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create history directory: {}", dir.display()))?;
+        let store = Self { dir: dir.to_path_buf(), segment_bytes: 4 * 1024 * 1024, current: None };
+
+        let schema_path = dir.join(SCHEMA_FILE);
+        let version = match fs::read_to_string(&schema_path) {
+            Ok(text) => serde_json::from_str::<SchemaFile>(&text)
+                .with_context(|| format!("Invalid history schema file: {}", schema_path.display()))?
+                .version,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HISTORY_SCHEMA_VERSION,
+            Err(err) => return Err(err).with_context(|| format!("Failed to read {}", schema_path.display())),
+        };
+        if version > HISTORY_SCHEMA_VERSION {
+            anyhow::bail!(
+                "History in {} has schema version {}, newer than this version supports ({})",
+                dir.display(),
+                version,
+                HISTORY_SCHEMA_VERSION
+            );
+        }
+        if version < HISTORY_SCHEMA_VERSION {
+            store.migrate(version)?;
+        }
+        let schema = serde_json::to_string(&SchemaFile { version: HISTORY_SCHEMA_VERSION })?;
+        fs::write(&schema_path, schema).with_context(|| format!("Failed to write {}", schema_path.display()))?;
+        Ok(store)
+    }
+
+    /// Start a new segment once the current one reaches `bytes`
+    pub fn with_segment_bytes(mut self, bytes: u64) -> Self {
+        self.segment_bytes = bytes.max(1);
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Segment files, oldest first
+    fn segments(&self) -> Result<Vec<PathBuf>> {
+        let mut segments: Vec<PathBuf> = fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to list history directory: {}", self.dir.display()))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| segment_number(path).is_some())
+            .collect();
+        segments.sort_by_key(|path| segment_number(path));
+        Ok(segments)
+    }
+
+    fn segment_path(&self, number: u64) -> PathBuf {
+        self.dir.join(format!("{:08}.jsonl", number))
+    }
+
+    /// Rewrite every segment from schema version `from` to the current one
+    fn migrate(&self, from: u32) -> Result<()> {
+        let steps = MIGRATIONS.get(from.saturating_sub(1) as usize..).unwrap_or_default();
+        for segment in self.segments()? {
+            let text = fs::read_to_string(&segment)
+                .with_context(|| format!("Failed to read history segment: {}", segment.display()))?;
+            let mut upgraded = String::with_capacity(text.len());
+            for line in text.lines().filter(|line| !line.trim().is_empty()) {
+                let mut record: Value = serde_json::from_str(line)
+                    .with_context(|| format!("Invalid record in {}", segment.display()))?;
+                for step in steps {
+                    step(&mut record);
+                }
+                upgraded.push_str(&serde_json::to_string(&record)?);
+                upgraded.push('\n');
+            }
+            fs::write(&segment, upgraded).with_context(|| format!("Failed to write {}", segment.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// `12` for `00000012.jsonl`
+fn segment_number(path: &Path) -> Option<u64> {
+    if path.extension()? != "jsonl" {
+        return None;
+    }
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+/// The records of a segment. A line cut short by a crash mid-append is
+/// skipped rather than failing every query.
+fn read_segment(path: &Path) -> Result<Vec<HistoryRecord>> {
+    let text = fs::read_to_string(path).with_context(|| format!("Failed to read history segment: {}", path.display()))?;
+    let mut records = Vec::new();
+    for (number, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        match serde_json::from_str(line) {
+            Ok(record) => records.push(record),
+            Err(err) => tracing::warn!("Skipping invalid history record at {}:{}: {}", path.display(), number + 1, err),
+        }
+    }
+    Ok(records)
+}
+
+impl HistoryStore for JsonlStore {
+    fn append(&mut self, record: &HistoryRecord) -> Result<()> {
+        let (path, size) = match self.current.take() {
+            Some(current) => current,
+            None => match self.segments()?.pop() {
+                Some(path) => {
+                    let size = fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+                    (path, size)
+                }
+                None => (self.segment_path(1), 0),
+            },
+        };
+        let (path, size) = if size >= self.segment_bytes {
+            (self.segment_path(segment_number(&path).unwrap_or(0) + 1), 0)
+        } else {
+            (path, size)
+        };
+
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .with_context(|| format!("Failed to append to history segment: {}", path.display()))?;
+        self.current = Some((path, size + line.len() as u64));
+        Ok(())
+    }
+
+    fn query(&self, query: &HistoryQuery) -> Result<HistoryPage> {
+        let mut records = Vec::new();
+        for segment in self.segments()? {
+            records.extend(read_segment(&segment)?.into_iter().filter(|record| query.matches(record)));
+        }
+        records.sort_by_key(|record| std::cmp::Reverse(record.timestamp));
+        Ok(query.paginate(records))
+    }
+
+    fn prune(&mut self, retention: &Retention, now: DateTime<Utc>) -> Result<usize> {
+        let mut segments = self.segments()?;
+        let mut dropped = 0;
+        let mut drop_segment = |segment: &Path| -> Result<()> {
+            dropped += read_segment(segment)?.len();
+            fs::remove_file(segment).with_context(|| format!("Failed to remove history segment: {}", segment.display()))
+        };
+
+        if let Some(max_age) = retention.max_age {
+            let cutoff = now - max_age;
+            let mut kept = Vec::with_capacity(segments.len());
+            for segment in segments {
+                let newest = read_segment(&segment)?.iter().map(|record| record.timestamp).max();
+                if newest.map_or(true, |newest| newest < cutoff) {
+                    drop_segment(&segment)?;
+                } else {
+                    kept.push(segment);
+                }
+            }
+            segments = kept;
+        }
+
+        if let (Some(max_bytes), Some((_, older))) = (retention.max_bytes, segments.split_last()) {
+            let size = |segment: &PathBuf| fs::metadata(segment).map(|meta| meta.len()).unwrap_or(0);
+            let mut total: u64 = segments.iter().map(size).sum();
+            // The newest segment is kept even when it alone is over the limit
+            for segment in older {
+                if total <= max_bytes {
+                    break;
+                }
+                total -= size(segment);
+                drop_segment(segment)?;
+            }
+        }
+
+        self.current = None;
+        Ok(dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{ChangeOrigin, ConfidenceLevel, FileEventKind};
+    use crate::history::{parse_time_bound, OriginKind};
+    use chrono::FixedOffset;
+    use tempfile::TempDir;
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().with_timezone(&Utc)
+    }
+
+    fn record(timestamp: &str, path: &str, origin: ChangeOrigin, confidence: Option<ConfidenceLevel>, batch: Option<&str>) -> HistoryRecord {
+        HistoryRecord {
+            timestamp: utc(timestamp),
+            path: PathBuf::from(path),
+            kind: FileEventKind::Modified,
+            origin,
+            confidence,
+            confidence_score: None,
+            batch_id: batch.map(str::to_string),
+            lines_added: 1,
+            lines_removed: 0,
+            content_hash: None,
+            rule_ids: Vec::new(),
+            diff: None,
+        }
+    }
+
+    fn ai() -> ChangeOrigin {
+        ChangeOrigin::AIAgent { tool_name: "claude".to_string(), process_id: None }
+    }
+
+    /// Tuesday 2024-05-07 around midnight in Berlin (UTC+2) and in UTC
+    fn seeded(dir: &Path) -> JsonlStore {
+        let mut store = JsonlStore::open(dir).unwrap().with_segment_bytes(300);
+        for record in [
+            record("2024-05-06T21:30:00Z", "src/main.rs", ChangeOrigin::Human, None, None),
+            record("2024-05-06T22:30:00Z", "src/lib.rs", ai(), Some(ConfidenceLevel::Risky), Some("batch-1")),
+            record("2024-05-07T12:00:00Z", "README.md", ai(), Some(ConfidenceLevel::Safe), Some("batch-1")),
+            record("2024-05-07T22:30:00Z", "src/auth/login.rs", ai(), Some(ConfidenceLevel::Review), Some("batch-2")),
+            record("2024-05-08T09:00:00Z", "tests/auth.rs", ChangeOrigin::Tool { name: "rustfmt".to_string() }, None, None),
+        ] {
+            store.append(&record).unwrap();
+        }
+        store
+    }
+
+    fn paths(page: &HistoryPage) -> Vec<&str> {
+        page.records.iter().map(|record| record.path.to_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_query_filters_and_pages_newest_first() {
+        let dir = TempDir::new().unwrap();
+        let store = seeded(dir.path());
+        assert!(store.segments().unwrap().len() > 1, "small segments roll over");
+
+        // The same Tuesday holds different changes in Berlin and in UTC
+        let berlin = FixedOffset::east_opt(2 * 3600).unwrap();
+        let tuesday = |tz: &FixedOffset| HistoryQuery {
+            since: Some(parse_time_bound("2024-05-07", tz, false).unwrap()),
+            until: Some(parse_time_bound("2024-05-07", tz, true).unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(paths(&store.query(&tuesday(&berlin)).unwrap()), vec!["README.md", "src/lib.rs"]);
+        let utc_tuesday = tuesday(&FixedOffset::east_opt(0).unwrap());
+        assert_eq!(paths(&store.query(&utc_tuesday).unwrap()), vec!["src/auth/login.rs", "README.md"]);
+
+        let query = |query: HistoryQuery| paths(&store.query(&query).unwrap()).into_iter().map(str::to_string).collect::<Vec<_>>();
+        assert_eq!(query(HistoryQuery::default().with_path_glob("*.rs").unwrap()).len(), 4);
+        assert_eq!(query(HistoryQuery::default().with_path_glob("src/**").unwrap()), vec!["src/auth/login.rs", "src/lib.rs", "src/main.rs"]);
+        assert_eq!(query(HistoryQuery { origin: Some(OriginKind::Ai), ..Default::default() }).len(), 3);
+        assert_eq!(query(HistoryQuery { confidence: Some(ConfidenceLevel::Risky), ..Default::default() }), vec!["src/lib.rs"]);
+        assert_eq!(query(HistoryQuery { batch: Some("batch-1".to_string()), ..Default::default() }), vec!["README.md", "src/lib.rs"]);
+
+        let page = store.query(&HistoryQuery { limit: 2, page: 3, ..Default::default() }).unwrap();
+        assert_eq!((page.total, page.pages), (5, 3));
+        assert_eq!(paths(&page), vec!["src/main.rs"]);
+    }
+
+    #[test]
+    fn test_retention_and_schema_version() {
+        let dir = TempDir::new().unwrap();
+        let mut store = seeded(dir.path());
+        let segments = store.segments().unwrap().len();
+
+        // Segments whose newest record is older than a day are dropped whole
+        let retention = Retention { max_age: Some(chrono::Duration::days(1)), max_bytes: None };
+        let dropped = store.prune(&retention, utc("2024-05-08T12:00:00Z")).unwrap();
+        assert!(dropped > 0);
+        let remaining = store.query(&HistoryQuery::default()).unwrap();
+        assert_eq!(remaining.total, 5 - dropped);
+        assert!(remaining.records.iter().any(|record| record.path == Path::new("tests/auth.rs")));
+        assert!(store.segments().unwrap().len() < segments);
+
+        // A size limit keeps only the newest segment
+        store.prune(&Retention { max_age: None, max_bytes: Some(1) }, utc("2024-05-08T12:00:00Z")).unwrap();
+        assert_eq!(store.segments().unwrap().len(), 1);
+        store.append(&record("2024-05-09T09:00:00Z", "new.rs", ChangeOrigin::Human, None, None)).unwrap();
+        assert_eq!(paths(&store.query(&HistoryQuery::default()).unwrap())[0], "new.rs");
+
+        fs::write(dir.path().join(SCHEMA_FILE), r#"{ "version": 99 }"#).unwrap();
+        assert!(JsonlStore::open(dir.path()).is_err());
+    }
+}
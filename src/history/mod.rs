@@ -0,0 +1,265 @@
+//! Changes recorded across sessions
+//!
+//! With `--history-db` every change is appended to a local store under the
+//! watch root, so `watchdiff history query` can still answer "what changed
+//! last Tuesday" after the session ended. Records keep the change's metadata;
+//! diffs only with `history.include_diffs`.
+//!
+//! Stores implement [`HistoryStore`], so the backend can change without
+//! touching the watcher or the query command. The one backend today is
+//! [`JsonlStore`], append-only segmented JSON Lines files.
+
+pub mod jsonl;
+
+pub use jsonl::JsonlStore;
+
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use clap::ValueEnum;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::config::HistoryConfig;
+use crate::core::{ChangeOrigin, ConfidenceLevel, FileEvent, FileEventKind};
+
+/// Version of the record format written by this version
+pub const HISTORY_SCHEMA_VERSION: u32 = 1;
+
+/// One change as kept in the history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    pub timestamp: DateTime<Utc>,
+    /// Relative to the watch root, unless the file is outside it
+    pub path: PathBuf,
+    pub kind: FileEventKind,
+    pub origin: ChangeOrigin,
+    #[serde(default)]
+    pub confidence: Option<ConfidenceLevel>,
+    #[serde(default)]
+    pub confidence_score: Option<f32>,
+    #[serde(default)]
+    pub batch_id: Option<String>,
+    #[serde(default)]
+    pub lines_added: usize,
+    #[serde(default)]
+    pub lines_removed: usize,
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// Ids of the confidence rules that fired
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rule_ids: Vec<String>,
+    /// Only with `history.include_diffs`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+}
+
+impl HistoryRecord {
+    pub fn from_event(event: &FileEvent, root: &Path, include_diff: bool) -> Self {
+        let stats = event.diff_stats();
+        Self {
+            timestamp: DateTime::<Utc>::from(event.timestamp),
+            path: event.path.strip_prefix(root).unwrap_or(&event.path).to_path_buf(),
+            kind: event.kind.clone(),
+            origin: event.origin.clone(),
+            confidence: event.confidence.as_ref().map(|c| c.level.clone()),
+            confidence_score: event.confidence.as_ref().map(|c| c.score),
+            batch_id: event.batch_id.clone(),
+            lines_added: stats.lines_added,
+            lines_removed: stats.lines_removed,
+            content_hash: event.content_hash.clone(),
+            rule_ids: event.rule_ids(),
+            diff: if include_diff { event.diff.clone() } else { None },
+        }
+    }
+}
+
+/// Origin of a change, without the agent or tool name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OriginKind {
+    Human,
+    Ai,
+    Tool,
+    Unknown,
+}
+
+impl OriginKind {
+    pub fn matches(self, origin: &ChangeOrigin) -> bool {
+        matches!(
+            (self, origin),
+            (Self::Human, ChangeOrigin::Human)
+                | (Self::Ai, ChangeOrigin::AIAgent { .. })
+                | (Self::Tool, ChangeOrigin::Tool { .. })
+                | (Self::Unknown, ChangeOrigin::Unknown)
+        )
+    }
+}
+
+/// Which records to return, and which page of them
+#[derive(Debug, Clone)]
+pub struct HistoryQuery {
+    /// Changes at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Changes before this time
+    pub until: Option<DateTime<Utc>>,
+    pub origin: Option<OriginKind>,
+    pub confidence: Option<ConfidenceLevel>,
+    /// Batch ID, or part of one
+    pub batch: Option<String>,
+    /// Records per page
+    pub limit: usize,
+    /// 1-based page number
+    pub page: usize,
+    path_glob: Option<Gitignore>,
+}
+
+impl Default for HistoryQuery {
+    fn default() -> Self {
+        Self {
+            since: None,
+            until: None,
+            origin: None,
+            confidence: None,
+            batch: None,
+            limit: 50,
+            page: 1,
+            path_glob: None,
+        }
+    }
+}
+
+impl HistoryQuery {
+    /// Only paths matching `glob`, in gitignore syntax relative to the watch
+    /// root: `*.rs` matches in every directory, `src/**` only under `src`
+    pub fn with_path_glob(mut self, glob: &str) -> Result<Self> {
+        let mut builder = GitignoreBuilder::new("");
+        builder.add_line(None, glob).with_context(|| format!("Invalid path glob: {}", glob))?;
+        self.path_glob = Some(builder.build().with_context(|| format!("Invalid path glob: {}", glob))?);
+        Ok(self)
+    }
+
+    pub fn matches(&self, record: &HistoryRecord) -> bool {
+        self.since.map_or(true, |since| record.timestamp >= since)
+            && self.until.map_or(true, |until| record.timestamp < until)
+            && self.origin.map_or(true, |origin| origin.matches(&record.origin))
+            && self.confidence.as_ref().map_or(true, |level| record.confidence.as_ref() == Some(level))
+            && self.batch.as_ref().map_or(true, |batch| {
+                record.batch_id.as_ref().is_some_and(|id| id.contains(batch.as_str()))
+            })
+            && self.path_glob.as_ref().map_or(true, |glob| {
+                // Files outside the root were recorded with absolute paths
+                !record.path.has_root()
+                    && glob.matched_path_or_any_parents(&record.path, false).is_ignore()
+            })
+    }
+
+    /// One page of `records`, which must be newest first
+    pub fn paginate(&self, records: Vec<HistoryRecord>) -> HistoryPage {
+        let limit = self.limit.max(1);
+        let total = records.len();
+        let page = self.page.max(1);
+        HistoryPage {
+            total,
+            page,
+            pages: ((total + limit - 1) / limit).max(1),
+            records: records.into_iter().skip((page - 1) * limit).take(limit).collect(),
+        }
+    }
+}
+
+/// A page of query results, newest first
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryPage {
+    /// Matching records on all pages
+    pub total: usize,
+    pub page: usize,
+    pub pages: usize,
+    pub records: Vec<HistoryRecord>,
+}
+
+/// How much history to keep
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Retention {
+    pub max_age: Option<chrono::Duration>,
+    pub max_bytes: Option<u64>,
+}
+
+impl Retention {
+    pub fn from_config(config: &HistoryConfig) -> Self {
+        Self {
+            max_age: config.max_age_days.map(|days| chrono::Duration::days(days as i64)),
+            max_bytes: config.max_bytes,
+        }
+    }
+}
+
+/// A backend for the change history
+pub trait HistoryStore: Send {
+    fn append(&mut self, record: &HistoryRecord) -> Result<()>;
+
+    /// The page of matching records asked for, newest first
+    fn query(&self, query: &HistoryQuery) -> Result<HistoryPage>;
+
+    /// Drop history beyond `retention` as of `now`. Returns the number of
+    /// records dropped.
+    fn prune(&mut self, retention: &Retention, now: DateTime<Utc>) -> Result<usize>;
+}
+
+/// The store directory configured for the watch root
+pub fn store_path(config: &HistoryConfig, root: &Path) -> PathBuf {
+    root.join(&config.path)
+}
+
+/// Open (creating if needed) the configured store
+pub fn open(config: &HistoryConfig, root: &Path) -> Result<Box<dyn HistoryStore>> {
+    let store = JsonlStore::open(&store_path(config, root))?.with_segment_bytes(config.segment_bytes);
+    Ok(Box::new(store))
+}
+
+/// A `--since` or `--until` time: RFC 3339, `YYYY-MM-DD HH:MM[:SS]` in
+/// `tz`, or a `YYYY-MM-DD` day in `tz`. A day starts at midnight, or with
+/// `end_of_day` at the next midnight, so `--until 2024-05-07` includes the 7th.
+pub fn parse_time_bound<Tz: TimeZone>(text: &str, tz: &Tz, end_of_day: bool) -> Result<DateTime<Utc>> {
+    let text = text.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok());
+    let naive = match naive {
+        Some(naive) => naive,
+        None => {
+            let day = NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .with_context(|| format!("Invalid time '{}': expected YYYY-MM-DD, YYYY-MM-DD HH:MM or RFC 3339", text))?;
+            let day = if end_of_day { day.succ_opt().unwrap_or(day) } else { day };
+            day.and_hms_opt(0, 0, 0).unwrap_or_default()
+        }
+    };
+    // In a DST gap the time does not exist; in an overlap take the first one
+    let time = tz
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| anyhow::anyhow!("Time '{}' does not exist in the local time zone", text))?;
+    Ok(time.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    #[test]
+    fn test_time_bounds_follow_the_time_zone() {
+        let berlin_summer = FixedOffset::east_opt(2 * 3600).unwrap();
+        let utc = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+
+        assert_eq!(parse_time_bound("2024-05-07", &berlin_summer, false).unwrap(), utc("2024-05-06T22:00:00Z"));
+        assert_eq!(parse_time_bound("2024-05-07", &berlin_summer, true).unwrap(), utc("2024-05-07T22:00:00Z"));
+        assert_eq!(parse_time_bound("2024-05-07", &Utc, true).unwrap(), utc("2024-05-08T00:00:00Z"));
+        assert_eq!(parse_time_bound("2024-05-07 09:30", &berlin_summer, false).unwrap(), utc("2024-05-07T07:30:00Z"));
+        // An explicit offset wins over the zone
+        assert_eq!(parse_time_bound("2024-05-07T09:30:00-05:00", &berlin_summer, true).unwrap(), utc("2024-05-07T14:30:00Z"));
+        assert!(parse_time_bound("last tuesday", &Utc, false).is_err());
+    }
+}
@@ -13,24 +13,47 @@
 //! - `export`: Export functionality for patches and diffs
 //! - `highlight`: Syntax highlighting support
 //! - `cli`: Command-line interface handling
+//! - `coverage`: Test coverage of changed lines from lcov/istanbul reports
+//! - `manifest`: Dependency changes in Cargo and npm manifests and lockfiles
+//! - `notifications`: Notification sinks, focus mode and quiet hours
+//! - `exec`: Running a user command on each change (`--exec`)
+//! - `trust`: Workspace trust for commands from `.watchdiff.toml`
+//! - `history`: Changes recorded across sessions (`--history-db`)
+//! - `security`: Secret detection on added lines and redaction in output
+//! - `serve`: Read-only queries over a local socket (`--serve`)
+//! - `shutdown`: Draining and closing background writers on exit
+//! - `simulate`: Seeded change workloads for testing (`watchdiff simulate`)
 
 pub mod ai;
+pub mod archive;
 pub mod cli;
 pub mod config;
 pub mod core;
+pub mod coverage;
 pub mod diff;
+pub mod exec;
 pub mod export;
 pub mod highlight;
+pub mod history;
+pub mod manifest;
+pub mod notifications;
 pub mod performance;
+pub mod profile;
 pub mod review;
+pub mod security;
+pub mod serve;
+pub mod shutdown;
+pub mod simulate;
+pub mod trust;
 pub mod ui;
 
 // Re-export commonly used types for backward compatibility
 pub use core::{AppState, FileEvent, FileEventKind, HighlightedFileEvent, FileWatcher, AppEvent};
 pub use core::{ChangeOrigin, ChangeConfidence, ConfidenceLevel};
-pub use core::{ChangeSummary, ChangeSummaryStats, FileSummaryEntry, SummaryFilters, SummaryTimeFrame, SummaryGrouping};
-pub use ai::{AIDetector, ConfidenceScorer};
-pub use config::{WatchDiffConfig, WatcherConfig, CacheConfig, UiConfig, AiConfig};
+pub use core::{ChangeSummary, ChangeSummaryStats, DirRollup, FileSummaryEntry, SummaryFilters, SummaryTimeFrame, SummaryGrouping};
+pub use security::{SecretFinding, SecretKind, SecretScanner};
+pub use ai::{AIDetector, ConfidenceScorer, ScoringContext};
+pub use config::{WatchDiffConfig, WatcherConfig, CacheConfig, UiConfig, AiConfig, BudgetConfig, NotificationConfig, ExecConfig, CoverageConfig, MountConfig, IntentConfig, HistoryConfig, SecurityConfig};
 pub use review::{ReviewSession, ReviewableChange, ReviewAction, ReviewFilters, ReviewNavigationAction, ReviewFilterPreset};
 pub use ui::{TuiApp, setup_terminal, restore_terminal};
 pub use diff::{DiffGenerator, DiffAlgorithmType, DiffFormatter, DiffFormat};
\ No newline at end of file
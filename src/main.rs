@@ -1,13 +1,19 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use watchdiff_tui::{
-    cli::{Cli, OutputFormat},
-    core::{AppEvent, FileWatcher},
-    ui::{restore_terminal, setup_terminal, TuiApp},
+    cli::{Cli, Command, HistoryCommand, HistoryFormat, JsonEventRecord, OutputFormat, ProfileCommand, ReviewCommand, SessionExportFormat, SessionsCommand, WatchMode},
+    config::{self, ExecConfig, WatchBackend, WatchDiffConfig, PROJECT_CONFIG_FILE},
+    core::{integrity, AppEvent, AppState, BranchChange, BudgetAlert, ChangeError, EventJournal, FileWatcher, PathDisplay, PinnedPaths, ProjectInfo, SummaryTimeFrame, TreeSnapshot},
+    exec::ExecRunner,
+    export::{LiveLocations, LocationFormat},
+    profile,
+    shutdown::{lock_writer, SharedWriter, ShutdownCoordinator},
+    trust::{self, ProjectConfig, TrustStore},
+    ui::{draw_shutdown_notice, restore_terminal, setup_terminal, DiffRenderOptions, FileGroups, Icons, Theme, TuiApp, UiState},
 };
 
 fn main() -> Result<()> {
@@ -20,38 +26,661 @@ fn main() -> Result<()> {
 
     cli.setup_logging();
 
+    if let Some(command) = &cli.command {
+        return run_command(command, &cli);
+    }
+
     let watch_path = cli.get_watch_path();
     tracing::info!("Starting WatchDiff on: {}", watch_path.display());
 
+    if let Some(ref socket) = cli.serve {
+        return run_serve_mode(&cli, socket);
+    }
+
     match cli.output {
         OutputFormat::Tui => run_tui_mode(&cli)?,
         OutputFormat::Json => run_json_mode(&cli)?,
         OutputFormat::Text => run_text_mode(&cli)?,
         OutputFormat::Compact => run_compact_mode(&cli)?,
+        OutputFormat::Csv => run_csv_mode(&cli)?,
+        OutputFormat::Patch => run_patch_mode(&cli)?,
     }
 
     Ok(())
 }
 
+fn run_command(command: &Command, cli: &Cli) -> Result<()> {
+    use watchdiff_tui::export::{BundleApplyOptions, ReviewBundle};
+    use watchdiff_tui::ReviewSession;
+
+    match command {
+        Command::ExportBundle { session_id, output } => {
+            let root = cli.get_watch_path();
+            let session = ReviewSession::load_from_disk(&root, session_id)?;
+            let bundle = ReviewBundle::from_session(&session, &root)?;
+            bundle.save(output)?;
+            println!("Exported {} file(s) to {}", bundle.entries.len(), output.display());
+            for entry in &bundle.entries {
+                for warning in &entry.warnings {
+                    eprintln!("Warning: {}: {}", entry.path.display(), warning);
+                }
+            }
+        }
+        Command::ApplyBundle { bundle, root, fuzzy } => {
+            let root = root.clone().unwrap_or_else(|| cli.get_watch_path());
+            let bundle = ReviewBundle::load(bundle)?;
+            let report = bundle.apply(&root, &BundleApplyOptions { fuzzy: *fuzzy })?;
+
+            for path in &report.files_modified {
+                println!("M {}", path.display());
+            }
+            for conflict in &report.conflicts {
+                match &conflict.hunk_id {
+                    Some(hunk_id) => eprintln!("! {} [{}]: {}", conflict.path.display(), hunk_id, conflict.reason),
+                    None => eprintln!("! {}: {}", conflict.path.display(), conflict.reason),
+                }
+            }
+            println!(
+                "{} file(s) modified, {} hunk(s) applied, {} conflict(s)",
+                report.files_modified.len(),
+                report.hunks_applied,
+                report.conflicts.len()
+            );
+
+            if report.has_conflicts() {
+                std::process::exit(1);
+            }
+        }
+        Command::Export { batch, out, events } => {
+            use watchdiff_tui::export::DiffExporter;
+
+            let state = replay_events(events)?;
+            let count = DiffExporter::unified()
+                .with_path_display(export_path_display(cli))
+                .export_batch(&state, batch, out)?;
+            println!("Exported {} change(s) from {} to {}", count, batch, out.display());
+        }
+        Command::ExportPatches { out, events, batch, resume } => {
+            use watchdiff_tui::export::{DiffExporter, PatchBundleOptions};
+
+            let state = replay_events(events)?;
+            let events: Vec<_> = match batch {
+                Some(batch) => state.events_in_batch(batch).into_iter().cloned().collect(),
+                None => state.events.iter().rev().cloned().collect(),
+            };
+            let report = DiffExporter::unified()
+                .with_path_display(export_path_display(cli))
+                .create_patch_bundle_with_options(&events, out, &PatchBundleOptions { resume: *resume })?;
+            println!(
+                "Wrote {} patch(es) to {} ({} kept from an earlier run)",
+                report.written,
+                out.display(),
+                report.skipped
+            );
+        }
+        Command::Locations { format, output, session, events, only } => {
+            use watchdiff_tui::export::LocationList;
+
+            let list = match (session, events) {
+                (Some(session_id), _) => {
+                    let session = ReviewSession::load_from_disk(&cli.get_watch_path(), session_id)?;
+                    LocationList::from_session(&session, *only)
+                }
+                (None, Some(events)) => LocationList::from_state(&replay_events(events)?),
+                (None, None) => anyhow::bail!("Pass --session or --events"),
+            };
+
+            match output {
+                Some(path) => {
+                    list.write(path, *format)?;
+                    println!("Wrote {} location(s) to {}", list.len(), path.display());
+                }
+                None => print!("{}", list.render(*format)?),
+            }
+        }
+        Command::Sessions { action } => run_sessions_command(action, cli)?,
+        Command::Review { action } => run_review_command(action, cli)?,
+        Command::History { action } => run_history_command(action, cli)?,
+        Command::Profile { action } => run_profile_command(action, cli)?,
+        Command::Simulate { scenario, dir, seed, speed, start_delay, agent, log } => {
+            use watchdiff_tui::simulate::{Scenario, Simulator};
+
+            let mut scenario = Scenario::load(scenario)?;
+            if let Some(seed) = seed {
+                scenario = scenario.with_seed(*seed);
+            }
+            if *agent && std::env::var_os(SIMULATE_AGENT_ENV).is_none() {
+                let status = respawn_as_agent(scenario.agent_tool())?;
+                std::process::exit(status.code().unwrap_or(1));
+            }
+
+            let root = match dir {
+                Some(dir) => {
+                    let occupied = dir.exists() && std::fs::read_dir(dir)?.next().is_some();
+                    anyhow::ensure!(!occupied, "{} is not empty", dir.display());
+                    dir.clone()
+                }
+                None => std::env::temp_dir().join(format!("watchdiff-simulate-{}-{}", scenario.name, std::process::id())),
+            };
+
+            let simulator = Simulator::new(scenario);
+            simulator.create_tree(&root)?;
+            eprintln!(
+                "Tree ready at {}; {} mutation(s) start in {}s",
+                root.display(),
+                simulator.mutations().len(),
+                start_delay
+            );
+            std::thread::sleep(Duration::from_secs(*start_delay));
+
+            let events = simulator.run(&root, *speed)?;
+            let mut lines = String::new();
+            for event in &events {
+                lines.push_str(&serde_json::to_string(event)?);
+                lines.push('\n');
+            }
+            match log {
+                Some(path) => {
+                    std::fs::write(path, lines)?;
+                    eprintln!("Wrote {} event(s) to {}", events.len(), path.display());
+                }
+                None => print!("{}", lines),
+            }
+        }
+        Command::Verify { snapshot } => {
+            let root = cli.get_watch_path();
+            let id = match snapshot {
+                Some(id) => id.clone(),
+                None => TreeSnapshot::latest_id(&root)?
+                    .ok_or_else(|| anyhow::anyhow!("No snapshots found; run with --integrity-snapshot first"))?,
+            };
+            let snapshot = TreeSnapshot::load(&root, &id)?;
+            let journal = EventJournal::open(&root, &id)?;
+
+            let cancel = Arc::new(AtomicBool::new(false));
+            let c = cancel.clone();
+            ctrlc::set_handler(move || c.store(true, Ordering::SeqCst))?;
+
+            let report = integrity::verify(&root, &snapshot, &journal, &cancel)?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+
+            if !report.is_clean() {
+                std::process::exit(1);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Set in the re-executed simulator so agent mode doesn't respawn again
+const SIMULATE_AGENT_ENV: &str = "WATCHDIFF_SIMULATE_AGENT";
+
+/// Re-run this command through a link named after `tool`, so the process
+/// name the AI detector sees is the tool's
+#[cfg(unix)]
+fn respawn_as_agent(tool: &str) -> Result<std::process::ExitStatus> {
+    use anyhow::Context;
+
+    let dir = std::env::temp_dir().join(format!("watchdiff-agent-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let link = dir.join(format!("{}-sim", tool));
+    std::os::unix::fs::symlink(std::env::current_exe()?, &link)
+        .with_context(|| format!("Failed to link {}", link.display()))?;
+
+    let status = std::process::Command::new(&link)
+        .args(std::env::args_os().skip(1))
+        .env(SIMULATE_AGENT_ENV, tool)
+        .status();
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(status?)
+}
+
+#[cfg(not(unix))]
+fn respawn_as_agent(_tool: &str) -> Result<std::process::ExitStatus> {
+    anyhow::bail!("--agent is only supported on Unix")
+}
+
+fn run_sessions_command(action: &SessionsCommand, cli: &Cli) -> Result<()> {
+    use watchdiff_tui::export::GithubReview;
+    use watchdiff_tui::review::SessionSummary;
+    use watchdiff_tui::ReviewSession;
+
+    let root = cli.get_watch_path();
+    match action {
+        SessionsCommand::List { search } => {
+            let summaries = SessionSummary::list(&root)?;
+            let summaries = match search {
+                Some(query) => SessionSummary::search(&summaries, query),
+                None => summaries,
+            };
+            for summary in &summaries {
+                let started: chrono::DateTime<chrono::Local> = summary.started_at.into();
+                println!(
+                    "{}  {}  {} change(s)  [{}]",
+                    summary.display_name(),
+                    started.format("%Y-%m-%d %H:%M"),
+                    summary.changes,
+                    summary.id
+                );
+                if let Some(description) = &summary.description {
+                    println!("    {}", description);
+                }
+            }
+        }
+        SessionsCommand::Rename { session_id, name, description } => {
+            ReviewSession::update_saved(&root, session_id, |session| {
+                session.name = Some(name.clone());
+                if let Some(description) = description {
+                    session.description = Some(description.clone());
+                }
+            })?;
+            println!("Renamed {} to {}", session_id, name);
+        }
+        SessionsCommand::Describe { session_id, description } => {
+            let session = ReviewSession::update_saved(&root, session_id, |session| {
+                session.description = Some(description.clone());
+            })?;
+            println!("Updated description of {}", session.display_name());
+        }
+        SessionsCommand::Export { session_id, format, output } => match format {
+            SessionExportFormat::GithubReview => {
+                let review = GithubReview::from_session(&ReviewSession::load_from_disk(&root, session_id)?, &root);
+                let output = output.clone().unwrap_or_else(|| GithubReview::default_path(&root, session_id));
+                review.write(&output)?;
+                println!("Exported {} review comment(s) to {}", review.comments.len(), output.display());
+            }
+            SessionExportFormat::Markdown => export_review_report(&root, session_id, output.as_ref())?,
+        },
+    }
+    Ok(())
+}
+
+fn run_review_command(action: &ReviewCommand, cli: &Cli) -> Result<()> {
+    match action {
+        ReviewCommand::Export { session, out } => export_review_report(&cli.get_watch_path(), session, out.as_ref()),
+    }
+}
+
+/// Write the markdown report of saved session `session_id` to `output`, or
+/// next to the session
+fn export_review_report(root: &std::path::Path, session_id: &str, output: Option<&std::path::PathBuf>) -> Result<()> {
+    use watchdiff_tui::export::ReviewReportExporter;
+    use watchdiff_tui::ReviewSession;
+
+    let session = ReviewSession::load_from_disk(root, session_id)?;
+    let output = output.cloned().unwrap_or_else(|| ReviewReportExporter::default_path(root, session_id));
+    ReviewReportExporter::new(root).export_markdown(&session, &output)?;
+    println!("Exported the review report of {} change(s) to {}", session.changes.len(), output.display());
+    Ok(())
+}
+
+fn run_profile_command(action: &ProfileCommand, cli: &Cli) -> Result<()> {
+    use std::io::IsTerminal;
+    use watchdiff_tui::profile::Profile;
+
+    match action {
+        ProfileCommand::Export { file } => {
+            let macros = UiState::load(&UiState::path(&cli.get_watch_path()))?.macros;
+            Profile::from_config(&watch_config(cli)?)?.with_macros(&macros).save(file)?;
+            println!("Exported profile to {}", file.display());
+        }
+        ProfileCommand::Import { file, force } => {
+            let path = profile::user_config_path()
+                .context("No user config directory: set HOME or XDG_CONFIG_HOME")?;
+            let mut user = profile::load_user_config(&path)?;
+            let interactive = std::io::stdin().is_terminal();
+            let resolve = |key: &str, current: &toml::Value, new: &toml::Value| {
+                *force
+                    || (interactive
+                        && profile::prompt_override(key, current, new, &mut std::io::stdin().lock(), &mut std::io::stderr())
+                            .unwrap_or(false))
+            };
+            let loaded = Profile::load(file)?;
+            let macros = loaded.macros.clone();
+            let mut report = profile::import(loaded, &mut user, resolve)?;
+            profile::save_user_config(&path, &user)?;
+
+            if !macros.is_empty() {
+                let state_path = UiState::path(&cli.get_watch_path());
+                let mut ui_state = UiState::load(&state_path)?;
+                profile::import_macros(&macros, &mut ui_state.macros, resolve, &mut report);
+                ui_state.save(&state_path)?;
+            }
+
+            for key in &report.added {
+                println!("+ {}", key);
+            }
+            for key in &report.overridden {
+                println!("~ {}", key);
+            }
+            for (key, reason) in &report.skipped {
+                println!("  {} (skipped: {})", key, reason);
+            }
+            println!(
+                "{} added, {} overridden, {} skipped in {}",
+                report.added.len(),
+                report.overridden.len(),
+                report.skipped.len(),
+                path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn run_history_command(action: &HistoryCommand, cli: &Cli) -> Result<()> {
+    use watchdiff_tui::history::{self, parse_time_bound, HistoryQuery, Retention};
+
+    let root = cli.get_watch_path();
+    let config = watch_config(cli)?.history;
+    let store_path = history::store_path(&config, &root);
+    if !store_path.is_dir() {
+        anyhow::bail!("No history at {}; record changes with --history-db first", store_path.display());
+    }
+    let mut store = history::open(&config, &root)?;
+
+    match action {
+        HistoryCommand::Query { since, until, path, origin, confidence, batch, utc, format, limit, page } => {
+            let bound = |text: &str, end_of_day: bool| match utc {
+                true => parse_time_bound(text, &chrono::Utc, end_of_day),
+                false => parse_time_bound(text, &chrono::Local, end_of_day),
+            };
+            let mut query = match path {
+                Some(glob) => HistoryQuery::default().with_path_glob(glob)?,
+                None => HistoryQuery::default(),
+            };
+            query.since = since.as_deref().map(|text| bound(text, false)).transpose()?;
+            query.until = until.as_deref().map(|text| bound(text, true)).transpose()?;
+            query.origin = *origin;
+            query.confidence = confidence.clone();
+            query.batch = batch.clone();
+            query.limit = *limit;
+            query.page = *page;
+            let page = store.query(&query)?;
+            match format {
+                HistoryFormat::Json => println!("{}", serde_json::to_string_pretty(&page)?),
+                HistoryFormat::Table => print_history_table(&page, *utc),
+            }
+        }
+        HistoryCommand::Prune => {
+            let dropped = store.prune(&Retention::from_config(&config), chrono::Utc::now())?;
+            println!("Dropped {} change(s) from {}", dropped, store_path.display());
+        }
+    }
+    Ok(())
+}
+
+fn print_history_table(page: &watchdiff_tui::history::HistoryPage, utc: bool) {
+    use watchdiff_tui::{ChangeOrigin, FileEventKind};
+
+    if page.records.is_empty() {
+        println!("No matching changes");
+        return;
+    }
+    println!("{:<19}  {:<8}  {:<16}  {:<6}  {:>9}  PATH", "TIME", "KIND", "ORIGIN", "CONF", "+/-");
+    for record in &page.records {
+        let time = match utc {
+            true => record.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+            false => record.timestamp.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string(),
+        };
+        let kind = match record.kind {
+            FileEventKind::Created => "created",
+            FileEventKind::Modified => "modified",
+            FileEventKind::Deleted => "deleted",
+            FileEventKind::Moved { .. } => "moved",
+        };
+        let origin = match record.origin {
+            ChangeOrigin::Human => "human".to_string(),
+            ChangeOrigin::AIAgent { ref tool_name, .. } => format!("ai:{}", tool_name),
+            ChangeOrigin::Tool { ref name } => format!("tool:{}", name),
+            ChangeOrigin::Unknown => "unknown".to_string(),
+        };
+        let confidence = record.confidence.as_ref().map_or("-".to_string(), |level| format!("{:?}", level).to_lowercase());
+        println!(
+            "{:<19}  {:<8}  {:<16}  {:<6}  {:>9}  {}",
+            time,
+            kind,
+            origin,
+            confidence,
+            format!("+{}/-{}", record.lines_added, record.lines_removed),
+            record.path.display()
+        );
+    }
+    println!("Page {} of {} ({} change(s))", page.page, page.pages, page.total);
+}
+
+/// Configuration from the user config, overridden by the project's
+/// `.watchdiff.toml`, then the environment, then command line overrides
+fn watch_config(cli: &Cli) -> Result<WatchDiffConfig> {
+    let root = cli.get_watch_path();
+    let mut table = match profile::user_config_path() {
+        Some(path) => profile::load_user_config(&path)?,
+        None => toml::Table::new(),
+    };
+    if let Some(project) = ProjectConfig::load(&root)? {
+        config::merge_tables(&mut table, trusted_project_table(project, &root)?);
+    }
+
+    let mut config = WatchDiffConfig::from_table(table)
+        .map_err(|e| anyhow::anyhow!("Invalid config: {}", e))?
+        .with_env();
+    config.validate().map_err(|e| anyhow::anyhow!("Invalid config: {}", e))?;
+    // `auto` is the default, so it leaves the project's choice alone
+    match cli.mode {
+        WatchMode::Auto => {}
+        WatchMode::Native => config.watcher.backend = WatchBackend::Native,
+        WatchMode::Polling => config.watcher.backend = WatchBackend::Polling,
+    }
+    if let Some(ms) = cli.poll_interval {
+        config.watcher.poll_interval_ms = ms;
+    }
+    if cli.expect_activity.is_some() {
+        config.watcher.expect_activity_secs = cli.expect_activity;
+    }
+    if cli.content_hash {
+        config.watcher.content_hash = true;
+    }
+    if cli.strict {
+        config.watcher.strict = true;
+    }
+    if cli.no_gitignore {
+        config.watcher.gitignore = false;
+    }
+    if let Some(depth) = cli.max_depth {
+        config.watcher.max_depth = Some(depth as usize);
+    }
+    config.watcher.include.extend(cli.include.iter().cloned());
+    config.watcher.exclude.extend(cli.exclude.iter().cloned());
+    config.ui.groups.extend(cli.groups.iter().cloned());
+    if cli.git_context {
+        config.watcher.git_context = true;
+    }
+    if cli.reveal_secrets {
+        config.security.reveal = true;
+    }
+    if cli.no_preview {
+        config.watcher.preview = false;
+    }
+    if let Some(workers) = cli.diff_workers {
+        config.watcher.diff_workers = workers as usize;
+    }
+    if cli.coverage.is_some() {
+        config.coverage.report = cli.coverage.clone();
+    }
+    if cli.coverage_strip_prefix.is_some() {
+        config.coverage.strip_prefix = cli.coverage_strip_prefix.clone();
+    }
+    if cli.coverage_path_prefix.is_some() {
+        config.coverage.path_prefix = cli.coverage_path_prefix.clone();
+    }
+    if let Some(ref path) = cli.intent_file {
+        config.intent.path = path.clone();
+    }
+    if let Some(secs) = cli.intent_max_age {
+        config.intent.max_age_secs = secs;
+    }
+    if let Some(ref path) = cli.confidence_rules {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read confidence rules {}", path.display()))?;
+        config.ai.rules = config::rules_from_toml(&text)
+            .map_err(|e| anyhow::anyhow!("Invalid confidence rules in {}: {}", path.display(), e))?;
+        watchdiff_tui::ai::ConfidenceScorer::with_config(&config.ai)
+            .with_context(|| format!("Invalid confidence rules in {}", path.display()))?;
+    }
+    if let Some(ref spec) = cli.quiet_hours {
+        watchdiff_tui::notifications::QuietHours::parse(spec).context("Invalid --quiet-hours")?;
+        config.notifications.quiet_hours = Some(spec.clone());
+    }
+    // Mappings given on the command line win over the project's
+    config.mounts.maps.splice(0..0, cli.maps.iter().cloned());
+    if cli.compose.is_some() {
+        config.mounts.compose = cli.compose.clone();
+    }
+    if let Some(display) = cli.display_paths {
+        config.mounts.display = display;
+    }
+    if let Some(ref path) = cli.history_db {
+        config.history.enabled = true;
+        if let Some(path) = path {
+            config.history.path = path.clone();
+        }
+    }
+    if cli.history_diffs {
+        config.history.include_diffs = true;
+    }
+    Ok(config)
+}
+
+/// The keys set in the project config, keeping its commands only if the user
+/// trusts this exact config for this directory. Asks on the terminal when needed.
+fn trusted_project_table(project: ProjectConfig, root: &std::path::Path) -> Result<toml::Table> {
+    use std::io::IsTerminal;
+
+    let store_path = TrustStore::default_path();
+    let mut store = match store_path {
+        Some(ref path) => TrustStore::load(path)?,
+        None => TrustStore::default(),
+    };
+
+    let mut table = project.table.clone();
+    let resolution = trust::resolve(project, root, &mut store, |project, commands| {
+        if !std::io::stdin().is_terminal() {
+            return None;
+        }
+        trust::prompt(project, commands, &mut std::io::stdin().lock(), &mut std::io::stderr()).ok()
+    });
+    if resolution.asked {
+        if let Some(path) = store_path {
+            store.save(&path)?;
+        }
+    }
+    for command in &resolution.ignored_commands {
+        eprintln!("Not running `{}` from {}: directory is not trusted", command, PROJECT_CONFIG_FILE);
+    }
+    if !resolution.ignored_commands.is_empty() {
+        trust::remove_commands(&mut table);
+    }
+    Ok(table)
+}
+
+/// Which paths exports write: `--display-paths`, else `WATCHDIFF_DISPLAY_PATHS`
+fn export_path_display(cli: &Cli) -> PathDisplay {
+    cli.display_paths.unwrap_or_else(|| WatchDiffConfig::from_env().mounts.display)
+}
+
+/// Rebuild the log from events recorded with `--output json`
+fn replay_events(path: &std::path::Path) -> Result<AppState> {
+    use watchdiff_tui::core::EventReplayer;
+
+    let file = std::fs::File::open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", path.display(), e))?;
+    let mut replayer = EventReplayer::new(&WatchDiffConfig::from_env());
+    replayer.replay_jsonl(std::io::BufReader::new(file))?;
+    Ok(replayer.into_state())
+}
+
 fn run_tui_mode(cli: &Cli) -> Result<()> {
     let watch_path = cli.get_watch_path();
 
     // Create file watcher
-    let watcher = FileWatcher::new(&watch_path)?;
+    let mut config = watch_config(cli)?;
+    // The TUI shows diffs on screen, where a secret is never shown in full
+    config.security.reveal = false;
+    // Keys reach the TUI in raw mode, so there is no signal handler to install
+    let shutdown = ShutdownCoordinator::new(Duration::from_millis(cli.shutdown_grace_ms));
+    let exec = start_exec(cli, &config.exec, &shutdown);
+    let confirm_review_exit = config.ui.confirm_review_exit;
+    let rollup_depth = config.ui.summary_rollup_depth;
+    let show_previews = config.watcher.preview;
+    let diff_render = DiffRenderOptions::from_config(&config.ui);
+    let max_pinned_events = config.watcher.max_pinned_events_per_path;
+    let path_display = config.mounts.display;
+    let file_groups = FileGroups::new(&watch_path, &config.ui.groups)?;
+    let watcher = FileWatcher::with_config(&watch_path, config)?.with_modified_since(cli.since);
+    let journal = start_integrity_snapshot(cli)?;
+    let ui_state_path = UiState::path(&watch_path);
+    let ui_state = UiState::load(&ui_state_path)?;
+    let pins = PinnedPaths::new(watch_path.clone())
+        .with_globs(&cli.pins)?
+        .with_paths(ui_state.pinned);
 
     // Setup terminal
     let mut terminal = setup_terminal()?;
 
     // Create TUI app
-    let app = TuiApp::new(watcher);
+    let mut app = TuiApp::new(watcher)
+        .with_refresh_interval(Duration::from_millis(cli.tui_refresh_ms))
+        .with_min_lines_changed(cli.min_lines_changed)
+        .with_review_exit_confirmation(confirm_review_exit)
+        .with_summary_rollup_depth(rollup_depth)
+        .with_diff_render_options(diff_render)
+        .with_icons(Icons::new(cli.icon_mode()))
+        .with_theme(Theme::new(cli.color_depth()))
+        .with_path_display(path_display)
+        .with_syntax_highlighting(!cli.no_highlight)
+        .with_previews(show_previews)
+        .with_locations_export(locations_export_path(cli), cli.locations_format)
+        .with_pins(pins, max_pinned_events)
+        .with_file_groups(file_groups)
+        .with_muted(cli.muted_paths())
+        .with_ui_state_path(ui_state_path)
+        .with_tour(cli.tour || !ui_state.tour_seen);
+    if let Some(journal) = journal {
+        app = app.with_integrity_journal(watch_path.clone(), journal);
+    }
+    if cli.project_header {
+        app = app.with_project(ProjectInfo::detect(&watch_path));
+    }
+    if let Some(live) = start_live_locations(cli, &shutdown) {
+        app = app.with_live_locations(live);
+    }
+    if let Some(exec) = exec {
+        app = app.with_exec(exec);
+    }
+    if let Some(since) = cli.since {
+        // The summary starts on the same window as the initial scan
+        let window = std::time::SystemTime::now().duration_since(since).unwrap_or_default();
+        app = app.with_summary_time_frame(SummaryTimeFrame::Custom(window));
+    }
 
     // Run the application
     let res = app.run(&mut terminal);
 
+    if shutdown.has_writers() {
+        if let Err(err) = draw_shutdown_notice(&mut terminal) {
+            tracing::warn!("Failed to draw shutdown notice: {}", err);
+        }
+    }
+    let report = shutdown.shutdown();
+
     // Restore terminal
     if let Err(err) = restore_terminal(&mut terminal) {
         eprintln!("Failed to restore terminal: {}", err);
     }
+    if !report.is_clean() {
+        eprintln!("{}", report);
+    }
 
     if let Err(err) = res {
         eprintln!("Application error: {}", err);
@@ -63,21 +692,36 @@ fn run_tui_mode(cli: &Cli) -> Result<()> {
 
 fn run_json_mode(cli: &Cli) -> Result<()> {
     let watch_path = cli.get_watch_path();
-    let watcher = FileWatcher::new(&watch_path)?;
-
-    let running = Arc::new(AtomicBool::new(true));
-    let r = running.clone();
-    ctrlc::set_handler(move || {
-        r.store(false, Ordering::SeqCst);
-    })?;
+    let config = watch_config(cli)?;
+    let shutdown = start_shutdown(cli)?;
+    let exec = start_exec(cli, &config.exec, &shutdown);
+    let watcher = FileWatcher::with_config(&watch_path, config)?;
+    report_polling(&watcher);
+    let mut journal = start_integrity_snapshot(cli)?;
+    let live = start_live_locations(cli, &shutdown);
+    // Event paths are absolute, relative_path is against the watch root
+    let root = std::env::current_dir().map_or_else(|_| watch_path.clone(), |cwd| cwd.join(&watch_path));
 
-    while running.load(Ordering::SeqCst) {
+    while !shutdown.is_stopping() {
+        flush_live_locations(live.as_ref());
+        poll_exec(exec.as_ref(), cli);
         match watcher.recv_timeout(Duration::from_millis(100)) {
             Ok(AppEvent::FileChanged(event)) => {
-                if should_include_file(&event.path, cli) {
-                    println!("{}", serde_json::to_string(&event)?);
+                record_event(journal.as_mut(), &event);
+                if should_include_event(&event, cli) {
+                    record_live_location(live.as_ref(), &event);
+                    record_exec(exec.as_ref(), &event);
+                    let record = JsonEventRecord::new(&event, &root, cli.stats_only)?;
+                    println!("{}", serde_json::to_string(&record)?);
                 }
             }
+            Ok(AppEvent::BudgetExceeded(alert)) => {
+                println!("{}", serde_json::json!({ "budget_alert": alert }));
+            }
+            Ok(AppEvent::BranchChanged(change)) => {
+                println!("{}", serde_json::json!({ "branch_change": change }));
+            }
+            Ok(AppEvent::Error(error)) => println!("{}", serde_json::json!({ "error": error })),
             Ok(AppEvent::Quit) => break,
             Ok(_) => continue, // Ignore other events
             Err(std::sync::mpsc::RecvTimeoutError::Timeout) => continue,
@@ -85,30 +729,47 @@ fn run_json_mode(cli: &Cli) -> Result<()> {
         }
     }
 
+    finish_shutdown(&shutdown);
+
     Ok(())
 }
 
 fn run_text_mode(cli: &Cli) -> Result<()> {
     let watch_path = cli.get_watch_path();
-    let watcher = FileWatcher::new(&watch_path)?;
+    let config = watch_config(cli)?;
+    let shutdown = start_shutdown(cli)?;
+    let exec = start_exec(cli, &config.exec, &shutdown);
+    let path_display = config.mounts.display;
+    let watcher = FileWatcher::with_config(&watch_path, config)?;
+    report_polling(&watcher);
+    let mut journal = start_integrity_snapshot(cli)?;
+    let live = start_live_locations(cli, &shutdown);
 
     println!("Watching: {}", watch_path.display());
+    if cli.project_header {
+        println!("Project: {}", ProjectInfo::detect(&watch_path).name);
+    }
+    if let Some(git) = watcher.git_context() {
+        println!("On branch: {}", git.describe());
+    }
     println!("Press Ctrl+C to quit");
     println!("---");
 
-    let running = Arc::new(AtomicBool::new(true));
-    let r = running.clone();
-    ctrlc::set_handler(move || {
-        r.store(false, Ordering::SeqCst);
-    })?;
-
-    while running.load(Ordering::SeqCst) {
+    while !shutdown.is_stopping() {
+        flush_live_locations(live.as_ref());
+        poll_exec(exec.as_ref(), cli);
         match watcher.recv_timeout(Duration::from_millis(100)) {
             Ok(AppEvent::FileChanged(event)) => {
-                if should_include_file(&event.path, cli) {
-                    print_text_event(&event, cli);
+                record_event(journal.as_mut(), &event);
+                if should_include_event(&event, cli) {
+                    record_live_location(live.as_ref(), &event);
+                    record_exec(exec.as_ref(), &event);
+                    print_text_event(&event, cli, path_display);
                 }
             }
+            Ok(AppEvent::BudgetExceeded(alert)) => print_budget_alert(&alert, cli),
+            Ok(AppEvent::BranchChanged(change)) => print_branch_change(&change, cli),
+            Ok(AppEvent::Error(error)) => print_change_error(&error, cli),
             Ok(AppEvent::Quit) => break,
             Ok(_) => continue, // Ignore other events
             Err(std::sync::mpsc::RecvTimeoutError::Timeout) => continue,
@@ -116,26 +777,78 @@ fn run_text_mode(cli: &Cli) -> Result<()> {
         }
     }
 
+    finish_shutdown(&shutdown);
+
     Ok(())
 }
 
 fn run_compact_mode(cli: &Cli) -> Result<()> {
     let watch_path = cli.get_watch_path();
-    let watcher = FileWatcher::new(&watch_path)?;
+    let config = watch_config(cli)?;
+    let shutdown = start_shutdown(cli)?;
+    let exec = start_exec(cli, &config.exec, &shutdown);
+    let path_display = config.mounts.display;
+    let watcher = FileWatcher::with_config(&watch_path, config)?;
+    report_polling(&watcher);
+    let mut journal = start_integrity_snapshot(cli)?;
+    let live = start_live_locations(cli, &shutdown);
+
+    while !shutdown.is_stopping() {
+        flush_live_locations(live.as_ref());
+        poll_exec(exec.as_ref(), cli);
+        match watcher.recv_timeout(Duration::from_millis(100)) {
+            Ok(AppEvent::FileChanged(event)) => {
+                record_event(journal.as_mut(), &event);
+                if should_include_event(&event, cli) {
+                    record_live_location(live.as_ref(), &event);
+                    record_exec(exec.as_ref(), &event);
+                    print_compact_event(&event, path_display);
+                }
+            }
+            Ok(AppEvent::BudgetExceeded(alert)) => println!("! {}", alert.message()),
+            Ok(AppEvent::BranchChanged(change)) => println!("! {}", change.message()),
+            Ok(AppEvent::Error(error)) => println!("! {}", error),
+            Ok(AppEvent::Quit) => break,
+            Ok(_) => continue, // Ignore other events
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
 
-    let running = Arc::new(AtomicBool::new(true));
-    let r = running.clone();
-    ctrlc::set_handler(move || {
-        r.store(false, Ordering::SeqCst);
-    })?;
+    finish_shutdown(&shutdown);
+
+    Ok(())
+}
+
+fn run_csv_mode(cli: &Cli) -> Result<()> {
+    use watchdiff_tui::export::CsvEventWriter;
+
+    let watch_path = cli.get_watch_path();
+    let config = watch_config(cli)?;
+    let shutdown = start_shutdown(cli)?;
+    let exec = start_exec(cli, &config.exec, &shutdown);
+    let watcher = FileWatcher::with_config(&watch_path, config)?;
+    report_polling(&watcher);
+    let mut journal = start_integrity_snapshot(cli)?;
+    let live = start_live_locations(cli, &shutdown);
+    let mut writer = CsvEventWriter::new(std::io::stdout())?;
 
-    while running.load(Ordering::SeqCst) {
+    while !shutdown.is_stopping() {
+        flush_live_locations(live.as_ref());
+        poll_exec(exec.as_ref(), cli);
         match watcher.recv_timeout(Duration::from_millis(100)) {
             Ok(AppEvent::FileChanged(event)) => {
-                if should_include_file(&event.path, cli) {
-                    print_compact_event(&event);
+                record_event(journal.as_mut(), &event);
+                if should_include_event(&event, cli) {
+                    record_live_location(live.as_ref(), &event);
+                    record_exec(exec.as_ref(), &event);
+                    writer.write_event(&event)?;
                 }
             }
+            // Keep stdout a valid CSV stream
+            Ok(AppEvent::BudgetExceeded(alert)) => eprintln!("Budget exceeded: {}", alert.message()),
+            Ok(AppEvent::BranchChanged(change)) => eprintln!("{}", change.message()),
+            Ok(AppEvent::Error(error)) => eprintln!("Error: {}", error),
             Ok(AppEvent::Quit) => break,
             Ok(_) => continue, // Ignore other events
             Err(std::sync::mpsc::RecvTimeoutError::Timeout) => continue,
@@ -143,28 +856,270 @@ fn run_compact_mode(cli: &Cli) -> Result<()> {
         }
     }
 
+    finish_shutdown(&shutdown);
+
     Ok(())
 }
 
-fn should_include_file(path: &std::path::Path, cli: &Cli) -> bool {
-    cli.should_watch_extension(path)
+fn run_patch_mode(cli: &Cli) -> Result<()> {
+    use watchdiff_tui::export::{PatchEventWriter, PatchOutcome};
+
+    let watch_path = cli.get_watch_path();
+    let config = watch_config(cli)?;
+    let shutdown = start_shutdown(cli)?;
+    let exec = start_exec(cli, &config.exec, &shutdown);
+    let watcher = FileWatcher::with_config(&watch_path, config)?;
+    report_polling(&watcher);
+    let mut journal = start_integrity_snapshot(cli)?;
+    let live = start_live_locations(cli, &shutdown);
+    // Event paths are absolute, patch paths relative to the watch root
+    let root = std::env::current_dir().map_or_else(|_| watch_path.clone(), |cwd| cwd.join(&watch_path));
+    let mut writer = PatchEventWriter::new(std::io::stdout(), root).with_algorithm(cli.algorithm);
+
+    while !shutdown.is_stopping() {
+        flush_live_locations(live.as_ref());
+        poll_exec(exec.as_ref(), cli);
+        match watcher.recv_timeout(Duration::from_millis(100)) {
+            Ok(AppEvent::FileChanged(event)) => {
+                record_event(journal.as_mut(), &event);
+                writer.seed(&event, |path| watcher.baseline(path));
+                if !should_include_event(&event, cli) {
+                    // Later patches of the file still apply on top of this change
+                    writer.track(&event);
+                    continue;
+                }
+                record_live_location(live.as_ref(), &event);
+                record_exec(exec.as_ref(), &event);
+                if let PatchOutcome::Skipped(reason) = writer.write_event(&event)? {
+                    eprintln!("No patch for {}: {}", event.path.display(), reason);
+                }
+            }
+            // Keep stdout a valid patch
+            Ok(AppEvent::BudgetExceeded(alert)) => eprintln!("Budget exceeded: {}", alert.message()),
+            Ok(AppEvent::BranchChanged(change)) => eprintln!("{}", change.message()),
+            Ok(AppEvent::Error(error)) => eprintln!("Error: {}", error),
+            Ok(AppEvent::Quit) => break,
+            Ok(_) => continue, // Ignore other events
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    finish_shutdown(&shutdown);
+
+    Ok(())
 }
 
-fn print_text_event(event: &watchdiff_tui::FileEvent, cli: &Cli) {
-    use watchdiff_tui::FileEventKind;
+#[cfg(unix)]
+fn run_serve_mode(cli: &Cli, socket: &std::path::Path) -> Result<()> {
+    use std::sync::Mutex;
+    use watchdiff_tui::serve::DiffServer;
+
+    let watch_path = cli.get_watch_path();
+    let config = watch_config(cli)?;
+    let state = Arc::new(Mutex::new(AppState::with_config(&config).with_highlighting(!cli.no_highlight)));
+    let shutdown = start_shutdown(cli)?;
+    let exec = start_exec(cli, &config.exec, &shutdown);
+    let watcher = FileWatcher::with_config(&watch_path, config)?;
+    report_polling(&watcher);
+    let mut journal = start_integrity_snapshot(cli)?;
+    let live = start_live_locations(cli, &shutdown);
+    // Event paths are absolute, request paths may be relative to the watch root
+    let root = std::env::current_dir().map_or_else(|_| watch_path.clone(), |cwd| cwd.join(&watch_path));
+    let server = DiffServer::bind(socket)?;
+    server.spawn(Arc::clone(&state), root)?;
+    eprintln!("Serving changes in {} on {}", watch_path.display(), server.path().display());
+    eprintln!("Press Ctrl+C to quit");
+
+    while !shutdown.is_stopping() {
+        flush_live_locations(live.as_ref());
+        poll_exec(exec.as_ref(), cli);
+        match watcher.recv_timeout(Duration::from_millis(100)) {
+            Ok(AppEvent::FileChanged(event)) => {
+                record_event(journal.as_mut(), &event);
+                if should_include_event(&event, cli) {
+                    record_live_location(live.as_ref(), &event);
+                    record_exec(exec.as_ref(), &event);
+                    state.lock().unwrap_or_else(|e| e.into_inner()).add_event(event);
+                }
+            }
+            Ok(AppEvent::BudgetExceeded(alert)) => eprintln!("Budget exceeded: {}", alert.message()),
+            Ok(AppEvent::BranchChanged(change)) => eprintln!("{}", change.message()),
+            Ok(AppEvent::Error(error)) => eprintln!("Error: {}", error),
+            Ok(AppEvent::Quit) => break,
+            Ok(_) => continue, // Ignore other events
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
 
-    let timestamp = event
-        .timestamp
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs();
+    finish_shutdown(&shutdown);
 
-    let time_str = format!(
-        "{:02}:{:02}:{:02}",
-        (timestamp % 86400) / 3600,
-        (timestamp % 3600) / 60,
-        timestamp % 60
-    );
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn run_serve_mode(_cli: &Cli, _socket: &std::path::Path) -> Result<()> {
+    anyhow::bail!("--serve needs Unix domain sockets, which this platform does not have")
+}
+
+/// The shutdown coordinator of a non-TUI run, stopping on Ctrl+C
+fn start_shutdown(cli: &Cli) -> Result<ShutdownCoordinator> {
+    let shutdown = ShutdownCoordinator::new(Duration::from_millis(cli.shutdown_grace_ms));
+    shutdown.install_signal_handler()?;
+    Ok(shutdown)
+}
+
+/// Drain and close the writers, reporting on stderr what was left behind
+fn finish_shutdown(shutdown: &ShutdownCoordinator) {
+    let report = shutdown.shutdown();
+    if report.is_clean() {
+        tracing::debug!("{}", report);
+    } else {
+        eprintln!("{}", report);
+    }
+}
+
+/// Note on stderr that changes are being polled for, as they show up late
+fn report_polling(watcher: &FileWatcher) {
+    if watcher.poll_interval().is_none() {
+        return;
+    }
+    match watcher.fallback_reason() {
+        Some(reason) => eprintln!(
+            "Native file events unavailable ({}); {}, so changes may show up late",
+            reason,
+            watcher.backend_description()
+        ),
+        None => eprintln!("Watching by {}; changes may show up late", watcher.backend_description()),
+    }
+}
+
+/// With --integrity-snapshot, hash the tree in the background and return the
+/// journal that events of this run are recorded in
+fn start_integrity_snapshot(cli: &Cli) -> Result<Option<EventJournal>> {
+    if !cli.integrity_snapshot {
+        return Ok(None);
+    }
+
+    let root = cli.get_watch_path();
+    let id = format!("startup_{}", chrono::Utc::now().timestamp_millis());
+    let journal = EventJournal::open(&root, &id)?;
+
+    std::thread::spawn(move || {
+        let result = TreeSnapshot::capture(&root, id, &AtomicBool::new(false))
+            .and_then(|snapshot| snapshot.save(&root));
+        if let Err(err) = result {
+            tracing::warn!("Failed to capture integrity snapshot: {}", err);
+        }
+    });
+
+    Ok(Some(journal))
+}
+
+fn record_event(journal: Option<&mut EventJournal>, event: &watchdiff_tui::FileEvent) {
+    if let Some(journal) = journal {
+        if let Err(err) = journal.record(event) {
+            tracing::warn!("Failed to record event for integrity check: {}", err);
+        }
+    }
+}
+
+/// With --locations-live, the location file kept up to date by this run
+fn start_live_locations(cli: &Cli, shutdown: &ShutdownCoordinator) -> Option<SharedWriter<LiveLocations>> {
+    cli.locations_live.clone().map(|path| {
+        let name = format!("location file {}", path.display());
+        shutdown.register(name, LiveLocations::new(path, cli.locations_format, cli.max_events))
+    })
+}
+
+/// File the TUI `x` key writes to: the live file if there is one
+fn locations_export_path(cli: &Cli) -> std::path::PathBuf {
+    cli.locations_live.clone().unwrap_or_else(|| match cli.locations_format {
+        LocationFormat::Quickfix => "changes.qf".into(),
+        LocationFormat::Json => "changes.json".into(),
+    })
+}
+
+fn record_live_location(live: Option<&SharedWriter<LiveLocations>>, event: &watchdiff_tui::FileEvent) {
+    if let Some(live) = live {
+        lock_writer(live).record(event, Instant::now());
+    }
+}
+
+/// Rewrite the live location file once events settle; the last rewrite
+/// happens on shutdown
+fn flush_live_locations(live: Option<&SharedWriter<LiveLocations>>) {
+    if let Some(live) = live {
+        let mut live = lock_writer(live);
+        if let Err(err) = live.flush_if_due(Instant::now()) {
+            tracing::warn!("Failed to update {}: {}", live.path().display(), err);
+        }
+    }
+}
+
+/// The runner for --exec, or else for a trusted project config's command.
+/// Its output is passed through only where it cannot corrupt the TUI or
+/// machine-readable output.
+fn start_exec(cli: &Cli, config: &ExecConfig, shutdown: &ShutdownCoordinator) -> Option<SharedWriter<ExecRunner>> {
+    let (template, restart, debounce_ms) = match cli.exec {
+        Some(ref template) => (template.clone(), cli.restart, cli.exec_debounce_ms),
+        None => (config.command.clone()?, config.restart || cli.restart, config.debounce_ms),
+    };
+    let runner = ExecRunner::new(template)
+        .with_restart(restart)
+        .with_debounce(Duration::from_millis(debounce_ms))
+        .with_inherited_output(matches!(cli.output, OutputFormat::Text | OutputFormat::Compact));
+    Some(shutdown.register("--exec command", runner))
+}
+
+fn record_exec(exec: Option<&SharedWriter<ExecRunner>>, event: &watchdiff_tui::FileEvent) {
+    if let Some(exec) = exec {
+        lock_writer(exec).record(event, Instant::now());
+    }
+}
+
+/// Start the --exec command when due and report runs that finished
+fn poll_exec(exec: Option<&SharedWriter<ExecRunner>>, cli: &Cli) {
+    let Some(exec) = exec else { return };
+    let polled = lock_writer(exec).poll(Instant::now());
+    let finished = match polled {
+        Ok(finished) => finished,
+        Err(err) => {
+            tracing::warn!("Failed to run --exec command: {}", err);
+            return;
+        }
+    };
+
+    for status in finished {
+        match cli.output {
+            OutputFormat::Json => println!(
+                "{}",
+                serde_json::json!({
+                    "type": "exec_status",
+                    "command": status.command,
+                    "code": status.code,
+                    "success": status.success,
+                    "duration_ms": status.duration.as_millis() as u64,
+                })
+            ),
+            OutputFormat::Csv | OutputFormat::Patch => eprintln!("[exec] {}", status),
+            _ if cli.no_color || status.success => println!("[exec] {}", status),
+            _ => println!("\x1b[31m[exec] {}\x1b[0m", status),
+        }
+    }
+}
+
+fn should_include_event(event: &watchdiff_tui::FileEvent, cli: &Cli) -> bool {
+    cli.should_watch_extension(&event.path)
+        && event.meets_line_threshold(cli.min_lines_changed)
+        && !cli.muted_paths().contains(&event.path)
+}
+
+fn print_text_event(event: &watchdiff_tui::FileEvent, cli: &Cli, path_display: PathDisplay) {
+    use watchdiff_tui::FileEventKind;
+
+    let time_str = watchdiff_tui::core::clock::format_time_of_day(event.timestamp);
 
     let event_type = match &event.kind {
         FileEventKind::Created => "CREATED",
@@ -173,8 +1128,9 @@ fn print_text_event(event: &watchdiff_tui::FileEvent, cli: &Cli) {
         FileEventKind::Moved { .. } => "MOVED",
     };
 
+    let hash = event.content_hash.as_ref().map(|hash| format!(" #{}", hash)).unwrap_or_default();
     if cli.no_color {
-        println!("[{}] {} {}", time_str, event_type, event.path.display());
+        println!("[{}] {} {}{}", time_str, event_type, event.display_path(path_display), hash);
     } else {
         let color = match &event.kind {
             FileEventKind::Created => "\x1b[32m",      // Green
@@ -183,14 +1139,28 @@ fn print_text_event(event: &watchdiff_tui::FileEvent, cli: &Cli) {
             FileEventKind::Moved { .. } => "\x1b[34m", // Blue
         };
         println!(
-            "[{}] {}{}\x1b[0m {}",
+            "[{}] {}{}\x1b[0m {}\x1b[2m{}\x1b[0m",
             time_str,
             color,
             event_type,
-            event.path.display()
+            event.display_path(path_display),
+            hash
         );
     }
 
+    if let Some(ref intent) = event.intent {
+        println!("  intent: {}", intent);
+    } else if event.undeclared {
+        println!("  ! undeclared change: not listed in the agent's intent file");
+    }
+
+    if let (FileEventKind::Created, None, Some(preview)) = (&event.kind, &event.diff, &event.content_preview) {
+        println!("  NEW FILE");
+        for line in preview.lines().take(10) {
+            println!("  {}", line);
+        }
+    }
+
     if let Some(diff) = &event.diff {
         for line in diff.lines().take(10) {
             if cli.no_color {
@@ -208,7 +1178,33 @@ fn print_text_event(event: &watchdiff_tui::FileEvent, cli: &Cli) {
     println!();
 }
 
-fn print_compact_event(event: &watchdiff_tui::FileEvent) {
+fn print_budget_alert(alert: &BudgetAlert, cli: &Cli) {
+    if cli.no_color {
+        println!("[BUDGET] {}", alert.message());
+    } else {
+        println!("\x1b[1;41m BUDGET \x1b[0m \x1b[31m{}\x1b[0m", alert.message());
+    }
+    println!();
+}
+
+fn print_change_error(error: &ChangeError, cli: &Cli) {
+    if cli.no_color {
+        eprintln!("[ERROR] {}", error);
+    } else {
+        eprintln!("\x1b[1;41m ERROR \x1b[0m \x1b[31m{}\x1b[0m", error);
+    }
+}
+
+fn print_branch_change(change: &BranchChange, cli: &Cli) {
+    if cli.no_color {
+        println!("[GIT] {}", change.message());
+    } else {
+        println!("\x1b[1;44m GIT \x1b[0m \x1b[34m{}\x1b[0m", change.message());
+    }
+    println!();
+}
+
+fn print_compact_event(event: &watchdiff_tui::FileEvent, path_display: PathDisplay) {
     use watchdiff_tui::FileEventKind;
 
     let event_type = match &event.kind {
@@ -218,5 +1214,5 @@ fn print_compact_event(event: &watchdiff_tui::FileEvent) {
         FileEventKind::Moved { .. } => "V",
     };
 
-    println!("{} {}", event_type, event.path.display());
+    println!("{} {}", event_type, event.display_path(path_display));
 }
@@ -0,0 +1,528 @@
+//! What a change to a dependency manifest means, beyond its line diff
+//!
+//! `Cargo.toml` and `package.json` are parsed before and after the change
+//! and compared: dependencies added, removed, bumped or moved to another
+//! source, Cargo features and npm scripts changed. `Cargo.lock` and
+//! `package-lock.json` get a count of the packages that changed. A manifest
+//! that does not parse on either side gets no analysis, so it shows as a
+//! plain diff.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ManifestKind {
+    Cargo,
+    CargoLock,
+    Npm,
+    NpmLock,
+}
+
+impl ManifestKind {
+    /// The kind of manifest `path` is, from its file name
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.file_name()?.to_str()? {
+            "Cargo.toml" => Some(Self::Cargo),
+            "Cargo.lock" => Some(Self::CargoLock),
+            "package.json" => Some(Self::Npm),
+            "package-lock.json" => Some(Self::NpmLock),
+            _ => None,
+        }
+    }
+}
+
+/// Where a dependency comes from when it is not a registry version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DependencySource {
+    Git(String),
+    Path(String),
+    Url(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DependencySpec {
+    /// Version requirement, `workspace` for Cargo workspace dependencies
+    pub version: Option<String>,
+    pub source: Option<DependencySource>,
+    /// Cargo features enabled on the dependency
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+}
+
+/// A dependency added (`old` is `None`), removed (`new` is `None`) or changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DependencyChange {
+    /// Table the dependency is declared in, e.g. `dev-dependencies` or `devDependencies`
+    pub section: String,
+    pub name: String,
+    pub old: Option<DependencySpec>,
+    pub new: Option<DependencySpec>,
+}
+
+impl DependencyChange {
+    /// The git URL the dependency is now fetched from, if that is new
+    pub fn new_git_source(&self) -> Option<&str> {
+        let new = match self.new.as_ref()?.source {
+            Some(DependencySource::Git(ref url)) => url,
+            _ => return None,
+        };
+        let old = self.old.as_ref().and_then(|old| old.source.as_ref());
+        (old != Some(&DependencySource::Git(new.clone()))).then_some(new.as_str())
+    }
+}
+
+/// A named entry, such as a Cargo feature or an npm script, added, removed or changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryChange {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// Packages of a lockfile whose resolved versions changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockfileChange {
+    pub added: usize,
+    pub removed: usize,
+    pub updated: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestChange {
+    pub kind: ManifestKind,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<DependencyChange>,
+    /// Cargo `[features]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<EntryChange>,
+    /// npm `scripts`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scripts: Vec<EntryChange>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lockfile: Option<LockfileChange>,
+}
+
+impl ManifestChange {
+    /// Compare the manifest at `path` before and after a change. `None` if
+    /// `path` is not a manifest, either side does not parse, or nothing
+    /// the analysis looks at changed.
+    pub fn analyze(path: &Path, old: &str, new: &str) -> Option<Self> {
+        let kind = ManifestKind::from_path(path)?;
+        let mut change = Self {
+            kind,
+            dependencies: Vec::new(),
+            features: Vec::new(),
+            scripts: Vec::new(),
+            lockfile: None,
+        };
+
+        match kind {
+            ManifestKind::Cargo => {
+                let old = old.parse::<toml::Table>().ok()?;
+                let new = new.parse::<toml::Table>().ok()?;
+                change.dependencies = diff_dependencies(&cargo_dependencies(&old), &cargo_dependencies(&new));
+                change.features = diff_entries(&cargo_features(&old), &cargo_features(&new));
+            }
+            ManifestKind::Npm => {
+                let old = serde_json::from_str::<serde_json::Value>(old).ok()?;
+                let new = serde_json::from_str::<serde_json::Value>(new).ok()?;
+                change.dependencies = diff_dependencies(&npm_dependencies(&old), &npm_dependencies(&new));
+                change.scripts = diff_entries(&npm_scripts(&old), &npm_scripts(&new));
+            }
+            ManifestKind::CargoLock => {
+                let old = cargo_lock_packages(&old.parse::<toml::Table>().ok()?);
+                let new = cargo_lock_packages(&new.parse::<toml::Table>().ok()?);
+                change.lockfile = diff_lockfile(&old, &new);
+            }
+            ManifestKind::NpmLock => {
+                let old = npm_lock_packages(&serde_json::from_str(old).ok()?);
+                let new = npm_lock_packages(&serde_json::from_str(new).ok()?);
+                change.lockfile = diff_lockfile(&old, &new);
+            }
+        }
+
+        let empty = change.dependencies.is_empty()
+            && change.features.is_empty()
+            && change.scripts.is_empty()
+            && change.lockfile.is_none();
+        (!empty).then_some(change)
+    }
+
+    /// One line per change, starting with `+`, `-` or `~` like a diff
+    pub fn summary_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for dep in &self.dependencies {
+            match (&dep.old, &dep.new) {
+                (None, Some(new)) => lines.push(format!("+ {} {} [{}]", dep.name, describe_spec(new), dep.section)),
+                (Some(old), None) => lines.push(format!("- {} {} [{}]", dep.name, describe_spec(old), dep.section)),
+                (Some(old), Some(new)) => {
+                    if old.version != new.version || old.source != new.source {
+                        lines.push(format!("~ {} {} → {} [{}]", dep.name, describe_spec(old), describe_spec(new), dep.section));
+                    }
+                    if old.features != new.features {
+                        let toggled: Vec<String> = new.features.iter()
+                            .filter(|f| !old.features.contains(f))
+                            .map(|f| format!("+{}", f))
+                            .chain(old.features.iter().filter(|f| !new.features.contains(f)).map(|f| format!("-{}", f)))
+                            .collect();
+                        lines.push(format!("~ {} features {} [{}]", dep.name, toggled.join(" "), dep.section));
+                    }
+                }
+                (None, None) => {}
+            }
+        }
+        for (label, entries) in [("feature", &self.features), ("script", &self.scripts)] {
+            for entry in entries {
+                lines.push(match (&entry.old, &entry.new) {
+                    (None, Some(new)) => format!("+ {} {} = {}", label, entry.name, new),
+                    (Some(_), None) => format!("- {} {}", label, entry.name),
+                    (old, new) => format!(
+                        "~ {} {}: {} → {}",
+                        label,
+                        entry.name,
+                        old.as_deref().unwrap_or(""),
+                        new.as_deref().unwrap_or(""),
+                    ),
+                });
+            }
+        }
+        if let Some(lock) = self.lockfile {
+            let counts: Vec<String> = [(lock.updated, "updated"), (lock.added, "added"), (lock.removed, "removed")]
+                .into_iter()
+                .filter(|(count, _)| *count > 0)
+                .map(|(count, what)| format!("{} {}", count, what))
+                .collect();
+            let noun = if lock.updated + lock.added + lock.removed == 1 { "package" } else { "packages" };
+            lines.push(format!("~ {} {}", noun, counts.join(", ")));
+        }
+        lines
+    }
+}
+
+/// Host a git dependency URL points at: `github.com` for
+/// `https://github.com/a/b`, `git@github.com:a/b` or npm's `github:a/b`
+pub fn git_host(url: &str) -> Option<String> {
+    let url = url.strip_prefix("git+").unwrap_or(url);
+    for (shorthand, host) in [("github:", "github.com"), ("gitlab:", "gitlab.com"), ("bitbucket:", "bitbucket.org")] {
+        if url.starts_with(shorthand) {
+            return Some(host.to_string());
+        }
+    }
+    let rest = match url.split_once("://") {
+        Some((_, rest)) => rest,
+        // scp-like syntax: git@host:path
+        None => url.split_once(':')?.0,
+    };
+    let authority = rest.split('/').next()?;
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+    (!host.is_empty()).then(|| host.to_lowercase())
+}
+
+fn describe_spec(spec: &DependencySpec) -> String {
+    let source = match spec.source {
+        Some(DependencySource::Git(ref url)) => Some(format!("git {}", url)),
+        Some(DependencySource::Path(ref path)) => Some(format!("path {}", path)),
+        Some(DependencySource::Url(ref url)) => Some(url.clone()),
+        None => None,
+    };
+    match (spec.version.as_deref(), source) {
+        (Some(version), Some(source)) => format!("{} ({})", version, source),
+        (Some(version), None) => version.to_string(),
+        (None, Some(source)) => source,
+        (None, None) => "*".to_string(),
+    }
+}
+
+type Dependencies = BTreeMap<(String, String), DependencySpec>;
+
+fn diff_dependencies(old: &Dependencies, new: &Dependencies) -> Vec<DependencyChange> {
+    let keys: BTreeSet<&(String, String)> = old.keys().chain(new.keys()).collect();
+    keys.into_iter()
+        .filter(|key| old.get(*key) != new.get(*key))
+        .map(|(section, name)| DependencyChange {
+            section: section.clone(),
+            name: name.clone(),
+            old: old.get(&(section.clone(), name.clone())).cloned(),
+            new: new.get(&(section.clone(), name.clone())).cloned(),
+        })
+        .collect()
+}
+
+fn diff_entries(old: &BTreeMap<String, String>, new: &BTreeMap<String, String>) -> Vec<EntryChange> {
+    let names: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    names.into_iter()
+        .filter(|name| old.get(*name) != new.get(*name))
+        .map(|name| EntryChange {
+            name: name.clone(),
+            old: old.get(name).cloned(),
+            new: new.get(name).cloned(),
+        })
+        .collect()
+}
+
+const CARGO_DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+fn cargo_dependencies(manifest: &toml::Table) -> Dependencies {
+    let mut deps = Dependencies::new();
+    let mut add_tables = |table: &toml::Table, prefix: &str| {
+        for section in CARGO_DEPENDENCY_TABLES {
+            let Some(entries) = table.get(section).and_then(toml::Value::as_table) else { continue };
+            for (name, value) in entries {
+                deps.insert((format!("{}{}", prefix, section), name.clone()), cargo_spec(value));
+            }
+        }
+    };
+
+    add_tables(manifest, "");
+    if let Some(workspace) = manifest.get("workspace").and_then(toml::Value::as_table) {
+        add_tables(workspace, "workspace.");
+    }
+    if let Some(targets) = manifest.get("target").and_then(toml::Value::as_table) {
+        for (target, table) in targets {
+            if let Some(table) = table.as_table() {
+                add_tables(table, &format!("target.{}.", target));
+            }
+        }
+    }
+    deps
+}
+
+fn cargo_spec(value: &toml::Value) -> DependencySpec {
+    let Some(table) = value.as_table() else {
+        return DependencySpec {
+            version: value.as_str().map(str::to_string),
+            ..DependencySpec::default()
+        };
+    };
+    let text = |key: &str| table.get(key).and_then(toml::Value::as_str).map(str::to_string);
+
+    let version = match table.get("workspace").and_then(toml::Value::as_bool) {
+        Some(true) => Some("workspace".to_string()),
+        _ => text("version"),
+    };
+    let source = match (text("git"), text("path")) {
+        (Some(git), _) => {
+            let reference = ["rev", "tag", "branch"].into_iter().find_map(text);
+            Some(DependencySource::Git(match reference {
+                Some(reference) => format!("{}#{}", git, reference),
+                None => git,
+            }))
+        }
+        (None, Some(path)) => Some(DependencySource::Path(path)),
+        (None, None) => None,
+    };
+    let features = table
+        .get("features")
+        .and_then(toml::Value::as_array)
+        .map(|features| features.iter().filter_map(|f| f.as_str().map(str::to_string)).collect())
+        .unwrap_or_default();
+    DependencySpec { version, source, features }
+}
+
+fn cargo_features(manifest: &toml::Table) -> BTreeMap<String, String> {
+    let Some(features) = manifest.get("features").and_then(toml::Value::as_table) else {
+        return BTreeMap::new();
+    };
+    features
+        .iter()
+        .map(|(name, enables)| {
+            let enables: Vec<&str> = enables
+                .as_array()
+                .map(|list| list.iter().filter_map(toml::Value::as_str).collect())
+                .unwrap_or_default();
+            (name.clone(), format!("[{}]", enables.join(", ")))
+        })
+        .collect()
+}
+
+const NPM_DEPENDENCY_TABLES: [&str; 4] = ["dependencies", "devDependencies", "peerDependencies", "optionalDependencies"];
+
+fn npm_dependencies(manifest: &serde_json::Value) -> Dependencies {
+    let mut deps = Dependencies::new();
+    for section in NPM_DEPENDENCY_TABLES {
+        let Some(entries) = manifest.get(section).and_then(serde_json::Value::as_object) else { continue };
+        for (name, value) in entries {
+            if let Some(spec) = value.as_str() {
+                deps.insert((section.to_string(), name.clone()), npm_spec(spec));
+            }
+        }
+    }
+    deps
+}
+
+fn npm_spec(spec: &str) -> DependencySpec {
+    let is_git = spec.starts_with("git+")
+        || spec.starts_with("git://")
+        || spec.starts_with("git@")
+        || ["github:", "gitlab:", "bitbucket:"].iter().any(|prefix| spec.starts_with(prefix));
+    let source = if is_git {
+        Some(DependencySource::Git(spec.to_string()))
+    } else if let Some(path) = spec.strip_prefix("file:").or_else(|| spec.strip_prefix("link:")) {
+        Some(DependencySource::Path(path.to_string()))
+    } else if spec.starts_with("http://") || spec.starts_with("https://") {
+        Some(DependencySource::Url(spec.to_string()))
+    } else {
+        None
+    };
+    DependencySpec {
+        version: source.is_none().then(|| spec.to_string()),
+        source,
+        features: Vec::new(),
+    }
+}
+
+fn npm_scripts(manifest: &serde_json::Value) -> BTreeMap<String, String> {
+    manifest
+        .get("scripts")
+        .and_then(serde_json::Value::as_object)
+        .map(|scripts| {
+            scripts
+                .iter()
+                .filter_map(|(name, command)| Some((name.clone(), command.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Resolved versions of each package in a lockfile
+type LockedPackages = BTreeMap<String, BTreeSet<String>>;
+
+fn cargo_lock_packages(lock: &toml::Table) -> LockedPackages {
+    let mut packages = LockedPackages::new();
+    for package in lock.get("package").and_then(toml::Value::as_array).into_iter().flatten() {
+        let name = package.get("name").and_then(toml::Value::as_str);
+        let version = package.get("version").and_then(toml::Value::as_str);
+        if let (Some(name), Some(version)) = (name, version) {
+            packages.entry(name.to_string()).or_default().insert(version.to_string());
+        }
+    }
+    packages
+}
+
+fn npm_lock_packages(lock: &serde_json::Value) -> LockedPackages {
+    let mut packages = LockedPackages::new();
+    // lockfileVersion 2 and 3 key packages by install path
+    if let Some(entries) = lock.get("packages").and_then(serde_json::Value::as_object) {
+        for (install_path, package) in entries {
+            let Some((_, name)) = install_path.rsplit_once("node_modules/") else { continue };
+            if let Some(version) = package.get("version").and_then(serde_json::Value::as_str) {
+                packages.entry(name.to_string()).or_default().insert(version.to_string());
+            }
+        }
+    } else if let Some(entries) = lock.get("dependencies").and_then(serde_json::Value::as_object) {
+        for (name, package) in entries {
+            if let Some(version) = package.get("version").and_then(serde_json::Value::as_str) {
+                packages.entry(name.clone()).or_default().insert(version.to_string());
+            }
+        }
+    }
+    packages
+}
+
+fn diff_lockfile(old: &LockedPackages, new: &LockedPackages) -> Option<LockfileChange> {
+    let mut change = LockfileChange::default();
+    for (name, versions) in new {
+        match old.get(name) {
+            None => change.added += 1,
+            Some(old_versions) if old_versions != versions => change.updated += 1,
+            Some(_) => {}
+        }
+    }
+    change.removed = old.keys().filter(|name| !new.contains_key(*name)).count();
+    (change != LockfileChange::default()).then_some(change)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyze(file: &str, old: &str, new: &str) -> ManifestChange {
+        ManifestChange::analyze(Path::new(file), old, new).unwrap()
+    }
+
+    #[test]
+    fn test_cargo_dependencies_added_removed_and_bumped() {
+        let old = "[package]\nname = \"app\"\n\n[dependencies]\nserde = \"1.0\"\nrand = \"0.8\"\n\n[dev-dependencies]\ntempfile = \"3.8\"\n";
+        let new = "[package]\nname = \"app\"\n\n[dependencies]\nserde = { version = \"1.0\", features = [\"derive\"] }\ntokio = \"1.38\"\n\n[dev-dependencies]\ntempfile = \"3.10\"\n\n[target.'cfg(unix)'.dependencies]\nlibc = \"0.2\"\n";
+
+        assert_eq!(analyze("Cargo.toml", old, new).summary_lines(), vec![
+            "- rand 0.8 [dependencies]",
+            "~ serde features +derive [dependencies]",
+            "+ tokio 1.38 [dependencies]",
+            "~ tempfile 3.8 → 3.10 [dev-dependencies]",
+            "+ libc 0.2 [target.cfg(unix).dependencies]",
+        ]);
+
+        // Only the package description changed
+        let renamed = old.replace("name = \"app\"", "name = \"app\"\ndescription = \"x\"");
+        assert!(ManifestChange::analyze(Path::new("Cargo.toml"), old, &renamed).is_none());
+    }
+
+    #[test]
+    fn test_cargo_features_and_sources() {
+        let old = "[features]\ndefault = [\"std\"]\nlegacy = []\n\n[dependencies]\nfoo = { path = \"../foo\" }\n";
+        let new = "[features]\ndefault = [\"std\", \"simd\"]\nsimd = []\n\n[dependencies]\nfoo = { git = \"https://example.org/foo.git\", rev = \"abc123\" }\n";
+
+        let change = analyze("crates/app/Cargo.toml", old, new);
+        assert_eq!(change.dependencies[0].new_git_source(), Some("https://example.org/foo.git#abc123"));
+        assert_eq!(change.summary_lines(), vec![
+            "~ foo path ../foo → git https://example.org/foo.git#abc123 [dependencies]",
+            "~ feature default: [std] → [std, simd]",
+            "- feature legacy",
+            "+ feature simd = []",
+        ]);
+    }
+
+    #[test]
+    fn test_npm_dependencies_and_scripts() {
+        let old = r#"{"name": "web", "scripts": {"test": "jest", "lint": "eslint ."},
+            "dependencies": {"react": "^18.2.0", "left-pad": "1.3.0"}, "devDependencies": {"jest": "^29.0.0"}}"#;
+        let new = r#"{"name": "web", "scripts": {"test": "vitest", "postinstall": "node setup.js"},
+            "dependencies": {"react": "^18.3.1", "utils": "github:someone/utils"}, "devDependencies": {"jest": "^29.0.0"}}"#;
+
+        let change = analyze("package.json", old, new);
+        assert_eq!(change.summary_lines(), vec![
+            "- left-pad 1.3.0 [dependencies]",
+            "~ react ^18.2.0 → ^18.3.1 [dependencies]",
+            "+ utils git github:someone/utils [dependencies]",
+            "- script lint",
+            "+ script postinstall = node setup.js",
+            "~ script test: jest → vitest",
+        ]);
+        assert_eq!(change.dependencies[2].new_git_source().and_then(git_host).as_deref(), Some("github.com"));
+    }
+
+    #[test]
+    fn test_lockfile_counts() {
+        let old = "version = 3\n\n[[package]]\nname = \"a\"\nversion = \"1.0.0\"\n\n[[package]]\nname = \"b\"\nversion = \"2.0.0\"\n\n[[package]]\nname = \"c\"\nversion = \"0.1.0\"\n";
+        let new = "version = 3\n\n[[package]]\nname = \"a\"\nversion = \"1.0.1\"\n\n[[package]]\nname = \"b\"\nversion = \"2.0.0\"\n\n[[package]]\nname = \"d\"\nversion = \"0.2.0\"\n";
+        let change = analyze("Cargo.lock", old, new);
+        assert_eq!(change.lockfile, Some(LockfileChange { added: 1, removed: 1, updated: 1 }));
+        assert_eq!(change.summary_lines(), vec!["~ packages 1 updated, 1 added, 1 removed"]);
+
+        let old = r#"{"lockfileVersion": 3, "packages": {"": {"name": "web"},
+            "node_modules/react": {"version": "18.2.0"}, "node_modules/a/node_modules/react": {"version": "17.0.2"}}}"#;
+        let new = r#"{"lockfileVersion": 3, "packages": {"": {"name": "web"},
+            "node_modules/react": {"version": "18.3.1"}}}"#;
+        let change = analyze("package-lock.json", old, new);
+        assert_eq!(change.lockfile, Some(LockfileChange { added: 0, removed: 0, updated: 1 }));
+        assert_eq!(change.summary_lines(), vec!["~ package 1 updated"]);
+    }
+
+    #[test]
+    fn test_malformed_manifests_fall_back_to_the_diff() {
+        assert!(ManifestChange::analyze(Path::new("Cargo.toml"), "[dependencies]\nserde = \"1\"\n", "[dependencies\nserde = ").is_none());
+        assert!(ManifestChange::analyze(Path::new("package.json"), "{\"dependencies\": {}}", "{\"dependencies\": {").is_none());
+        assert!(ManifestChange::analyze(Path::new("package-lock.json"), "not json", "{}").is_none());
+        assert!(ManifestChange::analyze(Path::new("src/main.rs"), "", "fn main() {}").is_none());
+    }
+
+    #[test]
+    fn test_git_host() {
+        assert_eq!(git_host("https://github.com/a/b.git").as_deref(), Some("github.com"));
+        assert_eq!(git_host("git+ssh://git@Example.org:2222/a/b.git#v1").as_deref(), Some("example.org"));
+        assert_eq!(git_host("git@gitlab.com:a/b.git").as_deref(), Some("gitlab.com"));
+        assert_eq!(git_host("bitbucket:a/b").as_deref(), Some("bitbucket.org"));
+    }
+}
@@ -0,0 +1,541 @@
+//! Notification delivery with focus mode and quiet hours
+//!
+//! Notifications are handed to an [`EventSink`]. [`FocusSink`] wraps another
+//! sink and, while focus mode is on or a quiet-hours window is active, holds
+//! back non-critical notifications and delivers a single digest once the
+//! quiet period ends. Critical findings are always delivered at once, and
+//! listed again in their own section of the digest. Focus mode is switched
+//! through a [`FocusSwitch`] shared with the TUI, which also shows whether
+//! notifications are being held.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use anyhow::{anyhow, Result};
+use chrono::NaiveTime;
+use crate::config::NotificationConfig;
+use crate::core::clock::{system_clock, SharedClock};
+
+/// A single user-facing notification
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    /// Short category used for breakthrough rules, e.g. "risky-change"
+    pub category: String,
+    pub title: String,
+    pub body: String,
+    pub risky: bool,
+    /// A Critical rule fired: delivered even in focus mode or quiet hours
+    pub critical: bool,
+    /// Secrets found by the change, counted in the digest
+    pub secrets: usize,
+}
+
+impl Notification {
+    pub fn new(category: impl Into<String>, title: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            category: category.into(),
+            title: title.into(),
+            body: body.into(),
+            risky: false,
+            critical: false,
+            secrets: 0,
+        }
+    }
+
+    pub fn risky(mut self) -> Self {
+        self.risky = true;
+        self
+    }
+
+    pub fn critical(mut self) -> Self {
+        self.risky = true;
+        self.critical = true;
+        self
+    }
+
+    pub fn with_secrets(mut self, secrets: usize) -> Self {
+        self.secrets = secrets;
+        self
+    }
+
+    /// Critical findings of a change, if any of its rules was Critical or
+    /// it added a secret
+    pub fn critical_finding(event: &crate::core::FileEvent) -> Option<Self> {
+        let critical_hits: Vec<_> = event.confidence.iter().flat_map(|confidence| confidence.critical_hits()).collect();
+        let mut reasons: Vec<String> = critical_hits
+            .iter()
+            .map(|hit| match hit.line {
+                Some(line) => format!("{} (line {})", hit.rule_id, line),
+                None => hit.rule_id.clone(),
+            })
+            .collect();
+        // A secret a Critical rule already reported on its line is not listed twice
+        reasons.extend(
+            event
+                .secrets
+                .iter()
+                .filter(|finding| !critical_hits.iter().any(|hit| hit.line == Some(finding.line)))
+                .map(|finding| format!("secret {} (line {})", finding.kind.name(), finding.line)),
+        );
+        if reasons.is_empty() {
+            return None;
+        }
+        let title = format!("Critical finding in {}", event.path.display());
+        Some(Notification::new("critical-finding", title, reasons.join(", ")).critical().with_secrets(event.secrets.len()))
+    }
+}
+
+impl From<&crate::core::BudgetAlert> for Notification {
+    fn from(alert: &crate::core::BudgetAlert) -> Self {
+        Notification::new("budget", "Change budget exceeded", alert.message()).risky()
+    }
+}
+
+/// Destination for notifications (desktop, webhook, log, ...)
+pub trait EventSink {
+    fn send(&mut self, notification: &Notification) -> Result<()>;
+}
+
+/// Sink that writes notifications to the tracing log
+#[derive(Debug, Default)]
+pub struct LogSink;
+
+impl EventSink for LogSink {
+    fn send(&mut self, notification: &Notification) -> Result<()> {
+        tracing::info!("{}: {}", notification.title, notification.body);
+        Ok(())
+    }
+}
+
+/// A daily wall-clock window; `start > end` spans midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietWindow {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// Set of quiet-hours windows, parsed from e.g. `12:00-13:00,18:00-09:00`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuietHours {
+    pub windows: Vec<QuietWindow>,
+}
+
+impl QuietHours {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut windows = Vec::new();
+
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (start, end) = part
+                .split_once('-')
+                .ok_or_else(|| anyhow!("Invalid quiet hours window '{}', expected HH:MM-HH:MM", part))?;
+            let start = NaiveTime::parse_from_str(start.trim(), "%H:%M")
+                .map_err(|_| anyhow!("Invalid start time in '{}'", part))?;
+            let end = NaiveTime::parse_from_str(end.trim(), "%H:%M")
+                .map_err(|_| anyhow!("Invalid end time in '{}'", part))?;
+            if start == end {
+                return Err(anyhow!("Quiet hours window '{}' is empty", part));
+            }
+            windows.push(QuietWindow { start, end });
+        }
+
+        Ok(Self { windows })
+    }
+
+    /// Whether the given local time falls in any window
+    pub fn is_quiet_at(&self, time: NaiveTime) -> bool {
+        self.windows.iter().any(|w| w.contains(time))
+    }
+}
+
+/// Focus mode on or off, shared between a [`FocusSink`] and the TUI that
+/// toggles it. Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct FocusSwitch {
+    shared: Arc<FocusState>,
+}
+
+#[derive(Debug, Default)]
+struct FocusState {
+    enabled: AtomicBool,
+    /// Whether the sink held notifications back when it last checked
+    quiet: AtomicBool,
+    /// Notifications held for the next digest
+    held: AtomicUsize,
+}
+
+impl FocusSwitch {
+    pub fn is_enabled(&self) -> bool {
+        self.shared.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Turn focus mode on or off. The digest goes out on the sink's next tick.
+    pub fn set(&self, enabled: bool) {
+        self.shared.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Flip focus mode, returning whether it is now on
+    pub fn toggle(&self) -> bool {
+        !self.shared.enabled.fetch_xor(true, Ordering::SeqCst)
+    }
+
+    /// Whether notifications are being held, by focus mode or quiet hours
+    pub fn is_quiet(&self) -> bool {
+        self.shared.quiet.load(Ordering::SeqCst)
+    }
+
+    /// Notifications held back for the next digest
+    pub fn held(&self) -> usize {
+        self.shared.held.load(Ordering::SeqCst)
+    }
+}
+
+/// Wraps a sink and batches notifications while focus mode or quiet hours are active
+pub struct FocusSink<S: EventSink> {
+    inner: S,
+    clock: SharedClock,
+    quiet_hours: QuietHours,
+    breakthrough_categories: Vec<String>,
+    focus: FocusSwitch,
+    was_quiet: bool,
+    pending: Vec<Notification>,
+    /// Critical notifications delivered while quiet, repeated in the digest
+    criticals: Vec<Notification>,
+}
+
+impl<S: EventSink> FocusSink<S> {
+    pub fn new(inner: S, config: &NotificationConfig) -> Result<Self> {
+        Self::with_clock(inner, system_clock(), config)
+    }
+
+    pub fn with_clock(inner: S, clock: SharedClock, config: &NotificationConfig) -> Result<Self> {
+        let quiet_hours = match config.quiet_hours {
+            Some(ref spec) => QuietHours::parse(spec)?,
+            None => QuietHours::default(),
+        };
+
+        Ok(Self {
+            inner,
+            clock,
+            quiet_hours,
+            breakthrough_categories: config.breakthrough_categories.clone(),
+            focus: FocusSwitch::default(),
+            was_quiet: false,
+            pending: Vec::new(),
+            criticals: Vec::new(),
+        })
+    }
+
+    /// Take focus mode from `focus`, e.g. the switch the TUI toggles
+    pub fn with_switch(mut self, focus: FocusSwitch) -> Self {
+        self.focus = focus;
+        self
+    }
+
+    pub fn is_focus_enabled(&self) -> bool {
+        self.focus.is_enabled()
+    }
+
+    /// Number of notifications held back for the next digest
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Whether notifications are currently being held back
+    pub fn is_quiet(&self) -> bool {
+        // Evaluate in the clock's current offset so timezone changes take effect immediately
+        self.focus.is_enabled() || self.quiet_hours.is_quiet_at(self.clock.local_now().time())
+    }
+
+    /// Turn focus mode on or off; turning it off delivers any pending digest
+    pub fn set_focus(&mut self, enabled: bool) -> Result<()> {
+        self.focus.set(enabled);
+        self.tick()
+    }
+
+    pub fn toggle_focus(&mut self) -> Result<()> {
+        self.focus.toggle();
+        self.tick()
+    }
+
+    /// Re-check the quiet state and flush the digest when a quiet period has ended.
+    /// Call periodically so the digest goes out even without new notifications.
+    pub fn tick(&mut self) -> Result<()> {
+        let quiet = self.is_quiet();
+        self.was_quiet = quiet;
+        self.focus.shared.quiet.store(quiet, Ordering::SeqCst);
+        if !quiet {
+            self.flush_digest()?;
+        }
+        Ok(())
+    }
+
+    fn breaks_through(&self, notification: &Notification) -> bool {
+        self.breakthrough_categories
+            .iter()
+            .any(|c| c.eq_ignore_ascii_case(&notification.category))
+    }
+
+    fn flush_digest(&mut self) -> Result<()> {
+        if self.pending.is_empty() && self.criticals.is_empty() {
+            return Ok(());
+        }
+
+        let total = self.pending.len() + self.criticals.len();
+        let risky = self.pending.iter().filter(|n| n.risky).count() + self.criticals.len();
+        let title = if !self.criticals.is_empty() {
+            format!("{} critical findings while you were away", self.criticals.len())
+        } else if risky > 0 {
+            format!("{} risky changes while you were away", risky)
+        } else {
+            format!("{} notifications while you were away", total)
+        };
+        let mut body = format!("{} notifications held during focus — open watchdiff for details", self.pending.len());
+        let secrets: usize = self.pending.iter().chain(&self.criticals).map(|n| n.secrets).sum();
+        if secrets > 0 {
+            body.push_str(&format!("\n{} secrets found", secrets));
+        }
+        if !self.criticals.is_empty() {
+            body.push_str("\n\nCritical findings:");
+            for critical in &self.criticals {
+                body.push_str(&format!("\n- {}: {}", critical.title, critical.body));
+            }
+        }
+        let digest = Notification {
+            category: "digest".to_string(),
+            title,
+            body,
+            risky: risky > 0,
+            critical: !self.criticals.is_empty(),
+            secrets,
+        };
+
+        self.pending.clear();
+        self.criticals.clear();
+        self.focus.shared.held.store(0, Ordering::SeqCst);
+        self.inner.send(&digest)
+    }
+}
+
+impl<S: EventSink> EventSink for FocusSink<S> {
+    fn send(&mut self, notification: &Notification) -> Result<()> {
+        self.tick()?;
+
+        if self.was_quiet && notification.critical {
+            self.criticals.push(notification.clone());
+        } else if self.was_quiet && !self.breaks_through(notification) {
+            self.pending.push(notification.clone());
+            self.focus.shared.held.store(self.pending.len(), Ordering::SeqCst);
+            return Ok(());
+        }
+
+        self.inner.send(notification)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::core::clock::MockClock;
+
+    #[derive(Clone, Default)]
+    struct RecordingSink(Rc<RefCell<Vec<Notification>>>);
+
+    impl EventSink for RecordingSink {
+        fn send(&mut self, notification: &Notification) -> Result<()> {
+            self.0.borrow_mut().push(notification.clone());
+            Ok(())
+        }
+    }
+
+    /// Mock clock set from RFC 3339 times, in the timezone they are given in
+    #[derive(Clone)]
+    struct LocalClock(MockClock);
+
+    impl LocalClock {
+        fn at(time: &str) -> Self {
+            let clock = Self(MockClock::new(std::time::SystemTime::UNIX_EPOCH));
+            clock.set(time);
+            clock
+        }
+
+        fn set(&self, time: &str) {
+            let time = chrono::DateTime::parse_from_rfc3339(time).unwrap();
+            self.0.set(time.into());
+            self.0.set_offset(*time.offset());
+        }
+
+        fn shared(&self) -> SharedClock {
+            Arc::new(self.0.clone())
+        }
+    }
+
+    fn config(quiet_hours: &str) -> NotificationConfig {
+        NotificationConfig {
+            quiet_hours: Some(quiet_hours.to_string()),
+            breakthrough_categories: vec!["security-rule".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_parse_quiet_hours() {
+        let hours = QuietHours::parse("12:00-13:00, 18:00-09:00").unwrap();
+        assert_eq!(hours.windows.len(), 2);
+
+        assert!(QuietHours::parse("12:00").is_err());
+        assert!(QuietHours::parse("25:00-26:00").is_err());
+        assert!(QuietHours::parse("10:00-10:00").is_err());
+    }
+
+    #[test]
+    fn test_midnight_spanning_window() {
+        let hours = QuietHours::parse("18:00-09:00").unwrap();
+        let t = |s| NaiveTime::parse_from_str(s, "%H:%M").unwrap();
+
+        assert!(hours.is_quiet_at(t("23:30")));
+        assert!(hours.is_quiet_at(t("00:15")));
+        assert!(hours.is_quiet_at(t("08:59")));
+        assert!(!hours.is_quiet_at(t("09:00")));
+        assert!(!hours.is_quiet_at(t("17:59")));
+    }
+
+    #[test]
+    fn test_window_entry_and_exit_delivers_digest() {
+        let sink = RecordingSink::default();
+        let clock = LocalClock::at("2024-05-01T11:59:00+00:00");
+        let mut focus = FocusSink::with_clock(sink.clone(), clock.shared(), &config("12:00-13:00")).unwrap();
+
+        focus.send(&Notification::new("risky-change", "before", "")).unwrap();
+        assert_eq!(sink.0.borrow().len(), 1);
+
+        clock.set("2024-05-01T12:10:00+00:00");
+        focus.send(&Notification::new("risky-change", "a", "").risky()).unwrap();
+        focus.send(&Notification::new("change", "b", "")).unwrap();
+        assert_eq!(sink.0.borrow().len(), 1);
+        assert_eq!(focus.pending_count(), 2);
+
+        clock.set("2024-05-01T13:00:00+00:00");
+        focus.tick().unwrap();
+
+        let delivered = sink.0.borrow();
+        assert_eq!(delivered.len(), 2);
+        assert_eq!(delivered[1].category, "digest");
+        assert_eq!(delivered[1].title, "1 risky changes while you were away");
+        assert_eq!(focus.pending_count(), 0);
+    }
+
+    #[test]
+    fn test_breakthrough_categories_bypass_quiet_hours() {
+        let sink = RecordingSink::default();
+        let clock = LocalClock::at("2024-05-01T23:00:00+00:00");
+        let mut focus = FocusSink::with_clock(sink.clone(), clock.shared(), &config("18:00-09:00")).unwrap();
+
+        focus.send(&Notification::new("security-rule", "secret added", "")).unwrap();
+        focus.send(&Notification::new("risky-change", "held", "")).unwrap();
+
+        assert_eq!(sink.0.borrow().len(), 1);
+        assert_eq!(sink.0.borrow()[0].title, "secret added");
+        assert_eq!(focus.pending_count(), 1);
+    }
+
+    #[test]
+    fn test_critical_findings_bypass_focus_and_head_the_digest() {
+        let sink = RecordingSink::default();
+        let clock = LocalClock::at("2024-05-01T10:00:00+00:00");
+        let mut focus = FocusSink::with_clock(sink.clone(), clock.shared(), &NotificationConfig::default()).unwrap();
+        focus.set_focus(true).unwrap();
+
+        focus.send(&Notification::new("critical-finding", "Critical finding in .env", "private_key (line 3)").critical()).unwrap();
+        focus.send(&Notification::new("risky-change", "held", "")).unwrap();
+        assert_eq!(sink.0.borrow().len(), 1);
+        assert_eq!(focus.pending_count(), 1);
+
+        focus.set_focus(false).unwrap();
+        let sent = sink.0.borrow();
+        let digest = &sent[1];
+        assert_eq!(digest.title, "1 critical findings while you were away");
+        assert!(digest.critical);
+        assert!(digest.body.contains("Critical findings:\n- Critical finding in .env: private_key (line 3)"));
+    }
+
+    #[test]
+    fn test_secret_findings_are_critical_and_counted_in_the_digest() {
+        let mut event = crate::core::FileEvent::new(".env".into(), crate::core::FileEventKind::Modified);
+        event.secrets = crate::security::SecretScanner::default().scan_diff("@@ -1 +1,2 @@\n DEBUG=1\n+API_TOKEN=f3Kq9ZrT7vW2xLp8N4bYc6Hd");
+        let notification = Notification::critical_finding(&event).unwrap();
+        assert!(notification.critical);
+        assert_eq!(notification.body, "secret high_entropy_secret (line 2)");
+
+        let sink = RecordingSink::default();
+        let clock = LocalClock::at("2024-05-01T10:00:00+00:00");
+        let mut focus = FocusSink::with_clock(sink.clone(), clock.shared(), &NotificationConfig::default()).unwrap();
+        focus.set_focus(true).unwrap();
+        focus.send(&notification).unwrap();
+        focus.set_focus(false).unwrap();
+        assert!(sink.0.borrow()[1].body.contains("\n1 secrets found"));
+    }
+
+    #[test]
+    fn test_manual_focus_toggle_flushes_on_exit() {
+        let sink = RecordingSink::default();
+        let clock = LocalClock::at("2024-05-01T10:00:00+00:00");
+        let mut focus = FocusSink::with_clock(sink.clone(), clock.shared(), &NotificationConfig::default()).unwrap();
+
+        focus.toggle_focus().unwrap();
+        focus.send(&Notification::new("change", "held", "")).unwrap();
+        assert!(sink.0.borrow().is_empty());
+
+        focus.toggle_focus().unwrap();
+        assert_eq!(sink.0.borrow().len(), 1);
+        assert_eq!(sink.0.borrow()[0].title, "1 notifications while you were away");
+    }
+
+    #[test]
+    fn test_switch_shared_with_the_tui_holds_until_the_next_tick() {
+        let sink = RecordingSink::default();
+        let clock = LocalClock::at("2024-05-01T10:00:00+00:00");
+        let switch = FocusSwitch::default();
+        let mut focus = FocusSink::with_clock(sink.clone(), clock.shared(), &NotificationConfig::default())
+            .unwrap()
+            .with_switch(switch.clone());
+
+        assert!(switch.toggle());
+        focus.send(&Notification::new("change", "a", "")).unwrap();
+        focus.send(&Notification::new("change", "b", "")).unwrap();
+        assert!(switch.is_quiet());
+        assert_eq!(switch.held(), 2);
+        assert!(sink.0.borrow().is_empty());
+
+        // Turned off from the TUI; the watcher's next tick sends the digest
+        assert!(!switch.toggle());
+        assert!(sink.0.borrow().is_empty());
+        focus.tick().unwrap();
+        assert_eq!(sink.0.borrow()[0].title, "2 notifications while you were away");
+        assert!(!switch.is_quiet());
+        assert_eq!(switch.held(), 0);
+    }
+
+    #[test]
+    fn test_timezone_change_uses_new_local_time() {
+        let sink = RecordingSink::default();
+        // 11:30 UTC is outside the window, 12:30 in UTC+1 is inside it
+        let clock = LocalClock::at("2024-05-01T11:30:00+00:00");
+        let mut focus = FocusSink::with_clock(sink.clone(), clock.shared(), &config("12:00-13:00")).unwrap();
+        assert!(!focus.is_quiet());
+
+        clock.set("2024-05-01T12:30:00+01:00");
+        assert!(focus.is_quiet());
+        focus.send(&Notification::new("change", "held", "")).unwrap();
+        assert!(sink.0.borrow().is_empty());
+    }
+}
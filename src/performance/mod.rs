@@ -1,8 +1,9 @@
-use std::path::PathBuf;
-use std::time::{Duration, Instant, SystemTime};
-use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use lru::LruCache;
 use ratatui::style::Style;
+use ratatui::text::Line;
 
 /// Cache for file contents to avoid repeated disk I/O
 pub struct FileContentCache {
@@ -37,10 +38,76 @@ pub struct SearchResultCache {
     pub last_all_files_hash: u64,
 }
 
-/// Event debouncer to reduce processing overhead
-pub struct EventDebouncer {
-    pending_events: HashMap<PathBuf, (crate::core::FileEvent, Instant)>,
-    debounce_duration: Duration,
+/// Cache of review hunks parsed from event diffs, so re-entering review
+/// mode does not re-parse every diff
+pub struct HunkParseCache {
+    cache: LruCache<HunkCacheKey, Vec<crate::review::DiffHunk>>,
+    hits: usize,
+    misses: usize,
+}
+
+/// Identity of an event's diff: a superseding event for the same path has a
+/// different timestamp (and usually diff), so it never reuses stale hunks
+#[derive(Hash, Eq, PartialEq, Clone)]
+struct HunkCacheKey {
+    path: PathBuf,
+    timestamp: SystemTime,
+    diff_hash: u64,
+}
+
+impl HunkCacheKey {
+    fn new(path: &Path, timestamp: SystemTime, diff: Option<&str>) -> Self {
+        Self { path: path.to_path_buf(), timestamp, diff_hash: diff_hash(diff) }
+    }
+}
+
+/// Changed words of the diff lines shown in the changes log, so a draw does
+/// not pair up every visible diff's lines again
+pub struct WordRangeCache {
+    cache: LruCache<HunkCacheKey, Vec<Vec<Range<usize>>>>,
+    hits: usize,
+    misses: usize,
+}
+
+/// Styled diff lines of review hunks, so a review frame only re-styles the
+/// hunks whose status or selection changed since the previous draw
+pub struct ReviewLineCache {
+    cache: LruCache<ReviewLineKey, Vec<Line<'static>>>,
+    session_id: String,
+    style_key: u64,
+    hits: usize,
+    styled_lines: usize,
+}
+
+/// How a hunk is drawn. Like `HunkCacheKey`, it names the event's diff by
+/// its content, so a diff that changed is styled again.
+#[derive(Hash, Eq, PartialEq, Clone)]
+pub struct ReviewLineKey {
+    pub path: PathBuf,
+    pub timestamp: SystemTime,
+    /// [`diff_hash`] of the event's diff
+    pub diff_hash: u64,
+    pub hunk_id: String,
+    pub current: bool,
+    pub action: crate::review::ReviewAction,
+}
+
+/// Side-by-side diff lines of the changes log, so scrolling does not lay out
+/// every visible diff again each frame
+pub struct SideBySideCache {
+    cache: LruCache<SideBySideKey, Vec<Line<'static>>>,
+    style_key: u64,
+    hits: usize,
+    misses: usize,
+}
+
+/// How an event's diff is laid out. Like `HunkCacheKey`, the event path and
+/// timestamp stand in for the diff content; a resized pane has a new width.
+#[derive(Hash, Eq, PartialEq, Clone)]
+pub struct SideBySideKey {
+    pub path: PathBuf,
+    pub timestamp: SystemTime,
+    pub width: usize,
 }
 
 impl FileContentCache {
@@ -65,7 +132,7 @@ impl FileContentCache {
         }
 
         // Cache miss or stale - read from disk
-        let content = std::fs::read_to_string(path)?;
+        let content = crate::core::ContentReader::default().read_to_string(path)?;
         let metadata = std::fs::metadata(path)?;
         let last_modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
         let size = metadata.len();
@@ -101,14 +168,14 @@ impl SyntaxHighlightCache {
     /// Get cached syntax highlighting or compute if not cached
     pub fn get_highlighted_content(
         &mut self,
-        path: &PathBuf,
+        path: &Path,
         content: &str,
         language: &str,
         highlighter: &crate::highlight::SyntaxHighlighter,
     ) -> Vec<Vec<(Style, String)>> {
         let content_hash = self.calculate_content_hash(content);
         let cache_key = SyntaxCacheKey {
-            path: path.clone(),
+            path: path.to_path_buf(),
             language: language.to_string(),
             content_hash,
         };
@@ -137,6 +204,11 @@ impl SyntaxHighlightCache {
         hasher.finish()
     }
 
+    /// Drop every entry, e.g. when highlighting is turned off
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
     /// Invalidate cache entries for a specific file
     pub fn invalidate_file(&mut self, path: &PathBuf) {
         let keys_to_remove: Vec<_> = self.cache
@@ -156,6 +228,52 @@ impl SyntaxHighlightCache {
     }
 }
 
+/// Hash of an event's diff, naming its content in cache keys
+pub fn diff_hash(diff: Option<&str>) -> u64 {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    diff.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl HunkParseCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            cache: LruCache::new(std::num::NonZeroUsize::new(capacity).unwrap()),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Get the parsed hunks for an event, parsing and caching on a miss
+    pub fn get_or_parse(&mut self, event: &crate::core::FileEvent) -> Vec<crate::review::DiffHunk> {
+        let key = HunkCacheKey::new(&event.path, event.timestamp, event.diff.as_deref());
+
+        if let Some(hunks) = self.cache.get(&key) {
+            self.hits += 1;
+            return hunks.clone();
+        }
+
+        self.misses += 1;
+        let hunks = crate::review::ReviewableChange::parse_diff_into_hunks(&event.diff);
+        self.cache.put(key, hunks.clone());
+        hunks
+    }
+
+    /// (hits, misses) since creation
+    pub fn stats(&self) -> (usize, usize) {
+        (self.hits, self.misses)
+    }
+}
+
+impl Default for SearchResultCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SearchResultCache {
     pub fn new() -> Self {
         Self {
@@ -195,50 +313,120 @@ impl SearchResultCache {
     }
 }
 
-impl EventDebouncer {
-    pub fn new(debounce_duration: Duration) -> Self {
+impl ReviewLineCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            cache: LruCache::new(std::num::NonZeroUsize::new(capacity).unwrap()),
+            session_id: String::new(),
+            style_key: 0,
+            hits: 0,
+            styled_lines: 0,
+        }
+    }
+
+    /// Drop everything when the session or the rendering style (icons,
+    /// invisibles, tab width) differs from the previous draw
+    pub fn prepare(&mut self, session_id: &str, style_key: u64) {
+        if self.session_id != session_id || self.style_key != style_key {
+            self.cache.clear();
+            self.session_id = session_id.to_string();
+            self.style_key = style_key;
+        }
+    }
+
+    /// Lines for `key`, calling `render` on a miss
+    pub fn get_or_render(&mut self, key: ReviewLineKey, render: impl FnOnce() -> Vec<Line<'static>>) -> Vec<Line<'static>> {
+        if let Some(lines) = self.cache.get(&key) {
+            self.hits += 1;
+            return lines.clone();
+        }
+
+        let lines = render();
+        self.styled_lines += lines.len();
+        self.cache.put(key, lines.clone());
+        lines
+    }
+
+    /// Hunks served from the cache since creation
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Lines styled on a miss since creation
+    pub fn styled_lines(&self) -> usize {
+        self.styled_lines
+    }
+}
+
+impl SideBySideCache {
+    pub fn new(capacity: usize) -> Self {
         Self {
-            pending_events: HashMap::new(),
-            debounce_duration,
+            cache: LruCache::new(std::num::NonZeroUsize::new(capacity).unwrap()),
+            style_key: 0,
+            hits: 0,
+            misses: 0,
         }
     }
 
-    /// Add an event to the debouncer
-    pub fn add_event(&mut self, event: crate::core::FileEvent) {
-        let now = Instant::now();
-        self.pending_events.insert(event.path.clone(), (event, now));
+    /// Drop everything when the rendering style (invisibles, tab width)
+    /// differs from the previous draw
+    pub fn prepare(&mut self, style_key: u64) {
+        if self.style_key != style_key {
+            self.cache.clear();
+            self.style_key = style_key;
+        }
     }
 
-    /// Get events that are ready to be processed (debounce period has elapsed)
-    pub fn get_ready_events(&mut self) -> Vec<crate::core::FileEvent> {
-        let now = Instant::now();
-        let mut ready_events = Vec::new();
-        
-        // Find events that have been pending long enough
-        let ready_paths: Vec<_> = self.pending_events
-            .iter()
-            .filter(|(_, (_, timestamp))| now.duration_since(*timestamp) >= self.debounce_duration)
-            .map(|(path, _)| path.clone())
-            .collect();
-        
-        // Remove ready events and collect them
-        for path in ready_paths {
-            if let Some((event, _)) = self.pending_events.remove(&path) {
-                ready_events.push(event);
-            }
+    /// Lay out `key` with `render` unless it is cached already
+    pub fn ensure(&mut self, key: SideBySideKey, render: impl FnOnce() -> Vec<Line<'static>>) {
+        if self.cache.get(&key).is_some() {
+            self.hits += 1;
+            return;
         }
-        
-        ready_events
+        self.misses += 1;
+        self.cache.put(key, render());
     }
 
-    /// Get count of pending events
-    pub fn pending_count(&self) -> usize {
-        self.pending_events.len()
+    /// Cached lines for `key`, without touching the recency order
+    pub fn get(&self, key: &SideBySideKey) -> Option<&Vec<Line<'static>>> {
+        self.cache.peek(key)
     }
 
-    /// Clear all pending events
-    pub fn clear(&mut self) {
-        self.pending_events.clear();
+    /// (hits, misses) since creation
+    pub fn stats(&self) -> (usize, usize) {
+        (self.hits, self.misses)
+    }
+}
+
+impl WordRangeCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            cache: LruCache::new(std::num::NonZeroUsize::new(capacity).unwrap()),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Compute the ranges of the event's `diff` with `compute` unless they
+    /// are cached already
+    pub fn ensure(&mut self, path: &Path, timestamp: SystemTime, diff: &str, compute: impl FnOnce() -> Vec<Vec<Range<usize>>>) {
+        let key = HunkCacheKey::new(path, timestamp, Some(diff));
+        if self.cache.get(&key).is_some() {
+            self.hits += 1;
+            return;
+        }
+        self.misses += 1;
+        self.cache.put(key, compute());
+    }
+
+    /// Cached ranges of the event's `diff`, without touching the recency order
+    pub fn get(&self, path: &Path, timestamp: SystemTime, diff: &str) -> Option<&Vec<Vec<Range<usize>>>> {
+        self.cache.peek(&HunkCacheKey::new(path, timestamp, Some(diff)))
+    }
+
+    /// (hits, misses) since creation
+    pub fn stats(&self) -> (usize, usize) {
+        (self.hits, self.misses)
     }
 }
 
@@ -247,7 +435,16 @@ pub struct PerformanceCache {
     pub file_content: FileContentCache,
     pub syntax_highlight: SyntaxHighlightCache,
     pub search_results: SearchResultCache,
-    pub event_debouncer: EventDebouncer,
+    pub review_hunks: HunkParseCache,
+    pub review_lines: ReviewLineCache,
+    pub side_by_side: SideBySideCache,
+    pub word_ranges: WordRangeCache,
+}
+
+impl Default for PerformanceCache {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PerformanceCache {
@@ -256,7 +453,10 @@ impl PerformanceCache {
             file_content: FileContentCache::new(200),        // Cache up to 200 files
             syntax_highlight: SyntaxHighlightCache::new(100), // Cache up to 100 highlighted files  
             search_results: SearchResultCache::new(),
-            event_debouncer: EventDebouncer::new(Duration::from_millis(100)), // 100ms debounce
+            review_hunks: HunkParseCache::new(4096),                          // Enough for a full event log
+            review_lines: ReviewLineCache::new(2048),                         // Two states of a large change's hunks
+            side_by_side: SideBySideCache::new(256),                          // The visible events at a few pane widths
+            word_ranges: WordRangeCache::new(256),                            // The visible events, with room to scroll back
         }
     }
 
@@ -277,7 +477,6 @@ impl PerformanceCache {
             file_content_capacity: content_cap,
             syntax_highlight_entries: syntax_size,
             syntax_highlight_capacity: syntax_cap,
-            pending_events: self.event_debouncer.pending_count(),
             search_cache_active: !self.search_results.last_query.is_empty(),
         }
     }
@@ -289,6 +488,5 @@ pub struct PerformanceCacheStats {
     pub file_content_capacity: usize,
     pub syntax_highlight_entries: usize,
     pub syntax_highlight_capacity: usize,
-    pub pending_events: usize,
     pub search_cache_active: bool,
-}
\ No newline at end of file
+}
@@ -0,0 +1,440 @@
+//! User config and shareable profiles
+//!
+//! The user config, `config.toml` in [`user_config_dir`], holds settings for
+//! every project; a project's `.watchdiff.toml` overrides it key by key.
+//!
+//! A profile is the effective configuration written as one TOML or JSON
+//! document with a `version`, for a team to share. Settings that only make
+//! sense on the machine that wrote it and secrets are replaced by
+//! placeholders, which import skips. Review mode's key macros travel along
+//! and are imported into the project's UI state. Importing merges the profile
+//! into the user config key by key, leaving out values that are the defaults
+//! anyway, and validates the result the way startup does before anything is
+//! written.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use crate::config::{user_config_dir, WatchDiffConfig, USER_CONFIG_FILE};
+
+/// Version of the profile format written by this build
+pub const PROFILE_VERSION: u32 = 1;
+
+/// Keys that only make sense where they were set: local paths, and commands,
+/// which only run on machines whose user set them up
+pub const MACHINE_SPECIFIC_KEYS: &[&str] = &[
+    "coverage.path_prefix",
+    "coverage.report",
+    "coverage.strip_prefix",
+    "exec.command",
+    "history.path",
+    "intent.path",
+    "mounts.compose",
+    "mounts.maps",
+];
+
+/// Exported in place of a machine-specific setting
+pub const MACHINE_SPECIFIC_PLACEHOLDER: &str = "<machine-specific>";
+
+/// Exported in place of a secret
+pub const SECRET_PLACEHOLDER: &str = "<secret>";
+
+/// Parts of key names that mark a secret, e.g. `webhook_token`
+const SECRET_KEY_PARTS: &[&str] = &["token", "secret", "password", "api_key"];
+
+/// `$XDG_CONFIG_HOME/watchdiff/config.toml`, falling back to `~/.config`
+pub fn user_config_path() -> Option<PathBuf> {
+    Some(user_config_dir()?.join(USER_CONFIG_FILE))
+}
+
+/// The user config at `path`; empty if the file does not exist yet
+pub fn load_user_config(path: &Path) -> Result<toml::Table> {
+    match fs::read_to_string(path) {
+        Ok(text) => toml::from_str(&text).with_context(|| format!("Invalid user config: {}", path.display())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(toml::Table::new()),
+        Err(err) => Err(err).with_context(|| format!("Failed to read user config: {}", path.display())),
+    }
+}
+
+pub fn save_user_config(path: &Path, config: &toml::Table) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(path, toml::to_string(config)?)
+        .with_context(|| format!("Failed to write user config: {}", path.display()))
+}
+
+/// A configuration to share, see the [module docs](self)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub version: u32,
+    /// Review mode key macros by register, in the notation of
+    /// [`crate::ui::UiState::macros`]. Registers are strings, as TOML keys are.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub macros: BTreeMap<String, String>,
+    /// Config sections, with placeholders for machine-specific settings and secrets
+    #[serde(flatten)]
+    pub config: toml::Table,
+}
+
+impl Profile {
+    pub fn from_config(config: &WatchDiffConfig) -> Result<Self> {
+        let mut table = config_table(config)?;
+        redact(&mut table, &config_table(&WatchDiffConfig::default())?, "");
+        Ok(Self { version: PROFILE_VERSION, macros: BTreeMap::new(), config: table })
+    }
+
+    /// Ship the key macros saved in a project's UI state along
+    pub fn with_macros(mut self, macros: &BTreeMap<char, String>) -> Self {
+        self.macros = macros.iter().map(|(register, keys)| (register.to_string(), keys.clone())).collect();
+        self
+    }
+
+    /// Write the profile, as JSON for a `.json` path and TOML otherwise
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = if is_json(path) {
+            serde_json::to_string_pretty(self)?
+        } else {
+            toml::to_string(self)?
+        };
+        fs::write(path, text).with_context(|| format!("Failed to write profile: {}", path.display()))
+    }
+
+    /// Read a profile written by [`save`](Self::save) of this or an earlier version
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read profile: {}", path.display()))?;
+        let profile: Self = if is_json(path) {
+            serde_json::from_str(&text).with_context(|| format!("Invalid profile: {}", path.display()))?
+        } else {
+            toml::from_str(&text).with_context(|| format!("Invalid profile: {}", path.display()))?
+        };
+        if profile.version > PROFILE_VERSION {
+            anyhow::bail!(
+                "{} is a version {} profile; this watchdiff reads up to version {}",
+                path.display(),
+                profile.version,
+                PROFILE_VERSION
+            );
+        }
+        Ok(profile)
+    }
+}
+
+fn config_table(config: &WatchDiffConfig) -> Result<toml::Table> {
+    match toml::Value::try_from(config).context("Failed to serialize the config")? {
+        toml::Value::Table(table) => Ok(table),
+        _ => anyhow::bail!("The config did not serialize to a table"),
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+/// Replace machine-specific settings and secrets under `prefix` with their
+/// placeholders. Machine-specific settings that are empty or as in
+/// `defaults` are left out, so importing never clears them.
+fn redact(table: &mut toml::Table, defaults: &toml::Table, prefix: &str) {
+    let keys: Vec<String> = table.keys().cloned().collect();
+    for key in keys {
+        let path = dotted(prefix, &key);
+        let lower = key.to_lowercase();
+        if MACHINE_SPECIFIC_KEYS.contains(&path.as_str()) {
+            if is_empty(&table[&key]) || defaults.get(&key) == Some(&table[&key]) {
+                table.remove(&key);
+            } else {
+                table.insert(key, toml::Value::String(MACHINE_SPECIFIC_PLACEHOLDER.to_string()));
+            }
+        } else if SECRET_KEY_PARTS.iter().any(|part| lower.contains(part)) {
+            table.insert(key, toml::Value::String(SECRET_PLACEHOLDER.to_string()));
+        } else if let Some(toml::Value::Table(inner)) = table.get_mut(&key) {
+            let empty = toml::Table::new();
+            let defaults = defaults.get(&key).and_then(toml::Value::as_table).unwrap_or(&empty);
+            redact(inner, defaults, &path);
+        }
+    }
+}
+
+fn is_empty(value: &toml::Value) -> bool {
+    match value {
+        toml::Value::String(text) => text.is_empty(),
+        toml::Value::Array(items) => items.is_empty(),
+        toml::Value::Table(table) => table.is_empty(),
+        _ => false,
+    }
+}
+
+fn is_placeholder(value: &toml::Value) -> bool {
+    matches!(value.as_str(), Some(MACHINE_SPECIFIC_PLACEHOLDER | SECRET_PLACEHOLDER))
+}
+
+fn dotted(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// What importing a profile did, by dotted key
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Keys the user config did not set
+    pub added: Vec<String>,
+    /// Keys whose value in the user config was replaced
+    pub overridden: Vec<String>,
+    /// Keys left as they were, with why
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Merge `profile` into the user config `user`. Tables are merged key by
+/// key; lists and other values are set whole. Values the profile only has
+/// because they are the defaults are left out, neither added nor asked
+/// about. Where the user config already has a different value, `resolve` is
+/// called with the key, the current and the profile's value, and overrides
+/// it if it returns `true`. The merged config must pass
+/// [`WatchDiffConfig::validate`], or `user` is left as it was. The profile's
+/// macros are not merged here, see [`import_macros`].
+pub fn import(
+    profile: Profile,
+    user: &mut toml::Table,
+    mut resolve: impl FnMut(&str, &toml::Value, &toml::Value) -> bool,
+) -> Result<ImportReport> {
+    let mut merged = user.clone();
+    let mut report = ImportReport::default();
+    let defaults = config_table(&WatchDiffConfig::default())?;
+    merge_profile(&mut merged, profile.config, &defaults, "", &mut resolve, &mut report);
+
+    let config = WatchDiffConfig::from_table(merged.clone()).map_err(|e| anyhow::anyhow!("Invalid profile: {}", e))?;
+    config.validate().map_err(|e| anyhow::anyhow!("Invalid profile: {}", e))?;
+    *user = merged;
+    Ok(report)
+}
+
+fn merge_profile(
+    base: &mut toml::Table,
+    over: toml::Table,
+    defaults: &toml::Table,
+    prefix: &str,
+    resolve: &mut impl FnMut(&str, &toml::Value, &toml::Value) -> bool,
+    report: &mut ImportReport,
+) {
+    let empty = toml::Table::new();
+    for (key, value) in over {
+        let path = dotted(prefix, &key);
+        if is_placeholder(&value) {
+            report.skipped.push((path, "placeholder for a machine-specific setting or secret".to_string()));
+            continue;
+        }
+        let default = defaults.get(&key);
+        let inner_defaults = default.and_then(toml::Value::as_table).unwrap_or(&empty);
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(over)) => {
+                merge_profile(base, over, inner_defaults, &path, resolve, report)
+            }
+            (None, toml::Value::Table(over)) => {
+                let mut added = toml::Table::new();
+                merge_profile(&mut added, over, inner_defaults, &path, resolve, report);
+                if !added.is_empty() {
+                    base.insert(key, toml::Value::Table(added));
+                }
+            }
+            (_, value) if default == Some(&value) => {}
+            (None, value) => {
+                base.insert(key, value);
+                report.added.push(path);
+            }
+            (Some(current), value) if *current == value => {}
+            (Some(current), value) => {
+                if resolve(&path, current, &value) {
+                    *current = value;
+                    report.overridden.push(path);
+                } else {
+                    report.skipped.push((path, "kept the current value".to_string()));
+                }
+            }
+        }
+    }
+}
+
+/// Merge the profile's key `macros` into the registers saved in a project's
+/// UI state, the way [`import`] merges keys: a register saved with other
+/// keys is only replaced if `resolve` returns `true`. Registers are reported
+/// as `macros.<register>`; names that are not a register are skipped.
+pub fn import_macros(
+    macros: &BTreeMap<String, String>,
+    saved: &mut BTreeMap<char, String>,
+    mut resolve: impl FnMut(&str, &toml::Value, &toml::Value) -> bool,
+    report: &mut ImportReport,
+) {
+    for (name, keys) in macros {
+        let path = format!("macros.{}", name);
+        let mut chars = name.chars();
+        let register = match (chars.next(), chars.next()) {
+            (Some(register), None) if register.is_ascii_lowercase() => register,
+            _ => {
+                report.skipped.push((path, "not a macro register".to_string()));
+                continue;
+            }
+        };
+        match saved.get(&register) {
+            None => {
+                saved.insert(register, keys.clone());
+                report.added.push(path);
+            }
+            Some(current) if current == keys => {}
+            Some(current) => {
+                if resolve(&path, &toml::Value::String(current.clone()), &toml::Value::String(keys.clone())) {
+                    saved.insert(register, keys.clone());
+                    report.overridden.push(path);
+                } else {
+                    report.skipped.push((path, "kept the current value".to_string()));
+                }
+            }
+        }
+    }
+}
+
+/// Ask on `output` whether `key` should change from `current` to `new`, and
+/// read a yes/no answer from `input`. Anything but yes keeps the current value.
+pub fn prompt_override(
+    key: &str,
+    current: &toml::Value,
+    new: &toml::Value,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<bool> {
+    write!(output, "{}: replace {} with {}? [y/N] ", key, current, new)?;
+    output.flush()?;
+
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RuleConfig;
+    use tempfile::TempDir;
+
+    fn team_config() -> WatchDiffConfig {
+        let mut config = WatchDiffConfig::default();
+        config.ui.tab_width = 8;
+        config.ui.tab_width_overrides.insert("go".to_string(), 4);
+        config.watcher.exclude = vec!["**/generated/**".to_string()];
+        config.budgets.max_files_per_batch = Some(5);
+        config.ai.rules.push(RuleConfig {
+            id: "no-todo".to_string(),
+            pattern: "TODO".to_string(),
+            impact: -0.2,
+            reason: "Leaves a TODO".to_string(),
+            severity: Default::default(),
+            path: None,
+        });
+        config.coverage.report = Some(PathBuf::from("/home/dev/project/lcov.info"));
+        config.coverage.strip_prefix = Some("/home/dev/project/".to_string());
+        config.history.path = PathBuf::from("/var/tmp/watchdiff-history");
+        config.mounts.maps = vec!["./src=/app/src".to_string()];
+        config.exec.command = Some("cargo test".to_string());
+        config
+    }
+
+    #[test]
+    fn test_export_wipe_import_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let config = team_config();
+        let mut expected = config.clone();
+        expected.coverage.report = None;
+        expected.coverage.strip_prefix = None;
+        expected.history.path = WatchDiffConfig::default().history.path;
+        expected.mounts.maps.clear();
+        expected.exec.command = None;
+
+        for name in ["team.toml", "team.json"] {
+            let path = dir.path().join(name);
+            let macros = BTreeMap::from([('a', "dj<Esc>".to_string())]);
+            Profile::from_config(&config).unwrap().with_macros(&macros).save(&path).unwrap();
+
+            // A new member's empty user config
+            let mut user = toml::Table::new();
+            let profile = Profile::load(&path).unwrap();
+            let shipped = profile.macros.clone();
+            assert_eq!(shipped, BTreeMap::from([("a".to_string(), "dj<Esc>".to_string())]), "{}", name);
+            let mut report = import(profile, &mut user, |key, _, _| panic!("conflict on {}", key)).unwrap();
+            let skipped: Vec<&str> = report.skipped.iter().map(|(key, _)| key.as_str()).collect();
+            assert_eq!(
+                skipped,
+                ["coverage.report", "coverage.strip_prefix", "exec.command", "history.path", "mounts.maps"],
+                "{}",
+                name
+            );
+            // Only what differs from the defaults is written
+            assert!(report.added.contains(&"ui.tab_width".to_string()));
+            assert!(!report.added.contains(&"history.enabled".to_string()));
+            assert!(report.overridden.is_empty());
+
+            let mut saved = BTreeMap::from([('b', "x".to_string())]);
+            import_macros(&shipped, &mut saved, |key, _, _| panic!("conflict on {}", key), &mut report);
+            assert_eq!(saved[&'a'], "dj<Esc>");
+            assert!(report.added.contains(&"macros.a".to_string()));
+
+            let imported = WatchDiffConfig::from_table(user).unwrap();
+            assert_eq!(toml::Value::try_from(&imported).unwrap(), toml::Value::try_from(&expected).unwrap(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_conflicts_resolved_per_key_and_validated() {
+        let profile = Profile::from_config(&team_config()).unwrap();
+        let mut user: toml::Table = toml::from_str("[ui]\ntab_width = 2\n\n[history]\nenabled = true\n").unwrap();
+
+        let report = import(profile.clone(), &mut user, |_, _, _| false).unwrap();
+        assert_eq!(report.skipped.iter().filter(|(key, _)| key == "ui.tab_width").count(), 1);
+        assert_eq!(user["ui"]["tab_width"].as_integer(), Some(2));
+
+        let mut asked = Vec::new();
+        let report = import(profile.clone(), &mut user, |key, current, new| {
+            asked.push((key.to_string(), current.to_string(), new.to_string()));
+            key == "ui.tab_width"
+        })
+        .unwrap();
+        // history.enabled is only in the profile as the default, so it is not asked about
+        assert_eq!(asked, [("ui.tab_width".to_string(), "2".to_string(), "8".to_string())]);
+        assert_eq!(report.overridden, ["ui.tab_width"]);
+        assert_eq!(user["ui"]["tab_width"].as_integer(), Some(8));
+        // Declined keys stay
+        assert_eq!(user["history"]["enabled"].as_bool(), Some(true));
+
+        // A profile that fails validation changes nothing
+        let mut invalid = profile;
+        invalid.config["ui"].as_table_mut().unwrap().insert("tab_width".to_string(), toml::Value::Integer(0));
+        let before = user.clone();
+        let error = import(invalid, &mut user, |_, _, _| true).unwrap_err();
+        assert!(error.to_string().contains("tab_width must be greater than 0"), "{}", error);
+        assert_eq!(user, before);
+
+        let mut output = Vec::new();
+        let answer = prompt_override("ui.tab_width", &toml::Value::Integer(2), &toml::Value::Integer(8), &mut io::Cursor::new("y\n"), &mut output).unwrap();
+        assert!(answer);
+        assert_eq!(String::from_utf8(output).unwrap(), "ui.tab_width: replace 2 with 8? [y/N] ");
+    }
+
+    #[test]
+    fn test_secrets_redacted_and_newer_versions_refused() {
+        let mut table: toml::Table = toml::from_str("[notifications]\nwebhook_token = \"abc123\"\nquiet_hours = \"12:00-13:00\"\n").unwrap();
+        redact(&mut table, &toml::Table::new(), "");
+        assert_eq!(table["notifications"]["webhook_token"].as_str(), Some(SECRET_PLACEHOLDER));
+        assert_eq!(table["notifications"]["quiet_hours"].as_str(), Some("12:00-13:00"));
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("future.toml");
+        fs::write(&path, "version = 2\n").unwrap();
+        assert!(Profile::load(&path).unwrap_err().to_string().contains("version 2 profile"));
+    }
+}
@@ -0,0 +1,647 @@
+//! Applying reviewed hunks to file contents
+//!
+//! Hunks are matched against the target content using their context and
+//! removed lines. An exact apply requires the hunk to sit where its header
+//! says (adjusted for previously applied hunks); a fuzzy apply searches the
+//! surrounding content for the nearest matching position instead.
+//!
+//! [`ReviewSession::apply_decisions`] uses this to write a session's
+//! decisions back to the working tree, starting from the pre-change copies
+//! [`ReviewSession::write_snapshots`] saved when there are any.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use crate::core::{FileEventKind, SnapshotStore};
+use crate::core::paths::SafePathResolver;
+use crate::security::SecretScanner;
+use super::{DiffHunk, ReviewAction, ReviewSession, ReviewableChange};
+
+/// Outcome of applying review decisions to a tree
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApplyReport {
+    pub files_modified: Vec<PathBuf>,
+    pub hunks_applied: usize,
+    pub hunks_reverted: usize,
+    pub conflicts: Vec<ApplyConflict>,
+}
+
+/// A file or hunk that could not be applied and was left untouched
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApplyConflict {
+    pub path: PathBuf,
+    pub hunk_id: Option<String>,
+    pub reason: String,
+}
+
+impl ApplyReport {
+    pub fn has_conflicts(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+
+    pub fn add_conflict(&mut self, path: PathBuf, hunk_id: Option<String>, reason: impl Into<String>) {
+        self.conflicts.push(ApplyConflict {
+            path,
+            hunk_id,
+            reason: reason.into(),
+        });
+    }
+}
+
+/// Result of applying a set of hunks to a single file's content
+#[derive(Debug, Clone)]
+pub struct HunkApplyOutcome {
+    pub content: String,
+    pub applied: Vec<String>,
+    /// (hunk_id, reason) for every hunk that did not match
+    pub conflicts: Vec<(String, String)>,
+}
+
+/// Apply `hunks` (in file order) to `content`.
+///
+/// Hunks that do not match are reported in the outcome and skipped; the
+/// remaining hunks are still applied. Lines the hunks leave alone keep
+/// their own line endings; added lines get the file's.
+pub fn apply_hunks(content: &str, hunks: &[&DiffHunk], fuzzy: bool) -> HunkApplyOutcome {
+    // Each line with its terminator
+    let mut lines: Vec<String> = content.split_inclusive('\n').map(|l| l.to_string()).collect();
+    let ending = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut applied = Vec::new();
+    let mut conflicts = Vec::new();
+    // Shift between the hunk's recorded old position and the current buffer
+    let mut offset: isize = 0;
+
+    for hunk in hunks {
+        let old_lines = expected_lines(hunk);
+        let expected = (hunk.old_start.saturating_sub(1) as isize + offset).max(0) as usize;
+
+        let position = if matches_at(&lines, &old_lines, expected) {
+            Some(expected)
+        } else if fuzzy {
+            find_nearest_match(&lines, &old_lines, expected)
+        } else {
+            None
+        };
+
+        match position {
+            Some(pos) => {
+                let new_lines = replacement_lines(hunk, &lines[pos..pos + old_lines.len()], ending);
+                lines.splice(pos..pos + old_lines.len(), new_lines.iter().cloned());
+                offset += new_lines.len() as isize - old_lines.len() as isize;
+                // Fuzzy matches may land away from the header position
+                offset += pos as isize - expected as isize;
+                applied.push(hunk.id.clone());
+            }
+            None => {
+                let reason = if fuzzy {
+                    "Hunk context not found in file".to_string()
+                } else {
+                    format!("Hunk context does not match at line {}", expected + 1)
+                };
+                conflicts.push((hunk.id.clone(), reason));
+            }
+        }
+    }
+
+    // A line that was last may no longer be, and the last line ends the
+    // way the file did
+    let last = lines.len().saturating_sub(1);
+    for line in &mut lines[..last] {
+        if !line.ends_with('\n') {
+            line.push_str(ending);
+        }
+    }
+    if let Some(line) = lines.last_mut() {
+        if !content.ends_with('\n') {
+            line.truncate(line.trim_end_matches(['\r', '\n']).len());
+        } else if !line.ends_with('\n') {
+            line.push_str(ending);
+        }
+    }
+
+    HunkApplyOutcome {
+        content: lines.concat(),
+        applied,
+        conflicts,
+    }
+}
+
+/// Content of one file with the session's decisions applied
+struct DecidedFile {
+    content: String,
+    kept: usize,
+    reverted: usize,
+    conflicts: Vec<(String, String)>,
+}
+
+impl ReviewSession {
+    /// Write the decisions of this session to the files under `base_dir`.
+    ///
+    /// Rejected hunks are reverted and every other hunk stays as it is on
+    /// disk. A file changed once in the session is rebuilt from its
+    /// pre-change copy under `snapshot_path` when there is one and the file
+    /// still equals that copy with the change applied; otherwise rejected
+    /// hunks are reverted in place, newest change first. Hunks whose context
+    /// no longer matches the file are reported as conflicts and left as they
+    /// are. Only files with rejected hunks are touched.
+    pub fn apply_decisions(&self, base_dir: &Path) -> Result<ApplyReport> {
+        let resolver = SafePathResolver::new(base_dir)
+            .with_context(|| format!("Invalid review root: {}", base_dir.display()))?;
+        let mut report = ApplyReport::default();
+
+        // Every change of each file with a rejected hunk, in session order
+        let mut files: Vec<(&Path, Vec<&ReviewableChange>)> = Vec::new();
+        for change in &self.changes {
+            match files.iter_mut().find(|(path, _)| *path == change.event.path) {
+                Some((_, changes)) => changes.push(change),
+                None => files.push((&change.event.path, vec![change])),
+            }
+        }
+        files.retain(|(_, changes)| changes.iter().any(|change| change.hunks.iter().any(|hunk| is_rejected(change, hunk))));
+
+        for (path, changes) in files {
+            let Some(relative) = resolver.relativize(path) else {
+                report.add_conflict(path.to_path_buf(), None, "Outside of the review root");
+                continue;
+            };
+            let target = match resolver.resolve(&relative) {
+                Ok(target) => target,
+                Err(err) => {
+                    report.add_conflict(relative, None, err.to_string());
+                    continue;
+                }
+            };
+            let last = changes[changes.len() - 1];
+            let current = match fs::read_to_string(&target) {
+                Ok(content) => content,
+                // A rejected deletion is undone from the lines it removed
+                Err(err) if err.kind() == io::ErrorKind::NotFound && matches!(last.event.kind, FileEventKind::Deleted) => String::new(),
+                Err(err) => {
+                    report.add_conflict(relative, None, format!("Cannot read file: {}", err));
+                    continue;
+                }
+            };
+
+            let snapshot = self.snapshot_path.as_ref().map(|dir| base_dir.join(dir).join(&relative));
+            let decided = match (changes.as_slice(), snapshot) {
+                ([change], Some(snapshot)) => rebuild_from_snapshot(change, &snapshot, &current),
+                _ => None,
+            };
+            let mut decided = decided.unwrap_or_else(|| revert_in_place(&changes, &current));
+
+            for (hunk_id, reason) in decided.conflicts.drain(..) {
+                report.add_conflict(relative.clone(), Some(hunk_id), reason);
+            }
+            if decided.reverted == 0 {
+                continue;
+            }
+
+            let created = matches!(changes[0].event.kind, FileEventKind::Created);
+            if decided.content.is_empty() && created {
+                // Every line of a new file was rejected
+                fs::remove_file(&target)
+                    .with_context(|| format!("Failed to remove {}", target.display()))?;
+            } else {
+                if current.is_empty() && !decided.content.ends_with('\n') {
+                    decided.content.push('\n');
+                }
+                if let Some(dir) = target.parent() {
+                    fs::create_dir_all(dir)
+                        .with_context(|| format!("Failed to create {}", dir.display()))?;
+                }
+                fs::write(&target, &decided.content)
+                    .with_context(|| format!("Failed to write {}", target.display()))?;
+            }
+            report.files_modified.push(relative);
+            report.hunks_applied += decided.kept;
+            report.hunks_reverted += decided.reverted;
+        }
+
+        Ok(report)
+    }
+}
+
+impl ReviewSession {
+    /// Save the content each changed file had when watching began, as kept
+    /// by `store`, under `.watchdiff/snapshots/<id>` in `base_dir`, and use
+    /// them as the session's pre-change copies. Copies already saved are
+    /// kept. Returns how many files were written.
+    ///
+    /// Content in which `secrets` finds a secret is not saved: a masked copy
+    /// would write the mask back into the file on rejection, so such files
+    /// are rebuilt from their current content instead.
+    pub fn write_snapshots(&mut self, store: &SnapshotStore, secrets: Option<&SecretScanner>, base_dir: &Path) -> Result<usize> {
+        let resolver = SafePathResolver::new(base_dir)
+            .with_context(|| format!("Invalid review root: {}", base_dir.display()))?;
+        let dir = self.snapshot_path.clone().unwrap_or_else(|| Self::snapshot_dir(&self.id));
+        let mut written = 0;
+        for change in &self.changes {
+            let Some(baseline) = store.get_baseline(&change.event.path) else { continue };
+            if secrets.is_some_and(|secrets| secrets.holds_secret(&change.event.path, &baseline)) {
+                continue;
+            }
+            let Some(relative) = resolver.relativize(&change.event.path) else { continue };
+            let target = resolver
+                .resolve(dir.join(&relative))
+                .with_context(|| format!("Invalid snapshot path for {}", relative.display()))?;
+            if target.exists() {
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create {}", parent.display()))?;
+            }
+            fs::write(&target, baseline.as_bytes())
+                .with_context(|| format!("Failed to write {}", target.display()))?;
+            written += 1;
+        }
+        if written > 0 {
+            self.snapshot_path = Some(dir);
+        }
+        Ok(written)
+    }
+
+    /// Where the pre-change copies of session `id` go, relative to the review root
+    pub fn snapshot_dir(id: &str) -> PathBuf {
+        Path::new(".watchdiff").join("sessions").join("snapshots").join(id)
+    }
+}
+
+fn is_rejected(change: &ReviewableChange, hunk: &DiffHunk) -> bool {
+    matches!(change.review_actions.get(&hunk.id), Some(ReviewAction::Reject))
+}
+
+/// Apply the kept hunks of `change` to the pre-change copy at `snapshot`.
+/// `None` if there is no copy, or the file no longer equals the copy with
+/// the whole change applied.
+fn rebuild_from_snapshot(change: &ReviewableChange, snapshot: &Path, current: &str) -> Option<DecidedFile> {
+    if change.event.masked {
+        return None;
+    }
+    let base = fs::read_to_string(snapshot).ok()?;
+    let hunks: Vec<DiffHunk> = change.hunks.iter().map(|hunk| change.full_hunk(hunk)).collect();
+    let changed = apply_hunks(&base, &hunks.iter().collect::<Vec<_>>(), false);
+    if !changed.conflicts.is_empty() || changed.content != current {
+        return None;
+    }
+
+    let kept: Vec<&DiffHunk> = hunks.iter().filter(|hunk| !is_rejected(change, hunk)).collect();
+    let outcome = apply_hunks(&base, &kept, false);
+    outcome.conflicts.is_empty().then(|| DecidedFile {
+        content: outcome.content,
+        kept: kept.len(),
+        reverted: hunks.len() - kept.len(),
+        conflicts: Vec::new(),
+    })
+}
+
+/// Revert the rejected hunks of `changes` from `current`, newest change first.
+/// Hunks of a change whose secrets were masked are left as conflicts: their
+/// lines hold the mask, not the values the file had.
+fn revert_in_place(changes: &[&ReviewableChange], current: &str) -> DecidedFile {
+    let mut decided = DecidedFile { content: current.to_string(), kept: 0, reverted: 0, conflicts: Vec::new() };
+    for (newer, change) in changes.iter().rev().enumerate() {
+        let rejected: Vec<&DiffHunk> = change.hunks.iter().filter(|hunk| is_rejected(change, hunk)).collect();
+        if change.event.masked {
+            decided.kept += change.hunks.len() - rejected.len();
+            decided.conflicts.extend(
+                rejected
+                    .iter()
+                    .map(|hunk| (hunk.id.clone(), "Holds masked secrets; revert it by hand".to_string())),
+            );
+            continue;
+        }
+        let reversed: Vec<DiffHunk> = rejected.iter().map(|hunk| reverse_hunk(&change.full_hunk(hunk))).collect();
+        // Later changes may have moved the lines of earlier ones
+        let outcome = apply_hunks(&decided.content, &reversed.iter().collect::<Vec<_>>(), newer > 0);
+        decided.kept += change.hunks.len() - reversed.len();
+        decided.reverted += outcome.applied.len();
+        decided.conflicts.extend(outcome.conflicts);
+        decided.content = outcome.content;
+    }
+    decided
+}
+
+/// Build the inverse of a hunk, turning additions into removals and vice versa
+pub fn reverse_hunk(hunk: &DiffHunk) -> DiffHunk {
+    let lines = hunk
+        .lines
+        .iter()
+        .map(|line| {
+            if let Some(rest) = line.strip_prefix('+') {
+                format!("-{}", rest)
+            } else if let Some(rest) = line.strip_prefix('-') {
+                format!("+{}", rest)
+            } else {
+                line.clone()
+            }
+        })
+        .collect();
+
+    DiffHunk {
+        id: hunk.id.clone(),
+        hunk_type: hunk.hunk_type.clone(),
+        old_start: hunk.new_start,
+        old_count: hunk.new_count,
+        new_start: hunk.old_start,
+        new_count: hunk.old_count,
+        lines,
+        header: format!(
+            "@@ -{},{} +{},{} @@",
+            hunk.new_start, hunk.new_count, hunk.old_start, hunk.old_count
+        ),
+    }
+}
+
+/// The lines a hunk expects: its context and removed lines
+fn expected_lines(hunk: &DiffHunk) -> Vec<String> {
+    hunk.lines
+        .iter()
+        .filter(|line| !line.starts_with('+') && !line.starts_with('\\'))
+        .map(|line| line.strip_prefix('-').or_else(|| line.strip_prefix(' ')).unwrap_or(line).to_string())
+        .collect()
+}
+
+/// The lines a hunk produces from `matched`, the lines it expects as found
+/// in the file: context lines are kept as found and added lines end with
+/// `ending`
+fn replacement_lines(hunk: &DiffHunk, matched: &[String], ending: &str) -> Vec<String> {
+    let mut matched = matched.iter();
+    let mut lines = Vec::new();
+    for line in &hunk.lines {
+        if let Some(rest) = line.strip_prefix('+') {
+            lines.push(format!("{}{}", rest.trim_end_matches('\r'), ending));
+        } else if line.starts_with('-') {
+            matched.next();
+        } else if line.starts_with('\\') {
+            // "\ No newline at end of file"
+            continue;
+        } else {
+            lines.extend(matched.next().cloned());
+        }
+    }
+    lines
+}
+
+fn matches_at(lines: &[String], expected: &[String], pos: usize) -> bool {
+    if pos + expected.len() > lines.len() {
+        return false;
+    }
+
+    // Diff lines are stored with trailing whitespace trimmed
+    lines[pos..pos + expected.len()]
+        .iter()
+        .zip(expected)
+        .all(|(actual, wanted)| actual.trim_end() == wanted.trim_end())
+}
+
+fn find_nearest_match(lines: &[String], expected: &[String], around: usize) -> Option<usize> {
+    if expected.is_empty() {
+        return Some(around.min(lines.len()));
+    }
+    if expected.len() > lines.len() {
+        return None;
+    }
+
+    let last = lines.len() - expected.len();
+    for distance in 0..=last.max(around) {
+        if let Some(pos) = around.checked_sub(distance) {
+            if pos <= last && matches_at(lines, expected, pos) {
+                return Some(pos);
+            }
+        }
+        let pos = around + distance;
+        if distance > 0 && pos <= last && matches_at(lines, expected, pos) {
+            return Some(pos);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::review::HunkType;
+
+    fn hunk(id: &str, old_start: usize, new_start: usize, lines: &[&str]) -> DiffHunk {
+        let old_count = lines.iter().filter(|l| !l.starts_with('+')).count();
+        let new_count = lines.iter().filter(|l| !l.starts_with('-')).count();
+        DiffHunk {
+            id: id.to_string(),
+            hunk_type: HunkType::Modification,
+            old_start,
+            old_count,
+            new_start,
+            new_count,
+            lines: lines.iter().map(|l| l.to_string()).collect(),
+            header: format!("@@ -{},{} +{},{} @@", old_start, old_count, new_start, new_count),
+        }
+    }
+
+    #[test]
+    fn test_apply_hunks_exact() {
+        let content = "a\nb\nc\nd\ne\n";
+        let h = hunk("hunk_0", 2, 2, &[" b", "-c", "+C", "+C2", " d"]);
+
+        let outcome = apply_hunks(content, &[&h], false);
+
+        assert_eq!(outcome.content, "a\nb\nC\nC2\nd\ne\n");
+        assert_eq!(outcome.applied, vec!["hunk_0"]);
+        assert!(outcome.conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_apply_hunks_tracks_offset_between_hunks() {
+        let content = "1\n2\n3\n4\n5\n6\n";
+        let first = hunk("hunk_0", 1, 1, &["+0", " 1"]);
+        let second = hunk("hunk_1", 5, 6, &[" 5", "-6", "+six"]);
+
+        let outcome = apply_hunks(content, &[&first, &second], false);
+
+        assert_eq!(outcome.content, "0\n1\n2\n3\n4\n5\nsix\n");
+        assert_eq!(outcome.applied.len(), 2);
+    }
+
+    #[test]
+    fn test_apply_hunks_conflict_leaves_content_untouched() {
+        let content = "x\ny\nz\n";
+        let h = hunk("hunk_0", 1, 1, &[" a", "-b", "+B"]);
+
+        let outcome = apply_hunks(content, &[&h], false);
+
+        assert_eq!(outcome.content, content);
+        assert!(outcome.applied.is_empty());
+        assert_eq!(outcome.conflicts.len(), 1);
+        assert_eq!(outcome.conflicts[0].0, "hunk_0");
+    }
+
+    #[test]
+    fn test_fuzzy_apply_finds_shifted_context() {
+        let content = "new header\nextra\na\nb\nc\n";
+        let h = hunk("hunk_0", 1, 1, &[" a", "-b", "+B", " c"]);
+
+        assert!(apply_hunks(content, &[&h], false).conflicts.len() == 1);
+
+        let outcome = apply_hunks(content, &[&h], true);
+        assert_eq!(outcome.content, "new header\nextra\na\nB\nc\n");
+        assert!(outcome.conflicts.is_empty());
+    }
+
+    const BEFORE: &str = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\n";
+    const AFTER: &str = "ONE\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nTEN\n";
+
+    /// A session with one reviewed change of `name` from BEFORE to AFTER, now on disk
+    fn reviewed(dir: &Path, name: &str, reject: &[usize]) -> ReviewSession {
+        let path = dir.join(name);
+        fs::write(&path, AFTER).unwrap();
+        let result = crate::diff::DiffGenerator::default().generate(BEFORE, AFTER);
+        let diff = crate::diff::DiffFormatter::format_unified(&result, "a", "b");
+        let mut change = ReviewableChange::new(crate::core::FileEvent::new(path, FileEventKind::Modified).with_diff(diff));
+        assert_eq!(change.hunks.len(), 2);
+        change.accept_all();
+        for &index in reject {
+            let id = change.hunks[index].id.clone();
+            change.reject_hunk(&id);
+        }
+        let mut session = ReviewSession::new();
+        session.changes.push(change);
+        session
+    }
+
+    #[test]
+    fn test_apply_decisions_reverts_rejected_hunks() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let session = reviewed(dir.path(), "notes.txt", &[1]);
+        let untouched = reviewed(dir.path(), "kept.txt", &[]);
+
+        let report = session.apply_decisions(dir.path()).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("notes.txt")).unwrap(), AFTER.replace("TEN", "ten"));
+        assert_eq!(report.files_modified, vec![PathBuf::from("notes.txt")]);
+        assert_eq!((report.hunks_applied, report.hunks_reverted, report.has_conflicts()), (1, 1, false));
+        assert_eq!(untouched.apply_decisions(dir.path()).unwrap(), ApplyReport::default());
+    }
+
+    #[test]
+    fn test_apply_decisions_reports_conflicts_and_leaves_them() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let session = reviewed(dir.path(), "notes.txt", &[0, 1]);
+        // The first line was edited again after the review
+        let edited = AFTER.replace("ONE", "uno");
+        fs::write(dir.path().join("notes.txt"), &edited).unwrap();
+
+        let report = session.apply_decisions(dir.path()).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("notes.txt")).unwrap(), edited.replace("TEN", "ten"));
+        assert_eq!(report.hunks_reverted, 1);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].hunk_id.as_deref(), Some(session.changes[0].hunks[0].id.as_str()));
+    }
+
+    #[test]
+    fn test_apply_decisions_rebuilds_from_snapshot() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut session = reviewed(dir.path(), "notes.txt", &[0]);
+        fs::create_dir(dir.path().join("before")).unwrap();
+        fs::write(dir.path().join("before/notes.txt"), BEFORE).unwrap();
+        session.snapshot_path = Some(PathBuf::from("before"));
+
+        let report = session.apply_decisions(dir.path()).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("notes.txt")).unwrap(), BEFORE.replace("ten", "TEN"));
+        assert_eq!((report.hunks_applied, report.hunks_reverted), (1, 1));
+    }
+
+    #[test]
+    fn test_write_snapshots_from_startup_baselines() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut session = reviewed(dir.path(), "notes.txt", &[0]);
+        session.changes.push(ReviewableChange::new(crate::core::FileEvent::new(dir.path().join("new.txt"), FileEventKind::Created)));
+        let mut store = SnapshotStore::default();
+        store.seed(dir.path().join("notes.txt"), BEFORE);
+        store.record(&dir.path().join("notes.txt"), AFTER);
+        store.record(&dir.path().join("new.txt"), "new\n");
+
+        // Only files that existed at startup have a pre-change copy
+        assert_eq!(session.write_snapshots(&store, None, dir.path()).unwrap(), 1);
+        let snapshot_dir = ReviewSession::snapshot_dir(&session.id);
+        assert_eq!(session.snapshot_path.as_ref(), Some(&snapshot_dir));
+        assert_eq!(fs::read_to_string(dir.path().join(&snapshot_dir).join("notes.txt")).unwrap(), BEFORE);
+        assert!(!dir.path().join(&snapshot_dir).join("new.txt").exists());
+        assert_eq!(session.write_snapshots(&store, None, dir.path()).unwrap(), 0);
+
+        let report = session.apply_decisions(dir.path()).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("notes.txt")).unwrap(), BEFORE.replace("ten", "TEN"));
+        assert_eq!((report.hunks_applied, report.hunks_reverted), (1, 1));
+    }
+
+    #[test]
+    fn test_write_snapshots_leaves_out_baselines_with_secrets() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut session = reviewed(dir.path(), "notes.txt", &[0]);
+        let env = dir.path().join(".env");
+        session.changes.push(ReviewableChange::new(crate::core::FileEvent::new(env.clone(), FileEventKind::Modified)));
+        let mut store = SnapshotStore::default();
+        store.seed(dir.path().join("notes.txt"), BEFORE);
+        store.seed(env.clone(), "AWS_KEY=AKIAZ7QK2M4T9VXW3BNC\n");
+
+        let scanner = SecretScanner::default();
+        assert_eq!(session.write_snapshots(&store, Some(&scanner), dir.path()).unwrap(), 1);
+        let snapshot_dir = dir.path().join(ReviewSession::snapshot_dir(&session.id));
+        assert!(snapshot_dir.join("notes.txt").exists());
+        assert!(!snapshot_dir.join(".env").exists());
+    }
+
+    #[test]
+    fn test_rejecting_a_masked_hunk_never_writes_the_mask() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(".env");
+        let before = "AWS_KEY=AKIAZ7QK2M4T9VXW3BNC\nREGION=eu-west-1\n";
+        let after = "REGION=eu-west-1\n";
+        fs::write(&path, after).unwrap();
+        let result = crate::diff::DiffGenerator::default().generate(before, after);
+        let diff = crate::diff::DiffFormatter::format_unified(&result, "a", "b");
+        let mut event = crate::core::FileEvent::new(path.clone(), FileEventKind::Modified).with_diff(diff);
+        SecretScanner::default().check(&mut event, false);
+        assert!(event.masked);
+
+        let mut change = ReviewableChange::new(event);
+        change.reject_all();
+        let hunk_id = change.hunks[0].id.clone();
+        let mut session = ReviewSession::new();
+        session.changes.push(change);
+
+        let report = session.apply_decisions(dir.path()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), after);
+        assert_eq!(report.hunks_reverted, 0);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].hunk_id.as_deref(), Some(hunk_id.as_str()));
+    }
+
+    #[test]
+    fn test_apply_keeps_crlf_line_endings() {
+        let original = "a\r\nb\r\nc\r\n";
+        let h = hunk("hunk_0", 1, 1, &[" a", "-b", "+B", "+B2", " c"]);
+
+        let changed = apply_hunks(original, &[&h], false).content;
+        assert_eq!(changed, "a\r\nB\r\nB2\r\nc\r\n");
+        let restored = apply_hunks(&changed, &[&reverse_hunk(&h)], false).content;
+        assert_eq!(restored, original);
+
+        // Lines the hunk does not touch keep their own endings
+        let mixed = "a\nb\r\nc";
+        let h = hunk("hunk_0", 2, 2, &["-b", "+B"]);
+        assert_eq!(apply_hunks(mixed, &[&h], false).content, "a\nB\r\nc");
+    }
+
+    #[test]
+    fn test_reverse_hunk_restores_original() {
+        let original = "a\nb\nc\n";
+        let h = hunk("hunk_0", 1, 1, &[" a", "-b", "+B", " c"]);
+
+        let changed = apply_hunks(original, &[&h], false).content;
+        let restored = apply_hunks(&changed, &[&reverse_hunk(&h)], false).content;
+
+        assert_eq!(restored, original);
+    }
+}
@@ -0,0 +1,217 @@
+//! Hints about hunks of one change that only work together
+//!
+//! Accepting a hunk while rejecting one it relies on easily leaves a file
+//! that does not even parse. The analysis is deliberately language-light: a
+//! definition is an identifier following a keyword such as `fn`, `let`,
+//! `function` or `class`, and a use is any identifier token. A hunk depends
+//! on another when its added lines use a name the other hunk introduces, or
+//! its removed lines use a name whose definition the other hunk removes.
+
+use std::collections::BTreeSet;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use super::{DiffHunk, ReviewAction};
+
+/// How one hunk relies on another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DependencyKind {
+    /// Uses a name the other hunk adds
+    UsesAdded,
+    /// Was written against a name the other hunk removes
+    UsesRemoved,
+}
+
+/// Hunk `hunk` relies on hunk `on` for `name`; both are indices into the
+/// change's hunks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HunkDependency {
+    pub hunk: usize,
+    pub on: usize,
+    pub name: String,
+    pub kind: DependencyKind,
+}
+
+impl HunkDependency {
+    /// A warning if the decisions for the two hunks split them apart
+    pub fn warning(&self, hunk_action: &ReviewAction, on_action: &ReviewAction) -> Option<String> {
+        let verb = |action: &ReviewAction| match action {
+            ReviewAction::Accept => Some("accepted"),
+            ReviewAction::Reject => Some("rejected"),
+            ReviewAction::Skip => Some("skipped"),
+            ReviewAction::Pending | ReviewAction::Defer => None,
+        };
+        let lands = |action: &ReviewAction| matches!(action, ReviewAction::Accept);
+
+        match self.kind {
+            // The use lands without the definition
+            DependencyKind::UsesAdded if lands(hunk_action) && !lands(on_action) => Some(format!(
+                "hunk {} uses `{}` added in hunk {}, which you {}",
+                self.hunk + 1, self.name, self.on + 1, verb(on_action)?
+            )),
+            // The definition goes away but the old use stays
+            DependencyKind::UsesRemoved if !lands(hunk_action) && lands(on_action) => Some(format!(
+                "hunk {}, which you {}, still uses `{}` removed in hunk {}",
+                self.hunk + 1, verb(hunk_action)?, self.name, self.on + 1
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Names defined and used by the lines of one hunk
+#[derive(Debug, Default)]
+struct HunkNames {
+    /// Defined in added lines and not in removed ones
+    introduced: BTreeSet<String>,
+    /// Defined in removed lines and not in added ones
+    removed: BTreeSet<String>,
+    /// Defined anywhere in the hunk, context included
+    defined: BTreeSet<String>,
+    used_in_added: BTreeSet<String>,
+    used_in_removed: BTreeSet<String>,
+}
+
+impl HunkNames {
+    fn from_hunk(hunk: &DiffHunk) -> Self {
+        let mut names = Self::default();
+        let mut added_defs = BTreeSet::new();
+        let mut removed_defs = BTreeSet::new();
+
+        for line in &hunk.lines {
+            let (marker, code) = line.split_at(line.chars().next().map_or(0, char::len_utf8));
+            let defs = definitions(code);
+            names.defined.extend(defs.iter().cloned());
+            match marker {
+                "+" => {
+                    added_defs.extend(defs);
+                    names.used_in_added.extend(identifiers(code));
+                }
+                "-" => {
+                    removed_defs.extend(defs);
+                    names.used_in_removed.extend(identifiers(code));
+                }
+                _ => {}
+            }
+        }
+
+        names.introduced = added_defs.difference(&removed_defs).cloned().collect();
+        names.removed = removed_defs.difference(&added_defs).cloned().collect();
+        names
+    }
+}
+
+/// Names introduced by definition keywords on `code`
+fn definitions(code: &str) -> Vec<String> {
+    static DEFINITION: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let definition = DEFINITION.get_or_init(|| {
+        Regex::new(
+            r"\b(?:fn|let|const|static|struct|enum|trait|type|mod|union|function|class|interface|var|def)\s+(?:mut\s+)?([A-Za-z_$][A-Za-z0-9_$]*)",
+        )
+        .unwrap()
+    });
+    definition.captures_iter(code).map(|c| c[1].to_string()).collect()
+}
+
+/// Every identifier token on `code`
+fn identifiers(code: &str) -> impl Iterator<Item = String> + '_ {
+    static IDENTIFIER: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let identifier = IDENTIFIER.get_or_init(|| Regex::new(r"[A-Za-z_$][A-Za-z0-9_$]*").unwrap());
+    identifier.find_iter(code).map(|m| m.as_str().to_string())
+}
+
+/// Cross-hunk dependencies within one change, in hunk order
+pub fn hunk_dependencies(hunks: &[DiffHunk]) -> Vec<HunkDependency> {
+    let names: Vec<HunkNames> = hunks.iter().map(HunkNames::from_hunk).collect();
+    let mut dependencies = Vec::new();
+
+    for (hunk, user) in names.iter().enumerate() {
+        for (on, definer) in names.iter().enumerate() {
+            if hunk == on {
+                continue;
+            }
+            let relies_on = |defs: &BTreeSet<String>, uses: &BTreeSet<String>, kind| {
+                defs.intersection(uses)
+                    // A hunk with its own definition does not rely on another's
+                    .filter(|name| !user.defined.contains(*name))
+                    .map(|name| HunkDependency { hunk, on, name: name.clone(), kind })
+                    .collect::<Vec<_>>()
+            };
+            dependencies.extend(relies_on(&definer.introduced, &user.used_in_added, DependencyKind::UsesAdded));
+            dependencies.extend(relies_on(&definer.removed, &user.used_in_removed, DependencyKind::UsesRemoved));
+        }
+    }
+    dependencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::review::ReviewableChange;
+    use crate::core::{FileEvent, FileEventKind};
+    use std::path::PathBuf;
+
+    fn change(path: &str, diff: &str) -> ReviewableChange {
+        ReviewableChange::new(FileEvent::new(PathBuf::from(path), FileEventKind::Modified).with_diff(diff.to_string()))
+    }
+
+    #[test]
+    fn test_rust_hunk_using_a_rejected_function() {
+        let diff = "--- a\n+++ b\n\
+            @@ -1,2 +1,6 @@\n use std::fs;\n+\n+fn parse_config(text: &str) -> Config {\n+    toml::from_str(text).unwrap()\n+}\n \n\
+            @@ -20,3 +24,3 @@\n fn main() {\n-    let config = Config::default();\n+    let config = parse_config(&fs::read_to_string(\"a.toml\").unwrap());\n     run(config);\n\
+            @@ -40,2 +44,2 @@\n fn helper() {\n-    let x = 1;\n+    let x = 2;\n";
+        let mut change = change("src/main.rs", diff);
+        assert_eq!(
+            change.dependencies,
+            vec![HunkDependency { hunk: 1, on: 0, name: "parse_config".to_string(), kind: DependencyKind::UsesAdded }]
+        );
+
+        let ids: Vec<String> = change.hunks.iter().map(|h| h.id.clone()).collect();
+        change.accept_hunk(&ids[1]);
+        assert!(change.dependency_warnings().is_empty());
+        change.reject_hunk(&ids[0]);
+        assert_eq!(
+            change.dependency_warnings(),
+            vec!["hunk 2 uses `parse_config` added in hunk 1, which you rejected"]
+        );
+        assert!(change.is_potentially_inconsistent());
+
+        // Flagged in the stats and the exported patch
+        let mut session = crate::review::ReviewSession::new();
+        session.changes.push(change.clone());
+        assert_eq!(session.get_review_stats().inconsistent, 1);
+        assert!(session
+            .accepted_patch(std::path::Path::new(""))
+            .starts_with("# warning: hunk 2 uses `parse_config` added in hunk 1, which you rejected\n--- a/src/main.rs\n"));
+
+        // Taking both, or neither, is consistent
+        change.reject_hunk(&ids[1]);
+        assert!(!change.is_potentially_inconsistent());
+    }
+
+    #[test]
+    fn test_typescript_rename_split_across_hunks() {
+        let diff = "--- a\n+++ b\n\
+            @@ -1,3 +1,3 @@\n import { api } from './api';\n-export function loadUser(id: string) {\n+export function fetchUser(id: string) {\n   return api.get(id);\n\
+            @@ -30,3 +30,4 @@\n const view = () => {\n-  const user = loadUser(currentId);\n+  const user = fetchUser(currentId);\n+  const avatar = new AvatarCache();\n   render(user);\n\
+            @@ -50,1 +51,3 @@\n-// cache goes here\n+class AvatarCache {\n+  size = 10;\n+}\n";
+        let mut change = change("src/user.ts", diff);
+
+        let expected = vec![
+            HunkDependency { hunk: 1, on: 0, name: "fetchUser".to_string(), kind: DependencyKind::UsesAdded },
+            HunkDependency { hunk: 1, on: 0, name: "loadUser".to_string(), kind: DependencyKind::UsesRemoved },
+            HunkDependency { hunk: 1, on: 2, name: "AvatarCache".to_string(), kind: DependencyKind::UsesAdded },
+        ];
+        assert_eq!(change.dependencies, expected);
+
+        // Accepting the rename but rejecting the call site keeps the old call
+        let ids: Vec<String> = change.hunks.iter().map(|h| h.id.clone()).collect();
+        change.accept_hunk(&ids[0]);
+        change.reject_hunk(&ids[1]);
+        change.accept_hunk(&ids[2]);
+        assert_eq!(
+            change.dependency_warnings(),
+            vec!["hunk 2, which you rejected, still uses `loadUser` removed in hunk 1"]
+        );
+    }
+}
@@ -6,12 +6,24 @@ use crate::core::{FileEvent, ConfidenceLevel, ChangeOrigin};
 use serde::{Deserialize, Serialize};
 use regex::Regex;
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub mod apply;
+pub mod dependencies;
+pub mod selection;
+pub mod sessions;
+
+pub use apply::{ApplyConflict, ApplyReport};
+pub use dependencies::{DependencyKind, HunkDependency};
+pub use selection::ReviewSelection;
+pub use sessions::SessionSummary;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ReviewAction {
     Accept,
     Reject,
     Skip,
     Pending,
+    /// Needs discussion with a teammate before a decision is made
+    Defer,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -34,6 +46,21 @@ pub struct DiffHunk {
     pub header: String,
 }
 
+impl DiffHunk {
+    /// `self` with its type set from what its lines add and remove
+    fn with_type_from_lines(mut self) -> Self {
+        let adds = self.lines.iter().any(|line| line.starts_with('+'));
+        let removes = self.lines.iter().any(|line| line.starts_with('-'));
+        self.hunk_type = match (adds, removes) {
+            (true, false) => HunkType::Addition,
+            (false, true) => HunkType::Deletion,
+            (true, true) => HunkType::Modification,
+            (false, false) => HunkType::Context,
+        };
+        self
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReviewableChange {
     pub event: FileEvent,
@@ -41,6 +68,12 @@ pub struct ReviewableChange {
     pub review_actions: HashMap<String, ReviewAction>, // hunk_id -> action
     pub overall_action: ReviewAction,
     pub reviewed_at: Option<std::time::SystemTime>,
+    /// Hunks that rely on names other hunks of this change add or remove
+    #[serde(default)]
+    pub dependencies: Vec<HunkDependency>,
+    /// Reviewer comments, by hunk id
+    #[serde(default)]
+    pub comments: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,11 +83,29 @@ pub struct ReviewSession {
     pub changes: Vec<ReviewableChange>,
     pub current_change_index: usize,
     pub current_hunk_index: usize,
+    #[serde(default)]
     pub filters: ReviewFilters,
     pub snapshot_path: Option<PathBuf>,
+    /// Human-friendly name; the id keeps identifying the file after a rename
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Decisions made through the session, newest last, for undo
+    #[serde(default)]
+    pub undo_history: Vec<ReviewHistoryEntry>,
+    /// Decisions undone since the last new one, most recently undone last
+    #[serde(default)]
+    pub redo_history: Vec<ReviewHistoryEntry>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Decisions kept for undo; the oldest are dropped beyond this
+pub const REVIEW_HISTORY_LIMIT: usize = 200;
+
+/// Fields missing from sessions saved by older versions take their defaults
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Default)]
+#[serde(default)]
 pub struct ReviewFilters {
     pub confidence_level: Option<ConfidenceLevel>,
     pub confidence_threshold: Option<f32>, // 0.0 - 1.0
@@ -68,6 +119,199 @@ pub struct ReviewFilters {
     pub max_hunks: Option<usize>,
     pub exclude_reviewed: bool,
     pub show_only_pending: bool,
+    /// Only changes touching lines the coverage report marks as not run
+    pub uncovered_only: bool,
+}
+
+/// One field of [`ReviewFilters`], as edited in the TUI's filter editor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReviewFilterField {
+    RiskyOnly,
+    AiOnly,
+    PendingOnly,
+    ExcludeReviewed,
+    UncoveredOnly,
+    ConfidenceLevel,
+    ConfidenceThreshold,
+    Origin,
+    FilePattern,
+    FileRegex,
+    Batch,
+    MinHunks,
+    MaxHunks,
+}
+
+impl ReviewFilterField {
+    pub const ALL: [ReviewFilterField; 13] = [
+        Self::RiskyOnly, Self::AiOnly, Self::PendingOnly, Self::ExcludeReviewed, Self::UncoveredOnly,
+        Self::ConfidenceLevel, Self::ConfidenceThreshold, Self::Origin, Self::FilePattern,
+        Self::FileRegex, Self::Batch, Self::MinHunks, Self::MaxHunks,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::RiskyOnly => "Risky only",
+            Self::AiOnly => "AI changes only",
+            Self::PendingOnly => "Pending only",
+            Self::ExcludeReviewed => "Exclude reviewed",
+            Self::UncoveredOnly => "Uncovered only",
+            Self::ConfidenceLevel => "Confidence level",
+            Self::ConfidenceThreshold => "Min confidence",
+            Self::Origin => "Origin",
+            Self::FilePattern => "File name contains",
+            Self::FileRegex => "Path regex",
+            Self::Batch => "Batch",
+            Self::MinHunks => "Min hunks",
+            Self::MaxHunks => "Max hunks",
+        }
+    }
+
+    /// Whether the field is typed in rather than cycled
+    pub fn is_text(self) -> bool {
+        matches!(self, Self::FilePattern | Self::FileRegex | Self::Batch)
+    }
+
+    /// Whether the field is on or off, with no value to show
+    pub fn is_flag(self) -> bool {
+        matches!(
+            self,
+            Self::RiskyOnly | Self::AiOnly | Self::PendingOnly | Self::ExcludeReviewed | Self::UncoveredOnly
+        )
+    }
+}
+
+/// The value after `current` in `steps`, wrapping back to `None`
+fn next_step<T: Clone + PartialEq>(current: &Option<T>, steps: &[T]) -> Option<T> {
+    match current {
+        None => steps.first().cloned(),
+        Some(value) => steps
+            .iter()
+            .position(|step| step == value)
+            .and_then(|i| steps.get(i + 1))
+            .cloned(),
+    }
+}
+
+impl ReviewFilters {
+    /// Turn every filter off
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Toggle a flag or step an option field to its next value, wrapping
+    /// back to off. Text fields are left alone; see [`Self::set_text`].
+    pub fn cycle(&mut self, field: ReviewFilterField) {
+        use ReviewFilterField as F;
+        match field {
+            F::RiskyOnly => self.show_only_risky = !self.show_only_risky,
+            F::AiOnly => self.show_only_ai_changes = !self.show_only_ai_changes,
+            F::PendingOnly => self.show_only_pending = !self.show_only_pending,
+            F::ExcludeReviewed => self.exclude_reviewed = !self.exclude_reviewed,
+            F::UncoveredOnly => self.uncovered_only = !self.uncovered_only,
+            F::ConfidenceLevel => {
+                self.confidence_level = next_step(
+                    &self.confidence_level,
+                    &[ConfidenceLevel::Safe, ConfidenceLevel::Review, ConfidenceLevel::Risky],
+                );
+            }
+            F::ConfidenceThreshold => {
+                self.confidence_threshold = next_step(&self.confidence_threshold, &[0.25, 0.5, 0.75]);
+            }
+            F::Origin => {
+                // Origins match by kind, so the names are placeholders
+                self.origin_filter = next_step(
+                    &self.origin_filter,
+                    &[
+                        ChangeOrigin::Human,
+                        ChangeOrigin::AIAgent { tool_name: String::new(), process_id: None },
+                        ChangeOrigin::Tool { name: String::new() },
+                        ChangeOrigin::Unknown,
+                    ],
+                );
+            }
+            F::MinHunks => self.min_hunks = next_step(&self.min_hunks, &[2, 5, 10]),
+            F::MaxHunks => self.max_hunks = next_step(&self.max_hunks, &[1, 3, 5]),
+            F::FilePattern | F::FileRegex | F::Batch => {}
+        }
+    }
+
+    /// Turn one filter off
+    pub fn reset(&mut self, field: ReviewFilterField) {
+        use ReviewFilterField as F;
+        let default = Self::default();
+        match field {
+            F::RiskyOnly => self.show_only_risky = default.show_only_risky,
+            F::AiOnly => self.show_only_ai_changes = default.show_only_ai_changes,
+            F::PendingOnly => self.show_only_pending = default.show_only_pending,
+            F::ExcludeReviewed => self.exclude_reviewed = default.exclude_reviewed,
+            F::UncoveredOnly => self.uncovered_only = default.uncovered_only,
+            F::ConfidenceLevel => self.confidence_level = default.confidence_level,
+            F::ConfidenceThreshold => self.confidence_threshold = default.confidence_threshold,
+            F::Origin => self.origin_filter = default.origin_filter,
+            F::FilePattern => self.file_pattern = default.file_pattern,
+            F::FileRegex => self.file_regex = default.file_regex,
+            F::Batch => self.batch_filter = default.batch_filter,
+            F::MinHunks => self.min_hunks = default.min_hunks,
+            F::MaxHunks => self.max_hunks = default.max_hunks,
+        }
+    }
+
+    /// The text of a text field
+    pub fn text(&self, field: ReviewFilterField) -> Option<&str> {
+        match field {
+            ReviewFilterField::FilePattern => self.file_pattern.as_deref(),
+            ReviewFilterField::FileRegex => self.file_regex.as_deref(),
+            ReviewFilterField::Batch => self.batch_filter.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Set a text field; blank text turns it off
+    pub fn set_text(&mut self, field: ReviewFilterField, text: &str) {
+        let text = text.trim();
+        let value = (!text.is_empty()).then(|| text.to_string());
+        match field {
+            ReviewFilterField::FilePattern => self.file_pattern = value,
+            ReviewFilterField::FileRegex => self.file_regex = value,
+            ReviewFilterField::Batch => self.batch_filter = value,
+            _ => {}
+        }
+    }
+
+    /// The field's value as shown in the filter editor, `None` when off
+    pub fn value(&self, field: ReviewFilterField) -> Option<String> {
+        use ReviewFilterField as F;
+        let flag = |on: bool| on.then(|| "on".to_string());
+        match field {
+            F::RiskyOnly => flag(self.show_only_risky),
+            F::AiOnly => flag(self.show_only_ai_changes),
+            F::PendingOnly => flag(self.show_only_pending),
+            F::ExcludeReviewed => flag(self.exclude_reviewed),
+            F::UncoveredOnly => flag(self.uncovered_only),
+            F::ConfidenceLevel => self.confidence_level.as_ref().map(|level| format!("{:?}", level)),
+            F::ConfidenceThreshold => self.confidence_threshold.map(|t| format!("{:.0}%", t * 100.0)),
+            F::Origin => self.origin_filter.as_ref().map(|origin| match origin {
+                ChangeOrigin::Human => "Human".to_string(),
+                ChangeOrigin::AIAgent { .. } => "AI".to_string(),
+                ChangeOrigin::Tool { .. } => "Tool".to_string(),
+                ChangeOrigin::Unknown => "Unknown".to_string(),
+            }),
+            F::FilePattern | F::FileRegex | F::Batch => self.text(field).map(str::to_string),
+            F::MinHunks => self.min_hunks.map(|n| n.to_string()),
+            F::MaxHunks => self.max_hunks.map(|n| n.to_string()),
+        }
+    }
+
+    /// `Label: value` of every filter that is on
+    pub fn active(&self) -> Vec<String> {
+        ReviewFilterField::ALL
+            .iter()
+            .filter_map(|&field| {
+                let value = self.value(field)?;
+                Some(if field.is_flag() { field.label().to_string() } else { format!("{}: {}", field.label(), value) })
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -78,6 +322,44 @@ pub struct ReviewFilterPreset {
     pub shortcut_key: Option<char>,
 }
 
+/// The decisions on some hunks of a change, with the change's overall
+/// decision, as they were on one side of a [`ReviewHistoryEntry`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecisionState {
+    pub actions: HashMap<String, ReviewAction>,
+    pub overall_action: ReviewAction,
+    pub reviewed_at: Option<std::time::SystemTime>,
+}
+
+impl DecisionState {
+    fn capture(change: &ReviewableChange, hunk_ids: &[String]) -> Self {
+        Self {
+            actions: hunk_ids
+                .iter()
+                .map(|id| (id.clone(), change.review_actions.get(id).cloned().unwrap_or(ReviewAction::Pending)))
+                .collect(),
+            overall_action: change.overall_action.clone(),
+            reviewed_at: change.reviewed_at,
+        }
+    }
+
+    fn restore(&self, change: &mut ReviewableChange) {
+        change.review_actions.extend(self.actions.iter().map(|(id, action)| (id.clone(), action.clone())));
+        change.overall_action = self.overall_action.clone();
+        change.reviewed_at = self.reviewed_at;
+    }
+}
+
+/// One decision made through a [`ReviewSession`]: which hunks of which
+/// change it touched, and their decisions before and after
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReviewHistoryEntry {
+    pub change_index: usize,
+    pub hunk_ids: Vec<String>,
+    pub before: DecisionState,
+    pub after: DecisionState,
+}
+
 #[derive(Debug, Clone)]
 pub enum ReviewNavigationAction {
     NextChange,
@@ -89,28 +371,15 @@ pub enum ReviewNavigationAction {
     JumpToFile(PathBuf),
 }
 
-impl Default for ReviewFilters {
-    fn default() -> Self {
-        Self {
-            confidence_level: None,
-            confidence_threshold: None,
-            show_only_risky: false,
-            show_only_ai_changes: false,
-            origin_filter: None,
-            file_pattern: None,
-            file_regex: None,
-            batch_filter: None,
-            min_hunks: None,
-            max_hunks: None,
-            exclude_reviewed: false,
-            show_only_pending: false,
-        }
-    }
-}
 
 impl ReviewableChange {
     pub fn new(event: FileEvent) -> Self {
         let hunks = Self::parse_diff_into_hunks(&event.diff);
+        Self::with_hunks(event, hunks)
+    }
+    
+    /// Build a change from already-parsed hunks
+    pub fn with_hunks(event: FileEvent, hunks: Vec<DiffHunk>) -> Self {
         let mut review_actions = HashMap::new();
         
         // Initialize all hunks as pending
@@ -120,13 +389,62 @@ impl ReviewableChange {
         
         Self {
             event,
+            dependencies: dependencies::hunk_dependencies(&hunks),
             hunks,
             review_actions,
             overall_action: ReviewAction::Pending,
             reviewed_at: None,
+            comments: HashMap::new(),
         }
     }
     
+    /// Dependencies whose hunks were decided differently, each with a
+    /// warning such as "hunk 3 uses `parse_config` added in hunk 1, which
+    /// you rejected"
+    pub fn split_dependencies(&self) -> impl Iterator<Item = (&HunkDependency, String)> + '_ {
+        let action = |index: usize| {
+            self.hunks
+                .get(index)
+                .and_then(|h| self.review_actions.get(&h.id))
+                .unwrap_or(&ReviewAction::Pending)
+        };
+        self.dependencies
+            .iter()
+            .filter_map(move |dep| dep.warning(action(dep.hunk), action(dep.on)).map(|warning| (dep, warning)))
+    }
+    
+    pub fn dependency_warnings(&self) -> Vec<String> {
+        self.split_dependencies().map(|(_, warning)| warning).collect()
+    }
+    
+    /// Whether the decisions split hunks that depend on each other
+    pub fn is_potentially_inconsistent(&self) -> bool {
+        !self.dependency_warnings().is_empty()
+    }
+    
+    /// `hunk` with any truncated long lines restored, for writing patches
+    pub fn full_hunk(&self, hunk: &DiffHunk) -> DiffHunk {
+        let mut hunk = hunk.clone();
+        if let Some(ref long_lines) = self.event.long_lines {
+            for line in &mut hunk.lines {
+                if let Some(full) = long_lines.full_line(line) {
+                    *line = full.to_string();
+                }
+            }
+        }
+        hunk
+    }
+
+    /// Comment on a hunk; a blank comment removes it
+    pub fn set_comment(&mut self, hunk_id: &str, comment: &str) {
+        let comment = comment.trim();
+        if comment.is_empty() {
+            self.comments.remove(hunk_id);
+        } else {
+            self.comments.insert(hunk_id.to_string(), comment.to_string());
+        }
+    }
+
     pub fn accept_hunk(&mut self, hunk_id: &str) {
         self.review_actions.insert(hunk_id.to_string(), ReviewAction::Accept);
         self.update_overall_action();
@@ -142,6 +460,11 @@ impl ReviewableChange {
         self.update_overall_action();
     }
     
+    pub fn defer_hunk(&mut self, hunk_id: &str) {
+        self.review_actions.insert(hunk_id.to_string(), ReviewAction::Defer);
+        self.update_overall_action();
+    }
+    
     pub fn accept_all(&mut self) {
         for hunk in &self.hunks {
             self.review_actions.insert(hunk.id.clone(), ReviewAction::Accept);
@@ -158,6 +481,14 @@ impl ReviewableChange {
         self.reviewed_at = Some(std::time::SystemTime::now());
     }
     
+    /// Record the same decision for several hunks at once
+    pub fn apply_to_hunks(&mut self, hunk_ids: &[String], action: ReviewAction) {
+        for hunk_id in hunk_ids {
+            self.review_actions.insert(hunk_id.clone(), action.clone());
+        }
+        self.update_overall_action();
+    }
+    
     fn update_overall_action(&mut self) {
         let actions: Vec<&ReviewAction> = self.review_actions.values().collect();
         
@@ -170,9 +501,24 @@ impl ReviewableChange {
         } else if actions.iter().all(|&a| matches!(a, ReviewAction::Skip)) {
             self.overall_action = ReviewAction::Skip;
             self.reviewed_at = Some(std::time::SystemTime::now());
-        } else if actions.iter().any(|&a| !matches!(a, ReviewAction::Pending)) {
+        } else if actions.iter().any(|&a| matches!(a, ReviewAction::Defer)) {
+            // Any hunk needing discussion flags the whole change
+            self.overall_action = ReviewAction::Defer;
+            self.reviewed_at = Some(std::time::SystemTime::now());
+        } else if actions.iter().any(|&a| matches!(a, ReviewAction::Pending)) {
             // Partially reviewed
             self.overall_action = ReviewAction::Pending;
+        } else {
+            // Every hunk decided, but not the same way. The change still
+            // lands in part if any hunk was accepted.
+            self.overall_action = if actions.iter().any(|&a| matches!(a, ReviewAction::Accept)) {
+                ReviewAction::Accept
+            } else if actions.iter().any(|&a| matches!(a, ReviewAction::Reject)) {
+                ReviewAction::Reject
+            } else {
+                ReviewAction::Skip
+            };
+            self.reviewed_at = Some(std::time::SystemTime::now());
         }
     }
     
@@ -280,117 +626,87 @@ impl ReviewableChange {
             return false;
         }
         
+        // Check coverage filter
+        if filter.uncovered_only && !self.event.coverage.is_some_and(|c| c.uncovered > 0) {
+            return false;
+        }
+        
         true
     }
     
     fn matches_origin_filter(&self, required_origin: &ChangeOrigin) -> bool {
-        match (required_origin, &self.event.origin) {
-            (ChangeOrigin::Human, ChangeOrigin::Human) => true,
-            (ChangeOrigin::AIAgent { .. }, ChangeOrigin::AIAgent { .. }) => true,
-            (ChangeOrigin::Tool { .. }, ChangeOrigin::Tool { .. }) => true,
-            (ChangeOrigin::Unknown, ChangeOrigin::Unknown) => true,
-            _ => false,
-        }
+        matches!(
+            (required_origin, &self.event.origin),
+            (ChangeOrigin::Human, ChangeOrigin::Human)
+                | (ChangeOrigin::AIAgent { .. }, ChangeOrigin::AIAgent { .. })
+                | (ChangeOrigin::Tool { .. }, ChangeOrigin::Tool { .. })
+                | (ChangeOrigin::Unknown, ChangeOrigin::Unknown)
+        )
     }
     
-    fn parse_diff_into_hunks(diff: &Option<String>) -> Vec<DiffHunk> {
+    pub(crate) fn parse_diff_into_hunks(diff: &Option<String>) -> Vec<DiffHunk> {
         let mut hunks = Vec::new();
         
         if let Some(diff_content) = diff {
-            let lines: Vec<&str> = diff_content.lines().collect();
             let mut current_hunk: Option<DiffHunk> = None;
-            let mut hunk_counter = 0;
             
-            for line in lines {
-                if line.starts_with("@@") {
-                    // Save previous hunk if exists
-                    if let Some(hunk) = current_hunk.take() {
-                        hunks.push(hunk);
+            for line in diff_content.lines() {
+                match crate::diff::DiffHunk::parse_header(line) {
+                    Some((old_start, old_count, new_start, new_count)) => {
+                        // Save previous hunk if exists
+                        if let Some(hunk) = current_hunk.take() {
+                            hunks.push(hunk.with_type_from_lines());
+                        }
+                        
+                        current_hunk = Some(DiffHunk {
+                            id: format!("hunk_{}", hunks.len()),
+                            hunk_type: HunkType::Modification,
+                            old_start,
+                            old_count,
+                            new_start,
+                            new_count,
+                            lines: Vec::new(),
+                            header: line.to_string(),
+                        });
                     }
-                    
-                    // Parse hunk header: @@ -old_start,old_count +new_start,new_count @@
-                    let hunk_id = format!("hunk_{}", hunk_counter);
-                    hunk_counter += 1;
-                    
-                    let (old_start, old_count, new_start, new_count) = 
-                        Self::parse_hunk_header(line);
-                    
-                    current_hunk = Some(DiffHunk {
-                        id: hunk_id,
-                        hunk_type: HunkType::Modification,
-                        old_start,
-                        old_count,
-                        new_start,
-                        new_count,
-                        lines: Vec::new(),
-                        header: line.to_string(),
-                    });
-                } else if let Some(ref mut hunk) = current_hunk {
-                    hunk.lines.push(line.to_string());
-                    
-                    // Determine hunk type based on content
-                    if line.starts_with('+') && !line.starts_with("+++") {
-                        hunk.hunk_type = HunkType::Addition;
-                    } else if line.starts_with('-') && !line.starts_with("---") {
-                        hunk.hunk_type = HunkType::Deletion;
+                    None => {
+                        if let Some(ref mut hunk) = current_hunk {
+                            hunk.lines.push(line.to_string());
+                        }
                     }
                 }
             }
             
             // Save last hunk
             if let Some(hunk) = current_hunk {
-                hunks.push(hunk);
+                hunks.push(hunk.with_type_from_lines());
             }
         }
         
         hunks
     }
-    
-    fn parse_hunk_header(header: &str) -> (usize, usize, usize, usize) {
-        // Parse @@ -old_start,old_count +new_start,new_count @@
-        let parts: Vec<&str> = header.split_whitespace().collect();
-        let mut old_start = 1;
-        let mut old_count = 1;
-        let mut new_start = 1;
-        let mut new_count = 1;
-        
-        for part in parts {
-            if part.starts_with('-') {
-                let old_part = &part[1..];
-                if let Some((start, count)) = old_part.split_once(',') {
-                    old_start = start.parse().unwrap_or(1);
-                    old_count = count.parse().unwrap_or(1);
-                } else {
-                    old_start = old_part.parse().unwrap_or(1);
-                }
-            } else if part.starts_with('+') {
-                let new_part = &part[1..];
-                if let Some((start, count)) = new_part.split_once(',') {
-                    new_start = start.parse().unwrap_or(1);
-                    new_count = count.parse().unwrap_or(1);
-                } else {
-                    new_start = new_part.parse().unwrap_or(1);
-                }
-            }
-        }
-        
-        (old_start, old_count, new_start, new_count)
+}
+
+impl Default for ReviewSession {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl ReviewSession {
     pub fn new() -> Self {
         Self {
-            id: format!("session_{}", std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_millis()),
+            id: format!("session_{}", chrono::Utc::now().timestamp_millis()),
             started_at: std::time::SystemTime::now(),
             changes: Vec::new(),
             current_change_index: 0,
             current_hunk_index: 0,
             filters: ReviewFilters::default(),
             snapshot_path: None,
+            name: None,
+            description: None,
+            undo_history: Vec::new(),
+            redo_history: Vec::new(),
         }
     }
     
@@ -404,15 +720,41 @@ impl ReviewSession {
             current_hunk_index: 0,
             filters: ReviewFilters::default(),
             snapshot_path: None,
+            name: None,
+            description: None,
+            undo_history: Vec::new(),
+            redo_history: Vec::new(),
         }
     }
     
+    /// Create a new session named after `root` and today's date, unique
+    /// among the sessions saved under `base_dir`
+    pub fn named_for(root: &std::path::Path, base_dir: &std::path::Path) -> Self {
+        let now = chrono::Local::now();
+        let seed = now.timestamp_subsec_nanos() as u64 ^ std::process::id() as u64;
+        let name = sessions::generate_session_name(root, now.date_naive(), seed, |id| {
+            sessions::session_exists(base_dir, id)
+        });
+        let mut session = Self::with_id(name.clone());
+        session.name = Some(name);
+        session
+    }
+    
+    /// Name shown to the user, falling back to the id
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.id)
+    }
+    
+    pub(crate) fn session_path(base_dir: &std::path::Path, session_id: &str) -> PathBuf {
+        base_dir.join(".watchdiff").join("sessions").join(format!("{}.json", session_id))
+    }
+    
     /// Save session to disk
     pub fn save_to_disk(&self, base_dir: &std::path::Path) -> io::Result<PathBuf> {
-        let sessions_dir = base_dir.join(".watchdiff").join("sessions");
-        fs::create_dir_all(&sessions_dir)?;
-        
-        let session_file = sessions_dir.join(format!("{}.json", self.id));
+        let session_file = Self::session_path(base_dir, &self.id);
+        if let Some(sessions_dir) = session_file.parent() {
+            fs::create_dir_all(sessions_dir)?;
+        }
         let session_json = serde_json::to_string_pretty(self)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
         
@@ -422,7 +764,7 @@ impl ReviewSession {
     
     /// Load session from disk
     pub fn load_from_disk(base_dir: &std::path::Path, session_id: &str) -> io::Result<Self> {
-        let session_file = base_dir.join(".watchdiff").join("sessions").join(format!("{}.json", session_id));
+        let session_file = Self::session_path(base_dir, session_id);
         let session_json = fs::read_to_string(session_file)?;
         let session: ReviewSession = serde_json::from_str(&session_json)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
@@ -449,12 +791,28 @@ impl ReviewSession {
         Ok(sessions)
     }
     
-    /// Delete a saved session
+    /// Load a saved session, change it and write it back under the same id
+    pub fn update_saved(
+        base_dir: &std::path::Path,
+        session_id: &str,
+        update: impl FnOnce(&mut ReviewSession),
+    ) -> io::Result<Self> {
+        let mut session = Self::load_from_disk(base_dir, session_id)?;
+        update(&mut session);
+        session.save_to_disk(base_dir)?;
+        Ok(session)
+    }
+    
+    /// Delete a saved session and its pre-change copies
     pub fn delete_session(base_dir: &std::path::Path, session_id: &str) -> io::Result<()> {
-        let session_file = base_dir.join(".watchdiff").join("sessions").join(format!("{}.json", session_id));
+        let session_file = Self::session_path(base_dir, session_id);
         if session_file.exists() {
             fs::remove_file(session_file)?;
         }
+        let snapshot_dir = base_dir.join(Self::snapshot_dir(session_id));
+        if snapshot_dir.exists() {
+            fs::remove_dir_all(snapshot_dir)?;
+        }
         Ok(())
     }
     
@@ -515,6 +873,16 @@ impl ReviewSession {
                 },
                 shortcut_key: Some('5'),
             },
+            ReviewFilterPreset {
+                name: "Untested Changes".to_string(),
+                description: "Show changes touching lines without test coverage".to_string(),
+                filters: ReviewFilters {
+                    uncovered_only: true,
+                    exclude_reviewed: true,
+                    ..Default::default()
+                },
+                shortcut_key: Some('6'),
+            },
         ]
     }
     
@@ -523,6 +891,49 @@ impl ReviewSession {
         self.changes.push(reviewable);
     }
     
+    /// Add events that are not yet part of the session, reusing cached hunk
+    /// parses. `events` are newest first, like the event log, and new changes
+    /// go in front of the existing ones in the same order. Existing changes
+    /// keep their review decisions unless their diff changed, which starts
+    /// them over. Returns the number of changes added.
+    pub fn sync_events<'a>(
+        &mut self,
+        events: impl IntoIterator<Item = &'a FileEvent>,
+        hunk_cache: &mut crate::performance::HunkParseCache,
+    ) -> usize {
+        let known: HashMap<(PathBuf, std::time::SystemTime), usize> = self.changes
+            .iter()
+            .enumerate()
+            .map(|(i, c)| ((c.event.path.clone(), c.event.timestamp), i))
+            .collect();
+        
+        let mut added = Vec::new();
+        for event in events {
+            match known.get(&(event.path.clone(), event.timestamp)) {
+                Some(&index) if self.changes[index].event.diff == event.diff => {}
+                Some(&index) => {
+                    let hunks = hunk_cache.get_or_parse(event);
+                    self.changes[index] = ReviewableChange::with_hunks(event.clone(), hunks);
+                }
+                None => {
+                    let hunks = hunk_cache.get_or_parse(event);
+                    added.push(ReviewableChange::with_hunks(event.clone(), hunks));
+                }
+            }
+        }
+        
+        // Indices into the existing changes move down past the new ones
+        let count = added.len();
+        if count > 0 && !self.changes.is_empty() {
+            self.current_change_index += count;
+        }
+        for entry in self.undo_history.iter_mut().chain(self.redo_history.iter_mut()) {
+            entry.change_index += count;
+        }
+        self.changes.splice(0..0, added);
+        count
+    }
+    
     pub fn get_current_change(&self) -> Option<&ReviewableChange> {
         self.changes.get(self.current_change_index)
     }
@@ -530,6 +941,78 @@ impl ReviewSession {
     pub fn get_current_change_mut(&mut self) -> Option<&mut ReviewableChange> {
         self.changes.get_mut(self.current_change_index)
     }
+
+    /// Accept one hunk of the change at `change_index`, recording it for undo.
+    /// The same methods on [`ReviewableChange`] are not recorded.
+    pub fn accept_hunk(&mut self, change_index: usize, hunk_id: &str) -> bool {
+        self.apply_to_hunks(change_index, &[hunk_id.to_string()], ReviewAction::Accept)
+    }
+
+    pub fn reject_hunk(&mut self, change_index: usize, hunk_id: &str) -> bool {
+        self.apply_to_hunks(change_index, &[hunk_id.to_string()], ReviewAction::Reject)
+    }
+
+    pub fn skip_hunk(&mut self, change_index: usize, hunk_id: &str) -> bool {
+        self.apply_to_hunks(change_index, &[hunk_id.to_string()], ReviewAction::Skip)
+    }
+
+    /// Decide several hunks of a change at once, as one undo step
+    pub fn apply_to_hunks(&mut self, change_index: usize, hunk_ids: &[String], action: ReviewAction) -> bool {
+        self.record(change_index, hunk_ids.to_vec(), |change| change.apply_to_hunks(hunk_ids, action))
+    }
+
+    pub fn accept_all(&mut self, change_index: usize) -> bool {
+        let hunk_ids = self.hunk_ids(change_index);
+        self.record(change_index, hunk_ids, ReviewableChange::accept_all)
+    }
+
+    pub fn reject_all(&mut self, change_index: usize) -> bool {
+        let hunk_ids = self.hunk_ids(change_index);
+        self.record(change_index, hunk_ids, ReviewableChange::reject_all)
+    }
+
+    fn hunk_ids(&self, change_index: usize) -> Vec<String> {
+        self.changes
+            .get(change_index)
+            .map(|change| change.hunks.iter().map(|hunk| hunk.id.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Apply `decide` to the change at `change_index` and push it onto the
+    /// undo history, dropping anything undone before; false if there is no
+    /// such change
+    fn record(&mut self, change_index: usize, hunk_ids: Vec<String>, decide: impl FnOnce(&mut ReviewableChange)) -> bool {
+        let Some(change) = self.changes.get_mut(change_index) else { return false };
+        let before = DecisionState::capture(change, &hunk_ids);
+        decide(change);
+        let after = DecisionState::capture(change, &hunk_ids);
+        self.undo_history.push(ReviewHistoryEntry { change_index, hunk_ids, before, after });
+        if self.undo_history.len() > REVIEW_HISTORY_LIMIT {
+            self.undo_history.drain(..self.undo_history.len() - REVIEW_HISTORY_LIMIT);
+        }
+        self.redo_history.clear();
+        true
+    }
+
+    /// Put back the decisions from before the last recorded one; returns the
+    /// index of the change it touched
+    pub fn undo_last_action(&mut self) -> Option<usize> {
+        let entry = self.undo_history.pop()?;
+        let change_index = entry.change_index;
+        entry.before.restore(self.changes.get_mut(change_index)?);
+        self.redo_history.push(entry);
+        Some(change_index)
+    }
+
+    /// Make the last undone decision again; returns the index of the change
+    /// it touched
+    pub fn redo_last_action(&mut self) -> Option<usize> {
+        let entry = self.redo_history.pop()?;
+        let change_index = entry.change_index;
+        entry.after.restore(self.changes.get_mut(change_index)?);
+        self.undo_history.push(entry);
+        Some(change_index)
+    }
     
     pub fn get_current_hunk(&self) -> Option<&DiffHunk> {
         self.get_current_change()?
@@ -626,6 +1109,63 @@ impl ReviewSession {
             .collect()
     }
     
+    /// Files with at least one rejected hunk, in review order
+    pub fn rejected_files(&self) -> Vec<&std::path::Path> {
+        let mut files: Vec<&std::path::Path> = Vec::new();
+        for change in &self.changes {
+            let rejected = change.review_actions.values().any(|a| matches!(a, ReviewAction::Reject));
+            if rejected && !files.contains(&change.event.path.as_path()) {
+                files.push(&change.event.path);
+            }
+        }
+        files
+    }
+    
+    /// Unified diff of the accepted hunks, with paths relative to `root`
+    /// where possible. Hunks are renumbered so the patch still applies
+    /// without the hunks that were left out. Files whose decisions split
+    /// dependent hunks are preceded by `# warning:` lines.
+    pub fn accepted_patch(&self, root: &std::path::Path) -> String {
+        let mut patch = String::new();
+        for change in &self.changes {
+            let path = change.event.path.strip_prefix(root).unwrap_or(&change.event.path);
+            let mut header_written = false;
+            // Lines added minus lines removed by the hunks left out so far
+            let mut left_out: isize = 0;
+            
+            for hunk in &change.hunks {
+                if !matches!(change.review_actions.get(&hunk.id), Some(ReviewAction::Accept)) {
+                    left_out += hunk.new_count as isize - hunk.old_count as isize;
+                    continue;
+                }
+                if !header_written {
+                    // Patch tools skip text before a file header
+                    for warning in change.dependency_warnings() {
+                        patch.push_str(&format!("# warning: {}\n", warning));
+                    }
+                    patch.push_str(&format!("--- a/{0}\n+++ b/{0}\n", path.display()));
+                    header_written = true;
+                }
+                let hunk = change.full_hunk(hunk);
+                let new_start = (hunk.new_start as isize - left_out).max(0);
+                patch.push_str(&format!(
+                    "@@ -{},{} +{},{} @@\n",
+                    hunk.old_start, hunk.old_count, new_start, hunk.new_count
+                ));
+                for line in &hunk.lines {
+                    patch.push_str(line);
+                    patch.push('\n');
+                }
+            }
+        }
+        patch
+    }
+    
+    /// Where the accepted patch of session `session_id` is exported
+    pub fn patch_path(base_dir: &std::path::Path, session_id: &str) -> PathBuf {
+        base_dir.join(".watchdiff").join("sessions").join(format!("{}.patch", session_id))
+    }
+    
     pub fn get_review_stats(&self) -> ReviewStats {
         let total = self.changes.len();
         let accepted = self.changes.iter()
@@ -637,7 +1177,13 @@ impl ReviewSession {
         let skipped = self.changes.iter()
             .filter(|c| matches!(c.overall_action, ReviewAction::Skip))
             .count();
-        let pending = total - accepted - rejected - skipped;
+        let deferred = self.changes.iter()
+            .filter(|c| matches!(c.overall_action, ReviewAction::Defer))
+            .count();
+        let pending = total - accepted - rejected - skipped - deferred;
+        let inconsistent = self.changes.iter()
+            .filter(|c| c.is_potentially_inconsistent())
+            .count();
         
         ReviewStats {
             total,
@@ -645,6 +1191,8 @@ impl ReviewSession {
             rejected,
             skipped,
             pending,
+            deferred,
+            inconsistent,
         }
     }
 }
@@ -656,14 +1204,330 @@ pub struct ReviewStats {
     pub rejected: usize,
     pub skipped: usize,
     pub pending: usize,
+    /// Changes flagged for discussion; not counted as done
+    pub deferred: usize,
+    /// Changes whose decisions split hunks that depend on each other
+    pub inconsistent: usize,
 }
 
 impl ReviewStats {
+    /// Number of changes with a final decision
+    pub fn completed(&self) -> usize {
+        self.accepted + self.rejected + self.skipped
+    }
+    
+    /// Every change has a decision or was deferred
+    pub fn is_complete(&self) -> bool {
+        self.total > 0 && self.pending == 0
+    }
+    
     pub fn completion_percentage(&self) -> f32 {
         if self.total == 0 {
             100.0
         } else {
-            ((self.total - self.pending) as f32 / self.total as f32) * 100.0
+            (self.completed() as f32 / self.total as f32) * 100.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::FileEventKind;
+
+    fn change_with_diff(path: &str) -> FileEvent {
+        FileEvent::new(PathBuf::from(path), FileEventKind::Modified)
+            .with_diff("--- a\n+++ b\n@@ -1,1 +1,1 @@\n-old\n+new".to_string())
+    }
+
+    #[test]
+    fn test_hunks_from_formatted_diff_match_line_ranges() {
+        let old: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+        let new = old.replace("line 3\n", "line three\n").replace("line 17\n", "line 17\nline 17b\n");
+        let result = crate::diff::DiffGenerator::default().generate(&old, &new);
+        assert_eq!(result.hunks.len(), 2);
+
+        let text = crate::diff::DiffFormatter::format_unified(&result, "a/notes.txt", "b/notes.txt");
+        let change = ReviewableChange::new(FileEvent::new(PathBuf::from("notes.txt"), FileEventKind::Modified).with_diff(text));
+        let ranges: Vec<_> = change.hunks.iter().map(|h| (h.old_start, h.old_count, h.new_start, h.new_count)).collect();
+        assert_eq!(ranges, vec![(1, 6, 1, 6), (15, 6, 15, 7)]);
+        assert!(matches!(change.hunks[0].hunk_type, HunkType::Modification));
+        assert!(matches!(change.hunks[1].hunk_type, HunkType::Addition));
+        assert_eq!(change.review_actions.len(), 2);
+        for (hunk, from_result) in change.hunks.iter().zip(&result.hunks) {
+            assert_eq!(hunk.header, from_result.header());
+        }
+    }
+
+    #[test]
+    fn test_merged_hunks_review_as_one() {
+        let old: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+        let new = old.replace("line 3\n", "line three\n").replace("line 11\n", "line eleven\n");
+        let result = crate::diff::DiffConfig::new().merge_gap(8).build().generate(&old, &new);
+        assert_eq!(result.hunks.len(), 1);
+
+        let text = crate::diff::DiffFormatter::format_unified(&result, "a/notes.txt", "b/notes.txt");
+        let parsed = ReviewableChange::parse_diff_into_hunks(&Some(text));
+        assert_eq!(parsed.len(), 1);
+        assert_eq!((parsed[0].old_start, parsed[0].old_count), (1, 14));
+    }
+
+    #[test]
+    fn test_hunk_header_section_text_is_not_a_range() {
+        let diff = "--- a\n+++ b\n@@ -10,2 +10,2 @@ let x = -5 + +3;\n-old\n+new\n@@ not a header\n";
+        let hunks = ReviewableChange::parse_diff_into_hunks(&Some(diff.to_string()));
+        assert_eq!(hunks.len(), 1);
+        assert_eq!((hunks[0].old_start, hunks[0].old_count, hunks[0].new_start, hunks[0].new_count), (10, 2, 10, 2));
+        assert_eq!(hunks[0].lines, vec!["-old", "+new", "@@ not a header"]);
+    }
+
+    #[test]
+    fn test_filters_clear_and_deserialize_older_shapes() {
+        // Every field set, so a new field must be added here too
+        let mut filters = ReviewFilters {
+            confidence_level: Some(ConfidenceLevel::Risky),
+            confidence_threshold: Some(0.5),
+            show_only_risky: true,
+            show_only_ai_changes: true,
+            origin_filter: Some(ChangeOrigin::Human),
+            file_pattern: Some("lib".to_string()),
+            file_regex: Some("^src/".to_string()),
+            batch_filter: Some("batch".to_string()),
+            min_hunks: Some(2),
+            max_hunks: Some(5),
+            exclude_reviewed: true,
+            show_only_pending: true,
+            uncovered_only: true,
+        };
+        assert_eq!(filters.active().len(), ReviewFilterField::ALL.len());
+        filters.clear();
+        assert_eq!(filters, ReviewFilters::default());
+        assert!(filters.active().is_empty());
+
+        let partial: ReviewFilters = serde_json::from_str(r#"{ "show_only_risky": true, "min_hunks": 3 }"#).unwrap();
+        assert_eq!(partial, ReviewFilters { show_only_risky: true, min_hunks: Some(3), ..Default::default() });
+
+        // Sessions saved before filters existed
+        let session = serde_json::json!({
+            "id": "old",
+            "started_at": { "secs_since_epoch": 0, "nanos_since_epoch": 0 },
+            "changes": [],
+            "current_change_index": 0,
+            "current_hunk_index": 0,
+            "snapshot_path": null,
+        });
+        let session: ReviewSession = serde_json::from_value(session).unwrap();
+        assert_eq!(session.filters, ReviewFilters::default());
+    }
+
+    #[test]
+    fn test_uncovered_only_filter() {
+        use crate::coverage::ChangeCoverage;
+
+        let untested = ReviewableChange::new(
+            change_with_diff("a.rs").with_coverage(ChangeCoverage { covered: 2, uncovered: 1, unknown: 0 }),
+        );
+        let tested = ReviewableChange::new(
+            change_with_diff("b.rs").with_coverage(ChangeCoverage { covered: 3, uncovered: 0, unknown: 1 }),
+        );
+        let no_report = ReviewableChange::new(change_with_diff("c.rs"));
+
+        let preset = ReviewSession::get_default_presets()
+            .into_iter()
+            .find(|p| p.shortcut_key == Some('6'))
+            .unwrap();
+        assert!(untested.matches_filter(&preset.filters));
+        assert!(!tested.matches_filter(&preset.filters));
+        assert!(!no_report.matches_filter(&preset.filters));
+    }
+
+    #[test]
+    fn test_deferred_change_excluded_from_completion() {
+        let mut session = ReviewSession::new();
+        session.add_change(change_with_diff("a.rs"));
+        session.add_change(change_with_diff("b.rs"));
+
+        session.changes[0].accept_all();
+        let hunk_id = session.changes[1].hunks[0].id.clone();
+        session.changes[1].defer_hunk(&hunk_id);
+
+        assert_eq!(session.changes[1].overall_action, ReviewAction::Defer);
+
+        let stats = session.get_review_stats();
+        assert_eq!(stats.accepted, 1);
+        assert_eq!(stats.deferred, 1);
+        assert_eq!(stats.pending, 0);
+        assert_eq!(stats.completed(), 1);
+        assert_eq!(stats.completion_percentage(), 50.0);
+    }
+
+    #[test]
+    fn test_review_complete_once_nothing_is_pending() {
+        let mut session = ReviewSession::new();
+        assert!(!session.get_review_stats().is_complete());
+
+        let two_hunks = "--- a\n+++ b\n@@ -1,1 +1,2 @@\n-old\n+new\n+extra\n@@ -10,1 +11,1 @@\n-old2\n+new2";
+        session.add_change(FileEvent::new(PathBuf::from("/repo/src/a.rs"), FileEventKind::Modified).with_diff(two_hunks.to_string()));
+        session.add_change(change_with_diff("/repo/src/b.rs"));
+        session.add_change(change_with_diff("/repo/src/c.rs"));
+
+        let first = session.changes[0].hunks[0].id.clone();
+        session.changes[0].reject_hunk(&first);
+        session.changes[1].accept_all();
+        // A partially reviewed change is still pending
+        assert!(!session.get_review_stats().is_complete());
+
+        let second = session.changes[0].hunks[1].id.clone();
+        session.changes[0].accept_hunk(&second);
+        let hunk_id = session.changes[2].hunks[0].id.clone();
+        session.changes[2].defer_hunk(&hunk_id);
+        let stats = session.get_review_stats();
+        assert!(stats.is_complete());
+        // The mixed change counts as accepted but its file is listed as rejected
+        assert_eq!((stats.accepted, stats.rejected, stats.deferred), (2, 0, 1));
+        assert_eq!(session.rejected_files(), vec![std::path::Path::new("/repo/src/a.rs")]);
+
+        // The second hunk moves up by the line the rejected one would have added
+        let patch = session.accepted_patch(std::path::Path::new("/repo"));
+        assert_eq!(
+            patch,
+            "--- a/src/a.rs\n+++ b/src/a.rs\n@@ -10,1 +10,1 @@\n-old2\n+new2\n\
+             --- a/src/b.rs\n+++ b/src/b.rs\n@@ -1,1 +1,1 @@\n-old\n+new\n"
+        );
+    }
+
+    #[test]
+    fn test_deferred_action_survives_save_and_load() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut session = ReviewSession::new();
+        session.add_change(change_with_diff("a.rs"));
+        let hunk_id = session.changes[0].hunks[0].id.clone();
+        session.changes[0].defer_hunk(&hunk_id);
+
+        session.save_to_disk(temp_dir.path()).unwrap();
+        let loaded = ReviewSession::load_from_disk(temp_dir.path(), &session.id).unwrap();
+
+        assert_eq!(loaded.changes[0].overall_action, ReviewAction::Defer);
+        assert_eq!(loaded.changes[0].review_actions[&hunk_id], ReviewAction::Defer);
+    }
+
+    #[test]
+    fn test_undo_and_redo_restore_decisions_across_save_and_load() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let two_hunks = "--- a\n+++ b\n@@ -1,1 +1,2 @@\n-old\n+new\n+extra\n@@ -10,1 +11,1 @@\n-old2\n+new2";
+        let mut session = ReviewSession::new();
+        session.add_change(FileEvent::new(PathBuf::from("a.rs"), FileEventKind::Modified).with_diff(two_hunks.to_string()));
+        session.add_change(change_with_diff("b.rs"));
+        let first = session.changes[0].hunks[0].id.clone();
+        let second = session.changes[0].hunks[1].id.clone();
+
+        assert!(session.accept_hunk(0, &first));
+        assert!(session.reject_hunk(0, &second));
+        let mixed = session.changes[0].clone();
+        assert!(session.accept_all(0));
+        assert_eq!(session.changes[0].overall_action, ReviewAction::Accept);
+        assert!(!session.skip_hunk(5, &first));
+
+        session.save_to_disk(temp_dir.path()).unwrap();
+        let mut session = ReviewSession::load_from_disk(temp_dir.path(), &session.id).unwrap();
+        assert_eq!(session.undo_history.len(), 3);
+
+        assert_eq!(session.undo_last_action(), Some(0));
+        assert_eq!(session.changes[0].review_actions, mixed.review_actions);
+        assert_eq!(session.changes[0].overall_action, mixed.overall_action);
+        assert_eq!(session.changes[0].reviewed_at, mixed.reviewed_at);
+        session.undo_last_action();
+        session.undo_last_action();
+        assert_eq!(session.changes[0].review_actions[&first], ReviewAction::Pending);
+        assert_eq!((session.changes[0].overall_action.clone(), session.changes[0].reviewed_at), (ReviewAction::Pending, None));
+        assert_eq!(session.undo_last_action(), None);
+
+        session.redo_last_action();
+        session.redo_last_action();
+        assert_eq!(session.changes[0].review_actions, mixed.review_actions);
+        assert_eq!(session.changes[0].reviewed_at, mixed.reviewed_at);
+        // A new decision drops what is left to redo
+        session.reject_all(1);
+        assert_eq!(session.redo_last_action(), None);
+
+        for _ in 0..REVIEW_HISTORY_LIMIT + 10 {
+            session.skip_hunk(1, &session.changes[1].hunks[0].id.clone());
         }
+        assert_eq!(session.undo_history.len(), REVIEW_HISTORY_LIMIT);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_actions_without_defer_still_deserialize() {
+        let actions: Vec<ReviewAction> =
+            serde_json::from_str(r#"["Accept","Reject","Skip","Pending"]"#).unwrap();
+        assert_eq!(actions.len(), 4);
+    }
+    fn synthetic_events(count: usize) -> Vec<FileEvent> {
+        (0..count)
+            .map(|i| {
+                FileEvent::new(PathBuf::from(format!("src/file_{}.rs", i)), FileEventKind::Modified)
+                    .with_diff(format!(
+                        "--- a\n+++ b\n@@ -1,3 +1,3 @@\n fn f{i}() {{\n-    old({i});\n+    new({i});\n }}\n@@ -10,1 +10,2 @@\n x\n+y{i}",
+                        i = i
+                    ))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sync_events_reuses_cached_hunks() {
+        let events = synthetic_events(2_000);
+        let mut cache = crate::performance::HunkParseCache::new(4096);
+
+        let mut first = ReviewSession::new();
+        assert_eq!(first.sync_events(&events, &mut cache), 2_000);
+        let mut second = ReviewSession::new();
+        assert_eq!(second.sync_events(&events, &mut cache), 2_000);
+
+        assert_eq!(cache.stats(), (2_000, 2_000));
+        for (change, event) in second.changes.iter().zip(&events) {
+            let eager = ReviewableChange::new(event.clone());
+            assert_eq!(change.hunks.len(), 2);
+            assert_eq!(
+                serde_json::to_string(&change.hunks).unwrap(),
+                serde_json::to_string(&eager.hunks).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_sync_events_keeps_existing_decisions() {
+        let mut events = synthetic_events(3);
+        let mut cache = crate::performance::HunkParseCache::new(16);
+        let mut session = ReviewSession::new();
+        session.sync_events(&events, &mut cache);
+        session.changes[0].accept_all();
+
+        session.current_change_index = 2;
+        assert!(session.reject_all(1));
+
+        // A newer event for the same path is a separate change, in front like in the log
+        let mut newer = change_with_diff("src/file_0.rs");
+        newer.timestamp = events[0].timestamp + std::time::Duration::from_secs(1);
+        events.insert(0, newer);
+
+        assert_eq!(session.sync_events(&events, &mut cache), 1);
+        assert_eq!(session.changes.len(), 4);
+        assert_eq!(session.changes[0].event.timestamp, events[0].timestamp);
+        assert_eq!(session.changes[0].overall_action, ReviewAction::Pending);
+        assert_eq!(session.changes[1].overall_action, ReviewAction::Accept);
+        // The selection and undo still point at the same changes
+        assert_eq!(session.current_change_index, 3);
+        assert_eq!(session.undo_last_action(), Some(2));
+        assert_eq!(session.changes[2].overall_action, ReviewAction::Pending);
+        assert_eq!(cache.stats(), (0, 4));
+
+        // An event whose diff changed is parsed again and starts over
+        session.changes[1].accept_all();
+        events[1].diff = Some("--- a\n+++ b\n@@ -1 +1 @@\n-x\n+z".to_string());
+        assert_eq!(session.sync_events(&events, &mut cache), 0);
+        assert_eq!(session.changes[1].hunks.len(), 1);
+        assert_eq!(session.changes[1].overall_action, ReviewAction::Pending);
+        assert_eq!(cache.stats(), (0, 5));
+    }
+}
@@ -0,0 +1,78 @@
+//! Hunks picked in the review hunk list for a bulk decision
+//!
+//! Lives only in the review UI and is never saved with the session. The
+//! selection belongs to one change; asking about any other change sees an
+//! empty selection, so moving away effectively clears it.
+
+use std::collections::BTreeSet;
+
+/// Toggled hunks plus an optional visual range anchored at one hunk
+#[derive(Debug, Clone, Default)]
+pub struct ReviewSelection {
+    change_index: usize,
+    toggled: BTreeSet<usize>,
+    anchor: Option<usize>,
+}
+
+impl ReviewSelection {
+    /// Flip `hunk` of `change` in or out of the selection
+    pub fn toggle(&mut self, change: usize, hunk: usize) {
+        self.follow(change);
+        if !self.toggled.remove(&hunk) {
+            self.toggled.insert(hunk);
+        }
+    }
+
+    /// Start a range at `hunk` that follows the cursor, or fix the running
+    /// range into the selection
+    pub fn toggle_range(&mut self, change: usize, hunk: usize) {
+        self.follow(change);
+        match self.anchor.take() {
+            Some(anchor) => self.toggled.extend(range(anchor, hunk)),
+            None => self.anchor = Some(hunk),
+        }
+    }
+
+    pub fn in_range_mode(&self, change: usize) -> bool {
+        self.change_index == change && self.anchor.is_some()
+    }
+
+    /// Whether anything is selected in `change`
+    pub fn is_active(&self, change: usize) -> bool {
+        self.change_index == change && (self.anchor.is_some() || !self.toggled.is_empty())
+    }
+
+    pub fn contains(&self, change: usize, hunk: usize, cursor: usize) -> bool {
+        self.change_index == change
+            && (self.toggled.contains(&hunk) || self.anchor.is_some_and(|anchor| range(anchor, cursor).contains(&hunk)))
+    }
+
+    /// Selected hunk indices of `change` in list order, with the running
+    /// range ending at `cursor`
+    pub fn indices(&self, change: usize, cursor: usize) -> Vec<usize> {
+        if self.change_index != change {
+            return Vec::new();
+        }
+        let mut indices = self.toggled.clone();
+        if let Some(anchor) = self.anchor {
+            indices.extend(range(anchor, cursor));
+        }
+        indices.into_iter().collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.toggled.clear();
+        self.anchor = None;
+    }
+
+    fn follow(&mut self, change: usize) {
+        if self.change_index != change {
+            self.clear();
+            self.change_index = change;
+        }
+    }
+}
+
+fn range(a: usize, b: usize) -> std::ops::RangeInclusive<usize> {
+    a.min(b)..=a.max(b)
+}
@@ -0,0 +1,206 @@
+//! Human-friendly names and search for saved review sessions
+//!
+//! New sessions are named after the watch root, the date and a random word
+//! pair, e.g. `bigrepo-apr02-quiet-otter`. The name doubles as the file name
+//! of the session; renaming only changes the name stored inside the file.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+use chrono::NaiveDate;
+use serde::Serialize;
+use super::ReviewSession;
+
+const ADJECTIVES: [&str; 32] = [
+    "amber", "bold", "brave", "bright", "calm", "clever", "cosmic", "crisp",
+    "dapper", "eager", "fancy", "gentle", "golden", "happy", "humble", "jolly",
+    "keen", "lively", "lucky", "mellow", "misty", "nimble", "proud", "quiet",
+    "rapid", "silent", "sleepy", "snowy", "sunny", "swift", "tidy", "witty",
+];
+
+const NOUNS: [&str; 32] = [
+    "badger", "beaver", "bison", "crane", "falcon", "ferret", "finch", "fox",
+    "gecko", "heron", "ibis", "koala", "lemur", "lynx", "marmot", "moose",
+    "newt", "ocelot", "otter", "panda", "puffin", "quokka", "raven", "robin",
+    "salmon", "seal", "sparrow", "tapir", "toucan", "walrus", "wombat", "yak",
+];
+
+/// `<root>-<mmmdd>-<adjective>-<noun>`; `seed` picks the word pair.
+/// Names for which `taken` returns true get `-2`, `-3`, ... appended.
+pub fn generate_session_name(root: &Path, date: NaiveDate, seed: u64, taken: impl Fn(&str) -> bool) -> String {
+    let adjective = ADJECTIVES[(seed % ADJECTIVES.len() as u64) as usize];
+    let noun = NOUNS[(seed / ADJECTIVES.len() as u64 % NOUNS.len() as u64) as usize];
+    let base = format!(
+        "{}-{}-{}-{}",
+        root_slug(root),
+        date.format("%b%d").to_string().to_lowercase(),
+        adjective,
+        noun
+    );
+
+    let mut name = base.clone();
+    let mut counter = 2;
+    while taken(&name) {
+        name = format!("{}-{}", base, counter);
+        counter += 1;
+    }
+    name
+}
+
+/// Lowercase basename of `root` with anything but letters and digits turned into dashes
+fn root_slug(root: &Path) -> String {
+    let name = root.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let slug = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        "watchdiff".to_string()
+    } else {
+        slug
+    }
+}
+
+/// What `sessions list` and the session picker show for a saved session
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    pub id: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub started_at: SystemTime,
+    pub changes: usize,
+}
+
+impl SessionSummary {
+    pub fn from_session(session: &ReviewSession) -> Self {
+        Self {
+            id: session.id.clone(),
+            name: session.name.clone(),
+            description: session.description.clone(),
+            started_at: session.started_at,
+            changes: session.changes.len(),
+        }
+    }
+
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.id)
+    }
+
+    /// How well `query` matches the name, description or id; `None` if it
+    /// matches none of them. An empty query matches everything.
+    pub fn search_score(&self, query: &str) -> Option<i32> {
+        [Some(self.display_name()), self.description.as_deref(), Some(self.id.as_str())]
+            .into_iter()
+            .flatten()
+            .filter_map(|text| fuzzy_score(query, text))
+            .max()
+    }
+
+    /// Summaries of every readable session under `base_dir`, newest first
+    pub fn list(base_dir: &Path) -> io::Result<Vec<SessionSummary>> {
+        let mut summaries = Vec::new();
+        for id in ReviewSession::list_saved_sessions(base_dir)? {
+            match ReviewSession::load_from_disk(base_dir, &id) {
+                Ok(session) => summaries.push(Self::from_session(&session)),
+                Err(err) => tracing::warn!("Skipping unreadable session {}: {}", id, err),
+            }
+        }
+        summaries.sort_by_key(|summary| std::cmp::Reverse(summary.started_at));
+        Ok(summaries)
+    }
+
+    /// Summaries matching `query`, best match first
+    pub fn search(summaries: &[SessionSummary], query: &str) -> Vec<SessionSummary> {
+        let mut matches: Vec<(i32, &SessionSummary)> = summaries
+            .iter()
+            .filter_map(|summary| summary.search_score(query).map(|score| (score, summary)))
+            .collect();
+        // Stable sort keeps newest first among equal scores
+        matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        matches.into_iter().map(|(_, summary)| summary.clone()).collect()
+    }
+}
+
+/// Case-insensitive fuzzy match: substrings score highest, otherwise every
+/// query character must appear in order, with gaps lowering the score
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let query = query.trim().to_lowercase();
+    let text = text.to_lowercase();
+    if query.is_empty() {
+        return Some(0);
+    }
+    if let Some(pos) = text.find(&query) {
+        return Some(1000 - pos.min(500) as i32);
+    }
+
+    let mut score = 0;
+    let mut last_match: Option<usize> = None;
+    let mut chars = text.chars().enumerate();
+    for q in query.chars() {
+        let (idx, _) = chars.by_ref().find(|(_, c)| *c == q)?;
+        score += match last_match {
+            Some(last) if idx == last + 1 => 10,
+            Some(last) => 5 - (idx - last).min(5) as i32,
+            None => 5,
+        };
+        last_match = Some(idx);
+    }
+    Some(score)
+}
+
+/// Whether a session file named `id` already exists under `base_dir`
+pub(crate) fn session_exists(base_dir: &Path, id: &str) -> bool {
+    fs::metadata(ReviewSession::session_path(base_dir, id)).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 4, 2).unwrap()
+    }
+
+    #[test]
+    fn test_generated_names_avoid_existing_sessions() {
+        let dir = TempDir::new().unwrap();
+        let root = Path::new("/work/Big Repo");
+
+        let first = generate_session_name(root, date(), 599, |id| session_exists(dir.path(), id));
+        assert_eq!(first, "big-repo-apr02-quiet-otter");
+
+        for _ in 0..2 {
+            let name = generate_session_name(root, date(), 599, |id| session_exists(dir.path(), id));
+            ReviewSession::with_id(name).save_to_disk(dir.path()).unwrap();
+        }
+        let third = generate_session_name(root, date(), 599, |id| session_exists(dir.path(), id));
+        assert_eq!(third, "big-repo-apr02-quiet-otter-3");
+        assert_eq!(generate_session_name(Path::new("/"), date(), 0, |_| false), "watchdiff-apr02-amber-badger");
+    }
+
+    #[test]
+    fn test_search_matches_names_and_descriptions() {
+        let dir = TempDir::new().unwrap();
+        let mut auth = ReviewSession::with_id("repo-apr02-calm-lynx".to_string());
+        auth.description = Some("Reviewing the OAuth refactor".to_string());
+        auth.save_to_disk(dir.path()).unwrap();
+        let mut renamed = ReviewSession::with_id("session_1712345678901".to_string());
+        renamed.name = Some("parser cleanup".to_string());
+        renamed.save_to_disk(dir.path()).unwrap();
+
+        let summaries = SessionSummary::list(dir.path()).unwrap();
+        let ids = |query: &str| -> Vec<String> {
+            SessionSummary::search(&summaries, query).into_iter().map(|s| s.id).collect()
+        };
+
+        assert_eq!(ids("oauth"), vec!["repo-apr02-calm-lynx"]);
+        // Fuzzy: characters in order across words
+        assert_eq!(ids("prsclnp"), vec!["session_1712345678901"]);
+        assert_eq!(ids("calm"), vec!["repo-apr02-calm-lynx"]);
+        assert!(ids("zebra").is_empty());
+        assert_eq!(ids("").len(), 2);
+    }
+}
@@ -0,0 +1,249 @@
+//! Read-only queries over a local socket, for editor integrations
+//!
+//! With `--serve <SOCKET>` WatchDiff listens on a Unix domain socket and
+//! answers newline-delimited JSON requests from the events it has seen:
+//!
+//! ```text
+//! {"cmd":"latest","path":"src/main.rs"}  -> {"ok":true,"event":{...}}
+//! {"cmd":"events","limit":20}            -> {"ok":true,"events":[...]}
+//! {"cmd":"stats"}                        -> {"ok":true,"stats":{...}}
+//! ```
+//!
+//! Nothing a client sends can change the state or the watched files, and the
+//! socket is only reachable by the user running WatchDiff.
+
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::core::{AppState, FileEvent};
+
+/// Events returned by `events` when the request gives no limit
+pub const DEFAULT_EVENTS_LIMIT: usize = 50;
+
+/// Requests longer than this are answered with an error and the connection closed
+pub const MAX_REQUEST_BYTES: u64 = 64 * 1024;
+
+/// One request line
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum ServeRequest {
+    /// The newest event, of `path` if given, with its diff
+    Latest {
+        #[serde(default)]
+        path: Option<PathBuf>,
+    },
+    /// Recent events newest first, without diffs unless `diffs` is set
+    Events {
+        #[serde(default)]
+        path: Option<PathBuf>,
+        #[serde(default)]
+        limit: Option<usize>,
+        #[serde(default)]
+        diffs: bool,
+    },
+    /// Event counts of the session
+    Stats,
+}
+
+/// The response line for one request line. Paths in requests may be
+/// relative to `root` or absolute.
+pub fn handle_request(state: &AppState, root: &Path, line: &str) -> Value {
+    let request = match serde_json::from_str::<ServeRequest>(line) {
+        Ok(request) => request,
+        Err(err) => return json!({ "ok": false, "error": format!("Invalid request: {}", err) }),
+    };
+
+    let for_path = |path: &Option<PathBuf>| {
+        let wanted = path.as_ref().map(|path| root.join(path));
+        move |event: &&FileEvent| wanted.as_ref().map_or(true, |wanted| event.path == *wanted)
+    };
+    match request {
+        ServeRequest::Latest { ref path } => {
+            let event = state.events.iter().find(for_path(path));
+            json!({ "ok": true, "event": event })
+        }
+        ServeRequest::Events { ref path, limit, diffs } => {
+            let events: Vec<Value> = state
+                .events
+                .iter()
+                .filter(for_path(path))
+                .take(limit.unwrap_or(DEFAULT_EVENTS_LIMIT))
+                .map(|event| {
+                    let mut value = serde_json::to_value(event).unwrap_or(Value::Null);
+                    if let (false, Some(object)) = (diffs, value.as_object_mut()) {
+                        object.remove("diff");
+                    }
+                    value
+                })
+                .collect();
+            json!({ "ok": true, "events": events })
+        }
+        ServeRequest::Stats => {
+            let (events, created, modified, deleted) = state.get_quick_stats();
+            json!({ "ok": true, "stats": {
+                "events": events,
+                "total_seen": state.total_seen(),
+                "created": created,
+                "modified": modified,
+                "deleted": deleted,
+            } })
+        }
+    }
+}
+
+#[cfg(unix)]
+pub use unix::DiffServer;
+
+#[cfg(unix)]
+mod unix {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use anyhow::{Context, Result};
+
+    use super::{handle_request, MAX_REQUEST_BYTES};
+    use crate::core::AppState;
+
+    /// A listening socket. The socket file is removed when it is dropped.
+    #[derive(Debug)]
+    pub struct DiffServer {
+        path: PathBuf,
+        listener: UnixListener,
+    }
+
+    impl DiffServer {
+        /// Listen on `path`, replacing a socket file left behind by a server
+        /// that is gone, but not one that is still answering. Anything else
+        /// already at `path` is left alone.
+        pub fn bind(path: &Path) -> Result<Self> {
+            if let Ok(metadata) = std::fs::symlink_metadata(path) {
+                if !metadata.file_type().is_socket() {
+                    anyhow::bail!("{} exists and is not a socket", path.display());
+                }
+                if UnixStream::connect(path).is_ok() {
+                    anyhow::bail!("Another server is already listening on {}", path.display());
+                }
+                std::fs::remove_file(path)
+                    .with_context(|| format!("Failed to remove stale socket: {}", path.display()))?;
+            }
+            let listener = UnixListener::bind(path)
+                .with_context(|| format!("Failed to listen on {}", path.display()))?;
+            // Only the user running WatchDiff may connect
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+                .with_context(|| format!("Failed to restrict access to {}", path.display()))?;
+            Ok(Self { path: path.to_path_buf(), listener })
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        /// Answer connections in the background, one thread per client
+        pub fn spawn(&self, state: Arc<Mutex<AppState>>, root: PathBuf) -> Result<()> {
+            let listener = self.listener.try_clone().context("Failed to share the server socket")?;
+            let root = Arc::new(root);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else { continue };
+                    let (state, root) = (Arc::clone(&state), Arc::clone(&root));
+                    thread::spawn(move || {
+                        if let Err(err) = serve_client(stream, &state, &root) {
+                            tracing::debug!("Serve client disconnected: {}", err);
+                        }
+                    });
+                }
+            });
+            Ok(())
+        }
+    }
+
+    impl Drop for DiffServer {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    fn serve_client(stream: UnixStream, state: &Mutex<AppState>, root: &Path) -> std::io::Result<()> {
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = (&mut reader).take(MAX_REQUEST_BYTES + 1).read_line(&mut line)?;
+            if read == 0 {
+                return Ok(());
+            }
+            if read as u64 > MAX_REQUEST_BYTES {
+                writeln!(writer, "{}", serde_json::json!({ "ok": false, "error": "Request too long" }))?;
+                return Ok(());
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = {
+                let state = state.lock().unwrap_or_else(|e| e.into_inner());
+                handle_request(&state, root, line.trim())
+            };
+            writeln!(writer, "{}", response)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::FileEventKind;
+
+    #[test]
+    fn test_requests_answered_from_state() {
+        let mut state = AppState::default();
+        state.add_event(FileEvent::new(PathBuf::from("/repo/src/a.rs"), FileEventKind::Created));
+        let mut modified = FileEvent::new(PathBuf::from("/repo/src/b.rs"), FileEventKind::Modified);
+        modified.diff = Some("--- b.rs\n+++ b.rs\n@@ -1,1 +1,1 @@\n-x\n+y".to_string());
+        state.add_event(modified);
+        let root = Path::new("/repo");
+
+        let latest = handle_request(&state, root, r#"{"cmd":"latest"}"#);
+        assert_eq!(latest["event"]["path"], "/repo/src/b.rs");
+        let latest = handle_request(&state, root, r#"{"cmd":"latest","path":"src/a.rs"}"#);
+        assert_eq!(latest["event"]["kind"], "Created");
+        let missing = handle_request(&state, root, r#"{"cmd":"latest","path":"nope.rs"}"#);
+        assert_eq!((missing["ok"].as_bool(), missing["event"].is_null()), (Some(true), true));
+
+        let events = handle_request(&state, root, r#"{"cmd":"events","limit":1}"#);
+        assert_eq!(events["events"].as_array().unwrap().len(), 1);
+        assert!(events["events"][0].get("diff").is_none());
+        let events = handle_request(&state, root, r#"{"cmd":"events","diffs":true}"#);
+        assert!(events["events"][0]["diff"].as_str().unwrap().contains("+y"));
+
+        assert_eq!(handle_request(&state, root, r#"{"cmd":"stats"}"#)["stats"]["events"], 2);
+        for bad in [r#"{"cmd":"delete"}"#, "not json"] {
+            assert_eq!(handle_request(&state, root, bad)["ok"], false);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bind_replaces_only_stale_sockets() {
+        let dir = tempfile::TempDir::new().unwrap();
+
+        let file = dir.path().join("notes.txt");
+        std::fs::write(&file, "keep me").unwrap();
+        assert!(DiffServer::bind(&file).is_err());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "keep me");
+
+        let socket = dir.path().join("watchdiff.sock");
+        let server = DiffServer::bind(&socket).unwrap();
+        assert!(DiffServer::bind(&socket).is_err(), "a live server is not replaced");
+        drop(server);
+
+        // A closed listener leaves its socket file behind, as a crashed server would
+        drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+        assert!(socket.exists());
+        assert!(DiffServer::bind(&socket).is_ok());
+    }
+}
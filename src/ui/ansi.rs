@@ -0,0 +1,206 @@
+//! ANSI escape sequences to styled spans
+//!
+//! Highlighted text from terminal-oriented highlighters carries its colors as
+//! SGR escape sequences (`ESC [ ... m`). [`ansi_spans`] turns those into
+//! ratatui styles and drops every other escape sequence, so nothing that
+//! moves the cursor or retitles the terminal reaches the screen.
+
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Span;
+
+/// `input` split into spans at each style change, without escape sequences.
+/// Styles start from the default and accumulate until a reset, as they would
+/// in a terminal.
+pub fn ansi_spans(input: &str) -> Vec<Span<'static>> {
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut style = Style::default();
+    let mut text = String::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\x1b' {
+            // Other control characters would move the cursor too; tabs are
+            // expanded by the renderer
+            if ch == '\t' || !ch.is_control() {
+                text.push(ch);
+            }
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameters, then a final byte in @..~
+            Some('[') => {
+                let mut params = String::new();
+                let mut terminator = None;
+                for ch in chars.by_ref() {
+                    if ('@'..='~').contains(&ch) {
+                        terminator = Some(ch);
+                        break;
+                    }
+                    params.push(ch);
+                }
+                if terminator == Some('m') {
+                    let next = apply_sgr(style, &params);
+                    if next != style {
+                        push_span(&mut spans, &mut text, style);
+                        style = next;
+                    }
+                }
+            }
+            // OSC: up to BEL or ESC \
+            Some(']') => {
+                while let Some(ch) = chars.next() {
+                    if ch == '\x07' {
+                        break;
+                    }
+                    if ch == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // Two-character sequences
+            _ => {}
+        }
+    }
+    push_span(&mut spans, &mut text, style);
+    spans
+}
+
+fn push_span(spans: &mut Vec<Span<'static>>, text: &mut String, style: Style) {
+    if text.is_empty() {
+        return;
+    }
+    match spans.last_mut() {
+        Some(last) if last.style == style => last.content.to_mut().push_str(text),
+        _ => spans.push(Span::styled(std::mem::take(text), style)),
+    }
+    text.clear();
+}
+
+/// `style` with the SGR parameters `params` (`1;38;5;208`) applied
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+    // An empty parameter means 0, so `ESC [ m` resets
+    let codes: Vec<Option<u16>> = params
+        .split(';')
+        .map(|code| if code.is_empty() { Some(0) } else { code.parse().ok() })
+        .collect();
+    let mut codes = codes.into_iter();
+
+    while let Some(code) = codes.next() {
+        let Some(code) = code else { continue };
+        style = match code {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            5 => style.add_modifier(Modifier::SLOW_BLINK),
+            6 => style.add_modifier(Modifier::RAPID_BLINK),
+            7 => style.add_modifier(Modifier::REVERSED),
+            8 => style.add_modifier(Modifier::HIDDEN),
+            9 => style.add_modifier(Modifier::CROSSED_OUT),
+            22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            25 => style.remove_modifier(Modifier::SLOW_BLINK | Modifier::RAPID_BLINK),
+            27 => style.remove_modifier(Modifier::REVERSED),
+            28 => style.remove_modifier(Modifier::HIDDEN),
+            29 => style.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => Style { fg: Some(basic_color(code - 30)), ..style },
+            90..=97 => Style { fg: Some(bright_color(code - 90)), ..style },
+            39 => Style { fg: None, ..style },
+            40..=47 => Style { bg: Some(basic_color(code - 40)), ..style },
+            100..=107 => Style { bg: Some(bright_color(code - 100)), ..style },
+            49 => Style { bg: None, ..style },
+            38 | 48 => {
+                // A malformed extended color leaves the rest ambiguous
+                let Some(color) = extended_color(&mut codes) else { break };
+                if code == 38 {
+                    Style { fg: Some(color), ..style }
+                } else {
+                    Style { bg: Some(color), ..style }
+                }
+            }
+            _ => style,
+        };
+    }
+    style
+}
+
+/// The color after `38` or `48`: `5;n` from the 256-color palette or
+/// `2;r;g;b` in truecolor
+fn extended_color(codes: &mut impl Iterator<Item = Option<u16>>) -> Option<Color> {
+    let mut component = || codes.next().flatten().and_then(|value| u8::try_from(value).ok());
+    match component()? {
+        5 => Some(Color::Indexed(component()?)),
+        2 => Some(Color::Rgb(component()?, component()?, component()?)),
+        _ => None,
+    }
+}
+
+fn basic_color(index: u16) -> Color {
+    match index {
+        0 => Color::Black,
+        1 => Color::Red,
+        2 => Color::Green,
+        3 => Color::Yellow,
+        4 => Color::Blue,
+        5 => Color::Magenta,
+        6 => Color::Cyan,
+        _ => Color::Gray,
+    }
+}
+
+fn bright_color(index: u16) -> Color {
+    match index {
+        0 => Color::DarkGray,
+        1 => Color::LightRed,
+        2 => Color::LightGreen,
+        3 => Color::LightYellow,
+        4 => Color::LightBlue,
+        5 => Color::LightMagenta,
+        6 => Color::LightCyan,
+        _ => Color::White,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styled(spans: &[Span]) -> Vec<(Style, String)> {
+        spans.iter().map(|span| (span.style, span.content.to_string())).collect()
+    }
+
+    #[test]
+    fn test_red_foreground_becomes_a_red_span() {
+        let spans = ansi_spans("\x1b[31mfn\x1b[0m main()");
+        assert_eq!(styled(&spans), vec![
+            (Style::default().fg(Color::Red), "fn".to_string()),
+            (Style::default(), " main()".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_extended_colors_modifiers_and_resets() {
+        let spans = ansi_spans("\x1b[1;38;5;208mA\x1b[48;2;10;20;30mB\x1b[22;39mC\x1b[49;94mD\x1b[mE");
+        let orange = Style::default().fg(Color::Indexed(208)).add_modifier(Modifier::BOLD);
+        assert_eq!(styled(&spans), vec![
+            (orange, "A".to_string()),
+            (orange.bg(Color::Rgb(10, 20, 30)), "B".to_string()),
+            (Style::default().bg(Color::Rgb(10, 20, 30)).remove_modifier(Modifier::BOLD | Modifier::DIM), "C".to_string()),
+            (Style::default().fg(Color::LightBlue).remove_modifier(Modifier::BOLD | Modifier::DIM), "D".to_string()),
+            (Style::default(), "E".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_other_escape_sequences_are_dropped() {
+        // Cursor movement, a window title, a bell and an unterminated sequence
+        let spans = ansi_spans("a\x1b[2Kb\x1b]0;title\x07c\x07d\x1b[38;5");
+        assert_eq!(styled(&spans), vec![(Style::default(), "abcd".to_string())]);
+        // A truncated extended color does not apply
+        assert_eq!(styled(&ansi_spans("\x1b[38;2;1mx")), vec![(Style::default(), "x".to_string())]);
+        assert!(ansi_spans("").is_empty());
+    }
+}
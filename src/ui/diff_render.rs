@@ -0,0 +1,222 @@
+//! Shared rendering of diff and file lines for the TUI panes
+//!
+//! Tabs are expanded to spaces before any width math so every pane lines up
+//! with its gutter, and whitespace on changed lines can optionally be made
+//! visible.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use crate::config::UiConfig;
+
+/// Shown in place of a tab on changed lines when invisibles are on
+const TAB_MARKER: char = '→';
+/// Shown in place of a trailing space on changed lines when invisibles are on
+const TRAILING_SPACE_MARKER: char = '·';
+
+/// Tab width and invisible-character settings for diff panes
+#[derive(Debug, Clone)]
+pub struct DiffRenderOptions {
+    /// Columns per tab stop
+    pub tab_width: usize,
+    /// Tab width per file extension (without the dot), e.g. `go` => 8
+    pub tab_width_overrides: HashMap<String, usize>,
+    /// Mark tabs and trailing spaces on added/removed lines
+    pub show_invisibles: bool,
+    /// Highlight the changed words of paired removed and added lines
+    pub word_diff: bool,
+}
+
+impl Default for DiffRenderOptions {
+    fn default() -> Self {
+        Self::from_config(&UiConfig::default())
+    }
+}
+
+impl DiffRenderOptions {
+    pub fn from_config(config: &UiConfig) -> Self {
+        Self {
+            tab_width: config.tab_width,
+            tab_width_overrides: config.tab_width_overrides.clone(),
+            show_invisibles: config.show_invisibles,
+            word_diff: config.word_diff,
+        }
+    }
+
+    /// Tab width to use for `path`, honouring per-extension overrides
+    pub fn tab_width_for(&self, path: &Path) -> usize {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| self.tab_width_overrides.get(ext))
+            .copied()
+            .unwrap_or(self.tab_width)
+            .max(1)
+    }
+
+    /// Render one line of a unified diff.
+    ///
+    /// The `+`/`-`/` ` marker is kept as is and tab stops are counted from the
+    /// start of the content after it. Invisibles are only marked on added and
+    /// removed lines.
+    pub fn render_diff_line(&self, line: &str, tab_width: usize) -> String {
+        let is_file_header = line.starts_with("+++") || line.starts_with("---");
+        match line.chars().next() {
+            Some(marker @ ('+' | '-')) if !is_file_header => {
+                let mut rendered = String::with_capacity(line.len());
+                rendered.push(marker);
+                rendered.push_str(&self.render_content(&line[1..], tab_width, true));
+                rendered
+            }
+            Some(' ') => format!(" {}", self.render_content(&line[1..], tab_width, false)),
+            _ => self.render_content(line, tab_width, false),
+        }
+    }
+
+    /// Expand tabs in plain content, marking invisibles if `changed` is set
+    pub fn render_content(&self, content: &str, tab_width: usize, changed: bool) -> String {
+        let mut column = 0;
+        self.render_content_from(content, tab_width, changed, &mut column)
+    }
+
+    /// Like `render_content`, continuing from `column` so a line split into
+    /// several styled spans keeps its tab stops; `column` is advanced.
+    pub fn render_content_from(&self, content: &str, tab_width: usize, changed: bool, column: &mut usize) -> String {
+        self.render_range(content, 0..content.len(), tab_width, changed, column)
+    }
+
+    /// Like `render_content`, split at the byte `ranges` of `content` (sorted,
+    /// not overlapping). Each piece is paired with whether it lies inside one
+    /// of the ranges.
+    pub fn render_content_ranges(&self, content: &str, tab_width: usize, changed: bool, ranges: &[Range<usize>]) -> Vec<(String, bool)> {
+        let mut pieces = Vec::new();
+        let mut column = 0;
+        let mut last = 0;
+        for range in ranges {
+            let (start, end) = (range.start.clamp(last, content.len()), range.end.min(content.len()));
+            if start >= end || !content.is_char_boundary(start) || !content.is_char_boundary(end) {
+                continue;
+            }
+            if start > last {
+                pieces.push((self.render_range(content, last..start, tab_width, changed, &mut column), false));
+            }
+            pieces.push((self.render_range(content, start..end, tab_width, changed, &mut column), true));
+            last = end;
+        }
+        if last < content.len() || pieces.is_empty() {
+            pieces.push((self.render_range(content, last..content.len(), tab_width, changed, &mut column), false));
+        }
+        pieces
+    }
+
+    /// Render `range` of `content`, so trailing whitespace is judged against
+    /// the whole line
+    fn render_range(&self, content: &str, range: Range<usize>, tab_width: usize, changed: bool, column: &mut usize) -> String {
+        let tab_width = tab_width.max(1);
+        let mark = changed && self.show_invisibles;
+        let trailing_start = content.trim_end_matches([' ', '\t']).len();
+        let mut rendered = String::with_capacity(range.len());
+
+        let offset = range.start;
+        for (index, ch) in content[range].char_indices() {
+            let index = offset + index;
+            match ch {
+                '\t' => {
+                    let width = tab_width - *column % tab_width;
+                    if mark {
+                        rendered.push(TAB_MARKER);
+                        rendered.extend(std::iter::repeat(' ').take(width - 1));
+                    } else {
+                        rendered.extend(std::iter::repeat(' ').take(width));
+                    }
+                    *column += width;
+                }
+                ' ' if mark && index >= trailing_start => {
+                    rendered.push(TRAILING_SPACE_MARKER);
+                    *column += 1;
+                }
+                _ => {
+                    rendered.push(ch);
+                    *column += 1;
+                }
+            }
+        }
+
+        rendered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn options(show_invisibles: bool) -> DiffRenderOptions {
+        DiffRenderOptions {
+            tab_width: 4,
+            tab_width_overrides: HashMap::from([("go".to_string(), 8)]),
+            show_invisibles,
+            word_diff: true,
+        }
+    }
+
+    #[test]
+    fn test_tab_width_override_by_extension() {
+        let opts = options(false);
+        assert_eq!(opts.tab_width_for(&PathBuf::from("main.go")), 8);
+        assert_eq!(opts.tab_width_for(&PathBuf::from("main.py")), 4);
+        assert_eq!(opts.tab_width_for(&PathBuf::from("Makefile")), 4);
+    }
+
+    #[test]
+    fn test_expands_mixed_tabs_and_spaces_to_tab_stops() {
+        let opts = options(false);
+        // Tab stops are relative to the content, not the diff marker
+        assert_eq!(opts.render_diff_line("+\tx", 4), "+    x");
+        assert_eq!(opts.render_diff_line("+  \tx", 4), "+    x");
+        assert_eq!(opts.render_diff_line(" ab\tcd\te", 4), " ab  cd  e");
+        assert_eq!(opts.render_diff_line("-\tx", 8), "-        x");
+        // Without invisibles trailing whitespace is left alone
+        assert_eq!(opts.render_diff_line("+x  ", 4), "+x  ");
+    }
+
+    #[test]
+    fn test_invisibles_only_on_changed_lines() {
+        let opts = options(true);
+        assert_eq!(opts.render_diff_line("+\tfoo = 1  ", 4), "+→   foo = 1··");
+        assert_eq!(opts.render_diff_line("-a b\t", 4), "-a b→");
+        // Context lines and headers are expanded but not marked
+        assert_eq!(opts.render_diff_line(" \tfoo  ", 4), "     foo  ");
+        assert_eq!(opts.render_diff_line("+++ b/file\t", 4), "+++ b/file  ");
+    }
+
+    #[test]
+    fn test_whitespace_only_change_is_visible() {
+        let opts = options(true);
+        let removed = opts.render_diff_line("-let x = 1;", 4);
+        let added = opts.render_diff_line("+let x = 1; ", 4);
+        assert_ne!(removed[1..], added[1..]);
+        assert!(added.ends_with('·'));
+    }
+
+    #[test]
+    fn test_continues_tab_stops_across_spans() {
+        let opts = options(false);
+        let mut column = 0;
+        let first = opts.render_content_from("ab", 4, false, &mut column);
+        let second = opts.render_content_from("\tc", 4, false, &mut column);
+        assert_eq!(format!("{}{}", first, second), "ab  c");
+        assert_eq!(column, 5);
+    }
+
+    #[test]
+    fn test_splits_rendered_content_at_ranges() {
+        let opts = options(true);
+        let pieces = opts.render_content_ranges("\tx = count ", 4, true, &[Range { start: 5, end: 10 }]);
+        assert_eq!(pieces, vec![
+            ("→   x = ".to_string(), false),
+            ("count".to_string(), true),
+            // The space is trailing on the whole line, not just in its piece
+            ("·".to_string(), false),
+        ]);
+    }
+}
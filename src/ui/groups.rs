@@ -0,0 +1,122 @@
+//! Labeled sections of the flat file list
+//!
+//! Each section is a label and a glob in gitignore syntax, relative to the
+//! watch root, given as `--group Source=src/**` or in `groups` under `[ui]`.
+//! A file goes into the first section whose glob matches it, and files no
+//! glob matches go into "Other".
+
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// Section of the files no group matches
+pub const OTHER_GROUP: &str = "Other";
+
+struct FileGroup {
+    label: String,
+    matcher: Gitignore,
+}
+
+/// Ordered section rules for the file list; with none the list stays flat
+#[derive(Default)]
+pub struct FileGroups {
+    root: PathBuf,
+    absolute_root: PathBuf,
+    groups: Vec<FileGroup>,
+}
+
+impl FileGroups {
+    /// Rules from `LABEL=GLOB` specs, in order
+    pub fn new(root: &Path, specs: &[String]) -> Result<Self> {
+        let mut groups = Vec::new();
+        for spec in specs {
+            let (label, glob) = spec
+                .split_once('=')
+                .map(|(label, glob)| (label.trim(), glob.trim()))
+                .filter(|(label, glob)| !label.is_empty() && !glob.is_empty())
+                .with_context(|| format!("Invalid --group {:?}, expected LABEL=GLOB", spec))?;
+            let mut builder = GitignoreBuilder::new(root);
+            builder
+                .add_line(None, glob)
+                .with_context(|| format!("Invalid --group pattern: {}", glob))?;
+            let matcher = builder.build().with_context(|| format!("Invalid --group pattern: {}", glob))?;
+            groups.push(FileGroup { label: label.to_string(), matcher });
+        }
+        // Watched files may be listed by absolute path
+        let absolute_root = std::env::current_dir().map_or_else(|_| root.to_path_buf(), |cwd| cwd.join(root));
+        Ok(Self { root: root.to_path_buf(), absolute_root, groups })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Label of the first group matching `path`, or [`OTHER_GROUP`]
+    pub fn label_of(&self, path: &Path) -> &str {
+        let relative = path
+            .strip_prefix(&self.root)
+            .or_else(|_| path.strip_prefix(&self.absolute_root))
+            .unwrap_or(path);
+        if relative.is_absolute() || relative.as_os_str().is_empty() {
+            return OTHER_GROUP;
+        }
+        self.groups
+            .iter()
+            .find(|group| group.matcher.matched_path_or_any_parents(relative, false).is_ignore())
+            .map_or(OTHER_GROUP, |group| group.label.as_str())
+    }
+
+    /// `files` bucketed by group, in rule order with "Other" last. Groups
+    /// without files are left out; files keep their order within a group.
+    pub fn bucket<'a>(&self, files: impl IntoIterator<Item = &'a PathBuf>) -> Vec<(&str, Vec<&'a PathBuf>)> {
+        let mut buckets: Vec<(&str, Vec<&PathBuf>)> = self
+            .groups
+            .iter()
+            .map(|group| group.label.as_str())
+            .chain([OTHER_GROUP])
+            .map(|label| (label, Vec::new()))
+            .collect();
+        for file in files {
+            let label = self.label_of(file);
+            if let Some((_, bucket)) = buckets.iter_mut().find(|(bucket_label, _)| *bucket_label == label) {
+                bucket.push(file);
+            }
+        }
+        buckets.retain(|(_, files)| !files.is_empty());
+        buckets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_files_go_to_the_first_matching_group() {
+        let root = Path::new("/repo");
+        let specs = ["Source=src/**", "Tests=tests/**", "Config=*.toml", "Empty=docs/"].map(String::from);
+        let groups = FileGroups::new(root, &specs).unwrap();
+        let files: Vec<PathBuf> = ["src/main.rs", "Cargo.toml", "tests/it.rs", "src/Cargo.toml", "README.md", "src/ui/tui.rs"]
+            .iter()
+            .map(|file| root.join(file))
+            .collect();
+
+        let buckets: Vec<(&str, Vec<&str>)> = groups
+            .bucket(&files)
+            .into_iter()
+            .map(|(label, files)| (label, files.iter().map(|file| file.strip_prefix(root).unwrap().to_str().unwrap()).collect()))
+            .collect();
+        assert_eq!(buckets, vec![
+            ("Source", vec!["src/main.rs", "src/Cargo.toml", "src/ui/tui.rs"]),
+            ("Tests", vec!["tests/it.rs"]),
+            ("Config", vec!["Cargo.toml"]),
+            (OTHER_GROUP, vec!["README.md"]),
+        ]);
+
+        // Relative paths are matched as they are
+        assert_eq!(groups.label_of(Path::new("tests/it.rs")), "Tests");
+        assert_eq!(groups.label_of(Path::new("/elsewhere/src/main.rs")), OTHER_GROUP);
+        assert!(FileGroups::new(root, &["Source".to_string()]).is_err());
+        assert!(FileGroups::new(root, &["=src/**".to_string()]).is_err());
+    }
+}
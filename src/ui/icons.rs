@@ -0,0 +1,219 @@
+//! Central lookup for the glyphs shown by the TUI
+//!
+//! Every renderer asks `Icons` for a glyph instead of embedding emoji, so
+//! terminals without emoji fonts can switch to ASCII tags or no icons at all.
+
+use clap::ValueEnum;
+use crate::core::{ChangeOrigin, ConfidenceLevel};
+use crate::review::ReviewAction;
+
+/// How icons are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, ValueEnum)]
+pub enum IconMode {
+    /// Emoji and Unicode symbols (default)
+    #[default]
+    Emoji,
+    /// Plain ASCII tags such as `[AI]` and `[!]`
+    Ascii,
+    /// No icons, text labels only
+    None,
+}
+
+/// Everything the TUI draws an icon for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Icon {
+    Human,
+    Ai,
+    Tool,
+    UnknownOrigin,
+    Safe,
+    Review,
+    Risky,
+    /// A Critical rule fired
+    Critical,
+    Unscored,
+    Created,
+    Modified,
+    Deleted,
+    Event,
+    Marked,
+    Accepted,
+    Rejected,
+    Skipped,
+    Pending,
+    Deferred,
+    Warning,
+    Changes,
+    Files,
+    File,
+    ChangedFile,
+    Recent,
+    Integrity,
+    Compare,
+    Preview,
+    Keyboard,
+    Info,
+    Search,
+    Pinned,
+    Muted,
+    Focus,
+    Branch,
+}
+
+/// Resolves icons for the configured mode
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Icons {
+    mode: IconMode,
+}
+
+impl Icons {
+    pub fn new(mode: IconMode) -> Self {
+        Self { mode }
+    }
+
+    pub fn mode(&self) -> IconMode {
+        self.mode
+    }
+
+    /// Glyph for `icon`, empty when icons are off
+    pub fn get(&self, icon: Icon) -> &'static str {
+        match self.mode {
+            IconMode::Emoji => emoji(icon),
+            IconMode::Ascii => ascii(icon),
+            IconMode::None => "",
+        }
+    }
+
+    /// `text` preceded by the icon, or `text` alone when there is no glyph
+    pub fn label(&self, icon: Icon, text: &str) -> String {
+        match self.get(icon) {
+            "" => text.to_string(),
+            glyph => format!("{} {}", glyph, text),
+        }
+    }
+
+    pub fn origin(origin: &ChangeOrigin) -> Icon {
+        match origin {
+            ChangeOrigin::Human => Icon::Human,
+            ChangeOrigin::AIAgent { .. } => Icon::Ai,
+            ChangeOrigin::Tool { .. } => Icon::Tool,
+            ChangeOrigin::Unknown => Icon::UnknownOrigin,
+        }
+    }
+
+    pub fn confidence(level: Option<&ConfidenceLevel>) -> Icon {
+        match level {
+            Some(ConfidenceLevel::Safe) => Icon::Safe,
+            Some(ConfidenceLevel::Review) => Icon::Review,
+            Some(ConfidenceLevel::Risky) => Icon::Risky,
+            None => Icon::Unscored,
+        }
+    }
+
+    pub fn review_action(action: &ReviewAction) -> Icon {
+        match action {
+            ReviewAction::Accept => Icon::Accepted,
+            ReviewAction::Reject => Icon::Rejected,
+            ReviewAction::Skip => Icon::Skipped,
+            ReviewAction::Pending => Icon::Pending,
+            ReviewAction::Defer => Icon::Deferred,
+        }
+    }
+}
+
+fn emoji(icon: Icon) -> &'static str {
+    match icon {
+        Icon::Human => "👤",
+        Icon::Ai => "🤖",
+        Icon::Tool => "🔧",
+        Icon::UnknownOrigin => "❓",
+        Icon::Safe | Icon::Created => "🟢",
+        Icon::Review | Icon::Modified | Icon::ChangedFile => "🟡",
+        Icon::Risky | Icon::Deleted => "🔴",
+        Icon::Critical => "‼",
+        Icon::Unscored => "⚪",
+        Icon::Event => "●",
+        Icon::Marked => "◆",
+        Icon::Accepted => "✅",
+        Icon::Rejected => "❌",
+        Icon::Skipped => "⏭️",
+        Icon::Pending => "⏳",
+        Icon::Deferred => "💬",
+        Icon::Warning => "⚠",
+        Icon::Changes => "📊",
+        Icon::Files => "📁",
+        Icon::File => "📄",
+        Icon::Recent => "🕘",
+        Icon::Integrity => "🛡",
+        Icon::Compare => "⇄",
+        Icon::Preview => "🔄",
+        // Variation-selector emoji are measured narrower than they render
+        Icon::Keyboard => "⌨️ ",
+        Icon::Info => "ℹ️ ",
+        Icon::Search => "🔍",
+        Icon::Pinned => "📌",
+        Icon::Muted => "🔇",
+        Icon::Focus => "🌙",
+        Icon::Branch => "🌿",
+    }
+}
+
+fn ascii(icon: Icon) -> &'static str {
+    match icon {
+        Icon::Human => "[H]",
+        Icon::Ai => "[AI]",
+        Icon::Tool => "[T]",
+        Icon::UnknownOrigin => "[?]",
+        Icon::Safe | Icon::Created | Icon::Accepted => "[+]",
+        Icon::Review | Icon::Modified => "[~]",
+        Icon::Risky | Icon::Warning => "[!]",
+        Icon::Critical => "!!",
+        Icon::Unscored | Icon::Pending => "[ ]",
+        Icon::Deleted | Icon::Rejected => "[-]",
+        Icon::Skipped => "[>]",
+        Icon::Deferred => "[?]",
+        Icon::Event | Icon::ChangedFile => "*",
+        Icon::Marked => "<>",
+        Icon::Compare => "<->",
+        Icon::File => "-",
+        Icon::Search => "/",
+        Icon::Pinned => "[P]",
+        Icon::Muted => "[M]",
+        Icon::Focus => "[F]",
+        Icon::Branch => "git:",
+        Icon::Changes
+        | Icon::Files
+        | Icon::Recent
+        | Icon::Integrity
+        | Icon::Preview
+        | Icon::Keyboard
+        | Icon::Info => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Icon; 35] = [
+        Icon::Human, Icon::Ai, Icon::Tool, Icon::UnknownOrigin, Icon::Safe, Icon::Review,
+        Icon::Risky, Icon::Critical, Icon::Unscored, Icon::Created, Icon::Modified, Icon::Deleted, Icon::Event,
+        Icon::Marked, Icon::Accepted, Icon::Rejected, Icon::Skipped, Icon::Pending, Icon::Deferred,
+        Icon::Warning, Icon::Changes, Icon::Files, Icon::File, Icon::ChangedFile, Icon::Recent,
+        Icon::Integrity, Icon::Compare, Icon::Preview, Icon::Keyboard, Icon::Info, Icon::Search,
+        Icon::Pinned, Icon::Muted, Icon::Focus, Icon::Branch,
+    ];
+
+    #[test]
+    fn test_ascii_and_none_modes_are_ascii_only() {
+        let ascii = Icons::new(IconMode::Ascii);
+        let none = Icons::new(IconMode::None);
+        for icon in ALL {
+            assert!(ascii.get(icon).is_ascii(), "{:?}", icon);
+            assert_eq!(none.get(icon), "");
+        }
+        assert_eq!(ascii.label(Icon::Ai, "claude"), "[AI] claude");
+        assert_eq!(ascii.label(Icon::Changes, "Changes"), "Changes");
+        assert_eq!(none.label(Icon::Ai, "claude"), "claude");
+    }
+}
@@ -0,0 +1,233 @@
+//! Keyboard macros for review mode
+//!
+//! `q<register>` records the keys that follow into a register until `q` is
+//! pressed again. `@<register>` replays them once and `<count>@<register>`
+//! replays them `count` times. Registers are the letters `a` to `z`.
+//!
+//! Registers are saved in the UI state file in a Vim-like notation: plain
+//! characters as themselves, other keys as `<Esc>`, `<Up>`, `<C-t>` and so
+//! on, and a literal `<` as `<lt>`.
+
+use std::collections::BTreeMap;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Upper bound on `<count>@<register>`, so a stray count cannot spin forever
+pub const MAX_MACRO_REPEAT: usize = 10_000;
+
+/// What a key meant to the macro layer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MacroInput {
+    /// Not a macro key; handle it normally
+    Pass,
+    /// Started or stopped recording, or waiting for a register
+    Consumed,
+    /// Replay a register `count` times
+    Replay { register: char, count: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Awaiting {
+    Record,
+    Replay,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct KeyMacros {
+    registers: BTreeMap<char, Vec<KeyEvent>>,
+    /// Register being recorded and the keys so far
+    recording: Option<(char, Vec<KeyEvent>)>,
+    awaiting: Option<Awaiting>,
+    /// Digits typed before `@`
+    count: String,
+    replaying: bool,
+}
+
+impl KeyMacros {
+    /// Registers saved as `notation`, skipping any that do not parse
+    pub fn from_saved(saved: &BTreeMap<char, String>) -> Self {
+        let registers = saved
+            .iter()
+            .filter(|(register, _)| register.is_ascii_lowercase())
+            .filter_map(|(register, notation)| Some((*register, decode(notation)?)))
+            .collect();
+        Self { registers, ..Self::default() }
+    }
+
+    /// Registers in the notation `from_saved` reads
+    pub fn to_saved(&self) -> BTreeMap<char, String> {
+        self.registers.iter().map(|(register, keys)| (*register, encode(keys))).collect()
+    }
+
+    /// Interpret `key`. Keys that pass are recorded while recording.
+    pub fn input(&mut self, key: &KeyEvent) -> MacroInput {
+        if self.replaying {
+            return MacroInput::Pass;
+        }
+
+        let register = match key.code {
+            KeyCode::Char(c) if c.is_ascii_lowercase() && key.modifiers.difference(KeyModifiers::SHIFT).is_empty() => Some(c),
+            _ => None,
+        };
+        if let Some(awaiting) = self.awaiting.take() {
+            if let Some(register) = register {
+                let count = std::mem::take(&mut self.count);
+                return match awaiting {
+                    Awaiting::Record => {
+                        self.recording = Some((register, Vec::new()));
+                        MacroInput::Consumed
+                    }
+                    Awaiting::Replay => MacroInput::Replay {
+                        register,
+                        count: count.parse::<usize>().unwrap_or(1).clamp(1, MAX_MACRO_REPEAT),
+                    },
+                };
+            }
+            // Anything else cancels the prefix and is handled as usual
+            self.count.clear();
+        }
+
+        match key.code {
+            KeyCode::Char('q') if key.modifiers.is_empty() => {
+                match self.recording.take() {
+                    Some((register, keys)) => {
+                        self.registers.insert(register, keys);
+                    }
+                    None => self.awaiting = Some(Awaiting::Record),
+                }
+                MacroInput::Consumed
+            }
+            KeyCode::Char('@') => {
+                self.awaiting = Some(Awaiting::Replay);
+                MacroInput::Consumed
+            }
+            code => {
+                match code {
+                    KeyCode::Char(d) if d.is_ascii_digit() && (d != '0' || !self.count.is_empty()) => self.count.push(d),
+                    _ => self.count.clear(),
+                }
+                if let Some((_, ref mut keys)) = self.recording {
+                    keys.push(*key);
+                }
+                MacroInput::Pass
+            }
+        }
+    }
+
+    /// Digits typed so far that would become a replay count
+    pub fn pending_count(&self) -> &str {
+        &self.count
+    }
+
+    pub fn recording(&self) -> Option<char> {
+        self.recording.as_ref().map(|(register, _)| *register)
+    }
+
+    pub fn keys(&self, register: char) -> Option<&[KeyEvent]> {
+        self.registers.get(&register).map(Vec::as_slice)
+    }
+
+    /// Recorded registers with their key counts, in register order
+    pub fn list(&self) -> impl Iterator<Item = (char, usize)> + '_ {
+        self.registers.iter().map(|(register, keys)| (*register, keys.len()))
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replaying
+    }
+
+    /// While set, keys pass through without being recorded or taken as macro keys
+    pub fn set_replaying(&mut self, replaying: bool) {
+        self.replaying = replaying;
+    }
+}
+
+const NAMED_KEYS: [(KeyCode, &str); 12] = [
+    (KeyCode::Esc, "Esc"),
+    (KeyCode::Enter, "Enter"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::Backspace, "BS"),
+    (KeyCode::Up, "Up"),
+    (KeyCode::Down, "Down"),
+    (KeyCode::Left, "Left"),
+    (KeyCode::Right, "Right"),
+    (KeyCode::PageUp, "PageUp"),
+    (KeyCode::PageDown, "PageDown"),
+    (KeyCode::Home, "Home"),
+    (KeyCode::End, "End"),
+];
+
+/// Keys in the saved notation; keys it cannot express are left out
+fn encode(keys: &[KeyEvent]) -> String {
+    keys.iter()
+        .filter_map(|key| {
+            let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+            match key.code {
+                KeyCode::Char(c) if ctrl => Some(format!("<C-{}>", c)),
+                KeyCode::Char('<') => Some("<lt>".to_string()),
+                KeyCode::Char(c) => Some(c.to_string()),
+                code => NAMED_KEYS.iter().find(|(named, _)| *named == code).map(|(_, name)| format!("<{}>", name)),
+            }
+        })
+        .collect()
+}
+
+fn decode(notation: &str) -> Option<Vec<KeyEvent>> {
+    let mut keys = Vec::new();
+    let mut rest = notation;
+    while let Some(c) = rest.chars().next() {
+        if c != '<' {
+            keys.push(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let (name, after) = rest[1..].split_once('>')?;
+        let key = if name == "lt" {
+            KeyEvent::new(KeyCode::Char('<'), KeyModifiers::NONE)
+        } else if let Some(c) = name.strip_prefix("C-").filter(|c| c.chars().count() == 1) {
+            KeyEvent::new(KeyCode::Char(c.chars().next()?), KeyModifiers::CONTROL)
+        } else {
+            let (code, _) = NAMED_KEYS.iter().find(|(_, named)| *named == name)?;
+            KeyEvent::new(*code, KeyModifiers::NONE)
+        };
+        keys.push(key);
+        rest = after;
+    }
+    Some(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(text: &str) -> Vec<KeyEvent> {
+        decode(text).unwrap()
+    }
+
+    #[test]
+    fn test_record_and_replay_with_count() {
+        let mut macros = KeyMacros::default();
+        let mut passed = Vec::new();
+        for key in keys("qaan<C-t>q12@a") {
+            match macros.input(&key) {
+                MacroInput::Pass => passed.push(key),
+                MacroInput::Consumed => {}
+                MacroInput::Replay { register, count } => assert_eq!((register, count), ('a', 12)),
+            }
+        }
+        assert_eq!(passed, keys("an<C-t>12"));
+        assert_eq!(macros.keys('a').unwrap(), keys("an<C-t>").as_slice());
+        assert_eq!(macros.recording(), None);
+
+        // A non-register key after @ cancels it and is handled as usual
+        assert_eq!(macros.input(&keys("@")[0]), MacroInput::Consumed);
+        assert_eq!(macros.input(&keys("<Esc>")[0]), MacroInput::Pass);
+
+        // Saved and restored through the UI state notation
+        let saved = macros.to_saved();
+        assert_eq!(saved[&'a'], "an<C-t>");
+        assert_eq!(KeyMacros::from_saved(&saved).keys('a'), macros.keys('a'));
+        assert_eq!(decode("<lt><Up>x").unwrap(), keys("<lt><Up>x"));
+        assert_eq!(encode(&keys("<lt><Up>x")), "<lt><Up>x");
+        assert!(decode("<Nope>").is_none());
+    }
+}
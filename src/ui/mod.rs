@@ -2,7 +2,27 @@
 //! 
 //! Contains TUI and other interface-related functionality
 
+pub mod ansi;
+pub mod diff_render;
+pub mod groups;
+pub mod icons;
+pub mod macros;
+pub mod side_by_side;
+pub mod theme;
+pub mod tour;
+pub mod tree;
 pub mod tui;
+pub mod ui_state;
+pub mod virtual_list;
 
 // Re-export main types
-pub use tui::{TuiApp, setup_terminal, restore_terminal};
\ No newline at end of file
+pub use ansi::ansi_spans;
+pub use diff_render::DiffRenderOptions;
+pub use groups::{FileGroups, OTHER_GROUP};
+pub use icons::{Icon, IconMode, Icons};
+pub use macros::KeyMacros;
+pub use theme::{ColorDepth, Role, Theme};
+pub use tour::{Tour, TourAnchor, TourStep, TOUR_STEPS};
+pub use tree::{FileTree, TreeRow};
+pub use tui::{PanelSizes, TuiApp, draw_shutdown_notice, setup_terminal, restore_terminal};
+pub use ui_state::UiState;
\ No newline at end of file
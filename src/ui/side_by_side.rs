@@ -0,0 +1,131 @@
+//! Two-column layout of a diff for the changes log
+//!
+//! The log shows each event's diff unified by default; `v` lays it out side
+//! by side instead. [`rows`] works from the structured [`DiffResult`] and
+//! pairs each run of removed lines with the added lines that follow it, so a
+//! changed line sits next to what replaced it. [`wrap`] splits a line over as
+//! many rows as its column needs, so nothing is cut off in a narrow pane.
+//! Widths are terminal columns, so wide characters such as CJK take two.
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use crate::diff::{DiffOperation, DiffResult};
+
+/// Columns between the two sides, e.g. `" │ "`
+pub const SEPARATOR_WIDTH: usize = 3;
+
+/// Narrowest a column gets, however small the pane
+pub const MIN_COLUMN_WIDTH: usize = 10;
+
+/// One row of a side-by-side diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SideBySideRow {
+    /// A hunk's `@@` header, across both columns
+    Header(String),
+    /// An unchanged line, on both sides
+    Context(String),
+    /// A removed line on the left next to the line added in its place on the
+    /// right. A side is empty when the other side's run is longer.
+    Change { removed: Option<String>, added: Option<String> },
+}
+
+/// Rows of `result`, hunk by hunk
+pub fn rows(result: &DiffResult) -> Vec<SideBySideRow> {
+    let mut rows = Vec::new();
+    for hunk in &result.hunks {
+        rows.push(SideBySideRow::Header(hunk.header()));
+        let (mut removed, mut added) = (Vec::new(), Vec::new());
+        for operation in &hunk.operations {
+            match operation {
+                DiffOperation::Equal(line) => {
+                    push_changes(&mut rows, &mut removed, &mut added);
+                    rows.push(SideBySideRow::Context(line.clone()));
+                }
+                DiffOperation::Delete(line) => {
+                    // Removals after additions start a new pair of runs
+                    if !added.is_empty() {
+                        push_changes(&mut rows, &mut removed, &mut added);
+                    }
+                    removed.push(line.clone());
+                }
+                DiffOperation::Insert(line) => added.push(line.clone()),
+            }
+        }
+        push_changes(&mut rows, &mut removed, &mut added);
+    }
+    rows
+}
+
+/// Pair up a run of removed lines with the run of added lines after it
+fn push_changes(rows: &mut Vec<SideBySideRow>, removed: &mut Vec<String>, added: &mut Vec<String>) {
+    let len = removed.len().max(added.len());
+    let (mut removed, mut added) = (removed.drain(..), added.drain(..));
+    for _ in 0..len {
+        rows.push(SideBySideRow::Change { removed: removed.next(), added: added.next() });
+    }
+}
+
+/// Terminal columns of each side in `inner_width` terminal columns of pane
+pub fn column_width(inner_width: usize) -> usize {
+    (inner_width.saturating_sub(SEPARATOR_WIDTH) / 2).max(MIN_COLUMN_WIDTH)
+}
+
+/// `text` in pieces at most `width` terminal columns wide; an empty line is
+/// one empty piece. A character wider than `width` gets a piece of its own.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut pieces = vec![String::new()];
+    let mut used = 0;
+    for c in text.chars() {
+        let columns = c.width().unwrap_or(0);
+        if used + columns > width && used > 0 {
+            pieces.push(String::new());
+            used = 0;
+        }
+        pieces.last_mut().expect("a piece").push(c);
+        used += columns;
+    }
+    pieces
+}
+
+/// `text` padded with spaces to `width` terminal columns
+pub fn pad(text: &str, width: usize) -> String {
+    format!("{}{}", text, " ".repeat(width.saturating_sub(text.width())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(removed: Option<&str>, added: Option<&str>) -> SideBySideRow {
+        SideBySideRow::Change { removed: removed.map(str::to_string), added: added.map(str::to_string) }
+    }
+
+    #[test]
+    fn test_removed_lines_pair_with_the_added_lines_after_them() {
+        let diff = "--- a\n+++ b\n@@ -1,5 +1,4 @@\n fn a() {}\n-let x = 1;\n-let y = 2;\n+let x = 10;\n fn b() {}\n+// added\n-// gone";
+        let rows = rows(&DiffResult::from_unified_diff(diff));
+
+        assert_eq!(rows, [
+            SideBySideRow::Header("@@ -1,5 +1,4 @@".to_string()),
+            SideBySideRow::Context("fn a() {}".to_string()),
+            change(Some("let x = 1;"), Some("let x = 10;")),
+            change(Some("let y = 2;"), None),
+            SideBySideRow::Context("fn b() {}".to_string()),
+            change(None, Some("// added")),
+            change(Some("// gone"), None),
+        ]);
+    }
+
+    #[test]
+    fn test_wrap_splits_by_terminal_columns() {
+        assert_eq!(wrap("", 4), [""]);
+        assert_eq!(wrap("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+        // 字 takes two columns, so it does not fit next to é in two
+        assert_eq!(wrap("é字é字", 2), ["é", "字", "é", "字"]);
+        assert_eq!(wrap("é字é字", 3), ["é字", "é字"]);
+        assert_eq!(wrap("字", 1), ["字"]);
+        assert_eq!(pad("é字", 5), "é字  ");
+        assert_eq!(pad("字字", 3), "字字");
+        assert_eq!(column_width(63), 30);
+        assert_eq!(column_width(5), MIN_COLUMN_WIDTH);
+    }
+}
@@ -0,0 +1,315 @@
+//! Colors adapted to what the terminal can show
+//!
+//! The TUI asks the [`Theme`] for the color of a [`Role`] instead of using
+//! RGB literals. Truecolor terminals get the RGB values, 256-color terminals
+//! the nearest xterm palette entry and 16-color terminals a named ANSI color,
+//! so dim text and diff backgrounds stay readable over SSH and in tmux.
+
+use clap::ValueEnum;
+use ratatui::style::Color;
+
+/// How many colors the terminal can draw
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+pub enum ColorDepth {
+    /// The 16 named ANSI colors
+    #[value(name = "16")]
+    Ansi16,
+    /// The xterm 256-color palette
+    #[value(name = "256")]
+    Ansi256,
+    /// 24-bit RGB
+    #[value(name = "truecolor")]
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Depth advertised by `COLORTERM` and `TERM`
+    pub fn detect() -> Self {
+        Self::detect_from(
+            std::env::var("COLORTERM").ok().as_deref(),
+            std::env::var("TERM").ok().as_deref(),
+        )
+    }
+
+    /// `COLORTERM=truecolor|24bit` wins; otherwise guess from the terminfo
+    /// name, assuming 16 colors for anything unknown.
+    pub fn detect_from(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if matches!(colorterm, Some("truecolor" | "24bit")) {
+            return Self::TrueColor;
+        }
+        let term = term.unwrap_or("");
+        if term.ends_with("-direct") || term.ends_with("-truecolor") {
+            Self::TrueColor
+        } else if term.contains("256")
+            || ["xterm", "screen", "tmux", "rxvt", "alacritty", "kitty", "wezterm"]
+                .iter()
+                .any(|prefix| term.starts_with(prefix))
+        {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+}
+
+/// What a color is used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Role {
+    /// `|` separators between status bar fields
+    Separator,
+    Border,
+    /// Timestamps, hashes and other barely-there text
+    Faint,
+    /// Field labels
+    Dim,
+    /// Key hints
+    Muted,
+    Secondary,
+    Text,
+    Bright,
+    AddedBg,
+    RemovedBg,
+    AddedText,
+    RemovedText,
+    /// Unchanged lines of the current hunk
+    ContextBg,
+    HunkHeaderBg,
+    /// Every other row of a list
+    StripeBg,
+    SelectionBg,
+    CreatedBg,
+    ModifiedBg,
+    DeletedBg,
+    MovedBg,
+}
+
+impl Role {
+    pub const ALL: [Role; 20] = [
+        Role::Separator, Role::Border, Role::Faint, Role::Dim, Role::Muted, Role::Secondary,
+        Role::Text, Role::Bright, Role::AddedBg, Role::RemovedBg, Role::AddedText,
+        Role::RemovedText, Role::ContextBg, Role::HunkHeaderBg, Role::StripeBg, Role::SelectionBg,
+        Role::CreatedBg, Role::ModifiedBg, Role::DeletedBg, Role::MovedBg,
+    ];
+
+    /// The truecolor value
+    fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            Role::Separator => (60, 60, 60),
+            Role::Border => (80, 80, 80),
+            Role::Faint => (100, 100, 100),
+            Role::Dim => (120, 120, 120),
+            Role::Muted => (150, 150, 150),
+            Role::Secondary => (180, 180, 180),
+            Role::Text => (200, 200, 200),
+            Role::Bright => (220, 220, 220),
+            Role::AddedBg => (0, 25, 0),
+            Role::RemovedBg => (25, 0, 0),
+            Role::AddedText => (150, 255, 150),
+            Role::RemovedText => (255, 150, 150),
+            Role::ContextBg => (10, 10, 10),
+            Role::HunkHeaderBg => (0, 20, 30),
+            Role::StripeBg => (20, 20, 25),
+            Role::SelectionBg => (0, 50, 100),
+            Role::CreatedBg => (0, 40, 0),
+            Role::ModifiedBg => (40, 40, 0),
+            Role::DeletedBg => (40, 0, 0),
+            Role::MovedBg => (0, 0, 40),
+        }
+    }
+
+    /// The 16-color value. Near-black backgrounds become the terminal's own
+    /// background rather than black, which may not match it.
+    fn ansi16(self) -> Color {
+        match self {
+            Role::Separator | Role::Border | Role::Faint | Role::Dim => Color::DarkGray,
+            Role::Muted | Role::Secondary | Role::Text => Color::Gray,
+            Role::Bright => Color::White,
+            Role::AddedText => Color::LightGreen,
+            Role::RemovedText => Color::LightRed,
+            Role::SelectionBg => Color::Blue,
+            Role::AddedBg
+            | Role::RemovedBg
+            | Role::ContextBg
+            | Role::HunkHeaderBg
+            | Role::StripeBg
+            | Role::CreatedBg
+            | Role::ModifiedBg
+            | Role::DeletedBg
+            | Role::MovedBg => Color::Reset,
+        }
+    }
+}
+
+/// Role colors for one color depth
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    depth: ColorDepth,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::new(ColorDepth::TrueColor)
+    }
+}
+
+impl Theme {
+    pub fn new(depth: ColorDepth) -> Self {
+        Self { depth }
+    }
+
+    pub fn depth(&self) -> ColorDepth {
+        self.depth
+    }
+
+    pub fn color(&self, role: Role) -> Color {
+        let (r, g, b) = role.rgb();
+        match self.depth {
+            ColorDepth::TrueColor => Color::Rgb(r, g, b),
+            ColorDepth::Ansi256 => Color::Indexed(nearest_256(r, g, b)),
+            ColorDepth::Ansi16 => role.ansi16(),
+        }
+    }
+
+    /// `color` as the terminal can draw it, for colors that do not come from
+    /// a role, such as syntax highlighting
+    pub fn adapt(&self, color: Color) -> Color {
+        match (self.depth, color) {
+            (ColorDepth::TrueColor, _) => color,
+            (ColorDepth::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(nearest_256(r, g, b)),
+            (ColorDepth::Ansi16, Color::Rgb(r, g, b)) => nearest_16(r, g, b),
+            (ColorDepth::Ansi16, Color::Indexed(index)) => {
+                let (r, g, b) = indexed_rgb(index);
+                nearest_16(r, g, b)
+            }
+            _ => color,
+        }
+    }
+}
+
+/// The 16 ANSI colors in palette order with xterm's default values
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// Channel levels of the 6x6x6 color cube at indices 16-231
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+/// Grays by brightness, other colors by hue. Plain RGB distance would turn
+/// pastels such as the diff text colors into gray.
+fn nearest_16(r: u8, g: u8, b: u8) -> Color {
+    let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+    if max < 48 {
+        return Color::Black;
+    }
+    if max - min < 40 {
+        return match max {
+            0..=159 => Color::DarkGray,
+            160..=234 => Color::Gray,
+            _ => Color::White,
+        };
+    }
+    let middle = (max as u16 + min as u16) / 2;
+    let bit = |c: u8| c as u16 > middle;
+    let bright = max > 220;
+    let index = bit(r) as usize + 2 * bit(g) as usize + 4 * bit(b) as usize + if bright { 8 } else { 0 };
+    ANSI16[index].0
+}
+
+/// The closer of the nearest color cube entry and the nearest gray
+fn nearest_256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |c: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - c as i32).abs())
+            .unwrap_or(0) as u8
+    };
+    let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray = 232 + ((average.saturating_sub(3)) / 10).min(23) as u8;
+
+    if distance(indexed_rgb(gray), (r, g, b)) < distance(indexed_rgb(cube), (r, g, b)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+/// The xterm default value of a 256-color palette entry
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI16[index as usize].1,
+        16..=231 => {
+            let i = index - 16;
+            (
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[(i / 6 % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        232..=255 => {
+            let level = 8 + 10 * (index - 232);
+            (level, level, level)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_colors_match_each_depth() {
+        let ansi16 = Theme::new(ColorDepth::Ansi16);
+        let ansi256 = Theme::new(ColorDepth::Ansi256);
+        let truecolor = Theme::new(ColorDepth::TrueColor);
+        for role in Role::ALL {
+            assert!(
+                !matches!(ansi16.color(role), Color::Rgb(..) | Color::Indexed(_)),
+                "{:?} is not a named color at 16 colors",
+                role
+            );
+            assert!(matches!(ansi256.color(role), Color::Indexed(_)), "{:?}", role);
+            assert!(matches!(truecolor.color(role), Color::Rgb(..)), "{:?}", role);
+        }
+        assert_eq!(ansi256.color(Role::Separator), Color::Indexed(237));
+        assert_eq!(ansi256.color(Role::SelectionBg), Color::Indexed(23));
+
+        assert_eq!(ansi16.adapt(Color::Rgb(150, 255, 150)), Color::LightGreen);
+        assert_eq!(ansi16.adapt(Color::Indexed(196)), Color::LightRed);
+        assert_eq!(ansi16.adapt(Color::Cyan), Color::Cyan);
+        assert_eq!(ansi256.adapt(Color::Rgb(255, 255, 255)), Color::Indexed(231));
+        assert_eq!(truecolor.adapt(Color::Rgb(1, 2, 3)), Color::Rgb(1, 2, 3));
+    }
+
+    #[test]
+    fn test_detect_color_depth() {
+        assert_eq!(ColorDepth::detect_from(Some("truecolor"), Some("xterm")), ColorDepth::TrueColor);
+        assert_eq!(ColorDepth::detect_from(Some("24bit"), None), ColorDepth::TrueColor);
+        assert_eq!(ColorDepth::detect_from(None, Some("xterm-direct")), ColorDepth::TrueColor);
+        assert_eq!(ColorDepth::detect_from(None, Some("screen-256color")), ColorDepth::Ansi256);
+        assert_eq!(ColorDepth::detect_from(None, Some("xterm")), ColorDepth::Ansi256);
+        assert_eq!(ColorDepth::detect_from(None, Some("linux")), ColorDepth::Ansi16);
+        assert_eq!(ColorDepth::detect_from(None, Some("vt100")), ColorDepth::Ansi16);
+        assert_eq!(ColorDepth::detect_from(None, None), ColorDepth::Ansi16);
+    }
+}
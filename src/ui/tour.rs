@@ -0,0 +1,264 @@
+//! Guided tour of the TUI for first-time users
+//!
+//! The tour is a list of `TourStep`s shown one at a time as a card over the
+//! normal view. A step either advances on Enter or waits until the user has
+//! switched to the mode it describes, so adding a step is one more entry in
+//! `TOUR_STEPS`.
+
+use ratatui::layout::Rect;
+use crate::ui::tui::AppMode;
+
+/// Widest a tour card gets
+pub const TOUR_CARD_WIDTH: u16 = 56;
+
+/// Part of the normal view a step's card is drawn next to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TourAnchor {
+    /// Middle of the screen
+    Center,
+    /// The changes log
+    Log,
+    /// The watched files list
+    Files,
+    /// The status bar
+    Status,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TourStep {
+    pub title: &'static str,
+    pub body: &'static str,
+    pub anchor: TourAnchor,
+    /// Mode the user has to open before the tour moves on; Enter advances
+    /// steps without one
+    pub wait_for: Option<AppMode>,
+}
+
+impl TourStep {
+    const fn new(title: &'static str, body: &'static str, anchor: TourAnchor) -> Self {
+        Self { title, body, anchor, wait_for: None }
+    }
+
+    const fn waiting_for(mut self, mode: AppMode) -> Self {
+        self.wait_for = Some(mode);
+        self
+    }
+}
+
+/// The tour `--tour`, the first run and `t` on the help screen start
+pub const TOUR_STEPS: &[TourStep] = &[
+    TourStep::new(
+        "Welcome to watchdiff",
+        "Every change under the watch root shows up here as it happens, with its diff.",
+        TourAnchor::Center,
+    ),
+    TourStep::new(
+        "The changes log",
+        "Newest changes come first. Scroll with j/k or the arrow keys once the tour is done.",
+        TourAnchor::Log,
+    ),
+    TourStep::new(
+        "Watched files",
+        "Files under the watch root, most recently changed first.",
+        TourAnchor::Files,
+    ),
+    TourStep::new("Search", "Press / to fuzzy-search the watched files.", TourAnchor::Log)
+        .waiting_for(AppMode::Search),
+    TourStep::new("Back to the log", "Type to filter, then press Esc to come back.", TourAnchor::Center)
+        .waiting_for(AppMode::Normal),
+    TourStep::new("Summary", "Press s for a summary of the changes so far.", TourAnchor::Status)
+        .waiting_for(AppMode::Summary),
+    TourStep::new("Back to the log", "Press Esc to leave the summary.", TourAnchor::Center)
+        .waiting_for(AppMode::Normal),
+    TourStep::new(
+        "Review AI changes",
+        "Press r to review changes hunk by hunk: accept, reject and write the result back.",
+        TourAnchor::Status,
+    ),
+    TourStep::new(
+        "That's it",
+        "Press h for every key. t on the help screen replays this tour.",
+        TourAnchor::Center,
+    ),
+];
+
+/// Where the tour is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tour {
+    steps: &'static [TourStep],
+    index: usize,
+    finished: bool,
+}
+
+impl Default for Tour {
+    fn default() -> Self {
+        Self::new(TOUR_STEPS)
+    }
+}
+
+impl Tour {
+    pub fn new(steps: &'static [TourStep]) -> Self {
+        Self { steps, index: 0, finished: steps.is_empty() }
+    }
+
+    /// Step on screen, `None` once the tour is finished
+    pub fn current(&self) -> Option<&TourStep> {
+        if self.finished {
+            None
+        } else {
+            self.steps.get(self.index)
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Advance past a step that does not wait for a mode; returns whether
+    /// the tour moved
+    pub fn next_step(&mut self) -> bool {
+        match self.current() {
+            Some(step) if step.wait_for.is_none() => {
+                self.advance();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn previous_step(&mut self) {
+        if !self.finished {
+            self.index = self.index.saturating_sub(1);
+        }
+    }
+
+    /// Advance if the user just opened the mode the current step waits for
+    pub fn observe(&mut self, mode: AppMode) {
+        if self.current().is_some_and(|step| step.wait_for == Some(mode)) {
+            self.advance();
+        }
+    }
+
+    fn advance(&mut self) {
+        if self.index + 1 < self.steps.len() {
+            self.index += 1;
+        } else {
+            self.finished = true;
+        }
+    }
+}
+
+/// Screen regions of the last normal view, for anchoring cards
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TourRegions {
+    pub log: Rect,
+    pub files: Rect,
+    pub status: Rect,
+}
+
+impl TourRegions {
+    fn get(&self, anchor: TourAnchor) -> Option<Rect> {
+        let region = match anchor {
+            TourAnchor::Center => return None,
+            TourAnchor::Log => self.log,
+            TourAnchor::Files => self.files,
+            TourAnchor::Status => self.status,
+        };
+        (region.area() > 0).then_some(region)
+    }
+}
+
+/// Where to draw a card `height` rows tall within `area`
+///
+/// A card goes inside the top right of an anchor in the upper half of the
+/// screen and just above one in the lower half. It is centered instead when
+/// the anchor is unknown or the terminal is too small to keep it beside the
+/// anchor.
+pub fn card_rect(anchor: TourAnchor, regions: &TourRegions, area: Rect, height: u16) -> Rect {
+    let width = TOUR_CARD_WIDTH.min(area.width);
+    let height = height.min(area.height);
+    let centered = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let Some(region) = regions.get(anchor) else { return centered };
+    if area.width < width + 4 || area.height < height + 4 {
+        return centered;
+    }
+
+    let x = region.right().saturating_sub(width + 2).max(area.x);
+    let y = if region.y < area.y + area.height / 2 {
+        region.y + 1
+    } else {
+        region.y.saturating_sub(height)
+    };
+    let y = y.clamp(area.y, area.bottom() - height);
+    Rect { x, y, width, height }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEPS: &[TourStep] = &[
+        TourStep::new("One", "", TourAnchor::Center),
+        TourStep::new("Two", "", TourAnchor::Log).waiting_for(AppMode::Search),
+        TourStep::new("Three", "", TourAnchor::Status),
+    ];
+
+    #[test]
+    fn test_steps_waiting_for_a_mode_ignore_enter() {
+        let mut tour = Tour::new(STEPS);
+        assert!(tour.next_step());
+        assert_eq!(tour.current().unwrap().title, "Two");
+
+        assert!(!tour.next_step());
+        tour.observe(AppMode::Summary);
+        assert_eq!(tour.index(), 1);
+        tour.observe(AppMode::Search);
+        assert_eq!(tour.current().unwrap().title, "Three");
+
+        tour.previous_step();
+        assert_eq!(tour.index(), 1);
+        tour.observe(AppMode::Search);
+        assert!(tour.next_step());
+        assert!(tour.is_finished());
+        assert_eq!(tour.current(), None);
+    }
+
+    #[test]
+    fn test_card_rect_anchors_and_falls_back_to_center() {
+        let area = Rect::new(0, 0, 120, 40);
+        let regions = TourRegions {
+            log: Rect::new(1, 1, 118, 26),
+            files: Rect::new(1, 27, 118, 9),
+            status: Rect::new(1, 36, 118, 3),
+        };
+
+        let log = card_rect(TourAnchor::Log, &regions, area, 6);
+        assert_eq!(log, Rect::new(118 + 1 - 56 - 2, 2, 56, 6));
+        let status = card_rect(TourAnchor::Status, &regions, area, 6);
+        assert_eq!(status.bottom(), regions.status.y);
+
+        // Too small to sit beside the anchor
+        let small = Rect::new(0, 0, 50, 8);
+        let card = card_rect(TourAnchor::Status, &regions, small, 6);
+        assert_eq!(card, Rect::new(0, 1, 50, 6));
+        assert_eq!(card_rect(TourAnchor::Center, &regions, area, 6), Rect::new(32, 17, 56, 6));
+    }
+}
@@ -0,0 +1,429 @@
+//! Watched files as a collapsible directory tree
+//!
+//! The tree is built incrementally from paths as they are seen, and each
+//! directory keeps the number of files and of files changed this session in
+//! its subtree, so counts stay current without walking the tree again.
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
+
+use crate::core::{FileEvent, FileEventKind};
+
+/// Index of a node; only valid until the node is removed
+pub type NodeId = usize;
+
+const ROOT: NodeId = 0;
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    name: OsString,
+    parent: Option<NodeId>,
+    /// Empty for files
+    children: BTreeMap<OsString, NodeId>,
+    is_dir: bool,
+    expanded: bool,
+    /// Changes in the subtree are left out of the change log
+    muted: bool,
+    /// Files in the subtree, 1 for a file
+    files: usize,
+    /// Files in the subtree changed this session
+    changed: usize,
+}
+
+/// One line of the tree as drawn
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeRow {
+    pub id: NodeId,
+    pub depth: usize,
+    pub name: String,
+    pub is_dir: bool,
+    pub expanded: bool,
+    pub muted: bool,
+    pub files: usize,
+    pub changed: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileTree {
+    root: PathBuf,
+    /// `root` made absolute, as event paths are
+    absolute_root: PathBuf,
+    nodes: Vec<Node>,
+    /// Slots of removed nodes, reused by later insertions
+    free: Vec<NodeId>,
+    /// Subtree the change log is limited to, relative to the root
+    scope: Option<PathBuf>,
+}
+
+impl FileTree {
+    pub fn new(root: &Path) -> Self {
+        let absolute_root = std::env::current_dir().map_or_else(|_| root.to_path_buf(), |cwd| cwd.join(root));
+        Self {
+            root: root.to_path_buf(),
+            absolute_root,
+            nodes: vec![Node { is_dir: true, expanded: true, ..Node::default() }],
+            free: Vec::new(),
+            scope: None,
+        }
+    }
+
+    /// The root made absolute, as event paths are
+    pub fn absolute_root(&self) -> &Path {
+        &self.absolute_root
+    }
+
+    /// Files in the whole tree
+    pub fn file_count(&self) -> usize {
+        self.nodes[ROOT].files
+    }
+
+    /// Files changed this session in the whole tree
+    pub fn changed_count(&self) -> usize {
+        self.nodes[ROOT].changed
+    }
+
+    /// `path` relative to the root, if it is under it
+    pub fn relative(&self, path: &Path) -> Option<PathBuf> {
+        path.strip_prefix(&self.root)
+            .or_else(|_| path.strip_prefix(&self.absolute_root))
+            .ok()
+            .map(Path::to_path_buf)
+    }
+
+    /// Add a file and the directories leading to it. Returns false if it was
+    /// already there or is outside the root.
+    pub fn insert(&mut self, path: &Path) -> bool {
+        let Some(relative) = self.relative(path) else { return false };
+        let names: Vec<OsString> = relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_os_string()),
+                _ => None,
+            })
+            .collect();
+        let Some((file_name, dirs)) = names.split_last() else { return false };
+
+        let mut parent = ROOT;
+        for name in dirs {
+            parent = match self.nodes[parent].children.get(name) {
+                Some(&id) if self.nodes[id].is_dir => id,
+                Some(_) => return false, // A file where a directory is expected
+                None => self.add_node(parent, name.clone(), true),
+            };
+        }
+        if self.nodes[parent].children.contains_key(file_name) {
+            return false;
+        }
+        let id = self.add_node(parent, file_name.clone(), false);
+        self.nodes[id].files = 1;
+        self.adjust_ancestors(id, 1, 0, true);
+        true
+    }
+
+    /// Remove a file, or a directory with everything under it, along with
+    /// directories left empty. Returns false if it was not in the tree.
+    pub fn remove(&mut self, path: &Path) -> bool {
+        let Some(id) = self.find(path) else { return false };
+        if id == ROOT {
+            return false;
+        }
+        let (files, changed) = (self.nodes[id].files, self.nodes[id].changed);
+        self.adjust_ancestors(id, files, changed, false);
+
+        let mut parent = self.unlink(id);
+        while let Some(dir) = parent.filter(|&dir| dir != ROOT && self.nodes[dir].files == 0) {
+            parent = self.unlink(dir);
+        }
+        true
+    }
+
+    /// Count a file as changed, adding it first if it is new. Each file is
+    /// counted once however often it changes.
+    pub fn mark_changed(&mut self, path: &Path) {
+        self.insert(path);
+        let Some(id) = self.find(path) else { return };
+        if self.nodes[id].is_dir || self.nodes[id].changed > 0 {
+            return;
+        }
+        self.nodes[id].changed = 1;
+        self.adjust_ancestors(id, 0, 1, true);
+    }
+
+    /// Keep the tree in step with a file event. Deleted files leave the tree
+    /// and with them their share of the changed counts.
+    pub fn apply_event(&mut self, event: &FileEvent) {
+        match event.kind {
+            FileEventKind::Created | FileEventKind::Modified => self.mark_changed(&event.path),
+            FileEventKind::Deleted => {
+                self.remove(&event.path);
+            }
+            FileEventKind::Moved { ref from, ref to } => {
+                self.remove(from);
+                self.mark_changed(to);
+            }
+        }
+    }
+
+    /// Rows of the expanded part of the tree, directories before files.
+    /// Collapsed directories are not walked.
+    pub fn visible_rows(&self) -> Vec<TreeRow> {
+        let mut rows = Vec::new();
+        self.push_rows(ROOT, 0, &mut rows);
+        rows
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id].parent.filter(|&parent| parent != ROOT)
+    }
+
+    pub fn set_expanded(&mut self, id: NodeId, expanded: bool) {
+        if self.nodes[id].is_dir && id != ROOT {
+            self.nodes[id].expanded = expanded;
+        }
+    }
+
+    /// Expand `id` and every directory under it with changed files
+    pub fn expand_changed(&mut self, id: NodeId) {
+        let mut pending = vec![id];
+        while let Some(dir) = pending.pop() {
+            if self.nodes[dir].is_dir && self.nodes[dir].changed > 0 {
+                self.set_expanded(dir, true);
+                pending.extend(self.nodes[dir].children.values());
+            }
+        }
+    }
+
+    /// Expand every directory with changed files
+    pub fn expand_all_changed(&mut self) {
+        self.expand_changed(ROOT);
+    }
+
+    pub fn toggle_muted(&mut self, id: NodeId) {
+        self.nodes[id].muted = !self.nodes[id].muted;
+    }
+
+    /// Path of `id` relative to the root
+    pub fn path_of(&self, id: NodeId) -> PathBuf {
+        let mut names = Vec::new();
+        let mut node = Some(id);
+        while let Some(current) = node.filter(|&current| current != ROOT) {
+            names.push(&self.nodes[current].name);
+            node = self.nodes[current].parent;
+        }
+        names.iter().rev().collect()
+    }
+
+    pub fn scope(&self) -> Option<&Path> {
+        self.scope.as_deref()
+    }
+
+    /// Limit the change log to the subtree of `id`, or lift the limit if it
+    /// is already there
+    pub fn toggle_scope(&mut self, id: NodeId) {
+        let path = self.path_of(id);
+        self.scope = if self.scope.as_ref() == Some(&path) { None } else { Some(path) };
+    }
+
+    /// Whether changes to `path` belong in the change log: not under a muted
+    /// directory, and inside the scope if there is one
+    pub fn shows(&self, path: &Path) -> bool {
+        let Some(relative) = self.relative(path) else {
+            return self.scope.is_none();
+        };
+        if self.scope.as_ref().is_some_and(|scope| !relative.starts_with(scope)) {
+            return false;
+        }
+        let mut node = ROOT;
+        for component in relative.components() {
+            let Component::Normal(name) = component else { continue };
+            match self.nodes[node].children.get(name) {
+                Some(&child) if self.nodes[child].muted => return false,
+                Some(&child) => node = child,
+                None => break,
+            }
+        }
+        true
+    }
+
+    fn find(&self, path: &Path) -> Option<NodeId> {
+        let relative = self.relative(path)?;
+        relative.components().try_fold(ROOT, |node, component| match component {
+            Component::Normal(name) => self.nodes[node].children.get(name).copied(),
+            _ => Some(node),
+        })
+    }
+
+    fn add_node(&mut self, parent: NodeId, name: OsString, is_dir: bool) -> NodeId {
+        let node = Node { name: name.clone(), parent: Some(parent), is_dir, ..Node::default() };
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.nodes[id] = node;
+                id
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+        self.nodes[parent].children.insert(name, id);
+        id
+    }
+
+    /// Add to or subtract from the counts of every ancestor of `id`
+    fn adjust_ancestors(&mut self, id: NodeId, files: usize, changed: usize, add: bool) {
+        let mut node = self.nodes[id].parent;
+        while let Some(current) = node {
+            let counts = &mut self.nodes[current];
+            if add {
+                counts.files += files;
+                counts.changed += changed;
+            } else {
+                counts.files -= files;
+                counts.changed -= changed;
+            }
+            node = counts.parent;
+        }
+    }
+
+    /// Detach `id` from its parent and free its subtree; returns the parent
+    fn unlink(&mut self, id: NodeId) -> Option<NodeId> {
+        let parent = self.nodes[id].parent;
+        if let Some(parent) = parent {
+            let name = self.nodes[id].name.clone();
+            self.nodes[parent].children.remove(&name);
+        }
+        let mut pending = vec![id];
+        while let Some(node) = pending.pop() {
+            pending.extend(std::mem::take(&mut self.nodes[node]).children.into_values());
+            self.free.push(node);
+        }
+        parent
+    }
+
+    fn push_rows(&self, dir: NodeId, depth: usize, rows: &mut Vec<TreeRow>) {
+        let children = &self.nodes[dir].children;
+        let dirs = children.values().filter(|&&id| self.nodes[id].is_dir);
+        let files = children.values().filter(|&&id| !self.nodes[id].is_dir);
+        for &id in dirs.chain(files) {
+            let node = &self.nodes[id];
+            rows.push(TreeRow {
+                id,
+                depth,
+                name: node.name.to_string_lossy().into_owned(),
+                is_dir: node.is_dir,
+                expanded: node.expanded,
+                muted: node.muted,
+                files: node.files,
+                changed: node.changed,
+            });
+            if node.is_dir && node.expanded {
+                self.push_rows(id, depth + 1, rows);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(paths: &[&str]) -> FileTree {
+        let mut tree = FileTree::new(Path::new("/repo"));
+        for path in paths {
+            tree.insert(&Path::new("/repo").join(path));
+        }
+        tree
+    }
+
+    fn row<'a>(rows: &'a [TreeRow], name: &str) -> &'a TreeRow {
+        rows.iter().find(|row| row.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_insertion_builds_directories_with_counts() {
+        let mut tree = tree(&["src/main.rs", "src/ui/tui.rs", "src/ui/tree.rs", "README.md"]);
+        assert!(!tree.insert(Path::new("/repo/src/main.rs")));
+        assert!(!tree.insert(Path::new("/elsewhere/x.rs")));
+        assert_eq!(tree.file_count(), 4);
+
+        // Only the top level shows until a directory is expanded
+        let rows = tree.visible_rows();
+        let names: Vec<&str> = rows.iter().map(|row| row.name.as_str()).collect();
+        assert_eq!(names, ["src", "README.md"]);
+        assert_eq!(row(&rows, "src").files, 3);
+
+        let src = row(&rows, "src").id;
+        tree.set_expanded(src, true);
+        let rows = tree.visible_rows();
+        let names: Vec<(&str, usize)> = rows.iter().map(|row| (row.name.as_str(), row.depth)).collect();
+        assert_eq!(names, [("src", 0), ("ui", 1), ("main.rs", 1), ("README.md", 0)]);
+        assert_eq!(tree.path_of(row(&rows, "ui").id), PathBuf::from("src/ui"));
+        assert_eq!(tree.parent(row(&rows, "ui").id), Some(src));
+        assert_eq!(tree.parent(src), None);
+    }
+
+    #[test]
+    fn test_deletion_prunes_empty_directories() {
+        let mut tree = tree(&["src/ui/tui.rs", "src/ui/tree.rs", "src/main.rs"]);
+        tree.mark_changed(Path::new("/repo/src/ui/tui.rs"));
+
+        assert!(tree.remove(Path::new("/repo/src/ui/tui.rs")));
+        assert!(!tree.remove(Path::new("/repo/src/ui/tui.rs")));
+        assert_eq!((tree.file_count(), tree.changed_count()), (2, 0));
+
+        tree.remove(Path::new("/repo/src/ui/tree.rs"));
+        tree.expand_all_changed();
+        let src = row(&tree.visible_rows(), "src").id;
+        tree.set_expanded(src, true);
+        let names: Vec<String> = tree.visible_rows().into_iter().map(|row| row.name).collect();
+        assert_eq!(names, ["src", "main.rs"]);
+
+        // A whole directory at once, and its slots are reused
+        let nodes = tree.nodes.len();
+        assert!(tree.remove(Path::new("/repo/src")));
+        assert!(tree.visible_rows().is_empty());
+        tree.insert(Path::new("/repo/lib/a.rs"));
+        assert_eq!((tree.nodes.len(), tree.file_count()), (nodes, 1));
+    }
+
+    #[test]
+    fn test_changed_counts_propagate_to_ancestors() {
+        let mut tree = tree(&["src/ui/tui.rs", "src/ui/tree.rs", "src/main.rs", "docs/a.md"]);
+        tree.mark_changed(Path::new("/repo/src/ui/tui.rs"));
+        tree.mark_changed(Path::new("/repo/src/ui/tui.rs"));
+        tree.apply_event(&FileEvent::new(PathBuf::from("/repo/src/ui/new.rs"), FileEventKind::Created));
+        assert_eq!((tree.file_count(), tree.changed_count()), (5, 2));
+
+        // Expanding the changed directories reaches ui but leaves docs closed
+        tree.expand_all_changed();
+        let rows = tree.visible_rows();
+        assert_eq!((row(&rows, "src").changed, row(&rows, "ui").changed), (2, 2));
+        assert!(!row(&rows, "docs").expanded);
+
+        let moved = FileEventKind::Moved { from: PathBuf::from("/repo/src/ui/new.rs"), to: PathBuf::from("/repo/docs/new.rs") };
+        tree.apply_event(&FileEvent::new(PathBuf::from("/repo/docs/new.rs"), moved));
+        let rows = tree.visible_rows();
+        assert_eq!((row(&rows, "src").changed, row(&rows, "docs").changed), (1, 1));
+        assert_eq!(row(&rows, "docs").files, 2);
+    }
+
+    #[test]
+    fn test_muted_and_scoped_subtrees() {
+        let mut tree = tree(&["src/ui/tui.rs", "src/main.rs", "docs/a.md"]);
+        let src = row(&tree.visible_rows(), "src").id;
+        tree.set_expanded(src, true);
+        let ui = row(&tree.visible_rows(), "ui").id;
+
+        tree.toggle_muted(ui);
+        assert!(!tree.shows(Path::new("/repo/src/ui/tui.rs")));
+        assert!(tree.shows(Path::new("/repo/src/main.rs")));
+
+        tree.toggle_scope(src);
+        assert_eq!(tree.scope(), Some(Path::new("src")));
+        assert!(!tree.shows(Path::new("/repo/docs/a.md")));
+        assert!(tree.shows(Path::new("/repo/src/new.rs")));
+        tree.toggle_scope(src);
+        assert!(tree.shows(Path::new("/repo/docs/a.md")));
+    }
+}
@@ -0,0 +1,50 @@
+//! UI state remembered between runs, kept in `.watchdiff/ui_state.json`
+//! under the watch root
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use crate::ui::tui::PanelSizes;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UiState {
+    /// Explicitly pinned paths, relative to the watch root
+    #[serde(default)]
+    pub pinned: Vec<PathBuf>,
+    /// Review mode keyboard macros by register, in `<Esc>`-style key notation
+    #[serde(default)]
+    pub macros: BTreeMap<char, String>,
+    /// The onboarding tour was finished or turned off
+    #[serde(default)]
+    pub tour_seen: bool,
+    /// Panel proportions last chosen with Ctrl+arrows
+    #[serde(default)]
+    pub panel_sizes: Option<PanelSizes>,
+}
+
+impl UiState {
+    pub fn path(root: &Path) -> PathBuf {
+        root.join(".watchdiff").join("ui_state.json")
+    }
+
+    /// State saved at `path`; the default if the file does not exist yet
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).with_context(|| format!("Invalid UI state file: {}", path.display())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).with_context(|| format!("Failed to read UI state file: {}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write UI state file: {}", path.display()))
+    }
+}
@@ -0,0 +1,135 @@
+//! Rendering only the visible rows of long lists
+//!
+//! Building a `ListItem` for every row each frame makes a list of tens of
+//! thousands of files lag. [`VirtualList`] keeps the scroll offset over the
+//! whole logical list and picks the rows worth building for the area: the
+//! ones that fit plus a few on either side. Selection stays an index into the
+//! logical list, so moving it works the same however long the list is.
+
+use std::ops::Range;
+use ratatui::widgets::ListState;
+use crate::diff::long_lines::group_digits;
+
+/// Rows built beyond each edge of the visible ones
+const MARGIN: usize = 4;
+
+/// Scroll position of a list rendered a window at a time
+#[derive(Debug, Clone, Default)]
+pub struct VirtualList {
+    /// First visible row of the logical list
+    offset: usize,
+    /// Rows visible when last rendered
+    height: usize,
+}
+
+/// Rows of the logical list to build, and how to show them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VirtualWindow {
+    pub rows: Range<usize>,
+    /// First visible row, relative to `rows.start`
+    pub offset: usize,
+}
+
+impl VirtualWindow {
+    /// State to render the built rows with, scrolled to the visible ones
+    pub fn list_state(&self) -> ListState {
+        ListState::default().with_offset(self.offset)
+    }
+}
+
+impl VirtualList {
+    /// Rows to build for `height` visible rows of a `len` row list, scrolled
+    /// as little as needed to keep `selected` in view
+    pub fn window(&mut self, len: usize, selected: usize, height: usize) -> VirtualWindow {
+        self.height = height;
+        if selected < self.offset {
+            self.offset = selected;
+        } else if height > 0 && selected >= self.offset + height {
+            self.offset = selected + 1 - height;
+        }
+        // No blank rows below the end of a list that has shrunk
+        self.offset = self.offset.min(len.saturating_sub(height));
+
+        let start = self.offset.saturating_sub(MARGIN);
+        let end = (self.offset + height + MARGIN).min(len);
+        VirtualWindow { rows: start..end.max(start), offset: self.offset - start }
+    }
+
+    /// First visible row when last rendered
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Rows a page moves, the visible rows when last rendered
+    pub fn page(&self) -> usize {
+        self.height.max(1)
+    }
+
+    /// Index a page above `selected`
+    pub fn page_up(&self, selected: usize) -> usize {
+        selected.saturating_sub(self.page())
+    }
+
+    /// Index a page below `selected` in a `len` row list
+    pub fn page_down(&self, selected: usize, len: usize) -> usize {
+        (selected + self.page()).min(len.saturating_sub(1))
+    }
+}
+
+/// `1,204 of 48,331` for the 1,204th row selected
+pub fn position(selected: usize, len: usize) -> String {
+    match len {
+        0 => "0 of 0".to_string(),
+        _ => format!("{} of {}", group_digits(selected + 1), group_digits(len)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+    use ratatui::text::Line;
+    use ratatui::widgets::ListItem;
+
+    #[test]
+    fn test_window_follows_selection_with_margin() {
+        let mut list = VirtualList::default();
+        assert_eq!(list.window(1000, 0, 10), VirtualWindow { rows: 0..14, offset: 0 });
+        // Moving within the visible rows does not scroll
+        assert_eq!(list.window(1000, 9, 10).rows, 0..14);
+        assert_eq!(list.window(1000, 10, 10), VirtualWindow { rows: 0..15, offset: 1 });
+        assert_eq!(list.window(1000, 500, 10), VirtualWindow { rows: 487..505, offset: 4 });
+        assert_eq!(list.offset(), 491);
+        assert_eq!(list.window(1000, 495, 10).rows, 487..505);
+        assert_eq!(list.window(1000, 999, 10), VirtualWindow { rows: 986..1000, offset: 4 });
+
+        // A shorter list pulls the window back to its end
+        assert_eq!(list.window(5, 2, 10), VirtualWindow { rows: 0..5, offset: 0 });
+        assert_eq!(list.window(0, 0, 10), VirtualWindow { rows: 0..0, offset: 0 });
+
+        assert_eq!(list.page_down(0, 1000), 10);
+        assert_eq!(list.page_down(995, 1000), 999);
+        assert_eq!(list.page_up(5), 0);
+        assert_eq!(position(1203, 48331), "1,204 of 48,331");
+        assert_eq!(position(0, 0), "0 of 0");
+    }
+
+    #[test]
+    fn test_building_the_visible_slice_of_100k_paths_is_fast() {
+        let paths: Vec<PathBuf> = (0..100_000).map(|i| PathBuf::from(format!("src/module_{}/file_{}.rs", i / 100, i))).collect();
+        let mut list = VirtualList::default();
+        let start = Instant::now();
+        // A frame for each page of the list, top to bottom
+        for selected in (0..paths.len()).step_by(50) {
+            let window = list.window(paths.len(), selected, 50);
+            let items: Vec<ListItem> = paths[window.rows.clone()]
+                .iter()
+                .map(|path| ListItem::new(Line::from(path.display().to_string())))
+                .collect();
+            assert!(items.len() <= 50 + 2 * MARGIN);
+        }
+        // Two thousand frames; building every row each frame would take minutes
+        assert!(start.elapsed() < Duration::from_secs(2), "{:?}", start.elapsed());
+    }
+}
@@ -1,7 +1,7 @@
 use std::fs;
 use std::time::Duration;
 use tempfile::TempDir;
-use watchdiff_tui::core::{FileWatcher, AppEvent, FileEventKind, ChangeOrigin, ConfidenceLevel};
+use watchdiff_tui::core::{FileWatcher, AppEvent, ChangeOrigin, ConfidenceLevel};
 
 #[test]
 fn test_basic_file_watching() {
@@ -58,9 +58,7 @@ fn test_confidence_scoring_applied() {
         match watcher.recv_timeout(Duration::from_millis(100)) {
             Ok(AppEvent::FileChanged(event)) => {
                 // Check if this event has confidence scoring
-                if event.confidence.is_some() {
-                    let confidence = event.confidence.unwrap();
-                    
+                if let Some(confidence) = event.confidence {
                     // Should be lower confidence due to unsafe code
                     assert!(confidence.score <= 1.0);
                     assert!(confidence.score >= 0.0);